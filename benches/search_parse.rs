@@ -0,0 +1,103 @@
+//! Allocations and latency of `search::parse_response` on a dense search
+//! response, against parsing the same response into a `serde_json::Value`
+//! tree, which is what every search did before the targeted parse.
+//!
+//! Run with `cargo bench --bench search_parse`.
+
+use rsstreetview::search::parse_response;
+use serde_json::{json, Value};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+/// Counts allocations and allocated bytes.
+struct Counting;
+
+static ALLOCS: AtomicUsize = AtomicUsize::new(0);
+static BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const PANORAMAS: usize = 400;
+const ITERATIONS: u32 = 200;
+
+/// A search response shaped like one from a dense city centre: many
+/// panoramas, each entry carrying the trailing blocks Google sends, and
+/// large sibling blocks the parser has no use for.
+fn dense_response() -> String {
+    let entries: Vec<Value> = (0..PANORAMAS)
+        .map(|i| {
+            let offset = i as f64 * 1e-5;
+            json!([
+                [2, format!("PANO{i:018}")],
+                null,
+                [[null, null, 41.8982 + offset, 12.4764 + offset], [29.5], [163.28, 89.71, 0.42]],
+                [31.6],
+                [[[1, format!("LINK{i:018}")], null, [[null, null, 41.8983, 12.4765]]]],
+                null,
+                [["Via del Governo Vecchio", "it"], ["Roma", "it"]],
+                [[0, 1, 2, 3], [4, 5, 6, 7], [8, 9, 10, 11]],
+                null,
+                [[[["© Google"]]]]
+            ])
+        })
+        .collect();
+    let dates: Vec<Value> = (0..PANORAMAS).map(|i| json!([[1, i], [2010 + i % 14, 1 + i % 12]])).collect();
+    let tiles: Vec<Value> = (0..PANORAMAS).map(|i| json!([[8192, 16384], [512, 512], [i, "tile"]])).collect();
+
+    let result = json!([[0], ["apiv3"], null, [entries], null, null, [tiles], null, dates]);
+    let data = json!([[0, null, "OK"], [[1], [2, "PANO000000000000000000"], null, null, null, [result]]]);
+    format!("/**/callbackfunc({data})")
+}
+
+/// Time and count the allocations of `f`, per call.
+fn measure(name: &str, mut f: impl FnMut()) {
+    f();
+    let allocs = ALLOCS.load(Ordering::Relaxed);
+    let bytes = BYTES.load(Ordering::Relaxed);
+    f();
+    let allocs = ALLOCS.load(Ordering::Relaxed) - allocs;
+    let bytes = BYTES.load(Ordering::Relaxed) - bytes;
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let per_call = start.elapsed() / ITERATIONS;
+    println!("{name:<16} {:>10.1} µs {allocs:>10} allocs {:>10.1} KiB", per_call.as_secs_f64() * 1e6, bytes as f64 / 1024.0);
+}
+
+fn main() {
+    let text = dense_response();
+    let panos = parse_response(&text).unwrap();
+    assert_eq!(panos.len(), PANORAMAS);
+    println!("{} KiB response, {} panoramas", text.len() / 1024, panos.len());
+
+    let payload = &text["/**/callbackfunc(".len()..text.len() - 1];
+    measure("parse_response", || {
+        black_box(parse_response(black_box(&text)).unwrap());
+    });
+    measure("Value tree", || {
+        black_box(serde_json::from_str::<Value>(black_box(payload)).unwrap());
+    });
+}
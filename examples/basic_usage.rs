@@ -50,7 +50,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Get metadata (requires API key)
     if let Ok(api_key) = env::var("GOOGLE_MAPS_API_KEY") {
         println!("\nFetching metadata with API key...");
-        let client_with_key = StreetView::with_api_key(api_key.clone());
+        let client_with_key = StreetView::builder().api_key(api_key.clone()).build()?;
 
         match client_with_key.get_panorama_meta(pano_id).await {
             Ok(meta) => {
@@ -32,14 +32,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             "  {}. ID: {}, Date: {:?}, Heading: {:.1}°",
             i + 1,
             pano.pano_id,
-            pano.date.as_deref().unwrap_or("None"),
+            pano.date.map_or_else(|| "None".to_string(), |date| date.to_string()),
             pano.heading
         );
     }
 
     let pano_id = &panos[0].pano_id;
     println!("\nUsing panorama: {pano_id}");
-    println!("Date: {:?}", panos[0].date.as_deref().unwrap_or("None"));
+    println!("Date: {:?}", panos[0].date.map_or_else(|| "None".to_string(), |date| date.to_string()));
     println!("Location: {}, {}", panos[0].lat, panos[0].lon);
     println!("Heading: {}", panos[0].heading);
 
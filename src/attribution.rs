@@ -0,0 +1,264 @@
+//! Copyright and logo overlays drawn onto images as they are saved.
+
+use crate::error::Result;
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, GenericImage, GenericImageView, Rgba};
+use std::path::PathBuf;
+
+/// Glyph columns of a 5×7 font for ASCII 0x20..=0x7E, least significant
+/// bit at the top.
+const FONT: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], [0x00, 0x00, 0x5F, 0x00, 0x00], [0x00, 0x07, 0x00, 0x07, 0x00],
+    [0x14, 0x7F, 0x14, 0x7F, 0x14], [0x24, 0x2A, 0x7F, 0x2A, 0x12], [0x23, 0x13, 0x08, 0x64, 0x62],
+    [0x36, 0x49, 0x56, 0x20, 0x50], [0x00, 0x05, 0x03, 0x00, 0x00], [0x00, 0x1C, 0x22, 0x41, 0x00],
+    [0x00, 0x41, 0x22, 0x1C, 0x00], [0x14, 0x08, 0x3E, 0x08, 0x14], [0x08, 0x08, 0x3E, 0x08, 0x08],
+    [0x00, 0x50, 0x30, 0x00, 0x00], [0x08, 0x08, 0x08, 0x08, 0x08], [0x00, 0x60, 0x60, 0x00, 0x00],
+    [0x20, 0x10, 0x08, 0x04, 0x02], [0x3E, 0x51, 0x49, 0x45, 0x3E], [0x00, 0x42, 0x7F, 0x40, 0x00],
+    [0x42, 0x61, 0x51, 0x49, 0x46], [0x21, 0x41, 0x45, 0x4B, 0x31], [0x18, 0x14, 0x12, 0x7F, 0x10],
+    [0x27, 0x45, 0x45, 0x45, 0x39], [0x3C, 0x4A, 0x49, 0x49, 0x30], [0x01, 0x71, 0x09, 0x05, 0x03],
+    [0x36, 0x49, 0x49, 0x49, 0x36], [0x06, 0x49, 0x49, 0x29, 0x1E], [0x00, 0x36, 0x36, 0x00, 0x00],
+    [0x00, 0x56, 0x36, 0x00, 0x00], [0x08, 0x14, 0x22, 0x41, 0x00], [0x14, 0x14, 0x14, 0x14, 0x14],
+    [0x00, 0x41, 0x22, 0x14, 0x08], [0x02, 0x01, 0x51, 0x09, 0x06], [0x32, 0x49, 0x79, 0x41, 0x3E],
+    [0x7E, 0x11, 0x11, 0x11, 0x7E], [0x7F, 0x49, 0x49, 0x49, 0x36], [0x3E, 0x41, 0x41, 0x41, 0x22],
+    [0x7F, 0x41, 0x41, 0x22, 0x1C], [0x7F, 0x49, 0x49, 0x49, 0x41], [0x7F, 0x09, 0x09, 0x09, 0x01],
+    [0x3E, 0x41, 0x49, 0x49, 0x7A], [0x7F, 0x08, 0x08, 0x08, 0x7F], [0x00, 0x41, 0x7F, 0x41, 0x00],
+    [0x20, 0x40, 0x41, 0x3F, 0x01], [0x7F, 0x08, 0x14, 0x22, 0x41], [0x7F, 0x40, 0x40, 0x40, 0x40],
+    [0x7F, 0x02, 0x0C, 0x02, 0x7F], [0x7F, 0x04, 0x08, 0x10, 0x7F], [0x3E, 0x41, 0x41, 0x41, 0x3E],
+    [0x7F, 0x09, 0x09, 0x09, 0x06], [0x3E, 0x41, 0x51, 0x21, 0x5E], [0x7F, 0x09, 0x19, 0x29, 0x46],
+    [0x46, 0x49, 0x49, 0x49, 0x31], [0x01, 0x01, 0x7F, 0x01, 0x01], [0x3F, 0x40, 0x40, 0x40, 0x3F],
+    [0x1F, 0x20, 0x40, 0x20, 0x1F], [0x3F, 0x40, 0x38, 0x40, 0x3F], [0x63, 0x14, 0x08, 0x14, 0x63],
+    [0x07, 0x08, 0x70, 0x08, 0x07], [0x61, 0x51, 0x49, 0x45, 0x43], [0x00, 0x7F, 0x41, 0x41, 0x00],
+    [0x02, 0x04, 0x08, 0x10, 0x20], [0x00, 0x41, 0x41, 0x7F, 0x00], [0x04, 0x02, 0x01, 0x02, 0x04],
+    [0x40, 0x40, 0x40, 0x40, 0x40], [0x00, 0x01, 0x02, 0x04, 0x00], [0x20, 0x54, 0x54, 0x54, 0x78],
+    [0x7F, 0x48, 0x44, 0x44, 0x38], [0x38, 0x44, 0x44, 0x44, 0x20], [0x38, 0x44, 0x44, 0x48, 0x7F],
+    [0x38, 0x54, 0x54, 0x54, 0x18], [0x08, 0x7E, 0x09, 0x01, 0x02], [0x0C, 0x52, 0x52, 0x52, 0x3E],
+    [0x7F, 0x08, 0x04, 0x04, 0x78], [0x00, 0x44, 0x7D, 0x40, 0x00], [0x20, 0x40, 0x44, 0x3D, 0x00],
+    [0x7F, 0x10, 0x28, 0x44, 0x00], [0x00, 0x41, 0x7F, 0x40, 0x00], [0x7C, 0x04, 0x18, 0x04, 0x78],
+    [0x7C, 0x08, 0x04, 0x04, 0x78], [0x38, 0x44, 0x44, 0x44, 0x38], [0x7C, 0x14, 0x14, 0x14, 0x08],
+    [0x08, 0x14, 0x14, 0x18, 0x7C], [0x7C, 0x08, 0x04, 0x04, 0x08], [0x48, 0x54, 0x54, 0x54, 0x20],
+    [0x04, 0x3F, 0x44, 0x40, 0x20], [0x3C, 0x40, 0x40, 0x20, 0x7C], [0x1C, 0x20, 0x40, 0x20, 0x1C],
+    [0x3C, 0x40, 0x30, 0x40, 0x3C], [0x44, 0x28, 0x10, 0x28, 0x44], [0x0C, 0x50, 0x50, 0x50, 0x3C],
+    [0x44, 0x64, 0x54, 0x4C, 0x44], [0x00, 0x08, 0x36, 0x41, 0x00], [0x00, 0x00, 0x7F, 0x00, 0x00],
+    [0x00, 0x41, 0x36, 0x08, 0x00], [0x08, 0x04, 0x08, 0x10, 0x08],
+];
+
+/// The copyright sign, which Google's notices always start with.
+const COPYRIGHT: [u8; 5] = [0x3E, 0x5D, 0x55, 0x55, 0x3E];
+
+/// Glyph height in font pixels.
+const GLYPH_HEIGHT: u32 = 7;
+/// Horizontal distance between glyphs in font pixels.
+const GLYPH_ADVANCE: u32 = 6;
+/// Space around the text inside its backdrop, in font pixels.
+const PADDING: u32 = 2;
+
+/// Corner of the image an [`Attribution`] is drawn in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum AttributionPosition {
+    /// Top-left corner
+    TopLeft,
+    /// Top-right corner
+    TopRight,
+    /// Bottom-left corner
+    BottomLeft,
+    /// Bottom-right corner (default)
+    #[default]
+    BottomRight,
+}
+
+/// A copyright notice and/or logo drawn onto saved images.
+///
+/// Set it with [`SaveOptions::attribution`](crate::SaveOptions::attribution).
+/// Text is drawn in white on a translucent dark backdrop using a built-in
+/// ASCII bitmap font (plus `©`); other characters are drawn as `?`.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Attribution {
+    /// Notice text, e.g. the `copyright` of the panorama's metadata
+    pub text: String,
+    /// Logo image drawn left of the text, scaled to the text's height
+    pub logo: Option<PathBuf>,
+    /// Corner to draw in (default bottom-right)
+    pub position: AttributionPosition,
+    /// Size of one font pixel in image pixels (default 0: about 1/300 of
+    /// the image's shorter side)
+    pub scale: u32,
+}
+
+impl Attribution {
+    /// A text notice.
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Self::default()
+        }
+    }
+
+    /// A logo without text.
+    pub fn logo(path: impl Into<PathBuf>) -> Self {
+        Self {
+            logo: Some(path.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Add a logo next to the text.
+    pub fn with_logo(mut self, path: impl Into<PathBuf>) -> Self {
+        self.logo = Some(path.into());
+        self
+    }
+
+    /// Set the corner to draw in.
+    pub fn position(mut self, position: AttributionPosition) -> Self {
+        self.position = position;
+        self
+    }
+
+    /// Set the size of one font pixel in image pixels (0 = automatic).
+    pub fn scale(mut self, scale: u32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Draw the attribution onto an image.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the logo cannot be loaded.
+    pub fn apply(&self, img: &mut DynamicImage) -> Result<()> {
+        let (width, height) = img.dimensions();
+        let scale = match self.scale {
+            0 => (width.min(height) / 300).max(1),
+            scale => scale,
+        };
+        let line_height = (GLYPH_HEIGHT + 2 * PADDING) * scale;
+
+        let logo = match &self.logo {
+            Some(path) => {
+                let logo = image::open(path)?;
+                let logo_width = (u64::from(logo.width()) * u64::from(line_height) / u64::from(logo.height().max(1))).max(1);
+                Some(logo.resize_exact(logo_width as u32, line_height, FilterType::Triangle).to_rgba8())
+            }
+            None => None,
+        };
+        let logo_width = logo.as_ref().map_or(0, |logo| logo.width());
+        let glyphs = self.text.chars().count() as u32;
+        let text_width = match glyphs {
+            0 => 0,
+            n => (n * GLYPH_ADVANCE - 1 + 2 * PADDING) * scale,
+        };
+
+        let margin = 4 * scale;
+        let box_width = logo_width + text_width;
+        let left = match self.position {
+            AttributionPosition::TopLeft | AttributionPosition::BottomLeft => margin,
+            _ => width.saturating_sub(box_width + margin),
+        };
+        let top = match self.position {
+            AttributionPosition::TopLeft | AttributionPosition::TopRight => margin,
+            _ => height.saturating_sub(line_height + margin),
+        };
+
+        if let Some(logo) = &logo {
+            imageops::overlay(img, logo, i64::from(left), i64::from(top));
+        }
+        if glyphs > 0 {
+            let left = left + logo_width;
+            for y in top..(top + line_height).min(height) {
+                for x in left..(left + text_width).min(width) {
+                    let Rgba([r, g, b, a]) = img.get_pixel(x, y);
+                    img.put_pixel(x, y, Rgba([r / 2, g / 2, b / 2, a]));
+                }
+            }
+            let (origin_x, origin_y) = (left + PADDING * scale, top + PADDING * scale);
+            for (i, c) in self.text.chars().enumerate() {
+                draw_glyph(img, glyph(c), origin_x + i as u32 * GLYPH_ADVANCE * scale, origin_y, scale);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<&str> for Attribution {
+    fn from(text: &str) -> Self {
+        Self::text(text)
+    }
+}
+
+impl From<String> for Attribution {
+    fn from(text: String) -> Self {
+        Self::text(text)
+    }
+}
+
+/// Glyph columns for a character, `?` if the font lacks it.
+fn glyph(c: char) -> [u8; 5] {
+    match c {
+        '©' => COPYRIGHT,
+        ' '..='~' => FONT[c as usize - 0x20],
+        _ => FONT['?' as usize - 0x20],
+    }
+}
+
+fn draw_glyph(img: &mut DynamicImage, columns: [u8; 5], x0: u32, y0: u32, scale: u32) {
+    let (width, height) = img.dimensions();
+    for (col, bits) in (0u32..).zip(columns) {
+        for row in (0..GLYPH_HEIGHT).filter(|row| bits >> row & 1 == 1) {
+            for dy in 0..scale {
+                for dx in 0..scale {
+                    let (x, y) = (x0 + col * scale + dx, y0 + row * scale + dy);
+                    if x < width && y < height {
+                        let alpha = img.get_pixel(x, y)[3];
+                        img.put_pixel(x, y, Rgba([255, 255, 255, alpha]));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbImage;
+
+    fn lit_pixels(img: &DynamicImage) -> Vec<(u32, u32)> {
+        let img = img.to_rgb8();
+        img.enumerate_pixels().filter(|(_, _, p)| p[0] == 255).map(|(x, y, _)| (x, y)).collect()
+    }
+
+    #[test]
+    fn test_attribution_text() {
+        let mut img = DynamicImage::ImageRgb8(RgbImage::new(200, 100));
+        Attribution::text("© Google").scale(1).apply(&mut img).unwrap();
+
+        // 8 glyphs: 47 px of text plus padding, 4 px from the bottom-right corner
+        let lit = lit_pixels(&img);
+        assert!(!lit.is_empty());
+        assert!(lit.iter().all(|&(x, y)| (200 - 4 - 51..200 - 4).contains(&x) && (100 - 4 - 11..100 - 4).contains(&y)));
+
+        let mut img = DynamicImage::ImageRgb8(RgbImage::new(200, 100));
+        Attribution::text("A").position(AttributionPosition::TopLeft).scale(2).apply(&mut img).unwrap();
+        let lit = lit_pixels(&img);
+        assert!(lit.iter().all(|&(x, y)| x < 30 && y < 30));
+        assert_eq!(glyph('é'), glyph('?'));
+    }
+
+    #[test]
+    fn test_attribution_logo() {
+        let path = std::env::temp_dir().join("rsstreetview_attribution_logo.png");
+        RgbImage::from_pixel(4, 2, image::Rgb([255, 0, 0])).save(&path).unwrap();
+
+        let mut img = DynamicImage::ImageRgb8(RgbImage::new(100, 100));
+        Attribution::logo(&path).scale(1).apply(&mut img).unwrap();
+        let img = img.to_rgb8();
+        // Scaled to the 11 px line height: 22×11 at the bottom-right
+        assert_eq!(img.get_pixel(100 - 4 - 1, 100 - 4 - 1).0, [255, 0, 0]);
+        assert_eq!(img.get_pixel(100 - 4 - 22, 100 - 4 - 11).0, [255, 0, 0]);
+        assert_eq!(img.get_pixel(100 - 4 - 23, 100 - 4 - 11).0, [0, 0, 0]);
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Options that take no value.
+const FLAGS: &[&str] = &["json", "quiet", "help", "photometa", "geojson"];
+
+/// A command-line usage error.
+#[derive(Debug)]
+pub struct UsageError(pub String);
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for UsageError {}
+
+/// Parsed command line: a subcommand, its positional arguments, and
+/// `--name value` / `--name=value` options.
+#[derive(Debug, Default)]
+pub struct Args {
+    pub command: Option<String>,
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+}
+
+impl Args {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, UsageError> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let Some(name) = arg.strip_prefix("--") else {
+                if parsed.command.is_none() {
+                    parsed.command = Some(arg);
+                } else {
+                    parsed.positional.push(arg);
+                }
+                continue;
+            };
+
+            let (name, value) = match name.split_once('=') {
+                Some((name, value)) => (name.to_string(), value.to_string()),
+                None if FLAGS.contains(&name) => (name.to_string(), String::new()),
+                None => {
+                    let value = args.next().ok_or_else(|| UsageError(format!("--{name} needs a value")))?;
+                    (name.to_string(), value)
+                }
+            };
+            parsed.options.insert(name, value);
+        }
+
+        Ok(parsed)
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.options.contains_key(name)
+    }
+
+    pub fn option(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(String::as_str)
+    }
+
+    /// Parse an option, falling back to `default` when absent.
+    pub fn value<T: FromStr>(&self, name: &str, default: T) -> Result<T, UsageError> {
+        match self.option(name) {
+            Some(value) => value.parse().map_err(|_| UsageError(format!("invalid value for --{name}: {value}"))),
+            None => Ok(default),
+        }
+    }
+
+    /// Parse a comma-separated list option.
+    pub fn list<T: FromStr + Clone>(&self, name: &str, default: &[T]) -> Result<Vec<T>, UsageError> {
+        match self.option(name) {
+            Some(value) => value
+                .split(',')
+                .map(|item| item.trim().parse().map_err(|_| UsageError(format!("invalid value for --{name}: {item}"))))
+                .collect(),
+            None => Ok(default.to_vec()),
+        }
+    }
+
+    pub fn positional(&self) -> &[String] {
+        &self.positional
+    }
+
+    /// Parse the positional argument at `idx`, named `name` in errors.
+    pub fn arg<T: FromStr>(&self, idx: usize, name: &str) -> Result<T, UsageError> {
+        let value = self.positional.get(idx).ok_or_else(|| UsageError(format!("missing <{name}>")))?;
+        value.parse().map_err(|_| UsageError(format!("invalid <{name}>: {value}")))
+    }
+
+    /// Reject options the command does not understand, to catch typos.
+    pub fn check_options(&self, known: &[&str]) -> Result<(), UsageError> {
+        match self.options.keys().find(|name| !known.contains(&name.as_str())) {
+            Some(name) => Err(UsageError(format!("unknown option --{name}"))),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Args {
+        Args::parse(line.split_whitespace().map(String::from)).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let args = parse("views PANO --zoom 4 --heading=0,90 --json");
+        assert_eq!(args.command.as_deref(), Some("views"));
+        assert_eq!(args.positional(), ["PANO"]);
+        assert_eq!(args.value("zoom", 5u8).unwrap(), 4);
+        assert_eq!(args.value("fov", 90u16).unwrap(), 90);
+        assert_eq!(args.list("heading", &[0u16]).unwrap(), [0, 90]);
+        assert!(args.flag("json"));
+        assert!(args.check_options(&["zoom", "heading", "json"]).is_ok());
+        assert!(args.check_options(&["zoom"]).is_err());
+
+        assert!(parse("download PANO --zoom x").value("zoom", 5u8).is_err());
+        assert!(Args::parse(["search".to_string(), "--out".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,187 @@
+//! `streetview batch run`: run a [`ScrapeJob`] described by a TOML file.
+//!
+//! ```toml
+//! out = "dataset"              # store directory
+//! zoom = 4                     # default: 5
+//! layout = "sharded"           # flat (default), by_zoom, or sharded
+//! format = "jpg"               # jpg (default), png, or webp
+//! quality = 90
+//! state = "dataset/job.json"   # default: job.json in `out`
+//! locations = [[41.8982, 12.4764], [41.9009, 12.4833]]
+//! panoramas = ["CAoSLEFGMVFpcE..."]
+//! captured_after = "2018-01"
+//! captured_before = "2023-12"
+//! newest_only = true
+//! concurrency = 4
+//! max_attempts = 3
+//! max_memory_mb = 2048
+//! ```
+//!
+//! Relative paths are resolved from the job file's directory. The state
+//! file is saved after every search and download, so rerunning the same
+//! command after a crash or Ctrl-C resumes where it left off, and stored
+//! images are checked against the hashes recorded in it.
+
+use crate::args::{Args, UsageError};
+use crate::{known, CliResult};
+use rsstreetview::jobs::{JobEvent, ScrapeJob};
+use rsstreetview::store::{PanoramaStore, StoreLayout};
+use rsstreetview::{CaptureDate, ImageFormat, SaveOptions, StreetView};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// A job file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JobFile {
+    out: PathBuf,
+    #[serde(default = "default_zoom")]
+    zoom: u8,
+    #[serde(default)]
+    layout: Option<String>,
+    #[serde(default)]
+    format: Option<String>,
+    #[serde(default)]
+    quality: Option<u8>,
+    #[serde(default)]
+    state: Option<PathBuf>,
+    #[serde(default)]
+    locations: Vec<(f64, f64)>,
+    #[serde(default)]
+    panoramas: Vec<String>,
+    #[serde(default)]
+    captured_after: Option<CaptureDate>,
+    #[serde(default)]
+    captured_before: Option<CaptureDate>,
+    #[serde(default)]
+    newest_only: bool,
+    #[serde(default)]
+    concurrency: Option<usize>,
+    #[serde(default)]
+    max_attempts: Option<u32>,
+    #[serde(default)]
+    max_memory_mb: Option<u64>,
+}
+
+fn default_zoom() -> u8 {
+    5
+}
+
+impl JobFile {
+    fn parse(text: &str) -> CliResult<Self> {
+        Ok(toml::from_str(text)?)
+    }
+
+    /// Build the job, resolving relative paths from `base`.
+    fn job(self, client: &StreetView, base: &Path) -> CliResult<ScrapeJob> {
+        let out = base.join(&self.out);
+        let state = match &self.state {
+            Some(state) => base.join(state),
+            None => out.join("job.json"),
+        };
+
+        let layout = match self.layout.as_deref() {
+            None | Some("flat") => StoreLayout::Flat,
+            Some("by_zoom") => StoreLayout::ByZoom,
+            Some("sharded") => StoreLayout::Sharded,
+            Some(other) => return Err(UsageError(format!("unknown layout {other:?}")).into()),
+        };
+        let format: ImageFormat = match self.format.as_deref() {
+            Some(format) => format.parse().map_err(|_| UsageError(format!("unknown format {format:?}")))?,
+            None => ImageFormat::Jpeg,
+        };
+        let quality = self.quality.unwrap_or(90);
+        let save = SaveOptions::new().format(format).jpeg_quality(quality).webp_quality(quality);
+
+        let store = PanoramaStore::new(client.clone(), out).layout(layout).save_options(save);
+        let mut job = ScrapeJob::new(store, self.zoom)
+            .locations(self.locations)
+            .panoramas(self.panoramas)
+            .captured_between(self.captured_after, self.captured_before)
+            .state_file(state)
+            // Lose nothing to a crash or Ctrl-C
+            .checkpoint_every(1);
+        if self.newest_only {
+            job = job.newest_only();
+        }
+        if let Some(concurrency) = self.concurrency {
+            job = job.concurrency(concurrency);
+        }
+        if let Some(attempts) = self.max_attempts {
+            job = job.max_attempts(attempts);
+        }
+        if let Some(mb) = self.max_memory_mb {
+            job = job.max_memory_bytes(mb * 1024 * 1024);
+        }
+        Ok(job)
+    }
+}
+
+pub async fn run(client: &StreetView, args: &Args) -> CliResult<()> {
+    args.check_options(&known(&[]))?;
+    let action: String = args.arg(0, "action")?;
+    if action != "run" {
+        return Err(UsageError(format!("unknown batch action {action:?}")).into());
+    }
+    let path: PathBuf = args.arg(1, "job.toml")?;
+
+    let text = std::fs::read_to_string(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let file = JobFile::parse(&text).map_err(|e| format!("{}: {e}", path.display()))?;
+    let base = path.parent().unwrap_or(Path::new(""));
+    let mut job = file.job(client, base)?;
+
+    let json = args.flag("json");
+    let quiet = args.flag("quiet");
+    job = job.with_hook(move |event: JobEvent| async move {
+        if json {
+            if let Ok(line) = serde_json::to_string(&event) {
+                println!("{line}");
+            }
+            return;
+        }
+        match event {
+            JobEvent::Completed { path, downloaded: true, .. } if !quiet => eprintln!("{}", path.display()),
+            JobEvent::Failed(failure) => eprintln!("{} (attempt {}): {}", failure.id, failure.attempts, failure.error),
+            _ => {}
+        }
+    });
+
+    let report = job.run().await?;
+    match json {
+        true => println!("{}", serde_json::to_string_pretty(&report)?),
+        false => eprintln!("{report}"),
+    }
+
+    match report.is_complete() {
+        true => Ok(()),
+        false => Err(format!("{} searches or downloads failed; run again to retry", report.failures.len()).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_file() {
+        let file = JobFile::parse(
+            r#"
+            out = "dataset"
+            zoom = 3
+            locations = [[41.9, 12.5]]
+            panoramas = ["A"]
+            captured_after = "2018-01"
+            "#,
+        )
+        .unwrap();
+        assert_eq!((file.zoom, file.locations.len()), (3, 1));
+        assert_eq!(file.captured_after, CaptureDate::new(2018, 1));
+
+        let job = file.job(&StreetView::new(), Path::new("jobs")).unwrap();
+        assert_eq!(job.targets().len(), 2);
+
+        assert!(JobFile::parse("out = \"dataset\"\nzom = 3").is_err());
+        let bad_layout = JobFile::parse("out = \"dataset\"\nlayout = \"nested\"").unwrap();
+        assert!(bad_layout.job(&StreetView::new(), Path::new("")).is_err());
+    }
+}
@@ -0,0 +1,305 @@
+//! `streetview crawl`: breadth-first download of connected panoramas.
+//!
+//! The crawl state is written to a checkpoint file after every panorama, so
+//! rerunning the same command after a crash or Ctrl-C resumes where it left
+//! off. A panorama that fails is recorded and skipped, and retried when the
+//! crawl is resumed until it has failed `--max-attempts` times. The
+//! checkpoint records a SHA-256 hash of every image saved, and outputs
+//! already on disk are only skipped if they still match it.
+
+use crate::args::{Args, UsageError};
+use crate::output::{render, Progress, TemplateValues};
+use crate::{known, save_options, CliResult, OUTPUT};
+use rsstreetview::{Location, PhotoMeta, SaveOptions, StreetView, StreetViewError};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::path::{Path, PathBuf};
+
+/// Crawl state saved between runs.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Checkpoint {
+    /// Panoramas waiting to be downloaded, in crawl order
+    queue: VecDeque<String>,
+    /// Every panorama queued so far, downloaded or not
+    seen: BTreeSet<String>,
+    /// Number of panoramas finished
+    done: usize,
+    /// Hex SHA-256 of each saved image, by panorama ID
+    hashes: BTreeMap<String, String>,
+    /// Panoramas that failed, by ID
+    failed: BTreeMap<String, Failure>,
+}
+
+/// A panorama that failed to download.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Failure {
+    attempts: u32,
+    error: String,
+}
+
+impl Checkpoint {
+    fn start(pano_id: String) -> Self {
+        Self {
+            queue: VecDeque::from([pano_id.clone()]),
+            seen: BTreeSet::from([pano_id]),
+            done: 0,
+            hashes: BTreeMap::new(),
+            failed: BTreeMap::new(),
+        }
+    }
+
+    fn load(path: &Path) -> CliResult<Option<Self>> {
+        match std::fs::read(path) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Write atomically, so a crash mid-write keeps the previous state.
+    fn save(&self, path: &Path) -> CliResult<()> {
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec(self)?)?;
+        std::fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    /// Queue panoramas not seen before.
+    fn enqueue(&mut self, pano_ids: impl IntoIterator<Item = String>) {
+        for pano_id in pano_ids {
+            if self.seen.insert(pano_id.clone()) {
+                self.queue.push_back(pano_id);
+            }
+        }
+    }
+
+    /// Record a failed attempt at the panorama at the front of the queue
+    /// and drop it from the queue.
+    fn fail(&mut self, error: String) {
+        if let Some(pano_id) = self.queue.pop_front() {
+            let failure = self.failed.entry(pano_id).or_default();
+            failure.attempts += 1;
+            failure.error = error;
+        }
+    }
+
+    /// Queue failed panoramas with attempts left again.
+    fn retry_failed(&mut self, max_attempts: u32) {
+        for (pano_id, failure) in &self.failed {
+            if failure.attempts < max_attempts && !self.queue.contains(pano_id) {
+                self.queue.push_back(pano_id.clone());
+            }
+        }
+    }
+}
+
+/// Where and how crawled panoramas are saved.
+struct Output<'a> {
+    template: &'a str,
+    zoom: u8,
+    options: SaveOptions,
+}
+
+pub async fn run(client: &StreetView, args: &Args) -> CliResult<()> {
+    args.check_options(&known(&[OUTPUT, &["max", "checkpoint", "max-attempts"]]))?;
+    let lat: f64 = args.arg(0, "lat")?;
+    let lon: f64 = args.arg(1, "lon")?;
+    let max = args.value("max", 100usize)?;
+    let max_attempts = args.value("max-attempts", 3u32)?.max(1);
+    let checkpoint_path = PathBuf::from(args.option("checkpoint").unwrap_or("crawl.json"));
+    let output = Output {
+        template: args.option("out").unwrap_or("crawl/{pano_id}_z{zoom}.{ext}"),
+        zoom: args.value("zoom", 5u8)?,
+        options: save_options(args)?,
+    };
+
+    let mut state = match Checkpoint::load(&checkpoint_path)? {
+        Some(mut state) => {
+            eprintln!("Resuming from {} ({} done)", checkpoint_path.display(), state.done);
+            state.retry_failed(max_attempts);
+            state
+        }
+        None => {
+            let panos = client.search_panoramas(lat, lon).await?;
+            let start = Location::new(lat, lon);
+            let nearest = panos
+                .into_iter()
+                .min_by(|a, b| {
+                    a.location().distance_to(&start).total_cmp(&b.location().distance_to(&start))
+                })
+                .ok_or_else(|| UsageError(format!("no panoramas near {lat}, {lon}")))?;
+            Checkpoint::start(nearest.pano_id)
+        }
+    };
+
+    let mut progress = Progress::new(max, args.flag("quiet"));
+    for _ in 0..state.done.min(max) {
+        progress.inc("");
+    }
+
+    let mut failed = 0;
+    while state.done < max {
+        let Some(pano_id) = state.queue.front().cloned() else {
+            break;
+        };
+
+        let (meta, path, hash) = match fetch(client, &pano_id, state.done, state.hashes.get(&pano_id), &output).await {
+            Ok(fetched) => fetched,
+            Err(e) if is_fatal(&e) => {
+                state.save(&checkpoint_path)?;
+                return Err(e.into());
+            }
+            Err(e) => {
+                failed += 1;
+                match args.flag("json") {
+                    true => progress.println(&json!({ "pano_id": pano_id, "error": e.to_string() }).to_string()),
+                    false => progress.println(&format!("{pano_id}: {e}")),
+                }
+                state.fail(e.to_string());
+                state.save(&checkpoint_path)?;
+                continue;
+            }
+        };
+
+        if args.flag("json") {
+            let line = json!({
+                "pano_id": pano_id,
+                "lat": meta.lat,
+                "lon": meta.lon,
+                "date": meta.date,
+                "path": path,
+                "skipped": hash.is_none(),
+            });
+            progress.println(&line.to_string());
+        }
+
+        state.queue.pop_front();
+        state.failed.remove(&pano_id);
+        if let Some(hash) = hash {
+            state.hashes.insert(pano_id.clone(), hash);
+        }
+        state.enqueue(meta.links.into_iter().map(|link| link.pano_id));
+        state.done += 1;
+        state.save(&checkpoint_path)?;
+        progress.inc(&pano_id);
+    }
+    progress.finish();
+
+    eprintln!("Crawled {} panoramas, {} queued", state.done, state.queue.len());
+    match failed {
+        0 => Ok(()),
+        n => Err(format!("{n} panoramas failed; run again to retry them").into()),
+    }
+}
+
+/// Fetch a panorama's metadata and save its image, unless the file from an
+/// earlier run still matches `hash`.
+///
+/// Returns the metadata, the output path, and the new image's hash, or
+/// `None` if the existing file was kept.
+async fn fetch(
+    client: &StreetView,
+    pano_id: &str,
+    index: usize,
+    hash: Option<&String>,
+    output: &Output<'_>,
+) -> rsstreetview::Result<(PhotoMeta, PathBuf, Option<String>)> {
+    let meta = client.get_photometa(pano_id).await?;
+    let ext = output.options.format.extension();
+    let path = render(
+        output.template,
+        &TemplateValues {
+            pano_id,
+            zoom: Some(output.zoom),
+            lat: Some(meta.lat),
+            lon: Some(meta.lon),
+            date: meta.date,
+            index,
+            ext,
+            ..Default::default()
+        },
+    );
+
+    if hash.is_some_and(|hash| hash_file(&path).is_ok_and(|h| &h == hash)) {
+        return Ok((meta, path, None));
+    }
+    let image = client.download_panorama(pano_id, output.zoom).await?;
+    let tmp = path.with_extension(format!("{ext}.part"));
+    output.options.save_async(image, &tmp).await?;
+    tokio::fs::rename(&tmp, &path).await?;
+    let hash = hash_file(&path)?;
+    Ok((meta, path, Some(hash)))
+}
+
+/// Errors that stop the crawl instead of skipping the panorama, since
+/// every later panorama would fail the same way.
+fn is_fatal(error: &StreetViewError) -> bool {
+    matches!(
+        error,
+        StreetViewError::Blocked { .. } | StreetViewError::QuotaBudgetExceeded(_) | StreetViewError::PolicyViolation(_)
+    )
+}
+
+/// Hex SHA-256 of a file.
+fn hash_file(path: &Path) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+    Ok(hasher.finalize().iter().map(|b| format!("{b:02x}")).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_roundtrip() {
+        let mut state = Checkpoint::start("A".to_string());
+        state.enqueue(["B".to_string(), "A".to_string(), "C".to_string()]);
+        assert_eq!(state.queue, ["A", "B", "C"]);
+
+        let path = std::env::temp_dir().join("rsstreetview_crawl_checkpoint.json");
+        state.save(&path).unwrap();
+        let loaded = Checkpoint::load(&path).unwrap().unwrap();
+        assert_eq!(loaded.queue, state.queue);
+        assert_eq!(loaded.seen, state.seen);
+        let _ = std::fs::remove_file(&path);
+
+        assert!(Checkpoint::load(&path).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_failures_are_skipped_and_retried() {
+        let mut state = Checkpoint::start("A".to_string());
+        state.enqueue(["B".to_string()]);
+        state.fail("timed out".to_string());
+        assert_eq!(state.queue, ["B"]);
+        assert_eq!(state.failed["A"].attempts, 1);
+
+        // A resumed crawl retries it until it runs out of attempts
+        state.retry_failed(3);
+        assert_eq!(state.queue, ["B", "A"]);
+        state.retry_failed(3);
+        assert_eq!(state.queue, ["B", "A"]);
+        state.queue.pop_front();
+        for _ in 0..2 {
+            state.fail("timed out".to_string());
+            state.retry_failed(3);
+        }
+        assert_eq!(state.failed["A"].attempts, 3);
+        assert!(state.queue.is_empty());
+    }
+
+    #[test]
+    fn test_hash_file() {
+        let path = std::env::temp_dir().join("rsstreetview_crawl_hash.txt");
+        std::fs::write(&path, "abc").unwrap();
+        assert_eq!(
+            hash_file(&path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,289 @@
+//! `streetview`: command-line access to the rsstreetview library.
+//!
+//! Build with `cargo install rsstreetview --features cli`. Run
+//! `streetview help` for usage.
+
+mod args;
+mod batch;
+mod crawl;
+mod output;
+mod viewer;
+
+use args::{Args, UsageError};
+use output::{render, Progress, TemplateValues};
+use rsstreetview::{ImageFormat, SaveOptions, StreetView, ToGeoJson, ViewConfig};
+use serde_json::json;
+use std::process::ExitCode;
+
+type CliResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+const USAGE: &str = "\
+Usage: streetview <command> [arguments] [options]
+
+Commands:
+  search <lat> <lon>        List panoramas near a coordinate
+  download <pano_id>...     Download full panoramas
+  views <pano_id>           Extract perspective views from a panorama
+  meta <pano_id>            Print panorama metadata as JSON
+  crawl <lat> <lon>         Download connected panoramas, starting near a
+                            coordinate; rerun with the same --checkpoint
+                            to resume an interrupted crawl
+  batch run <job.toml>      Run a scrape job described by a TOML file;
+                            rerun to resume, re-downloading any stored
+                            image that no longer matches its hash
+  viewer <image|pano_id>    Export a panorama as an offline HTML viewer
+                            folder, from an image saved with a .json
+                            sidecar or by downloading a panorama ID
+
+Common options:
+  --api-key KEY             Google Maps API key (default: $GOOGLE_MAPS_API_KEY)
+  --max-qps N               Limit requests per second
+  --json                    Print results as JSON
+  --quiet                   Hide progress bars
+
+Output options (download, views, crawl):
+  --out TEMPLATE            Output path; placeholders {pano_id} {zoom}
+                            {heading} {lat} {lon} {date} {index} {ext}
+  --format jpg|png|webp     Image format (default: jpg)
+  --quality N               JPEG/WebP quality (default: 90)
+  --zoom N                  Zoom level 1-7 (default: 5; views: 3)
+
+Command options:
+  search: --geojson         Print results as a GeoJSON FeatureCollection
+  views:  --heading H,...   Headings in degrees (default: 0,90,180,270)
+          --fov N           Field of view (default: 90)
+          --pitch N         Pitch in degrees (default: 0)
+          --size WxH        View size (default: 1024x768)
+          --view SPEC;...   Views in compact form instead, e.g.
+                            \"h90,fov100,p-10,z4;back_left\"
+  meta:   --photometa       Use the undocumented photometa endpoint, even
+                            with an API key
+  crawl:  --max N           Stop after N panoramas (default: 100)
+          --checkpoint FILE Crawl state file (default: crawl.json)
+          --max-attempts N  Runs that may retry a failed panorama
+                            (default: 3)
+  viewer: --out DIR         Output folder (default: <pano_id>_viewer)
+          --zoom N          Zoom level to download (default: 4)
+          --title TEXT      Page title (default: the panorama ID)
+          --pannellum DIR   Embed pannellum from a release's build/ folder
+                            instead of the built-in viewer
+          --max-width N     Width of the embedded image (default: 8192)
+          --quality N       JPEG quality (default: 85)
+";
+
+const COMMON: &[&str] = &["api-key", "max-qps", "json", "quiet"];
+const OUTPUT: &[&str] = &["out", "format", "quality", "zoom"];
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let result = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => run(args).await,
+        Err(e) => Err(e.into()),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) if e.is::<UsageError>() => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            ExitCode::from(2)
+        }
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(args: Args) -> CliResult<()> {
+    let command = args.command.as_deref().unwrap_or("help");
+    if args.flag("help") || command == "help" {
+        print!("{USAGE}");
+        return Ok(());
+    }
+
+    let client = client(&args)?;
+    match command {
+        "search" => search(&client, &args).await,
+        "download" => download(&client, &args).await,
+        "views" => views(&client, &args).await,
+        "meta" => meta(&client, &args).await,
+        "crawl" => crawl::run(&client, &args).await,
+        "batch" => batch::run(&client, &args).await,
+        "viewer" => viewer::run(&client, &args).await,
+        other => Err(UsageError(format!("unknown command {other:?}")).into()),
+    }
+}
+
+fn client(args: &Args) -> CliResult<StreetView> {
+    let mut builder = StreetView::builder();
+    if let Some(key) = args.option("api-key").map(String::from).or_else(|| std::env::var("GOOGLE_MAPS_API_KEY").ok()) {
+        builder = builder.api_key(key);
+    }
+    if let Some(qps) = args.option("max-qps") {
+        builder = builder.max_qps(qps.parse().map_err(|_| UsageError(format!("invalid value for --max-qps: {qps}")))?);
+    }
+    Ok(builder.build()?)
+}
+
+/// Known options for a command: the common ones plus `extra`.
+fn known<'a>(extra: &[&'a [&'a str]]) -> Vec<&'a str> {
+    COMMON.iter().chain(extra.iter().flat_map(|set| set.iter())).copied().collect()
+}
+
+/// Save options from `--format` and `--quality`.
+pub(crate) fn save_options(args: &Args) -> CliResult<SaveOptions> {
+    let format = args.option("format").unwrap_or("jpg");
+    let format: ImageFormat = format.parse().map_err(|_| UsageError(format!("unknown format {format:?}")))?;
+    let quality = args.value("quality", 90u8)?;
+    Ok(SaveOptions::new().format(format).jpeg_quality(quality).webp_quality(quality))
+}
+
+async fn search(client: &StreetView, args: &Args) -> CliResult<()> {
+    args.check_options(&known(&[&["geojson"]]))?;
+    let lat: f64 = args.arg(0, "lat")?;
+    let lon: f64 = args.arg(1, "lon")?;
+
+    let panos = client.search_panoramas(lat, lon).await?;
+    if args.flag("geojson") {
+        println!("{}", serde_json::to_string_pretty(&panos.to_geojson())?);
+        return Ok(());
+    }
+    if args.flag("json") {
+        println!("{}", serde_json::to_string_pretty(&panos)?);
+        return Ok(());
+    }
+
+    for pano in &panos {
+        println!(
+            "{}\t{:.6}\t{:.6}\t{}\t{:.1}",
+            pano.pano_id,
+            pano.lat,
+            pano.lon,
+            pano.date.map_or_else(|| "-".to_string(), |date| date.to_string()),
+            pano.heading
+        );
+    }
+    Ok(())
+}
+
+async fn download(client: &StreetView, args: &Args) -> CliResult<()> {
+    args.check_options(&known(&[OUTPUT]))?;
+    let pano_ids = args.positional();
+    if pano_ids.is_empty() {
+        return Err(UsageError("missing <pano_id>".to_string()).into());
+    }
+    let zoom = args.value("zoom", 5u8)?;
+    let template = args.option("out").unwrap_or("{pano_id}_z{zoom}.{ext}");
+    let options = save_options(args)?;
+
+    let mut progress = Progress::new(pano_ids.len(), args.flag("quiet"));
+    let mut failed = 0;
+    for (index, pano_id) in pano_ids.iter().enumerate() {
+        let path = render(
+            template,
+            &TemplateValues {
+                pano_id,
+                zoom: Some(zoom),
+                index,
+                ext: options.format.extension(),
+                ..Default::default()
+            },
+        );
+
+        let result = match client.download_panorama(pano_id, zoom).await {
+            Ok(image) => options.save_async(image, &path).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) if args.flag("json") => {
+                progress.println(&json!({ "pano_id": pano_id, "path": path }).to_string())
+            }
+            Ok(()) => progress.println(&path.display().to_string()),
+            Err(e) => {
+                failed += 1;
+                progress.println(&format!("{pano_id}: {e}"));
+            }
+        }
+        progress.inc(pano_id);
+    }
+    progress.finish();
+
+    match failed {
+        0 => Ok(()),
+        n => Err(format!("{n} of {} downloads failed", pano_ids.len()).into()),
+    }
+}
+
+async fn views(client: &StreetView, args: &Args) -> CliResult<()> {
+    args.check_options(&known(&[OUTPUT, &["heading", "fov", "pitch", "size", "view"]]))?;
+    let pano_id: String = args.arg(0, "pano_id")?;
+    let zoom = args.value("zoom", 3u8)?;
+    let headings = args.list("heading", &[0u16, 90, 180, 270])?;
+    let fov = args.value("fov", 90u16)?;
+    let pitch = args.value("pitch", 0i16)?;
+    let size = args.option("size").unwrap_or("1024x768");
+    let (width, height) = size
+        .split_once('x')
+        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+        .ok_or_else(|| UsageError(format!("invalid value for --size: {size}")))?;
+    let template = args.option("out").unwrap_or("{pano_id}_h{heading}.{ext}");
+    let options = save_options(args)?;
+
+    let configs: Vec<ViewConfig> = match args.option("view") {
+        Some(views) => views
+            .split(';')
+            .map(|view| {
+                let config: ViewConfig = view.parse().map_err(|_| UsageError(format!("invalid value for --view: {view}")))?;
+                Ok(match config.size {
+                    Some(_) => config,
+                    None => config.size(width, height),
+                })
+            })
+            .collect::<Result<_, UsageError>>()?,
+        None => headings
+            .iter()
+            .map(|&heading| ViewConfig::new(heading).fov(fov).pitch(pitch).size(width, height).zoom(zoom))
+            .collect(),
+    };
+    let images = client.extract_multiple_views(&pano_id, &configs).await?;
+
+    let mut saved = Vec::new();
+    for (index, (image, config)) in images.into_iter().zip(&configs).enumerate() {
+        let heading = config.heading;
+        let path = render(
+            template,
+            &TemplateValues {
+                pano_id: &pano_id,
+                zoom: Some(config.zoom),
+                heading: Some(heading),
+                index,
+                ext: options.format.extension(),
+                ..Default::default()
+            },
+        );
+        options.save_async(image, &path).await?;
+        saved.push(json!({ "heading": heading, "path": path }));
+        if !args.flag("json") {
+            println!("{}", path.display());
+        }
+    }
+
+    if args.flag("json") {
+        println!("{}", serde_json::to_string_pretty(&json!({ "pano_id": pano_id, "views": saved }))?);
+    }
+    Ok(())
+}
+
+async fn meta(client: &StreetView, args: &Args) -> CliResult<()> {
+    args.check_options(&known(&[&["photometa"]]))?;
+    let pano_id: String = args.arg(0, "pano_id")?;
+
+    let has_key = args.option("api-key").is_some() || std::env::var_os("GOOGLE_MAPS_API_KEY").is_some();
+    let meta = if has_key && !args.flag("photometa") {
+        serde_json::to_value(client.get_panorama_meta(&pano_id).await?)?
+    } else {
+        serde_json::to_value(client.get_photometa(&pano_id).await?)?
+    };
+    println!("{}", serde_json::to_string_pretty(&meta)?);
+    Ok(())
+}
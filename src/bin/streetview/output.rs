@@ -0,0 +1,111 @@
+use rsstreetview::CaptureDate;
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+
+/// Values substituted into an output path template.
+///
+/// Placeholders: `{pano_id}`, `{zoom}`, `{heading}`, `{lat}`, `{lon}`,
+/// `{date}`, `{index}`, `{ext}`. Values that are not known for a command
+/// render as `unknown`.
+#[derive(Debug, Default)]
+pub struct TemplateValues<'a> {
+    pub pano_id: &'a str,
+    pub zoom: Option<u8>,
+    pub heading: Option<u16>,
+    pub lat: Option<f64>,
+    pub lon: Option<f64>,
+    pub date: Option<CaptureDate>,
+    pub index: usize,
+    pub ext: &'a str,
+}
+
+/// Render an output path from `template`.
+pub fn render(template: &str, values: &TemplateValues) -> PathBuf {
+    fn known<T: ToString>(value: Option<T>) -> String {
+        value.map_or_else(|| "unknown".to_string(), |v| v.to_string())
+    }
+
+    let rendered = template
+        .replace("{pano_id}", values.pano_id)
+        .replace("{zoom}", &known(values.zoom))
+        .replace("{heading}", &known(values.heading))
+        .replace("{lat}", &known(values.lat))
+        .replace("{lon}", &known(values.lon))
+        .replace("{date}", &known(values.date))
+        .replace("{index}", &values.index.to_string())
+        .replace("{ext}", values.ext);
+    PathBuf::from(rendered)
+}
+
+/// A progress bar on stderr, shown only when stderr is a terminal.
+pub struct Progress {
+    total: usize,
+    done: usize,
+    visible: bool,
+}
+
+impl Progress {
+    const WIDTH: usize = 30;
+
+    pub fn new(total: usize, quiet: bool) -> Self {
+        let progress = Self {
+            total,
+            done: 0,
+            visible: !quiet && std::io::stderr().is_terminal(),
+        };
+        progress.draw("");
+        progress
+    }
+
+    /// Count one item as finished.
+    pub fn inc(&mut self, label: &str) {
+        self.done += 1;
+        self.draw(label);
+    }
+
+    /// Print a line above the bar.
+    pub fn println(&self, line: &str) {
+        if self.visible {
+            eprint!("\r\x1b[2K");
+        }
+        eprintln!("{line}");
+        self.draw("");
+    }
+
+    pub fn finish(&self) {
+        if self.visible {
+            eprintln!();
+        }
+    }
+
+    fn draw(&self, label: &str) {
+        if !self.visible {
+            return;
+        }
+        let filled = (self.done * Self::WIDTH).checked_div(self.total).unwrap_or(Self::WIDTH).min(Self::WIDTH);
+        let bar = format!("{}{}", "#".repeat(filled), ".".repeat(Self::WIDTH - filled));
+        eprint!("\r\x1b[2K[{bar}] {}/{} {label}", self.done, self.total);
+        let _ = std::io::stderr().flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let values = TemplateValues {
+            pano_id: "PANO",
+            zoom: Some(4),
+            lat: Some(41.9),
+            index: 2,
+            ext: "jpg",
+            ..Default::default()
+        };
+        assert_eq!(
+            render("out/{index}_{pano_id}_z{zoom}_{lat}_{date}.{ext}", &values),
+            PathBuf::from("out/2_PANO_z4_41.9_unknown.jpg")
+        );
+    }
+}
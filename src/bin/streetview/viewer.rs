@@ -0,0 +1,75 @@
+//! `streetview viewer`: export a panorama as a static HTML viewer.
+//!
+//! Takes either a saved panorama image with its `.json` sidecar, or a
+//! panorama ID to download, and writes a folder that opens from disk with
+//! no server or Google dependency.
+
+use crate::args::{Args, UsageError};
+use crate::{known, CliResult};
+use image::DynamicImage;
+use rsstreetview::viewer::{export_viewer, PannellumAssets, ViewerOptions};
+use rsstreetview::{sidecar_path, DateConfidence, Panorama, SidecarMetadata, StreetView};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+
+pub async fn run(client: &StreetView, args: &Args) -> CliResult<()> {
+    args.check_options(&known(&[&["out", "zoom", "title", "pannellum", "max-width", "quality"]]))?;
+    let mut options = ViewerOptions::new()
+        .max_width(args.value("max-width", 8192u32)?)
+        .jpeg_quality(args.value("quality", 85u8)?);
+    if let Some(title) = args.option("title") {
+        options = options.title(title);
+    }
+    if let Some(dir) = args.option("pannellum") {
+        options = options.pannellum(PannellumAssets::from_dir(dir)?);
+    }
+
+    let source: String = args.arg(0, "image|pano_id")?;
+    let (image, pano) = if Path::new(&source).is_file() {
+        open_saved(Path::new(&source))?
+    } else {
+        download(client, &source, args.value("zoom", 4u8)?).await?
+    };
+
+    let out = args.option("out").map_or_else(|| PathBuf::from(format!("{}_viewer", pano.pano_id)), PathBuf::from);
+    let index = tokio::task::spawn_blocking(move || export_viewer(&image, &pano, &out, &options)).await??;
+    if args.flag("json") {
+        println!("{}", json!({ "index": index }));
+    } else {
+        println!("{}", index.display());
+    }
+    Ok(())
+}
+
+/// A saved panorama image and the panorama described by its sidecar.
+fn open_saved(path: &Path) -> CliResult<(DynamicImage, Panorama)> {
+    let sidecar = sidecar_path(path);
+    if !sidecar.exists() {
+        return Err(format!("{}: no sidecar metadata at {}", path.display(), sidecar.display()).into());
+    }
+    let meta = SidecarMetadata::read(&sidecar)?;
+    if meta.view.is_some() {
+        return Err(UsageError(format!("{} is a perspective view, not a panorama", path.display())).into());
+    }
+    Ok((image::open(path)?, meta.panorama()))
+}
+
+/// Download a panorama and its metadata.
+async fn download(client: &StreetView, pano_id: &str, zoom: u8) -> CliResult<(DynamicImage, Panorama)> {
+    let meta = client.get_photometa(pano_id).await?;
+    let image = client.download_panorama(pano_id, zoom).await?;
+    let pano = Panorama {
+        pano_id: meta.pano_id,
+        lat: meta.lat,
+        lon: meta.lon,
+        heading: meta.heading.unwrap_or(0.0),
+        pitch: meta.pitch,
+        roll: meta.roll,
+        date: meta.date,
+        date_confidence: DateConfidence::of(meta.date),
+        elevation: meta.elevation,
+        copyright: meta.copyright,
+        street_name: meta.address.into_iter().next(),
+    };
+    Ok((image, pano))
+}
@@ -0,0 +1,318 @@
+//! Bing Maps Streetside panoramas.
+//!
+//! [`BingStreetside`] searches Bing's Streetside coverage and downloads its
+//! cubemap panoramas, reprojected to the same equirectangular layout as
+//! Google panoramas. It implements
+//! [`PanoramaProvider`](crate::provider::PanoramaProvider), so it can stand
+//! in where Google has no coverage.
+//!
+//! Like Google's tile endpoints, the Streetside endpoints are undocumented
+//! and may change without notice. Requests go through the
+//! [`StreetView`](crate::StreetView) client the provider was created from,
+//! so its rate limit, proxies, cache, retry policy, and usage policy apply.
+//!
+//! Requires the `bing` feature.
+
+use crate::download::{fetch_tile_with_retry, CONCURRENT_DOWNLOADS};
+use crate::error::{Result, StreetViewError};
+use crate::http::Http;
+use crate::policy::Policy;
+use crate::provider::PanoramaProvider;
+use crate::types::{CaptureDate, DateConfidence, Location, Panorama, TileInfo};
+use crate::utils::bounding_box;
+use crate::StreetView;
+use futures::future::BoxFuture;
+use futures::stream::{self, StreamExt};
+use image::{DynamicImage, Rgb, RgbImage};
+use serde_json::Value;
+use std::f64::consts::PI;
+
+const METADATA_ENDPOINT: &str = "https://t.ssl.ak.tiles.virtualearth.net/tiles/cmd/StreetSideBubbleMetaData";
+const TILE_ENDPOINT: &str = "https://t.ssl.ak.tiles.virtualearth.net/tiles";
+const TILE_SIZE: u32 = 256;
+const MAX_ZOOM: u8 = 4;
+const MAX_RESULTS: u32 = 50;
+const DEFAULT_RADIUS_M: f64 = 25.0;
+
+/// Cube faces in Bing's numbering: front, right, back, left, top, bottom.
+const FACES: [u8; 6] = [1, 2, 3, 4, 11, 12];
+
+/// Bing Maps Streetside provider.
+///
+/// Pano IDs are Bing's numeric bubble IDs as decimal strings. Zoom levels
+/// run from 1 to 4; level `z` downloads cube faces of `256 * 2^z` pixels and
+/// yields a `1024 * 2^z` pixel wide panorama.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rsstreetview::bing::BingStreetside;
+/// # use rsstreetview::StreetView;
+/// # #[tokio::main]
+/// # async fn main() -> rsstreetview::Result<()> {
+/// let bing = BingStreetside::new(&StreetView::new()).radius(50.0);
+/// let panos = bing.search_panoramas(47.6205, -122.3493).await?;
+/// let image = bing.download_panorama(&panos[0].pano_id, 3).await?;
+/// image.save("streetside.jpg")?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct BingStreetside {
+    http: Http,
+    policy: Policy,
+    radius: f64,
+}
+
+impl BingStreetside {
+    /// Create a provider sending requests through `client`.
+    pub fn new(client: &StreetView) -> Self {
+        Self {
+            http: client.http.clone(),
+            policy: client.policy.clone(),
+            radius: DEFAULT_RADIUS_M,
+        }
+    }
+
+    /// Set the search radius in meters (default 25).
+    pub fn radius(mut self, meters: f64) -> Self {
+        self.radius = meters.max(1.0);
+        self
+    }
+
+    /// Search for Streetside panoramas near a coordinate, nearest first.
+    pub async fn search_panoramas(&self, lat: f64, lon: f64) -> Result<Vec<Panorama>> {
+        self.policy.check_unofficial("Bing Streetside search")?;
+
+        let (south, west, north, east) = bounding_box(lat, lon, self.radius);
+        let url = format!(
+            "{METADATA_ENDPOINT}?count={MAX_RESULTS}&north={north}&south={south}&east={east}&west={west}"
+        );
+
+        let mut panos = self.http.fetch(&url, false, parse_bubbles).await?;
+        let target = Location::new(lat, lon);
+        panos.sort_by(|a, b| a.location().distance_to(&target).total_cmp(&b.location().distance_to(&target)));
+        Ok(panos)
+    }
+
+    /// Download the six cube faces of a panorama, in the order front, right,
+    /// back, left, top, bottom.
+    ///
+    /// Each face is `256 * 2^zoom` pixels square, with `zoom` clamped to 1-4.
+    /// The top face's bottom edge and the bottom face's top edge adjoin the
+    /// front face.
+    pub async fn download_faces(&self, pano_id: &str, zoom: u8) -> Result<[RgbImage; 6]> {
+        self.policy.check_unofficial("Bing Streetside download")?;
+        let id: u64 = pano_id
+            .trim()
+            .parse()
+            .map_err(|_| StreetViewError::InvalidPanoId(format!("{pano_id:?} (expected a numeric Bing bubble ID)")))?;
+        let zoom = zoom.clamp(1, MAX_ZOOM);
+
+        let jobs: Vec<(usize, TileInfo)> = (0..FACES.len())
+            .flat_map(|face| {
+                quadkeys(zoom).into_iter().map(move |(quadkey, x, y)| {
+                    let url = tile_url(id, FACES[face], &quadkey);
+                    (face, TileInfo { x, y, url })
+                })
+            })
+            .collect();
+
+        let tiles: Vec<_> = stream::iter(jobs)
+            .map(|(face, tile_info)| async move { (face, fetch_tile_with_retry(&self.http, &tile_info, zoom).await) })
+            .buffer_unordered(CONCURRENT_DOWNLOADS)
+            .collect()
+            .await;
+
+        let size = TILE_SIZE << zoom;
+        let mut faces: [RgbImage; 6] = std::array::from_fn(|_| RgbImage::new(size, size));
+        for (face, tile) in tiles {
+            let tile = tile?;
+            let (x, y) = (i64::from(tile.x * TILE_SIZE), i64::from(tile.y * TILE_SIZE));
+            image::imageops::replace(&mut faces[face], &tile.image.to_rgb8(), x, y);
+        }
+        Ok(faces)
+    }
+
+    /// Download a panorama as an equirectangular image centered on its
+    /// front face.
+    pub async fn download_panorama(&self, pano_id: &str, zoom: u8) -> Result<DynamicImage> {
+        let faces = self.download_faces(pano_id, zoom).await?;
+        let image = tokio::task::spawn_blocking(move || cubemap_to_equirectangular(&faces))
+            .await
+            .map_err(|e| StreetViewError::IoError(std::io::Error::other(e)))?;
+        Ok(DynamicImage::ImageRgb8(image))
+    }
+}
+
+impl PanoramaProvider for BingStreetside {
+    fn name(&self) -> &'static str {
+        "bing"
+    }
+
+    fn search(&self, lat: f64, lon: f64) -> BoxFuture<'_, Result<Vec<Panorama>>> {
+        Box::pin(self.search_panoramas(lat, lon))
+    }
+
+    fn download<'a>(&'a self, pano_id: &'a str, zoom: u8) -> BoxFuture<'a, Result<DynamicImage>> {
+        Box::pin(self.download_panorama(pano_id, zoom))
+    }
+}
+
+/// Parse a bubble metadata response: a JSON array whose first element is
+/// request information and the rest are panoramas.
+fn parse_bubbles(body: &[u8]) -> Result<Vec<Panorama>> {
+    let bubbles: Vec<Value> = serde_json::from_slice(body)
+        .map_err(|e| StreetViewError::ParseError(format!("JSON parse error: {e}")))?;
+    Ok(bubbles.iter().filter_map(parse_bubble).collect())
+}
+
+fn parse_bubble(bubble: &Value) -> Option<Panorama> {
+    let date = bubble["cd"].as_str().and_then(parse_capture_date);
+    Some(Panorama {
+        pano_id: bubble["id"].as_u64()?.to_string(),
+        lat: bubble["la"].as_f64()?,
+        lon: bubble["lo"].as_f64()?,
+        heading: bubble["he"].as_f64().unwrap_or(0.0),
+        pitch: bubble["pi"].as_f64(),
+        roll: bubble["ro"].as_f64(),
+        date,
+        date_confidence: DateConfidence::of(date),
+        elevation: bubble["al"].as_f64(),
+        copyright: None,
+        street_name: None,
+    })
+}
+
+/// Parse Bing's `"M/D/YYYY h:mm:ss AM"` capture timestamps.
+fn parse_capture_date(text: &str) -> Option<CaptureDate> {
+    let date = text.split_whitespace().next()?;
+    let mut parts = date.split('/');
+    let month = parts.next()?.parse().ok()?;
+    let year = parts.nth(1)?.parse().ok()?;
+    CaptureDate::new(year, month)
+}
+
+/// Tile URL: the bubble ID in base 4, zero-padded to 16 digits, then the
+/// face number and the tile's quadkey within the face.
+fn tile_url(id: u64, face: u8, quadkey: &str) -> String {
+    let mut base4 = [b'0'; 16];
+    let mut rest = id;
+    for digit in base4.iter_mut().rev() {
+        *digit = b'0' + (rest % 4) as u8;
+        rest /= 4;
+    }
+    let base4 = String::from_utf8_lossy(&base4);
+    format!("{TILE_ENDPOINT}/hs{base4}{face:02}{quadkey}.jpg?g=14042&n=z")
+}
+
+/// Every quadkey of `level` digits with its tile column and row.
+///
+/// Each digit picks a quadrant: 0 top-left, 1 top-right, 2 bottom-left,
+/// 3 bottom-right.
+fn quadkeys(level: u8) -> Vec<(String, u32, u32)> {
+    let side = 1u32 << level;
+    let mut keys = Vec::with_capacity((side * side) as usize);
+    for y in 0..side {
+        for x in 0..side {
+            let key = (0..level)
+                .rev()
+                .map(|bit| char::from(b'0' + (((x >> bit) & 1) + 2 * ((y >> bit) & 1)) as u8))
+                .collect();
+            keys.push((key, x, y));
+        }
+    }
+    keys
+}
+
+/// Which face a direction hits, and where on it in `[0, 1]` texture
+/// coordinates. Directions are `x` right, `y` forward, `z` up, relative to
+/// the front face.
+fn cube_lookup(x: f64, y: f64, z: f64) -> (usize, f64, f64) {
+    let (ax, ay, az) = (x.abs(), y.abs(), z.abs());
+    let (face, s, t) = if ay >= ax && ay >= az {
+        if y > 0.0 {
+            (0, x / ay, -z / ay)
+        } else {
+            (2, -x / ay, -z / ay)
+        }
+    } else if ax >= az {
+        if x > 0.0 {
+            (1, -y / ax, -z / ax)
+        } else {
+            (3, y / ax, -z / ax)
+        }
+    } else if z > 0.0 {
+        (4, x / az, y / az)
+    } else {
+        (5, x / az, -y / az)
+    };
+    (face, (s + 1.0) / 2.0, (t + 1.0) / 2.0)
+}
+
+/// Reproject cube faces (front, right, back, left, top, bottom) to an
+/// equirectangular image four faces wide, centered on the front face.
+fn cubemap_to_equirectangular(faces: &[RgbImage; 6]) -> RgbImage {
+    let size = faces[0].width();
+    let (width, height) = (size * 4, size * 2);
+
+    RgbImage::from_fn(width, height, |u, v| {
+        let lon = (f64::from(u) + 0.5) / f64::from(width) * 2.0 * PI - PI;
+        let lat = PI / 2.0 - (f64::from(v) + 0.5) / f64::from(height) * PI;
+        let (face, s, t) = cube_lookup(lat.cos() * lon.sin(), lat.cos() * lon.cos(), lat.sin());
+        image::imageops::sample_bilinear(&faces[face], s as f32, t as f32).unwrap_or(Rgb([0, 0, 0]))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bubbles() {
+        let body = br#"[
+            {"elapsed": 12},
+            {"id": 12345, "la": 47.62, "lo": -122.35, "he": 91.5, "pi": -1.0, "ro": 0.5,
+             "al": 40.0, "cd": "4/22/2014 12:00:00 AM"},
+            {"id": 678, "la": 47.63, "lo": -122.34}
+        ]"#;
+        let panos = parse_bubbles(body).unwrap();
+        assert_eq!(panos.len(), 2);
+        assert_eq!(panos[0].pano_id, "12345");
+        assert_eq!((panos[0].lat, panos[0].lon, panos[0].heading), (47.62, -122.35, 91.5));
+        assert_eq!(panos[0].date, CaptureDate::new(2014, 4));
+        assert_eq!(panos[0].elevation, Some(40.0));
+        assert_eq!((panos[1].heading, panos[1].date), (0.0, None));
+    }
+
+    #[test]
+    fn test_tile_url_and_quadkeys() {
+        assert_eq!(
+            tile_url(12345, 11, "03"),
+            "https://t.ssl.ak.tiles.virtualearth.net/tiles/hs00000000030003211103.jpg?g=14042&n=z"
+        );
+
+        let keys = quadkeys(2);
+        assert_eq!(keys.len(), 16);
+        assert_eq!(keys[0], ("00".to_string(), 0, 0));
+        assert_eq!(keys[1], ("01".to_string(), 1, 0));
+        assert_eq!(keys[4], ("02".to_string(), 0, 1));
+        assert_eq!(keys[15], ("33".to_string(), 3, 3));
+    }
+
+    #[test]
+    fn test_cubemap_to_equirectangular() {
+        let colors = [[255, 0, 0], [0, 255, 0], [0, 0, 255], [255, 255, 0], [255, 255, 255], [0, 0, 0]];
+        let faces = colors.map(|color| RgbImage::from_pixel(8, 8, Rgb(color)));
+        let pano = cubemap_to_equirectangular(&faces);
+        assert_eq!(pano.dimensions(), (32, 16));
+
+        let at = |u: u32, v: u32| pano.get_pixel(u, v).0;
+        assert_eq!(at(16, 8), colors[0]); // front at the center
+        assert_eq!(at(24, 8), colors[1]); // right
+        assert_eq!(at(0, 8), colors[2]); // back at the seam
+        assert_eq!(at(8, 8), colors[3]); // left
+        assert_eq!(at(16, 0), colors[4]); // top
+        assert_eq!(at(16, 15), colors[5]); // bottom
+    }
+}
@@ -0,0 +1,411 @@
+use crate::cache::{Cache, PanoramaCache};
+use crate::capture::RawCapture;
+use crate::endpoints::Endpoints;
+use crate::error::Result;
+use crate::http::Http;
+#[cfg(feature = "metrics")]
+use crate::metrics::{ConnectionObserver, Metrics};
+use crate::policy::Policy;
+use crate::transport::{RetryPolicy, Timeouts};
+use crate::proxy::ProxyPool;
+use crate::quota::QuotaTracker;
+use crate::signing::UrlSigner;
+#[cfg(any(test, feature = "testing"))]
+use crate::testing::MockTransport;
+use crate::types::{Locale, RequestHeaders};
+use crate::StreetView;
+use reqwest::{Client, ClientBuilder};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Connection tuning applied to the reqwest client the builder creates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConnectionPreset {
+    HighThroughput,
+    LowFootprint,
+}
+
+impl ConnectionPreset {
+    fn configure(self, builder: ClientBuilder) -> ClientBuilder {
+        let builder = builder.connect_timeout(Duration::from_secs(10));
+        match self {
+            ConnectionPreset::HighThroughput => builder
+                .pool_max_idle_per_host(64)
+                .pool_idle_timeout(Duration::from_secs(90))
+                .tcp_nodelay(true)
+                .tcp_keepalive(Duration::from_secs(60))
+                .http2_adaptive_window(true)
+                .http2_keep_alive_interval(Duration::from_secs(30))
+                .http2_keep_alive_while_idle(true),
+            ConnectionPreset::LowFootprint => builder
+                .pool_max_idle_per_host(2)
+                .pool_idle_timeout(Duration::from_secs(10))
+                .http2_initial_stream_window_size(64 * 1024)
+                .http2_initial_connection_window_size(256 * 1024),
+        }
+    }
+}
+
+/// Builder for a [`StreetView`] client.
+///
+/// Every setting is optional; `StreetView::builder().build()` is the same
+/// as `StreetView::new()`.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rsstreetview::{MemoryCache, RetryPolicy, StreetView};
+/// # use std::time::Duration;
+/// # fn main() -> rsstreetview::Result<()> {
+/// let client = StreetView::builder()
+///     .api_key("YOUR_API_KEY")
+///     .signing_secret("YOUR_SIGNING_SECRET")
+///     .client(reqwest::Client::builder().timeout(Duration::from_secs(60)).build()?)
+///     .cache(MemoryCache::new(4096))
+///     .max_qps(5.0)
+///     .retry(RetryPolicy::new().max_retries(3).backoff(2.0))
+///     .locale("ja", "JP")
+///     .user_agents(["Mozilla/5.0 (X11; Linux x86_64)", "Mozilla/5.0 (Macintosh)"])
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct StreetViewBuilder {
+    api_key: Option<String>,
+    signing_secret: Option<String>,
+    client: Option<Client>,
+    preset: Option<ConnectionPreset>,
+    headers: RequestHeaders,
+    policy: Policy,
+    retry: RetryPolicy,
+    timeouts: Timeouts,
+    locale: Option<Locale>,
+    cache: Option<Arc<dyn Cache>>,
+    panoramas: Option<Arc<PanoramaCache>>,
+    offline: bool,
+    quota: Option<Arc<QuotaTracker>>,
+    proxies: Option<Arc<ProxyPool>>,
+    endpoints: Endpoints,
+    capture_dir: Option<PathBuf>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<Arc<dyn Metrics>>,
+    #[cfg(any(test, feature = "testing"))]
+    mock: Option<MockTransport>,
+}
+
+impl StreetViewBuilder {
+    /// Create a builder with default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the Google Maps API key used by the official API functions.
+    pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Sign official API requests with a URL signing secret.
+    ///
+    /// `secret` is the URL-safe base64 secret from the Google Cloud console;
+    /// Google requires signatures for Static API usage beyond the unsigned
+    /// quota.
+    pub fn signing_secret(mut self, secret: impl Into<String>) -> Self {
+        self.signing_secret = Some(secret.into());
+        self
+    }
+
+    /// Use a custom reqwest client (proxies, timeouts, TLS settings).
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Tune connections for heavy tile workloads.
+    ///
+    /// Keeps up to 64 idle connections per host alive for 90 seconds, sends
+    /// TCP and HTTP/2 keepalives, disables Nagle's algorithm, and lets
+    /// HTTP/2 flow-control windows grow with the connection's bandwidth.
+    /// Google's servers negotiate HTTP/2, so concurrent tile requests share
+    /// a few multiplexed connections. Dual-stack hosts race IPv6 and IPv4
+    /// connections (happy eyeballs) as with every reqwest client.
+    ///
+    /// Has no effect if a custom [`client`](Self::client) is set.
+    pub fn high_throughput(mut self) -> Self {
+        self.preset = Some(ConnectionPreset::HighThroughput);
+        self
+    }
+
+    /// Tune connections for small footprints: occasional requests from
+    /// constrained hosts, serverless functions, or many clients per process.
+    ///
+    /// Keeps at most 2 idle connections per host for 10 seconds and uses
+    /// small HTTP/2 flow-control windows, bounding per-connection buffers.
+    ///
+    /// Has no effect if a custom [`client`](Self::client) is set.
+    pub fn low_footprint(mut self) -> Self {
+        self.preset = Some(ConnectionPreset::LowFootprint);
+        self
+    }
+
+    /// Cache search results, metadata, geocoding results, and tiles.
+    pub fn cache(mut self, cache: impl Cache + 'static) -> Self {
+        self.cache = Some(Arc::new(cache));
+        self
+    }
+
+    /// Keep decoded panoramas in memory. See [`StreetView::with_panorama_cache`].
+    pub fn panorama_cache(mut self, cache: PanoramaCache) -> Self {
+        self.panoramas = Some(Arc::new(cache));
+        self
+    }
+
+    /// Serve only from the [`cache`](Self::cache), never the network.
+    ///
+    /// Requests that miss the cache fail with
+    /// `StreetViewError::OfflineCacheMiss`, so test suites and air-gapped
+    /// batch jobs can re-run against a previously warmed cache and see
+    /// exactly what is missing. Static API images are never cached, so
+    /// `get_streetview` always fails offline.
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Apply a usage policy. See [`StreetView::with_policy`].
+    pub fn policy(mut self, policy: Policy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Limit requests per second across all endpoints.
+    pub fn max_qps(mut self, qps: f64) -> Self {
+        self.policy = self.policy.max_qps(qps);
+        self
+    }
+
+    /// Set how failed tile downloads are retried.
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Limit how long each search, tile, metadata, and image request may
+    /// take. See [`Timeouts`].
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Request results in a language and region (e.g. `"ja"`, `"JP"`).
+    ///
+    /// Applies to search, photometa, and geocoding. Defaults to English/US
+    /// for the undocumented endpoints and Google's default for geocoding.
+    pub fn locale(mut self, language: impl Into<String>, region: impl Into<String>) -> Self {
+        self.locale = Some(Locale::new(language, region));
+        self
+    }
+
+    /// Send requests to custom endpoints (mirrors, proxies, mock servers).
+    pub fn endpoints(mut self, endpoints: Endpoints) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
+    /// Track billable requests. See [`StreetView::with_quota`].
+    pub fn quota(mut self, tracker: QuotaTracker) -> Self {
+        self.quota = Some(Arc::new(tracker));
+        self
+    }
+
+    /// Spread requests across proxies. See [`StreetView::with_proxy_pool`].
+    pub fn proxy_pool(mut self, pool: ProxyPool) -> Self {
+        self.proxies = Some(Arc::new(pool));
+        self
+    }
+
+    /// Replace the header configuration for undocumented endpoints.
+    pub fn headers(mut self, headers: RequestHeaders) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    /// Send a single user-agent to undocumented endpoints.
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.headers = self.headers.user_agent(user_agent);
+        self
+    }
+
+    /// Rotate through a pool of user-agents, one per request.
+    pub fn user_agents<I, S>(mut self, user_agents: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.headers = self.headers.user_agents(user_agents);
+        self
+    }
+
+    /// Set the `Accept-Language` header sent to undocumented endpoints.
+    pub fn accept_language(mut self, language: impl Into<String>) -> Self {
+        self.headers = self.headers.accept_language(language);
+        self
+    }
+
+    /// Add an extra header sent to undocumented endpoints.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers = self.headers.header(name, value);
+        self
+    }
+
+    /// Write search, photometa, metadata, and geocoding responses that fail
+    /// to parse or carry an error status to `dir`, for debugging changes in
+    /// Google's formats.
+    ///
+    /// Each response is saved as a `.body` file with the raw bytes and a
+    /// `.json` file with the request URL, status, headers, and error. API
+    /// keys and signatures are redacted from the URL; the body is written
+    /// as received. The directory is created on first use.
+    pub fn capture_raw_responses(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.capture_dir = Some(dir.into());
+        self
+    }
+
+    /// Report requests, cache hits, retries, and new connections to a
+    /// metrics hook.
+    ///
+    /// Requires the `metrics` feature. Pass an `Arc` and keep a clone to
+    /// read a [`MetricsRecorder`](crate::metrics::MetricsRecorder) later.
+    /// New connections are only seen if the builder creates the HTTP
+    /// client, i.e. without a custom [`client`](Self::client).
+    #[cfg(feature = "metrics")]
+    pub fn metrics(mut self, metrics: Arc<dyn Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Answer requests from a [`MockTransport`] instead of the network.
+    ///
+    /// Requires the `testing` feature. Ignored for requests sent through a
+    /// proxy pool.
+    #[cfg(any(test, feature = "testing"))]
+    pub fn mock_transport(mut self, mock: MockTransport) -> Self {
+        self.mock = Some(mock);
+        self
+    }
+
+    /// Build the client.
+    ///
+    /// # Errors
+    ///
+    /// Returns `StreetViewError::ParseError` if the signing secret is not
+    /// valid URL-safe base64, an endpoint is not a valid URL, or a header
+    /// name or value is not valid in HTTP, and `StreetViewError::HttpError`
+    /// if the HTTP client cannot be created.
+    pub fn build(self) -> Result<StreetView> {
+        self.endpoints.validate()?;
+        let client = match self.client {
+            Some(client) => client,
+            None => {
+                let builder = match self.preset {
+                    Some(preset) => preset.configure(Client::builder()),
+                    None => Client::builder(),
+                };
+                #[cfg(feature = "metrics")]
+                let builder = match &self.metrics {
+                    Some(metrics) => builder.connector_layer(ConnectionObserver::new(metrics.clone())),
+                    None => builder,
+                };
+                builder.build()?
+            }
+        };
+        let mut http = Http::new(client);
+        http.set_endpoints(self.endpoints);
+        http.set_headers(&self.headers)?;
+        http.set_signer(self.signing_secret.as_deref().map(UrlSigner::new).transpose()?);
+        http.set_max_qps(self.policy.max_qps);
+        http.set_retry(self.retry);
+        http.set_timeouts(self.timeouts);
+        http.set_locale(self.locale);
+        http.set_cache(self.cache);
+        http.set_panorama_cache(self.panoramas);
+        http.set_offline(self.offline);
+        http.set_quota(self.quota);
+        http.set_proxy_pool(self.proxies);
+        http.set_capture(self.capture_dir.map(RawCapture::new));
+        #[cfg(feature = "metrics")]
+        http.set_metrics(self.metrics);
+        #[cfg(any(test, feature = "testing"))]
+        if let Some(mock) = self.mock {
+            http.set_transport(Arc::new(mock));
+        }
+
+        Ok(StreetView {
+            http,
+            api_key: self.api_key,
+            policy: self.policy,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::StreetViewError;
+
+    #[test]
+    fn test_build() {
+        let client = StreetViewBuilder::new().api_key("key").accept_language("en").build().unwrap();
+        assert_eq!(client.api_key.as_deref(), Some("key"));
+
+        let client = StreetViewBuilder::new().max_qps(2.0).locale("ja", "JP").build().unwrap();
+        assert_eq!(client.policy().max_qps, Some(2.0));
+        assert_eq!(client.http.locale(), Some(&Locale::new("ja", "JP")));
+
+        let invalid = StreetViewBuilder::new().signing_secret("not base64!").build();
+        assert!(matches!(invalid, Err(StreetViewError::ParseError(_))));
+        let invalid = StreetViewBuilder::new().endpoints(Endpoints::new().metadata("nope")).build();
+        assert!(matches!(invalid, Err(StreetViewError::ParseError(_))));
+        let invalid = StreetViewBuilder::new().header("bad name", "value").build();
+        assert!(matches!(invalid, Err(StreetViewError::ParseError(_))));
+        let invalid = StreetViewBuilder::new().user_agent("line\nbreak").build();
+        assert!(matches!(invalid, Err(StreetViewError::ParseError(_))));
+    }
+
+    #[test]
+    fn test_build_urls() {
+        let client = StreetViewBuilder::new().api_key("key").build().unwrap();
+        let url = client.build_metadata_url("PANO000000000000000000").unwrap();
+        assert!(url.ends_with("/metadata?pano=PANO000000000000000000&key=key"));
+        let url = client.build_streetview_url("PANO000000000000000000", 640, 400, 90, 80, -5).unwrap();
+        assert!(url.ends_with("?size=640x400&fov=80&pitch=-5&heading=90&pano=PANO000000000000000000&key=key"));
+        assert!(matches!(client.build_metadata_url("bad id"), Err(StreetViewError::InvalidPanoId(_))));
+
+        let signed = StreetViewBuilder::new()
+            .api_key("key")
+            .signing_secret("vNIXE0xscrmjlyV-12Nj_BvUPaw=")
+            .build()
+            .unwrap();
+        let url = signed.build_streetview_url("PANO000000000000000000", 640, 400, 90, 80, -5).unwrap();
+        assert!(url.contains("&key=key&signature="));
+
+        let no_key = StreetViewBuilder::new().build().unwrap();
+        assert!(matches!(no_key.build_metadata_url("PANO000000000000000000"), Err(StreetViewError::MissingApiKey)));
+    }
+
+    #[test]
+    fn test_connection_presets() {
+        let builder = StreetViewBuilder::new().low_footprint().high_throughput();
+        assert_eq!(builder.preset, Some(ConnectionPreset::HighThroughput));
+        assert!(builder.build().is_ok());
+        assert!(StreetViewBuilder::new().low_footprint().build().is_ok());
+
+        #[cfg(feature = "metrics")]
+        {
+            let recorder = Arc::new(crate::metrics::MetricsRecorder::new());
+            assert!(StreetViewBuilder::new().high_throughput().metrics(recorder).build().is_ok());
+        }
+    }
+}
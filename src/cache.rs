@@ -0,0 +1,540 @@
+//! Response caching.
+//!
+//! A [`Cache`] attached with [`StreetView::with_cache`](crate::StreetView::with_cache)
+//! stores raw response bodies keyed by request URL, so repeated searches,
+//! metadata lookups, and tile downloads are served locally. Signed URLs are
+//! keyed before signing. Only successful responses that parse are stored.
+//!
+//! Street View Static API images are not cached: they are billed per
+//! request and the caller may need the response headers.
+//!
+//! A [`DiskCache`] can be shared by several worker processes scraping the
+//! same region: a process about to download an entry claims it with a file
+//! lock, and the others wait for that download instead of repeating it.
+//!
+//! A [`PanoramaCache`] sits above the response cache and keeps assembled,
+//! decoded panoramas, so extracting another view of a recent panorama
+//! skips both the download and the decoding.
+
+use image::DynamicImage;
+use sha1::{Digest, Sha1};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File, TryLockError};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// Storage for response bodies, keyed by URL.
+///
+/// Implementations must be safe to share between concurrent requests.
+/// Methods are synchronous and best effort: a failed write should be
+/// ignored rather than failing the request.
+pub trait Cache: Send + Sync {
+    /// Look up a stored response body.
+    fn get(&self, key: &str) -> Option<Vec<u8>>;
+
+    /// Store a response body.
+    fn put(&self, key: &str, value: &[u8]);
+
+    /// Claim `key` before downloading it, so other processes sharing the
+    /// cache wait for this download instead of repeating it.
+    ///
+    /// Returns `None` if the cache doesn't coordinate downloads (the
+    /// default). The client checks the cache again while an entry is
+    /// [`Claim::Busy`], and downloads it anyway if the claim isn't released
+    /// within a minute.
+    fn claim(&self, key: &str) -> Option<Claim> {
+        let _ = key;
+        None
+    }
+}
+
+/// Result of [`Cache::claim`].
+pub enum Claim {
+    /// The caller should download the entry. The claim is released when
+    /// the guard is dropped, after the entry is stored.
+    Owned(Box<dyn Send>),
+    /// Someone else is downloading the entry.
+    Busy,
+}
+
+impl std::fmt::Debug for Claim {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Claim::Owned(_) => f.write_str("Owned"),
+            Claim::Busy => f.write_str("Busy"),
+        }
+    }
+}
+
+/// In-memory cache that evicts the least recently used entry when full.
+#[derive(Debug)]
+pub struct MemoryCache {
+    capacity: usize,
+    inner: Mutex<Lru<Vec<u8>>>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Lru<V> {
+    entries: HashMap<String, (V, u64)>,
+    order: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+impl<V> Default for Lru<V> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+}
+
+impl<V> Lru<V> {
+    /// Mark `key` as most recently used.
+    fn touch(&mut self, key: &str) {
+        self.tick += 1;
+        if let Some((_, used)) = self.entries.get_mut(key) {
+            self.order.remove(used);
+            *used = self.tick;
+            self.order.insert(self.tick, key.to_string());
+        }
+    }
+
+    /// Remove the least recently used entry.
+    fn pop_oldest(&mut self) -> Option<V> {
+        let (_, oldest) = self.order.pop_first()?;
+        self.entries.remove(&oldest).map(|(value, _)| value)
+    }
+
+    /// Insert or replace `key` as the most recently used entry, evicting
+    /// the least recently used ones to stay within `capacity`. Returns
+    /// whether `key` was new.
+    pub(crate) fn insert(&mut self, key: &str, value: V, capacity: usize) -> bool {
+        let new = match self.entries.get_mut(key) {
+            Some((old, _)) => {
+                *old = value;
+                false
+            }
+            None => {
+                while self.entries.len() >= capacity.max(1) && self.pop_oldest().is_some() {}
+                self.entries.insert(key.to_string(), (value, 0));
+                true
+            }
+        };
+        self.touch(key);
+        new
+    }
+}
+
+impl MemoryCache {
+    /// Create a cache holding at most `capacity` responses.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::new(Lru::default()),
+        }
+    }
+
+    /// Number of cached responses.
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Lru<Vec<u8>>> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Cache for MemoryCache {
+    fn get(&self, key: &str) -> Option<Vec<u8>> {
+        let mut lru = self.lock();
+        let value = lru.entries.get(key)?.0.clone();
+        lru.touch(key);
+        Some(value)
+    }
+
+    fn put(&self, key: &str, value: &[u8]) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.lock().insert(key, value.to_vec(), self.capacity);
+    }
+}
+
+/// In-memory cache of decoded panoramas, bounded by their total size.
+///
+/// Attach one with [`StreetView::with_panorama_cache`](crate::StreetView::with_panorama_cache)
+/// and every method that downloads a whole panorama (`download_panorama`,
+/// `extract_view`, `extract_multiple_views`, `extract_views_batch`) reuses
+/// panoramas already in memory, keyed by panorama ID and zoom level. The
+/// least recently used panoramas are evicted once the decoded pixels
+/// exceed the size limit; a panorama larger than the whole limit is not
+/// kept at all.
+///
+/// A zoom 5 panorama takes 384 MiB decoded, a zoom 3 one 24 MiB.
+#[derive(Debug)]
+pub struct PanoramaCache {
+    max_bytes: usize,
+    inner: Mutex<PanoramaLru>,
+}
+
+#[derive(Debug, Default)]
+struct PanoramaLru {
+    lru: Lru<Arc<DynamicImage>>,
+    bytes: usize,
+}
+
+impl PanoramaCache {
+    /// Create a cache holding at most `max_bytes` of decoded pixels.
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            inner: Mutex::new(PanoramaLru::default()),
+        }
+    }
+
+    /// Look up a panorama, marking it as recently used.
+    pub fn get(&self, pano_id: &str, zoom: u8) -> Option<Arc<DynamicImage>> {
+        let key = Self::key(pano_id, zoom);
+        let mut inner = self.lock();
+        let image = inner.lru.entries.get(&key)?.0.clone();
+        inner.lru.touch(&key);
+        Some(image)
+    }
+
+    /// Store a panorama, evicting the least recently used ones to make room.
+    pub fn insert(&self, pano_id: &str, zoom: u8, image: Arc<DynamicImage>) {
+        let size = image.as_bytes().len();
+        if size > self.max_bytes {
+            return;
+        }
+        let key = Self::key(pano_id, zoom);
+        let mut inner = self.lock();
+        if let Some((old, _)) = inner.lru.entries.remove(&key) {
+            inner.bytes -= old.as_bytes().len();
+        }
+        while inner.bytes + size > self.max_bytes {
+            let Some(evicted) = inner.lru.pop_oldest() else { break };
+            inner.bytes -= evicted.as_bytes().len();
+        }
+        inner.bytes += size;
+        inner.lru.entries.insert(key.clone(), (image, 0));
+        inner.lru.touch(&key);
+    }
+
+    /// Whether a panorama is cached, without marking it as used.
+    pub fn contains(&self, pano_id: &str, zoom: u8) -> bool {
+        self.lock().lru.entries.contains_key(&Self::key(pano_id, zoom))
+    }
+
+    /// Number of cached panoramas.
+    pub fn len(&self) -> usize {
+        self.lock().lru.entries.len()
+    }
+
+    /// Whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Total decoded size of the cached panoramas, in bytes.
+    pub fn size_bytes(&self) -> usize {
+        self.lock().bytes
+    }
+
+    /// Drop every cached panorama.
+    pub fn clear(&self) {
+        *self.lock() = PanoramaLru::default();
+    }
+
+    fn key(pano_id: &str, zoom: u8) -> String {
+        format!("{pano_id}/{zoom}")
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, PanoramaLru> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// On-disk cache storing one file per response in a directory.
+///
+/// Files are named by the SHA-1 of the URL. The directory can be shared by
+/// several processes: downloads are claimed with per-entry lock files, so
+/// workers scraping overlapping areas fetch each shared tile once. Entries
+/// never expire; set [`max_bytes`](Self::max_bytes) to bound the directory
+/// size, or delete the directory to clear the cache.
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    dir: PathBuf,
+    max_bytes: Option<u64>,
+    /// Bytes stored, as last counted plus writes since (by this process)
+    stored: Arc<AtomicU64>,
+}
+
+impl DiskCache {
+    /// Create a cache in `dir`, creating the directory if needed.
+    pub fn new(dir: impl AsRef<Path>) -> std::io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            max_bytes: None,
+            stored: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    /// Limit the total size of cached responses.
+    ///
+    /// When a write takes the cache over the limit, the least recently
+    /// used entries (by file modification time, which reads refresh) are
+    /// deleted until it is back under 90% of the limit. The limit applies
+    /// to the whole directory, including entries written by other
+    /// processes.
+    pub fn max_bytes(mut self, bytes: u64) -> Self {
+        self.max_bytes = Some(bytes);
+        self.stored.store(self.size_bytes(), Ordering::Relaxed);
+        self
+    }
+
+    /// Directory holding the cached responses.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Total size of the cached responses in bytes.
+    pub fn size_bytes(&self) -> u64 {
+        self.entries().iter().map(|entry| entry.1).sum()
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        let name: String = Sha1::digest(key.as_bytes())
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        self.dir.join(name)
+    }
+
+    /// Cached responses as `(path, size, last used)`. Lock and temporary
+    /// files are skipped.
+    fn entries(&self) -> Vec<(PathBuf, u64, SystemTime)> {
+        let Ok(dir) = fs::read_dir(&self.dir) else { return Vec::new() };
+        dir.flatten()
+            .filter(|entry| entry.path().extension().is_none())
+            .filter_map(|entry| {
+                let meta = entry.metadata().ok().filter(|meta| meta.is_file())?;
+                Some((entry.path(), meta.len(), meta.modified().unwrap_or(SystemTime::UNIX_EPOCH)))
+            })
+            .collect()
+    }
+
+    /// Delete least recently used entries until under 90% of `max_bytes`.
+    fn evict(&self, max_bytes: u64) {
+        // One process evicts at a time; the others carry on writing
+        let Ok(lock) = File::create(self.dir.join(".evict.lock")) else { return };
+        if lock.try_lock().is_err() {
+            return;
+        }
+
+        let mut entries = self.entries();
+        entries.sort_by_key(|entry| entry.2);
+        let mut total: u64 = entries.iter().map(|entry| entry.1).sum();
+        let target = max_bytes / 10 * 9;
+        for (path, size, _) in entries {
+            if total <= target {
+                break;
+            }
+            if fs::remove_file(path).is_ok() {
+                total -= size;
+            }
+        }
+        self.stored.store(total, Ordering::Relaxed);
+    }
+}
+
+/// Lock file held while a [`DiskCache`] entry is downloaded.
+struct ClaimFile {
+    path: PathBuf,
+    // Closing the file releases the lock
+    _file: File,
+}
+
+impl Drop for ClaimFile {
+    fn drop(&mut self) {
+        // Removed while still locked, so a waiter that opened the old file
+        // can only lock it after the entry has been stored
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+impl Cache for DiskCache {
+    fn get(&self, key: &str) -> Option<Vec<u8>> {
+        let path = self.path(key);
+        let value = fs::read(&path).ok()?;
+        if self.max_bytes.is_some() {
+            // Mark as recently used for eviction
+            if let Ok(file) = File::options().write(true).open(&path) {
+                let _ = file.set_modified(SystemTime::now());
+            }
+        }
+        Some(value)
+    }
+
+    fn put(&self, key: &str, value: &[u8]) {
+        // Write then rename so concurrent readers never see a partial file
+        let path = self.path(key);
+        let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+        if fs::write(&tmp, value).is_ok() && fs::rename(&tmp, &path).is_err() {
+            let _ = fs::remove_file(&tmp);
+            return;
+        }
+
+        if let Some(max_bytes) = self.max_bytes {
+            let stored = self.stored.fetch_add(value.len() as u64, Ordering::Relaxed) + value.len() as u64;
+            if stored > max_bytes {
+                self.evict(max_bytes);
+            }
+        }
+    }
+
+    fn claim(&self, key: &str) -> Option<Claim> {
+        let path = self.path(key).with_extension("lock");
+        let file = File::create(&path).ok()?;
+        match file.try_lock() {
+            Ok(()) => Some(Claim::Owned(Box::new(ClaimFile { path, _file: file }))),
+            Err(TryLockError::WouldBlock) => Some(Claim::Busy),
+            Err(TryLockError::Error(_)) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_cache_lru() {
+        let cache = MemoryCache::new(2);
+        cache.put("a", b"1");
+        cache.put("b", b"2");
+        assert_eq!(cache.get("a").as_deref(), Some(&b"1"[..]));
+
+        // "b" is now least recently used
+        cache.put("c", b"3");
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("a").is_some());
+
+        cache.put("a", b"4");
+        assert_eq!(cache.get("a").as_deref(), Some(&b"4"[..]));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_lru_insert() {
+        let mut lru = Lru::default();
+        assert!(lru.insert("a", (), 2));
+        assert!(lru.insert("b", (), 2));
+        assert!(!lru.insert("a", (), 2));
+
+        // "b" is least recently used, so it is forgotten first
+        assert!(lru.insert("c", (), 2));
+        assert!(lru.insert("b", (), 2));
+        assert!(!lru.insert("c", (), 2));
+        assert_eq!(lru.entries.len(), 2);
+    }
+
+    #[test]
+    fn test_panorama_cache_lru() {
+        let pano = || Arc::new(DynamicImage::new_rgb8(10, 10));
+        let cache = PanoramaCache::new(700);
+        cache.insert("a", 1, pano());
+        cache.insert("b", 1, pano());
+        assert_eq!(cache.size_bytes(), 600);
+        assert!(cache.get("a", 1).is_some());
+        assert!(cache.get("a", 2).is_none());
+
+        // "b" is now least recently used
+        cache.insert("c", 1, pano());
+        assert_eq!(cache.len(), 2);
+        assert!(!cache.contains("b", 1));
+        assert!(cache.contains("a", 1));
+
+        // Replacing an entry doesn't count it twice
+        cache.insert("c", 1, pano());
+        assert_eq!(cache.size_bytes(), 600);
+
+        // Too large to keep
+        cache.insert("big", 1, Arc::new(DynamicImage::new_rgb8(20, 20)));
+        assert!(!cache.contains("big", 1));
+        assert_eq!(cache.len(), 2);
+
+        cache.clear();
+        assert!(cache.is_empty());
+        assert_eq!(cache.size_bytes(), 0);
+    }
+
+    #[test]
+    fn test_disk_cache() {
+        let dir = std::env::temp_dir().join("rsstreetview_disk_cache");
+        let _ = fs::remove_dir_all(&dir);
+        let cache = DiskCache::new(&dir).unwrap();
+        assert!(cache.get("https://example.com/?q=1").is_none());
+
+        cache.put("https://example.com/?q=1", b"body");
+        assert_eq!(cache.get("https://example.com/?q=1").as_deref(), Some(&b"body"[..]));
+        assert!(cache.get("https://example.com/?q=2").is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_disk_cache_claim() {
+        let dir = std::env::temp_dir().join("rsstreetview_disk_cache_claim");
+        let _ = fs::remove_dir_all(&dir);
+        // Separate instances stand in for separate processes
+        let a = DiskCache::new(&dir).unwrap();
+        let b = DiskCache::new(&dir).unwrap();
+
+        let claim = a.claim("tile");
+        assert!(matches!(claim, Some(Claim::Owned(_))));
+        assert!(matches!(b.claim("tile"), Some(Claim::Busy)));
+        assert!(matches!(b.claim("other"), Some(Claim::Owned(_))));
+
+        a.put("tile", b"body");
+        drop(claim);
+        assert!(matches!(b.claim("tile"), Some(Claim::Owned(_))));
+        assert_eq!(b.get("tile").as_deref(), Some(&b"body"[..]));
+        // Lock files are not entries
+        assert_eq!(b.size_bytes(), 4);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_disk_cache_eviction() {
+        let dir = std::env::temp_dir().join("rsstreetview_disk_cache_eviction");
+        let _ = fs::remove_dir_all(&dir);
+        let cache = DiskCache::new(&dir).unwrap().max_bytes(300);
+
+        for key in ["a", "b", "c"] {
+            cache.put(key, &[0; 100]);
+            // File timestamps are coarser than a put
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        assert!(cache.get("a").is_some());
+        std::thread::sleep(std::time::Duration::from_millis(20));
+
+        // Over the limit: "b" is least recently used, then "c"
+        cache.put("d", &[0; 100]);
+        assert!(cache.size_bytes() <= 270, "{}", cache.size_bytes());
+        assert!(cache.get("b").is_none() && cache.get("c").is_none());
+        assert!(cache.get("a").is_some() && cache.get("d").is_some());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,164 @@
+//! Raw response capture for debugging endpoint format changes.
+//!
+//! When enabled with
+//! [`StreetViewBuilder::capture_raw_responses`](crate::StreetViewBuilder::capture_raw_responses),
+//! every search, photometa, metadata, or geocoding response that fails to
+//! parse or comes back with an error status is written to the capture
+//! directory as two files sharing a name:
+//!
+//! - `{millis}-{seq}-{endpoint}.body`: the response body as received
+//! - `{millis}-{seq}-{endpoint}.json`: the request URL (with `key`,
+//!   `signature`, and `client` redacted), status, headers, error, and time
+//!
+//! Attach both to a bug report when Google changes a format.
+
+use crate::error::StreetViewError;
+use crate::transport::HttpResponse;
+use serde_json::json;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Query parameters whose values are credentials.
+const SECRET_PARAMS: [&str; 3] = ["key", "signature", "client"];
+
+/// Internal: writes failing responses to a directory.
+#[derive(Debug)]
+pub(crate) struct RawCapture {
+    dir: PathBuf,
+    next: AtomicU64,
+}
+
+impl RawCapture {
+    pub(crate) fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            next: AtomicU64::new(0),
+        }
+    }
+
+    /// Write `response` to `url` and the `error` it caused.
+    ///
+    /// Capturing is a debugging aid, so failures to write are logged (with
+    /// the `tracing` feature) rather than returned.
+    pub(crate) fn record(&self, url: &str, response: &HttpResponse, error: &StreetViewError) {
+        if let Err(_e) = self.write(url, response, error) {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(error = %_e, dir = %self.dir.display(), "failed to capture raw response");
+        }
+    }
+
+    fn write(&self, url: &str, response: &HttpResponse, error: &StreetViewError) -> std::io::Result<()> {
+        let millis = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis());
+        let seq = self.next.fetch_add(1, Ordering::Relaxed);
+        let stem = format!("{millis}-{seq}-{}", endpoint_name(url));
+
+        let headers: serde_json::Map<String, serde_json::Value> = response
+            .headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), String::from_utf8_lossy(value.as_bytes()).into()))
+            .collect();
+        let context = json!({
+            "url": redact(url),
+            "status": response.status.as_u16(),
+            "headers": headers,
+            "error": error.to_string(),
+            "captured_at_ms": millis as u64,
+            "body_file": format!("{stem}.body"),
+        });
+
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(self.dir.join(format!("{stem}.body")), &response.body)?;
+        let context = serde_json::to_vec_pretty(&context).map_err(std::io::Error::other)?;
+        std::fs::write(self.dir.join(format!("{stem}.json")), context)
+    }
+}
+
+/// `url` with credential parameters replaced by `REDACTED`.
+fn redact(url: &str) -> String {
+    let Ok(mut parsed) = reqwest::Url::parse(url) else {
+        return url.split('?').next().unwrap_or(url).to_string();
+    };
+    let pairs: Vec<(String, String)> = parsed
+        .query_pairs()
+        .map(|(name, value)| {
+            let value = match SECRET_PARAMS.contains(&name.as_ref()) {
+                true => "REDACTED".into(),
+                false => value.into_owned(),
+            };
+            (name.into_owned(), value)
+        })
+        .collect();
+    if !pairs.is_empty() {
+        parsed.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    parsed.to_string()
+}
+
+/// Last two path segments of `url` (e.g. `streetview-metadata`), reduced
+/// to characters safe in file names.
+fn endpoint_name(url: &str) -> String {
+    let path = url.split('?').next().unwrap_or(url).trim_end_matches('/');
+    let mut segments: Vec<&str> = path.rsplit('/').take(2).collect();
+    segments.reverse();
+    let name: String = segments
+        .join("-")
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        .collect();
+    match name.trim_matches('.').is_empty() {
+        true => "response".to_string(),
+        false => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+    use reqwest::{StatusCode, Version};
+
+    #[test]
+    fn test_redact() {
+        assert_eq!(
+            redact("https://maps.googleapis.com/maps/api/streetview/metadata?pano=abc&key=SECRET&signature=sig"),
+            "https://maps.googleapis.com/maps/api/streetview/metadata?pano=abc&key=REDACTED&signature=REDACTED"
+        );
+        assert_eq!(redact("not a url?key=SECRET"), "not a url");
+        assert_eq!(
+            endpoint_name("https://maps.googleapis.com/maps/api/js/GeoPhotoService.SingleImageSearch?pb=!1m5"),
+            "js-GeoPhotoService.SingleImageSearch"
+        );
+        assert_eq!(endpoint_name("https://maps.googleapis.com/maps/api/geocode/json?key=k"), "geocode-json");
+    }
+
+    #[test]
+    fn test_record() {
+        let dir = std::env::temp_dir().join("rsstreetview_capture");
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/javascript"));
+        let response = HttpResponse {
+            status: StatusCode::OK,
+            version: Version::HTTP_11,
+            headers,
+            body: b"callbackfunc([1, 2])".to_vec(),
+        };
+
+        let capture = RawCapture::new(&dir);
+        let error = StreetViewError::ParseError("nothing at [1][5]".to_string());
+        capture.record("https://example.com/api/search?key=SECRET", &response, &error);
+
+        let mut files: Vec<PathBuf> = std::fs::read_dir(&dir).unwrap().map(|e| e.unwrap().path()).collect();
+        files.sort();
+        assert_eq!(files.len(), 2);
+        assert!(files[0].to_string_lossy().ends_with("-0-api-search.body"));
+        assert_eq!(std::fs::read(&files[0]).unwrap(), response.body);
+        let context: serde_json::Value = serde_json::from_slice(&std::fs::read(&files[1]).unwrap()).unwrap();
+        assert_eq!(context["url"], "https://example.com/api/search?key=REDACTED");
+        assert_eq!(context["status"], 200);
+        assert_eq!(context["headers"]["content-type"], "text/javascript");
+        assert_eq!(context["error"], "Failed to parse response: nothing at [1][5]");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
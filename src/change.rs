@@ -0,0 +1,314 @@
+//! Change detection between two captures of the same place.
+//!
+//! [`compare_panoramas`] aligns two equirectangular panoramas, typically
+//! from different dates at the same location, and scores how much each
+//! region of the scene differs. Captures are rarely taken facing the same
+//! way, so the heading offset between them is either given or estimated by
+//! circular cross-correlation before comparing. Brightness and contrast are
+//! normalized first, so a sunny and an overcast capture of an unchanged
+//! street still score low.
+
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, GrayImage, Luma};
+
+/// Options for [`compare_panoramas`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct CompareOptions {
+    /// Columns of the change grid, spanning 360° (default 16)
+    pub cols: u32,
+    /// Rows of the change grid, spanning 180° (default 8)
+    pub rows: u32,
+    /// Heading of the second panorama minus the first, in degrees, if known
+    /// (default: estimated from the images)
+    pub heading_offset: Option<f64>,
+    /// Width both panoramas are reduced to before comparing (default 512)
+    pub work_width: u32,
+}
+
+impl CompareOptions {
+    /// Defaults: a 16×8 grid (22.5° cells), estimated heading offset, and
+    /// comparison at 512×256.
+    pub fn new() -> Self {
+        Self {
+            cols: 16,
+            rows: 8,
+            heading_offset: None,
+            work_width: 512,
+        }
+    }
+
+    /// Set the size of the change grid.
+    pub fn grid(mut self, cols: u32, rows: u32) -> Self {
+        self.cols = cols.max(1);
+        self.rows = rows.max(1);
+        self
+    }
+
+    /// Use a known heading offset instead of estimating one.
+    ///
+    /// With search results, this is `b.heading - a.heading`.
+    pub fn heading_offset(mut self, degrees: f64) -> Self {
+        self.heading_offset = Some(degrees);
+        self
+    }
+
+    /// Set the width both panoramas are reduced to before comparing.
+    pub fn work_width(mut self, width: u32) -> Self {
+        self.work_width = width.max(16);
+        self
+    }
+}
+
+impl Default for CompareOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Per-region difference scores between two panoramas.
+///
+/// Cells are laid out in the first panorama's frame: column 0 starts at its
+/// left edge, row 0 at the zenith.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChangeMap {
+    /// Grid columns
+    pub cols: u32,
+    /// Grid rows
+    pub rows: u32,
+    /// Scores in [0, 1], row by row: 0 is identical, unrelated imagery
+    /// scores around 0.5
+    pub scores: Vec<f32>,
+    /// Heading of the second panorama minus the first, in degrees
+    /// (-180, 180], as given or estimated
+    pub heading_offset: f64,
+}
+
+/// A grid cell whose score passed a threshold.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChangedRegion {
+    /// Grid column
+    pub col: u32,
+    /// Grid row
+    pub row: u32,
+    /// Difference score
+    pub score: f32,
+    /// Horizontal center of the cell, as a fraction of the first
+    /// panorama's width
+    pub u: f64,
+    /// Vertical center of the cell, as a fraction of its height
+    pub v: f64,
+}
+
+impl ChangeMap {
+    /// Score of one cell.
+    pub fn score(&self, col: u32, row: u32) -> f32 {
+        self.scores[(row * self.cols + col) as usize]
+    }
+
+    /// Highest score in the map.
+    pub fn max_score(&self) -> f32 {
+        self.scores.iter().copied().fold(0.0, f32::max)
+    }
+
+    /// Mean score over the map.
+    pub fn mean_score(&self) -> f32 {
+        self.scores.iter().sum::<f32>() / self.scores.len() as f32
+    }
+
+    /// Cells scoring at least `threshold`, highest first.
+    ///
+    /// To turn a region into a compass bearing, use
+    /// `heading_a + (region.u - 0.5) * 360.0`.
+    pub fn changed_regions(&self, threshold: f32) -> Vec<ChangedRegion> {
+        let mut regions: Vec<ChangedRegion> = (0..self.rows)
+            .flat_map(|row| (0..self.cols).map(move |col| (col, row)))
+            .filter_map(|(col, row)| {
+                let score = self.score(col, row);
+                (score >= threshold).then(|| ChangedRegion {
+                    col,
+                    row,
+                    score,
+                    u: (f64::from(col) + 0.5) / f64::from(self.cols),
+                    v: (f64::from(row) + 0.5) / f64::from(self.rows),
+                })
+            })
+            .collect();
+        regions.sort_by(|a, b| b.score.total_cmp(&a.score));
+        regions
+    }
+
+    /// Render the map as a grayscale heatmap (white = changed), e.g. to
+    /// blend over the first panorama.
+    pub fn to_image(&self, width: u32, height: u32) -> GrayImage {
+        let cells = GrayImage::from_fn(self.cols, self.rows, |col, row| {
+            Luma([(self.score(col, row).clamp(0.0, 1.0) * 255.0).round() as u8])
+        });
+        imageops::resize(&cells, width.max(1), height.max(1), FilterType::Nearest)
+    }
+}
+
+/// Compare two panoramas of the same place and score changes per region.
+///
+/// The panoramas may differ in resolution. Both are reduced to
+/// `options.work_width`, converted to grayscale, and normalized for
+/// exposure. The second is rotated by the heading offset, and each grid
+/// cell's score is the mean absolute difference, halved and clamped to
+/// [0, 1].
+///
+/// # Example
+///
+/// ```no_run
+/// # use rsstreetview::StreetView;
+/// # use rsstreetview::change::{compare_panoramas, CompareOptions};
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = StreetView::new();
+/// let panos = client.search_panoramas(41.8982208, 12.4764804).await?;
+/// let (old, new) = (&panos[0], &panos[1]);
+/// let a = client.download_panorama(&old.pano_id, 3).await?;
+/// let b = client.download_panorama(&new.pano_id, 3).await?;
+///
+/// let changes = compare_panoramas(&a, &b, &CompareOptions::new().heading_offset(new.heading - old.heading));
+/// for region in changes.changed_regions(0.3) {
+///     println!("changed toward {:.0}°", old.heading + (region.u - 0.5) * 360.0);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn compare_panoramas(a: &DynamicImage, b: &DynamicImage, options: &CompareOptions) -> ChangeMap {
+    let width = options.work_width.max(16);
+    let height = width / 2;
+    let a = normalized(a, width, height);
+    let b = normalized(b, width, height);
+
+    // b's content sits `shift` columns to the left of the same content in a
+    let shift = match options.heading_offset {
+        Some(offset) => (offset / 360.0 * f64::from(width)).round() as i64,
+        None => estimate_shift(&a, &b, width, height),
+    }
+    .rem_euclid(i64::from(width)) as u32;
+
+    let (cols, rows) = (options.cols.max(1), options.rows.max(1));
+    let mut sums = vec![0.0f64; (cols * rows) as usize];
+    let mut counts = vec![0u32; sums.len()];
+    for y in 0..height {
+        let row = (y * rows / height).min(rows - 1);
+        for x in 0..width {
+            let col = (x * cols / width).min(cols - 1);
+            let bx = (x + width - shift) % width;
+            let diff = (a[(y * width + x) as usize] - b[(y * width + bx) as usize]).abs();
+            let cell = (row * cols + col) as usize;
+            sums[cell] += f64::from(diff);
+            counts[cell] += 1;
+        }
+    }
+
+    let scores = sums
+        .iter()
+        .zip(&counts)
+        .map(|(&sum, &count)| match count {
+            0 => 0.0,
+            n => ((sum / f64::from(n)) / 2.0).min(1.0) as f32,
+        })
+        .collect();
+
+    let offset = f64::from(shift) / f64::from(width) * 360.0;
+    ChangeMap {
+        cols,
+        rows,
+        scores,
+        heading_offset: if offset > 180.0 { offset - 360.0 } else { offset },
+    }
+}
+
+/// Grayscale pixels resized to `width`×`height`, normalized by their median
+/// and median absolute deviation so that large changes don't skew the
+/// exposure correction of the rest of the image.
+fn normalized(img: &DynamicImage, width: u32, height: u32) -> Vec<f32> {
+    let gray = imageops::resize(&img.to_luma8(), width, height, FilterType::Triangle);
+    let values: Vec<f32> = gray.pixels().map(|p| f32::from(p[0])).collect();
+    let center = median(values.clone());
+    // 1.4826 × MAD estimates the standard deviation of normal data
+    let spread = 1.4826 * median(values.iter().map(|v| (v - center).abs()).collect());
+    let spread = if spread < 1e-3 { 1.0 } else { spread };
+    values.into_iter().map(|v| (v - center) / spread).collect()
+}
+
+fn median(mut values: Vec<f32>) -> f32 {
+    values.sort_unstable_by(f32::total_cmp);
+    values.get(values.len() / 2).copied().unwrap_or(0.0)
+}
+
+/// Circular column shift of `b` that best matches `a`, by cross-correlation.
+fn estimate_shift(a: &[f32], b: &[f32], width: u32, height: u32) -> i64 {
+    // The bottom rows show the capture vehicle, which moves with the camera
+    let rows = height * 3 / 4;
+    (0..width)
+        .map(|shift| {
+            let score: f32 = (0..rows)
+                .flat_map(|y| (0..width).map(move |x| (y, x)))
+                .map(|(y, x)| a[(y * width + x) as usize] * b[(y * width + (x + width - shift) % width) as usize])
+                .sum();
+            (shift, score)
+        })
+        .max_by(|(_, x), (_, y)| x.total_cmp(y))
+        .map_or(0, |(shift, _)| i64::from(shift))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    /// An irregular street-like scene with bright and dark columns.
+    fn scene(shift: u32) -> RgbImage {
+        RgbImage::from_fn(360, 180, |x, y| {
+            let x = (x + 360 - shift) % 360;
+            let v = ((x * 7 + y / 9 * 13) % 97 + (x / 30) * 11) as u8;
+            Rgb([v, v.wrapping_mul(3), 255 - v])
+        })
+    }
+
+    #[test]
+    fn test_compare_aligned() {
+        let a = DynamicImage::ImageRgb8(scene(0));
+        // Same place, camera rotated 90° and a darker exposure
+        let mut darker = scene(270);
+        darker.pixels_mut().for_each(|p| p.0 = p.0.map(|c| c / 2));
+        let b = DynamicImage::ImageRgb8(darker);
+
+        let options = CompareOptions::new().work_width(360);
+        let changes = compare_panoramas(&a, &b, &options);
+        assert!((changes.heading_offset - 90.0).abs() < 1.5, "{}", changes.heading_offset);
+        assert!(changes.max_score() < 0.1, "{}", changes.max_score());
+
+        let given = compare_panoramas(&a, &b, &options.clone().heading_offset(90.0));
+        assert_eq!(given.heading_offset, 90.0);
+        assert!(given.max_score() < 0.1);
+    }
+
+    #[test]
+    fn test_compare_detects_change() {
+        let a = scene(0);
+        let mut b = a.clone();
+        // A new "building" in the third column of a 4×2 grid
+        for y in 10..80 {
+            for x in 190..260 {
+                b.put_pixel(x, y, Rgb([255, 255, 255]));
+            }
+        }
+        let changes = compare_panoramas(
+            &DynamicImage::ImageRgb8(a),
+            &DynamicImage::ImageRgb8(b),
+            &CompareOptions::new().grid(4, 2).heading_offset(0.0).work_width(360),
+        );
+        let regions = changes.changed_regions(0.2);
+        assert_eq!((regions[0].col, regions[0].row), (2, 0));
+        assert!(changes.score(0, 1) < 0.05);
+        assert_eq!(changes.to_image(8, 4).dimensions(), (8, 4));
+    }
+}
@@ -0,0 +1,263 @@
+//! COLMAP export of views extracted from several panoramas.
+//!
+//! [`StreetView::export_colmap`](crate::StreetView::export_colmap) extracts
+//! the same set of views from every panorama and writes them as a COLMAP
+//! text model with known camera poses, ready for triangulation or as the
+//! starting point of a reconstruction:
+//!
+//! ```text
+//! {out_dir}/images/{pano_id}_{label}.jpg
+//! {out_dir}/sparse/0/cameras.txt    one PINHOLE camera per view label
+//! {out_dir}/sparse/0/images.txt     world-to-camera pose of every image
+//! {out_dir}/sparse/0/points3D.txt   empty
+//! ```
+//!
+//! Positions are in meters in a local east-north-up frame centered on the
+//! first panorama ([`ColmapModel::reference`]), from the panoramas'
+//! latitude, longitude, and elevation. Orientations come from the
+//! panorama heading and each view's heading and pitch; panoramas are
+//! treated as level.
+
+use crate::error::Result;
+use crate::pose::{GeoPosition, Pose, Quaternion};
+use crate::types::{ImageFormat, Panorama, SaveOptions};
+use crate::views::{CameraIntrinsics, ViewConfig};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+/// Options for [`StreetView::export_colmap`](crate::StreetView::export_colmap).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ColmapOptions {
+    /// Labelled views extracted from every panorama (default: eight views
+    /// 45° apart with a 90° field of view at 1024×1024, so neighbors overlap
+    /// by half)
+    pub views: Vec<(String, ViewConfig)>,
+    /// Output directory (default: current directory)
+    pub out_dir: PathBuf,
+    /// Image save options (default: JPEG)
+    pub save: SaveOptions,
+}
+
+impl ColmapOptions {
+    /// Create default export options.
+    pub fn new() -> Self {
+        let views = (0..8)
+            .map(|i| {
+                let heading = i * 45;
+                (format!("h{heading:03}"), ViewConfig::new(heading).size(1024, 1024))
+            })
+            .collect();
+
+        Self {
+            views,
+            out_dir: PathBuf::from("."),
+            save: SaveOptions::new().format(ImageFormat::Jpeg),
+        }
+    }
+
+    /// Set the output directory.
+    pub fn out_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.out_dir = dir.into();
+        self
+    }
+
+    /// Replace the views to extract.
+    pub fn views(mut self, views: Vec<(String, ViewConfig)>) -> Self {
+        self.views = views;
+        self
+    }
+
+    /// Add a labelled view.
+    pub fn add_view(mut self, label: impl Into<String>, config: ViewConfig) -> Self {
+        self.views.push((label.into(), config));
+        self
+    }
+
+    /// Set the image save options.
+    pub fn save_options(mut self, save: SaveOptions) -> Self {
+        self.save = save;
+        self
+    }
+}
+
+impl Default for ColmapOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An image in a COLMAP model.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ColmapImage {
+    /// COLMAP image ID, from 1
+    pub image_id: u32,
+    /// COLMAP camera ID, from 1
+    pub camera_id: u32,
+    /// File name in the `images` directory
+    pub name: String,
+    /// Panorama the view was extracted from
+    pub pano_id: String,
+    /// World-to-camera rotation, in COLMAP's camera frame (`x` right, `y`
+    /// down, `z` forward)
+    pub rotation: Quaternion,
+    /// World-to-camera translation in meters
+    pub translation: [f64; 3],
+}
+
+impl ColmapImage {
+    /// Build the image for a view extracted with `config` from `pano`,
+    /// with positions relative to `reference`.
+    pub fn new(
+        image_id: u32,
+        camera_id: u32,
+        name: impl Into<String>,
+        pano: &Panorama,
+        config: &ViewConfig,
+        reference: &GeoPosition,
+    ) -> Self {
+        // The panorama image center faces the panorama heading
+        let heading = pano.heading + f64::from(config.heading) - 180.0;
+        let camera_to_world = Pose::new(heading, f64::from(config.pitch), 0.0)
+            .quaternion()
+            // COLMAP cameras look along +z with +y down
+            .multiply(&Quaternion::axis_angle([1.0, 0.0, 0.0], -90.0));
+        let rotation = camera_to_world.conjugate();
+
+        let center = pano.pose().enu(reference).unwrap_or_default();
+        let rotated = rotation.rotate(center);
+        Self {
+            image_id,
+            camera_id,
+            name: name.into(),
+            pano_id: pano.pano_id.clone(),
+            rotation,
+            translation: [-rotated[0], -rotated[1], -rotated[2]],
+        }
+    }
+
+    /// Camera position in the model's east-north-up frame.
+    pub fn center(&self) -> [f64; 3] {
+        let t = self.translation;
+        let center = self.rotation.conjugate().rotate(t);
+        [-center[0], -center[1], -center[2]]
+    }
+}
+
+/// A COLMAP model written by
+/// [`StreetView::export_colmap`](crate::StreetView::export_colmap).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ColmapModel {
+    /// Output directory
+    pub out_dir: PathBuf,
+    /// Origin of the east-north-up frame
+    pub reference: GeoPosition,
+    /// Cameras, by camera ID from 1
+    pub cameras: Vec<CameraIntrinsics>,
+    /// Images in the model
+    pub images: Vec<ColmapImage>,
+}
+
+impl ColmapModel {
+    /// Directory holding the saved views.
+    pub fn images_dir(&self) -> PathBuf {
+        self.out_dir.join("images")
+    }
+
+    /// Directory holding the text model.
+    pub fn sparse_dir(&self) -> PathBuf {
+        self.out_dir.join("sparse").join("0")
+    }
+
+    /// Write `cameras.txt`, `images.txt`, and an empty `points3D.txt` to
+    /// [`sparse_dir`](Self::sparse_dir).
+    pub fn write(&self) -> Result<()> {
+        write_text_model(&self.sparse_dir(), &self.cameras, &self.images)
+    }
+}
+
+/// Write a COLMAP text model to `dir`.
+pub fn write_text_model(dir: &Path, cameras: &[CameraIntrinsics], images: &[ColmapImage]) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+
+    let mut text = String::from("# Camera list with one line of data per camera:\n");
+    text.push_str("#   CAMERA_ID, MODEL, WIDTH, HEIGHT, PARAMS[]\n");
+    let _ = writeln!(text, "# Number of cameras: {}", cameras.len());
+    for (i, camera) in cameras.iter().enumerate() {
+        let _ = writeln!(
+            text,
+            "{} PINHOLE {} {} {} {} {} {}",
+            i + 1,
+            camera.width,
+            camera.height,
+            camera.fx,
+            camera.fy,
+            camera.cx,
+            camera.cy
+        );
+    }
+    std::fs::write(dir.join("cameras.txt"), text)?;
+
+    let mut text = String::from("# Image list with two lines of data per image:\n");
+    text.push_str("#   IMAGE_ID, QW, QX, QY, QZ, TX, TY, TZ, CAMERA_ID, NAME\n");
+    text.push_str("#   POINTS2D[] as (X, Y, POINT3D_ID)\n");
+    let _ = writeln!(text, "# Number of images: {}", images.len());
+    for image in images {
+        let (q, t) = (image.rotation, image.translation);
+        let _ = writeln!(
+            text,
+            "{} {} {} {} {} {} {} {} {} {}\n",
+            image.image_id, q.w, q.x, q.y, q.z, t[0], t[1], t[2], image.camera_id, image.name
+        );
+    }
+    std::fs::write(dir.join("images.txt"), text)?;
+
+    let text = "# 3D point list with one line of data per point:\n\
+                #   POINT3D_ID, X, Y, Z, R, G, B, ERROR, TRACK[] as (IMAGE_ID, POINT2D_IDX)\n\
+                # Number of points: 0\n";
+    std::fs::write(dir.join("points3D.txt"), text)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures::panorama;
+
+    fn assert_vec_eq(a: [f64; 3], b: [f64; 3]) {
+        for (x, y) in a.iter().zip(b.iter()) {
+            assert!((x - y).abs() < 1e-6, "{a:?} != {b:?}");
+        }
+    }
+
+    #[test]
+    fn test_image_pose() {
+        let mut pano = Panorama {
+            heading: 90.0,
+            elevation: Some(35.0),
+            ..panorama("A000000000000000000000", 48.0, 2.0)
+        };
+        let reference = GeoPosition::new(48.0, 2.0, 35.0);
+
+        // The panorama center faces east
+        let image = ColmapImage::new(1, 1, "a.jpg", &pano, &ViewConfig::new(180), &reference);
+        let camera_to_world = image.rotation.conjugate();
+        assert_vec_eq(camera_to_world.rotate([0.0, 0.0, 1.0]), [1.0, 0.0, 0.0]);
+        assert_vec_eq(camera_to_world.rotate([0.0, 1.0, 0.0]), [0.0, 0.0, -1.0]);
+        assert_vec_eq(image.center(), [0.0, 0.0, 0.0]);
+
+        // 90° left of the center faces north; 30° up
+        let image = ColmapImage::new(2, 1, "b.jpg", &pano, &ViewConfig::new(90).pitch(30), &reference);
+        let forward = image.rotation.conjugate().rotate([0.0, 0.0, 1.0]);
+        assert_vec_eq(forward, [0.0, 30f64.to_radians().cos(), 30f64.to_radians().sin()]);
+
+        // A panorama 0.001° further north sits ~111 m north, 2 m higher
+        pano.lat += 0.001;
+        pano.elevation = Some(37.0);
+        let center = ColmapImage::new(3, 1, "c.jpg", &pano, &ViewConfig::new(0), &reference).center();
+        assert!(center[0].abs() < 1e-6 && (center[1] - 111.2).abs() < 0.1 && (center[2] - 2.0).abs() < 0.01);
+    }
+}
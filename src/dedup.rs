@@ -0,0 +1,371 @@
+//! Merging panoramas from overlapping searches.
+//!
+//! Neighboring search points return many of the same panoramas. [`PanoSet`]
+//! collects panoramas keeping the first of each ID, and optionally drops
+//! any panorama within a distance of one already kept;
+//! [`DedupPanoramas`] does the same in place on a `Vec`.
+//! [`group_by_position`] keeps every capture instead, as one timeline per
+//! spot.
+//!
+//! Spatial deduplication treats captures from different dates at the same
+//! spot as duplicates too, keeping whichever came first.
+
+use crate::types::{CaptureDate, Panorama};
+use crate::utils::haversine_distance;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Meters per degree of latitude, for sizing grid cells.
+const METERS_PER_DEGREE: f64 = 111_320.0;
+
+/// A kept position as `(lat, lon, id)`.
+type GridPoint = (f64, f64, usize);
+
+/// Grid of kept positions, for finding neighbors within `meters`.
+///
+/// Rows are bands of latitude `meters` tall; each row's cells are `meters`
+/// wide at the row's latitude, so a neighbor is always in the same or an
+/// adjacent cell of the same or an adjacent row. Each position carries an
+/// ID for its caller.
+#[derive(Debug, Clone)]
+struct SpatialGrid {
+    meters: f64,
+    cells: HashMap<(i64, i64), Vec<GridPoint>>,
+}
+
+impl SpatialGrid {
+    fn new(meters: f64) -> Self {
+        Self {
+            // Keep cells from degenerating at 0 m
+            meters: meters.max(0.01),
+            cells: HashMap::new(),
+        }
+    }
+
+    fn cell(&self, row: i64, lon: f64) -> (i64, i64) {
+        let row_height = self.meters / METERS_PER_DEGREE;
+        let lat = (row as f64 + 0.5) * row_height;
+        let width = row_height / lat.to_radians().cos().max(0.01);
+        (row, (lon / width).floor() as i64)
+    }
+
+    fn row(&self, lat: f64) -> i64 {
+        (lat * METERS_PER_DEGREE / self.meters).floor() as i64
+    }
+
+    /// ID of the nearest kept position within `meters` of `(lat, lon)`.
+    fn nearest(&self, lat: f64, lon: f64) -> Option<usize> {
+        let row = self.row(lat);
+        (row - 1..=row + 1)
+            .flat_map(|row| {
+                let (row, col) = self.cell(row, lon);
+                (col - 1..=col + 1).filter_map(move |col| self.cells.get(&(row, col)))
+            })
+            .flatten()
+            .map(|&(p_lat, p_lon, id)| (haversine_distance(lat, lon, p_lat, p_lon), id))
+            .filter(|&(distance, _)| distance <= self.meters)
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, id)| id)
+    }
+
+    fn add(&mut self, lat: f64, lon: f64, id: usize) {
+        let cell = self.cell(self.row(lat), lon);
+        self.cells.entry(cell).or_default().push((lat, lon, id));
+    }
+
+    /// Keep `(lat, lon)` unless it has a neighbor; returns whether it was kept.
+    fn insert(&mut self, lat: f64, lon: f64) -> bool {
+        if self.nearest(lat, lon).is_some() {
+            return false;
+        }
+        self.add(lat, lon, 0);
+        true
+    }
+}
+
+/// A collection of panoramas without duplicates.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rsstreetview::{PanoSet, StreetView};
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = StreetView::new();
+/// // One panorama per 10 m along the street
+/// let mut panos = PanoSet::within_meters(10.0);
+/// for (lat, lon) in [(41.8982, 12.4764), (41.8984, 12.4766)] {
+///     panos.extend(client.search_panoramas(lat, lon).await?);
+/// }
+/// println!("{} panoramas", panos.len());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PanoSet {
+    panos: Vec<Panorama>,
+    ids: HashSet<String>,
+    grid: Option<SpatialGrid>,
+}
+
+impl PanoSet {
+    /// An empty set that drops panoramas with an ID already in it.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An empty set that also drops panoramas within `meters` of one
+    /// already in it.
+    pub fn within_meters(meters: f64) -> Self {
+        Self {
+            grid: Some(SpatialGrid::new(meters)),
+            ..Self::default()
+        }
+    }
+
+    /// Add a panorama, returning whether it was kept.
+    pub fn insert(&mut self, pano: Panorama) -> bool {
+        if self.ids.contains(&pano.pano_id) {
+            return false;
+        }
+        if let Some(grid) = &mut self.grid {
+            if !grid.insert(pano.lat, pano.lon) {
+                return false;
+            }
+        }
+        self.ids.insert(pano.pano_id.clone());
+        self.panos.push(pano);
+        true
+    }
+
+    /// Whether a panorama with this ID was kept.
+    pub fn contains(&self, pano_id: &str) -> bool {
+        self.ids.contains(pano_id)
+    }
+
+    /// Number of panoramas kept.
+    pub fn len(&self) -> usize {
+        self.panos.len()
+    }
+
+    /// Whether no panorama was kept.
+    pub fn is_empty(&self) -> bool {
+        self.panos.is_empty()
+    }
+
+    /// Kept panoramas, in insertion order.
+    pub fn iter(&self) -> std::slice::Iter<'_, Panorama> {
+        self.panos.iter()
+    }
+
+    /// Kept panoramas, in insertion order.
+    pub fn into_vec(self) -> Vec<Panorama> {
+        self.panos
+    }
+}
+
+impl Extend<Panorama> for PanoSet {
+    fn extend<I: IntoIterator<Item = Panorama>>(&mut self, panos: I) {
+        for pano in panos {
+            self.insert(pano);
+        }
+    }
+}
+
+impl FromIterator<Panorama> for PanoSet {
+    fn from_iter<I: IntoIterator<Item = Panorama>>(panos: I) -> Self {
+        let mut set = Self::new();
+        set.extend(panos);
+        set
+    }
+}
+
+impl IntoIterator for PanoSet {
+    type Item = Panorama;
+    type IntoIter = std::vec::IntoIter<Panorama>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.panos.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a PanoSet {
+    type Item = &'a Panorama;
+    type IntoIter = std::slice::Iter<'a, Panorama>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.panos.iter()
+    }
+}
+
+/// In-place deduplication of a list of panoramas, keeping the first of
+/// each duplicate and the order of the rest.
+pub trait DedupPanoramas {
+    /// Remove panoramas whose ID appeared earlier in the list.
+    fn dedup_by_id(&mut self);
+
+    /// Remove panoramas within `meters` of an earlier one that was kept.
+    fn dedup_by_distance(&mut self, meters: f64);
+}
+
+impl DedupPanoramas for Vec<Panorama> {
+    fn dedup_by_id(&mut self) {
+        let mut ids = HashSet::new();
+        self.retain(|pano| ids.insert(pano.pano_id.clone()));
+    }
+
+    fn dedup_by_distance(&mut self, meters: f64) {
+        let mut grid = SpatialGrid::new(meters);
+        self.retain(|pano| grid.insert(pano.lat, pano.lon));
+    }
+}
+
+/// The captures of one spot, oldest first.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PositionTimeline {
+    /// Latitude of the spot (the first panorama seen there)
+    pub lat: f64,
+    /// Longitude of the spot
+    pub lon: f64,
+    /// Panoramas sorted by capture date, undated ones first
+    pub panoramas: VecDeque<Panorama>,
+}
+
+impl PositionTimeline {
+    /// The oldest dated capture.
+    pub fn oldest(&self) -> Option<&Panorama> {
+        self.panoramas.iter().find(|pano| pano.date.is_some())
+    }
+
+    /// The newest dated capture.
+    pub fn newest(&self) -> Option<&Panorama> {
+        self.panoramas.back().filter(|pano| pano.date.is_some())
+    }
+
+    /// Capture dates, oldest first.
+    pub fn dates(&self) -> Vec<CaptureDate> {
+        self.panoramas.iter().filter_map(|pano| pano.date).collect()
+    }
+}
+
+/// Group panoramas into one timeline per spot.
+///
+/// Each panorama joins the nearest spot within `radius_m` meters of it,
+/// or starts a new spot there. Duplicate IDs are dropped. Spots are in the
+/// order they were first seen.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rsstreetview::StreetView;
+/// # use rsstreetview::dedup::group_by_position;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = StreetView::new();
+/// let panos = client.search_panoramas(41.8982208, 12.4764804).await?;
+/// for spot in group_by_position(panos, 5.0) {
+///     println!("{:.5}, {:.5}: {:?}", spot.lat, spot.lon, spot.dates());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn group_by_position(panos: impl IntoIterator<Item = Panorama>, radius_m: f64) -> Vec<PositionTimeline> {
+    let mut grid = SpatialGrid::new(radius_m);
+    let mut ids = HashSet::new();
+    let mut timelines: Vec<PositionTimeline> = Vec::new();
+
+    for pano in panos {
+        if !ids.insert(pano.pano_id.clone()) {
+            continue;
+        }
+        match grid.nearest(pano.lat, pano.lon) {
+            Some(spot) => timelines[spot].panoramas.push_back(pano),
+            None => {
+                grid.add(pano.lat, pano.lon, timelines.len());
+                timelines.push(PositionTimeline {
+                    lat: pano.lat,
+                    lon: pano.lon,
+                    panoramas: VecDeque::from([pano]),
+                });
+            }
+        }
+    }
+
+    for timeline in &mut timelines {
+        timeline.panoramas.make_contiguous().sort_by_key(|pano| pano.date);
+    }
+    timelines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures::panorama;
+
+    #[test]
+    fn test_pano_set() {
+        // ~1.1 m of latitude per 0.00001°
+        let panos = [
+            panorama("a", 60.0, 10.0),
+            panorama("a", 60.0, 10.0),
+            panorama("b", 60.00005, 10.0),
+            panorama("c", 60.0002, 10.0),
+            panorama("d", 60.0002, 10.00001),
+        ];
+
+        let by_id: PanoSet = panos.iter().cloned().collect();
+        assert_eq!(by_id.len(), 4);
+        assert!(by_id.contains("d"));
+
+        let mut near = PanoSet::within_meters(10.0);
+        near.extend(panos.iter().cloned());
+        let ids: Vec<_> = near.iter().map(|p| p.pano_id.as_str()).collect();
+        assert_eq!(ids, ["a", "c"]);
+    }
+
+    #[test]
+    fn test_group_by_position() {
+        let dated = |id: &str, lat: f64, year: u16| Panorama {
+            date: CaptureDate::new(year, 6),
+            ..panorama(id, lat, 10.0)
+        };
+        let panos = vec![
+            dated("a", 60.0, 2021),
+            dated("b", 60.00002, 2014),
+            dated("far", 60.001, 2019),
+            panorama("c", 59.99998, 10.0),
+            dated("b", 60.00002, 2014),
+            dated("d", 60.00003, 2017),
+        ];
+
+        let spots = group_by_position(panos, 5.0);
+        assert_eq!(spots.len(), 2);
+        let ids: Vec<_> = spots[0].panoramas.iter().map(|p| p.pano_id.as_str()).collect();
+        assert_eq!(ids, ["c", "b", "d", "a"]);
+        assert_eq!((spots[0].lat, spots[0].lon), (60.0, 10.0));
+        assert_eq!(spots[0].oldest().unwrap().pano_id, "b");
+        assert_eq!(spots[0].newest().unwrap().pano_id, "a");
+        assert_eq!(spots[0].dates().len(), 3);
+        assert_eq!(spots[1].panoramas[0].pano_id, "far");
+    }
+
+    #[test]
+    fn test_dedup_across_cells() {
+        // Neighbors in every direction are found across cell and row edges
+        for (lat, lon) in [(0.0, 0.0), (45.0, 7.0), (-33.9, 151.2), (78.2, 15.6)] {
+            let mut panos = vec![panorama("a", lat, lon)];
+            for i in 0..16 {
+                let angle = f64::from(i) * std::f64::consts::PI / 8.0;
+                // 9 m away in 16 directions
+                let d_lat = 9.0 * angle.cos() / METERS_PER_DEGREE;
+                let d_lon = 9.0 * angle.sin() / METERS_PER_DEGREE / lat.to_radians().cos();
+                panos.push(panorama(&i.to_string(), lat + d_lat, lon + d_lon));
+            }
+            let mut by_id = panos.clone();
+            by_id.dedup_by_id();
+            assert_eq!(by_id.len(), 17);
+            panos.dedup_by_distance(10.0);
+            assert_eq!(panos.len(), 1, "at {lat}, {lon}");
+        }
+    }
+}
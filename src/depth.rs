@@ -0,0 +1,273 @@
+//! Depth map decoding.
+//!
+//! Street View panoramas carry a coarse depth map in their photometa
+//! response: a zlib-compressed, URL-safe base64 blob describing the scene
+//! as a set of planes plus a per-pixel plane index. Index 0 means no plane
+//! (sky or infinity).
+//!
+//! The decoded images use the same equirectangular layout as the
+//! panorama, so they line up with it after resizing to the panorama size.
+
+use crate::error::{Result, StreetViewError};
+use crate::types::{Location, Panorama};
+use crate::utils::{destination, BASE64_URL_SAFE};
+use base64::Engine;
+use flate2::read::ZlibDecoder;
+use image::{GrayImage, ImageBuffer, Luma};
+use std::f64::consts::{FRAC_PI_2, PI};
+use std::io::Read;
+
+/// Size of the fixed header fields read from the decompressed blob.
+const HEADER_LEN: usize = 9;
+/// Each plane is a unit normal and a distance, as four `f32`s.
+const PLANE_LEN: usize = 16;
+
+/// A scene plane: points `p` on it satisfy `normal · p = -distance`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Plane {
+    /// Unit normal in the depth map frame
+    pub normal: [f32; 3],
+    /// Distance from the camera in meters
+    pub distance: f32,
+}
+
+/// A decoded panorama depth map.
+#[derive(Debug, Clone)]
+pub struct DepthMap {
+    /// Width in pixels (typically 512)
+    pub width: u32,
+    /// Height in pixels (typically 256)
+    pub height: u32,
+    /// Scene planes; index 0 is unused
+    pub planes: Vec<Plane>,
+    /// Plane index per pixel, row-major, aligned to the panorama
+    pub plane_indices: Vec<u8>,
+    /// Depth in meters per pixel, `f32::INFINITY` where there is no plane
+    pub depths: Vec<f32>,
+}
+
+impl DepthMap {
+    /// Decode the base64 depth map blob from a photometa response.
+    ///
+    /// # Errors
+    ///
+    /// Returns `StreetViewError::ParseError` if the blob is not valid
+    /// base64/zlib or is truncated.
+    pub fn decode(data: &str) -> Result<Self> {
+        let compressed = BASE64_URL_SAFE
+            .decode(data)
+            .map_err(|e| StreetViewError::ParseError(format!("Invalid depth map base64: {e}")))?;
+        let mut raw = Vec::new();
+        ZlibDecoder::new(compressed.as_slice())
+            .read_to_end(&mut raw)
+            .map_err(|e| StreetViewError::ParseError(format!("Invalid depth map data: {e}")))?;
+        Self::from_raw(&raw)
+    }
+
+    /// Parse a decompressed depth map.
+    fn from_raw(raw: &[u8]) -> Result<Self> {
+        let truncated = || StreetViewError::ParseError("Truncated depth map".to_string());
+        if raw.len() < HEADER_LEN {
+            return Err(truncated());
+        }
+
+        let u16_at = |pos: usize| u16::from_le_bytes([raw[pos], raw[pos + 1]]) as usize;
+        let (plane_count, width, height, offset) = (u16_at(1), u16_at(3), u16_at(5), u16_at(7));
+
+        let pixels = width * height;
+        let planes_start = offset + pixels;
+        if raw.len() < planes_start + plane_count * PLANE_LEN {
+            return Err(truncated());
+        }
+
+        let f32_at = |pos: usize| f32::from_le_bytes([raw[pos], raw[pos + 1], raw[pos + 2], raw[pos + 3]]);
+        let planes: Vec<Plane> = (0..plane_count)
+            .map(|i| {
+                let pos = planes_start + i * PLANE_LEN;
+                Plane {
+                    normal: [f32_at(pos), f32_at(pos + 4), f32_at(pos + 8)],
+                    distance: f32_at(pos + 12),
+                }
+            })
+            .collect();
+
+        // The stored map is mirrored horizontally relative to the panorama
+        let mut plane_indices = vec![0u8; pixels];
+        let mut depths = vec![f32::INFINITY; pixels];
+        for y in 0..height {
+            for x in 0..width {
+                let index = raw[offset + y * width + x];
+                let target = y * width + (width - x - 1);
+                plane_indices[target] = index;
+
+                if let Some(plane) = planes.get(index as usize).filter(|_| index > 0) {
+                    let phi = (width - x - 1) as f64 / (width - 1).max(1) as f64 * 2.0 * PI + FRAC_PI_2;
+                    let theta = (height - y - 1) as f64 / (height - 1).max(1) as f64 * PI;
+                    let ray = [theta.sin() * phi.cos(), theta.sin() * phi.sin(), theta.cos()];
+
+                    let dot: f64 = ray.iter().zip(plane.normal).map(|(r, n)| r * n as f64).sum();
+                    depths[target] = (plane.distance as f64 / dot).abs() as f32;
+                }
+            }
+        }
+
+        Ok(Self {
+            width: width as u32,
+            height: height as u32,
+            planes,
+            plane_indices,
+            depths,
+        })
+    }
+
+    /// Depth in meters at a pixel, or `None` for sky/infinity.
+    pub fn depth(&self, x: u32, y: u32) -> Option<f32> {
+        let depth = *self.depths.get((y * self.width + x) as usize)?;
+        depth.is_finite().then_some(depth)
+    }
+
+    /// Depth at normalized panorama coordinates (`u`, `v` in `0.0..1.0`).
+    ///
+    /// Use this to look up the depth of a pixel in a full-resolution
+    /// panorama: `u = x / pano_width`, `v = y / pano_height`.
+    pub fn depth_at(&self, u: f64, v: f64) -> Option<f32> {
+        let x = ((u.clamp(0.0, 1.0) * self.width as f64) as u32).min(self.width.saturating_sub(1));
+        let y = ((v.clamp(0.0, 1.0) * self.height as f64) as u32).min(self.height.saturating_sub(1));
+        self.depth(x, y)
+    }
+
+    /// Depth as a floating point image in meters (`f32::INFINITY` for sky).
+    pub fn depth_image(&self) -> ImageBuffer<Luma<f32>, Vec<f32>> {
+        ImageBuffer::from_raw(self.width, self.height, self.depths.clone())
+            .expect("depth buffer matches dimensions")
+    }
+
+    /// Plane index per pixel as a grayscale image.
+    pub fn plane_image(&self) -> GrayImage {
+        GrayImage::from_raw(self.width, self.height, self.plane_indices.clone())
+            .expect("plane buffer matches dimensions")
+    }
+
+    /// Sky mask: 255 where there is no plane (sky or infinity), 0 elsewhere.
+    pub fn sky_mask(&self) -> GrayImage {
+        let mask = self.plane_indices.iter().map(|&i| if i == 0 { 255 } else { 0 }).collect();
+        GrayImage::from_raw(self.width, self.height, mask).expect("mask buffer matches dimensions")
+    }
+}
+
+/// Estimate where the point seen at a panorama pixel is in the world.
+///
+/// `u` and `v` are normalized panorama coordinates as in
+/// [`DepthMap::depth_at`]: `u = x / pano_width`, `v = y / pano_height`.
+/// The pixel's ray leaves the camera at the compass bearing of that
+/// column ([`Panorama::heading`] is the image center) and is followed for
+/// the depth at the pixel; the result is the ground position below the
+/// point it hits. Returns `None` for sky.
+///
+/// Depth maps are coarse planes, so expect errors of a few meters, more
+/// for distant or thin objects. Camera tilt is not corrected.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rsstreetview::StreetView;
+/// # use rsstreetview::depth::geolocate_pixel;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = StreetView::new();
+/// let pano = &client.search_panoramas(41.8982208, 12.4764804).await?[0];
+/// let depth = client.get_depth_map(&pano.pano_id).await?;
+/// // A sign detected at (9000, 4300) in a zoom 5 (16384×8192) panorama
+/// if let Some(location) = geolocate_pixel(pano, &depth, 9000.0 / 16384.0, 4300.0 / 8192.0) {
+///     println!("sign at {}, {}", location.lat, location.lng);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn geolocate_pixel(pano: &Panorama, depth: &DepthMap, u: f64, v: f64) -> Option<Location> {
+    let distance = f64::from(depth.depth_at(u, v)?);
+    let bearing = pano.heading + (u - 0.5) * 360.0;
+    let elevation = (0.5 - v) * PI;
+    let (lat, lng) = destination(pano.lat, pano.lon, bearing, distance * elevation.cos());
+    Some(Location::new(lat, lng))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures::panorama;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    /// Build a 4×3 depth map: top row sky, the rest on a ground plane 2 m below.
+    fn encoded_depth_map() -> String {
+        let (width, height) = (4u16, 3u16);
+        let mut raw = vec![HEADER_LEN as u8];
+        for value in [2u16, width, height, HEADER_LEN as u16] {
+            raw.extend_from_slice(&value.to_le_bytes());
+        }
+        raw.extend_from_slice(&[0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1]);
+        for value in [0.0f32, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 2.0] {
+            raw.extend_from_slice(&value.to_le_bytes());
+        }
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&raw).unwrap();
+        BASE64_URL_SAFE.encode(encoder.finish().unwrap())
+    }
+
+    #[test]
+    fn test_decode_depth_map() {
+        let depth = DepthMap::decode(&encoded_depth_map()).unwrap();
+        assert_eq!((depth.width, depth.height), (4, 3));
+        assert_eq!(depth.planes.len(), 2);
+
+        // Top row is sky
+        assert_eq!(depth.depth(0, 0), None);
+        assert_eq!(depth.sky_mask().get_pixel(2, 0)[0], 255);
+
+        // Middle row looks at the horizon, bottom row straight down at the plane
+        assert!(depth.depth(1, 1).unwrap() > 1e6);
+        assert!((depth.depth(1, 2).unwrap() - 2.0).abs() < 1e-4);
+        assert_eq!(depth.depth_at(0.3, 0.99), depth.depth(1, 2));
+        assert_eq!(depth.plane_image().get_pixel(3, 2)[0], 1);
+    }
+
+    #[test]
+    fn test_geolocate_pixel() {
+        // 8×4 map: sky on top, a wall 10 m away along the ray below
+        let mut depths = vec![f32::INFINITY; 32];
+        depths[8..].fill(10.0);
+        let depth = DepthMap {
+            width: 8,
+            height: 4,
+            planes: Vec::new(),
+            plane_indices: Vec::new(),
+            depths,
+        };
+        let pano = Panorama {
+            heading: 90.0,
+            ..panorama("A000000000000000000000", 48.0, 2.0)
+        };
+
+        assert!(geolocate_pixel(&pano, &depth, 0.5, 0.1).is_none());
+
+        // The image center faces east, on the horizon
+        let east = geolocate_pixel(&pano, &depth, 0.5, 0.5).unwrap();
+        assert!((east.lat - 48.0).abs() < 1e-7 && east.lng > 2.0);
+        assert!((crate::utils::haversine_distance(48.0, 2.0, east.lat, east.lng) - 10.0).abs() < 1e-6);
+
+        // A quarter turn left faces north; 45° down lands closer
+        let north = geolocate_pixel(&pano, &depth, 0.25, 0.75).unwrap();
+        assert!((north.lng - 2.0).abs() < 1e-7 && north.lat > 48.0);
+        let distance = crate::utils::haversine_distance(48.0, 2.0, north.lat, north.lng);
+        assert!((distance - 10.0 * std::f64::consts::FRAC_1_SQRT_2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_decode_truncated() {
+        assert!(DepthMap::decode("AAAA").is_err());
+        assert!(DepthMap::from_raw(&[9, 1, 0, 8, 0, 8, 0, 9, 0]).is_err());
+    }
+}
@@ -1,16 +1,22 @@
+use crate::endpoints::Endpoints;
 use crate::error::{Result, StreetViewError};
 use crate::types::{Tile, TileInfo};
-use futures::stream::{self, StreamExt};
-use image::{DynamicImage, GenericImage};
-use reqwest::Client;
-use std::time::Duration;
+use crate::views::{crop_region, resize_view, ViewConfig};
+use futures::stream::{self, FuturesUnordered, StreamExt};
+use image::{DynamicImage, GenericImage, GenericImageView};
+use crate::http::Http;
+use std::collections::VecDeque;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{Semaphore, SemaphorePermit};
 
 const TILE_WIDTH: u32 = 512;
 const TILE_HEIGHT: u32 = 512;
-const DEFAULT_MAX_RETRIES: u32 = 6;
-const RETRY_DELAY_SECS: u64 = 2;
-const TILE_ENDPOINT: &str = "https://cbk0.google.com/cbk";
-const CONCURRENT_DOWNLOADS: usize = 8;
+pub(crate) const CONCURRENT_DOWNLOADS: usize = 8;
+/// Typical size of one JPEG tile, for estimates without a probe.
+const TYPICAL_TILE_BYTES: u64 = 40_000;
 
 /// Calculate the width and height of the panorama grid from zoom level.
 ///
@@ -22,14 +28,15 @@ fn get_width_and_height_from_zoom(zoom: u8) -> (u32, u32) {
 }
 
 /// Build the download URL for a single tile.
-fn make_download_url(pano_id: &str, zoom: u8, x: u32, y: u32) -> String {
+fn make_download_url(endpoints: &Endpoints, pano_id: &str, zoom: u8, x: u32, y: u32) -> String {
     format!(
-        "{TILE_ENDPOINT}?output=tile&panoid={pano_id}&zoom={zoom}&x={x}&y={y}"
+        "{}?output=tile&panoid={pano_id}&zoom={zoom}&x={x}&y={y}",
+        endpoints.tile_endpoint(x, y)
     )
 }
 
 /// Generate all tile info for a panorama.
-fn iter_tile_info(pano_id: &str, zoom: u8) -> Vec<TileInfo> {
+fn iter_tile_info(endpoints: &Endpoints, pano_id: &str, zoom: u8) -> Vec<TileInfo> {
     let (width, height) = get_width_and_height_from_zoom(zoom);
     let mut tiles = Vec::new();
 
@@ -38,7 +45,7 @@ fn iter_tile_info(pano_id: &str, zoom: u8) -> Vec<TileInfo> {
             tiles.push(TileInfo {
                 x,
                 y,
-                url: make_download_url(pano_id, zoom, x, y),
+                url: make_download_url(endpoints, pano_id, zoom, x, y),
             });
         }
     }
@@ -47,69 +54,86 @@ fn iter_tile_info(pano_id: &str, zoom: u8) -> Vec<TileInfo> {
 }
 
 /// Download a single tile with retry logic.
-async fn fetch_tile_with_retry(
-    client: &Client,
+///
+/// Missing tiles, blocked requests, and offline cache misses fail
+/// immediately; rate limited requests wait at least as long as Google's
+/// `Retry-After` asks.
+pub(crate) async fn fetch_tile_with_retry(http: &Http, tile_info: &TileInfo, zoom: u8) -> Result<Tile> {
+    let image = fetch_tile_data(http, tile_info, zoom, |bytes| Ok(image::load_from_memory(bytes)?)).await?;
+    Ok(Tile {
+        x: tile_info.x,
+        y: tile_info.y,
+        image,
+    })
+}
+
+/// Download a single tile with retry logic, parsing the body with
+/// `parse`. A body `parse` rejects is retried like a failed request.
+async fn fetch_tile_data<T>(
+    http: &Http,
     tile_info: &TileInfo,
-    max_retries: u32,
-) -> Result<Tile> {
+    zoom: u8,
+    parse: impl Fn(&[u8]) -> Result<T>,
+) -> Result<T> {
+    let max_retries = http.retry().max_retries;
     let mut retries = 0;
 
     loop {
-        match client.get(&tile_info.url).send().await {
-            Ok(response) => {
-                match response.bytes().await {
-                    Ok(bytes) => {
-                        // Try to load the image
-                        match image::load_from_memory(&bytes) {
-                            Ok(img) => {
-                                return Ok(Tile {
-                                    x: tile_info.x,
-                                    y: tile_info.y,
-                                    image: img,
-                                });
-                            }
-                            Err(e) => {
-                                if retries >= max_retries {
-                                    return Err(StreetViewError::ImageError(e));
-                                }
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        if retries >= max_retries {
-                            return Err(StreetViewError::HttpError(e));
-                        }
-                    }
-                }
+        let mut delay = http.retry().delay_for(retries);
+        match http.fetch(&tile_info.url, false, &parse).await {
+            Ok(data) => return Ok(data),
+            // The tile server answers 400 for tiles beyond the panorama's resolution
+            Err(StreetViewError::ApiError { status: 400 | 404, message }) => {
+                return Err(StreetViewError::TileNotFound {
+                    x: tile_info.x,
+                    y: tile_info.y,
+                    zoom,
+                    body: message,
+                });
             }
-            Err(_e) => {
-                if retries >= max_retries {
-                    return Err(StreetViewError::TileDownloadFailed(max_retries));
+            Err(e @ (StreetViewError::Blocked { .. } | StreetViewError::OfflineCacheMiss { .. })) => return Err(e),
+            Err(StreetViewError::HttpError(_)) if retries >= max_retries => {
+                return Err(StreetViewError::TileDownloadFailed(max_retries));
+            }
+            Err(e) if retries >= max_retries => return Err(e),
+            // Connection, decode, or rate limit error, retry
+            Err(e) => {
+                if let StreetViewError::RateLimited { retry_after: Some(wait), .. } = &e {
+                    delay = delay.max(*wait);
                 }
-                // Connection error, retry
+                http.observe_retry(&tile_info.url, retries + 1, &e);
             }
         }
 
+        tokio::time::sleep(delay).await;
         retries += 1;
-        tokio::time::sleep(Duration::from_secs(RETRY_DELAY_SECS)).await;
     }
 }
 
-/// Download all tiles for a panorama concurrently.
-async fn download_tiles(client: &Client, pano_id: &str, zoom: u8) -> Result<Vec<Tile>> {
-    let tile_infos = iter_tile_info(pano_id, zoom);
+/// Download all tiles for a panorama concurrently, keeping every tile's
+/// outcome along with its position.
+async fn fetch_all_tiles(http: &Http, pano_id: &str, zoom: u8) -> Vec<(u32, u32, Result<Tile>)> {
+    let tile_infos = iter_tile_info(http.endpoints(), pano_id, zoom);
 
     // Download tiles concurrently with controlled concurrency
-    let tiles: Vec<Result<Tile>> = stream::iter(tile_infos)
+    stream::iter(tile_infos)
         .map(|tile_info| async move {
-            fetch_tile_with_retry(client, &tile_info, DEFAULT_MAX_RETRIES).await
+            let tile = fetch_tile_with_retry(http, &tile_info, zoom).await;
+            (tile_info.x, tile_info.y, tile)
         })
         .buffer_unordered(CONCURRENT_DOWNLOADS)
         .collect()
-        .await;
+        .await
+}
 
+/// Download all tiles for a panorama concurrently.
+async fn download_tiles(http: &Http, pano_id: &str, zoom: u8) -> Result<Vec<Tile>> {
     // Collect results and return errors if any
-    tiles.into_iter().collect()
+    fetch_all_tiles(http, pano_id, zoom)
+        .await
+        .into_iter()
+        .map(|(_, _, tile)| tile)
+        .collect()
 }
 
 /// Assemble tiles into a single panorama image.
@@ -134,11 +158,21 @@ fn assemble_tiles(tiles: Vec<Tile>, zoom: u8) -> Result<DynamicImage> {
     Ok(panorama)
 }
 
+/// Check that `zoom` is a valid zoom level.
+fn check_zoom(zoom: u8) -> Result<()> {
+    if !(1..=7).contains(&zoom) {
+        return Err(StreetViewError::ParseError(
+            "Zoom level must be between 1 and 7".to_string(),
+        ));
+    }
+    Ok(())
+}
+
 /// Download a full panorama image.
 ///
 /// # Arguments
 ///
-/// * `client` - HTTP client to use for requests
+/// * `http` - HTTP client to use for requests
 /// * `pano_id` - The panorama ID
 /// * `zoom` - Zoom level (1-7)
 ///   - Zoom 1: 1024x512 pixels
@@ -150,24 +184,883 @@ fn assemble_tiles(tiles: Vec<Tile>, zoom: u8) -> Result<DynamicImage> {
 ///   - Zoom 7: 65536x32768 pixels
 ///
 /// Higher zoom levels produce larger images with more detail but take longer to download.
-pub async fn download_panorama(client: &Client, pano_id: &str, zoom: u8) -> Result<DynamicImage> {
-    // Validate zoom level
-    if !(1..=7).contains(&zoom) {
-        return Err(StreetViewError::ParseError(
-            "Zoom level must be between 1 and 7".to_string(),
-        ));
-    }
+pub async fn download_panorama(http: &Http, pano_id: &str, zoom: u8) -> Result<DynamicImage> {
+    check_zoom(zoom)?;
 
     // Download all tiles
-    let tiles = download_tiles(client, pano_id, zoom).await?;
+    let tiles = download_tiles(http, pano_id, zoom);
+    #[cfg(feature = "tracing")]
+    let tiles = tracing::Instrument::instrument(tiles, tracing::info_span!("download_panorama", pano_id, zoom));
+    let tiles = tiles.await?;
 
     // Assemble into final panorama
     assemble_tiles(tiles, zoom)
 }
 
+/// Like [`download_panorama`], but served from and stored in the client's
+/// panorama cache, if it has one.
+pub(crate) async fn cached_panorama(http: &Http, pano_id: &str, zoom: u8) -> Result<Arc<DynamicImage>> {
+    let Some(cache) = http.panorama_cache() else {
+        return download_panorama(http, pano_id, zoom).await.map(Arc::new);
+    };
+    if let Some(panorama) = cache.get(pano_id, zoom) {
+        return Ok(panorama);
+    }
+
+    let panorama = Arc::new(download_panorama(http, pano_id, zoom).await?);
+    cache.insert(pano_id, zoom, panorama.clone());
+    Ok(panorama)
+}
+
+/// Which tiles of a download are fetched first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum TilePriority {
+    /// Fetched before any low priority tile starts
+    High,
+    /// Everything else
+    Low,
+}
+
+/// Order tiles of the same priority are requested in.
+///
+/// With a single tile in flight, tiles arrive in this order; with more,
+/// roughly so. Writers that consume tiles as they arrive (a streaming
+/// encoder taking rows of tiles, a memory mapped file) hold fewer tiles
+/// back when the order matches their layout.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum TileOrder {
+    /// Left to right, then top to bottom, as scanline encoders want
+    #[default]
+    RowMajor,
+    /// Top to bottom, then left to right
+    ColumnMajor,
+    /// Along a Hilbert curve, so consecutive tiles are neighbours and
+    /// nearby tiles arrive close together in time
+    Hilbert,
+}
+
+impl TileOrder {
+    /// Position of tile `(x, y)` in a grid of `cols` × `rows` tiles.
+    ///
+    /// Positions are unique within the grid, but not necessarily
+    /// contiguous: Hilbert curves fill power of two squares, which may
+    /// overhang the grid.
+    ///
+    /// ```
+    /// # use rsstreetview::TileOrder;
+    /// let mut tiles = [(0, 0), (1, 0), (0, 1), (1, 1)];
+    /// tiles.sort_by_key(|&(x, y)| TileOrder::Hilbert.index(x, y, 2, 2));
+    /// assert_eq!(tiles, [(0, 0), (0, 1), (1, 1), (1, 0)]);
+    /// ```
+    pub fn index(self, x: u32, y: u32, cols: u32, rows: u32) -> u64 {
+        match self {
+            TileOrder::RowMajor => u64::from(y) * u64::from(cols) + u64::from(x),
+            TileOrder::ColumnMajor => u64::from(x) * u64::from(rows) + u64::from(y),
+            TileOrder::Hilbert => {
+                // Panorama grids are twice as wide as tall: walk square
+                // blocks along the long side, each filled by its own curve,
+                // which ends next to where the next block's curve starts
+                let side = u64::from(cols.min(rows).max(1).next_power_of_two());
+                let (block, curve) = match cols >= rows {
+                    true => (u64::from(x) / side, hilbert_index(side, u64::from(x) % side, u64::from(y))),
+                    false => (u64::from(y) / side, hilbert_index(side, u64::from(y) % side, u64::from(x))),
+                };
+                block * side * side + curve
+            }
+        }
+    }
+}
+
+/// Distance of `(x, y)` along the Hilbert curve filling a `side` × `side`
+/// square, `side` being a power of two. The curve runs from `(0, 0)` to
+/// `(side - 1, 0)`.
+fn hilbert_index(side: u64, mut x: u64, mut y: u64) -> u64 {
+    let mut index = 0;
+    let mut s = side / 2;
+    while s > 0 {
+        let rx = u64::from(x & s > 0);
+        let ry = u64::from(y & s > 0);
+        index += s * s * ((3 * rx) ^ ry);
+        // Rotate the quadrant so the curve's sub-square starts at the origin
+        if ry == 0 {
+            if rx == 1 {
+                x = side - 1 - x;
+                y = side - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    index
+}
+
+/// Called with the partly assembled panorama once every high priority
+/// tile has arrived.
+type PriorityReady = Arc<dyn Fn(&DynamicImage) + Send + Sync>;
+
+/// Options for [`StreetView::download_panorama_with`](crate::StreetView::download_panorama_with).
+///
+/// Tiles covering the priority views and tiles download first, so a view
+/// can be extracted (see [`on_priority_ready`](Self::on_priority_ready))
+/// while the rest of the panorama is still being fetched.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rsstreetview::{DownloadOptions, StreetView, ViewConfig};
+/// # use rsstreetview::views::extract_view_from_panorama;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = StreetView::new();
+/// let front = ViewConfig::new(180).fov(90).zoom(4);
+/// let view = front.clone();
+/// let options = DownloadOptions::new()
+///     .zoom(4)
+///     .prioritize_view(front)
+///     .on_priority_ready(move |partial| {
+///         // The view's tiles are in place; the rest are still downloading
+///         extract_view_from_panorama(partial, &view).unwrap().save("front.jpg").unwrap();
+///     });
+/// let panorama = client.download_panorama_with("PANO_ID_HERE_0000000000", &options).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct DownloadOptions {
+    /// Zoom level (1-7, default 3)
+    pub zoom: u8,
+    /// Tiles downloading at once (default 8)
+    pub concurrency: usize,
+    /// Views whose tiles are fetched first
+    pub priority_views: Vec<ViewConfig>,
+    /// Tiles fetched first, as `(x, y)`
+    pub priority_tiles: Vec<(u32, u32)>,
+    /// Lower `zoom` to the panorama's highest zoom level, if above it
+    pub clamp_zoom: bool,
+    /// Order tiles of the same priority are requested in (default row-major)
+    pub tile_order: TileOrder,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    on_priority_ready: Option<PriorityReady>,
+}
+
+impl DownloadOptions {
+    /// Defaults: zoom 3, 8 tiles at a time, and no priorities.
+    pub fn new() -> Self {
+        Self {
+            zoom: 3,
+            concurrency: CONCURRENT_DOWNLOADS,
+            priority_views: Vec::new(),
+            priority_tiles: Vec::new(),
+            clamp_zoom: false,
+            tile_order: TileOrder::RowMajor,
+            on_priority_ready: None,
+        }
+    }
+
+    /// Set the zoom level.
+    pub fn zoom(mut self, zoom: u8) -> Self {
+        self.zoom = zoom;
+        self
+    }
+
+    /// Lower the zoom level to the panorama's highest one when it is above
+    /// it, looking that up with
+    /// [`StreetView::get_max_zoom`](crate::StreetView::get_max_zoom) first.
+    ///
+    /// User-contributed photospheres often stop at zoom 4, and tiles above
+    /// a panorama's highest zoom are missing or garbage.
+    pub fn clamp_zoom(mut self, clamp: bool) -> Self {
+        self.clamp_zoom = clamp;
+        self
+    }
+
+    /// Set how many tiles download at once.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Set the order tiles of the same priority are requested in.
+    pub fn tile_order(mut self, order: TileOrder) -> Self {
+        self.tile_order = order;
+        self
+    }
+
+    /// Fetch the tiles `config` crops from first.
+    pub fn prioritize_view(mut self, config: ViewConfig) -> Self {
+        self.priority_views.push(config);
+        self
+    }
+
+    /// Fetch a tile first.
+    pub fn prioritize_tile(mut self, x: u32, y: u32) -> Self {
+        self.priority_tiles.push((x, y));
+        self
+    }
+
+    /// Call `callback` with the partly assembled panorama as soon as every
+    /// high priority tile has arrived. Low priority tiles not yet
+    /// downloaded are black.
+    ///
+    /// Not called if there are no priority tiles, or a priority tile fails.
+    pub fn on_priority_ready(mut self, callback: impl Fn(&DynamicImage) + Send + Sync + 'static) -> Self {
+        self.on_priority_ready = Some(Arc::new(callback));
+        self
+    }
+
+    /// Priority of the tile at column `x`, row `y`.
+    pub fn tile_priority(&self, x: u32, y: u32) -> TilePriority {
+        let (width_tiles, height_tiles) = get_width_and_height_from_zoom(self.zoom.clamp(1, 7));
+        let (width, height) = (width_tiles * TILE_WIDTH, height_tiles * TILE_HEIGHT);
+        let (tile_x, tile_y) = (x * TILE_WIDTH, y * TILE_HEIGHT);
+        let in_view = |config: &ViewConfig| {
+            let (cx, cy, cw, ch) = crop_region(config, width, height);
+            cx < tile_x + TILE_WIDTH && tile_x < cx + cw && cy < tile_y + TILE_HEIGHT && tile_y < cy + ch
+        };
+        match self.priority_tiles.contains(&(x, y)) || self.priority_views.iter().any(in_view) {
+            true => TilePriority::High,
+            false => TilePriority::Low,
+        }
+    }
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for DownloadOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DownloadOptions")
+            .field("zoom", &self.zoom)
+            .field("concurrency", &self.concurrency)
+            .field("priority_views", &self.priority_views)
+            .field("priority_tiles", &self.priority_tiles)
+            .field("clamp_zoom", &self.clamp_zoom)
+            .field("tile_order", &self.tile_order)
+            .field("on_priority_ready", &self.on_priority_ready.is_some())
+            .finish()
+    }
+}
+
+/// Two-tier queue of tiles waiting to download.
+#[derive(Debug, Default)]
+struct TileQueue {
+    high: VecDeque<TileInfo>,
+    low: VecDeque<TileInfo>,
+}
+
+impl TileQueue {
+    fn push(&mut self, tile: TileInfo, priority: TilePriority) {
+        match priority {
+            TilePriority::High => self.high.push_back(tile),
+            TilePriority::Low => self.low.push_back(tile),
+        }
+    }
+
+    /// The next tile to start, high priority first.
+    fn pop(&mut self) -> Option<(TileInfo, TilePriority)> {
+        match self.high.pop_front() {
+            Some(tile) => Some((tile, TilePriority::High)),
+            None => self.low.pop_front().map(|tile| (tile, TilePriority::Low)),
+        }
+    }
+}
+
+/// Download a panorama with tile priorities, pasting tiles into the image
+/// as they arrive.
+pub async fn download_panorama_with(http: &Http, pano_id: &str, options: &DownloadOptions) -> Result<DynamicImage> {
+    let zoom = options.zoom;
+    check_zoom(zoom)?;
+
+    let (width_tiles, height_tiles) = get_width_and_height_from_zoom(zoom);
+    let mut tiles = iter_tile_info(http.endpoints(), pano_id, zoom);
+    tiles.sort_by_key(|tile| options.tile_order.index(tile.x, tile.y, width_tiles, height_tiles));
+
+    let mut queue = TileQueue::default();
+    for tile in tiles {
+        let priority = options.tile_priority(tile.x, tile.y);
+        queue.push(tile, priority);
+    }
+    let mut high_left = queue.high.len();
+    let mut panorama = DynamicImage::new_rgb8(width_tiles * TILE_WIDTH, height_tiles * TILE_HEIGHT);
+
+    let mut in_flight = FuturesUnordered::new();
+    loop {
+        while in_flight.len() < options.concurrency.max(1) {
+            let Some((tile, priority)) = queue.pop() else { break };
+            in_flight.push(async move { (priority, fetch_tile_with_retry(http, &tile, zoom).await) });
+        }
+        let Some((priority, tile)) = in_flight.next().await else { break };
+        let tile = tile?;
+        panorama.copy_from(&tile.image, tile.x * TILE_WIDTH, tile.y * TILE_HEIGHT)?;
+
+        if priority == TilePriority::High {
+            high_left -= 1;
+            if let Some(callback) = options.on_priority_ready.as_ref().filter(|_| high_left == 0) {
+                callback(&panorama);
+            }
+        }
+    }
+    Ok(panorama)
+}
+
+/// How big a panorama download will be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DownloadEstimate {
+    /// Zoom level
+    pub zoom: u8,
+    /// Number of tile requests
+    pub tiles: u32,
+    /// Width of the assembled panorama in pixels
+    pub width: u32,
+    /// Height of the assembled panorama in pixels
+    pub height: u32,
+    /// Pixels in the assembled panorama
+    pub pixels: u64,
+    /// Approximate bytes transferred
+    pub approx_bytes: u64,
+    /// Memory needed for the assembled RGB image
+    pub memory_bytes: u64,
+    /// Whether `approx_bytes` is based on a downloaded tile rather than a
+    /// typical tile size
+    pub probed: bool,
+}
+
+impl DownloadEstimate {
+    /// Estimate a download at `zoom` without any requests, assuming
+    /// typical tile sizes of about 40 KB.
+    ///
+    /// # Errors
+    ///
+    /// Returns `StreetViewError::ParseError` if the zoom is not 1-7.
+    pub fn for_zoom(zoom: u8) -> Result<Self> {
+        check_zoom(zoom)?;
+        let (width_tiles, height_tiles) = get_width_and_height_from_zoom(zoom);
+        let (width, height) = (width_tiles * TILE_WIDTH, height_tiles * TILE_HEIGHT);
+        let tiles = width_tiles * height_tiles;
+        let pixels = u64::from(width) * u64::from(height);
+        Ok(Self {
+            zoom,
+            tiles,
+            width,
+            height,
+            pixels,
+            approx_bytes: u64::from(tiles) * TYPICAL_TILE_BYTES,
+            memory_bytes: pixels * 3,
+            probed: false,
+        })
+    }
+}
+
+/// One zoom level of an exported tile pyramid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PyramidLevel {
+    /// Zoom level
+    pub zoom: u8,
+    /// Tile columns
+    pub cols: u32,
+    /// Tile rows
+    pub rows: u32,
+    /// Width of the level in pixels
+    pub width: u32,
+    /// Height of the level in pixels
+    pub height: u32,
+}
+
+/// A tile pyramid written by
+/// [`StreetView::export_tile_pyramid`](crate::StreetView::export_tile_pyramid).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TilePyramid {
+    /// Directory holding the pyramid
+    pub dir: PathBuf,
+    /// Levels, lowest zoom first
+    pub levels: Vec<PyramidLevel>,
+    /// Tiles downloaded by this export
+    pub downloaded: usize,
+    /// Tiles already in the directory, which were not downloaded again
+    pub existing: usize,
+}
+
+impl TilePyramid {
+    /// Path of a tile in the pyramid: `{dir}/{zoom}/{y}/{x}.jpg`.
+    pub fn tile_path(&self, zoom: u8, x: u32, y: u32) -> PathBuf {
+        pyramid_tile_path(&self.dir, zoom, x, y)
+    }
+}
+
+fn pyramid_tile_path(dir: &Path, zoom: u8, x: u32, y: u32) -> PathBuf {
+    dir.join(zoom.to_string()).join(y.to_string()).join(format!("{x}.jpg"))
+}
+
+/// Save a panorama's tiles at several zoom levels as a tile pyramid.
+///
+/// Each level is made of Google's own tiles at that zoom, saved as the
+/// JPEGs the tile server sends: nothing is decoded, assembled, resized, or
+/// re-encoded. Tiles already in the directory are kept, so an interrupted
+/// export can be resumed. A `pyramid.json` manifest lists the levels for
+/// viewers such as Marzipano or OpenSeadragon.
+pub async fn export_tile_pyramid(
+    http: &Http,
+    pano_id: &str,
+    dir: &Path,
+    zooms: RangeInclusive<u8>,
+) -> Result<TilePyramid> {
+    let mut levels = Vec::new();
+    let mut tiles = Vec::new();
+    for zoom in zooms {
+        check_zoom(zoom)?;
+        let (cols, rows) = get_width_and_height_from_zoom(zoom);
+        levels.push(PyramidLevel {
+            zoom,
+            cols,
+            rows,
+            width: cols * TILE_WIDTH,
+            height: rows * TILE_HEIGHT,
+        });
+        let level_tiles = iter_tile_info(http.endpoints(), pano_id, zoom);
+        tiles.extend(level_tiles.into_iter().map(|tile| (zoom, tile)));
+    }
+
+    let (existing, missing): (Vec<_>, Vec<_>) = tiles
+        .into_iter()
+        .partition(|(zoom, tile)| pyramid_tile_path(dir, *zoom, tile.x, tile.y).is_file());
+    let downloaded = missing.len();
+    stream::iter(missing)
+        .map(|(zoom, tile)| async move {
+            // Check the body is an image so error pages are never saved
+            let bytes = fetch_tile_data(http, &tile, zoom, |bytes| {
+                image::guess_format(bytes)?;
+                Ok(bytes.to_vec())
+            })
+            .await?;
+            let path = pyramid_tile_path(dir, zoom, tile.x, tile.y);
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            let tmp = path.with_extension("jpg.part");
+            tokio::fs::write(&tmp, bytes).await?;
+            tokio::fs::rename(&tmp, &path).await?;
+            Ok(())
+        })
+        .buffer_unordered(CONCURRENT_DOWNLOADS)
+        .collect::<Vec<Result<()>>>()
+        .await
+        .into_iter()
+        .collect::<Result<()>>()?;
+
+    let manifest = serde_json::json!({
+        "pano_id": pano_id,
+        "tile_size": TILE_WIDTH,
+        "path": "{z}/{y}/{x}.jpg",
+        "levels": levels
+            .iter()
+            .map(|level| serde_json::json!({
+                "zoom": level.zoom,
+                "cols": level.cols,
+                "rows": level.rows,
+                "width": level.width,
+                "height": level.height,
+            }))
+            .collect::<Vec<_>>(),
+    });
+    tokio::fs::create_dir_all(dir).await?;
+    tokio::fs::write(dir.join("pyramid.json"), manifest.to_string()).await?;
+
+    Ok(TilePyramid {
+        dir: dir.to_path_buf(),
+        levels,
+        downloaded,
+        existing: existing.len(),
+    })
+}
+
+/// Limits the memory held by panoramas downloading at once.
+///
+/// Each download reserves its expected peak memory before it starts and
+/// releases it when done, so a batch waits instead of assembling more
+/// panoramas than fit. Reservations are counted in KiB so that budgets up
+/// to terabytes fit in a semaphore.
+#[derive(Debug)]
+pub(crate) struct MemoryBudget {
+    semaphore: Semaphore,
+    kib: u32,
+}
+
+impl MemoryBudget {
+    pub(crate) fn new(max_bytes: u64) -> Self {
+        let kib = (max_bytes / 1024).clamp(1, u64::from(u32::MAX) >> 3) as u32;
+        Self { semaphore: Semaphore::new(kib as usize), kib }
+    }
+
+    /// Expected peak memory of a download at `zoom`: the assembled RGB
+    /// image plus the decoded tiles it is assembled from.
+    pub(crate) fn cost(zoom: u8) -> u64 {
+        DownloadEstimate::for_zoom(zoom).map_or(0, |estimate| estimate.memory_bytes * 2)
+    }
+
+    /// Wait until `bytes` are free and reserve them until the permit is
+    /// dropped. A reservation larger than the whole budget waits for the
+    /// whole budget, so it runs alone rather than never.
+    pub(crate) async fn reserve(&self, bytes: u64) -> SemaphorePermit<'_> {
+        let kib = bytes.div_ceil(1024).clamp(1, u64::from(self.kib)) as u32;
+        self.semaphore
+            .acquire_many(kib)
+            .await
+            .expect("the budget's semaphore is never closed")
+    }
+}
+
+/// Estimate a download by fetching one tile from the panorama's horizon
+/// row, where tiles carry the most detail, and scaling its size up.
+///
+/// The probed tile goes through the response cache like any other tile.
+pub async fn estimate_download(http: &Http, pano_id: &str, zoom: u8) -> Result<DownloadEstimate> {
+    let mut estimate = DownloadEstimate::for_zoom(zoom)?;
+    let (_, height_tiles) = get_width_and_height_from_zoom(zoom);
+    let url = make_download_url(http.endpoints(), pano_id, zoom, 0, height_tiles / 2);
+    let tile_bytes = http.fetch(&url, false, |bytes| Ok(bytes.len() as u64)).await?;
+
+    estimate.approx_bytes = tile_bytes * u64::from(estimate.tiles);
+    estimate.probed = true;
+    Ok(estimate)
+}
+
+/// Find the highest zoom level the tile server has for a panorama by
+/// requesting the top-left tile at each zoom, from 7 down, until one
+/// exists.
+///
+/// Probed tiles go through the response cache like any other tile.
+pub async fn probe_max_zoom(http: &Http, pano_id: &str) -> Result<u8> {
+    for zoom in (1..=7).rev() {
+        let url = make_download_url(http.endpoints(), pano_id, zoom, 0, 0);
+        match http.fetch(&url, false, |_| Ok(())).await {
+            Ok(()) => return Ok(zoom),
+            // The tile server answers 400 for tiles beyond the panorama's resolution
+            Err(StreetViewError::ApiError { status: 400 | 404, .. }) => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(0)
+}
+
+/// A tile that could not be downloaded.
+#[derive(Debug)]
+pub struct MissingTile {
+    /// Tile column
+    pub x: u32,
+    /// Tile row
+    pub y: u32,
+    /// Why the last attempt failed
+    pub error: StreetViewError,
+}
+
+/// A panorama assembled from the tiles that could be downloaded.
+#[derive(Debug)]
+pub struct PartialPanorama {
+    /// The panorama, with missing tiles left black
+    pub image: DynamicImage,
+    /// Tiles that failed after all retries, in row order
+    pub missing_tiles: Vec<MissingTile>,
+}
+
+impl PartialPanorama {
+    /// Whether every tile was downloaded.
+    pub fn is_complete(&self) -> bool {
+        self.missing_tiles.is_empty()
+    }
+
+    /// Paint the areas of missing tiles with `color`.
+    pub fn fill_missing(&mut self, color: image::Rgb<u8>) {
+        let (width, height) = self.image.dimensions();
+        let color = image::Rgba([color[0], color[1], color[2], 255]);
+        for tile in &self.missing_tiles {
+            let (x0, y0) = (tile.x * TILE_WIDTH, tile.y * TILE_HEIGHT);
+            for y in y0..(y0 + TILE_HEIGHT).min(height) {
+                for x in x0..(x0 + TILE_WIDTH).min(width) {
+                    self.image.put_pixel(x, y, color);
+                }
+            }
+        }
+    }
+}
+
+/// Download a panorama, keeping whatever tiles succeed.
+///
+/// Each tile is retried as in [`download_panorama`]; tiles that still fail
+/// are reported in `missing_tiles` instead of failing the whole download.
+/// Fails only if no tile at all could be downloaded, with the first
+/// tile's error.
+pub async fn download_panorama_partial(http: &Http, pano_id: &str, zoom: u8) -> Result<PartialPanorama> {
+    check_zoom(zoom)?;
+
+    let results = fetch_all_tiles(http, pano_id, zoom);
+    #[cfg(feature = "tracing")]
+    let results = tracing::Instrument::instrument(results, tracing::info_span!("download_panorama", pano_id, zoom));
+    let results = results.await;
+
+    let mut tiles = Vec::new();
+    let mut missing_tiles = Vec::new();
+    for (x, y, tile) in results {
+        match tile {
+            Ok(tile) => tiles.push(tile),
+            Err(error) => missing_tiles.push(MissingTile { x, y, error }),
+        }
+    }
+    missing_tiles.sort_by_key(|tile| (tile.y, tile.x));
+    if tiles.is_empty() && !missing_tiles.is_empty() {
+        return Err(missing_tiles.swap_remove(0).error);
+    }
+
+    Ok(PartialPanorama {
+        image: assemble_tiles(tiles, zoom)?,
+        missing_tiles,
+    })
+}
+
+/// A panorama's tiles, kept as the JPEGs the tile server sent.
+///
+/// Nothing is decoded up front: [`crop`](Self::crop) and
+/// [`extract_view`](Self::extract_view) decode only the tiles they cover,
+/// and [`save_tiles`](Self::save_tiles) writes the JPEGs as they are. The
+/// full image is only built if [`assemble`](Self::assemble) is called.
+#[derive(Clone)]
+pub struct PanoramaTiles {
+    pano_id: String,
+    zoom: u8,
+    cols: u32,
+    rows: u32,
+    /// Encoded tiles in row order
+    tiles: Vec<Vec<u8>>,
+}
+
+impl PanoramaTiles {
+    /// The panorama ID.
+    pub fn pano_id(&self) -> &str {
+        &self.pano_id
+    }
+
+    /// The zoom level the tiles were downloaded at.
+    pub fn zoom(&self) -> u8 {
+        self.zoom
+    }
+
+    /// Tile columns and rows.
+    pub fn grid(&self) -> (u32, u32) {
+        (self.cols, self.rows)
+    }
+
+    /// Width and height of the assembled panorama in pixels.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.cols * TILE_WIDTH, self.rows * TILE_HEIGHT)
+    }
+
+    /// The encoded tile at column `x`, row `y`.
+    pub fn tile_bytes(&self, x: u32, y: u32) -> Option<&[u8]> {
+        (x < self.cols && y < self.rows).then(|| self.tiles[(y * self.cols + x) as usize].as_slice())
+    }
+
+    /// Decode the tile at column `x`, row `y`.
+    pub fn decode_tile(&self, x: u32, y: u32) -> Result<DynamicImage> {
+        let bytes = self
+            .tile_bytes(x, y)
+            .ok_or_else(|| StreetViewError::ParseError(format!("No tile at ({x}, {y})")))?;
+        Ok(image::load_from_memory(bytes)?)
+    }
+
+    /// Decode every tile and assemble the full panorama.
+    pub fn assemble(&self) -> Result<DynamicImage> {
+        let (width, height) = self.dimensions();
+        self.crop(0, 0, width, height)
+    }
+
+    /// Crop a region of the panorama, decoding only the tiles it covers.
+    ///
+    /// The region is clipped to the panorama's bounds.
+    pub fn crop(&self, x: u32, y: u32, width: u32, height: u32) -> Result<DynamicImage> {
+        let (pano_width, pano_height) = self.dimensions();
+        let (x, y) = (x.min(pano_width), y.min(pano_height));
+        let (width, height) = (width.min(pano_width - x), height.min(pano_height - y));
+
+        let mut image = DynamicImage::new_rgb8(width, height);
+        if width == 0 || height == 0 {
+            return Ok(image);
+        }
+        for tile_y in y / TILE_HEIGHT..=(y + height - 1) / TILE_HEIGHT {
+            for tile_x in x / TILE_WIDTH..=(x + width - 1) / TILE_WIDTH {
+                let tile = self.decode_tile(tile_x, tile_y)?;
+                let (tile_left, tile_top) = (tile_x * TILE_WIDTH, tile_y * TILE_HEIGHT);
+                // Overlap of the tile and the region, in panorama pixels
+                let (left, top) = (x.max(tile_left), y.max(tile_top));
+                let right = (x + width).min(tile_left + TILE_WIDTH);
+                let bottom = (y + height).min(tile_top + TILE_HEIGHT);
+                let part = tile.view(left - tile_left, top - tile_top, right - left, bottom - top);
+                image.copy_from(&*part, left - x, top - y)?;
+            }
+        }
+        Ok(image)
+    }
+
+    /// Extract a view, as [`extract_view_from_panorama`](crate::views::extract_view_from_panorama)
+    /// would from the assembled panorama, decoding only the tiles it covers.
+    ///
+    /// The tiles' zoom is used; `config.zoom` is ignored.
+    pub fn extract_view(&self, config: &ViewConfig) -> Result<DynamicImage> {
+        let (width, height) = self.dimensions();
+        let (x, y, crop_width, crop_height) = crop_region(config, width, height);
+        Ok(resize_view(self.crop(x, y, crop_width, crop_height)?, config))
+    }
+
+    /// Write every tile to `dir` as `{zoom}/{y}/{x}.jpg`, without
+    /// re-encoding, and return the paths in row order.
+    ///
+    /// This is the layout of [`TilePyramid`], so tiles saved at several
+    /// zooms into one directory form a pyramid.
+    pub fn save_tiles(&self, dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+        let dir = dir.as_ref();
+        let mut paths = Vec::with_capacity(self.tiles.len());
+        for y in 0..self.rows {
+            for x in 0..self.cols {
+                let path = pyramid_tile_path(dir, self.zoom, x, y);
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&path, &self.tiles[(y * self.cols + x) as usize])?;
+                paths.push(path);
+            }
+        }
+        Ok(paths)
+    }
+}
+
+impl fmt::Debug for PanoramaTiles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PanoramaTiles")
+            .field("pano_id", &self.pano_id)
+            .field("zoom", &self.zoom)
+            .field("cols", &self.cols)
+            .field("rows", &self.rows)
+            .field("bytes", &self.tiles.iter().map(Vec::len).sum::<usize>())
+            .finish()
+    }
+}
+
+/// Download a panorama's tiles without decoding or assembling them.
+pub async fn fetch_panorama_tiles(http: &Http, pano_id: &str, zoom: u8) -> Result<PanoramaTiles> {
+    check_zoom(zoom)?;
+    let (cols, rows) = get_width_and_height_from_zoom(zoom);
+
+    let tile_infos = iter_tile_info(http.endpoints(), pano_id, zoom);
+    let fetched: Vec<(TileInfo, Result<Vec<u8>>)> = stream::iter(tile_infos)
+        .map(|tile_info| async move {
+            // Check the body is an image so error pages fail here, not on decode
+            let bytes = fetch_tile_data(http, &tile_info, zoom, |bytes| {
+                image::guess_format(bytes)?;
+                Ok(bytes.to_vec())
+            })
+            .await;
+            (tile_info, bytes)
+        })
+        .buffer_unordered(CONCURRENT_DOWNLOADS)
+        .collect()
+        .await;
+
+    let mut tiles = vec![Vec::new(); (cols * rows) as usize];
+    for (tile_info, bytes) in fetched {
+        tiles[(tile_info.y * cols + tile_info.x) as usize] = bytes?;
+    }
+    Ok(PanoramaTiles {
+        pano_id: pano_id.to_string(),
+        zoom,
+        cols,
+        rows,
+        tiles,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::endpoints::TILE_ENDPOINT;
+
+    #[test]
+    fn test_tile_queue() {
+        let options = DownloadOptions::new()
+            .zoom(2)
+            .prioritize_view(ViewConfig::new(0).fov(60))
+            .prioritize_tile(2, 1);
+        let mut queue = TileQueue::default();
+        for tile in iter_tile_info(&Endpoints::new(), "pano", 2) {
+            let priority = options.tile_priority(tile.x, tile.y);
+            queue.push(tile, priority);
+        }
+        // A view at heading 0 crops from the left edge
+        let order: Vec<(u32, u32, TilePriority)> = std::iter::from_fn(|| queue.pop())
+            .map(|(tile, priority)| (tile.x, tile.y, priority))
+            .collect();
+        assert_eq!(&order[..3], [(0, 0, TilePriority::High), (0, 1, TilePriority::High), (2, 1, TilePriority::High)]);
+        assert!(order[3..].iter().all(|&(_, _, priority)| priority == TilePriority::Low));
+        assert_eq!(order.len(), 8);
+    }
+
+    #[test]
+    fn test_tile_order() {
+        let order = |order: TileOrder, cols: u32, rows: u32| {
+            let mut tiles: Vec<(u32, u32)> = (0..rows).flat_map(|y| (0..cols).map(move |x| (x, y))).collect();
+            tiles.sort_by_key(|&(x, y)| order.index(x, y, cols, rows));
+            tiles
+        };
+        assert_eq!(order(TileOrder::RowMajor, 3, 2), [(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1)]);
+        assert_eq!(order(TileOrder::ColumnMajor, 3, 2), [(0, 0), (0, 1), (1, 0), (1, 1), (2, 0), (2, 1)]);
+
+        // Every step of a Hilbert curve moves to a neighbouring tile, including
+        // on the 2:1 grids of panorama zoom levels
+        for (cols, rows) in [(4, 4), (2, 1), (8, 4), (16, 8), (4, 8)] {
+            let tiles = order(TileOrder::Hilbert, cols, rows);
+            assert_eq!(tiles.len() as u32, cols * rows);
+            for pair in tiles.windows(2) {
+                let (a, b) = (pair[0], pair[1]);
+                assert_eq!(a.0.abs_diff(b.0) + a.1.abs_diff(b.1), 1, "{a:?} -> {b:?} in {cols}x{rows}");
+            }
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_memory_budget() {
+        use std::time::Duration;
+
+        // Zoom 3 needs about 50 MB, zoom 4 about 200 MB
+        let budget = MemoryBudget::new(120_000_000);
+        let first = budget.reserve(MemoryBudget::cost(3)).await;
+        let second = budget.reserve(MemoryBudget::cost(3)).await;
+        let third = tokio::time::timeout(Duration::from_secs(1), budget.reserve(MemoryBudget::cost(3)));
+        assert!(third.await.is_err());
+
+        drop((first, second));
+        // Larger than the budget: runs once everything else is done
+        let _large = budget.reserve(MemoryBudget::cost(4)).await;
+        assert_eq!(budget.semaphore.available_permits(), 0);
+    }
+
+    #[test]
+    fn test_download_estimate() {
+        let estimate = DownloadEstimate::for_zoom(7).unwrap();
+        assert_eq!((estimate.width, estimate.height, estimate.tiles), (65536, 32768, 8192));
+        assert_eq!(estimate.pixels, 65536 * 32768);
+        assert_eq!(estimate.memory_bytes, 65536 * 32768 * 3);
+        assert!(!estimate.probed);
+        assert!(DownloadEstimate::for_zoom(0).is_err());
+    }
 
     #[test]
     fn test_zoom_dimensions() {
@@ -180,16 +1073,20 @@ mod tests {
 
     #[test]
     fn test_make_download_url() {
-        let url = make_download_url("test_pano_id", 3, 5, 2);
+        let url = make_download_url(&Endpoints::new(), "test_pano_id", 3, 5, 2);
+        assert!(url.starts_with(TILE_ENDPOINT));
         assert!(url.contains("panoid=test_pano_id"));
         assert!(url.contains("zoom=3"));
         assert!(url.contains("x=5"));
         assert!(url.contains("y=2"));
+
+        let url = make_download_url(&Endpoints::new().google_tile_shards(), "test_pano_id", 3, 5, 2);
+        assert!(url.starts_with("https://cbk3.google.com/cbk?"), "{url}");
     }
 
     #[test]
     fn test_iter_tile_info() {
-        let tiles = iter_tile_info("test", 2);
+        let tiles = iter_tile_info(&Endpoints::new(), "test", 2);
         assert_eq!(tiles.len(), 8); // 4x2 = 8 tiles
 
         // Check first and last tiles
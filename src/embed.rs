@@ -0,0 +1,651 @@
+//! EXIF and XMP (GPano) metadata embedding.
+//!
+//! The image crate's encoders don't write EXIF or XMP, so the payloads are
+//! built here and spliced into the encoded stream as it is written:
+//!
+//! - JPEG: `APP1` segments right after the SOI marker
+//! - PNG: `eXIf` and `iTXt` chunks right after `IHDR`
+//! - WebP: a `VP8X` header chunk plus trailing `EXIF` / `XMP ` chunks
+
+use crate::types::{CaptureDate, ImageFormat, Panorama};
+use std::io::{self, Write};
+
+/// Custom XMP namespace for fields GPano has no slot for.
+pub(crate) const RSV_NAMESPACE: &str = "https://github.com/TennisBowling/rsstreetview/ns/1.0/";
+
+const XMP_JPEG_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const EXIF_JPEG_HEADER: &[u8] = b"Exif\0\0";
+
+// TIFF field types
+const TYPE_BYTE: u16 = 1;
+const TYPE_ASCII: u16 = 2;
+const TYPE_LONG: u16 = 4;
+const TYPE_RATIONAL: u16 = 5;
+
+/// Metadata to embed into a saved panorama.
+pub(crate) struct EmbeddedMetadata {
+    exif: Vec<u8>,
+    xmp: String,
+}
+
+impl EmbeddedMetadata {
+    /// Build EXIF and XMP payloads for a panorama image of the given size.
+    pub(crate) fn new(pano: &Panorama, width: u32, height: u32) -> Self {
+        Self {
+            exif: build_exif(pano),
+            xmp: build_xmp(pano, width, height),
+        }
+    }
+
+    /// Wrap `writer` so the metadata is spliced into the encoded stream.
+    pub(crate) fn writer<W: Write>(
+        &self,
+        format: ImageFormat,
+        width: u32,
+        height: u32,
+        has_alpha: bool,
+        writer: W,
+    ) -> SpliceWriter<W> {
+        match format {
+            ImageFormat::Jpeg => {
+                let mut insert = Vec::new();
+                push_jpeg_app1(&mut insert, EXIF_JPEG_HEADER, &self.exif);
+                push_jpeg_app1(&mut insert, XMP_JPEG_HEADER, self.xmp.as_bytes());
+                // SOI marker
+                SpliceWriter::new(writer, 2, insert, Vec::new())
+            }
+            ImageFormat::Png => {
+                let mut insert = Vec::new();
+                push_png_chunk(&mut insert, b"eXIf", &self.exif);
+                let mut itxt = b"XML:com.adobe.xmp\0\0\0\0\0".to_vec();
+                itxt.extend_from_slice(self.xmp.as_bytes());
+                push_png_chunk(&mut insert, b"iTXt", &itxt);
+                // Signature (8) + IHDR chunk (25)
+                SpliceWriter::new(writer, 33, insert, Vec::new())
+            }
+            ImageFormat::WebP => {
+                let mut vp8x = Vec::with_capacity(10);
+                let mut flags = 0x08 | 0x04; // EXIF | XMP
+                if has_alpha {
+                    flags |= 0x10;
+                }
+                vp8x.push(flags);
+                vp8x.extend_from_slice(&[0, 0, 0]);
+                vp8x.extend_from_slice(&(width.saturating_sub(1)).to_le_bytes()[..3]);
+                vp8x.extend_from_slice(&(height.saturating_sub(1)).to_le_bytes()[..3]);
+
+                let mut insert = Vec::new();
+                push_riff_chunk(&mut insert, b"VP8X", &vp8x);
+                let mut trailer = Vec::new();
+                push_riff_chunk(&mut trailer, b"EXIF", &self.exif);
+                push_riff_chunk(&mut trailer, b"XMP ", self.xmp.as_bytes());
+
+                let extra = (insert.len() + trailer.len()) as u32;
+                // "RIFF" + size + "WEBP": the size field grows by the added chunks
+                SpliceWriter::new(writer, 12, insert, trailer).with_riff_growth(extra)
+            }
+        }
+    }
+}
+
+/// `Write` adapter that inserts bytes after a fixed-size header and appends
+/// a trailer when finished.
+pub(crate) struct SpliceWriter<W: Write> {
+    inner: W,
+    header: Vec<u8>,
+    header_len: usize,
+    insert: Vec<u8>,
+    trailer: Vec<u8>,
+    riff_growth: Option<u32>,
+}
+
+impl<W: Write> SpliceWriter<W> {
+    fn new(inner: W, header_len: usize, insert: Vec<u8>, trailer: Vec<u8>) -> Self {
+        Self {
+            inner,
+            header: Vec::with_capacity(header_len),
+            header_len,
+            insert,
+            trailer,
+            riff_growth: None,
+        }
+    }
+
+    fn with_riff_growth(mut self, growth: u32) -> Self {
+        self.riff_growth = Some(growth);
+        self
+    }
+
+    /// Write the trailer and flush. Must be called after encoding.
+    pub(crate) fn finish(mut self) -> io::Result<W> {
+        if self.header.len() < self.header_len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "encoded image shorter than its container header",
+            ));
+        }
+        self.inner.write_all(&self.trailer)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    fn emit_header(&mut self) -> io::Result<()> {
+        if let Some(growth) = self.riff_growth {
+            let size = u32::from_le_bytes([self.header[4], self.header[5], self.header[6], self.header[7]]);
+            self.header[4..8].copy_from_slice(&(size + growth).to_le_bytes());
+        }
+        self.inner.write_all(&self.header)?;
+        self.inner.write_all(&self.insert)
+    }
+}
+
+impl<W: Write> Write for SpliceWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let missing = self.header_len - self.header.len();
+        if missing == 0 {
+            return self.inner.write(data);
+        }
+
+        let take = missing.min(data.len());
+        self.header.extend_from_slice(&data[..take]);
+        if self.header.len() == self.header_len {
+            self.emit_header()?;
+        }
+        Ok(take)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn push_jpeg_app1(out: &mut Vec<u8>, header: &[u8], payload: &[u8]) {
+    let len = 2 + header.len() + payload.len();
+    if len > u16::MAX as usize {
+        // Segment would overflow; skip rather than corrupt the file
+        return;
+    }
+    out.extend_from_slice(&[0xFF, 0xE1]);
+    out.extend_from_slice(&(len as u16).to_be_bytes());
+    out.extend_from_slice(header);
+    out.extend_from_slice(payload);
+}
+
+fn push_png_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let crc_start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[crc_start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn push_riff_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(kind);
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        out.push(0);
+    }
+}
+
+/// CRC-32 (IEEE) as used by PNG chunks.
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    !crc32_update(0xFFFF_FFFF, data)
+}
+
+/// Feed `data` into a running CRC-32 register (not inverted).
+pub(crate) fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    crc
+}
+
+/// Convert a capture date to an EXIF timestamp.
+fn exif_datetime(date: CaptureDate) -> String {
+    format!("{:04}:{:02}:01 00:00:00", date.year, date.month)
+}
+
+struct IfdEntry {
+    tag: u16,
+    kind: u16,
+    count: u32,
+    data: Vec<u8>,
+}
+
+impl IfdEntry {
+    fn ascii(tag: u16, text: &str) -> Self {
+        let mut data = text.as_bytes().to_vec();
+        data.push(0);
+        Self { tag, kind: TYPE_ASCII, count: data.len() as u32, data }
+    }
+
+    fn bytes(tag: u16, bytes: &[u8]) -> Self {
+        Self { tag, kind: TYPE_BYTE, count: bytes.len() as u32, data: bytes.to_vec() }
+    }
+
+    fn long(tag: u16, value: u32) -> Self {
+        Self { tag, kind: TYPE_LONG, count: 1, data: value.to_be_bytes().to_vec() }
+    }
+
+    fn rationals(tag: u16, values: &[(u32, u32)]) -> Self {
+        let mut data = Vec::with_capacity(values.len() * 8);
+        for (num, den) in values {
+            data.extend_from_slice(&num.to_be_bytes());
+            data.extend_from_slice(&den.to_be_bytes());
+        }
+        Self { tag, kind: TYPE_RATIONAL, count: values.len() as u32, data }
+    }
+}
+
+fn ifd_size(entries: &[IfdEntry]) -> usize {
+    let overflow: usize = entries
+        .iter()
+        .filter(|e| e.data.len() > 4)
+        .map(|e| e.data.len() + e.data.len() % 2)
+        .sum();
+    2 + entries.len() * 12 + 4 + overflow
+}
+
+/// Append an IFD to a TIFF buffer. Offsets are relative to the buffer start.
+fn write_ifd(out: &mut Vec<u8>, entries: &[IfdEntry]) {
+    let start = out.len();
+    let mut data_offset = start + 2 + entries.len() * 12 + 4;
+    let mut overflow = Vec::new();
+
+    out.extend_from_slice(&(entries.len() as u16).to_be_bytes());
+    for entry in entries {
+        out.extend_from_slice(&entry.tag.to_be_bytes());
+        out.extend_from_slice(&entry.kind.to_be_bytes());
+        out.extend_from_slice(&entry.count.to_be_bytes());
+        if entry.data.len() <= 4 {
+            let mut value = [0u8; 4];
+            value[..entry.data.len()].copy_from_slice(&entry.data);
+            out.extend_from_slice(&value);
+        } else {
+            out.extend_from_slice(&(data_offset as u32).to_be_bytes());
+            overflow.extend_from_slice(&entry.data);
+            if entry.data.len() % 2 == 1 {
+                overflow.push(0);
+            }
+            data_offset += entry.data.len() + entry.data.len() % 2;
+        }
+    }
+    // No next IFD
+    out.extend_from_slice(&0u32.to_be_bytes());
+    out.extend_from_slice(&overflow);
+}
+
+fn dms(value: f64) -> [(u32, u32); 3] {
+    let value = value.abs();
+    let degrees = value.trunc();
+    let minutes = ((value - degrees) * 60.0).trunc();
+    let seconds = ((value - degrees) * 60.0 - minutes) * 60.0;
+    [
+        (degrees as u32, 1),
+        (minutes as u32, 1),
+        ((seconds * 10_000.0).round() as u32, 10_000),
+    ]
+}
+
+/// Build a big-endian TIFF/EXIF block with GPS position and capture date.
+pub(crate) fn build_exif(pano: &Panorama) -> Vec<u8> {
+    let mut gps = vec![
+        IfdEntry::bytes(0x0000, &[2, 3, 0, 0]),
+        IfdEntry::ascii(0x0001, if pano.lat >= 0.0 { "N" } else { "S" }),
+        IfdEntry::rationals(0x0002, &dms(pano.lat)),
+        IfdEntry::ascii(0x0003, if pano.lon >= 0.0 { "E" } else { "W" }),
+        IfdEntry::rationals(0x0004, &dms(pano.lon)),
+    ];
+    if let Some(elevation) = pano.elevation {
+        gps.push(IfdEntry::bytes(0x0005, &[u8::from(elevation < 0.0)]));
+        gps.push(IfdEntry::rationals(0x0006, &[((elevation.abs() * 100.0).round() as u32, 100)]));
+    }
+    gps.push(IfdEntry::ascii(0x0010, "T"));
+    gps.push(IfdEntry::rationals(0x0011, &[((pano.compass_heading() * 100.0).round() as u32, 100)]));
+
+    let datetime = pano.date.map(exif_datetime);
+    let exif_ifd: Vec<IfdEntry> = datetime
+        .iter()
+        .map(|dt| IfdEntry::ascii(0x9003, dt))
+        .collect();
+
+    // IFD0 layout is fixed so the sub-IFD offsets can be computed up front
+    let mut ifd0_len = 1; // GPS pointer
+    if datetime.is_some() {
+        ifd0_len += 2; // DateTime + Exif pointer
+    }
+    let ifd0_size = 2 + ifd0_len * 12 + 4 + if datetime.is_some() { 20 } else { 0 };
+    let exif_offset = 8 + ifd0_size;
+    let gps_offset = exif_offset + if exif_ifd.is_empty() { 0 } else { ifd_size(&exif_ifd) };
+
+    let mut ifd0 = Vec::new();
+    if let Some(dt) = &datetime {
+        ifd0.push(IfdEntry::ascii(0x0132, dt));
+        ifd0.push(IfdEntry::long(0x8769, exif_offset as u32));
+    }
+    ifd0.push(IfdEntry::long(0x8825, gps_offset as u32));
+
+    let mut out = b"MM\0\x2A\0\0\0\x08".to_vec();
+    write_ifd(&mut out, &ifd0);
+    if !exif_ifd.is_empty() {
+        write_ifd(&mut out, &exif_ifd);
+    }
+    write_ifd(&mut out, &gps);
+    out
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Build an XMP packet with GPano tags for an equirectangular image.
+///
+/// Panoramas cropped with `crop_black_borders` lose rows at the bottom, so
+/// the full pano height is derived from the width (2:1) and the image is
+/// described as a cropped area anchored at the top-left.
+pub(crate) fn build_xmp(pano: &Panorama, width: u32, height: u32) -> String {
+    let full_height = (width / 2).max(height);
+    let mut extra = String::new();
+    if let Some(date) = &pano.date {
+        extra.push_str(&format!("   <rsv:Date>{date}</rsv:Date>\n"));
+    }
+
+    format!(
+        "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n\
+<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n\
+ <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n\
+  <rdf:Description rdf:about=\"\"\n\
+    xmlns:GPano=\"http://ns.google.com/photos/1.0/panorama/\"\n\
+    xmlns:rsv=\"{RSV_NAMESPACE}\">\n\
+   <GPano:ProjectionType>equirectangular</GPano:ProjectionType>\n\
+   <GPano:UsePanoramaViewer>True</GPano:UsePanoramaViewer>\n\
+   <GPano:FullPanoWidthPixels>{width}</GPano:FullPanoWidthPixels>\n\
+   <GPano:FullPanoHeightPixels>{full_height}</GPano:FullPanoHeightPixels>\n\
+   <GPano:CroppedAreaImageWidthPixels>{width}</GPano:CroppedAreaImageWidthPixels>\n\
+   <GPano:CroppedAreaImageHeightPixels>{height}</GPano:CroppedAreaImageHeightPixels>\n\
+   <GPano:CroppedAreaLeftPixels>0</GPano:CroppedAreaLeftPixels>\n\
+   <GPano:CroppedAreaTopPixels>0</GPano:CroppedAreaTopPixels>\n\
+   <GPano:PoseHeadingDegrees>{heading:.2}</GPano:PoseHeadingDegrees>\n\
+   <rsv:PanoId>{pano_id}</rsv:PanoId>\n\
+   <rsv:Lat>{lat}</rsv:Lat>\n\
+   <rsv:Lon>{lon}</rsv:Lon>\n\
+{extra}  </rdf:Description>\n\
+ </rdf:RDF>\n\
+</x:xmpmeta>\n\
+<?xpacket end=\"w\"?>",
+        heading = pano.compass_heading(),
+        pano_id = escape_xml(&pano.pano_id),
+        lat = pano.lat,
+        lon = pano.lon,
+    )
+}
+
+/// Fields recovered from an embedded XMP packet.
+#[derive(Debug, Default)]
+pub(crate) struct ParsedXmp {
+    pub pano_id: Option<String>,
+    pub lat: Option<f64>,
+    pub lon: Option<f64>,
+    pub date: Option<String>,
+    pub heading: Option<f64>,
+    pub projection: Option<String>,
+}
+
+/// Fields recovered from an embedded EXIF block.
+#[derive(Debug, Default)]
+pub(crate) struct ParsedExif {
+    pub lat: Option<f64>,
+    pub lon: Option<f64>,
+    pub date: Option<String>,
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&amp;", "&")
+}
+
+fn xmp_tag(xmp: &str, tag: &str) -> Option<String> {
+    let open = format!("<{tag}>");
+    let close = format!("</{tag}>");
+    let start = xmp.find(&open)? + open.len();
+    let end = start + xmp[start..].find(&close)?;
+    Some(unescape_xml(xmp[start..end].trim()))
+}
+
+/// Find and parse the XMP packet in an encoded image file.
+pub(crate) fn parse_xmp(file: &[u8]) -> Option<ParsedXmp> {
+    let start = find_bytes(file, b"<x:xmpmeta")?;
+    let end = start + find_bytes(&file[start..], b"</x:xmpmeta>")?;
+    let xmp = std::str::from_utf8(&file[start..end]).ok()?;
+
+    Some(ParsedXmp {
+        pano_id: xmp_tag(xmp, "rsv:PanoId"),
+        lat: xmp_tag(xmp, "rsv:Lat").and_then(|v| v.parse().ok()),
+        lon: xmp_tag(xmp, "rsv:Lon").and_then(|v| v.parse().ok()),
+        date: xmp_tag(xmp, "rsv:Date"),
+        heading: xmp_tag(xmp, "GPano:PoseHeadingDegrees").and_then(|v| v.parse().ok()),
+        projection: xmp_tag(xmp, "GPano:ProjectionType"),
+    })
+}
+
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Minimal TIFF reader for the tags this crate writes.
+struct TiffReader<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> TiffReader<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        let big_endian = match data.get(..4)? {
+            b"MM\0\x2A" => true,
+            b"II\x2A\0" => false,
+            _ => return None,
+        };
+        Some(Self { data, big_endian })
+    }
+
+    fn u16_at(&self, pos: usize) -> Option<u16> {
+        let b: [u8; 2] = self.data.get(pos..pos + 2)?.try_into().ok()?;
+        Some(if self.big_endian { u16::from_be_bytes(b) } else { u16::from_le_bytes(b) })
+    }
+
+    fn u32_at(&self, pos: usize) -> Option<u32> {
+        let b: [u8; 4] = self.data.get(pos..pos + 4)?.try_into().ok()?;
+        Some(if self.big_endian { u32::from_be_bytes(b) } else { u32::from_le_bytes(b) })
+    }
+
+    /// Returns `(tag, type, count, value_pos)` for each entry of the IFD at `offset`.
+    fn entries(&self, offset: usize) -> Vec<(u16, u16, u32, usize)> {
+        let count = self.u16_at(offset).unwrap_or(0) as usize;
+        (0..count)
+            .filter_map(|i| {
+                let pos = offset + 2 + i * 12;
+                Some((self.u16_at(pos)?, self.u16_at(pos + 2)?, self.u32_at(pos + 4)?, pos + 8))
+            })
+            .collect()
+    }
+
+    /// Position of an entry's data: inline when it fits in 4 bytes.
+    fn data_pos(&self, kind: u16, count: u32, value_pos: usize) -> Option<usize> {
+        let unit = match kind {
+            TYPE_BYTE | TYPE_ASCII => 1,
+            TYPE_LONG => 4,
+            TYPE_RATIONAL => 8,
+            _ => return None,
+        };
+        let size = unit * count as usize;
+        if size <= 4 {
+            Some(value_pos)
+        } else {
+            Some(self.u32_at(value_pos)? as usize)
+        }
+    }
+
+    fn ascii(&self, kind: u16, count: u32, value_pos: usize) -> Option<String> {
+        let pos = self.data_pos(kind, count, value_pos)?;
+        let bytes = self.data.get(pos..pos + count as usize)?;
+        let text = std::str::from_utf8(bytes).ok()?;
+        Some(text.trim_end_matches('\0').to_string())
+    }
+
+    fn rationals(&self, kind: u16, count: u32, value_pos: usize) -> Option<Vec<f64>> {
+        let pos = self.data_pos(kind, count, value_pos)?;
+        (0..count as usize)
+            .map(|i| {
+                let num = self.u32_at(pos + i * 8)? as f64;
+                let den = self.u32_at(pos + i * 8 + 4)? as f64;
+                (den != 0.0).then_some(num / den)
+            })
+            .collect()
+    }
+}
+
+/// Find and parse the EXIF block in an encoded image file.
+pub(crate) fn parse_exif(file: &[u8]) -> Option<ParsedExif> {
+    // JPEG APP1, PNG eXIf chunk, or WebP EXIF chunk
+    let tiff_start = find_bytes(file, EXIF_JPEG_HEADER)
+        .map(|pos| pos + EXIF_JPEG_HEADER.len())
+        .or_else(|| find_bytes(file, b"eXIf").map(|pos| pos + 4))
+        .or_else(|| find_bytes(file, b"EXIF").map(|pos| pos + 8))?;
+    let tiff = TiffReader::new(&file[tiff_start..])?;
+
+    let mut parsed = ParsedExif::default();
+    let mut gps_offset = None;
+    let mut exif_offset = None;
+
+    for (tag, kind, count, value_pos) in tiff.entries(tiff.u32_at(4)? as usize) {
+        match tag {
+            0x0132 => parsed.date = tiff.ascii(kind, count, value_pos),
+            0x8769 => exif_offset = tiff.u32_at(value_pos),
+            0x8825 => gps_offset = tiff.u32_at(value_pos),
+            _ => {}
+        }
+    }
+
+    if let Some(offset) = exif_offset {
+        for (tag, kind, count, value_pos) in tiff.entries(offset as usize) {
+            if tag == 0x9003 {
+                parsed.date = tiff.ascii(kind, count, value_pos);
+            }
+        }
+    }
+
+    if let Some(offset) = gps_offset {
+        let (mut lat_ref, mut lon_ref) = (None, None);
+        let (mut lat, mut lon) = (None, None);
+        for (tag, kind, count, value_pos) in tiff.entries(offset as usize) {
+            match tag {
+                0x0001 => lat_ref = tiff.ascii(kind, count, value_pos),
+                0x0002 => lat = tiff.rationals(kind, count, value_pos),
+                0x0003 => lon_ref = tiff.ascii(kind, count, value_pos),
+                0x0004 => lon = tiff.rationals(kind, count, value_pos),
+                _ => {}
+            }
+        }
+        let to_degrees = |dms: Vec<f64>| -> Option<f64> {
+            Some(dms.first()? + dms.get(1).unwrap_or(&0.0) / 60.0 + dms.get(2).unwrap_or(&0.0) / 3600.0)
+        };
+        parsed.lat = lat.and_then(to_degrees).map(|v| if lat_ref.as_deref() == Some("S") { -v } else { v });
+        parsed.lon = lon.and_then(to_degrees).map(|v| if lon_ref.as_deref() == Some("W") { -v } else { v });
+    }
+
+    // EXIF timestamps are "YYYY:MM:DD HH:MM:SS"; capture dates are "YYYY-MM"
+    parsed.date = parsed.date.and_then(|dt| match (dt.get(0..4), dt.get(5..7)) {
+        (Some(year), Some(month)) => Some(format!("{year}-{month}")),
+        _ => None,
+    });
+
+    Some(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures::panorama;
+    use crate::types::DateConfidence;
+
+    fn test_pano() -> Panorama {
+        Panorama {
+            heading: 370.0,
+            date: CaptureDate::new(2019, 7),
+            date_confidence: DateConfidence::Exact,
+            elevation: Some(12.5),
+            ..panorama("abc", -33.5, 151.25)
+        }
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+    }
+
+    #[test]
+    fn test_exif_layout() {
+        let exif = build_exif(&test_pano());
+        assert_eq!(&exif[..8], b"MM\0\x2A\0\0\0\x08");
+
+        // IFD0: DateTime, Exif pointer, GPS pointer
+        assert_eq!(u16::from_be_bytes([exif[8], exif[9]]), 3);
+        assert_eq!(u16::from_be_bytes([exif[34], exif[35]]), 0x8825);
+        let gps_offset = u32::from_be_bytes([exif[42], exif[43], exif[44], exif[45]]) as usize;
+        // GPS IFD starts with GPSVersionID
+        assert_eq!(u16::from_be_bytes([exif[gps_offset + 2], exif[gps_offset + 3]]), 0x0000);
+        assert!(exif.windows(19).any(|w| w == b"2019:07:01 00:00:00"));
+    }
+
+    #[test]
+    fn test_xmp_contains_gpano() {
+        let xmp = build_xmp(&test_pano(), 1024, 400);
+        assert!(xmp.contains("<GPano:ProjectionType>equirectangular</GPano:ProjectionType>"));
+        assert!(xmp.contains("<GPano:FullPanoHeightPixels>512</GPano:FullPanoHeightPixels>"));
+        assert!(xmp.contains("<GPano:CroppedAreaImageHeightPixels>400</GPano:CroppedAreaImageHeightPixels>"));
+        assert!(xmp.contains("<GPano:PoseHeadingDegrees>10.00</GPano:PoseHeadingDegrees>"));
+        assert!(xmp.contains("<rsv:PanoId>abc</rsv:PanoId>"));
+    }
+
+    #[test]
+    fn test_parse_roundtrip() {
+        let pano = test_pano();
+        let mut file = b"junk".to_vec();
+        push_jpeg_app1(&mut file, EXIF_JPEG_HEADER, &build_exif(&pano));
+        push_jpeg_app1(&mut file, XMP_JPEG_HEADER, build_xmp(&pano, 1024, 512).as_bytes());
+
+        let xmp = parse_xmp(&file).unwrap();
+        assert_eq!(xmp.pano_id.as_deref(), Some("abc"));
+        assert_eq!(xmp.lat, Some(-33.5));
+        assert_eq!(xmp.date.as_deref(), Some("2019-07"));
+        assert_eq!(xmp.projection.as_deref(), Some("equirectangular"));
+
+        let exif = parse_exif(&file).unwrap();
+        assert!((exif.lat.unwrap() + 33.5).abs() < 1e-6);
+        assert!((exif.lon.unwrap() - 151.25).abs() < 1e-6);
+        assert_eq!(exif.date.as_deref(), Some("2019-07"));
+    }
+
+    #[test]
+    fn test_splice_jpeg() {
+        let meta = EmbeddedMetadata::new(&test_pano(), 4, 2);
+        let mut writer = meta.writer(ImageFormat::Jpeg, 4, 2, false, Vec::new());
+        writer.write_all(&[0xFF]).unwrap();
+        writer.write_all(&[0xD8, 0xFF, 0xDB]).unwrap();
+        let out = writer.finish().unwrap();
+
+        assert_eq!(&out[..4], &[0xFF, 0xD8, 0xFF, 0xE1]);
+        assert_eq!(&out[out.len() - 2..], &[0xFF, 0xDB]);
+    }
+}
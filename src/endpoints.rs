@@ -0,0 +1,305 @@
+use crate::error::{Result, StreetViewError};
+
+// Undocumented endpoints exist only in builds with the `unofficial`
+// feature, so official-only artifacts contain no trace of them.
+#[cfg(feature = "unofficial")]
+pub(crate) const SEARCH_ENDPOINT: &str = "https://maps.googleapis.com/maps/api/js/GeoPhotoService.SingleImageSearch";
+#[cfg(feature = "unofficial")]
+pub(crate) const TILE_ENDPOINT: &str = "https://cbk0.google.com/cbk";
+#[cfg(feature = "unofficial")]
+pub(crate) const PHOTOMETA_ENDPOINT: &str = "https://www.google.com/maps/photometa/v1";
+pub(crate) const METADATA_ENDPOINT: &str = "https://maps.googleapis.com/maps/api/streetview/metadata";
+pub(crate) const STREETVIEW_ENDPOINT: &str = "https://maps.googleapis.com/maps/api/streetview";
+pub(crate) const GEOCODE_ENDPOINT: &str = "https://maps.googleapis.com/maps/api/geocode/json";
+/// Google's tile hosts, which serve the same tiles.
+#[cfg(feature = "unofficial")]
+pub(crate) const GOOGLE_TILE_SHARDS: [&str; 4] = [
+    "https://cbk0.google.com/cbk",
+    "https://cbk1.google.com/cbk",
+    "https://cbk2.google.com/cbk",
+    "https://cbk3.google.com/cbk",
+];
+
+/// Base URLs the client sends requests to.
+///
+/// Defaults to Google's endpoints. Override them to route requests through
+/// a caching mirror or an internal proxy, or to point tests at a mock
+/// server. Query parameters are appended unchanged, so a replacement must
+/// accept the same parameters as the endpoint it stands in for.
+///
+/// The undocumented endpoints (search, tiles, photometa) are only present
+/// with the `unofficial` feature. Tiles can also be spread over several
+/// hosts (see `tile_shards`), which helps very large downloads when one
+/// host throttles.
+///
+/// Because the fields depend on the enabled features, the struct is
+/// `#[non_exhaustive]`: build it with [`new`](Self::new) and the setters
+/// rather than a struct literal, so code keeps compiling when another
+/// crate in the build turns `unofficial` on or off.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rsstreetview::{Endpoints, StreetView};
+/// # fn main() -> rsstreetview::Result<()> {
+/// let client = StreetView::builder()
+///     .endpoints(Endpoints::new().metadata("https://maps.mirror.internal/streetview/metadata"))
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Endpoints {
+    /// Panorama search (`GeoPhotoService.SingleImageSearch`)
+    #[cfg(feature = "unofficial")]
+    pub search: String,
+    /// Panorama tiles
+    #[cfg(feature = "unofficial")]
+    pub tiles: String,
+    /// Tile endpoints to spread tiles over instead of `tiles`, each tile
+    /// always going to the same one (default: none)
+    #[cfg(feature = "unofficial")]
+    pub tile_shards: Vec<String>,
+    /// Rich panorama metadata (photometa)
+    #[cfg(feature = "unofficial")]
+    pub photometa: String,
+    /// Official Street View metadata API
+    pub metadata: String,
+    /// Official Street View Static API
+    pub static_api: String,
+    /// Geocoding API
+    pub geocode: String,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Self {
+            #[cfg(feature = "unofficial")]
+            search: SEARCH_ENDPOINT.to_string(),
+            #[cfg(feature = "unofficial")]
+            tiles: TILE_ENDPOINT.to_string(),
+            #[cfg(feature = "unofficial")]
+            tile_shards: Vec::new(),
+            #[cfg(feature = "unofficial")]
+            photometa: PHOTOMETA_ENDPOINT.to_string(),
+            metadata: METADATA_ENDPOINT.to_string(),
+            static_api: STREETVIEW_ENDPOINT.to_string(),
+            geocode: GEOCODE_ENDPOINT.to_string(),
+        }
+    }
+}
+
+impl Endpoints {
+    /// Google's default endpoints.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the panorama search endpoint.
+    #[cfg(feature = "unofficial")]
+    pub fn search(mut self, url: impl Into<String>) -> Self {
+        self.search = url.into();
+        self
+    }
+
+    /// Set the tile endpoint.
+    #[cfg(feature = "unofficial")]
+    pub fn tiles(mut self, url: impl Into<String>) -> Self {
+        self.tiles = url.into();
+        self
+    }
+
+    /// Spread tile requests over several endpoints serving the same tiles.
+    ///
+    /// The endpoint for a tile is picked from its coordinates, so each
+    /// tile always has the same URL and the response cache keeps working
+    /// across runs with the same shards. An empty list sends every tile to
+    /// [`tiles`](Self::tiles) again.
+    ///
+    /// ```no_run
+    /// # use rsstreetview::{Endpoints, StreetView};
+    /// # fn main() -> rsstreetview::Result<()> {
+    /// let client = StreetView::builder()
+    ///     .endpoints(Endpoints::new().tile_shards(["https://tiles1.internal/cbk", "https://tiles2.internal/cbk"]))
+    ///     .build()?;
+    /// // Or Google's own cbk0-cbk3 hosts
+    /// let client = StreetView::builder().endpoints(Endpoints::new().google_tile_shards()).build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "unofficial")]
+    pub fn tile_shards<I, S>(mut self, urls: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tile_shards = urls.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Spread tile requests over Google's tile hosts, `cbk0` to `cbk3`.
+    #[cfg(feature = "unofficial")]
+    pub fn google_tile_shards(self) -> Self {
+        self.tile_shards(GOOGLE_TILE_SHARDS)
+    }
+
+    /// The endpoint the tile at column `x`, row `y` is requested from.
+    ///
+    /// Neighbouring tiles go to different shards, so a download's
+    /// concurrent requests are spread evenly.
+    #[cfg(feature = "unofficial")]
+    pub fn tile_endpoint(&self, x: u32, y: u32) -> &str {
+        match self.tile_shards.len() {
+            0 => &self.tiles,
+            n => &self.tile_shards[(x as usize + y as usize) % n],
+        }
+    }
+
+    /// Whether `url` is on the tile endpoint or one of its shards.
+    #[cfg(feature = "unofficial")]
+    pub(crate) fn is_tile_url(&self, url: &str) -> bool {
+        std::iter::once(&self.tiles)
+            .chain(&self.tile_shards)
+            .any(|endpoint| url.starts_with(endpoint.as_str()))
+    }
+
+    /// Set the photometa endpoint.
+    #[cfg(feature = "unofficial")]
+    pub fn photometa(mut self, url: impl Into<String>) -> Self {
+        self.photometa = url.into();
+        self
+    }
+
+    /// Set the official metadata endpoint.
+    pub fn metadata(mut self, url: impl Into<String>) -> Self {
+        self.metadata = url.into();
+        self
+    }
+
+    /// Set the Static API endpoint.
+    pub fn static_api(mut self, url: impl Into<String>) -> Self {
+        self.static_api = url.into();
+        self
+    }
+
+    /// Set the geocoding endpoint.
+    pub fn geocode(mut self, url: impl Into<String>) -> Self {
+        self.geocode = url.into();
+        self
+    }
+
+    /// Check that every endpoint is an absolute URL.
+    pub(crate) fn validate(&self) -> Result<()> {
+        let official = [&self.metadata, &self.static_api, &self.geocode];
+        #[cfg(feature = "unofficial")]
+        let all = official
+            .into_iter()
+            .chain([&self.search, &self.tiles, &self.photometa])
+            .chain(&self.tile_shards);
+        #[cfg(not(feature = "unofficial"))]
+        let all = official;
+        for url in all {
+            reqwest::Url::parse(url)
+                .map_err(|e| StreetViewError::ParseError(format!("Invalid endpoint {url}: {e}")))?;
+        }
+        Ok(())
+    }
+}
+
+/// Internal: which configured endpoint a request URL is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EndpointKind {
+    #[cfg(feature = "unofficial")]
+    Search,
+    #[cfg(feature = "unofficial")]
+    Tile,
+    #[cfg(feature = "unofficial")]
+    Photometa,
+    Metadata,
+    StaticImage,
+    Geocode,
+}
+
+impl Endpoints {
+    /// The endpoint `url` is on, or `None` for URLs elsewhere (other
+    /// providers).
+    ///
+    /// The longest match wins, since the Static API endpoint is a prefix of
+    /// the metadata endpoint. Tile shards count as the tile endpoint.
+    pub(crate) fn kind_of(&self, url: &str) -> Option<EndpointKind> {
+        #[cfg(feature = "unofficial")]
+        if self.is_tile_url(url) {
+            return Some(EndpointKind::Tile);
+        }
+        [
+            #[cfg(feature = "unofficial")]
+            (&self.search, EndpointKind::Search),
+            #[cfg(feature = "unofficial")]
+            (&self.photometa, EndpointKind::Photometa),
+            (&self.metadata, EndpointKind::Metadata),
+            (&self.static_api, EndpointKind::StaticImage),
+            (&self.geocode, EndpointKind::Geocode),
+        ]
+        .into_iter()
+        .filter(|(endpoint, _)| url.starts_with(endpoint.as_str()))
+        .max_by_key(|(endpoint, _)| endpoint.len())
+        .map(|(_, kind)| kind)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoints() {
+        assert!(Endpoints::new().validate().is_ok());
+        assert!(Endpoints::new().metadata("not a url").validate().is_err());
+    }
+
+    #[test]
+    fn test_kind_of() {
+        let endpoints = Endpoints::new();
+        let kind = |url: &str| endpoints.kind_of(url);
+        assert_eq!(kind(&format!("{}?pano=X&key=K", endpoints.metadata)), Some(EndpointKind::Metadata));
+        assert_eq!(kind(&format!("{}?pano=X&key=K", endpoints.static_api)), Some(EndpointKind::StaticImage));
+        assert_eq!(kind(&format!("{}?address=Rome", endpoints.geocode)), Some(EndpointKind::Geocode));
+        assert_eq!(kind("https://graph.mapillary.com/images"), None);
+        #[cfg(feature = "unofficial")]
+        {
+            assert_eq!(kind(&format!("{}?pb=x", endpoints.search)), Some(EndpointKind::Search));
+            assert_eq!(kind(&format!("{}?output=tile", endpoints.tiles)), Some(EndpointKind::Tile));
+            assert_eq!(kind(&format!("{}?id=X", endpoints.photometa)), Some(EndpointKind::Photometa));
+            let sharded = Endpoints::new().google_tile_shards();
+            assert_eq!(sharded.kind_of("https://cbk1.google.com/cbk?output=tile"), Some(EndpointKind::Tile));
+        }
+    }
+
+    #[cfg(feature = "unofficial")]
+    #[test]
+    fn test_unofficial_endpoints() {
+        let endpoints = Endpoints::new().tiles("http://127.0.0.1:8080/cbk");
+        assert_eq!(endpoints.tiles, "http://127.0.0.1:8080/cbk");
+        assert_eq!(endpoints.search, SEARCH_ENDPOINT);
+        assert!(Endpoints::new().tile_shards(["cbk0"]).validate().is_err());
+    }
+
+    #[cfg(feature = "unofficial")]
+    #[test]
+    fn test_tile_shards() {
+        let endpoints = Endpoints::new();
+        assert_eq!(endpoints.tile_endpoint(3, 1), TILE_ENDPOINT);
+
+        let endpoints = endpoints.google_tile_shards();
+        assert!(endpoints.validate().is_ok());
+        let hosts: Vec<&str> = (0..4).map(|x| endpoints.tile_endpoint(x, 0)).collect();
+        assert_eq!(hosts, GOOGLE_TILE_SHARDS);
+        assert_eq!(endpoints.tile_endpoint(1, 2), endpoints.tile_endpoint(1, 2));
+        assert_ne!(endpoints.tile_endpoint(1, 0), endpoints.tile_endpoint(1, 1));
+        assert!(endpoints.is_tile_url("https://cbk2.google.com/cbk?output=tile"));
+        assert!(!endpoints.is_tile_url(&format!("{SEARCH_ENDPOINT}?pb=")));
+
+        assert_eq!(endpoints.tile_shards(Vec::<String>::new()).tile_endpoint(1, 0), TILE_ENDPOINT);
+    }
+}
@@ -1,3 +1,5 @@
+use crate::transport::Stage;
+use std::time::Duration;
 use thiserror::Error;
 
 /// Result type alias for StreetView operations.
@@ -22,23 +24,193 @@ pub enum StreetViewError {
     #[error("Image error: {0}")]
     ImageError(#[from] image::ImageError),
 
+    /// Image encoding failed
+    #[error("Image encoding failed: {0}")]
+    EncodeError(String),
+
     /// IO error
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 
     /// Missing API key
-    #[error("API key required for this operation. Use StreetView::with_api_key() to set one.")]
+    #[error("API key required for this operation. Use StreetView::builder().api_key() to set one.")]
     MissingApiKey,
 
     /// No panoramas found
     #[error("No panoramas found at the specified location")]
     NoPanoramasFound,
 
+    /// The Static API has no imagery for the request, and answered with
+    /// its gray "no imagery" placeholder or a `404`
+    #[error("No Street View imagery for this request")]
+    NoImagery,
+
+    /// Malformed panorama ID
+    #[error("Invalid panorama ID: {0}")]
+    InvalidPanoId(String),
+
     /// Invalid URL format
     #[error("Invalid Google Maps URL format")]
     InvalidUrl,
 
+    /// A view configuration failed [`ViewConfig::validate`](crate::ViewConfig::validate)
+    #[error("Invalid view configuration: {0}")]
+    InvalidViewConfig(String),
+
+    /// Operation refused by the client's usage policy
+    #[error("Policy violation: {0}")]
+    PolicyViolation(String),
+
+    /// Google rejected the request because the API key is over quota
+    #[error("API quota exceeded: {0}")]
+    QuotaExceeded(String),
+
+    /// The API returned an error page instead of the expected content
+    #[error("API error (HTTP {status}): {message}")]
+    ApiError {
+        /// HTTP status code
+        status: u16,
+        /// Error text from the response body
+        message: String,
+    },
+
+    /// Google is rate limiting this client (HTTP 429)
+    #[error("Rate limited by Google{}", retry_after.map(|d| format!(", retry after {}s", d.as_secs())).unwrap_or_default())]
+    RateLimited {
+        /// Wait requested by the `Retry-After` header, if any
+        retry_after: Option<Duration>,
+        /// Raw response body
+        body: String,
+    },
+
+    /// Google refused the request as automated traffic, answering with a
+    /// CAPTCHA or "unusual traffic" page. Retrying soon will not help.
+    #[error("Request blocked by Google (HTTP {status})")]
+    Blocked {
+        /// HTTP status code
+        status: u16,
+        /// Raw response body
+        body: String,
+    },
+
+    /// An undocumented endpoint answered, but without data where this
+    /// library expects it, usually because Google changed the format
+    #[error("Unexpected response format: nothing at {path}")]
+    EndpointFormatChanged {
+        /// Location in the response where data was expected (e.g. `[1][5][0]`)
+        path: String,
+        /// Raw response body
+        body: String,
+    },
+
+    /// The tile server has no tile at this position, usually because the
+    /// panorama is not available at the requested zoom
+    #[error("Tile ({x}, {y}) not found at zoom {zoom}")]
+    TileNotFound {
+        /// Tile column
+        x: u32,
+        /// Tile row
+        y: u32,
+        /// Zoom level
+        zoom: u8,
+        /// Raw response body
+        body: String,
+    },
+
+    /// The client's quota tracker refused a billable request
+    #[error("Quota budget exceeded: {0}")]
+    QuotaBudgetExceeded(String),
+
+    /// SQLite index error
+    #[cfg(feature = "sqlite")]
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] rusqlite::Error),
+
+    /// Parquet export error
+    #[cfg(feature = "parquet")]
+    #[error("Parquet error: {0}")]
+    ParquetError(#[from] parquet::errors::ParquetError),
+
+    /// Every proxy in the client's proxy pool has been removed
+    #[error("No proxies left in the proxy pool")]
+    NoProxiesAvailable,
+
     /// Tile download failed after retries
     #[error("Failed to download tile after {0} retries")]
     TileDownloadFailed(u32),
+
+    /// A request took longer than the client's [`Timeouts`](crate::Timeouts)
+    /// allow for its stage
+    #[error("{stage} request timed out after {:.1}s", elapsed.as_secs_f64())]
+    Timeout {
+        /// Kind of request that timed out
+        stage: Stage,
+        /// Time the request had been running
+        elapsed: Duration,
+    },
+
+    /// The client is offline and the response is not in its cache
+    #[error("Offline and not cached: {url}")]
+    OfflineCacheMiss {
+        /// URL that would have been requested
+        url: String,
+    },
+}
+
+#[cfg(feature = "serde")]
+impl StreetViewError {
+    /// The variant name, used as a stable `kind` when serializing.
+    fn kind(&self) -> &'static str {
+        match self {
+            StreetViewError::HttpError(_) => "HttpError",
+            StreetViewError::ParseError(_) => "ParseError",
+            StreetViewError::InvalidResponse(_) => "InvalidResponse",
+            StreetViewError::ImageError(_) => "ImageError",
+            StreetViewError::EncodeError(_) => "EncodeError",
+            StreetViewError::IoError(_) => "IoError",
+            StreetViewError::MissingApiKey => "MissingApiKey",
+            StreetViewError::NoPanoramasFound => "NoPanoramasFound",
+            StreetViewError::NoImagery => "NoImagery",
+            StreetViewError::InvalidPanoId(_) => "InvalidPanoId",
+            StreetViewError::InvalidUrl => "InvalidUrl",
+            StreetViewError::InvalidViewConfig(_) => "InvalidViewConfig",
+            StreetViewError::PolicyViolation(_) => "PolicyViolation",
+            StreetViewError::QuotaExceeded(_) => "QuotaExceeded",
+            StreetViewError::ApiError { .. } => "ApiError",
+            StreetViewError::RateLimited { .. } => "RateLimited",
+            StreetViewError::Blocked { .. } => "Blocked",
+            StreetViewError::EndpointFormatChanged { .. } => "EndpointFormatChanged",
+            StreetViewError::TileNotFound { .. } => "TileNotFound",
+            StreetViewError::QuotaBudgetExceeded(_) => "QuotaBudgetExceeded",
+            #[cfg(feature = "sqlite")]
+            StreetViewError::DatabaseError(_) => "DatabaseError",
+            #[cfg(feature = "parquet")]
+            StreetViewError::ParquetError(_) => "ParquetError",
+            StreetViewError::NoProxiesAvailable => "NoProxiesAvailable",
+            StreetViewError::TileDownloadFailed(_) => "TileDownloadFailed",
+            StreetViewError::OfflineCacheMiss { .. } => "OfflineCacheMiss",
+            StreetViewError::Timeout { .. } => "Timeout",
+        }
+    }
+}
+
+/// Serializes as `{"kind": "<variant>", "message": "<display text>"}`, plus
+/// `"status"` for `ApiError` and `Blocked`, for result manifests and job
+/// logs.
+///
+/// Errors wrap foreign types (`reqwest::Error`, `std::io::Error`) and cannot
+/// be deserialized.
+#[cfg(feature = "serde")]
+impl serde::Serialize for StreetViewError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("kind", self.kind())?;
+        map.serialize_entry("message", &self.to_string())?;
+        if let StreetViewError::ApiError { status, .. } | StreetViewError::Blocked { status, .. } = self {
+            map.serialize_entry("status", status)?;
+        }
+        map.end()
+    }
 }
@@ -0,0 +1,384 @@
+//! Export panorama listings to GIS and data tools.
+//!
+//! [`ToGeoJson`] turns search or crawl results into a GeoJSON
+//! `FeatureCollection` of points that QGIS, kepler.gl, or geojson.io can
+//! open as-is. For large coverage scans, [`CsvWriter`] and (with the
+//! `parquet` feature) `ParquetWriter` stream rows to a writer without
+//! holding the whole listing in memory.
+//!
+//! CSV and Parquet files share one column schema, [`COLUMNS`]:
+//!
+//! | column        | type                  |
+//! |---------------|-----------------------|
+//! | `pano_id`     | string                |
+//! | `lat`         | float64               |
+//! | `lon`         | float64               |
+//! | `heading`     | float64               |
+//! | `pitch`       | float64, nullable     |
+//! | `roll`        | float64, nullable     |
+//! | `date`        | `"YYYY-MM"`, nullable |
+//! | `elevation`   | float64, nullable     |
+//! | `street_name` | string, nullable      |
+//!
+//! Columns are only ever appended, so readers can rely on names and order.
+
+use crate::error::Result;
+use crate::types::Panorama;
+use serde_json::{json, Value};
+use std::io::Write;
+
+/// Column names of CSV and Parquet exports, in order.
+pub const COLUMNS: [&str; 9] = [
+    "pano_id", "lat", "lon", "heading", "pitch", "roll", "date", "elevation", "street_name",
+];
+
+/// Conversion of panoramas to GeoJSON.
+///
+/// Each panorama becomes a `Point` feature at `[lon, lat]` (plus elevation
+/// when known) with `pano_id`, `date` (`"YYYY-MM"` or `null`), `heading`,
+/// and, when known, `street_name` properties.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rsstreetview::{StreetView, ToGeoJson};
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = StreetView::new();
+/// let panos = client.search_panoramas(41.8982208, 12.4764804).await?;
+/// std::fs::write("coverage.geojson", panos.to_geojson().to_string())?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait ToGeoJson {
+    /// The GeoJSON representation.
+    fn to_geojson(&self) -> Value;
+}
+
+/// A single `Feature`.
+impl ToGeoJson for Panorama {
+    fn to_geojson(&self) -> Value {
+        let mut coordinates = vec![self.lon, self.lat];
+        coordinates.extend(self.elevation);
+
+        let mut properties = json!({
+            "pano_id": self.pano_id,
+            "date": self.date,
+            "heading": self.heading,
+        });
+        if let Some(street_name) = &self.street_name {
+            properties["street_name"] = json!(street_name);
+        }
+
+        json!({
+            "type": "Feature",
+            "geometry": {
+                "type": "Point",
+                "coordinates": coordinates,
+            },
+            "properties": properties,
+        })
+    }
+}
+
+/// A `FeatureCollection` with one feature per panorama.
+impl ToGeoJson for [Panorama] {
+    fn to_geojson(&self) -> Value {
+        json!({
+            "type": "FeatureCollection",
+            "features": self.iter().map(ToGeoJson::to_geojson).collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// Streaming CSV writer for panorama listings.
+///
+/// Writes a header row of [`COLUMNS`], then one row per panorama. Missing
+/// values are empty fields. Wrap files in a `BufWriter`.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rsstreetview::export::CsvWriter;
+/// # use rsstreetview::StreetView;
+/// # use std::io::BufWriter;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = StreetView::new();
+/// let mut csv = CsvWriter::new(BufWriter::new(std::fs::File::create("coverage.csv")?))?;
+/// for (lat, lon) in [(41.8982, 12.4764), (41.9009, 12.4833)] {
+///     for pano in client.search_panoramas(lat, lon).await? {
+///         csv.write(&pano)?;
+///     }
+/// }
+/// csv.finish()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct CsvWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> CsvWriter<W> {
+    /// Start a CSV file, writing the header row.
+    pub fn new(mut writer: W) -> Result<Self> {
+        writeln!(writer, "{}", COLUMNS.join(","))?;
+        Ok(Self { writer })
+    }
+
+    /// Write one panorama as a row.
+    pub fn write(&mut self, pano: &Panorama) -> Result<()> {
+        let optional = |value: Option<f64>| value.map(|v| v.to_string()).unwrap_or_default();
+        writeln!(
+            self.writer,
+            "{},{},{},{},{},{},{},{},{}",
+            csv_field(&pano.pano_id),
+            pano.lat,
+            pano.lon,
+            pano.heading,
+            optional(pano.pitch),
+            optional(pano.roll),
+            pano.date.map(|date| date.to_string()).unwrap_or_default(),
+            optional(pano.elevation),
+            pano.street_name.as_deref().map(csv_field).unwrap_or_default(),
+        )?;
+        Ok(())
+    }
+
+    /// Flush and return the underlying writer.
+    pub fn finish(mut self) -> Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Quote a field if it contains a delimiter, quote, or line break.
+fn csv_field(value: &str) -> std::borrow::Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\"")).into()
+    } else {
+        value.into()
+    }
+}
+
+/// Write panoramas to `writer` as CSV. See [`CsvWriter`].
+pub fn export_csv<'a, W: Write>(panos: impl IntoIterator<Item = &'a Panorama>, writer: W) -> Result<W> {
+    let mut csv = CsvWriter::new(writer)?;
+    for pano in panos {
+        csv.write(pano)?;
+    }
+    csv.finish()
+}
+
+#[cfg(feature = "parquet")]
+pub use self::parquet_export::{export_parquet, ParquetWriter};
+
+#[cfg(feature = "parquet")]
+mod parquet_export {
+    use super::Panorama;
+    use crate::error::Result;
+    use parquet::basic::Compression;
+    use parquet::data_type::{ByteArray, ByteArrayType, DataType, DoubleType};
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::{SerializedColumnWriter, SerializedFileWriter};
+    use parquet::schema::parser::parse_message_type;
+    use std::io::Write;
+    use std::sync::Arc;
+
+    /// Parquet schema matching [`COLUMNS`](super::COLUMNS).
+    const SCHEMA: &str = "
+        message panorama {
+            REQUIRED BYTE_ARRAY pano_id (UTF8);
+            REQUIRED DOUBLE lat;
+            REQUIRED DOUBLE lon;
+            REQUIRED DOUBLE heading;
+            OPTIONAL DOUBLE pitch;
+            OPTIONAL DOUBLE roll;
+            OPTIONAL BYTE_ARRAY date (UTF8);
+            OPTIONAL DOUBLE elevation;
+            OPTIONAL BYTE_ARRAY street_name (UTF8);
+        }
+    ";
+
+    /// Rows buffered per row group.
+    const DEFAULT_ROW_GROUP_SIZE: usize = 65_536;
+
+    /// Streaming Parquet writer for panorama listings.
+    ///
+    /// Rows are buffered and written as Snappy-compressed row groups of
+    /// 65,536 rows, so memory use stays bounded for listings of millions
+    /// of panoramas. Requires the `parquet` feature.
+    pub struct ParquetWriter<W: Write + Send> {
+        writer: SerializedFileWriter<W>,
+        rows: Vec<Panorama>,
+        row_group_size: usize,
+    }
+
+    impl<W: Write + Send> ParquetWriter<W> {
+        /// Start a Parquet file.
+        pub fn new(writer: W) -> Result<Self> {
+            let schema = Arc::new(parse_message_type(SCHEMA)?);
+            let properties = WriterProperties::builder()
+                .set_compression(Compression::SNAPPY)
+                .build();
+            Ok(Self {
+                writer: SerializedFileWriter::new(writer, schema, Arc::new(properties))?,
+                rows: Vec::new(),
+                row_group_size: DEFAULT_ROW_GROUP_SIZE,
+            })
+        }
+
+        /// Set the number of rows per row group (default 65,536).
+        pub fn row_group_size(mut self, rows: usize) -> Self {
+            self.row_group_size = rows.max(1);
+            self
+        }
+
+        /// Write one panorama as a row.
+        pub fn write(&mut self, pano: &Panorama) -> Result<()> {
+            self.rows.push(pano.clone());
+            if self.rows.len() >= self.row_group_size {
+                self.flush_row_group()?;
+            }
+            Ok(())
+        }
+
+        /// Write the remaining rows and the file footer, and return the
+        /// underlying writer.
+        pub fn finish(mut self) -> Result<W> {
+            self.flush_row_group()?;
+            Ok(self.writer.into_inner()?)
+        }
+
+        fn flush_row_group(&mut self) -> Result<()> {
+            if self.rows.is_empty() {
+                return Ok(());
+            }
+            let rows = std::mem::take(&mut self.rows);
+            let text = |value: Option<String>| value.map(|v| ByteArray::from(v.as_str()));
+
+            let mut row_group = self.writer.next_row_group()?;
+            let mut index = 0;
+            while let Some(mut column) = row_group.next_column()? {
+                match index {
+                    0 => write_column::<ByteArrayType>(&mut column, rows.iter().map(|p| text(Some(p.pano_id.clone()))), false)?,
+                    1 => write_column::<DoubleType>(&mut column, rows.iter().map(|p| Some(p.lat)), false)?,
+                    2 => write_column::<DoubleType>(&mut column, rows.iter().map(|p| Some(p.lon)), false)?,
+                    3 => write_column::<DoubleType>(&mut column, rows.iter().map(|p| Some(p.heading)), false)?,
+                    4 => write_column::<DoubleType>(&mut column, rows.iter().map(|p| p.pitch), true)?,
+                    5 => write_column::<DoubleType>(&mut column, rows.iter().map(|p| p.roll), true)?,
+                    6 => write_column::<ByteArrayType>(&mut column, rows.iter().map(|p| text(p.date.map(|d| d.to_string()))), true)?,
+                    7 => write_column::<DoubleType>(&mut column, rows.iter().map(|p| p.elevation), true)?,
+                    _ => write_column::<ByteArrayType>(&mut column, rows.iter().map(|p| text(p.street_name.clone())), true)?,
+                }
+                column.close()?;
+                index += 1;
+            }
+            row_group.close()?;
+            Ok(())
+        }
+    }
+
+    /// Write one column; `optional` columns get definition levels marking nulls.
+    fn write_column<T: DataType>(
+        column: &mut SerializedColumnWriter<'_>,
+        values: impl Iterator<Item = Option<T::T>>,
+        optional: bool,
+    ) -> Result<()> {
+        let values: Vec<Option<T::T>> = values.collect();
+        let levels: Vec<i16> = values.iter().map(|value| i16::from(value.is_some())).collect();
+        let present: Vec<T::T> = values.into_iter().flatten().collect();
+        column
+            .typed::<T>()
+            .write_batch(&present, optional.then_some(levels.as_slice()), None)?;
+        Ok(())
+    }
+
+    /// Write panoramas to `writer` as Parquet. See [`ParquetWriter`].
+    pub fn export_parquet<'a, W: Write + Send>(
+        panos: impl IntoIterator<Item = &'a Panorama>,
+        writer: W,
+    ) -> Result<W> {
+        let mut parquet = ParquetWriter::new(writer)?;
+        for pano in panos {
+            parquet.write(pano)?;
+        }
+        parquet.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures::panorama;
+    use crate::types::{CaptureDate, DateConfidence};
+
+    fn pano(pano_id: &str, date: Option<CaptureDate>, elevation: Option<f64>) -> Panorama {
+        Panorama {
+            heading: 90.0,
+            date,
+            date_confidence: DateConfidence::of(date),
+            elevation,
+            ..panorama(pano_id, 41.9, 12.5)
+        }
+    }
+
+    #[test]
+    fn test_to_geojson() {
+        let panos = [
+            pano("A", CaptureDate::new(2019, 5), None),
+            pano("B", None, Some(21.5)),
+        ];
+        let geojson = panos[..].to_geojson();
+
+        assert_eq!(geojson["type"], "FeatureCollection");
+        let features = geojson["features"].as_array().unwrap();
+        assert_eq!(features.len(), 2);
+
+        assert_eq!(
+            features[0],
+            json!({
+                "type": "Feature",
+                "geometry": { "type": "Point", "coordinates": [12.5, 41.9] },
+                "properties": { "pano_id": "A", "date": "2019-05", "heading": 90.0 },
+            })
+        );
+        assert_eq!(features[1]["geometry"]["coordinates"], json!([12.5, 41.9, 21.5]));
+        assert_eq!(features[1]["properties"]["date"], Value::Null);
+
+        let mut named = pano("C", None, None);
+        named.street_name = Some("Via Roma".to_string());
+        assert_eq!(named.to_geojson()["properties"]["street_name"], "Via Roma");
+
+        assert_eq!(Vec::<Panorama>::new().to_geojson()["features"], json!([]));
+    }
+
+    #[test]
+    fn test_export_csv() {
+        let mut odd = pano("C,\"D\"", None, None);
+        odd.pitch = Some(1.5);
+        let mut named = pano("A", CaptureDate::new(2019, 5), Some(21.5));
+        named.street_name = Some("Piazza Navona, Roma".to_string());
+        let panos = [named, odd];
+
+        let csv = String::from_utf8(export_csv(&panos, Vec::new()).unwrap()).unwrap();
+        assert_eq!(
+            csv,
+            "pano_id,lat,lon,heading,pitch,roll,date,elevation,street_name\n\
+             A,41.9,12.5,90,,,2019-05,21.5,\"Piazza Navona, Roma\"\n\
+             \"C,\"\"D\"\"\",41.9,12.5,90,1.5,,,,\n"
+        );
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_export_parquet() {
+        let panos: Vec<_> = (0..5).map(|i| pano(&format!("P{i}"), CaptureDate::new(2020, 1), None)).collect();
+        let mut writer = ParquetWriter::new(Vec::new()).unwrap().row_group_size(2);
+        for pano in &panos {
+            writer.write(pano).unwrap();
+        }
+        let bytes = writer.finish().unwrap();
+        assert!(bytes.starts_with(b"PAR1") && bytes.ends_with(b"PAR1"));
+    }
+}
@@ -0,0 +1,163 @@
+//! Geoid models for converting between vertical datums.
+//!
+//! Providers report elevations as orthometric heights: meters above mean sea
+//! level, which Google defines by the EGM96 geoid. GPS receivers, ECEF
+//! coordinates, and most photogrammetry tools use heights above the WGS84
+//! ellipsoid instead. The two differ by the geoid undulation `N`, which
+//! ranges from about -107 m to +85 m around the world:
+//!
+//! ```text
+//! h_wgs84 = H_egm96 + N(lat, lon)
+//! ```
+//!
+//! [`Egm96Grid`] reads NGA's 15' undulation grid (`WW15MGH.DAC`). Any
+//! `Fn(lat, lon) -> f64` is also a [`Geoid`], for other models or a fixed
+//! local offset.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use rsstreetview::geoid::Egm96Grid;
+//! # fn demo(pano: &rsstreetview::Panorama) -> rsstreetview::Result<()> {
+//! let geoid = Egm96Grid::open("WW15MGH.DAC")?;
+//! let ellipsoidal = pano.elevation_wgs84(&geoid);
+//!
+//! // Or with a known local undulation, in meters
+//! let ellipsoidal = pano.elevation_wgs84(&|_lat: f64, _lon: f64| 48.9);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Result, StreetViewError};
+use std::path::Path;
+
+/// A geoid model: the height of the geoid above the WGS84 ellipsoid.
+pub trait Geoid {
+    /// Geoid undulation in meters at a WGS84 position in degrees.
+    fn undulation(&self, lat: f64, lon: f64) -> f64;
+
+    /// Convert a height above the geoid to a height above the ellipsoid.
+    fn to_ellipsoidal(&self, lat: f64, lon: f64, orthometric: f64) -> f64 {
+        orthometric + self.undulation(lat, lon)
+    }
+
+    /// Convert a height above the ellipsoid to a height above the geoid.
+    fn to_orthometric(&self, lat: f64, lon: f64, ellipsoidal: f64) -> f64 {
+        ellipsoidal - self.undulation(lat, lon)
+    }
+}
+
+impl<F: Fn(f64, f64) -> f64> Geoid for F {
+    fn undulation(&self, lat: f64, lon: f64) -> f64 {
+        self(lat, lon)
+    }
+}
+
+/// Grid columns: 0° to 359.75° east.
+const COLS: usize = 1440;
+/// Grid rows: 90°N to 90°S.
+const ROWS: usize = 721;
+/// Grid spacing in degrees.
+const SPACING: f64 = 0.25;
+
+/// The EGM96 geoid on NGA's 15' grid, interpolated bilinearly.
+///
+/// Load it from `WW15MGH.DAC`, distributed by NGA with the EGM96 model: 721
+/// rows from 90°N to 90°S of 1440 big-endian `i16` undulations in
+/// centimeters, from 0°E eastwards. Interpolation error is below 2 cm.
+#[derive(Clone)]
+pub struct Egm96Grid {
+    /// Undulations in centimeters, row-major from the north pole
+    heights: Vec<i16>,
+}
+
+impl std::fmt::Debug for Egm96Grid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Egm96Grid").finish_non_exhaustive()
+    }
+}
+
+impl Egm96Grid {
+    /// Read the grid from a `WW15MGH.DAC` file.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_dac(&std::fs::read(path)?)
+    }
+
+    /// Parse the contents of a `WW15MGH.DAC` file.
+    pub fn from_dac(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != ROWS * COLS * 2 {
+            return Err(StreetViewError::ParseError(format!(
+                "EGM96 grid must be {} bytes, got {}",
+                ROWS * COLS * 2,
+                bytes.len()
+            )));
+        }
+        let heights = bytes
+            .chunks_exact(2)
+            .map(|pair| i16::from_be_bytes([pair[0], pair[1]]))
+            .collect();
+        Ok(Self { heights })
+    }
+
+    fn at(&self, row: usize, col: usize) -> f64 {
+        f64::from(self.heights[row * COLS + col % COLS]) / 100.0
+    }
+}
+
+impl Geoid for Egm96Grid {
+    fn undulation(&self, lat: f64, lon: f64) -> f64 {
+        let y = ((90.0 - lat.clamp(-90.0, 90.0)) / SPACING).min((ROWS - 1) as f64);
+        let x = lon.rem_euclid(360.0) / SPACING;
+        let (row, col) = (y.floor() as usize, x.floor() as usize);
+        let (fy, fx) = (y - row as f64, x - col as f64);
+        let below = (row + 1).min(ROWS - 1);
+
+        let top = self.at(row, col) * (1.0 - fx) + self.at(row, col + 1) * fx;
+        let bottom = self.at(below, col) * (1.0 - fx) + self.at(below, col + 1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A grid whose undulation in centimeters is `row + col`.
+    fn ramp_grid() -> Egm96Grid {
+        let bytes: Vec<u8> = (0..ROWS)
+            .flat_map(|row| (0..COLS).map(move |col| (row + col) as i16))
+            .flat_map(i16::to_be_bytes)
+            .collect();
+        Egm96Grid::from_dac(&bytes).unwrap()
+    }
+
+    #[test]
+    fn test_egm96_grid() {
+        let grid = ramp_grid();
+        assert!((grid.undulation(90.0, 0.0) - 0.0).abs() < 1e-9);
+        assert!((grid.undulation(89.75, 0.5) - 0.03).abs() < 1e-9);
+        // Halfway between grid points
+        assert!((grid.undulation(89.875, 0.125) - 0.01).abs() < 1e-9);
+        // Negative longitudes wrap to the east
+        assert!((grid.undulation(90.0, -0.25) - 14.39).abs() < 1e-9);
+        assert!((grid.undulation(-90.0, 0.0) - 7.20).abs() < 1e-9);
+
+        assert!((grid.to_ellipsoidal(89.75, 0.5, 100.0) - 100.03).abs() < 1e-9);
+        assert!((grid.to_orthometric(89.75, 0.5, 100.03) - 100.0).abs() < 1e-9);
+        assert!(Egm96Grid::from_dac(&[0; 10]).is_err());
+    }
+
+    #[test]
+    fn test_closure_geoid() {
+        let offset = |_lat: f64, _lon: f64| 48.9;
+        assert!((offset.to_ellipsoidal(41.9, 12.5, 20.0) - 68.9).abs() < 1e-9);
+
+        let pano: crate::Panorama = serde_json::from_value(serde_json::json!({
+            "pano_id": "PANO_A", "lat": 41.9, "lon": 12.5, "heading": 0.0,
+            "pitch": null, "roll": null, "date": null, "elevation": 20.0
+        }))
+        .unwrap();
+        assert_eq!(pano.elevation_egm96(), Some(20.0));
+        assert!((pano.elevation_wgs84(&offset).unwrap() - 68.9).abs() < 1e-9);
+    }
+}
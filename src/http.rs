@@ -0,0 +1,531 @@
+use crate::cache::{Cache, Claim, PanoramaCache};
+use crate::capture::RawCapture;
+use crate::endpoints::Endpoints;
+use crate::error::{Result, StreetViewError};
+#[cfg(feature = "metrics")]
+use crate::metrics::{Metrics, RequestEvent, RequestKind};
+use crate::policy::RateLimiter;
+use crate::proxy::ProxyPool;
+use crate::quota::QuotaTracker;
+use crate::signing::UrlSigner;
+use crate::singleflight::SingleFlight;
+use crate::transport::{HttpResponse, HttpTransport, ReqwestTransport, RetryPolicy, Stage, Timeouts};
+use crate::types::{Locale, RequestHeaders};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT_LANGUAGE, RETRY_AFTER, USER_AGENT};
+use reqwest::{Client, RequestBuilder, StatusCode};
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Internal: HTTP access shared by all request paths.
+///
+/// Wraps the reqwest client so cross-cutting concerns (rate limiting, URL
+/// signing, quota tracking, caching, request coalescing, proxy rotation,
+/// headers) apply uniformly to search, tile, and metadata requests.
+#[derive(Clone)]
+pub(crate) struct Http {
+    client: Client,
+    transport: Arc<dyn HttpTransport>,
+    limiter: Option<Arc<RateLimiter>>,
+    signer: Option<UrlSigner>,
+    quota: Option<Arc<QuotaTracker>>,
+    cache: Option<Arc<dyn Cache>>,
+    panoramas: Option<Arc<PanoramaCache>>,
+    proxies: Option<Arc<ProxyPool>>,
+    headers: Option<Arc<HeaderStrategy>>,
+    retry: RetryPolicy,
+    timeouts: Timeouts,
+    locale: Option<Locale>,
+    endpoints: Arc<Endpoints>,
+    inflight: Arc<SingleFlight>,
+    offline: bool,
+    capture: Option<Arc<RawCapture>>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<Arc<dyn Metrics>>,
+}
+
+/// Internal: validated `RequestHeaders` plus the user-agent rotation state.
+struct HeaderStrategy {
+    user_agents: Vec<HeaderValue>,
+    next_user_agent: AtomicUsize,
+    headers: HeaderMap,
+}
+
+impl HeaderStrategy {
+    fn new(config: &RequestHeaders) -> Result<Self> {
+        let invalid = |what: &str| StreetViewError::ParseError(format!("Invalid header {what}"));
+
+        let user_agents = config
+            .user_agents
+            .iter()
+            .map(|ua| HeaderValue::from_str(ua).map_err(|_| invalid(&format!("value {ua:?}"))))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut headers = HeaderMap::new();
+        if let Some(language) = &config.accept_language {
+            let value = HeaderValue::from_str(language).map_err(|_| invalid(&format!("value {language:?}")))?;
+            headers.insert(ACCEPT_LANGUAGE, value);
+        }
+        for (name, value) in &config.extra {
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid(&format!("name {name:?}")))?;
+            let value = HeaderValue::from_str(value).map_err(|_| invalid(&format!("value {value:?}")))?;
+            headers.append(name, value);
+        }
+
+        Ok(Self {
+            user_agents,
+            next_user_agent: AtomicUsize::new(0),
+            headers,
+        })
+    }
+
+    fn apply(&self, mut request: RequestBuilder) -> RequestBuilder {
+        if !self.user_agents.is_empty() {
+            let idx = self.next_user_agent.fetch_add(1, Ordering::Relaxed) % self.user_agents.len();
+            request = request.header(USER_AGENT, self.user_agents[idx].clone());
+        }
+        request.headers(self.headers.clone())
+    }
+}
+
+impl Http {
+    pub(crate) fn new(client: Client) -> Self {
+        Self {
+            transport: Arc::new(ReqwestTransport::new(client.clone())),
+            client,
+            limiter: None,
+            signer: None,
+            quota: None,
+            cache: None,
+            panoramas: None,
+            proxies: None,
+            headers: None,
+            retry: RetryPolicy::default(),
+            timeouts: Timeouts::default(),
+            locale: None,
+            endpoints: Arc::new(Endpoints::default()),
+            inflight: Arc::new(SingleFlight::new()),
+            offline: false,
+            capture: None,
+            #[cfg(feature = "metrics")]
+            metrics: None,
+        }
+    }
+
+    /// Execute requests with `transport` instead of the network.
+    #[cfg(any(test, feature = "testing"))]
+    pub(crate) fn set_transport(&mut self, transport: Arc<dyn HttpTransport>) {
+        self.transport = transport;
+    }
+
+    /// Sign official API requests made with `get_official`.
+    pub(crate) fn set_signer(&mut self, signer: Option<UrlSigner>) {
+        self.signer = signer;
+    }
+
+    /// Count billable requests made with `get_billable`.
+    pub(crate) fn set_quota(&mut self, quota: Option<Arc<QuotaTracker>>) {
+        self.quota = quota;
+    }
+
+    pub(crate) fn quota(&self) -> Option<&QuotaTracker> {
+        self.quota.as_deref()
+    }
+
+    /// Serve and store `fetch` responses through a cache.
+    pub(crate) fn set_cache(&mut self, cache: Option<Arc<dyn Cache>>) {
+        self.cache = cache;
+    }
+
+    /// Keep assembled panoramas in memory for `download::cached_panorama`.
+    pub(crate) fn set_panorama_cache(&mut self, panoramas: Option<Arc<PanoramaCache>>) {
+        self.panoramas = panoramas;
+    }
+
+    pub(crate) fn panorama_cache(&self) -> Option<&Arc<PanoramaCache>> {
+        self.panoramas.as_ref()
+    }
+
+    /// Send requests through a rotating proxy pool.
+    pub(crate) fn set_proxy_pool(&mut self, proxies: Option<Arc<ProxyPool>>) {
+        self.proxies = proxies;
+    }
+
+    pub(crate) fn proxy_pool(&self) -> Option<&ProxyPool> {
+        self.proxies.as_deref()
+    }
+
+    /// Apply headers to requests to the undocumented endpoints.
+    pub(crate) fn set_headers(&mut self, headers: &RequestHeaders) -> Result<()> {
+        self.headers = if headers.is_empty() {
+            None
+        } else {
+            Some(Arc::new(HeaderStrategy::new(headers)?))
+        };
+        Ok(())
+    }
+
+    /// Set how failed tile downloads are retried.
+    pub(crate) fn set_retry(&mut self, retry: RetryPolicy) {
+        self.retry = retry;
+    }
+
+    #[cfg_attr(not(feature = "unofficial"), allow(dead_code))]
+    pub(crate) fn retry(&self) -> &RetryPolicy {
+        &self.retry
+    }
+
+    /// Limit how long each request may take, per stage.
+    pub(crate) fn set_timeouts(&mut self, timeouts: Timeouts) {
+        self.timeouts = timeouts;
+    }
+
+    /// Request a language and region from the endpoints that take one.
+    pub(crate) fn set_locale(&mut self, locale: Option<Locale>) {
+        self.locale = locale;
+    }
+
+    /// The configured locale, or `None` to use each endpoint's default.
+    pub(crate) fn locale(&self) -> Option<&Locale> {
+        self.locale.as_ref()
+    }
+
+    /// Send requests to custom endpoints instead of Google's.
+    pub(crate) fn set_endpoints(&mut self, endpoints: Endpoints) {
+        self.endpoints = Arc::new(endpoints);
+    }
+
+    pub(crate) fn endpoints(&self) -> &Endpoints {
+        &self.endpoints
+    }
+
+    /// Report requests, cache hits, and retries to a metrics hook.
+    #[cfg(feature = "metrics")]
+    pub(crate) fn set_metrics(&mut self, metrics: Option<Arc<dyn Metrics>>) {
+        self.metrics = metrics;
+    }
+
+    /// Fail every request that would reach the network, so only cached
+    /// responses are served.
+    pub(crate) fn set_offline(&mut self, offline: bool) {
+        self.offline = offline;
+    }
+
+    /// Write failing `fetch` responses to a directory.
+    pub(crate) fn set_capture(&mut self, capture: Option<RawCapture>) {
+        self.capture = capture.map(Arc::new);
+    }
+
+    /// Fail with `OfflineCacheMiss` if the client is offline.
+    fn ensure_online(&self, url: &str) -> Result<()> {
+        match self.offline {
+            true => Err(StreetViewError::OfflineCacheMiss { url: url.to_string() }),
+            false => Ok(()),
+        }
+    }
+
+    /// Limit requests to `max_qps` per second, or remove the limit.
+    pub(crate) fn set_max_qps(&mut self, max_qps: Option<f64>) {
+        self.limiter = max_qps.map(|qps| Arc::new(RateLimiter::new(qps)));
+    }
+
+    /// Send a GET request to an undocumented endpoint, with the configured
+    /// user-agent and extra headers.
+    pub(crate) async fn get(&self, url: &str) -> Result<HttpResponse> {
+        self.send(url, true).await
+    }
+
+    /// Send a GET request and report it to the tracing subscriber and
+    /// metrics hook.
+    async fn send(&self, url: &str, unofficial: bool) -> Result<HttpResponse> {
+        self.ensure_online(url)?;
+        let started = Instant::now();
+        let result = self.execute(url, unofficial);
+        #[cfg(feature = "tracing")]
+        let result = tracing::Instrument::instrument(result, tracing::debug_span!("http", endpoint = endpoint_of(url)));
+        let result = result.await;
+        self.observe(url, &result, started.elapsed());
+        result
+    }
+
+    /// Send a GET request, waiting for a rate limit slot first.
+    ///
+    /// With a proxy pool, the request goes through the next proxy and the
+    /// outcome is reported back so failing proxies are dropped.
+    async fn execute(&self, url: &str, unofficial: bool) -> Result<HttpResponse> {
+        if let Some(limiter) = &self.limiter {
+            limiter.acquire().await;
+        }
+
+        let prepare = |client: &Client| {
+            let request = client.get(url);
+            match self.headers.as_ref().filter(|_| unofficial) {
+                Some(headers) => headers.apply(request),
+                None => request,
+            }
+        };
+
+        let Some(pool) = &self.proxies else {
+            let request = prepare(&self.client).build()?;
+            return self.timed(url, self.transport.execute(request)).await;
+        };
+        let (proxy, client) = pool.pick()?;
+        let request = prepare(&client).build()?;
+        let result = self.timed(url, ReqwestTransport::new(client).execute(request)).await;
+        pool.report(&proxy, result.is_ok());
+        result
+    }
+
+    /// Run `request`, failing with `StreetViewError::Timeout` if it takes
+    /// longer than the limit for the stage `url` belongs to.
+    async fn timed(&self, url: &str, request: impl Future<Output = Result<HttpResponse>>) -> Result<HttpResponse> {
+        let Some(stage) = self.endpoints.kind_of(url).map(Stage::from) else {
+            return request.await;
+        };
+        let Some(limit) = self.timeouts.get(stage) else {
+            return request.await;
+        };
+        let started = tokio::time::Instant::now();
+        tokio::time::timeout(limit, request).await.unwrap_or_else(|_| {
+            Err(StreetViewError::Timeout {
+                stage,
+                elapsed: started.elapsed(),
+            })
+        })
+    }
+
+    /// Send a GET request to an official Google Maps API, signing the URL
+    /// if a signing secret is configured.
+    pub(crate) async fn get_official(&self, url: &str) -> Result<HttpResponse> {
+        self.send(&self.sign_official(url)?, false).await
+    }
+
+    /// `url` with a `signature` parameter if a signing secret is configured.
+    pub(crate) fn sign_official(&self, url: &str) -> Result<String> {
+        match &self.signer {
+            Some(signer) => signer.sign(url),
+            None => Ok(url.to_string()),
+        }
+    }
+
+    /// Send a billable official API request, charging it to the quota
+    /// tracker first.
+    pub(crate) async fn get_billable(&self, url: &str) -> Result<HttpResponse> {
+        // Offline requests are never sent, so must not be charged
+        self.ensure_online(url)?;
+        if let Some(quota) = &self.quota {
+            quota.charge()?;
+        }
+        self.get_official(url).await
+    }
+
+    /// Fetch `url` and parse the body, going through the cache if one is
+    /// set. `official` routes the request through `get_official`.
+    ///
+    /// Rate limit and block responses, and error statuses from the
+    /// undocumented endpoints, fail with typed errors before `parse` runs.
+    /// The body is stored only if the request succeeded and `parse`
+    /// accepted it, so error pages and rate limit responses are not cached.
+    /// Concurrent fetches of the same URL, from this client or its clones,
+    /// are coalesced into one request, and a cache shared with other
+    /// processes is given the chance to [`claim`](Cache::claim) the download.
+    pub(crate) async fn fetch<T>(
+        &self,
+        url: &str,
+        official: bool,
+        parse: impl Fn(&[u8]) -> Result<T>,
+    ) -> Result<T> {
+        if let Some(value) = self.cached(url, &parse) {
+            return Ok(value);
+        }
+
+        // Another process sharing the cache may be downloading this URL
+        let mut claim = None;
+        if let Some(cache) = &self.cache {
+            let deadline = tokio::time::Instant::now() + CLAIM_TIMEOUT;
+            loop {
+                match cache.claim(url) {
+                    Some(Claim::Busy) if tokio::time::Instant::now() < deadline => {
+                        tokio::time::sleep(CLAIM_POLL_INTERVAL).await;
+                        if let Some(value) = self.cached(url, &parse) {
+                            return Ok(value);
+                        }
+                    }
+                    Some(Claim::Owned(guard)) => {
+                        // It may have finished just before we claimed it
+                        if let Some(value) = self.cached(url, &parse) {
+                            return Ok(value);
+                        }
+                        claim = Some(guard);
+                        break;
+                    }
+                    _ => break,
+                }
+            }
+        }
+
+        // Concurrent fetches of the same URL share one request
+        let response = self
+            .inflight
+            .run(url, || async {
+                match official {
+                    true => self.get_official(url).await,
+                    false => self.get(url).await,
+                }
+            })
+            .await?;
+        let value = check_response(&response, official).and_then(|()| parse(&response.body));
+        let value = match (value, &self.capture) {
+            (Err(error), Some(capture)) => {
+                capture.record(url, &response, &error);
+                return Err(error);
+            }
+            (value, _) => value?,
+        };
+
+        if let Some(cache) = self.cache.as_ref().filter(|_| response.status.is_success()) {
+            cache.put(url, &response.body);
+        }
+        drop(claim);
+        Ok(value)
+    }
+
+    /// Look up `url` in the cache, ignoring entries `parse` rejects.
+    fn cached<T>(&self, url: &str, parse: &impl Fn(&[u8]) -> Result<T>) -> Option<T> {
+        let body = self.cache.as_ref()?.get(url)?;
+        let value = parse(&body).ok()?;
+        #[cfg(feature = "tracing")]
+        tracing::trace!(endpoint = endpoint_of(url), "served from cache");
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            metrics.cache_hit(RequestKind::from(self.endpoints.kind_of(url)));
+        }
+        Some(value)
+    }
+
+    /// Send a GET request to an undocumented endpoint, bypassing the cache,
+    /// and fail with the same typed errors as `fetch` on error statuses.
+    ///
+    /// For responses that must not be cached, such as expiring signed URLs.
+    #[cfg_attr(not(any(feature = "mapillary", feature = "kartaview")), allow(dead_code))]
+    pub(crate) async fn get_checked(&self, url: &str) -> Result<HttpResponse> {
+        let response = self.get(url).await?;
+        check_response(&response, false)?;
+        Ok(response)
+    }
+
+    /// Report that a request to `url` failed with `error` and is about to be
+    /// retried for the `attempt`th time.
+    #[cfg_attr(not(feature = "unofficial"), allow(dead_code))]
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn observe_retry(&self, url: &str, attempt: u32, error: &StreetViewError) {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(endpoint = endpoint_of(url), attempt, %error, "retrying request");
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            metrics.retry(RequestKind::from(self.endpoints.kind_of(url)), attempt);
+        }
+    }
+
+    #[cfg_attr(not(any(feature = "tracing", feature = "metrics")), allow(unused_variables))]
+    fn observe(&self, url: &str, result: &Result<HttpResponse>, latency: Duration) {
+        #[cfg(feature = "tracing")]
+        match result {
+            Ok(response) => tracing::debug!(
+                endpoint = endpoint_of(url),
+                status = response.status.as_u16(),
+                version = ?response.version,
+                bytes = response.body.len(),
+                latency_ms = latency.as_millis() as u64,
+                "request finished"
+            ),
+            Err(error) => tracing::warn!(endpoint = endpoint_of(url), %error, "request failed"),
+        }
+
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            metrics.request(&RequestEvent {
+                kind: RequestKind::from(self.endpoints.kind_of(url)),
+                status: result.as_ref().ok().map(|response| response.status.as_u16()),
+                latency,
+                bytes: result.as_ref().map_or(0, |response| response.body.len()),
+                http2: result.as_ref().is_ok_and(|response| response.version == reqwest::Version::HTTP_2),
+            });
+        }
+    }
+}
+
+/// How long to wait for another process's claim on a cache entry before
+/// downloading it anyway.
+const CLAIM_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How often to check the cache while another process holds a claim.
+const CLAIM_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Markers of Google's "unusual traffic" CAPTCHA page.
+const BLOCK_PAGE_MARKERS: [&[u8]; 2] = [b"google.com/sorry/", b"unusual traffic"];
+
+/// Turn failed responses into typed errors.
+///
+/// Error statuses from official APIs are left to `parse`, since their JSON
+/// bodies carry Google's status codes.
+fn check_response(response: &HttpResponse, official: bool) -> Result<()> {
+    let status = response.status;
+    if status.is_success() {
+        return Ok(());
+    }
+    let body = || String::from_utf8_lossy(&response.body).into_owned();
+
+    let blocked = BLOCK_PAGE_MARKERS
+        .iter()
+        .any(|marker| response.body.windows(marker.len()).any(|window| window == *marker));
+    if blocked {
+        return Err(StreetViewError::Blocked {
+            status: status.as_u16(),
+            body: body(),
+        });
+    }
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        let retry_after = response
+            .headers
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok()?.trim().parse().ok())
+            .map(Duration::from_secs);
+        return Err(StreetViewError::RateLimited { retry_after, body: body() });
+    }
+    if !official {
+        return Err(StreetViewError::ApiError {
+            status: status.as_u16(),
+            message: body(),
+        });
+    }
+    Ok(())
+}
+
+/// The URL without its query string, which may hold an API key or signature.
+#[cfg(feature = "tracing")]
+fn endpoint_of(url: &str) -> &str {
+    url.split('?').next().unwrap_or(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_agent_rotation() {
+        let config = RequestHeaders::new().user_agents(["ua-1", "ua-2"]).header("X-Test", "1");
+        let strategy = HeaderStrategy::new(&config).unwrap();
+        let client = Client::new();
+
+        let agents: Vec<_> = (0..3)
+            .map(|_| {
+                let request = strategy.apply(client.get("https://example.com")).build().unwrap();
+                assert_eq!(request.headers()["x-test"], "1");
+                request.headers()[USER_AGENT].to_str().unwrap().to_string()
+            })
+            .collect();
+        assert_eq!(agents, ["ua-1", "ua-2", "ua-1"]);
+    }
+}
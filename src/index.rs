@@ -0,0 +1,244 @@
+//! SQLite index of stored panoramas.
+//!
+//! A [`PanoramaIndex`] records where each panorama in a
+//! [`PanoramaStore`](crate::store::PanoramaStore) came from and where it
+//! lives on disk, so large local corpora can be queried by area or capture
+//! date without walking the directory or re-reading images.
+//!
+//! Requires the `sqlite` feature.
+
+use crate::error::Result;
+use crate::types::{CaptureDate, Panorama};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use sha1::{Digest, Sha1};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS panoramas (
+    pano_id  TEXT NOT NULL,
+    zoom     INTEGER NOT NULL,
+    lat      REAL NOT NULL,
+    lon      REAL NOT NULL,
+    date     TEXT,
+    path     TEXT NOT NULL,
+    bytes    INTEGER NOT NULL,
+    checksum TEXT NOT NULL,
+    PRIMARY KEY (pano_id, zoom)
+);
+CREATE INDEX IF NOT EXISTS panoramas_location ON panoramas (lat, lon);
+CREATE INDEX IF NOT EXISTS panoramas_date ON panoramas (date);
+";
+
+const COLUMNS: &str = "pano_id, zoom, lat, lon, date, path, bytes, checksum";
+
+/// One stored panorama file.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IndexEntry {
+    /// Panorama ID
+    pub pano_id: String,
+    /// Zoom level the file was downloaded at
+    pub zoom: u8,
+    /// Latitude coordinate
+    pub lat: f64,
+    /// Longitude coordinate
+    pub lon: f64,
+    /// Capture month, if known
+    pub date: Option<CaptureDate>,
+    /// Path of the image file
+    pub path: PathBuf,
+    /// File size in bytes
+    pub bytes: u64,
+    /// SHA-1 of the file contents, hex encoded
+    pub checksum: String,
+}
+
+impl IndexEntry {
+    /// Describe a stored file, reading it to compute its size and checksum.
+    pub fn from_file(pano: &Panorama, zoom: u8, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let data = std::fs::read(path)?;
+        Ok(Self {
+            pano_id: pano.pano_id.clone(),
+            zoom,
+            lat: pano.lat,
+            lon: pano.lon,
+            date: pano.date,
+            path: path.to_path_buf(),
+            bytes: data.len() as u64,
+            checksum: Sha1::digest(&data).iter().map(|b| format!("{b:02x}")).collect(),
+        })
+    }
+
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            pano_id: row.get(0)?,
+            zoom: row.get::<_, i64>(1)? as u8,
+            lat: row.get(2)?,
+            lon: row.get(3)?,
+            date: row.get::<_, Option<String>>(4)?.and_then(|date| date.parse().ok()),
+            path: PathBuf::from(row.get::<_, String>(5)?),
+            bytes: row.get::<_, i64>(6)? as u64,
+            checksum: row.get(7)?,
+        })
+    }
+}
+
+/// A SQLite database of stored panoramas.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rsstreetview::index::PanoramaIndex;
+/// # fn main() -> rsstreetview::Result<()> {
+/// let index = PanoramaIndex::open("panoramas/index.sqlite")?;
+/// for entry in index.find_in_bbox(41.89, 12.47, 41.91, 12.49)? {
+///     println!("{} {:?} {}", entry.pano_id, entry.date, entry.path.display());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct PanoramaIndex {
+    conn: Mutex<Connection>,
+}
+
+impl PanoramaIndex {
+    /// Open or create an index database at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::init(Connection::open(path)?)
+    }
+
+    /// Create an index that lives in memory only.
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Add an entry, replacing any existing entry for the same pano_id and zoom.
+    pub fn insert(&self, entry: &IndexEntry) -> Result<()> {
+        self.lock().execute(
+            &format!("INSERT OR REPLACE INTO panoramas ({COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)"),
+            params![
+                entry.pano_id,
+                entry.zoom as i64,
+                entry.lat,
+                entry.lon,
+                entry.date.map(|date| date.to_string()),
+                entry.path.to_string_lossy(),
+                entry.bytes as i64,
+                entry.checksum,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Look up a panorama at a zoom level.
+    pub fn get(&self, pano_id: &str, zoom: u8) -> Result<Option<IndexEntry>> {
+        Ok(self
+            .lock()
+            .query_row(
+                &format!("SELECT {COLUMNS} FROM panoramas WHERE pano_id = ?1 AND zoom = ?2"),
+                params![pano_id, zoom as i64],
+                IndexEntry::from_row,
+            )
+            .optional()?)
+    }
+
+    /// Remove a panorama at a zoom level. Returns whether it was indexed.
+    pub fn remove(&self, pano_id: &str, zoom: u8) -> Result<bool> {
+        let removed = self.lock().execute(
+            "DELETE FROM panoramas WHERE pano_id = ?1 AND zoom = ?2",
+            params![pano_id, zoom as i64],
+        )?;
+        Ok(removed > 0)
+    }
+
+    /// Number of indexed files.
+    pub fn len(&self) -> Result<usize> {
+        let count: i64 = self.lock().query_row("SELECT COUNT(*) FROM panoramas", [], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
+    /// Whether the index is empty.
+    pub fn is_empty(&self) -> Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Panoramas inside a latitude/longitude box, inclusive.
+    pub fn find_in_bbox(&self, min_lat: f64, min_lon: f64, max_lat: f64, max_lon: f64) -> Result<Vec<IndexEntry>> {
+        self.query(
+            &format!(
+                "SELECT {COLUMNS} FROM panoramas \
+                 WHERE lat BETWEEN ?1 AND ?2 AND lon BETWEEN ?3 AND ?4 ORDER BY pano_id, zoom"
+            ),
+            params![min_lat, max_lat, min_lon, max_lon],
+        )
+    }
+
+    /// Panoramas captured between two months, inclusive.
+    ///
+    /// Panoramas without a known date are never returned.
+    pub fn find_by_date_range(&self, from: CaptureDate, to: CaptureDate) -> Result<Vec<IndexEntry>> {
+        self.query(
+            &format!("SELECT {COLUMNS} FROM panoramas WHERE date BETWEEN ?1 AND ?2 ORDER BY date, pano_id"),
+            params![from.to_string(), to.to_string()],
+        )
+    }
+
+    fn query(&self, sql: &str, params: impl rusqlite::Params) -> Result<Vec<IndexEntry>> {
+        let conn = self.lock();
+        let mut stmt = conn.prepare(sql)?;
+        let entries = stmt
+            .query_map(params, IndexEntry::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(pano_id: &str, lat: f64, lon: f64, date: Option<&str>) -> IndexEntry {
+        IndexEntry {
+            pano_id: pano_id.to_string(),
+            zoom: 4,
+            lat,
+            lon,
+            date: date.map(|d| d.parse().unwrap()),
+            path: PathBuf::from(format!("{pano_id}_z4.jpg")),
+            bytes: 1000,
+            checksum: "00".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_index_queries() {
+        let index = PanoramaIndex::open_in_memory().unwrap();
+        index.insert(&entry("A", 41.90, 12.47, Some("2019-05"))).unwrap();
+        index.insert(&entry("B", 41.95, 12.50, Some("2021-08"))).unwrap();
+        index.insert(&entry("C", 48.85, 2.35, None)).unwrap();
+        index.insert(&entry("A", 41.90, 12.47, Some("2019-05"))).unwrap();
+        assert_eq!(index.len().unwrap(), 3);
+
+        let rome: Vec<_> = index.find_in_bbox(41.8, 12.4, 42.0, 12.6).unwrap();
+        assert_eq!(rome.iter().map(|e| e.pano_id.as_str()).collect::<Vec<_>>(), ["A", "B"]);
+
+        let recent = index.find_by_date_range("2020-01".parse().unwrap(), "2022-12".parse().unwrap()).unwrap();
+        assert_eq!(recent, [entry("B", 41.95, 12.50, Some("2021-08"))]);
+
+        assert_eq!(index.get("C", 4).unwrap().unwrap().lat, 48.85);
+        assert!(index.remove("C", 4).unwrap());
+        assert!(index.get("C", 4).unwrap().is_none());
+    }
+}
@@ -0,0 +1,883 @@
+//! Resumable scrape jobs.
+//!
+//! A [`ScrapeJob`] takes a list of coordinates and panorama IDs, finds the
+//! panoramas at each coordinate, filters them, and downloads everything
+//! into a [`PanoramaStore`]. Progress is checkpointed to a JSON state file,
+//! so a job that crashes or is killed picks up where it left off when run
+//! again, and every run ends with a [`JobReport`]. [`JobHook`]s are told
+//! about panoramas found, stored, and failed as the job runs, to keep
+//! queues, databases, or dashboards up to date during long scrapes.
+//!
+//! The state file records a SHA-256 hash of every stored image, and a
+//! resumed job checks them, so files truncated or modified since are
+//! downloaded again.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use rsstreetview::{CaptureDate, StreetView};
+//! # use rsstreetview::jobs::ScrapeJob;
+//! # use rsstreetview::store::PanoramaStore;
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let store = PanoramaStore::new(StreetView::new(), "dataset");
+//! let report = ScrapeJob::new(store, 4)
+//!     .locations([(41.8982, 12.4764), (41.9009, 12.4833)])
+//!     .captured_between(CaptureDate::new(2018, 1), None)
+//!     .newest_only()
+//!     .state_file("dataset/job.json")
+//!     .run()
+//!     .await?;
+//! println!("{report}");
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::download::MemoryBudget;
+use crate::error::{Result, StreetViewError};
+use crate::store::PanoramaStore;
+use crate::types::{CaptureDate, Panorama};
+use futures::future::BoxFuture;
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Something for a job to download.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum JobTarget {
+    /// Every panorama found at a coordinate, after the job's filters
+    Location {
+        /// Latitude
+        lat: f64,
+        /// Longitude
+        lon: f64,
+    },
+    /// One panorama by ID, downloaded without searching or filtering
+    Panorama(String),
+}
+
+impl JobTarget {
+    /// Key identifying the target in the state file.
+    fn key(&self) -> String {
+        match self {
+            JobTarget::Location { lat, lon } => format!("{lat},{lon}"),
+            JobTarget::Panorama(pano_id) => pano_id.clone(),
+        }
+    }
+}
+
+/// Something that happened while a job ran.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "event", rename_all = "snake_case"))]
+pub enum JobEvent {
+    /// A location search found a panorama new to the job, after filtering.
+    /// Panoramas listed by ID are not reported.
+    Discovered {
+        /// The location searched
+        target: JobTarget,
+        /// The search result
+        panorama: Panorama,
+    },
+    /// A panorama is in the store
+    Completed {
+        /// Panorama ID
+        pano_id: String,
+        /// Path of the stored image
+        path: PathBuf,
+        /// Whether this job downloaded it, rather than finding it stored
+        downloaded: bool,
+    },
+    /// A search or download failed; it is retried on the next run until
+    /// `attempts` reaches the job's maximum
+    Failed(JobFailure),
+}
+
+/// Receives [`JobEvent`]s as a job runs.
+///
+/// Hooks are awaited in turn after each event, before the job moves on, so
+/// a slow hook slows the job rather than letting events pile up. Handle
+/// errors inside the hook; they don't stop the job.
+///
+/// Any `Fn(JobEvent) -> impl Future<Output = ()>` closure is a hook.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rsstreetview::StreetView;
+/// # use rsstreetview::jobs::{JobEvent, ScrapeJob};
+/// # use rsstreetview::store::PanoramaStore;
+/// # async fn notify(_: String) {}
+/// let job = ScrapeJob::new(PanoramaStore::new(StreetView::new(), "out"), 3).with_hook(|event: JobEvent| async move {
+///     if let JobEvent::Completed { pano_id, .. } = event {
+///         notify(pano_id).await;
+///     }
+/// });
+/// ```
+pub trait JobHook: Send + Sync {
+    /// Handle one event.
+    fn on_event(&self, event: JobEvent) -> BoxFuture<'_, ()>;
+}
+
+impl<F, Fut> JobHook for F
+where
+    F: Fn(JobEvent) -> Fut + Send + Sync,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    fn on_event(&self, event: JobEvent) -> BoxFuture<'_, ()> {
+        Box::pin(self(event))
+    }
+}
+
+/// A batch download of panoramas into a store.
+///
+/// Jobs run in two phases: every location is searched, then every
+/// panorama found (or listed by ID) is downloaded with
+/// [`PanoramaStore::get_or_download_panorama`], so panoramas already in the
+/// store are not fetched again. A panorama found from several locations is
+/// downloaded once.
+///
+/// When resuming, every panorama the state file lists as stored is hashed
+/// and compared with the hash recorded when it was stored; missing or
+/// changed files are downloaded again.
+///
+/// Failed searches and downloads are recorded and retried on the next run,
+/// up to [`max_attempts`](Self::max_attempts) times. Errors that retrying
+/// cannot fix (`Blocked`, `QuotaBudgetExceeded`, `PolicyViolation`, and
+/// `OfflineCacheMiss`) stop the job: the state is saved and the error
+/// returned.
+#[derive(Clone)]
+pub struct ScrapeJob {
+    store: PanoramaStore,
+    zoom: u8,
+    targets: Vec<JobTarget>,
+    captured_after: Option<CaptureDate>,
+    captured_before: Option<CaptureDate>,
+    newest_only: bool,
+    concurrency: usize,
+    max_memory_bytes: Option<u64>,
+    max_attempts: u32,
+    state_file: Option<PathBuf>,
+    checkpoint_every: usize,
+    hooks: Vec<Arc<dyn JobHook>>,
+}
+
+impl ScrapeJob {
+    /// Create a job downloading panoramas at `zoom` into `store`.
+    ///
+    /// Defaults: no targets or filters, 4 panoramas at a time, 3 attempts
+    /// per panorama, and no state file.
+    pub fn new(store: PanoramaStore, zoom: u8) -> Self {
+        Self {
+            store,
+            zoom,
+            targets: Vec::new(),
+            captured_after: None,
+            captured_before: None,
+            newest_only: false,
+            concurrency: 4,
+            max_memory_bytes: None,
+            max_attempts: 3,
+            state_file: None,
+            checkpoint_every: 25,
+            hooks: Vec::new(),
+        }
+    }
+
+    /// Add a coordinate to search.
+    pub fn location(mut self, lat: f64, lon: f64) -> Self {
+        self.targets.push(JobTarget::Location { lat, lon });
+        self
+    }
+
+    /// Add coordinates to search.
+    pub fn locations(mut self, locations: impl IntoIterator<Item = (f64, f64)>) -> Self {
+        self.targets
+            .extend(locations.into_iter().map(|(lat, lon)| JobTarget::Location { lat, lon }));
+        self
+    }
+
+    /// Add a panorama by ID.
+    pub fn panorama(mut self, pano_id: impl Into<String>) -> Self {
+        self.targets.push(JobTarget::Panorama(pano_id.into()));
+        self
+    }
+
+    /// Add panoramas by ID.
+    pub fn panoramas<S: Into<String>>(mut self, pano_ids: impl IntoIterator<Item = S>) -> Self {
+        self.targets
+            .extend(pano_ids.into_iter().map(|id| JobTarget::Panorama(id.into())));
+        self
+    }
+
+    /// Only keep panoramas captured within a range of months (inclusive).
+    ///
+    /// Panoramas without a capture date are dropped when either bound is
+    /// set; year-only dates match when any month of the year is in range.
+    /// Applies to panoramas found by location search.
+    pub fn captured_between(mut self, after: Option<CaptureDate>, before: Option<CaptureDate>) -> Self {
+        self.captured_after = after;
+        self.captured_before = before;
+        self
+    }
+
+    /// Only keep the most recent capture at each location.
+    ///
+    /// Undated panoramas count as the most recent, since Google leaves the
+    /// current capture undated in search results.
+    pub fn newest_only(mut self) -> Self {
+        self.newest_only = true;
+        self
+    }
+
+    /// Set how many panoramas are searched for or downloaded at once.
+    ///
+    /// Each download fetches its tiles concurrently as well.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Limit the memory held by panoramas downloading at once.
+    ///
+    /// Each download reserves its expected peak memory, twice the
+    /// [`DownloadEstimate::memory_bytes`](crate::DownloadEstimate) at the
+    /// job's zoom, and waits while the reservations would exceed the
+    /// limit, so a batch of zoom 5 or 6 downloads can't exhaust the host's
+    /// memory however high the concurrency. A panorama larger than the
+    /// limit is downloaded on its own.
+    pub fn max_memory_bytes(mut self, bytes: u64) -> Self {
+        self.max_memory_bytes = Some(bytes);
+        self
+    }
+
+    /// Set how many runs may attempt a failed search or download before
+    /// it is left out of later runs.
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// Checkpoint progress to a JSON file, and resume from it if it exists.
+    ///
+    /// Keep one state file per job: it records targets by coordinate or
+    /// ID, so targets added to a job later are picked up on the next run.
+    pub fn state_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.state_file = Some(path.into());
+        self
+    }
+
+    /// Save the state file after this many searches or downloads finish
+    /// (default 25). It is also saved when the job ends.
+    pub fn checkpoint_every(mut self, updates: usize) -> Self {
+        self.checkpoint_every = updates.max(1);
+        self
+    }
+
+    /// Add a hook told about panoramas discovered, stored, and failed.
+    pub fn with_hook(mut self, hook: impl JobHook + 'static) -> Self {
+        self.hooks.push(Arc::new(hook));
+        self
+    }
+
+    /// The job's targets.
+    pub fn targets(&self) -> &[JobTarget] {
+        &self.targets
+    }
+
+    /// Run the job, resuming from the state file if there is one.
+    ///
+    /// Returns `StreetViewError::ParseError` if the state file exists but
+    /// is not a job state, `StreetViewError::IoError` if it cannot be
+    /// read or written, or the error that stopped the job.
+    pub async fn run(&self) -> Result<JobReport> {
+        let started = Instant::now();
+        let mut state = match &self.state_file {
+            Some(path) => JobState::load(path).await?,
+            None => None,
+        };
+        let resumed = state.is_some();
+        let state = state.get_or_insert_with(JobState::default);
+        let mut run = Run { job: self, state, updates: 0, downloaded: 0, already_stored: 0, invalid: 0 };
+
+        if resumed {
+            run.verify().await?;
+        }
+        let result = match run.search().await {
+            Ok(()) => run.download().await,
+            Err(e) => Err(e),
+        };
+        run.checkpoint().await?;
+        result?;
+
+        Ok(JobReport {
+            targets: self.targets.len(),
+            searched: self.targets.iter().filter(|t| run.state.searched.contains(&t.key())).count(),
+            discovered: run.state.panos.len(),
+            completed: run.state.panos.values().filter(|p| p.status.is_done()).count(),
+            downloaded: run.downloaded,
+            already_stored: run.already_stored,
+            invalid: run.invalid,
+            failures: run.state.failures(),
+            resumed,
+            elapsed: started.elapsed(),
+        })
+    }
+
+    /// Whether a search result passes the date filter.
+    fn keep(&self, pano: &Panorama) -> bool {
+        if self.captured_after.is_none() && self.captured_before.is_none() {
+            return true;
+        }
+        pano.captured_within(self.captured_after, self.captured_before)
+    }
+
+    /// Filter the panoramas found at one location.
+    fn filter(&self, panos: Vec<Panorama>) -> Vec<Panorama> {
+        let mut panos: Vec<Panorama> = panos.into_iter().filter(|pano| self.keep(pano)).collect();
+        if self.newest_only {
+            // Undated first, then latest date, then first listed
+            let newest = panos
+                .iter()
+                .enumerate()
+                .max_by_key(|(i, pano)| (pano.date.is_none(), pano.date, std::cmp::Reverse(*i)))
+                .map(|(i, _)| i);
+            panos = newest.map(|i| vec![panos.swap_remove(i)]).unwrap_or_default();
+        }
+        panos
+    }
+}
+
+impl fmt::Debug for ScrapeJob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScrapeJob")
+            .field("dir", &self.store.dir())
+            .field("zoom", &self.zoom)
+            .field("targets", &self.targets.len())
+            .field("captured_after", &self.captured_after)
+            .field("captured_before", &self.captured_before)
+            .field("newest_only", &self.newest_only)
+            .field("concurrency", &self.concurrency)
+            .field("max_memory_bytes", &self.max_memory_bytes)
+            .field("max_attempts", &self.max_attempts)
+            .field("state_file", &self.state_file)
+            .field("hooks", &self.hooks.len())
+            .finish()
+    }
+}
+
+/// Progress saved in the state file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct JobState {
+    /// Keys of targets searched successfully
+    searched: BTreeSet<String>,
+    /// Failed searches by target key: attempts and last error
+    search_failures: BTreeMap<String, Attempts>,
+    /// Panoramas to download, by ID
+    panos: BTreeMap<String, PanoState>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Attempts {
+    attempts: u32,
+    error: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PanoState {
+    /// Search result, if found by location
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    panorama: Option<Panorama>,
+    status: PanoStatus,
+    #[serde(default)]
+    attempts: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+enum PanoStatus {
+    Pending,
+    Done {
+        path: PathBuf,
+        /// Hex SHA-256 of the file, missing in state files from older versions
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sha256: Option<String>,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+impl PanoStatus {
+    fn is_done(&self) -> bool {
+        matches!(self, PanoStatus::Done { .. })
+    }
+}
+
+impl JobState {
+    /// Read a state file, or `None` if it doesn't exist yet.
+    async fn load(path: &Path) -> Result<Option<Self>> {
+        match tokio::fs::read(path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map(Some)
+                .map_err(|e| StreetViewError::ParseError(format!("Invalid job state {}: {e}", path.display()))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Write the state file under a temporary name and rename it into
+    /// place, so a crash mid-write leaves the previous checkpoint intact.
+    async fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_vec_pretty(self).map_err(|e| StreetViewError::ParseError(e.to_string()))?;
+        let tmp = path.with_extension("json.part");
+        tokio::fs::write(&tmp, json).await?;
+        tokio::fs::rename(&tmp, path).await?;
+        Ok(())
+    }
+
+    fn failures(&self) -> Vec<JobFailure> {
+        let searches = self.search_failures.iter().map(|(key, failed)| JobFailure {
+            id: key.clone(),
+            error: failed.error.clone(),
+            attempts: failed.attempts,
+        });
+        let downloads = self.panos.iter().filter_map(|(pano_id, pano)| match &pano.status {
+            PanoStatus::Failed { error } => Some(JobFailure {
+                id: pano_id.clone(),
+                error: error.clone(),
+                attempts: pano.attempts,
+            }),
+            _ => None,
+        });
+        searches.chain(downloads).collect()
+    }
+}
+
+/// One run of a job over its state.
+struct Run<'a> {
+    job: &'a ScrapeJob,
+    state: &'a mut JobState,
+    updates: usize,
+    downloaded: usize,
+    already_stored: usize,
+    invalid: usize,
+}
+
+impl Run<'_> {
+    /// Check every stored panorama against its recorded hash, queueing
+    /// missing or changed files to be downloaded again.
+    ///
+    /// Files stored by older versions have no hash; they are trusted if
+    /// present and their hash is recorded.
+    async fn verify(&mut self) -> Result<()> {
+        let stored: Vec<(String, PathBuf, Option<String>)> = self
+            .state
+            .panos
+            .iter()
+            .filter_map(|(pano_id, pano)| match &pano.status {
+                PanoStatus::Done { path, sha256 } => Some((pano_id.clone(), path.clone(), sha256.clone())),
+                _ => None,
+            })
+            .collect();
+
+        let mut checks = stream::iter(stored)
+            .map(|(pano_id, path, expected)| async move {
+                let actual = match tokio::fs::try_exists(&path).await? {
+                    true => Some(hash_file(path.clone()).await?),
+                    false => None,
+                };
+                Ok::<_, StreetViewError>((pano_id, path, expected, actual))
+            })
+            .buffer_unordered(self.job.concurrency);
+        while let Some(checked) = checks.next().await {
+            let (pano_id, path, expected, actual) = checked?;
+            let Some(pano) = self.state.panos.get_mut(&pano_id) else { continue };
+            match (expected, actual) {
+                (Some(expected), Some(actual)) if expected == actual => {}
+                (None, Some(actual)) => pano.status = PanoStatus::Done { path, sha256: Some(actual) },
+                (_, actual) => {
+                    // Remove the bad file so the store doesn't return it
+                    if actual.is_some() {
+                        tokio::fs::remove_file(&path).await?;
+                    }
+                    pano.status = PanoStatus::Pending;
+                    self.invalid += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Search every location not searched yet, and queue panorama targets.
+    async fn search(&mut self) -> Result<()> {
+        let job = self.job;
+        let mut locations = Vec::new();
+        for target in &job.targets {
+            let key = target.key();
+            if self.state.searched.contains(&key) {
+                continue;
+            }
+            match target {
+                JobTarget::Panorama(pano_id) => {
+                    self.state.panos.entry(pano_id.clone()).or_insert_with(|| PanoState {
+                        panorama: None,
+                        status: PanoStatus::Pending,
+                        attempts: 0,
+                    });
+                    self.state.searched.insert(key);
+                }
+                JobTarget::Location { lat, lon } => {
+                    if self.state.search_failures.get(&key).is_none_or(|f| f.attempts < job.max_attempts) {
+                        locations.push((key, *lat, *lon));
+                    }
+                }
+            }
+        }
+
+        let client = job.store.client();
+        let mut searches = stream::iter(locations)
+            .map(|(key, lat, lon)| async move { (key, JobTarget::Location { lat, lon }, client.search_panoramas(lat, lon).await) })
+            .buffer_unordered(job.concurrency);
+        while let Some((key, target, result)) = searches.next().await {
+            match result {
+                Ok(panos) => {
+                    let mut discovered = Vec::new();
+                    for pano in job.filter(panos) {
+                        if !self.state.panos.contains_key(&pano.pano_id) {
+                            self.state.panos.insert(
+                                pano.pano_id.clone(),
+                                PanoState {
+                                    panorama: Some(pano.clone()),
+                                    status: PanoStatus::Pending,
+                                    attempts: 0,
+                                },
+                            );
+                            discovered.push(pano);
+                        }
+                    }
+                    self.state.search_failures.remove(&key);
+                    self.state.searched.insert(key);
+                    for panorama in discovered {
+                        self.emit(JobEvent::Discovered { target: target.clone(), panorama }).await;
+                    }
+                }
+                Err(StreetViewError::NoPanoramasFound) => {
+                    self.state.search_failures.remove(&key);
+                    self.state.searched.insert(key);
+                }
+                Err(e) if is_fatal(&e) => return Err(e),
+                Err(e) => {
+                    let failed = self.state.search_failures.entry(key.clone()).or_default();
+                    failed.attempts += 1;
+                    failed.error = e.to_string();
+                    let failure = JobFailure { id: key, error: failed.error.clone(), attempts: failed.attempts };
+                    self.emit(JobEvent::Failed(failure)).await;
+                }
+            }
+            self.updated().await?;
+        }
+        Ok(())
+    }
+
+    /// Download every panorama not downloaded yet.
+    async fn download(&mut self) -> Result<()> {
+        let job = self.job;
+        let pending: Vec<(String, Option<Panorama>)> = self
+            .state
+            .panos
+            .iter()
+            .filter(|(_, pano)| match pano.status {
+                PanoStatus::Done { .. } => false,
+                PanoStatus::Pending => true,
+                PanoStatus::Failed { .. } => pano.attempts < job.max_attempts,
+            })
+            .map(|(pano_id, pano)| (pano_id.clone(), pano.panorama.clone()))
+            .collect();
+
+        let store = &job.store;
+        let budget = job.max_memory_bytes.map(MemoryBudget::new);
+        let budget = budget.as_ref();
+        let mut downloads = stream::iter(pending)
+            .map(|(pano_id, panorama)| async move {
+                let _reserved = match budget {
+                    Some(budget) => Some(budget.reserve(MemoryBudget::cost(job.zoom)).await),
+                    None => None,
+                };
+                let result = match &panorama {
+                    Some(pano) => store.get_or_download_panorama(pano, job.zoom).await,
+                    None => store.get_or_download(pano_id.as_str(), job.zoom).await,
+                };
+                // Drop the image while its memory is still reserved
+                let result = match result {
+                    Ok(stored) => hash_file(stored.path.clone()).await.map(|sha256| (stored.path, stored.downloaded, sha256)),
+                    Err(e) => Err(e),
+                };
+                (pano_id, result)
+            })
+            .buffer_unordered(job.concurrency);
+        while let Some((pano_id, result)) = downloads.next().await {
+            let Some(pano) = self.state.panos.get_mut(&pano_id) else { continue };
+            let event = match result {
+                Ok((path, downloaded, sha256)) => {
+                    match downloaded {
+                        true => self.downloaded += 1,
+                        false => self.already_stored += 1,
+                    }
+                    pano.status = PanoStatus::Done { path: path.clone(), sha256: Some(sha256) };
+                    JobEvent::Completed { pano_id, path, downloaded }
+                }
+                Err(e) if is_fatal(&e) => return Err(e),
+                Err(e) => {
+                    pano.attempts += 1;
+                    pano.status = PanoStatus::Failed { error: e.to_string() };
+                    JobEvent::Failed(JobFailure { id: pano_id, error: e.to_string(), attempts: pano.attempts })
+                }
+            };
+            self.emit(event).await;
+            self.updated().await?;
+        }
+        Ok(())
+    }
+
+    /// Pass an event to every hook in turn.
+    async fn emit(&self, event: JobEvent) {
+        for hook in &self.job.hooks {
+            hook.on_event(event.clone()).await;
+        }
+    }
+
+    /// Count a finished search or download, checkpointing periodically.
+    async fn updated(&mut self) -> Result<()> {
+        self.updates += 1;
+        if self.updates.is_multiple_of(self.job.checkpoint_every) {
+            self.checkpoint().await?;
+        }
+        Ok(())
+    }
+
+    async fn checkpoint(&self) -> Result<()> {
+        match &self.job.state_file {
+            Some(path) => self.state.save(path).await,
+            None => Ok(()),
+        }
+    }
+}
+
+/// Hex SHA-256 of a file, computed on the blocking thread pool.
+async fn hash_file(path: PathBuf) -> Result<String> {
+    tokio::task::spawn_blocking(move || {
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+        Ok(hasher.finalize().iter().map(|b| format!("{b:02x}")).collect())
+    })
+    .await
+    .map_err(|e| StreetViewError::IoError(std::io::Error::other(e)))?
+}
+
+/// Errors that retrying later in the same job won't fix.
+fn is_fatal(error: &StreetViewError) -> bool {
+    matches!(
+        error,
+        StreetViewError::Blocked { .. }
+            | StreetViewError::QuotaBudgetExceeded(_)
+            | StreetViewError::PolicyViolation(_)
+            | StreetViewError::OfflineCacheMiss { .. }
+    )
+}
+
+/// A search or download that failed.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct JobFailure {
+    /// Panorama ID, or `"lat,lon"` for a failed search
+    pub id: String,
+    /// The last error
+    pub error: String,
+    /// Runs that have attempted it
+    pub attempts: u32,
+}
+
+/// Summary of a job after a run.
+///
+/// Counts cover the whole job, including previous runs, except
+/// `downloaded` and `already_stored`, which count this run's work.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct JobReport {
+    /// Targets in the job
+    pub targets: usize,
+    /// Targets searched (or, for panorama IDs, queued)
+    pub searched: usize,
+    /// Distinct panoramas to download after filtering
+    pub discovered: usize,
+    /// Panoramas in the store
+    pub completed: usize,
+    /// Panoramas downloaded by this run
+    pub downloaded: usize,
+    /// Panoramas this run found already in the store
+    pub already_stored: usize,
+    /// Stored panoramas this run found missing or changed since they were
+    /// stored, and queued to download again
+    pub invalid: usize,
+    /// Searches and downloads that have not succeeded
+    pub failures: Vec<JobFailure>,
+    /// Whether this run resumed from a state file
+    pub resumed: bool,
+    /// Duration of this run
+    pub elapsed: Duration,
+}
+
+impl JobReport {
+    /// Whether every target was searched and every panorama stored.
+    pub fn is_complete(&self) -> bool {
+        self.searched == self.targets && self.completed == self.discovered
+    }
+}
+
+impl fmt::Display for JobReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} of {} targets searched, {} of {} panoramas stored{}",
+            self.searched,
+            self.targets,
+            self.completed,
+            self.discovered,
+            if self.resumed { " (resumed)" } else { "" }
+        )?;
+        write!(
+            f,
+            "this run: {} downloaded, {} already stored, {} failed in {:.1}s",
+            self.downloaded,
+            self.already_stored,
+            self.failures.len(),
+            self.elapsed.as_secs_f64()
+        )?;
+        if self.invalid > 0 {
+            write!(f, "\n{} stored panoramas were missing or changed and downloaded again", self.invalid)?;
+        }
+        for failure in &self.failures {
+            write!(f, "\n  {} ({} attempts): {}", failure.id, failure.attempts, failure.error)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures::*;
+    use crate::testing::{MockResponse, MockTransport};
+    use crate::types::DateConfidence;
+    use crate::StreetView;
+
+    fn dated(pano_id: &str, year: u16) -> Panorama {
+        let mut pano = panorama(pano_id, 41.9, 12.5);
+        pano.date = CaptureDate::new(year, 6);
+        pano
+    }
+
+    #[test]
+    fn test_filter() {
+        let panos = vec![dated("A", 2015), dated("B", 2021), dated("C", 2018)];
+        let job = ScrapeJob::new(PanoramaStore::new(StreetView::new(), "out"), 1);
+        assert_eq!(job.filter(panos.clone()).len(), 3);
+
+        let recent = job.clone().captured_between(CaptureDate::new(2016, 1), None);
+        let ids: Vec<String> = recent.filter(panos.clone()).into_iter().map(|p| p.pano_id).collect();
+        assert_eq!(ids, ["B", "C"]);
+
+        // A year-only 2017 date may fall after June, an exact January one not
+        let january = Panorama { date: CaptureDate::new(2017, 1), ..dated("D", 2017) };
+        let year_only = Panorama { pano_id: "E".into(), date_confidence: DateConfidence::YearOnly, ..january.clone() };
+        let summer = job.clone().captured_between(CaptureDate::new(2017, 6), CaptureDate::new(2017, 8));
+        let ids: Vec<String> = summer.filter(vec![january, year_only]).into_iter().map(|p| p.pano_id).collect();
+        assert_eq!(ids, ["E"]);
+
+        let newest = job.clone().newest_only();
+        assert_eq!(newest.filter(panos.clone())[0].pano_id, "B");
+        let mut with_current = panos;
+        with_current.push(panorama("D", 41.9, 12.5));
+        assert_eq!(newest.filter(with_current)[0].pano_id, "D");
+    }
+
+    #[tokio::test]
+    async fn test_resume_job() {
+        let dir = std::env::temp_dir().join("rsstreetview_job");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let panos = [
+            panorama("A000000000000000000000", 41.9, 12.5),
+            panorama("B000000000000000000000", 41.9, 12.5),
+        ];
+        let mock = MockTransport::new()
+            .route("SingleImageSearch", MockResponse::ok(search_response(&panos)))
+            .route("panoid=A000000000000000000000", MockResponse::image(tile_image()));
+        let client = StreetView::builder().mock_transport(mock.clone()).build().unwrap();
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let log = events.clone();
+        let job = ScrapeJob::new(PanoramaStore::new(client, &dir), 1)
+            .locations([(41.9, 12.5), (41.9001, 12.5001)])
+            .panorama("A000000000000000000000")
+            .state_file(dir.join("job.json"))
+            .max_memory_bytes(1_000_000)
+            .with_hook(move |event: JobEvent| {
+                let log = log.clone();
+                async move { log.lock().unwrap().push(event) }
+            });
+
+        // "B" has no tiles, so its download fails
+        let report = job.run().await.unwrap();
+        assert_eq!((report.targets, report.searched, report.discovered), (3, 3, 2));
+        assert_eq!((report.completed, report.downloaded), (1, 1));
+        assert_eq!(report.failures[0].id, "B000000000000000000000");
+        assert!(!report.is_complete() && !report.resumed);
+        // "A" was listed by ID, so only "B" is discovered, and only once
+        let mut kinds: Vec<String> = events
+            .lock()
+            .unwrap()
+            .drain(..)
+            .map(|event| match event {
+                JobEvent::Discovered { panorama, .. } => format!("discovered {}", &panorama.pano_id[..1]),
+                JobEvent::Completed { pano_id, downloaded, .. } => format!("completed {} {downloaded}", &pano_id[..1]),
+                JobEvent::Failed(failure) => format!("failed {} {}", &failure.id[..1], failure.attempts),
+            })
+            .collect();
+        // Downloads finish in any order
+        kinds.sort();
+        assert_eq!(kinds, ["completed A true", "discovered B", "failed B 1"]);
+
+        // Resuming retries only the failure
+        mock.add_route("panoid=B000000000000000000000", MockResponse::image(tile_image()));
+        let requests = mock.requests().len();
+        let report = job.run().await.unwrap();
+        assert!(report.resumed && report.is_complete());
+        assert_eq!((report.completed, report.downloaded, report.already_stored), (2, 1, 0));
+        // Two tiles at zoom 1
+        assert_eq!(mock.requests().len(), requests + 2);
+        assert!(matches!(&events.lock().unwrap()[..], [JobEvent::Completed { downloaded: true, .. }]));
+        assert!(report.to_string().starts_with("3 of 3 targets searched, 2 of 2 panoramas stored (resumed)"));
+
+        // A truncated output fails its hash check and is downloaded again
+        let stored = job.store.path("A000000000000000000000", 1);
+        let bytes = std::fs::read(&stored).unwrap();
+        std::fs::write(&stored, &bytes[..bytes.len() / 2]).unwrap();
+        let report = job.run().await.unwrap();
+        assert!(report.is_complete());
+        assert_eq!((report.invalid, report.downloaded, report.already_stored), (1, 1, 0));
+        assert_eq!(std::fs::read(&stored).unwrap().len(), bytes.len());
+        let report = job.run().await.unwrap();
+        assert_eq!((report.invalid, report.downloaded), (0, 0));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,327 @@
+//! KartaView (formerly OpenStreetCam) photos through its open API.
+//!
+//! [`KartaView`] searches KartaView's openly licensed imagery by bounding
+//! box, lists and downloads whole sequences, and implements
+//! [`PanoramaProvider`](crate::provider::PanoramaProvider) for its 360°
+//! photos.
+//!
+//! The API needs no key and does not touch any Google endpoint, so it works
+//! in builds without the `unofficial` feature. Requests go through the
+//! [`StreetView`](crate::StreetView) client the provider was created from,
+//! so its rate limit, proxies, cache, and retry policy apply.
+//!
+//! Requires the `kartaview` feature.
+
+use crate::error::{Result, StreetViewError};
+use crate::http::Http;
+use crate::provider::PanoramaProvider;
+use crate::types::{CaptureDate, DateConfidence, Location, Panorama};
+use crate::utils::bounding_box;
+use crate::StreetView;
+use futures::future::BoxFuture;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use image::DynamicImage;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+const API_ENDPOINT: &str = "https://api.openstreetcam.org/2.0";
+const PAGE_SIZE: u32 = 100;
+const MAX_PAGES: u32 = 100;
+const CONCURRENT_DOWNLOADS: usize = 8;
+const DEFAULT_RADIUS_M: f64 = 25.0;
+
+/// A photo returned by the KartaView API.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KartaViewPhoto {
+    /// Photo ID
+    pub id: String,
+    /// ID of the sequence the photo belongs to
+    pub sequence_id: String,
+    /// Position of the photo within its sequence
+    pub sequence_index: u32,
+    /// Latitude
+    pub lat: f64,
+    /// Longitude
+    pub lon: f64,
+    /// Camera heading in degrees from north
+    pub heading: Option<f64>,
+    /// Capture month
+    pub date: Option<CaptureDate>,
+    /// Whether this is a 360° equirectangular photo
+    pub is_pano: bool,
+    /// Full-resolution image URL
+    pub image_url: Option<String>,
+    /// Large thumbnail URL
+    pub thumbnail_url: Option<String>,
+}
+
+impl KartaViewPhoto {
+    /// The photo as a [`Panorama`].
+    pub fn to_panorama(&self) -> Panorama {
+        Panorama {
+            pano_id: self.id.clone(),
+            lat: self.lat,
+            lon: self.lon,
+            heading: self.heading.unwrap_or(0.0),
+            pitch: None,
+            roll: None,
+            date: self.date,
+            date_confidence: DateConfidence::of(self.date),
+            elevation: None,
+            copyright: None,
+            street_name: None,
+        }
+    }
+}
+
+/// KartaView open API provider.
+///
+/// Photo IDs are KartaView's numeric photo IDs as strings. The `zoom`
+/// passed to downloads picks the image size: 1 for the large thumbnail, and
+/// 2 or more for the full-resolution photo.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rsstreetview::kartaview::KartaView;
+/// # use rsstreetview::StreetView;
+/// # #[tokio::main]
+/// # async fn main() -> rsstreetview::Result<()> {
+/// let kartaview = KartaView::new(&StreetView::new());
+/// let photos = kartaview.search_bbox(46.76, 23.58, 46.78, 23.60).await?;
+/// let files = kartaview.download_sequence(&photos[0].sequence_id, "sequence").await?;
+/// println!("saved {} photos", files.len());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct KartaView {
+    http: Http,
+    radius: f64,
+}
+
+impl KartaView {
+    /// Create a provider sending requests through `client`.
+    pub fn new(client: &StreetView) -> Self {
+        Self {
+            http: client.http.clone(),
+            radius: DEFAULT_RADIUS_M,
+        }
+    }
+
+    /// Set the search radius in meters for [`search_photos`](Self::search_photos)
+    /// (default 25).
+    pub fn radius(mut self, meters: f64) -> Self {
+        self.radius = meters.max(1.0);
+        self
+    }
+
+    /// All photos inside a bounding box given in degrees.
+    ///
+    /// Results are fetched a page at a time, up to 10,000 photos.
+    pub async fn search_bbox(&self, south: f64, west: f64, north: f64, east: f64) -> Result<Vec<KartaViewPhoto>> {
+        let url = format!("{API_ENDPOINT}/photo/?bbTopLeft={north},{west}&bbBottomRight={south},{east}");
+        self.paginate(&url).await
+    }
+
+    /// Photos near a coordinate, nearest first. Includes both 360° and
+    /// regular perspective photos.
+    pub async fn search_photos(&self, lat: f64, lon: f64) -> Result<Vec<KartaViewPhoto>> {
+        let (south, west, north, east) = bounding_box(lat, lon, self.radius);
+        let mut photos = self.search_bbox(south, west, north, east).await?;
+        let target = Location::new(lat, lon);
+        let distance = |photo: &KartaViewPhoto| Location::new(photo.lat, photo.lon).distance_to(&target);
+        photos.sort_by(|a, b| distance(a).total_cmp(&distance(b)));
+        Ok(photos)
+    }
+
+    /// 360° photos near a coordinate, nearest first.
+    pub async fn search_panoramas(&self, lat: f64, lon: f64) -> Result<Vec<Panorama>> {
+        let photos = self.search_photos(lat, lon).await?;
+        Ok(photos.iter().filter(|photo| photo.is_pano).map(KartaViewPhoto::to_panorama).collect())
+    }
+
+    /// Look up a single photo by ID.
+    pub async fn photo(&self, photo_id: &str) -> Result<KartaViewPhoto> {
+        if photo_id.is_empty() || !photo_id.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(StreetViewError::InvalidPanoId(format!(
+                "{photo_id:?} (expected a numeric KartaView photo ID)"
+            )));
+        }
+        let url = format!("{API_ENDPOINT}/photo/{photo_id}");
+        self.http
+            .fetch(&url, false, |body| {
+                let (result, _) = parse_result(body)?;
+                parse_photo(&result["data"]).ok_or_else(|| StreetViewError::EndpointFormatChanged {
+                    path: "[\"result\"][\"data\"]".to_string(),
+                    body: String::from_utf8_lossy(body).into_owned(),
+                })
+            })
+            .await
+    }
+
+    /// Photos of a sequence, in capture order.
+    pub async fn sequence(&self, sequence_id: &str) -> Result<Vec<KartaViewPhoto>> {
+        let url = format!("{API_ENDPOINT}/sequence/{sequence_id}/photos?");
+        let mut photos = self.paginate(&url).await?;
+        photos.sort_by_key(|photo| photo.sequence_index);
+        Ok(photos)
+    }
+
+    /// Download a photo at full resolution, or as a large thumbnail with
+    /// `zoom` 1.
+    pub async fn download_photo(&self, photo: &KartaViewPhoto, zoom: u8) -> Result<DynamicImage> {
+        let bytes = self.photo_bytes(photo, zoom).await?;
+        Ok(image::load_from_memory(&bytes)?)
+    }
+
+    /// Download every photo of a sequence at full resolution into `dir`,
+    /// named `<sequence index>_<photo id>.jpg`, and return the paths in
+    /// capture order.
+    ///
+    /// Photos are written as served, without re-encoding.
+    pub async fn download_sequence(&self, sequence_id: &str, dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+        let dir = dir.as_ref();
+        tokio::fs::create_dir_all(dir).await?;
+        let photos = self.sequence(sequence_id).await?;
+
+        stream::iter(&photos)
+            .map(|photo| async move {
+                let bytes = self.photo_bytes(photo, 2).await?;
+                let path = dir.join(format!("{:05}_{}.jpg", photo.sequence_index, photo.id));
+                tokio::fs::write(&path, &bytes).await?;
+                Ok::<_, StreetViewError>(path)
+            })
+            .buffered(CONCURRENT_DOWNLOADS)
+            .try_collect()
+            .await
+    }
+
+    async fn photo_bytes(&self, photo: &KartaViewPhoto, zoom: u8) -> Result<Vec<u8>> {
+        let url = match zoom {
+            0 | 1 => photo.thumbnail_url.as_ref().or(photo.image_url.as_ref()),
+            _ => photo.image_url.as_ref().or(photo.thumbnail_url.as_ref()),
+        };
+        let url = url.ok_or_else(|| StreetViewError::InvalidResponse(format!("KartaView photo {} has no image", photo.id)))?;
+        Ok(self.http.get_checked(url).await?.body)
+    }
+
+    /// Fetch every page of a photo listing. `url` must end in `?` or carry
+    /// a query string already.
+    async fn paginate(&self, url: &str) -> Result<Vec<KartaViewPhoto>> {
+        let separator = if url.ends_with('?') { "" } else { "&" };
+        let mut photos = Vec::new();
+        for page in 1..=MAX_PAGES {
+            let page_url = format!("{url}{separator}itemsPerPage={PAGE_SIZE}&page={page}");
+            let (batch, more) = self.http.fetch(&page_url, false, parse_photo_page).await?;
+            photos.extend(batch);
+            if !more {
+                break;
+            }
+        }
+        Ok(photos)
+    }
+}
+
+impl PanoramaProvider for KartaView {
+    fn name(&self) -> &'static str {
+        "kartaview"
+    }
+
+    fn search(&self, lat: f64, lon: f64) -> BoxFuture<'_, Result<Vec<Panorama>>> {
+        Box::pin(self.search_panoramas(lat, lon))
+    }
+
+    fn download<'a>(&'a self, pano_id: &'a str, zoom: u8) -> BoxFuture<'a, Result<DynamicImage>> {
+        Box::pin(async move {
+            let photo = self.photo(pano_id).await?;
+            self.download_photo(&photo, zoom).await
+        })
+    }
+}
+
+/// Parse the `{"status": {...}, "result": {...}}` envelope, returning the
+/// result and whether more pages follow.
+fn parse_result(body: &[u8]) -> Result<(Value, bool)> {
+    let mut value: Value =
+        serde_json::from_slice(body).map_err(|e| StreetViewError::ParseError(format!("JSON parse error: {e}")))?;
+    let status = &value["status"];
+    if let Some(code) = status["httpCode"].as_u64().filter(|code| *code >= 400) {
+        return Err(StreetViewError::ApiError {
+            status: code as u16,
+            message: status["httpMessage"].as_str().unwrap_or_default().to_string(),
+        });
+    }
+    let more = value["result"]["hasMoreData"].as_bool().unwrap_or(false);
+    Ok((value["result"].take(), more))
+}
+
+fn parse_photo_page(body: &[u8]) -> Result<(Vec<KartaViewPhoto>, bool)> {
+    let (result, more) = parse_result(body)?;
+    let data = result["data"].as_array().ok_or_else(|| StreetViewError::EndpointFormatChanged {
+        path: "[\"result\"][\"data\"]".to_string(),
+        body: String::from_utf8_lossy(body).into_owned(),
+    })?;
+    Ok((data.iter().filter_map(parse_photo).collect(), more))
+}
+
+/// Parse a photo record. The API returns most numbers as strings, so both
+/// forms are accepted. Photos without an ID or location are skipped.
+fn parse_photo(photo: &Value) -> Option<KartaViewPhoto> {
+    let number = |key: &str| match &photo[key] {
+        Value::String(text) => text.trim().parse::<f64>().ok(),
+        value => value.as_f64(),
+    };
+    let text = |key: &str| match &photo[key] {
+        Value::String(text) if !text.is_empty() => Some(text.clone()),
+        Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    };
+
+    Some(KartaViewPhoto {
+        id: text("id")?,
+        sequence_id: text("sequenceId").unwrap_or_default(),
+        sequence_index: number("sequenceIndex").map_or(0, |index| index as u32),
+        lat: number("lat")?,
+        lon: number("lng")?,
+        heading: number("heading"),
+        date: text("shotDate").and_then(|date| date.parse().ok()),
+        is_pano: photo["projection"].as_str() == Some("SPHERE"),
+        image_url: text("fileurlProc").or_else(|| text("fileurl")),
+        thumbnail_url: text("fileurlLTh").or_else(|| text("fileurlTh")),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_photo_page() {
+        let body = br#"{
+            "status": {"apiCode": 600, "httpCode": 200, "httpMessage": "Success"},
+            "result": {"hasMoreData": true, "data": [
+                {"id": "501", "sequenceId": "77", "sequenceIndex": "3", "lat": "46.7712", "lng": "23.5897",
+                 "heading": "181.5", "shotDate": "2019-05-12 10:11:12", "projection": "SPHERE",
+                 "fileurlProc": "https://storage.example/proc/501.jpg", "fileurlLTh": "https://storage.example/lth/501.jpg"},
+                {"id": 502, "sequenceId": 77, "sequenceIndex": 4, "lat": 46.7713, "lng": 23.5898, "projection": "PLANE"},
+                {"id": "503"}
+            ]}
+        }"#;
+        let (photos, more) = parse_photo_page(body).unwrap();
+        assert!(more);
+        assert_eq!(photos.len(), 2);
+        assert_eq!((photos[0].lat, photos[0].lon, photos[0].heading), (46.7712, 23.5897, Some(181.5)));
+        assert_eq!((photos[0].sequence_id.as_str(), photos[0].sequence_index), ("77", 3));
+        assert_eq!(photos[0].date, CaptureDate::new(2019, 5));
+        assert!(photos[0].is_pano && !photos[1].is_pano);
+        assert_eq!((photos[1].id.as_str(), photos[1].sequence_index), ("502", 4));
+
+        let pano = photos[0].to_panorama();
+        assert_eq!((pano.pano_id.as_str(), pano.heading), ("501", 181.5));
+
+        let error = br#"{"status": {"apiCode": 612, "httpCode": 404, "httpMessage": "Not Found"}, "result": {}}"#;
+        assert!(matches!(parse_photo_page(error), Err(StreetViewError::ApiError { status: 404, .. })));
+    }
+}
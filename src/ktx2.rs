@@ -0,0 +1,602 @@
+//! KTX2 texture export for game engines and VR runtimes.
+//!
+//! Writes panoramas (as 2D textures) or six cubemap faces into a KTX 2.0
+//! container with a full mip chain, ready to be loaded by engines and
+//! `libktx`. Texels are stored as uncompressed RGBA8 by default. With the
+//! `basis` feature, [`Ktx2Encoding::Uastc`] and [`Ktx2Encoding::Etc1s`]
+//! compress them with the Basis Universal encoder (bundled C++, built by
+//! the `basis-universal` crate) into supercompressed textures that engines
+//! transcode to the GPU's native format at load time.
+
+use crate::error::{Result, StreetViewError};
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, RgbaImage};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+const KTX2_IDENTIFIER: [u8; 12] = [0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, 0x0D, 0x0A, 0x1A, 0x0A];
+const VK_FORMAT_R8G8B8A8_UNORM: u32 = 37;
+const VK_FORMAT_R8G8B8A8_SRGB: u32 = 43;
+/// Identifier, header, and index sizes in bytes.
+const HEADER_LEN: usize = 12 + 9 * 4 + 4 * 4 + 2 * 8;
+/// Size of one level index entry.
+const LEVEL_INDEX_LEN: usize = 3 * 8;
+const BYTES_PER_TEXEL: usize = 4;
+/// Zeros for aligning sections; no alignment exceeds 16 bytes.
+const PADDING: [u8; 16] = [0; 16];
+
+/// How texels are stored in a KTX2 file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Ktx2Encoding {
+    /// Uncompressed 8-bit RGBA (default)
+    #[default]
+    Rgba8,
+    /// Basis Universal UASTC: high quality 4×4 blocks, 8 bits per texel
+    #[cfg(feature = "basis")]
+    Uastc,
+    /// Basis Universal ETC1S with BasisLZ supercompression: much smaller
+    /// files at lower quality
+    #[cfg(feature = "basis")]
+    Etc1s,
+}
+
+/// Options for KTX2 export.
+#[derive(Debug, Clone)]
+pub struct Ktx2Options {
+    /// Generate a full mip chain down to 1×1 (default true)
+    pub mipmaps: bool,
+    /// Tag texels as sRGB rather than linear (default true)
+    pub srgb: bool,
+    /// Texel storage (default uncompressed RGBA8)
+    pub encoding: Ktx2Encoding,
+}
+
+impl Ktx2Options {
+    /// Create default KTX2 options.
+    pub fn new() -> Self {
+        Self {
+            mipmaps: true,
+            srgb: true,
+            encoding: Ktx2Encoding::Rgba8,
+        }
+    }
+
+    /// Set whether to generate mipmaps.
+    pub fn mipmaps(mut self, mipmaps: bool) -> Self {
+        self.mipmaps = mipmaps;
+        self
+    }
+
+    /// Set whether texels are tagged as sRGB.
+    pub fn srgb(mut self, srgb: bool) -> Self {
+        self.srgb = srgb;
+        self
+    }
+
+    /// Set how texels are stored.
+    pub fn encoding(mut self, encoding: Ktx2Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+}
+
+impl Default for Ktx2Options {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Export a panorama (or any image) as a 2D KTX2 texture.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rsstreetview::ktx2::{export_ktx2, Ktx2Options};
+/// # fn run(image: &image::DynamicImage) -> rsstreetview::Result<()> {
+/// export_ktx2(image, "panorama.ktx2", &Ktx2Options::new())?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn export_ktx2(img: &DynamicImage, path: impl AsRef<Path>, options: &Ktx2Options) -> Result<()> {
+    write_file(std::slice::from_ref(img), path.as_ref(), options)
+}
+
+/// Export six cubemap faces as a KTX2 cubemap texture.
+///
+/// Faces are in the Vulkan order +X, -X, +Y, -Y, +Z, -Z and must be square
+/// and equally sized.
+///
+/// # Errors
+///
+/// Returns `StreetViewError::EncodeError` if the faces differ in size or
+/// are not square.
+pub fn export_ktx2_cubemap(faces: &[DynamicImage; 6], path: impl AsRef<Path>, options: &Ktx2Options) -> Result<()> {
+    write_file(faces, path.as_ref(), options)
+}
+
+/// Encode one 2D image or six cubemap faces as KTX2 into any `Write` sink.
+///
+/// # Errors
+///
+/// Returns `StreetViewError::EncodeError` if `faces` is not 1 or 6 images,
+/// if cubemap faces differ in size or are not square, or if the Basis
+/// Universal encoder fails.
+pub fn encode_ktx2<W: Write>(faces: &[DynamicImage], options: &Ktx2Options, mut writer: W) -> Result<()> {
+    let (width, height) = match faces {
+        [img] => img.dimensions(),
+        [first, rest @ ..] if rest.len() == 5 => {
+            let size = first.dimensions();
+            if size.0 != size.1 || rest.iter().any(|face| face.dimensions() != size) {
+                return Err(StreetViewError::EncodeError(
+                    "Cubemap faces must be square and equally sized".to_string(),
+                ));
+            }
+            size
+        }
+        _ => {
+            return Err(StreetViewError::EncodeError(format!(
+                "KTX2 export needs 1 image or 6 cubemap faces, got {}",
+                faces.len()
+            )))
+        }
+    };
+
+    let faces: Vec<RgbaImage> = faces.iter().map(|face| face.to_rgba8()).collect();
+    let texture = match options.encoding {
+        Ktx2Encoding::Rgba8 => rgba8_texture(&faces, width, height, options),
+        #[cfg(feature = "basis")]
+        Ktx2Encoding::Uastc | Ktx2Encoding::Etc1s => basis::texture(&faces, options)?,
+    };
+    let level_count = texture.levels.len();
+    let kvd = key_value_data();
+
+    let dfd_offset = HEADER_LEN + LEVEL_INDEX_LEN * level_count;
+    let kvd_offset = dfd_offset + texture.dfd.len();
+    let kvd_end = kvd_offset + kvd.len();
+    let sgd_offset = if texture.sgd.is_empty() { 0 } else { align(kvd_end, 8) };
+    let mut data_offset = if texture.sgd.is_empty() { kvd_end } else { sgd_offset + texture.sgd.len() };
+
+    // Mip data is stored smallest level first
+    let mut level_offsets = vec![0usize; level_count];
+    for (level, data) in texture.levels.iter().enumerate().rev() {
+        data_offset = align(data_offset, texture.alignment);
+        level_offsets[level] = data_offset;
+        data_offset += data.len();
+    }
+
+    let mut header = Vec::with_capacity(dfd_offset);
+    header.extend_from_slice(&KTX2_IDENTIFIER);
+    for value in [
+        texture.vk_format,
+        1,
+        width,
+        height,
+        0,
+        0,
+        faces.len() as u32,
+        level_count as u32,
+        texture.supercompression,
+    ] {
+        header.extend_from_slice(&value.to_le_bytes());
+    }
+    for value in [dfd_offset, texture.dfd.len(), kvd_offset, kvd.len()] {
+        header.extend_from_slice(&(value as u32).to_le_bytes());
+    }
+    for value in [sgd_offset, texture.sgd.len()] {
+        header.extend_from_slice(&(value as u64).to_le_bytes());
+    }
+    for (offset, data) in level_offsets.iter().zip(&texture.levels) {
+        // Supercompressed levels have no meaningful uncompressed length
+        let uncompressed = if texture.supercompression == 0 { data.len() } else { 0 };
+        for value in [*offset, data.len(), uncompressed] {
+            header.extend_from_slice(&(value as u64).to_le_bytes());
+        }
+    }
+
+    writer.write_all(&header)?;
+    writer.write_all(&texture.dfd)?;
+    writer.write_all(&kvd)?;
+
+    let mut written = kvd_end;
+    if !texture.sgd.is_empty() {
+        writer.write_all(&PADDING[..sgd_offset - written])?;
+        writer.write_all(&texture.sgd)?;
+        written = sgd_offset + texture.sgd.len();
+    }
+    for (level, data) in texture.levels.iter().enumerate().rev() {
+        writer.write_all(&PADDING[..level_offsets[level] - written])?;
+        writer.write_all(data)?;
+        written = level_offsets[level] + data.len();
+    }
+
+    Ok(())
+}
+
+/// Encoded texel data and the header fields that describe it.
+struct Texture {
+    vk_format: u32,
+    supercompression: u32,
+    dfd: Vec<u8>,
+    /// Supercompression global data
+    sgd: Vec<u8>,
+    /// Every face of each mip level, face after face, largest level first
+    levels: Vec<Vec<u8>>,
+    /// Alignment of each level's data in the file
+    alignment: usize,
+}
+
+/// Uncompressed RGBA8 texels, with mips filtered here.
+fn rgba8_texture(faces: &[RgbaImage], width: u32, height: u32, options: &Ktx2Options) -> Texture {
+    let level_count = if options.mipmaps { mip_count(width, height) } else { 1 };
+    let levels = (0..level_count)
+        .map(|level| {
+            let (w, h) = ((width >> level).max(1), (height >> level).max(1));
+            faces
+                .iter()
+                .flat_map(|face| {
+                    if level == 0 {
+                        face.as_raw().clone()
+                    } else {
+                        image::imageops::resize(face, w, h, FilterType::Triangle).into_raw()
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    Texture {
+        vk_format: if options.srgb { VK_FORMAT_R8G8B8A8_SRGB } else { VK_FORMAT_R8G8B8A8_UNORM },
+        supercompression: 0,
+        dfd: data_format_descriptor(options.srgb),
+        sgd: Vec::new(),
+        levels,
+        alignment: BYTES_PER_TEXEL,
+    }
+}
+
+fn write_file(faces: &[DynamicImage], path: &Path, options: &Ktx2Options) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    encode_ktx2(faces, options, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Number of mip levels down to 1×1.
+fn mip_count(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+fn align(offset: usize, alignment: usize) -> usize {
+    offset.div_ceil(alignment) * alignment
+}
+
+/// Khronos basic data format descriptor for straight-alpha RGBA8.
+fn data_format_descriptor(srgb: bool) -> Vec<u8> {
+    const KHR_DF_MODEL_RGBSDA: u32 = 1;
+    const KHR_DF_PRIMARIES_BT709: u32 = 1;
+    const KHR_DF_TRANSFER_LINEAR: u32 = 1;
+    const KHR_DF_TRANSFER_SRGB: u32 = 2;
+    const KHR_DF_SAMPLE_DATATYPE_LINEAR: u32 = 0x10;
+    const CHANNEL_IDS: [u32; 4] = [0, 1, 2, 15];
+
+    let block_size = 24 + 16 * CHANNEL_IDS.len() as u32;
+    let transfer = if srgb { KHR_DF_TRANSFER_SRGB } else { KHR_DF_TRANSFER_LINEAR };
+
+    let mut words = vec![
+        4 + block_size,
+        0, // vendor 0 (Khronos), descriptor type 0 (basic)
+        2 | (block_size << 16),
+        KHR_DF_MODEL_RGBSDA | (KHR_DF_PRIMARIES_BT709 << 8) | (transfer << 16),
+        0, // 1×1×1×1 texel block
+        BYTES_PER_TEXEL as u32,
+        0,
+    ];
+    for (i, &channel) in CHANNEL_IDS.iter().enumerate() {
+        // Alpha is always linear, even in sRGB formats
+        let qualifiers = if srgb && channel == 15 { KHR_DF_SAMPLE_DATATYPE_LINEAR } else { 0 };
+        words.extend_from_slice(&[(i as u32 * 8) | (7 << 16) | ((channel | qualifiers) << 24), 0, 0, 255]);
+    }
+
+    words.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+/// Key/value data identifying the writer.
+fn key_value_data() -> Vec<u8> {
+    let entry = concat!("KTXwriter\0rsstreetview ", env!("CARGO_PKG_VERSION"), "\0");
+    let mut kvd = Vec::with_capacity(entry.len() + 8);
+    kvd.extend_from_slice(&(entry.len() as u32).to_le_bytes());
+    kvd.extend_from_slice(entry.as_bytes());
+    kvd.resize(align(kvd.len(), 4), 0);
+    kvd
+}
+
+#[cfg(feature = "basis")]
+mod basis {
+    use super::{Ktx2Encoding, Ktx2Options, Texture};
+    use crate::error::{Result, StreetViewError};
+    use basis_universal::{BasisTextureFormat, ColorSpace, Compressor, CompressorParams};
+    use image::RgbaImage;
+
+    const KTX2_SS_BASIS_LZ: u32 = 1;
+    const KHR_DF_MODEL_ETC1S: u32 = 163;
+    const KHR_DF_MODEL_UASTC: u32 = 166;
+    /// Size of a `.basis` slice description.
+    const SLICE_DESC_LEN: usize = 23;
+    /// Size of a UASTC block, which KTX2 level data must be aligned to.
+    const UASTC_BLOCK_LEN: usize = 16;
+
+    /// One compressed image of a `.basis` file.
+    struct Slice<'a> {
+        face: usize,
+        level: usize,
+        alpha: bool,
+        data: &'a [u8],
+    }
+
+    /// Compress `faces` with the Basis Universal encoder.
+    ///
+    /// The encoder writes `.basis` files, so its slices and codebooks are
+    /// repacked into KTX2 levels and global data the same way `basisu -ktx2`
+    /// does.
+    pub(super) fn texture(faces: &[RgbaImage], options: &Ktx2Options) -> Result<Texture> {
+        let uastc = options.encoding == Ktx2Encoding::Uastc;
+        let mut params = CompressorParams::new();
+        params.set_basis_format(if uastc { BasisTextureFormat::UASTC4x4 } else { BasisTextureFormat::ETC1S });
+        params.set_color_space(if options.srgb { ColorSpace::Srgb } else { ColorSpace::Linear });
+        params.set_generate_mipmaps(options.mipmaps);
+        params.set_mipmap_smallest_dimension(1);
+        for (i, face) in faces.iter().enumerate() {
+            params.source_image_mut(i as u32).init(face.as_raw(), face.width(), face.height(), 4);
+        }
+
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let mut compressor = Compressor::new(threads as u32);
+        // SAFETY: the parameters were set through the crate's checked setters
+        // and every source image was initialized from a full RGBA buffer
+        unsafe {
+            if !compressor.init(&params) {
+                return Err(StreetViewError::EncodeError("Basis encoder rejected its parameters".to_string()));
+            }
+            compressor
+                .process()
+                .map_err(|e| StreetViewError::EncodeError(format!("Basis encoding failed: {e:?}")))?;
+        }
+
+        let alpha = compressor.any_source_image_has_alpha();
+        repack(compressor.basis_file(), faces.len(), uastc, alpha, options.srgb)
+    }
+
+    /// Split a `.basis` file into KTX2 level data and, for ETC1S, BasisLZ
+    /// global data.
+    fn repack(basis: &[u8], faces: usize, uastc: bool, alpha: bool, srgb: bool) -> Result<Texture> {
+        let slice_count = read(basis, 14, 3)?;
+        let descs = read(basis, 65, 4)?;
+        let slices = (0..slice_count)
+            .map(|i| {
+                let desc = descs + i * SLICE_DESC_LEN;
+                Ok(Slice {
+                    face: read(basis, desc, 3)?,
+                    level: read(basis, desc + 3, 1)?,
+                    alpha: read(basis, desc + 4, 1)? & 1 != 0,
+                    data: bytes(basis, read(basis, desc + 13, 4)?, read(basis, desc + 17, 4)?)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let level_count = slices.iter().map(|slice| slice.level + 1).max().unwrap_or(0);
+        let mut levels = vec![Vec::new(); level_count];
+        // rgbSliceByteOffset, rgbSliceByteLength, alphaSliceByteOffset,
+        // alphaSliceByteLength per image, level by level
+        let mut image_descs = vec![[0u32; 4]; level_count * faces];
+        for slice in &slices {
+            let level = &mut levels[slice.level];
+            let desc = image_descs
+                .get_mut(slice.level * faces + slice.face)
+                .ok_or_else(|| StreetViewError::EncodeError("Basis encoder wrote an unexpected slice".to_string()))?;
+            let at = if slice.alpha { 2 } else { 0 };
+            desc[at..at + 2].copy_from_slice(&[level.len() as u32, slice.data.len() as u32]);
+            level.extend_from_slice(slice.data);
+        }
+
+        if uastc {
+            return Ok(Texture {
+                vk_format: 0,
+                supercompression: 0,
+                dfd: data_format_descriptor(KHR_DF_MODEL_UASTC, UASTC_BLOCK_LEN as u32, uastc_samples(alpha), srgb),
+                sgd: Vec::new(),
+                levels,
+                alignment: UASTC_BLOCK_LEN,
+            });
+        }
+
+        let endpoints = bytes(basis, read(basis, 41, 4)?, read(basis, 45, 3)?)?;
+        let selectors = bytes(basis, read(basis, 50, 4)?, read(basis, 54, 3)?)?;
+        let tables = bytes(basis, read(basis, 57, 4)?, read(basis, 61, 4)?)?;
+
+        let mut sgd = Vec::new();
+        sgd.extend_from_slice(&(read(basis, 39, 2)? as u16).to_le_bytes());
+        sgd.extend_from_slice(&(read(basis, 48, 2)? as u16).to_le_bytes());
+        // Endpoint, selector, table, and (empty) extended data lengths
+        for len in [endpoints.len(), selectors.len(), tables.len(), 0] {
+            sgd.extend_from_slice(&(len as u32).to_le_bytes());
+        }
+        for desc in &image_descs {
+            // imageFlags: every image is a key frame
+            sgd.extend_from_slice(&0u32.to_le_bytes());
+            for value in desc {
+                sgd.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        sgd.extend_from_slice(endpoints);
+        sgd.extend_from_slice(selectors);
+        sgd.extend_from_slice(tables);
+
+        Ok(Texture {
+            vk_format: 0,
+            supercompression: KTX2_SS_BASIS_LZ,
+            dfd: data_format_descriptor(KHR_DF_MODEL_ETC1S, 0, etc1s_samples(alpha), srgb),
+            sgd,
+            levels,
+            alignment: 1,
+        })
+    }
+
+    /// (bit offset, bit length, channel) of the UASTC block's one sample.
+    fn uastc_samples(alpha: bool) -> Vec<(u32, u32, u32)> {
+        const KHR_DF_CHANNEL_UASTC_RGB: u32 = 0;
+        const KHR_DF_CHANNEL_UASTC_RGBA: u32 = 3;
+        vec![(0, 128, if alpha { KHR_DF_CHANNEL_UASTC_RGBA } else { KHR_DF_CHANNEL_UASTC_RGB })]
+    }
+
+    /// (bit offset, bit length, channel) of the ETC1S color and alpha slices.
+    fn etc1s_samples(alpha: bool) -> Vec<(u32, u32, u32)> {
+        const KHR_DF_CHANNEL_ETC1S_RGB: u32 = 0;
+        const KHR_DF_CHANNEL_ETC1S_AAA: u32 = 15;
+        let mut samples = vec![(0, 64, KHR_DF_CHANNEL_ETC1S_RGB)];
+        if alpha {
+            samples.push((64, 64, KHR_DF_CHANNEL_ETC1S_AAA));
+        }
+        samples
+    }
+
+    /// Khronos basic data format descriptor for a 4×4 block-compressed
+    /// model.
+    fn data_format_descriptor(model: u32, bytes_plane0: u32, samples: Vec<(u32, u32, u32)>, srgb: bool) -> Vec<u8> {
+        const KHR_DF_PRIMARIES_BT709: u32 = 1;
+        const KHR_DF_TRANSFER_LINEAR: u32 = 1;
+        const KHR_DF_TRANSFER_SRGB: u32 = 2;
+
+        let block_size = 24 + 16 * samples.len() as u32;
+        let transfer = if srgb { KHR_DF_TRANSFER_SRGB } else { KHR_DF_TRANSFER_LINEAR };
+
+        let mut words = vec![
+            4 + block_size,
+            0, // vendor 0 (Khronos), descriptor type 0 (basic)
+            2 | (block_size << 16),
+            model | (KHR_DF_PRIMARIES_BT709 << 8) | (transfer << 16),
+            3 | (3 << 8), // 4×4 texel blocks
+            bytes_plane0,
+            0,
+        ];
+        for (offset, bits, channel) in samples {
+            words.extend_from_slice(&[offset | ((bits - 1) << 16) | (channel << 24), 0, 0, u32::MAX]);
+        }
+
+        words.iter().flat_map(|word| word.to_le_bytes()).collect()
+    }
+
+    /// Read a little-endian field of `len` bytes from a `.basis` file.
+    fn read(basis: &[u8], offset: usize, len: usize) -> Result<usize> {
+        let field = bytes(basis, offset, len)?;
+        Ok(field.iter().rev().fold(0, |value, &byte| value << 8 | byte as usize))
+    }
+
+    fn bytes(basis: &[u8], offset: usize, len: usize) -> Result<&[u8]> {
+        basis
+            .get(offset..offset + len)
+            .ok_or_else(|| StreetViewError::EncodeError("Basis encoder wrote a truncated file".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+        u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_ktx2_layout() {
+        let img = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(8, 4, image::Rgb([10, 20, 30])));
+        let mut bytes = Vec::new();
+        encode_ktx2(std::slice::from_ref(&img), &Ktx2Options::new(), &mut bytes).unwrap();
+
+        assert_eq!(bytes[..12], KTX2_IDENTIFIER);
+        assert_eq!(read_u32(&bytes, 12), VK_FORMAT_R8G8B8A8_SRGB);
+        assert_eq!((read_u32(&bytes, 20), read_u32(&bytes, 24)), (8, 4));
+        assert_eq!(read_u32(&bytes, 36), 1); // faces
+        assert_eq!(read_u32(&bytes, 40), 4); // 8×4, 4×2, 2×1, 1×1
+
+        // Level 0 is stored last and holds the full-size texels
+        let offset = read_u64(&bytes, HEADER_LEN) as usize;
+        let len = read_u64(&bytes, HEADER_LEN + 8) as usize;
+        assert_eq!(len, 8 * 4 * 4);
+        assert_eq!(offset + len, bytes.len());
+        assert_eq!(bytes[offset..offset + 4], [10, 20, 30, 255]);
+
+        // The smallest level follows the metadata, 4-byte aligned
+        let smallest = read_u64(&bytes, HEADER_LEN + 3 * LEVEL_INDEX_LEN) as usize;
+        assert_eq!(smallest % 4, 0);
+        assert_eq!(read_u32(&bytes, 48) as usize, HEADER_LEN + 4 * LEVEL_INDEX_LEN);
+    }
+
+    #[test]
+    fn test_cubemap_faces_validated() {
+        let face = DynamicImage::ImageRgba8(RgbaImage::new(16, 16));
+        let faces: [DynamicImage; 6] = std::array::from_fn(|_| face.clone());
+        let mut bytes = Vec::new();
+        encode_ktx2(&faces, &Ktx2Options::new().mipmaps(false), &mut bytes).unwrap();
+        assert_eq!(read_u32(&bytes, 36), 6);
+        assert_eq!(read_u64(&bytes, HEADER_LEN + 8), 6 * 16 * 16 * 4);
+
+        let mut uneven = faces.to_vec();
+        uneven[3] = DynamicImage::ImageRgba8(RgbaImage::new(8, 8));
+        assert!(encode_ktx2(&uneven, &Ktx2Options::new(), Vec::new()).is_err());
+        assert!(encode_ktx2(&faces[..2], &Ktx2Options::new(), Vec::new()).is_err());
+    }
+
+    #[cfg(feature = "basis")]
+    #[test]
+    fn test_basis_encodings() {
+        let img = DynamicImage::ImageRgb8(image::RgbImage::from_fn(16, 8, |x, y| image::Rgb([x as u8 * 16, y as u8 * 32, 128])));
+
+        let mut uastc = Vec::new();
+        encode_ktx2(std::slice::from_ref(&img), &Ktx2Options::new().encoding(Ktx2Encoding::Uastc), &mut uastc).unwrap();
+        assert_eq!(read_u32(&uastc, 12), 0); // VK_FORMAT_UNDEFINED
+        assert_eq!(read_u32(&uastc, 40), 5); // 16×8 down to 1×1
+        assert_eq!(read_u32(&uastc, 44), 0); // no supercompression
+        // Level 0 is 4×2 blocks of 16 bytes, stored last and block aligned
+        let offset = read_u64(&uastc, HEADER_LEN) as usize;
+        assert_eq!(read_u64(&uastc, HEADER_LEN + 8), 4 * 2 * 16);
+        assert_eq!((offset % 16, offset + 128), (0, uastc.len()));
+        let dfd = read_u32(&uastc, 48) as usize;
+        assert_eq!(uastc[dfd + 12], 166); // KHR_DF_MODEL_UASTC
+
+        let faces: [DynamicImage; 6] = std::array::from_fn(|_| img.crop_imm(0, 0, 8, 8));
+        let mut etc1s = Vec::new();
+        encode_ktx2(&faces, &Ktx2Options::new().encoding(Ktx2Encoding::Etc1s), &mut etc1s).unwrap();
+        assert_eq!((read_u32(&etc1s, 36), read_u32(&etc1s, 40)), (6, 4));
+        assert_eq!(read_u32(&etc1s, 44), 1); // BasisLZ
+        let dfd = read_u32(&etc1s, 48) as usize;
+        assert_eq!(etc1s[dfd + 12], 163); // KHR_DF_MODEL_ETC1S
+
+        // Global data: header, one image description per face and level,
+        // then the codebooks and tables
+        let sgd = read_u64(&etc1s, 64) as usize;
+        let sgd_len = read_u64(&etc1s, 72) as usize;
+        assert_eq!(sgd % 8, 0);
+        let codebooks: u32 = (0..3).map(|i| read_u32(&etc1s, sgd + 4 + 4 * i)).sum();
+        assert_eq!(sgd_len, 20 + 6 * 4 * 20 + codebooks as usize);
+        // Each image's color slice lies within its level
+        for level in 0..4 {
+            let level_len = read_u64(&etc1s, HEADER_LEN + level * LEVEL_INDEX_LEN + 8) as u32;
+            assert_eq!(read_u64(&etc1s, HEADER_LEN + level * LEVEL_INDEX_LEN + 16), 0);
+            let slices: Vec<_> = (0..6)
+                .map(|face| sgd + 20 + (level * 6 + face) * 20)
+                .map(|desc| (read_u32(&etc1s, desc + 4), read_u32(&etc1s, desc + 8)))
+                .collect();
+            assert!(slices.iter().all(|&(offset, len)| len > 0 && offset + len <= level_len));
+            assert_eq!(slices.iter().map(|&(_, len)| len).sum::<u32>(), level_len);
+        }
+    }
+}
@@ -7,6 +7,55 @@
 //! - Retrieve historical Street View photos
 //! - Download full panoramic images (360-degree)
 //! - Save images in multiple formats (JPEG, PNG, WebP)
+//! - Export a self-contained HTML viewer for sharing panoramas offline
+//! - Export KTX2 textures and cubemaps for game engines
+//! - Export search and crawl results as GeoJSON, CSV, or Parquet
+//! - Project panoramas to top-down orthophotos of the road surface
+//! - Detect changes between captures of the same place from different dates
+//! - Run resumable scrape jobs with checkpointing and summary reports
+//! - Export extracted views with camera poses as a COLMAP model
+//!
+//! ## Cargo features
+//!
+//! - `unofficial` (default): search, tile download, and view extraction via
+//!   Google's undocumented endpoints. Build with `default-features = false`
+//!   to compile these code paths out entirely, leaving only the official,
+//!   API-key based functions.
+//! - `nalgebra`: conversions from [`Pose`] to nalgebra quaternions and rotations.
+//! - `parallel`: multithreaded PNG encoding, selected with
+//!   [`SaveOptions::threads`].
+//! - `sqlite`: a SQLite index of stored panoramas, queryable by area and
+//!   capture date (`index::PanoramaIndex`).
+//! - `serde`: `Serialize`/`Deserialize` for configuration and result types
+//!   ([`ViewConfig`], [`SaveOptions`], [`Direction`], [`PanoId`], ...), so
+//!   job configs and manifests can be stored as JSON. Field names follow
+//!   the Rust fields, enums use snake_case, and missing fields in options
+//!   take their defaults. Errors serialize as `{"kind", "message"}`.
+//! - `parquet`: Parquet export of panorama listings
+//!   (`export::export_parquet`), alongside the always-available CSV and
+//!   GeoJSON exports.
+//! - `bing`: Bing Maps Streetside as a second panorama source
+//!   (`bing::BingStreetside`), usable through the [`PanoramaProvider`] trait
+//!   and `provider::search_with_fallback`.
+//! - `kartaview`: KartaView photos and sequences through its open API
+//!   (`kartaview::KartaView`), also a [`PanoramaProvider`] and also
+//!   independent of `unofficial`.
+//! - `mapillary`: Mapillary images through the official Graph API
+//!   (`mapillary::Mapillary`), also a [`PanoramaProvider`]. Independent of
+//!   `unofficial`, so it works in restricted builds.
+//! - `privacy`: face and license-plate blurring with pluggable detectors
+//!   (`privacy::Redactor`), applied to snapshots and stores before saving.
+//! - `video`: hyperlapse frames, GIFs, and MP4s (through `ffmpeg`) along a
+//!   sequence of panoramas (`video::Hyperlapse`).
+//! - `tracing`: `tracing` spans for searches, downloads, and metadata calls,
+//!   and events for every HTTP request and retry. URLs are logged without
+//!   their query string, so API keys stay out of logs.
+//! - `metrics`: hooks reporting request counts, latencies, bytes, cache
+//!   hits, and retries (`metrics` module).
+//! - `cli`: the `streetview` command-line binary (`search`, `download`,
+//!   `views`, `meta`, `crawl`).
+//! - `testing`: a mock transport and response fixtures for unit-testing code
+//!   that uses [`StreetView`] without network access (`testing` module).
 //!
 //! ## Example
 //!
@@ -30,29 +79,127 @@
 //! }
 //! ```
 
+#[cfg(feature = "bing")]
+pub mod bing;
+mod attribution;
+mod builder;
+pub mod cache;
+mod capture;
+pub mod change;
+pub mod colmap;
+pub mod dedup;
+pub mod depth;
+mod endpoints;
+mod embed;
 mod error;
+pub mod export;
+pub mod geoid;
 mod types;
-mod search;
+#[cfg(feature = "unofficial")]
+pub mod search;
+#[cfg(feature = "unofficial")]
 mod download;
+mod http;
+#[cfg(feature = "unofficial")]
+pub mod jobs;
+#[cfg(feature = "kartaview")]
+pub mod kartaview;
+#[cfg(feature = "mapillary")]
+pub mod mapillary;
+#[cfg(feature = "sqlite")]
+pub mod index;
 mod metadata;
+pub mod ortho;
+mod pano_id;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "unofficial")]
+pub mod photometa;
+#[cfg(feature = "parallel")]
+mod parallel;
 mod save;
-mod utils;
+mod sidecar;
+mod signing;
+mod singleflight;
+pub mod spatial;
+#[cfg(feature = "unofficial")]
+pub mod snapshot;
+#[cfg(feature = "unofficial")]
+pub mod store;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+mod transport;
+pub mod utils;
+#[cfg(feature = "video")]
+pub mod video;
+pub mod ktx2;
+pub mod policy;
+pub mod pose;
+#[cfg(feature = "privacy")]
+pub mod privacy;
+pub mod provider;
+pub mod proxy;
+pub mod quota;
+pub mod viewer;
 pub mod views;
 
+pub use attribution::{Attribution, AttributionPosition};
+pub use builder::StreetViewBuilder;
+pub use cache::{Cache, Claim, DiskCache, MemoryCache, PanoramaCache};
+pub use dedup::{DedupPanoramas, PanoSet, PositionTimeline};
+pub use depth::DepthMap;
+#[cfg(feature = "unofficial")]
+pub use download::{
+    DownloadEstimate, DownloadOptions, MissingTile, PanoramaTiles, PartialPanorama, PyramidLevel, TileOrder,
+    TilePriority, TilePyramid,
+};
+pub use endpoints::Endpoints;
 pub use error::{Result, StreetViewError};
-pub use types::{ImageFormat, Location, MetaData, Panorama, SaveOptions};
-pub use save::PanoramaSaveExt;
-pub use views::{Direction, ViewConfig};
+pub use export::ToGeoJson;
+pub use types::{
+    ApiStatus, CameraGeneration, CaptureDate, ChromaSubsampling, DateConfidence, ImageFormat, Locale, Location, MetaData, MetaOptions, MetaSource, Panorama, PngCompression, PngFilter,
+    RequestHeaders, SaveOptions, StaticImage, StaticLocation, StaticViewOptions,
+};
+pub use save::{
+    encode_to_async_writer, encode_to_writer, encode_with_metadata_to_writer, save_with_metadata,
+    save_with_thumbnails, thumbnail_path, PanoramaSaveExt,
+};
+#[cfg(feature = "unofficial")]
+pub use photometa::{PanoLink, PhotoMeta};
+pub use pano_id::{PanoId, PanoIdKind};
+pub use policy::Policy;
+pub use transport::{RetryPolicy, Stage, Timeouts};
+pub use colmap::{ColmapImage, ColmapModel, ColmapOptions};
+pub use pose::{GeoPosition, Pose, Quaternion};
+pub use provider::{MultiProvider, PanoramaProvider, ProviderPanorama};
+pub use proxy::{ProxyPool, ProxyRotation};
+pub use quota::QuotaTracker;
+pub use sidecar::{
+    read_saved_metadata, sidecar_path, MetadataSource, SavedMetadata, SidecarMetadata, SidecarView,
+};
+#[cfg(feature = "unofficial")]
+pub use snapshot::{SavedView, SnapshotOptions, SnapshotTarget};
+pub use spatial::PanoIndex;
+#[cfg(feature = "unofficial")]
+pub use store::{PanoramaStore, StoreLayout, StoredPanorama};
+#[cfg(feature = "sqlite")]
+pub use index::{IndexEntry, PanoramaIndex};
+pub use views::{BatchViewOptions, CameraIntrinsics, Direction, ExtractedView, ResampleFilter, ViewConfig};
 
+use http::Http;
 use reqwest::Client;
 
+/// Metadata requests in flight at once for `get_panorama_meta_batch`.
+const META_BATCH_CONCURRENCY: usize = 8;
+
 /// Main client for interacting with Google Street View.
 ///
 /// This client maintains a reusable HTTP client for efficient connection pooling.
 #[derive(Clone)]
 pub struct StreetView {
-    client: Client,
+    http: Http,
     api_key: Option<String>,
+    policy: Policy,
 }
 
 impl StreetView {
@@ -63,48 +210,200 @@ impl StreetView {
     /// Google Maps API functions.
     pub fn new() -> Self {
         Self {
-            client: Client::new(),
+            http: Http::new(Client::new()),
             api_key: None,
+            policy: Policy::default(),
         }
     }
 
+    /// Start building a client with custom settings.
+    ///
+    /// Use the builder to combine an API key, signing secret, custom
+    /// reqwest client, cache, rate limit, retry policy, endpoints, and
+    /// locale.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rsstreetview::StreetView;
+    /// # fn main() -> rsstreetview::Result<()> {
+    /// let client = StreetView::builder()
+    ///     .api_key("YOUR_API_KEY")
+    ///     .signing_secret("YOUR_SIGNING_SECRET")
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn builder() -> StreetViewBuilder {
+        StreetViewBuilder::new()
+    }
+
     /// Creates a new StreetView client with a Google Maps API key.
     ///
     /// The API key is required for:
     /// - `get_panorama_meta()` - Get official metadata
     /// - `get_streetview()` - Get partial Street View images
+    #[deprecated(note = "use `StreetView::builder().api_key(..).build()`")]
     pub fn with_api_key(api_key: impl Into<String>) -> Self {
         Self {
-            client: Client::new(),
+            http: Http::new(Client::new()),
             api_key: Some(api_key.into()),
+            policy: Policy::default(),
         }
     }
 
+    /// Creates a new StreetView client that signs official API requests.
+    ///
+    /// # Errors
+    ///
+    /// Returns `StreetViewError::ParseError` if the secret is not valid
+    /// URL-safe base64.
+    #[deprecated(note = "use `StreetView::builder().api_key(..).signing_secret(..).build()`")]
+    pub fn with_api_key_and_secret(api_key: impl Into<String>, secret: &str) -> Result<Self> {
+        Self::builder().api_key(api_key).signing_secret(secret).build()
+    }
+
     /// Creates a new StreetView client with a custom reqwest Client.
+    #[deprecated(note = "use `StreetView::builder().client(..).build()`")]
+    pub fn with_client(client: Client) -> Self {
+        Self {
+            http: Http::new(client),
+            api_key: None,
+            policy: Policy::default(),
+        }
+    }
+
+    /// Apply a usage policy to this client.
     ///
-    /// This allows you to configure the HTTP client with custom settings
-    /// such as proxies, timeouts, or custom headers.
+    /// The policy's request rate limit applies to every request the client
+    /// makes, and `official_endpoints_only` makes the undocumented-endpoint
+    /// methods fail with `StreetViewError::PolicyViolation`.
     ///
     /// # Example
     ///
     /// ```no_run
-    /// # use rsstreetview::StreetView;
-    /// # use reqwest::Client;
-    /// # use std::time::Duration;
+    /// # use rsstreetview::{Policy, StreetView};
+    /// let client = StreetView::new()
+    ///     .with_policy(Policy::new().max_qps(5.0).official_endpoints_only(true));
+    /// ```
+    pub fn with_policy(mut self, policy: Policy) -> Self {
+        self.http.set_max_qps(policy.max_qps);
+        self.policy = policy;
+        self
+    }
+
+    /// The usage policy applied to this client.
+    pub fn policy(&self) -> &Policy {
+        &self.policy
+    }
+
+    /// Track billable Static API requests, optionally enforcing a budget.
+    ///
+    /// Once a budget is spent, image requests fail with
+    /// `StreetViewError::QuotaBudgetExceeded` before anything is sent.
+    /// Clones of the client share the tracker.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rsstreetview::{QuotaTracker, StreetView};
+    /// let client = StreetView::new()
+    ///     .with_quota(QuotaTracker::new().daily_budget(1000));
+    /// ```
+    pub fn with_quota(mut self, tracker: QuotaTracker) -> Self {
+        self.http.set_quota(Some(std::sync::Arc::new(tracker)));
+        self
+    }
+
+    /// Cache search results, metadata, geocoding results, and tiles.
+    ///
+    /// Responses are keyed by request URL, so repeated runs during
+    /// development are served locally instead of downloading everything
+    /// again. Clones of the client share the cache.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rsstreetview::{DiskCache, StreetView};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = StreetView::new().with_cache(DiskCache::new(".streetview-cache")?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_cache(mut self, cache: impl Cache + 'static) -> Self {
+        self.http.set_cache(Some(std::sync::Arc::new(cache)));
+        self
+    }
+
+    /// Keep recently used panoramas in memory, decoded.
+    ///
+    /// Viewers and other interactive apps often extract several views of
+    /// the same panorama at different times; with a panorama cache only
+    /// the first one downloads and assembles the tiles. Use
+    /// [`prefetch_panorama`](Self::prefetch_panorama) to load a panorama
+    /// before its views are needed. Clones of the client share the cache.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rsstreetview::{PanoramaCache, StreetView, ViewConfig};
     /// # #[tokio::main]
     /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// let custom_client = Client::builder()
-    ///     .timeout(Duration::from_secs(60))
-    ///     .build()?;
-    /// let client = StreetView::with_client(custom_client);
+    /// // Room for about ten zoom 3 panoramas
+    /// let client = StreetView::new().with_panorama_cache(PanoramaCache::new(256 << 20));
+    /// let pano_id = "PANO_ID_HERE_0000000000";
+    /// client.prefetch_panorama(pano_id, 3).await?;
+    ///
+    /// // Served from memory
+    /// let east = client.extract_view(pano_id, &ViewConfig::new(90).zoom(3)).await?;
     /// # Ok(())
     /// # }
     /// ```
-    pub fn with_client(client: Client) -> Self {
-        Self {
-            client,
-            api_key: None,
-        }
+    pub fn with_panorama_cache(mut self, cache: PanoramaCache) -> Self {
+        self.http.set_panorama_cache(Some(std::sync::Arc::new(cache)));
+        self
+    }
+
+    /// The panorama cache attached to this client, if any.
+    pub fn panorama_cache(&self) -> Option<&PanoramaCache> {
+        self.http.panorama_cache().map(|cache| &**cache)
+    }
+
+    /// Spread requests across a pool of proxies.
+    ///
+    /// Each request goes through the next proxy in the pool; proxies that
+    /// keep failing are removed. Once the pool is empty, requests fail with
+    /// `StreetViewError::NoProxiesAvailable` rather than falling back to a
+    /// direct connection. Clones of the client share the pool.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rsstreetview::{ProxyPool, StreetView};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = StreetView::new()
+    ///     .with_proxy_pool(ProxyPool::new(["http://proxy1:8080", "http://proxy2:8080"])?);
+    /// if let Some(pool) = client.proxy_pool() {
+    ///     let dead = pool.check_health("https://www.google.com/generate_204").await;
+    ///     println!("removed {} dead proxies", dead.len());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_proxy_pool(mut self, pool: ProxyPool) -> Self {
+        self.http.set_proxy_pool(Some(std::sync::Arc::new(pool)));
+        self
+    }
+
+    /// The proxy pool attached to this client, if any.
+    pub fn proxy_pool(&self) -> Option<&ProxyPool> {
+        self.http.proxy_pool()
+    }
+
+    /// The quota tracker attached to this client, if any.
+    pub fn quota(&self) -> Option<&QuotaTracker> {
+        self.http.quota()
     }
 
     /// Search for panoramas at a given GPS coordinate.
@@ -129,22 +428,166 @@ impl StreetView {
     /// # Ok(())
     /// # }
     /// ```
+    #[cfg(feature = "unofficial")]
     pub async fn search_panoramas(&self, lat: f64, lon: f64) -> Result<Vec<Panorama>> {
-        search::search_panoramas(&self.client, lat, lon).await
+        self.policy.check_unofficial("search_panoramas")?;
+        search::search_panoramas(&self.http, lat, lon).await
+    }
+
+    /// Search many points as a stream of unique panoramas.
+    ///
+    /// For area, route, and coverage scans too large to collect: points
+    /// are consumed lazily with up to `concurrency` searches at once, and
+    /// each panorama is yielded once, as soon as the search that found it
+    /// completes. A failed search yields its error and the scan continues;
+    /// drop the stream to stop early.
+    ///
+    /// Deduplication remembers the 100,000 most recently found panorama
+    /// IDs (roughly 15 MB), so memory stays bounded however long the scan
+    /// runs. A panorama not found again within that many others may be
+    /// yielded a second time; deduplicate downstream when that matters.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use futures::StreamExt;
+    /// # use rsstreetview::StreetView;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = StreetView::new();
+    /// // A lazy grid of points every ~0.001° over a square kilometer
+    /// let points = (0..10).flat_map(|i| (0..10).map(move |j| (41.89 + 0.001 * i as f64, 12.47 + 0.001 * j as f64)));
+    /// let mut panos = client.search_stream(points, 8);
+    /// while let Some(pano) = panos.next().await {
+    ///     let pano = pano?;
+    ///     println!("{} {:?}", pano.pano_id, pano.date);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "unofficial")]
+    pub fn search_stream<'a, I>(
+        &'a self,
+        points: I,
+        concurrency: usize,
+    ) -> futures::stream::BoxStream<'a, Result<Panorama>>
+    where
+        I: IntoIterator<Item = (f64, f64)>,
+        I::IntoIter: Send + 'a,
+    {
+        use futures::StreamExt;
+
+        match self.policy.check_unofficial("search_panoramas") {
+            Ok(()) => search::search_stream(&self.http, points, concurrency),
+            Err(e) => futures::stream::once(async { Err(e) }).boxed(),
+        }
     }
 
     /// Search for panoramas from a Google Maps URL.
     ///
     /// Extracts the GPS coordinates from the URL and searches for panoramas.
+    #[cfg(feature = "unofficial")]
     pub async fn search_panoramas_url(&self, url: &str) -> Result<Vec<Panorama>> {
-        search::search_panoramas_url(&self.client, url).await
+        self.policy.check_unofficial("search_panoramas_url")?;
+        search::search_panoramas_url(&self.http, url).await
     }
 
     /// Find the exact panorama shown in a Google Maps URL.
     ///
     /// Returns the specific panorama if it can be identified from the URL.
+    #[cfg(feature = "unofficial")]
     pub async fn search_panoramas_url_exact(&self, url: &str) -> Result<Option<Panorama>> {
-        search::search_panoramas_url_exact(&self.client, url).await
+        self.policy.check_unofficial("search_panoramas_url_exact")?;
+        search::search_panoramas_url_exact(&self.http, url).await
+    }
+
+    /// Get rich metadata for a panorama without an API key.
+    ///
+    /// Uses the undocumented photometa endpoint, which reports the image
+    /// size at every zoom level, tile size, depth map presence, links to
+    /// neighbouring panoramas, address, and copyright.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rsstreetview::StreetView;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = StreetView::new();
+    /// let panos = client.search_panoramas(41.8982208, 12.4764804).await?;
+    /// let meta = client.get_photometa(&panos[0].pano_id).await?;
+    /// println!("Max zoom {}, {} links", meta.max_zoom(), meta.links.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "unofficial")]
+    pub async fn get_photometa(&self, pano_id: impl Into<PanoId>) -> Result<PhotoMeta> {
+        self.policy.check_unofficial("get_photometa")?;
+        let pano_id = PanoId::new(pano_id)?;
+        photometa::get_photometa(&self.http, pano_id.as_str()).await
+    }
+
+    /// Highest zoom level available for a panorama.
+    ///
+    /// Read from the panorama's photometa. When that lists no image sizes,
+    /// the tile server is probed instead, one tile per zoom level from 7
+    /// down. Google's own panoramas usually reach zoom 5; user-contributed
+    /// photospheres often stop at 4 or lower.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rsstreetview::StreetView;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = StreetView::new();
+    /// let zoom = client.get_max_zoom("PANO_ID_HERE_0000000000").await?.min(5);
+    /// let image = client.download_panorama("PANO_ID_HERE_0000000000", zoom).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "unofficial")]
+    pub async fn get_max_zoom(&self, pano_id: impl Into<PanoId>) -> Result<u8> {
+        self.policy.check_unofficial("get_max_zoom")?;
+        let pano_id = PanoId::new(pano_id)?;
+        let meta = photometa::get_photometa(&self.http, pano_id.as_str()).await?;
+        match meta.image_sizes.is_empty() {
+            false => Ok(meta.max_zoom()),
+            true => download::probe_max_zoom(&self.http, pano_id.as_str()).await,
+        }
+    }
+
+    /// Download and decode the depth map of a panorama.
+    ///
+    /// The depth map is a coarse (typically 512×256) equirectangular image
+    /// aligned with the panorama, giving the distance to the scene in
+    /// meters and marking sky pixels.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rsstreetview::StreetView;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = StreetView::new();
+    /// let panos = client.search_panoramas(41.8982208, 12.4764804).await?;
+    /// let depth = client.get_depth_map(&panos[0].pano_id).await?;
+    /// depth.sky_mask().save("sky.png")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `StreetViewError::InvalidResponse` if the panorama has no
+    /// depth map (e.g. user-contributed photospheres).
+    #[cfg(feature = "unofficial")]
+    pub async fn get_depth_map(&self, pano_id: impl Into<PanoId>) -> Result<DepthMap> {
+        let pano_id = pano_id.into();
+        self.get_photometa(&pano_id)
+            .await?
+            .depth_map()?
+            .ok_or_else(|| StreetViewError::InvalidResponse(format!("Panorama {pano_id} has no depth map")))
     }
 
     /// Download a full panorama image.
@@ -170,30 +613,352 @@ impl StreetView {
     /// # Ok(())
     /// # }
     /// ```
+    #[cfg(feature = "unofficial")]
     pub async fn download_panorama(
         &self,
-        pano_id: &str,
+        pano_id: impl Into<PanoId>,
         zoom: u8,
     ) -> Result<image::DynamicImage> {
-        download::download_panorama(&self.client, pano_id, zoom).await
+        self.policy.check_unofficial("download_panorama")?;
+        let pano_id = PanoId::new(pano_id)?;
+        let panorama = download::cached_panorama(&self.http, pano_id.as_str(), zoom).await?;
+        Ok(std::sync::Arc::unwrap_or_clone(panorama))
+    }
+
+    /// Download a panorama into the client's
+    /// [panorama cache](Self::with_panorama_cache), so later views of it
+    /// are extracted without waiting for the download.
+    ///
+    /// Does nothing if the panorama is already cached. Without a panorama
+    /// cache the download is wasted, apart from warming the response
+    /// cache if there is one.
+    #[cfg(feature = "unofficial")]
+    pub async fn prefetch_panorama(&self, pano_id: impl Into<PanoId>, zoom: u8) -> Result<()> {
+        self.policy.check_unofficial("download_panorama")?;
+        let pano_id = PanoId::new(pano_id)?;
+        if self.panorama_cache().is_some_and(|cache| cache.contains(pano_id.as_str(), zoom)) {
+            return Ok(());
+        }
+        download::cached_panorama(&self.http, pano_id.as_str(), zoom).await?;
+        Ok(())
+    }
+
+    /// Download a full panorama with [`DownloadOptions`]: tile concurrency
+    /// and which tiles to fetch first.
+    ///
+    /// Tiles covering the options' priority views download before the rest,
+    /// and the options' `on_priority_ready` callback gets the partly
+    /// assembled panorama as soon as they are in, so a view can be used
+    /// while the rest of the panorama is still downloading.
+    #[cfg(feature = "unofficial")]
+    pub async fn download_panorama_with(
+        &self,
+        pano_id: impl Into<PanoId>,
+        options: &DownloadOptions,
+    ) -> Result<image::DynamicImage> {
+        self.policy.check_unofficial("download_panorama")?;
+        let pano_id = PanoId::new(pano_id)?;
+        let clamped;
+        let options = match options.clamp_zoom {
+            true => {
+                let max_zoom = self.get_max_zoom(&pano_id).await?.max(1);
+                if options.zoom <= max_zoom {
+                    options
+                } else {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(
+                        pano_id = pano_id.as_str(),
+                        zoom = options.zoom,
+                        max_zoom,
+                        "zoom above the panorama's highest, clamping"
+                    );
+                    clamped = options.clone().zoom(max_zoom);
+                    &clamped
+                }
+            }
+            false => options,
+        };
+        let image = download::download_panorama_with(&self.http, pano_id.as_str(), options);
+        #[cfg(feature = "tracing")]
+        let image = tracing::Instrument::instrument(
+            image,
+            tracing::info_span!("download_panorama", pano_id = pano_id.as_str(), zoom = options.zoom),
+        );
+        image.await
+    }
+
+    /// Download a panorama's tiles without assembling them.
+    ///
+    /// The returned [`PanoramaTiles`] keeps the tiles encoded, so callers
+    /// that only need a view, a crop, or the tiles themselves never hold
+    /// the full panorama in memory.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rsstreetview::{StreetView, ViewConfig};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = StreetView::new();
+    /// let tiles = client.fetch_panorama_tiles("PANO_ID_HERE_0000000000", 5).await?;
+    /// // Decodes only the tiles the view covers
+    /// tiles.extract_view(&ViewConfig::new(90).fov(60))?.save("east.jpg")?;
+    /// tiles.save_tiles("tiles")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "unofficial")]
+    pub async fn fetch_panorama_tiles(&self, pano_id: impl Into<PanoId>, zoom: u8) -> Result<PanoramaTiles> {
+        self.policy.check_unofficial("download_panorama")?;
+        let pano_id = PanoId::new(pano_id)?;
+        let tiles = download::fetch_panorama_tiles(&self.http, pano_id.as_str(), zoom);
+        #[cfg(feature = "tracing")]
+        let tiles = tracing::Instrument::instrument(
+            tiles,
+            tracing::info_span!("download_panorama", pano_id = pano_id.as_str(), zoom),
+        );
+        tiles.await
+    }
+
+    /// Estimate the size of a panorama download before starting it.
+    ///
+    /// Downloads one tile to measure how well this panorama compresses,
+    /// then scales up to the whole tile grid, so applications can warn
+    /// before a multi-gigabyte zoom 7 download. For an estimate without any
+    /// request, use [`DownloadEstimate::for_zoom`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rsstreetview::StreetView;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = StreetView::new();
+    /// let estimate = client.estimate_download("PANO_ID_HERE_0000000000", 7).await?;
+    /// println!(
+    ///     "{} tiles, ~{} MB to download, {} MB in memory",
+    ///     estimate.tiles,
+    ///     estimate.approx_bytes / 1_000_000,
+    ///     estimate.memory_bytes / 1_000_000
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "unofficial")]
+    pub async fn estimate_download(&self, pano_id: impl Into<PanoId>, zoom: u8) -> Result<DownloadEstimate> {
+        self.policy.check_unofficial("download_panorama")?;
+        let pano_id = PanoId::new(pano_id)?;
+        download::estimate_download(&self.http, pano_id.as_str(), zoom).await
+    }
+
+    /// Save a panorama as a multi-zoom tile pyramid in `dir`, laid out as
+    /// `{zoom}/{y}/{x}.jpg` with a `pyramid.json` manifest.
+    ///
+    /// Every level is made of the tile server's own tiles at that zoom, so
+    /// nothing is assembled or downscaled locally. Tiles already in `dir`
+    /// are not downloaded again.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rsstreetview::StreetView;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = StreetView::new();
+    /// let pyramid = client.export_tile_pyramid("PANO_ID_HERE_0000000000", "tiles/pano", 1..=5).await?;
+    /// println!("{} tiles downloaded", pyramid.downloaded);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `StreetViewError::ParseError` if a zoom is not 1-7, and
+    /// `StreetViewError::PolicyViolation` with a `no_face_storage` policy.
+    #[cfg(feature = "unofficial")]
+    pub async fn export_tile_pyramid(
+        &self,
+        pano_id: impl Into<PanoId>,
+        dir: impl AsRef<std::path::Path>,
+        zooms: std::ops::RangeInclusive<u8>,
+    ) -> Result<TilePyramid> {
+        self.policy.check_unofficial("download_panorama")?;
+        self.policy.check_storage(false)?;
+        let pano_id = PanoId::new(pano_id)?;
+        download::export_tile_pyramid(&self.http, pano_id.as_str(), dir.as_ref(), zooms).await
+    }
+
+    /// Download a panorama, keeping whatever tiles succeed.
+    ///
+    /// Like [`download_panorama`](Self::download_panorama), but a tile that
+    /// still fails after its retries is reported in
+    /// [`PartialPanorama::missing_tiles`] and left black instead of
+    /// discarding the rest of the download.
+    ///
+    /// # Errors
+    ///
+    /// Fails only if no tile could be downloaded at all.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rsstreetview::StreetView;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = StreetView::new();
+    /// let mut pano = client.download_panorama_partial("PANO_ID_HERE_0000000000", 5).await?;
+    /// for tile in &pano.missing_tiles {
+    ///     eprintln!("tile ({}, {}) missing: {}", tile.x, tile.y, tile.error);
+    /// }
+    /// pano.fill_missing(image::Rgb([128, 128, 128]));
+    /// pano.image.save("panorama.jpg")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "unofficial")]
+    pub async fn download_panorama_partial(
+        &self,
+        pano_id: impl Into<PanoId>,
+        zoom: u8,
+    ) -> Result<PartialPanorama> {
+        self.policy.check_unofficial("download_panorama")?;
+        let pano_id = PanoId::new(pano_id)?;
+        download::download_panorama_partial(&self.http, pano_id.as_str(), zoom).await
     }
 
     /// Get official metadata for a panorama.
     ///
-    /// Requires an API key. Use `StreetView::with_api_key()` to set one.
+    /// Requires an API key. Use `StreetView::builder().api_key()` to set one.
     ///
     /// # Errors
     ///
     /// Returns an error if no API key is set.
-    pub async fn get_panorama_meta(&self, pano_id: &str) -> Result<MetaData> {
+    pub async fn get_panorama_meta(&self, pano_id: impl Into<PanoId>) -> Result<MetaData> {
+        let api_key = self.api_key.as_ref()
+            .ok_or_else(|| StreetViewError::MissingApiKey)?;
+        let pano_id = PanoId::new(pano_id)?;
+        metadata::get_panorama_meta(&self.http, pano_id.as_str(), api_key).await
+    }
+
+    /// Get official metadata for many panoramas.
+    ///
+    /// A cheap pre-flight check before downloading a large list: metadata
+    /// requests do not consume quota. Up to 8 requests run at once, spaced
+    /// to at most `max_qps` per second (pass 0 for no spacing beyond the
+    /// client's [`Policy`]). Duplicate IDs are requested once.
+    ///
+    /// Every ID gets an entry in the returned map, so one missing or
+    /// malformed panorama does not fail the batch: IDs that fail
+    /// validation map to `StreetViewError::InvalidPanoId` without a
+    /// request, and IDs without coverage to
+    /// `StreetViewError::NoPanoramasFound`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rsstreetview::StreetView;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = StreetView::builder().api_key("YOUR_API_KEY").build()?;
+    /// let ids: Vec<String> = std::fs::read_to_string("pano_ids.txt")?.lines().map(String::from).collect();
+    /// let results = client.get_panorama_meta_batch(&ids, 20.0).await?;
+    /// for (pano_id, meta) in &results {
+    ///     match meta {
+    ///         Ok(meta) => println!("{pano_id}: {}", meta.date),
+    ///         Err(e) => println!("{pano_id}: {e}"),
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `StreetViewError::MissingApiKey` if no API key is set.
+    pub async fn get_panorama_meta_batch<I>(
+        &self,
+        pano_ids: &[I],
+        max_qps: f64,
+    ) -> Result<std::collections::HashMap<PanoId, Result<MetaData>>>
+    where
+        I: Into<PanoId> + Clone,
+    {
+        let api_key = self.api_key.as_ref()
+            .ok_or_else(|| StreetViewError::MissingApiKey)?;
+        let mut invalid = std::collections::HashMap::new();
+        let mut valid = Vec::new();
+        for pano_id in pano_ids {
+            let pano_id: PanoId = pano_id.clone().into();
+            match PanoId::new(pano_id.clone()) {
+                Ok(pano_id) => valid.push(pano_id),
+                Err(e) => {
+                    invalid.insert(pano_id, Err(e));
+                }
+            }
+        }
+
+        let mut results =
+            metadata::get_panorama_meta_batch(&self.http, valid, api_key, META_BATCH_CONCURRENCY, max_qps).await;
+        results.extend(invalid);
+        Ok(results)
+    }
+
+    /// Get official metadata for the panorama nearest to a GPS coordinate.
+    ///
+    /// Requires an API key but, like all metadata requests, does not
+    /// consume Street View quota. Use it to resolve an official pano_id
+    /// cheaply before fetching imagery.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rsstreetview::{MetaOptions, StreetView};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = StreetView::builder().api_key("YOUR_API_KEY").build()?;
+    /// let options = MetaOptions::new().radius(100).outdoor();
+    /// let meta = client.get_meta_at(41.8982208, 12.4764804, &options).await?;
+    /// println!("Nearest pano: {}", meta.pano_id);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `StreetViewError::NoPanoramasFound` if there is no imagery
+    /// within the radius.
+    pub async fn get_meta_at(&self, lat: f64, lon: f64, options: &MetaOptions) -> Result<MetaData> {
+        let api_key = self.api_key.as_ref()
+            .ok_or_else(|| StreetViewError::MissingApiKey)?;
+        metadata::get_meta_at(&self.http, &format!("{lat},{lon}"), api_key, options).await
+    }
+
+    /// Get official metadata for the panorama nearest to an address.
+    ///
+    /// Google geocodes the address itself, so no separate geocoding call
+    /// is made. Requires an API key.
+    pub async fn get_meta_at_address(&self, address: &str, options: &MetaOptions) -> Result<MetaData> {
+        let api_key = self.api_key.as_ref()
+            .ok_or_else(|| StreetViewError::MissingApiKey)?;
+        metadata::get_meta_at(&self.http, address, api_key, options).await
+    }
+
+    /// Geocode an address to GPS coordinates.
+    ///
+    /// Requires an API key. Use `StreetView::builder().api_key()` to set one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no API key is set or the address has no results.
+    pub async fn geocode(&self, address: &str) -> Result<Location> {
         let api_key = self.api_key.as_ref()
             .ok_or_else(|| StreetViewError::MissingApiKey)?;
-        metadata::get_panorama_meta(&self.client, pano_id, api_key).await
+        metadata::geocode(&self.http, address, api_key).await
     }
 
     /// Get a partial Street View image using the official API.
     ///
-    /// Requires an API key. Use `StreetView::with_api_key()` to set one.
+    /// Requires an API key. Use `StreetView::builder().api_key()` to set one.
     ///
     /// # Arguments
     ///
@@ -203,18 +968,209 @@ impl StreetView {
     /// * `heading` - Camera heading in degrees (0-360)
     /// * `fov` - Field of view (default 120)
     /// * `pitch` - Camera pitch in degrees
+    ///
+    /// # Errors
+    ///
+    /// Returns `StreetViewError::QuotaExceeded` if the key is over quota,
+    /// `StreetViewError::NoImagery` if Google answers with its "no imagery"
+    /// placeholder, and `StreetViewError::ApiError` if Google answers with
+    /// anything other than an image.
     pub async fn get_streetview(
         &self,
-        pano_id: &str,
+        pano_id: impl Into<PanoId>,
+        width: u32,
+        height: u32,
+        heading: u16,
+        fov: u16,
+        pitch: i16,
+    ) -> Result<image::DynamicImage> {
+        self.get_streetview_response(pano_id, width, height, heading, fov, pitch)
+            .await
+            .map(|response| response.image)
+    }
+
+    /// Like [`get_streetview`](Self::get_streetview), but also returns the
+    /// HTTP status and response headers.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rsstreetview::StreetView;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = StreetView::builder().api_key("YOUR_API_KEY").build()?;
+    /// let response = client.get_streetview_response("PANO_ID", 640, 640, 0, 90, 0).await?;
+    /// println!("{:?} {:?}", response.content_type(), response.header("x-staticmap-api-warning"));
+    /// response.image.save("view.jpg")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_streetview_response(
+        &self,
+        pano_id: impl Into<PanoId>,
+        width: u32,
+        height: u32,
+        heading: u16,
+        fov: u16,
+        pitch: i16,
+    ) -> Result<StaticImage> {
+        let api_key = self.api_key.as_ref()
+            .ok_or_else(|| StreetViewError::MissingApiKey)?;
+        let pano_id = PanoId::new(pano_id)?;
+        metadata::get_streetview(&self.http, pano_id.as_str(), api_key, width, height, heading, fov, pitch).await
+    }
+
+    /// Get a Street View image with [`StaticViewOptions`] using the
+    /// official API.
+    ///
+    /// Exposes what [`get_streetview`](Self::get_streetview) does not:
+    /// fractional heading, FOV, and pitch, a location instead of a pano_id,
+    /// and the `radius`, `source`, and `return_error_code` parameters.
+    /// Requires an API key.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rsstreetview::{StaticViewOptions, StreetView};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = StreetView::builder().api_key("YOUR_API_KEY").build()?;
+    /// let options = StaticViewOptions::at(41.8982208, 12.4764804)
+    ///     .heading(87.5)
+    ///     .outdoor()
+    ///     .return_error_code(true);
+    /// client.get_streetview_opts(&options).await?.image.save("view.jpg")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// As [`get_streetview`](Self::get_streetview).
+    pub async fn get_streetview_opts(&self, options: &StaticViewOptions) -> Result<StaticImage> {
+        let api_key = self.api_key.as_ref()
+            .ok_or_else(|| StreetViewError::MissingApiKey)?;
+        if let StaticLocation::Pano(pano_id) = &options.location {
+            PanoId::new(pano_id)?;
+        }
+        metadata::get_streetview_opts(&self.http, api_key, options).await
+    }
+
+    /// Build the Static API URL that
+    /// [`get_streetview`](Self::get_streetview) would request, without
+    /// requesting it.
+    ///
+    /// The URL is signed when a signing secret is configured, so a server
+    /// can hand it to a browser to load the image directly instead of
+    /// proxying the bytes. The URL contains the API key: restrict the key
+    /// to the Street View Static API and your site's referrers before
+    /// exposing it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rsstreetview::StreetView;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = StreetView::builder()
+    ///     .api_key("YOUR_API_KEY")
+    ///     .signing_secret("dGVzdC1zZWNyZXQ=")
+    ///     .build()?;
+    /// let url = client.build_streetview_url("PANO000000000000000000", 640, 400, 90, 90, 0)?;
+    /// assert!(url.contains("&signature="));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `StreetViewError::MissingApiKey` if no API key is set and
+    /// `StreetViewError::InvalidPanoId` for a malformed ID.
+    pub fn build_streetview_url(
+        &self,
+        pano_id: impl Into<PanoId>,
         width: u32,
         height: u32,
         heading: u16,
         fov: u16,
         pitch: i16,
+    ) -> Result<String> {
+        let api_key = self.api_key.as_ref()
+            .ok_or_else(|| StreetViewError::MissingApiKey)?;
+        let pano_id = PanoId::new(pano_id)?;
+        let url = metadata::streetview_url(&self.http, pano_id.as_str(), api_key, width, height, heading, fov, pitch);
+        self.http.sign_official(&url)
+    }
+
+    /// Build the metadata URL that
+    /// [`get_panorama_meta`](Self::get_panorama_meta) would request,
+    /// without requesting it.
+    ///
+    /// Signed like [`build_streetview_url`](Self::build_streetview_url).
+    ///
+    /// # Errors
+    ///
+    /// Returns `StreetViewError::MissingApiKey` if no API key is set and
+    /// `StreetViewError::InvalidPanoId` for a malformed ID.
+    pub fn build_metadata_url(&self, pano_id: impl Into<PanoId>) -> Result<String> {
+        let api_key = self.api_key.as_ref()
+            .ok_or_else(|| StreetViewError::MissingApiKey)?;
+        let pano_id = PanoId::new(pano_id)?;
+        self.http.sign_official(&metadata::metadata_url(&self.http, pano_id.as_str(), api_key))
+    }
+
+    /// Get a Street View image at a GPS coordinate using the official API.
+    ///
+    /// Google picks the panorama nearest to the location, so no separate
+    /// search round-trip is needed. Heading, FOV, pitch, and size come from
+    /// `config` (size defaults to 640×640 and is capped there, FOV is capped
+    /// at 120); `options` sets the search radius and imagery source.
+    ///
+    /// Requires an API key.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rsstreetview::{MetaOptions, StreetView, ViewConfig};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = StreetView::builder().api_key("YOUR_API_KEY").build()?;
+    /// let config = ViewConfig::new(90).fov(100).size(640, 400);
+    /// let image = client
+    ///     .get_streetview_at(41.8982208, 12.4764804, &config, &MetaOptions::new().outdoor())
+    ///     .await?;
+    /// image.save("view.jpg")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_streetview_at(
+        &self,
+        lat: f64,
+        lon: f64,
+        config: &ViewConfig,
+        options: &MetaOptions,
     ) -> Result<image::DynamicImage> {
         let api_key = self.api_key.as_ref()
             .ok_or_else(|| StreetViewError::MissingApiKey)?;
-        metadata::get_streetview(&self.client, pano_id, api_key, width, height, heading, fov, pitch).await
+        metadata::get_streetview_at(&self.http, &format!("{lat},{lon}"), api_key, config, options)
+            .await
+            .map(|response| response.image)
+    }
+
+    /// Get a Street View image at an address using the official API.
+    ///
+    /// Like [`get_streetview_at`](Self::get_streetview_at), with Google
+    /// geocoding the address. Requires an API key.
+    pub async fn get_streetview_at_address(
+        &self,
+        address: &str,
+        config: &ViewConfig,
+        options: &MetaOptions,
+    ) -> Result<image::DynamicImage> {
+        let api_key = self.api_key.as_ref()
+            .ok_or_else(|| StreetViewError::MissingApiKey)?;
+        metadata::get_streetview_at(&self.http, address, api_key, config, options)
+            .await
+            .map(|response| response.image)
     }
 
     /// Extract a specific view from a panorama.
@@ -244,12 +1200,44 @@ impl StreetView {
     /// # Ok(())
     /// # }
     /// ```
+    #[cfg(feature = "unofficial")]
     pub async fn extract_view(
         &self,
-        pano_id: &str,
+        pano_id: impl Into<PanoId>,
         config: &ViewConfig,
     ) -> Result<image::DynamicImage> {
-        views::extract_view(&self.client, pano_id, config).await
+        self.policy.check_unofficial("extract_view")?;
+        let pano_id = PanoId::new(pano_id)?;
+        views::fetch_and_extract_view(&self.http, pano_id.as_str(), config).await
+    }
+
+    /// Like [`extract_view`](Self::extract_view), but also returns the
+    /// heading and pitch range the view actually covers and its
+    /// resolution in pixels per degree, for mapping positions in the view
+    /// back to angles.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rsstreetview::{StreetView, ViewConfig};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = StreetView::new();
+    /// let view = client.extract_view_with_bounds("PANO_ID_HERE_0000000000", &ViewConfig::new(90)).await?;
+    /// // Heading of a detection at column 300
+    /// let heading = view.heading_range.0 + 300.0 / view.pixels_per_degree.0;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "unofficial")]
+    pub async fn extract_view_with_bounds(
+        &self,
+        pano_id: impl Into<PanoId>,
+        config: &ViewConfig,
+    ) -> Result<ExtractedView> {
+        self.policy.check_unofficial("extract_view")?;
+        let pano_id = PanoId::new(pano_id)?;
+        views::fetch_and_extract_view_with_bounds(&self.http, pano_id.as_str(), config).await
     }
 
     /// Extract multiple views from a panorama in one call.
@@ -267,26 +1255,195 @@ impl StreetView {
     /// let panos = client.search_panoramas(41.8982208, 12.4764804).await?;
     ///
     /// // Extract all cardinal direction views
-    /// let configs = vec![
-    ///     ViewConfig::from_direction(Direction::Front),
-    ///     ViewConfig::from_direction(Direction::Right),
-    ///     ViewConfig::from_direction(Direction::Back),
-    ///     ViewConfig::from_direction(Direction::Left),
-    /// ];
+    /// let configs: Vec<ViewConfig> = Direction::CARDINAL.into_iter().map(ViewConfig::from).collect();
     ///
     /// let views = client.extract_multiple_views(&panos[0].pano_id, &configs).await?;
-    /// for (dir, view) in [Direction::Front, Direction::Right, Direction::Back, Direction::Left].iter().zip(views.iter()) {
+    /// for (dir, view) in Direction::CARDINAL.iter().zip(views.iter()) {
     ///     view.save(format!("{}.jpg", dir.name()))?;
     /// }
     /// # Ok(())
     /// # }
     /// ```
+    #[cfg(feature = "unofficial")]
     pub async fn extract_multiple_views(
         &self,
-        pano_id: &str,
+        pano_id: impl Into<PanoId>,
         configs: &[ViewConfig],
     ) -> Result<Vec<image::DynamicImage>> {
-        views::extract_multiple_views(&self.client, pano_id, configs).await
+        self.policy.check_unofficial("extract_multiple_views")?;
+        let pano_id = PanoId::new(pano_id)?;
+        views::fetch_and_extract_views(&self.http, pano_id.as_str(), configs).await
+    }
+
+    /// Extract views from many panoramas, as a stream of each panorama's
+    /// views in the order their extractions finish.
+    ///
+    /// Downloads and view extraction are pipelined with separate limits
+    /// from [`BatchViewOptions`]: panoramas download while earlier ones
+    /// are cropped on the blocking pool. Each panorama is downloaded once,
+    /// at its first view's zoom level. A failed panorama yields its error
+    /// and the batch continues; drop the stream to stop early.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use futures::StreamExt;
+    /// # use rsstreetview::{BatchViewOptions, Direction, PanoId, StreetView, ViewConfig};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = StreetView::new();
+    /// let panos = client.search_panoramas(41.8982208, 12.4764804).await?;
+    /// let views: Vec<ViewConfig> = Direction::iter().map(|dir| ViewConfig::from(dir).size(512, 512)).collect();
+    /// let requests: Vec<(PanoId, Vec<ViewConfig>)> =
+    ///     panos.iter().map(|pano| (PanoId::from(&pano.pano_id), views.clone())).collect();
+    ///
+    /// let options = BatchViewOptions::new().download_concurrency(8);
+    /// let mut batch = client.extract_views_batch(&requests, &options);
+    /// while let Some((pano_id, images)) = batch.next().await {
+    ///     for (i, image) in images?.iter().enumerate() {
+    ///         image.save(format!("{pano_id}_{i}.jpg"))?;
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "unofficial")]
+    pub fn extract_views_batch<'a>(
+        &'a self,
+        requests: &'a [(PanoId, Vec<ViewConfig>)],
+        options: &BatchViewOptions,
+    ) -> futures::stream::BoxStream<'a, (PanoId, Result<Vec<image::DynamicImage>>)> {
+        use futures::StreamExt;
+
+        let msg = match self.policy.check_unofficial("extract_views_batch") {
+            Ok(()) => return views::extract_views_batch(&self.http, requests, options),
+            Err(StreetViewError::PolicyViolation(msg)) => msg,
+            Err(e) => e.to_string(),
+        };
+        futures::stream::iter(requests)
+            .map(move |(pano_id, _)| (pano_id.clone(), Err(StreetViewError::PolicyViolation(msg.clone()))))
+            .boxed()
+    }
+
+    /// Snapshot an address or coordinate in one call.
+    ///
+    /// Geocodes the address (requires an API key), picks the nearest
+    /// panorama, preferring the most recent capture at the same spot,
+    /// extracts the configured views, and saves each one as
+    /// `{out_dir}/{pano_id}_{label}.{ext}` with a JSON sidecar.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rsstreetview::{SnapshotOptions, StreetView};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = StreetView::builder().api_key("YOUR_API_KEY").build()?;
+    /// let options = SnapshotOptions::new().out_dir("snapshots");
+    ///
+    /// let saved = client.snapshot("Piazza Navona, Rome", &options).await?;
+    /// for view in &saved {
+    ///     println!("{} -> {}", view.label, view.path.display());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `StreetViewError::MissingApiKey` for an address without an
+    /// API key, and `StreetViewError::NoPanoramasFound` if there is no
+    /// coverage at the location. With a `no_face_storage` policy, returns
+    /// `StreetViewError::PolicyViolation` unless the options carry a
+    /// redactor (`privacy` feature).
+    #[cfg(feature = "unofficial")]
+    pub async fn snapshot(
+        &self,
+        target: impl Into<SnapshotTarget>,
+        options: &SnapshotOptions,
+    ) -> Result<Vec<SavedView>> {
+        self.policy.check_unofficial("snapshot")?;
+        self.policy.check_storage(options.removes_faces())?;
+
+        let (lat, lon) = match target.into() {
+            SnapshotTarget::Address(address) => {
+                let location = self.geocode(&address).await?;
+                (location.lat, location.lng)
+            }
+            SnapshotTarget::Coords(lat, lon) => (lat, lon),
+        };
+
+        let panos = search::search_panoramas(&self.http, lat, lon).await?;
+        let pano = snapshot::pick_panorama(&panos, lat, lon)
+            .ok_or(StreetViewError::NoPanoramasFound)?;
+
+        let configs: Vec<ViewConfig> = options.views.iter().map(|(_, config)| config.clone()).collect();
+        let views = views::fetch_and_extract_views(&self.http, &pano.pano_id, &configs).await?;
+
+        tokio::fs::create_dir_all(&options.out_dir).await?;
+        snapshot::save_views(pano, views, options).await
+    }
+
+    /// Extract the same views from several panoramas and save them as a
+    /// COLMAP text model with known camera poses.
+    ///
+    /// Images go to `{out_dir}/images` and the model to
+    /// `{out_dir}/sparse/0`; see the [`colmap`] module for the layout and
+    /// coordinate frame.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rsstreetview::{ColmapOptions, StreetView};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = StreetView::new();
+    /// let panos = client.search_panoramas(41.8982208, 12.4764804).await?;
+    /// let model = client.export_colmap(&panos, &ColmapOptions::new().out_dir("reconstruction")).await?;
+    /// println!("{} images, origin {:?}", model.images.len(), model.reference);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `StreetViewError::NoPanoramasFound` if `panos` is empty, and
+    /// `StreetViewError::PolicyViolation` with a `no_face_storage` policy.
+    #[cfg(feature = "unofficial")]
+    pub async fn export_colmap(&self, panos: &[Panorama], options: &ColmapOptions) -> Result<ColmapModel> {
+        use image::GenericImageView;
+
+        self.policy.check_unofficial("export_colmap")?;
+        self.policy.check_storage(false)?;
+        let first = panos.first().ok_or(StreetViewError::NoPanoramasFound)?;
+
+        let mut model = ColmapModel {
+            out_dir: options.out_dir.clone(),
+            reference: GeoPosition::new(first.lat, first.lon, first.elevation.unwrap_or(0.0)),
+            cameras: Vec::new(),
+            images: Vec::new(),
+        };
+        let images_dir = model.images_dir();
+        tokio::fs::create_dir_all(&images_dir).await?;
+
+        let configs: Vec<ViewConfig> = options.views.iter().map(|(_, config)| config.clone()).collect();
+        for pano in panos {
+            let views = views::fetch_and_extract_views(&self.http, &pano.pano_id, &configs).await?;
+            // One camera per view label
+            for (camera, ((label, config), view)) in options.views.iter().zip(views).enumerate() {
+                if model.cameras.len() == camera {
+                    let (width, height) = view.dimensions();
+                    model.cameras.push(config.intrinsics(width, height));
+                }
+                let name = format!("{}_{label}.{}", pano.pano_id, options.save.format.extension());
+                let image_id = model.images.len() as u32 + 1;
+                model.images.push(ColmapImage::new(image_id, camera as u32 + 1, &name, pano, config, &model.reference));
+                options.save.save_async(view, images_dir.join(&name)).await?;
+            }
+        }
+
+        model.write()?;
+        Ok(model)
     }
 
     /// Crop black borders from the bottom and right edges of a panorama.
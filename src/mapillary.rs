@@ -0,0 +1,315 @@
+//! Mapillary images through the official Graph API.
+//!
+//! [`Mapillary`] searches Mapillary's crowd-sourced imagery, lists
+//! sequences, and downloads images. It implements
+//! [`PanoramaProvider`](crate::provider::PanoramaProvider) for the 360°
+//! images, which Mapillary stores as equirectangular panoramas.
+//!
+//! The Graph API is documented and needs a client access token from
+//! <https://www.mapillary.com/dashboard/developers>. It does not touch any
+//! Google endpoint, so it works in builds without the `unofficial` feature.
+//! Requests go through the [`StreetView`](crate::StreetView) client the
+//! provider was created from, so its rate limit, proxies, cache, and retry
+//! policy apply.
+//!
+//! Requires the `mapillary` feature.
+
+use crate::error::{Result, StreetViewError};
+use crate::http::Http;
+use crate::provider::PanoramaProvider;
+use crate::types::{CaptureDate, DateConfidence, Location, Panorama};
+use crate::utils::bounding_box;
+use crate::StreetView;
+use futures::future::BoxFuture;
+use image::DynamicImage;
+use serde::Deserialize;
+use serde_json::Value;
+
+const GRAPH_ENDPOINT: &str = "https://graph.mapillary.com";
+const IMAGE_FIELDS: &str = "id,geometry,computed_geometry,compass_angle,computed_compass_angle,captured_at,is_pano,sequence,altitude";
+const MAX_RESULTS: u32 = 100;
+const DEFAULT_RADIUS_M: f64 = 25.0;
+
+/// An image returned by a Mapillary search.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MapillaryImage {
+    /// Image ID
+    pub id: String,
+    /// Latitude, as refined by Mapillary's reconstruction when available
+    pub lat: f64,
+    /// Longitude, as refined by Mapillary's reconstruction when available
+    pub lon: f64,
+    /// Camera heading in degrees from north
+    pub compass_angle: Option<f64>,
+    /// Capture time in milliseconds since the Unix epoch
+    pub captured_at: Option<i64>,
+    /// Whether this is a 360° equirectangular image
+    pub is_pano: bool,
+    /// ID of the sequence the image belongs to
+    pub sequence: Option<String>,
+    /// Altitude in meters
+    pub altitude: Option<f64>,
+}
+
+impl MapillaryImage {
+    /// The image as a [`Panorama`], with its capture month as the date.
+    pub fn to_panorama(&self) -> Panorama {
+        let date = self.captured_at.and_then(capture_date);
+        Panorama {
+            pano_id: self.id.clone(),
+            lat: self.lat,
+            lon: self.lon,
+            heading: self.compass_angle.unwrap_or(0.0),
+            pitch: None,
+            roll: None,
+            date,
+            date_confidence: DateConfidence::of(date),
+            elevation: self.altitude,
+            copyright: None,
+            street_name: None,
+        }
+    }
+}
+
+/// Mapillary Graph API provider.
+///
+/// Image IDs are Mapillary's numeric image IDs as strings. The `zoom`
+/// passed to downloads picks the image size: 1 for 1024 pixels wide, 2 for
+/// 2048, and 3 or more for the original resolution.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rsstreetview::mapillary::Mapillary;
+/// # use rsstreetview::StreetView;
+/// # #[tokio::main]
+/// # async fn main() -> rsstreetview::Result<()> {
+/// let mapillary = Mapillary::new(&StreetView::new(), "MLY|123|abc");
+/// let images = mapillary.search_images(52.5163, 13.3777).await?;
+/// let sequence = mapillary.sequence(images[0].sequence.as_deref().unwrap()).await?;
+/// for id in sequence.iter().take(10) {
+///     mapillary.download_image(id, 2).await?.save(format!("{id}.jpg"))?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Mapillary {
+    http: Http,
+    access_token: String,
+    radius: f64,
+}
+
+impl Mapillary {
+    /// Create a provider authenticating with `access_token` and sending
+    /// requests through `client`.
+    pub fn new(client: &StreetView, access_token: impl Into<String>) -> Self {
+        Self {
+            http: client.http.clone(),
+            access_token: access_token.into(),
+            radius: DEFAULT_RADIUS_M,
+        }
+    }
+
+    /// Set the search radius in meters (default 25).
+    ///
+    /// The Graph API rejects search areas larger than 0.01 square degrees,
+    /// about a 5 km radius.
+    pub fn radius(mut self, meters: f64) -> Self {
+        self.radius = meters.max(1.0);
+        self
+    }
+
+    /// Search for images near a coordinate, nearest first. Includes both
+    /// 360° and regular perspective images.
+    pub async fn search_images(&self, lat: f64, lon: f64) -> Result<Vec<MapillaryImage>> {
+        let (south, west, north, east) = bounding_box(lat, lon, self.radius);
+        let url = format!(
+            "{GRAPH_ENDPOINT}/images?access_token={}&fields={IMAGE_FIELDS}&bbox={west},{south},{east},{north}&limit={MAX_RESULTS}",
+            self.access_token
+        );
+
+        let mut images = self.http.fetch(&url, false, parse_images).await?;
+        let target = Location::new(lat, lon);
+        let distance = |image: &MapillaryImage| Location::new(image.lat, image.lon).distance_to(&target);
+        images.sort_by(|a, b| distance(a).total_cmp(&distance(b)));
+        Ok(images)
+    }
+
+    /// Search for 360° images near a coordinate, nearest first.
+    pub async fn search_panoramas(&self, lat: f64, lon: f64) -> Result<Vec<Panorama>> {
+        let images = self.search_images(lat, lon).await?;
+        Ok(images.iter().filter(|image| image.is_pano).map(MapillaryImage::to_panorama).collect())
+    }
+
+    /// IDs of the images in a sequence, in capture order.
+    pub async fn sequence(&self, sequence_id: &str) -> Result<Vec<String>> {
+        let url = format!(
+            "{GRAPH_ENDPOINT}/image_ids?access_token={}&sequence_id={sequence_id}",
+            self.access_token
+        );
+        self.http.fetch(&url, false, parse_image_ids).await
+    }
+
+    /// Download an image: equirectangular for 360° images, perspective
+    /// otherwise.
+    pub async fn download_image(&self, image_id: &str, zoom: u8) -> Result<DynamicImage> {
+        if image_id.is_empty() || !image_id.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(StreetViewError::InvalidPanoId(format!(
+                "{image_id:?} (expected a numeric Mapillary image ID)"
+            )));
+        }
+        // The returned thumbnail URLs are signed and expire, so neither the
+        // lookup nor the image goes through the cache.
+        let field = thumb_field(zoom);
+        let url = format!("{GRAPH_ENDPOINT}/{image_id}?access_token={}&fields={field}", self.access_token);
+        let response = self.http.get_checked(&url).await?;
+        let value: Value = serde_json::from_slice(&response.body)
+            .map_err(|e| StreetViewError::ParseError(format!("JSON parse error: {e}")))?;
+        let image_url = value[field].as_str().ok_or_else(|| StreetViewError::EndpointFormatChanged {
+            path: format!("[{field:?}]"),
+            body: String::from_utf8_lossy(&response.body).into_owned(),
+        })?;
+
+        let response = self.http.get_checked(image_url).await?;
+        Ok(image::load_from_memory(&response.body)?)
+    }
+}
+
+impl PanoramaProvider for Mapillary {
+    fn name(&self) -> &'static str {
+        "mapillary"
+    }
+
+    fn search(&self, lat: f64, lon: f64) -> BoxFuture<'_, Result<Vec<Panorama>>> {
+        Box::pin(self.search_panoramas(lat, lon))
+    }
+
+    fn download<'a>(&'a self, pano_id: &'a str, zoom: u8) -> BoxFuture<'a, Result<DynamicImage>> {
+        Box::pin(self.download_image(pano_id, zoom))
+    }
+}
+
+/// Graph API field holding the thumbnail URL for a zoom level.
+fn thumb_field(zoom: u8) -> &'static str {
+    match zoom {
+        0 | 1 => "thumb_1024_url",
+        2 => "thumb_2048_url",
+        _ => "thumb_original_url",
+    }
+}
+
+#[derive(Deserialize)]
+struct Page<T> {
+    data: Vec<T>,
+}
+
+#[derive(Deserialize)]
+struct Point {
+    coordinates: [f64; 2],
+}
+
+#[derive(Deserialize)]
+struct ImageRecord {
+    id: String,
+    geometry: Option<Point>,
+    computed_geometry: Option<Point>,
+    compass_angle: Option<f64>,
+    computed_compass_angle: Option<f64>,
+    captured_at: Option<i64>,
+    #[serde(default)]
+    is_pano: bool,
+    sequence: Option<String>,
+    altitude: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct ImageId {
+    id: String,
+}
+
+fn parse_page<T: serde::de::DeserializeOwned>(body: &[u8]) -> Result<Vec<T>> {
+    serde_json::from_slice::<Page<T>>(body)
+        .map(|page| page.data)
+        .map_err(|e| StreetViewError::ParseError(format!("JSON parse error: {e}")))
+}
+
+/// Parse an image search page, skipping images without a location.
+fn parse_images(body: &[u8]) -> Result<Vec<MapillaryImage>> {
+    let records: Vec<ImageRecord> = parse_page(body)?;
+    Ok(records
+        .into_iter()
+        .filter_map(|record| {
+            let [lon, lat] = record.computed_geometry.or(record.geometry)?.coordinates;
+            Some(MapillaryImage {
+                id: record.id,
+                lat,
+                lon,
+                compass_angle: record.computed_compass_angle.or(record.compass_angle),
+                captured_at: record.captured_at,
+                is_pano: record.is_pano,
+                sequence: record.sequence,
+                altitude: record.altitude,
+            })
+        })
+        .collect())
+}
+
+fn parse_image_ids(body: &[u8]) -> Result<Vec<String>> {
+    let ids: Vec<ImageId> = parse_page(body)?;
+    Ok(ids.into_iter().map(|image| image.id).collect())
+}
+
+/// Capture month of a millisecond Unix timestamp (UTC).
+fn capture_date(millis: i64) -> Option<CaptureDate> {
+    // Days to civil date, from Howard Hinnant's `civil_from_days`.
+    let days = millis.div_euclid(86_400_000) + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    CaptureDate::new(u16::try_from(year).ok()?, month as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_images() {
+        let body = br#"{"data": [
+            {"id": "101", "geometry": {"type": "Point", "coordinates": [13.3777, 52.5163]},
+             "computed_geometry": {"type": "Point", "coordinates": [13.3778, 52.5164]},
+             "compass_angle": 90.0, "computed_compass_angle": 92.5,
+             "captured_at": 1565000000000, "is_pano": true, "sequence": "seq1", "altitude": 40.5},
+            {"id": "102", "geometry": {"type": "Point", "coordinates": [13.3779, 52.5165]}},
+            {"id": "103"}
+        ]}"#;
+        let images = parse_images(body).unwrap();
+        assert_eq!(images.len(), 2);
+        assert_eq!((images[0].lat, images[0].lon), (52.5164, 13.3778));
+        assert_eq!(images[0].compass_angle, Some(92.5));
+        assert!(images[0].is_pano && !images[1].is_pano);
+
+        let pano = images[0].to_panorama();
+        assert_eq!(pano.pano_id, "101");
+        assert_eq!((pano.heading, pano.elevation), (92.5, Some(40.5)));
+        assert_eq!(pano.date, CaptureDate::new(2019, 8));
+
+        let ids = parse_image_ids(br#"{"data": [{"id": "1"}, {"id": "2"}]}"#).unwrap();
+        assert_eq!(ids, ["1", "2"]);
+        assert!(parse_images(br#"{"error": {"message": "Invalid OAuth access token"}}"#).is_err());
+    }
+
+    #[test]
+    fn test_capture_date() {
+        assert_eq!(capture_date(0), CaptureDate::new(1970, 1));
+        assert_eq!(capture_date(951_782_400_000), CaptureDate::new(2000, 2)); // 2000-02-29
+        assert_eq!(capture_date(951_868_800_000), CaptureDate::new(2000, 3)); // 2000-03-01
+        assert_eq!(capture_date(1_704_067_199_000), CaptureDate::new(2023, 12));
+    }
+}
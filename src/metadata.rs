@@ -1,19 +1,40 @@
-use crate::error::Result;
-use crate::types::{Location, MetaData};
-use image::DynamicImage;
-use reqwest::Client;
+use crate::error::{Result, StreetViewError};
+use crate::types::{
+    ApiStatus, Location, MetaData, MetaOptions, MetaSource, StaticImage, StaticLocation, StaticViewOptions,
+};
+use crate::views::ViewConfig;
+use crate::http::Http;
+use crate::pano_id::PanoId;
+use crate::policy::RateLimiter;
+use futures::stream::{self, StreamExt};
 use serde::Deserialize;
+use std::collections::HashMap;
 
-const METADATA_ENDPOINT: &str = "https://maps.googleapis.com/maps/api/streetview/metadata";
-const STREETVIEW_ENDPOINT: &str = "https://maps.googleapis.com/maps/api/streetview";
+
+/// Largest image side the Static API renders.
+const STATIC_MAX_SIZE: u32 = 640;
+/// Widest field of view the Static API accepts.
+const STATIC_MAX_FOV: u16 = 120;
+/// Longest error body quoted in `ApiError` messages.
+const MAX_ERROR_LEN: usize = 300;
+/// Background color of the Static API's "no imagery" placeholder.
+const PLACEHOLDER_COLOR: [u8; 3] = [228, 227, 223];
+/// Per-channel tolerance when matching the placeholder color, for JPEG noise.
+const PLACEHOLDER_TOLERANCE: u8 = 4;
+/// Share of pixels that must match for an image to be the placeholder.
+const PLACEHOLDER_COVERAGE: f64 = 0.9;
 
 /// Internal structure for parsing metadata response
+///
+/// Only `status` is always present; the rest are omitted on errors.
 #[derive(Debug, Deserialize)]
 struct MetaDataResponse {
-    date: String,
-    location: LocationResponse,
-    pano_id: String,
-    copyright: String,
+    status: ApiStatus,
+    date: Option<String>,
+    location: Option<LocationResponse>,
+    pano_id: Option<String>,
+    copyright: Option<String>,
+    error_message: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -22,13 +43,32 @@ struct LocationResponse {
     lng: f64,
 }
 
+/// Internal structure for parsing geocoding responses
+#[derive(Debug, Deserialize)]
+struct GeocodeResponse {
+    status: ApiStatus,
+    #[serde(default)]
+    results: Vec<GeocodeResult>,
+    error_message: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeocodeResult {
+    geometry: GeocodeGeometry,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeocodeGeometry {
+    location: LocationResponse,
+}
+
 /// Get official metadata for a panorama using the Google Maps API.
 ///
 /// This function requires an API key but does not consume quota.
 ///
 /// # Arguments
 ///
-/// * `client` - HTTP client to use
+/// * `http` - HTTP client to use
 /// * `pano_id` - The panorama ID
 /// * `api_key` - Google Maps API key
 ///
@@ -38,34 +78,145 @@ struct LocationResponse {
 /// # use rsstreetview::StreetView;
 /// # #[tokio::main]
 /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
-/// let client = StreetView::with_api_key("YOUR_API_KEY");
+/// let client = StreetView::builder().api_key("YOUR_API_KEY").build()?;
 /// let panos = client.search_panoramas(41.8982208, 12.4764804).await?;
 /// let meta = client.get_panorama_meta(&panos[0].pano_id).await?;
 /// println!("Date: {}, Copyright: {}", meta.date, meta.copyright);
 /// # Ok(())
 /// # }
 /// ```
+///
+/// # Errors
+///
+/// Returns `StreetViewError::NoPanoramasFound` for `ZERO_RESULTS` and
+/// `NOT_FOUND`, and `StreetViewError::InvalidResponse` with the status and
+/// Google's error message for any other non-OK status.
 pub async fn get_panorama_meta(
-    client: &Client,
+    http: &Http,
     pano_id: &str,
     api_key: &str,
 ) -> Result<MetaData> {
-    let url = format!("{METADATA_ENDPOINT}?pano={pano_id}&key={api_key}");
+    let url = metadata_url(http, pano_id, api_key);
+
+    let meta = http.fetch(&url, true, parse_metadata);
+    #[cfg(feature = "tracing")]
+    let meta = tracing::Instrument::instrument(meta, tracing::info_span!("get_panorama_meta", pano_id));
+    meta.await
+}
+
+/// Unsigned metadata request URL for a panorama.
+pub fn metadata_url(http: &Http, pano_id: &str, api_key: &str) -> String {
+    let endpoint = &http.endpoints().metadata;
+    format!("{endpoint}?pano={pano_id}&key={api_key}")
+}
+
+/// Get official metadata for many panoramas.
+///
+/// Requests run up to `concurrency` at once and start at most `max_qps`
+/// per second (unlimited if not positive). Each ID is requested once;
+/// failures are kept per ID rather than failing the batch.
+pub async fn get_panorama_meta_batch(
+    http: &Http,
+    pano_ids: impl IntoIterator<Item = PanoId>,
+    api_key: &str,
+    concurrency: usize,
+    max_qps: f64,
+) -> HashMap<PanoId, Result<MetaData>> {
+    let mut ids: Vec<PanoId> = pano_ids.into_iter().collect();
+    ids.sort();
+    ids.dedup();
+    let limiter = (max_qps > 0.0).then(|| RateLimiter::new(max_qps));
+    let limiter = limiter.as_ref();
+
+    stream::iter(ids)
+        .map(|pano_id| async move {
+            if let Some(limiter) = limiter {
+                limiter.acquire().await;
+            }
+            let meta = get_panorama_meta(http, pano_id.as_str(), api_key).await;
+            (pano_id, meta)
+        })
+        .buffer_unordered(concurrency.max(1))
+        .collect()
+        .await
+}
+
+/// Get official metadata for the panorama nearest to a location.
+///
+/// `location` is either `"lat,lng"` or an address, which Google geocodes.
+///
+/// # Errors
+///
+/// Returns `StreetViewError::NoPanoramasFound` if there is no imagery
+/// within the search radius.
+pub async fn get_meta_at(
+    http: &Http,
+    location: &str,
+    api_key: &str,
+    options: &MetaOptions,
+) -> Result<MetaData> {
+    let url = reqwest::Url::parse_with_params(&http.endpoints().metadata, location_params(location, api_key, options))
+        .map_err(|e| StreetViewError::ParseError(format!("Invalid metadata URL: {e}")))?;
 
-    let response = client.get(&url).send().await?;
-    let data: MetaDataResponse = response.json().await?;
+    let meta = http.fetch(url.as_str(), true, parse_metadata);
+    #[cfg(feature = "tracing")]
+    let meta = tracing::Instrument::instrument(meta, tracing::info_span!("get_meta_at", location));
+    meta.await
+}
+
+fn location_params(location: &str, api_key: &str, options: &MetaOptions) -> Vec<(&'static str, String)> {
+    let mut params = vec![("location", location.to_string())];
+    if let Some(radius) = options.radius {
+        params.push(("radius", radius.to_string()));
+    }
+    if options.source == MetaSource::Outdoor {
+        params.push(("source", "outdoor".to_string()));
+    }
+    params.push(("key", api_key.to_string()));
+    params
+}
+
+fn parse_json<T: serde::de::DeserializeOwned>(body: &[u8]) -> Result<T> {
+    serde_json::from_slice(body).map_err(|e| StreetViewError::ParseError(format!("JSON parse error: {e}")))
+}
+
+fn parse_metadata(body: &[u8]) -> Result<MetaData> {
+    metadata_from_response(parse_json(body)?)
+}
+
+fn metadata_from_response(data: MetaDataResponse) -> Result<MetaData> {
+    match data.status {
+        ApiStatus::Ok => {}
+        ApiStatus::ZeroResults | ApiStatus::NotFound => return Err(StreetViewError::NoPanoramasFound),
+        status => return Err(api_error("Metadata request", status, data.error_message)),
+    }
+
+    let missing = |field: &str| StreetViewError::ParseError(format!("Metadata response missing `{field}`"));
+    let location = data.location.ok_or_else(|| missing("location"))?;
 
     Ok(MetaData {
-        date: data.date,
+        status: data.status,
+        date: data.date.ok_or_else(|| missing("date"))?,
         location: Location {
-            lat: data.location.lat,
-            lng: data.location.lng,
+            lat: location.lat,
+            lng: location.lng,
         },
-        pano_id: data.pano_id,
-        copyright: data.copyright,
+        pano_id: data.pano_id.ok_or_else(|| missing("pano_id"))?,
+        copyright: data.copyright.unwrap_or_default(),
     })
 }
 
+/// Error for a non-OK status, including Google's explanation if any.
+fn api_error(request: &str, status: ApiStatus, message: Option<String>) -> StreetViewError {
+    match message {
+        Some(message) => StreetViewError::InvalidResponse(format!(
+            "{request} failed with status {}: {message}",
+            status.as_str()
+        )),
+        None => StreetViewError::InvalidResponse(format!("{request} failed with status {}", status.as_str())),
+    }
+}
+
 /// Get a partial Street View image using the official Google Maps API.
 ///
 /// This returns a rendered view of the panorama from a specific angle,
@@ -73,7 +224,7 @@ pub async fn get_panorama_meta(
 ///
 /// # Arguments
 ///
-/// * `client` - HTTP client to use
+/// * `http` - HTTP client to use
 /// * `pano_id` - The panorama ID
 /// * `api_key` - Google Maps API key
 /// * `width` - Image width in pixels (max 640 for free tier)
@@ -88,7 +239,7 @@ pub async fn get_panorama_meta(
 /// # use rsstreetview::StreetView;
 /// # #[tokio::main]
 /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
-/// let client = StreetView::with_api_key("YOUR_API_KEY");
+/// let client = StreetView::builder().api_key("YOUR_API_KEY").build()?;
 /// let panos = client.search_panoramas(41.8982208, 12.4764804).await?;
 ///
 /// // Get a view looking north (heading=0) at normal pitch
@@ -97,8 +248,9 @@ pub async fn get_panorama_meta(
 /// # Ok(())
 /// # }
 /// ```
+#[allow(clippy::too_many_arguments)]
 pub async fn get_streetview(
-    client: &Client,
+    http: &Http,
     pano_id: &str,
     api_key: &str,
     width: u32,
@@ -106,21 +258,206 @@ pub async fn get_streetview(
     heading: u16,
     fov: u16,
     pitch: i16,
-) -> Result<DynamicImage> {
-    let url = format!(
-        "{STREETVIEW_ENDPOINT}?size={width}x{height}&fov={fov}&pitch={pitch}&heading={heading}&pano={pano_id}&key={api_key}"
-    );
+) -> Result<StaticImage> {
+    let url = streetview_url(http, pano_id, api_key, width, height, heading, fov, pitch);
+    get_static_image(http, &url).await
+}
 
-    let response = client.get(&url).send().await?;
-    let bytes = response.bytes().await?;
+/// Unsigned Static API request URL for a view of a panorama.
+#[allow(clippy::too_many_arguments)]
+pub fn streetview_url(
+    http: &Http,
+    pano_id: &str,
+    api_key: &str,
+    width: u32,
+    height: u32,
+    heading: u16,
+    fov: u16,
+    pitch: i16,
+) -> String {
+    let endpoint = &http.endpoints().static_api;
+    format!("{endpoint}?size={width}x{height}&fov={fov}&pitch={pitch}&heading={heading}&pano={pano_id}&key={api_key}")
+}
+
+/// Get a Street View image of the panorama nearest to a location.
+///
+/// `location` is either `"lat,lng"` or an address. The view's heading,
+/// FOV, pitch, and size come from `config` (size defaults to 640×640, the
+/// Static API maximum); `options` sets the search radius and source.
+pub async fn get_streetview_at(
+    http: &Http,
+    location: &str,
+    api_key: &str,
+    config: &ViewConfig,
+    options: &MetaOptions,
+) -> Result<StaticImage> {
+    let url = reqwest::Url::parse_with_params(&http.endpoints().static_api, streetview_params(location, api_key, config, options))
+        .map_err(|e| StreetViewError::ParseError(format!("Invalid Street View URL: {e}")))?;
+    get_static_image(http, url.as_str()).await
+}
+
+/// Get a Street View image with [`StaticViewOptions`].
+pub async fn get_streetview_opts(http: &Http, api_key: &str, options: &StaticViewOptions) -> Result<StaticImage> {
+    get_static_image(http, &static_view_url(http, api_key, options)?).await
+}
 
-    let img = image::load_from_memory(&bytes)?;
-    Ok(img)
+/// Unsigned Static API request URL for [`StaticViewOptions`].
+pub fn static_view_url(http: &Http, api_key: &str, options: &StaticViewOptions) -> Result<String> {
+    reqwest::Url::parse_with_params(&http.endpoints().static_api, static_view_params(api_key, options))
+        .map(String::from)
+        .map_err(|e| StreetViewError::ParseError(format!("Invalid Street View URL: {e}")))
+}
+
+fn static_view_params(api_key: &str, options: &StaticViewOptions) -> Vec<(&'static str, String)> {
+    let (width, height) = options.size;
+    let mut params = vec![
+        ("size", format!("{}x{}", width.min(STATIC_MAX_SIZE), height.min(STATIC_MAX_SIZE))),
+        ("fov", options.fov.min(f64::from(STATIC_MAX_FOV)).to_string()),
+        ("pitch", options.pitch.to_string()),
+    ];
+    if let Some(heading) = options.heading {
+        params.push(("heading", heading.to_string()));
+    }
+    if options.return_error_code {
+        params.push(("return_error_code", "true".to_string()));
+    }
+
+    let location = match &options.location {
+        StaticLocation::Pano(pano_id) => {
+            params.extend([("pano", pano_id.clone()), ("key", api_key.to_string())]);
+            return params;
+        }
+        StaticLocation::Coords(lat, lon) => format!("{lat},{lon}"),
+        StaticLocation::Address(address) => address.clone(),
+    };
+    let lookup = MetaOptions {
+        radius: options.radius,
+        source: options.source,
+    };
+    params.extend(location_params(&location, api_key, &lookup));
+    params
+}
+
+/// Fetch and decode a Static API image.
+///
+/// Over-quota and rejected requests come back as plain text or HTML
+/// rather than an image, so check the status and content type first.
+async fn get_static_image(http: &Http, url: &str) -> Result<StaticImage> {
+    let response = http.get_billable(url).await?;
+    let status = response.status.as_u16();
+    let headers = response.headers;
+    let is_image = headers
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("image/"));
+
+    let bytes = response.body;
+    if !(200..300).contains(&status) || !is_image {
+        return Err(static_api_error(status, &String::from_utf8_lossy(&bytes)));
+    }
+
+    let image = image::load_from_memory(&bytes)?;
+    if is_placeholder(&image) {
+        return Err(StreetViewError::NoImagery);
+    }
+    Ok(StaticImage { image, status, headers })
+}
+
+/// Whether an image is Google's "Sorry, we have no imagery here"
+/// placeholder: flat light gray apart from the message text.
+fn is_placeholder(image: &image::DynamicImage) -> bool {
+    let rgb = image.to_rgb8();
+    let total = rgb.pixels().len();
+    let matching = rgb
+        .pixels()
+        .filter(|pixel| {
+            pixel.0.iter().zip(PLACEHOLDER_COLOR).all(|(&c, p)| c.abs_diff(p) <= PLACEHOLDER_TOLERANCE)
+        })
+        .count();
+    total > 0 && matching as f64 >= total as f64 * PLACEHOLDER_COVERAGE
+}
+
+/// Map a non-image Static API response to a typed error.
+fn static_api_error(status: u16, body: &str) -> StreetViewError {
+    let message = body_text(body);
+    if status == 429 || message.to_lowercase().contains("quota") {
+        StreetViewError::QuotaExceeded(message)
+    } else if status == 404 {
+        // Only sent for missing imagery with `return_error_code=true`
+        StreetViewError::NoImagery
+    } else {
+        StreetViewError::ApiError { status, message }
+    }
+}
+
+/// Reduce an error body (often an HTML page) to a short line of text.
+fn body_text(body: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in body.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match text.char_indices().nth(MAX_ERROR_LEN) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text,
+    }
+}
+
+fn streetview_params(
+    location: &str,
+    api_key: &str,
+    config: &ViewConfig,
+    options: &MetaOptions,
+) -> Vec<(&'static str, String)> {
+    let (width, height) = config.size.unwrap_or((STATIC_MAX_SIZE, STATIC_MAX_SIZE));
+    let mut params = vec![
+        ("size", format!("{}x{}", width.min(STATIC_MAX_SIZE), height.min(STATIC_MAX_SIZE))),
+        ("fov", config.fov.min(STATIC_MAX_FOV).to_string()),
+        ("pitch", config.pitch.to_string()),
+        ("heading", config.heading.to_string()),
+    ];
+    params.extend(location_params(location, api_key, options));
+    params
+}
+
+/// Resolve an address to coordinates using the Google Geocoding API.
+///
+/// Returns the location of the best match.
+pub async fn geocode(http: &Http, address: &str, api_key: &str) -> Result<Location> {
+    let mut params = vec![("address", address), ("key", api_key)];
+    if let Some(locale) = http.locale() {
+        params.extend([("language", locale.language.as_str()), ("region", locale.region.as_str())]);
+    }
+    let url = reqwest::Url::parse_with_params(&http.endpoints().geocode, &params)
+        .map_err(|e| StreetViewError::ParseError(format!("Invalid geocoding URL: {e}")))?;
+
+    http.fetch(url.as_str(), true, parse_geocode).await
+}
+
+fn parse_geocode(body: &[u8]) -> Result<Location> {
+    let data: GeocodeResponse = parse_json(body)?;
+    match data.results.into_iter().next() {
+        Some(result) if data.status == ApiStatus::Ok => Ok(Location {
+            lat: result.geometry.location.lat,
+            lng: result.geometry.location.lng,
+        }),
+        _ => Err(api_error("Geocoding", data.status, data.error_message)),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::endpoints::{METADATA_ENDPOINT, STREETVIEW_ENDPOINT};
 
     #[test]
     fn test_metadata_url_construction() {
@@ -129,6 +466,105 @@ mod tests {
         assert!(url.contains("key=test_key"));
     }
 
+    #[test]
+    fn test_location_params() {
+        let options = MetaOptions::new().radius(100).outdoor();
+        let params = location_params("41.9,12.47", "test_key", &options);
+        let url = reqwest::Url::parse_with_params(METADATA_ENDPOINT, params).unwrap();
+        assert_eq!(
+            url.query(),
+            Some("location=41.9%2C12.47&radius=100&source=outdoor&key=test_key")
+        );
+
+        let params = location_params("Piazza Navona, Rome", "k", &MetaOptions::new());
+        assert_eq!(params.len(), 2);
+    }
+
+    #[test]
+    fn test_streetview_params() {
+        let config = ViewConfig::new(90).fov(150).pitch(-10);
+        let params = streetview_params("41.9,12.47", "k", &config, &MetaOptions::new().outdoor());
+        let url = reqwest::Url::parse_with_params(STREETVIEW_ENDPOINT, params).unwrap();
+        assert_eq!(
+            url.query(),
+            Some("size=640x640&fov=120&pitch=-10&heading=90&location=41.9%2C12.47&source=outdoor&key=k")
+        );
+    }
+
+    #[test]
+    fn test_static_view_params() {
+        let options = StaticViewOptions::at(41.9, 12.47).heading(87.5).fov(150.0).pitch(-4.25).radius(30).outdoor();
+        let url = reqwest::Url::parse_with_params(STREETVIEW_ENDPOINT, static_view_params("k", &options)).unwrap();
+        assert_eq!(
+            url.query(),
+            Some("size=640x640&fov=120&pitch=-4.25&heading=87.5&location=41.9%2C12.47&radius=30&source=outdoor&key=k")
+        );
+
+        let options = StaticViewOptions::pano("PANO").size(800, 300).radius(30).return_error_code(true);
+        let url = reqwest::Url::parse_with_params(STREETVIEW_ENDPOINT, static_view_params("k", &options)).unwrap();
+        assert_eq!(url.query(), Some("size=640x300&fov=90&pitch=0&return_error_code=true&pano=PANO&key=k"));
+    }
+
+    #[test]
+    fn test_static_api_error() {
+        let quota = "<html><body><p>The Google Maps Platform server rejected your request. \
+            You have exceeded your daily request quota for this API.</p></body></html>";
+        match static_api_error(403, quota) {
+            StreetViewError::QuotaExceeded(msg) => assert!(msg.starts_with("The Google Maps Platform server rejected")),
+            e => panic!("unexpected error: {e:?}"),
+        }
+        assert!(matches!(static_api_error(429, ""), StreetViewError::QuotaExceeded(_)));
+        assert!(matches!(static_api_error(404, ""), StreetViewError::NoImagery));
+        assert!(matches!(
+            static_api_error(403, "The provided API key is invalid."),
+            StreetViewError::ApiError { status: 403, ref message } if message == "The provided API key is invalid."
+        ));
+        assert!(body_text(&"x".repeat(1000)).ends_with("..."));
+    }
+
+    #[test]
+    fn test_placeholder() {
+        let mut placeholder = image::RgbImage::from_pixel(100, 100, image::Rgb([229, 226, 224]));
+        for x in 20..80 {
+            for y in 45..52 {
+                placeholder.put_pixel(x, y, image::Rgb([120, 120, 120]));
+            }
+        }
+        assert!(is_placeholder(&placeholder.into()));
+
+        let view = image::RgbImage::from_fn(100, 100, |x, y| image::Rgb([x as u8 * 2, y as u8 * 2, 200]));
+        assert!(!is_placeholder(&view.into()));
+        let gray_sky = image::RgbImage::from_fn(100, 100, |_, y| {
+            image::Rgb(if y < 50 { PLACEHOLDER_COLOR } else { [60, 60, 60] })
+        });
+        assert!(!is_placeholder(&gray_sky.into()));
+    }
+
+    #[test]
+    fn test_metadata_status() {
+        let ok: MetaDataResponse = serde_json::from_str(
+            r#"{"copyright":"© Google","date":"2019-06","location":{"lat":41.9,"lng":12.47},
+                "pano_id":"abc","status":"OK"}"#,
+        )
+        .unwrap();
+        let meta = metadata_from_response(ok).unwrap();
+        assert_eq!(meta.status, ApiStatus::Ok);
+        assert_eq!(meta.pano_id, "abc");
+
+        let zero: MetaDataResponse = serde_json::from_str(r#"{"status":"ZERO_RESULTS"}"#).unwrap();
+        assert!(matches!(metadata_from_response(zero), Err(StreetViewError::NoPanoramasFound)));
+
+        let denied: MetaDataResponse = serde_json::from_str(
+            r#"{"error_message":"The provided API key is invalid.","status":"REQUEST_DENIED"}"#,
+        )
+        .unwrap();
+        let err = metadata_from_response(denied).unwrap_err().to_string();
+        assert!(err.contains("REQUEST_DENIED") && err.contains("API key is invalid"));
+
+        let unknown: MetaDataResponse = serde_json::from_str(r#"{"status":"SOMETHING_NEW"}"#).unwrap();
+        assert_eq!(unknown.status, ApiStatus::Other);
+    }
+
     #[test]
     fn test_streetview_url_construction() {
         let url = format!(
@@ -0,0 +1,380 @@
+//! Metrics hooks for HTTP requests.
+//!
+//! Attach a [`Metrics`] implementation with
+//! [`StreetViewBuilder::metrics`](crate::StreetViewBuilder::metrics) to be
+//! told about every request, cache hit, and tile retry, e.g. to export them
+//! to Prometheus. [`MetricsRecorder`] keeps simple in-process totals.
+//!
+//! The hook also hears about every connection the builder's HTTP client
+//! opens, which with each request's HTTP version shows how well
+//! connections are reused. Tile hosts speak HTTP/2, so a whole panorama
+//! normally downloads over one or two multiplexed connections; many new
+//! connections point at a proxy or network that forces HTTP/1.1.
+//! Connections of a custom [`client`](crate::StreetViewBuilder::client)
+//! or a proxy pool are not seen, and reqwest does not report when a
+//! connection closes, so the number currently open is not available.
+//!
+//! Requires the `metrics` feature.
+
+use crate::endpoints::EndpointKind;
+use futures::future::BoxFuture;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// Which endpoint a request went to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum RequestKind {
+    /// Panorama search
+    Search,
+    /// Panorama tile
+    Tile,
+    /// Rich panorama metadata (photometa)
+    Photometa,
+    /// Official metadata API
+    Metadata,
+    /// Official Static API image
+    StaticImage,
+    /// Geocoding API
+    Geocode,
+    /// A URL outside the configured endpoints
+    Other,
+}
+
+impl From<Option<EndpointKind>> for RequestKind {
+    fn from(kind: Option<EndpointKind>) -> Self {
+        match kind {
+            #[cfg(feature = "unofficial")]
+            Some(EndpointKind::Search) => RequestKind::Search,
+            #[cfg(feature = "unofficial")]
+            Some(EndpointKind::Tile) => RequestKind::Tile,
+            #[cfg(feature = "unofficial")]
+            Some(EndpointKind::Photometa) => RequestKind::Photometa,
+            Some(EndpointKind::Metadata) => RequestKind::Metadata,
+            Some(EndpointKind::StaticImage) => RequestKind::StaticImage,
+            Some(EndpointKind::Geocode) => RequestKind::Geocode,
+            None => RequestKind::Other,
+        }
+    }
+}
+
+/// A finished HTTP request.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RequestEvent {
+    /// Endpoint the request went to
+    pub kind: RequestKind,
+    /// Response status, or `None` if no response was received
+    pub status: Option<u16>,
+    /// Time from sending the request to reading the whole body, including
+    /// any wait for a rate limit slot
+    pub latency: Duration,
+    /// Response body size in bytes
+    pub bytes: usize,
+    /// Whether the response came over HTTP/2
+    pub http2: bool,
+}
+
+impl RequestEvent {
+    /// Whether a 2xx response was received.
+    pub fn is_success(&self) -> bool {
+        self.status.is_some_and(|status| (200..300).contains(&status))
+    }
+}
+
+/// Receives request metrics. All methods default to doing nothing.
+pub trait Metrics: Send + Sync {
+    /// Called after every HTTP request, successful or not.
+    fn request(&self, _event: &RequestEvent) {}
+
+    /// Called when a response is served from the cache instead.
+    fn cache_hit(&self, _kind: RequestKind) {}
+
+    /// Called before a failed request is retried; `attempt` starts at 1.
+    fn retry(&self, _kind: RequestKind, _attempt: u32) {}
+
+    /// Called when the client opens a new connection, after the TLS
+    /// handshake.
+    fn connection_opened(&self) {}
+}
+
+/// Totals collected by [`MetricsRecorder`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct MetricsSnapshot {
+    /// HTTP requests sent
+    pub requests: u64,
+    /// Requests that failed or got a non-2xx response
+    pub failures: u64,
+    /// Responses served from the cache
+    pub cache_hits: u64,
+    /// Retried requests
+    pub retries: u64,
+    /// Response bytes received
+    pub bytes: u64,
+    /// Summed latency of all requests
+    pub total_latency: Duration,
+    /// Responses received over HTTP/2
+    pub http2_requests: u64,
+    /// Connections opened by the client
+    pub connections_opened: u64,
+}
+
+impl MetricsSnapshot {
+    /// Mean request latency, or zero if no requests were sent.
+    pub fn mean_latency(&self) -> Duration {
+        match self.requests {
+            0 => Duration::ZERO,
+            n => self.total_latency / n as u32,
+        }
+    }
+
+    /// Fraction of requests sent over an already open connection, or zero
+    /// if no requests were sent.
+    ///
+    /// Close to 1 when connections are reused well. Only meaningful when
+    /// the builder created the HTTP client; see the [module docs](self).
+    pub fn reuse_rate(&self) -> f64 {
+        match self.requests {
+            0 => 0.0,
+            n => 1.0 - (self.connections_opened as f64 / n as f64).min(1.0),
+        }
+    }
+}
+
+/// A [`Metrics`] implementation that keeps running totals.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rsstreetview::metrics::MetricsRecorder;
+/// # use rsstreetview::StreetView;
+/// # use std::sync::Arc;
+/// # #[tokio::main]
+/// # async fn main() -> rsstreetview::Result<()> {
+/// let recorder = Arc::new(MetricsRecorder::new());
+/// let client = StreetView::builder().metrics(recorder.clone()).build()?;
+///
+/// client.search_panoramas(41.8982208, 12.4764804).await?;
+/// let totals = recorder.snapshot();
+/// println!("{} requests, {} bytes, {:?} mean", totals.requests, totals.bytes, totals.mean_latency());
+/// println!("{} connections, {:.0}% reused", totals.connections_opened, totals.reuse_rate() * 100.0);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct MetricsRecorder {
+    requests: AtomicU64,
+    failures: AtomicU64,
+    cache_hits: AtomicU64,
+    retries: AtomicU64,
+    bytes: AtomicU64,
+    latency_micros: AtomicU64,
+    http2_requests: AtomicU64,
+    connections_opened: AtomicU64,
+}
+
+impl MetricsRecorder {
+    /// A recorder with all totals at zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The current totals.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            requests: self.requests.load(Ordering::Relaxed),
+            failures: self.failures.load(Ordering::Relaxed),
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+            retries: self.retries.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            total_latency: Duration::from_micros(self.latency_micros.load(Ordering::Relaxed)),
+            http2_requests: self.http2_requests.load(Ordering::Relaxed),
+            connections_opened: self.connections_opened.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Metrics for MetricsRecorder {
+    fn request(&self, event: &RequestEvent) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        if !event.is_success() {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+        }
+        self.bytes.fetch_add(event.bytes as u64, Ordering::Relaxed);
+        self.latency_micros.fetch_add(event.latency.as_micros() as u64, Ordering::Relaxed);
+        if event.http2 {
+            self.http2_requests.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn cache_hit(&self, _kind: RequestKind) {
+        self.cache_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn retry(&self, _kind: RequestKind, _attempt: u32) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn connection_opened(&self) {
+        self.connections_opened.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Internal: connector layer reporting each new connection to a metrics
+/// hook, installed with reqwest's `ClientBuilder::connector_layer`.
+#[derive(Clone)]
+pub(crate) struct ConnectionObserver {
+    metrics: Arc<dyn Metrics>,
+}
+
+impl ConnectionObserver {
+    pub(crate) fn new(metrics: Arc<dyn Metrics>) -> Self {
+        Self { metrics }
+    }
+}
+
+impl<S> tower_layer::Layer<S> for ConnectionObserver {
+    type Service = ObservedConnector<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ObservedConnector {
+            inner,
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+/// Internal: a connector wrapped by [`ConnectionObserver`].
+#[derive(Clone)]
+pub(crate) struct ObservedConnector<S> {
+    inner: S,
+    metrics: Arc<dyn Metrics>,
+}
+
+impl<S, R> tower_service::Service<R> for ObservedConnector<S>
+where
+    S: tower_service::Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<S::Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let connecting = self.inner.call(request);
+        let metrics = self.metrics.clone();
+        Box::pin(async move {
+            let connection = connecting.await?;
+            metrics.connection_opened();
+            Ok(connection)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::endpoints::Endpoints;
+
+    #[test]
+    fn test_request_kind() {
+        let endpoints = Endpoints::new();
+        let kind = |url: &str| RequestKind::from(endpoints.kind_of(url));
+        assert_eq!(kind(&format!("{}?pano=X&key=K", endpoints.static_api)), RequestKind::StaticImage);
+        assert_eq!(kind(&format!("{}?address=Rome", endpoints.geocode)), RequestKind::Geocode);
+        assert_eq!(kind("https://example.com"), RequestKind::Other);
+    }
+
+    #[test]
+    fn test_recorder() {
+        let recorder = MetricsRecorder::new();
+        let event = |status, ms| RequestEvent {
+            kind: RequestKind::Tile,
+            status,
+            latency: Duration::from_millis(ms),
+            bytes: 100,
+            http2: status.is_some(),
+        };
+        recorder.request(&event(Some(200), 10));
+        recorder.request(&event(Some(503), 30));
+        recorder.request(&event(None, 20));
+        recorder.retry(RequestKind::Tile, 1);
+        recorder.cache_hit(RequestKind::Search);
+        recorder.connection_opened();
+
+        let totals = recorder.snapshot();
+        assert_eq!((totals.requests, totals.failures, totals.retries, totals.cache_hits), (3, 2, 1, 1));
+        assert_eq!(totals.bytes, 300);
+        assert_eq!(totals.mean_latency(), Duration::from_millis(20));
+        assert_eq!((totals.http2_requests, totals.connections_opened), (2, 1));
+        assert!((totals.reuse_rate() - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(MetricsSnapshot::default().reuse_rate(), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_connection_observer() {
+        use futures::future::{ready, Ready};
+        use tower_layer::Layer;
+        use tower_service::Service;
+
+        /// Connects successfully unless the target is empty.
+        #[derive(Clone)]
+        struct Connector;
+
+        impl Service<&'static str> for Connector {
+            type Response = ();
+            type Error = &'static str;
+            type Future = Ready<Result<(), &'static str>>;
+
+            fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+                Poll::Ready(Ok(()))
+            }
+
+            fn call(&mut self, target: &'static str) -> Self::Future {
+                ready(if target.is_empty() { Err("no target") } else { Ok(()) })
+            }
+        }
+
+        let recorder = Arc::new(MetricsRecorder::new());
+        let mut connector = ConnectionObserver::new(recorder.clone()).layer(Connector);
+        connector.call("example.com").await.unwrap();
+        connector.call("example.com").await.unwrap();
+        connector.call("").await.unwrap_err();
+        assert_eq!(recorder.snapshot().connections_opened, 2);
+    }
+
+    #[tokio::test]
+    async fn test_client_metrics() {
+        use crate::testing::{fixtures, MockResponse, MockTransport};
+        use crate::{MemoryCache, StreetView};
+        use std::sync::Arc;
+
+        let pano = fixtures::panorama("PANO000000000000000000", 1.0, 2.0);
+        let mock = MockTransport::new().route("metadata", MockResponse::json(fixtures::metadata_response(&pano)));
+        let recorder = Arc::new(MetricsRecorder::new());
+        let client = StreetView::builder()
+            .api_key("KEY")
+            .cache(MemoryCache::new(8))
+            .metrics(recorder.clone())
+            .mock_transport(mock)
+            .build()
+            .unwrap();
+
+        client.get_panorama_meta("PANO000000000000000000").await.unwrap();
+        client.get_panorama_meta("PANO000000000000000000").await.unwrap();
+        client.get_panorama_meta("OTHER00000000000000000").await.unwrap();
+
+        let totals = recorder.snapshot();
+        assert_eq!((totals.requests, totals.cache_hits, totals.failures), (2, 1, 0));
+        assert!(totals.bytes > 0);
+    }
+}
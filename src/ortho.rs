@@ -0,0 +1,231 @@
+//! Top-down orthophotos of the ground around a panorama.
+//!
+//! [`orthophoto`] projects the bottom hemisphere of an equirectangular
+//! panorama onto a flat ground plane below the camera, producing a
+//! pseudo-aerial image of the road surface centered on the capture point.
+//! With a [`DepthMap`], ground hidden behind cars, walls, and other objects
+//! is left transparent instead of smeared across the road, and the camera
+//! height is measured rather than assumed.
+//!
+//! North-up orthophotos can be georeferenced with [`world_file`].
+
+use crate::depth::DepthMap;
+use crate::utils::bounding_box;
+use image::{DynamicImage, Rgba, RgbaImage};
+use std::f64::consts::PI;
+
+/// Ground farther along the ray than the depth map allows, by more than
+/// this fraction, is treated as hidden.
+const OCCLUSION_TOLERANCE: f64 = 0.1;
+
+/// Options for [`orthophoto`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct OrthoOptions {
+    /// Distance from the camera to each edge of the image, in meters
+    pub extent: f64,
+    /// Ground size of one pixel, in meters
+    pub resolution: f64,
+    /// Camera height above the ground in meters, used without a depth map
+    pub camera_height: f64,
+    /// Orient the image north-up; otherwise the panorama's heading is up
+    pub north_up: bool,
+}
+
+impl OrthoOptions {
+    /// Defaults: 20 m in each direction at 5 cm per pixel (an 800×800
+    /// image), a 2.5 m camera height, north up.
+    pub fn new() -> Self {
+        Self {
+            extent: 20.0,
+            resolution: 0.05,
+            camera_height: 2.5,
+            north_up: true,
+        }
+    }
+
+    /// Set the distance from the camera to each edge of the image, in meters.
+    pub fn extent(mut self, meters: f64) -> Self {
+        self.extent = meters.max(0.1);
+        self
+    }
+
+    /// Set the ground size of one pixel, in meters.
+    pub fn resolution(mut self, meters_per_pixel: f64) -> Self {
+        self.resolution = meters_per_pixel.max(0.001);
+        self
+    }
+
+    /// Set the camera height above the ground, in meters.
+    ///
+    /// Street View cars carry the camera about 2.5 m up; trekker and
+    /// tripod captures are lower.
+    pub fn camera_height(mut self, meters: f64) -> Self {
+        self.camera_height = meters.max(0.1);
+        self
+    }
+
+    /// Orient the image north-up (default) or with the panorama's heading up.
+    pub fn north_up(mut self, north_up: bool) -> Self {
+        self.north_up = north_up;
+        self
+    }
+
+    /// Width and height of the output image in pixels.
+    pub fn size(&self) -> u32 {
+        ((2.0 * self.extent / self.resolution).round() as u32).max(1)
+    }
+}
+
+impl Default for OrthoOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Project a panorama onto the ground plane, seen from above.
+///
+/// `heading` is the compass heading of the panorama's center column
+/// ([`Panorama::heading`](crate::Panorama::heading)). The camera sits at the
+/// center of the output. Pixels the panorama cannot see, because an object
+/// in the depth map is in the way, are transparent.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rsstreetview::{StreetView, ortho::{orthophoto, world_file, OrthoOptions}};
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = StreetView::new();
+/// let pano = &client.search_panoramas(41.8982208, 12.4764804).await?[0];
+/// let image = client.download_panorama(&pano.pano_id, 4).await?;
+/// let depth = client.get_depth_map(&pano.pano_id).await?;
+///
+/// let options = OrthoOptions::new().extent(15.0).resolution(0.02);
+/// orthophoto(&image, pano.heading, Some(&depth), &options).save("ground.png")?;
+/// std::fs::write("ground.pgw", world_file(pano.lat, pano.lon, &options))?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn orthophoto(pano: &DynamicImage, heading: f64, depth: Option<&DepthMap>, options: &OrthoOptions) -> RgbaImage {
+    let pano = pano.to_rgb8();
+    let size = options.size();
+    let half = f64::from(size) / 2.0;
+    let camera_height = depth.and_then(nadir_depth).unwrap_or(options.camera_height);
+    let up = if options.north_up { 0.0 } else { heading };
+
+    RgbaImage::from_fn(size, size, |x, y| {
+        let right = (f64::from(x) + 0.5 - half) * options.resolution;
+        let forward = (half - (f64::from(y) + 0.5)) * options.resolution;
+        let distance = right.hypot(forward);
+
+        // Compass bearing to the ground point, then its position in the panorama
+        let bearing = right.atan2(forward).to_degrees() + up;
+        let u = (0.5 + (bearing - heading) / 360.0).rem_euclid(1.0);
+        let v = 0.5 + camera_height.atan2(distance) / PI;
+
+        if let Some(depth) = depth.and_then(|depth| depth.depth_at(u, v)) {
+            let ray = distance.hypot(camera_height);
+            if f64::from(depth) < ray * (1.0 - OCCLUSION_TOLERANCE) {
+                return Rgba([0, 0, 0, 0]);
+            }
+        }
+        let [r, g, b] = image::imageops::sample_bilinear(&pano, u as f32, v as f32).map_or([0, 0, 0], |pixel| pixel.0);
+        Rgba([r, g, b, 255])
+    })
+}
+
+/// Camera height from the depth map: the median distance straight down.
+fn nadir_depth(depth: &DepthMap) -> Option<f64> {
+    let mut below: Vec<f32> = (0..depth.width).filter_map(|x| depth.depth(x, depth.height - 1)).collect();
+    if below.is_empty() {
+        return None;
+    }
+    below.sort_by(f32::total_cmp);
+    Some(f64::from(below[below.len() / 2]))
+}
+
+/// An ESRI world file (`.pgw`, `.jgw`, `.tfw`) placing a north-up
+/// orthophoto taken at `lat`, `lon` in WGS 84 degrees.
+///
+/// The six lines are the pixel width and rotation terms, the (negative)
+/// pixel height, and the coordinates of the top-left pixel's center.
+pub fn world_file(lat: f64, lon: f64, options: &OrthoOptions) -> String {
+    let (south, west, north, east) = bounding_box(lat, lon, options.extent);
+    let size = f64::from(options.size());
+    let (pixel_width, pixel_height) = ((east - west) / size, (north - south) / size);
+    format!(
+        "{pixel_width:.10}\n0.0\n0.0\n{:.10}\n{:.10}\n{:.10}\n",
+        -pixel_height,
+        west + pixel_width / 2.0,
+        north - pixel_height / 2.0
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbImage;
+
+    /// Sky on top; the ground colored by quarter: back, left, front, right.
+    fn banded_pano() -> DynamicImage {
+        let colors = [[255, 0, 0], [0, 255, 0], [0, 0, 255], [255, 255, 0]];
+        DynamicImage::ImageRgb8(RgbImage::from_fn(360, 180, |x, y| match y < 90 {
+            true => image::Rgb([255, 255, 255]),
+            false => image::Rgb(colors[((x + 45) / 90 % 4) as usize]),
+        }))
+    }
+
+    #[test]
+    fn test_orthophoto() {
+        let options = OrthoOptions::new().extent(10.0).resolution(1.0);
+        assert_eq!(options.size(), 20);
+        let pixel = |image: &RgbaImage, x, y| image.get_pixel(x, y).0;
+
+        // Facing north: forward is up, right is east.
+        let ortho = orthophoto(&banded_pano(), 0.0, None, &options);
+        assert_eq!(ortho.dimensions(), (20, 20));
+        assert_eq!(pixel(&ortho, 10, 2), [0, 0, 255, 255]);
+        assert_eq!(pixel(&ortho, 17, 10), [255, 255, 0, 255]);
+        assert_eq!(pixel(&ortho, 10, 17), [255, 0, 0, 255]);
+
+        // Facing east, north-up: north is on the panorama's left.
+        let ortho = orthophoto(&banded_pano(), 90.0, None, &options);
+        assert_eq!(pixel(&ortho, 10, 2), [0, 255, 0, 255]);
+        let heading_up = orthophoto(&banded_pano(), 90.0, None, &options.clone().north_up(false));
+        assert_eq!(pixel(&heading_up, 10, 2), [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn test_orthophoto_occlusion() {
+        // Everything is 1 m away except straight down, 2 m below: only the
+        // ground right under the camera is visible.
+        let (width, height) = (8, 4);
+        let mut depths = vec![1.0f32; (width * height) as usize];
+        depths[(width * (height - 1)) as usize..].fill(2.0);
+        let depth = DepthMap {
+            width,
+            height,
+            planes: Vec::new(),
+            plane_indices: vec![1; depths.len()],
+            depths,
+        };
+        assert_eq!(nadir_depth(&depth), Some(2.0));
+
+        let options = OrthoOptions::new().extent(10.0).resolution(1.0);
+        let ortho = orthophoto(&banded_pano(), 0.0, Some(&depth), &options);
+        assert_eq!(ortho.get_pixel(10, 10)[3], 255);
+        assert_eq!(ortho.get_pixel(10, 1)[3], 0);
+    }
+
+    #[test]
+    fn test_world_file() {
+        let options = OrthoOptions::new().extent(10.0).resolution(1.0);
+        let lines: Vec<f64> = world_file(0.0, 0.0, &options).lines().map(|line| line.parse().unwrap()).collect();
+        assert_eq!(lines.len(), 6);
+        // 1 m is about 9e-6 degrees at the equator.
+        assert!((lines[0] - 8.99e-6).abs() < 1e-8 && (lines[3] + 8.99e-6).abs() < 1e-8);
+        assert!(lines[4] < 0.0 && lines[5] > 0.0);
+    }
+}
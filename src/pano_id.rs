@@ -0,0 +1,193 @@
+use crate::error::{Result, StreetViewError};
+use std::fmt;
+use std::str::FromStr;
+
+/// Length of a classic Street View panorama ID.
+const CLASSIC_LEN: usize = 22;
+/// Shortest and longest user-contributed photosphere IDs accepted.
+const PHOTOSPHERE_LEN: std::ops::RangeInclusive<usize> = 30..=256;
+
+/// The two families of panorama IDs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum PanoIdKind {
+    /// Google-captured Street View imagery: 22 URL-safe base64 characters
+    Classic,
+    /// User-contributed photosphere: a longer ID, usually starting `AF1Qip`
+    /// or `CAoS`
+    Photosphere,
+}
+
+/// A panorama ID.
+///
+/// API methods accept anything convertible into a `PanoId`, including
+/// `&str` and `String`. Conversion trims whitespace and strips the `F:`
+/// prefix Google Maps URLs put on photosphere IDs; the methods then check
+/// the ID's shape and fail with `StreetViewError::InvalidPanoId` before
+/// sending any request, instead of a burst of failing tile downloads.
+///
+/// # Example
+///
+/// ```
+/// # use rsstreetview::{PanoId, PanoIdKind};
+/// let id = PanoId::new(" F:AF1QipNbnQqLtEDGjzfF8uHpXQjMvZ0Rh4VPr-Nt-pMo ")?;
+/// assert_eq!(id.as_str(), "AF1QipNbnQqLtEDGjzfF8uHpXQjMvZ0Rh4VPr-Nt-pMo");
+/// assert_eq!(id.kind(), Some(PanoIdKind::Photosphere));
+///
+/// assert!(PanoId::new("not a pano id").is_err());
+/// # Ok::<(), rsstreetview::StreetViewError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PanoId(String);
+
+impl PanoId {
+    /// Normalize and validate a panorama ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns `StreetViewError::InvalidPanoId` if the ID has the wrong
+    /// length or characters for either kind of panorama.
+    pub fn new(id: impl Into<PanoId>) -> Result<Self> {
+        let id = id.into();
+        id.validate()?;
+        Ok(id)
+    }
+
+    /// The normalized ID.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Which kind of panorama the ID belongs to, or `None` if it is
+    /// malformed.
+    pub fn kind(&self) -> Option<PanoIdKind> {
+        let charset_ok = self
+            .0
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+        match self.0.len() {
+            _ if !charset_ok => None,
+            CLASSIC_LEN => Some(PanoIdKind::Classic),
+            len if PHOTOSPHERE_LEN.contains(&len) => Some(PanoIdKind::Photosphere),
+            _ => None,
+        }
+    }
+
+    /// Check that the ID is well formed.
+    pub fn validate(&self) -> Result<()> {
+        match self.kind() {
+            Some(_) => Ok(()),
+            None => Err(StreetViewError::InvalidPanoId(format!(
+                "{:?} (expected {CLASSIC_LEN} characters, or {} to {} for a photosphere, \
+                 using only letters, digits, '-' and '_')",
+                self.0,
+                PHOTOSPHERE_LEN.start(),
+                PHOTOSPHERE_LEN.end()
+            ))),
+        }
+    }
+
+    /// Take the ID as a `String`.
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl From<&str> for PanoId {
+    fn from(id: &str) -> Self {
+        let id = id.trim();
+        let id = id
+            .strip_prefix("F:")
+            .or_else(|| id.strip_prefix("F%3A"))
+            .unwrap_or(id);
+        PanoId(id.to_string())
+    }
+}
+
+impl From<String> for PanoId {
+    fn from(id: String) -> Self {
+        id.as_str().into()
+    }
+}
+
+impl From<&String> for PanoId {
+    fn from(id: &String) -> Self {
+        id.as_str().into()
+    }
+}
+
+impl From<&PanoId> for PanoId {
+    fn from(id: &PanoId) -> Self {
+        id.clone()
+    }
+}
+
+impl FromStr for PanoId {
+    type Err = StreetViewError;
+
+    fn from_str(id: &str) -> Result<Self> {
+        PanoId::new(id)
+    }
+}
+
+impl AsRef<str> for PanoId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for PanoId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Serializes as the ID string; deserializing validates it.
+#[cfg(feature = "serde")]
+impl serde::Serialize for PanoId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for PanoId {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let id = String::deserialize(deserializer)?;
+        PanoId::new(id).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pano_id() {
+        let id = PanoId::new("  _pOKkvBrFPSvgChaUeWTjQ\n").unwrap();
+        assert_eq!(id.as_str(), "_pOKkvBrFPSvgChaUeWTjQ");
+        assert_eq!(id.kind(), Some(PanoIdKind::Classic));
+
+        let id: PanoId = "F%3ACAoSLEFGMVFpcE1yVGlHY3pPSlBJZGdZcnE2b2tMUUxBdzRsU3hOQ05EZmdG".parse().unwrap();
+        assert_eq!(id.kind(), Some(PanoIdKind::Photosphere));
+        assert!(id.as_str().starts_with("CAoS"));
+
+        for bad in ["", "short", "_pOKkvBrFPSvgChaUeWTj/", "../../etc/passwd_xxxxxx", "_pOKkvBrFPSvgChaUeWTjQQ"] {
+            assert!(matches!(PanoId::new(bad), Err(StreetViewError::InvalidPanoId(_))), "{bad}");
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let id: PanoId = serde_json::from_str(r#""_pOKkvBrFPSvgChaUeWTjQ""#).unwrap();
+        assert_eq!(serde_json::to_string(&id).unwrap(), r#""_pOKkvBrFPSvgChaUeWTjQ""#);
+        assert!(serde_json::from_str::<PanoId>(r#""../etc/passwd""#).is_err());
+
+        let error = PanoId::new("short").unwrap_err();
+        let value = serde_json::to_value(&error).unwrap();
+        assert_eq!(value["kind"], "InvalidPanoId");
+        assert_eq!(value["message"], error.to_string());
+    }
+}
@@ -0,0 +1,322 @@
+//! Multithreaded PNG encoder.
+//!
+//! The image is split into horizontal stripes that are filtered and
+//! deflated on separate threads. Every stripe but the last ends with a
+//! sync flush, so the raw deflate streams concatenate into one valid zlib
+//! stream (the approach used by pigz). The Adler-32 checksums of the
+//! stripes are combined without re-reading the data.
+
+use crate::embed::crc32_update;
+use crate::error::{Result, StreetViewError};
+use crate::types::{PngCompression, PngFilter, SaveOptions};
+use flate2::{Compress, Compression, FlushCompress, Status};
+use image::{DynamicImage, GenericImageView};
+use std::borrow::Cow;
+use std::io::Write;
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+/// Stripes smaller than this compress poorly and aren't worth a thread.
+const MIN_STRIPE_ROWS: usize = 64;
+/// Largest IDAT chunk written (the format allows up to 2^31 - 1 bytes).
+const MAX_IDAT_LEN: usize = 1 << 30;
+const ADLER_MOD: u32 = 65521;
+/// Largest run of bytes that can be summed before the Adler-32 sums overflow.
+const ADLER_NMAX: usize = 5552;
+
+/// Row filter types as numbered in the PNG specification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Filter {
+    None = 0,
+    Sub = 1,
+    Up = 2,
+    Avg = 3,
+    Paeth = 4,
+}
+
+const ALL_FILTERS: [Filter; 5] = [Filter::None, Filter::Sub, Filter::Up, Filter::Avg, Filter::Paeth];
+
+/// A filtered and deflated stripe of rows.
+struct Stripe {
+    deflated: Vec<u8>,
+    adler: u32,
+    filtered_len: usize,
+}
+
+/// Encode `img` as PNG on `options.threads` threads.
+pub(crate) fn write_png<W: Write>(img: &DynamicImage, options: &SaveOptions, mut writer: W) -> Result<()> {
+    let (width, height) = img.dimensions();
+    let (color_type, bit_depth, channels, pixels) = png_pixels(img);
+    let bpp = channels * bit_depth as usize / 8;
+    let row_len = width as usize * bpp;
+
+    let rows = height as usize;
+    let stripe_rows = rows.div_ceil(options.thread_count()).max(MIN_STRIPE_ROWS);
+    let level = compression_level(options.png_compression);
+
+    let stripes: Vec<Stripe> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..rows)
+            .step_by(stripe_rows)
+            .map(|start| {
+                let end = (start + stripe_rows).min(rows);
+                let pixels = &pixels;
+                scope.spawn(move || {
+                    encode_stripe(pixels, row_len, bpp, start..end, options.png_filter, level, end == rows)
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("PNG encoder thread panicked"))
+            .collect::<std::io::Result<_>>()
+    })?;
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    ihdr.extend_from_slice(&[bit_depth, color_type, 0, 0, 0]);
+
+    writer.write_all(&PNG_SIGNATURE)?;
+    write_chunk(&mut writer, b"IHDR", &ihdr)?;
+    write_chunk(&mut writer, b"IDAT", &zlib_header(options.png_compression))?;
+
+    let mut adler = 1;
+    for stripe in &stripes {
+        for piece in stripe.deflated.chunks(MAX_IDAT_LEN) {
+            write_chunk(&mut writer, b"IDAT", piece)?;
+        }
+        adler = adler32_combine(adler, stripe.adler, stripe.filtered_len);
+    }
+    if stripes.is_empty() {
+        // Zero-height image: a single empty final block
+        write_chunk(&mut writer, b"IDAT", &[0x03, 0x00])?;
+    }
+
+    write_chunk(&mut writer, b"IDAT", &adler.to_be_bytes())?;
+    write_chunk(&mut writer, b"IEND", &[])?;
+    Ok(())
+}
+
+/// PNG color type, bit depth, channel count, and big-endian sample bytes.
+fn png_pixels(img: &DynamicImage) -> (u8, u8, usize, Cow<'_, [u8]>) {
+    fn be(samples: &[u16]) -> Cow<'static, [u8]> {
+        Cow::Owned(samples.iter().flat_map(|s| s.to_be_bytes()).collect())
+    }
+
+    match img {
+        DynamicImage::ImageLuma8(buf) => (0, 8, 1, Cow::Borrowed(buf.as_raw().as_slice())),
+        DynamicImage::ImageLumaA8(buf) => (4, 8, 2, Cow::Borrowed(buf.as_raw().as_slice())),
+        DynamicImage::ImageRgb8(buf) => (2, 8, 3, Cow::Borrowed(buf.as_raw().as_slice())),
+        DynamicImage::ImageRgba8(buf) => (6, 8, 4, Cow::Borrowed(buf.as_raw().as_slice())),
+        DynamicImage::ImageLuma16(buf) => (0, 16, 1, be(buf.as_raw())),
+        DynamicImage::ImageLumaA16(buf) => (4, 16, 2, be(buf.as_raw())),
+        DynamicImage::ImageRgb16(buf) => (2, 16, 3, be(buf.as_raw())),
+        DynamicImage::ImageRgba16(buf) => (6, 16, 4, be(buf.as_raw())),
+        // Float images are stored as 16-bit, like the single-threaded encoder
+        _ if img.color().has_alpha() => (6, 16, 4, be(img.to_rgba16().as_raw())),
+        _ => (2, 16, 3, be(img.to_rgb16().as_raw())),
+    }
+}
+
+/// Filter and deflate rows `rows` of the image.
+fn encode_stripe(
+    pixels: &[u8],
+    row_len: usize,
+    bpp: usize,
+    rows: std::ops::Range<usize>,
+    filter: PngFilter,
+    level: Compression,
+    last: bool,
+) -> std::io::Result<Stripe> {
+    let mut filtered = Vec::with_capacity(rows.len() * (row_len + 1));
+    let mut candidate = Vec::with_capacity(row_len + 1);
+    let mut best = Vec::with_capacity(row_len + 1);
+    let zero_row = vec![0u8; row_len];
+
+    for y in rows {
+        let row = &pixels[y * row_len..(y + 1) * row_len];
+        let prev = if y == 0 { &zero_row[..] } else { &pixels[(y - 1) * row_len..y * row_len] };
+
+        match fixed_filter(filter) {
+            Some(kind) => filter_row(kind, row, prev, bpp, &mut filtered),
+            None => {
+                // Minimum sum of absolute differences heuristic
+                let mut best_cost = u64::MAX;
+                for kind in ALL_FILTERS {
+                    candidate.clear();
+                    filter_row(kind, row, prev, bpp, &mut candidate);
+                    let cost = candidate[1..].iter().map(|&b| (b as i8).unsigned_abs() as u64).sum();
+                    if cost < best_cost {
+                        best_cost = cost;
+                        std::mem::swap(&mut best, &mut candidate);
+                    }
+                }
+                filtered.extend_from_slice(&best);
+            }
+        }
+    }
+
+    Ok(Stripe {
+        deflated: deflate(&filtered, level, last)?,
+        adler: adler32(&filtered),
+        filtered_len: filtered.len(),
+    })
+}
+
+fn fixed_filter(filter: PngFilter) -> Option<Filter> {
+    match filter {
+        PngFilter::NoFilter => Some(Filter::None),
+        PngFilter::Sub => Some(Filter::Sub),
+        PngFilter::Up => Some(Filter::Up),
+        PngFilter::Avg => Some(Filter::Avg),
+        PngFilter::Paeth => Some(Filter::Paeth),
+        PngFilter::Adaptive => None,
+    }
+}
+
+/// Append the filter type byte and the filtered row to `out`.
+fn filter_row(kind: Filter, row: &[u8], prev: &[u8], bpp: usize, out: &mut Vec<u8>) {
+    out.push(kind as u8);
+    for (i, (&x, &b)) in row.iter().zip(prev).enumerate() {
+        let (a, c) = if i >= bpp { (row[i - bpp], prev[i - bpp]) } else { (0, 0) };
+        let predicted = match kind {
+            Filter::None => 0,
+            Filter::Sub => a,
+            Filter::Up => b,
+            Filter::Avg => ((a as u16 + b as u16) / 2) as u8,
+            Filter::Paeth => paeth(a, b, c),
+        };
+        out.push(x.wrapping_sub(predicted));
+    }
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = ((p - a as i16).abs(), (p - b as i16).abs(), (p - c as i16).abs());
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// Raw deflate `data`, ending with a final block if `last` and a sync flush otherwise.
+fn deflate(data: &[u8], level: Compression, last: bool) -> std::io::Result<Vec<u8>> {
+    let mut compress = Compress::new(level, false);
+    let mut out = Vec::with_capacity(data.len() / 2 + 1024);
+    let flush = if last { FlushCompress::Finish } else { FlushCompress::Sync };
+
+    loop {
+        if out.len() == out.capacity() {
+            out.reserve(out.capacity().max(64 * 1024));
+        }
+        let consumed = compress.total_in() as usize;
+        let status = compress
+            .compress_vec(&data[consumed..], &mut out, flush)
+            .map_err(std::io::Error::other)?;
+
+        let flushed = compress.total_in() as usize == data.len() && out.len() < out.capacity();
+        if status == Status::StreamEnd || (!last && flushed) {
+            return Ok(out);
+        }
+    }
+}
+
+fn compression_level(compression: PngCompression) -> Compression {
+    match compression {
+        PngCompression::Fast => Compression::fast(),
+        PngCompression::Default => Compression::default(),
+        PngCompression::Best => Compression::best(),
+    }
+}
+
+/// zlib stream header with the FLEVEL hint matching the compression level.
+fn zlib_header(compression: PngCompression) -> [u8; 2] {
+    match compression {
+        PngCompression::Fast => [0x78, 0x01],
+        PngCompression::Default => [0x78, 0x9C],
+        PngCompression::Best => [0x78, 0xDA],
+    }
+}
+
+fn write_chunk<W: Write>(writer: &mut W, kind: &[u8; 4], data: &[u8]) -> Result<()> {
+    let len = u32::try_from(data.len())
+        .map_err(|_| StreetViewError::EncodeError("PNG chunk too large".to_string()))?;
+    let crc = !crc32_update(crc32_update(0xFFFF_FFFF, kind), data);
+
+    writer.write_all(&len.to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+    writer.write_all(&crc.to_be_bytes())?;
+    Ok(())
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(ADLER_NMAX) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= ADLER_MOD;
+        b %= ADLER_MOD;
+    }
+    (b << 16) | a
+}
+
+/// Adler-32 of the concatenation of two buffers, given their checksums
+/// and the length of the second one.
+fn adler32_combine(adler1: u32, adler2: u32, len2: usize) -> u32 {
+    let m = ADLER_MOD as u64;
+    let rem = len2 as u64 % m;
+    let (a1, b1) = ((adler1 & 0xFFFF) as u64, (adler1 >> 16) as u64);
+    let (a2, b2) = ((adler2 & 0xFFFF) as u64, (adler2 >> 16) as u64);
+
+    let a = (a1 + a2 + m - 1) % m;
+    let b = (rem * a1 % m + b1 + b2 + m - rem) % m;
+    ((b << 16) | a) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgb, Rgba};
+
+    #[test]
+    fn test_adler32_combine() {
+        let data: Vec<u8> = (0..20_000u32).map(|i| (i * 7 % 251) as u8).collect();
+        let (left, right) = data.split_at(12_345);
+        assert_eq!(adler32_combine(adler32(left), adler32(right), right.len()), adler32(&data));
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+
+    #[test]
+    fn test_parallel_png_roundtrip() {
+        let img = DynamicImage::ImageRgb8(ImageBuffer::from_fn(97, 300, |x, y| {
+            Rgb([(x * 3) as u8, (y * 5) as u8, ((x + y) % 256) as u8])
+        }));
+
+        for filter in [PngFilter::NoFilter, PngFilter::Paeth, PngFilter::Adaptive] {
+            let options = SaveOptions::new().png_filter(filter).threads(4);
+            let mut bytes = Vec::new();
+            write_png(&img, &options, &mut bytes).unwrap();
+
+            let decoded = image::load_from_memory_with_format(&bytes, image::ImageFormat::Png).unwrap();
+            assert_eq!(decoded, img);
+        }
+    }
+
+    #[test]
+    fn test_parallel_png_16bit() {
+        let img = DynamicImage::ImageRgba16(ImageBuffer::from_fn(40, 200, |x, y| {
+            Rgba([x as u16 * 1000, y as u16 * 300, 65535, 12345])
+        }));
+        let mut bytes = Vec::new();
+        write_png(&img, &SaveOptions::new().png_fast().threads(3), &mut bytes).unwrap();
+
+        let decoded = image::load_from_memory_with_format(&bytes, image::ImageFormat::Png).unwrap();
+        assert_eq!(decoded, img);
+    }
+}
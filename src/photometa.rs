@@ -0,0 +1,367 @@
+//! Rich panorama metadata from the undocumented photometa endpoint.
+//!
+//! `GeoPhotoService.GetMetadata` returns everything the Maps web client
+//! knows about a panorama: image dimensions at every zoom level, tile
+//! size, depth map, links to neighbouring panoramas, address, and
+//! copyright. No API key is needed.
+
+use crate::depth::DepthMap;
+use crate::error::{Result, StreetViewError};
+use crate::http::Http;
+use crate::pano_id::{PanoId, PanoIdKind};
+use crate::types::{CameraGeneration, CaptureDate, Locale};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Prefix Google puts before JSON responses to prevent script inclusion.
+const XSSI_PREFIX: &str = ")]}'";
+
+/// Rich metadata for a single panorama.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhotoMeta {
+    /// Panorama ID
+    pub pano_id: String,
+    /// Latitude coordinate
+    pub lat: f64,
+    /// Longitude coordinate
+    pub lon: f64,
+    /// Camera heading in degrees
+    pub heading: Option<f64>,
+    /// Camera tilt in degrees (90 = level)
+    pub pitch: Option<f64>,
+    /// Camera roll in degrees
+    pub roll: Option<f64>,
+    /// Camera elevation in meters above mean sea level (the EGM96 geoid)
+    pub elevation: Option<f64>,
+    /// Capture month
+    pub date: Option<CaptureDate>,
+    /// Full image size `(width, height)` at each zoom level, starting at zoom 0
+    pub image_sizes: Vec<(u32, u32)>,
+    /// Tile size `(width, height)`
+    pub tile_size: Option<(u32, u32)>,
+    /// Panoramas reachable by the navigation arrows
+    pub links: Vec<PanoLink>,
+    /// Address lines, most specific first
+    pub address: Vec<String>,
+    /// Copyright notice (e.g. "© 2021 Google")
+    pub copyright: Option<String>,
+    /// Imagery collection program, e.g. `"launch"` for the car or `"scout"`
+    /// for the Trekker and other collects away from roads
+    pub source: Option<String>,
+    /// Compressed depth map, base64 encoded
+    #[serde(skip)]
+    pub(crate) depth_data: Option<String>,
+}
+
+impl PhotoMeta {
+    /// Highest zoom level available for this panorama.
+    pub fn max_zoom(&self) -> u8 {
+        self.image_sizes.len().saturating_sub(1) as u8
+    }
+
+    /// Full image size `(width, height)` at the highest zoom level.
+    ///
+    /// This is the true size of the imagery, without the black padding
+    /// the tile grid adds on some panoramas.
+    pub fn image_size(&self) -> Option<(u32, u32)> {
+        self.image_sizes.last().copied()
+    }
+
+    /// The camera generation that captured the panorama, inferred from its
+    /// ID, copyright, source, full image size, and date.
+    ///
+    /// Photospheres and imagery whose copyright does not name Google are
+    /// [`ThirdParty`](CameraGeneration::ThirdParty), and Google imagery from
+    /// the `"scout"` program is [`Trekker`](CameraGeneration::Trekker); the
+    /// rest goes by [`CameraGeneration::from_image_size`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rsstreetview::{CameraGeneration, StreetView};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = StreetView::new();
+    /// let meta = client.get_photometa("PANO_ID_HERE_0000000000").await?;
+    /// if meta.camera_generation() == CameraGeneration::Gen4 {
+    ///     let image = client.download_panorama(&meta.pano_id, 5).await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn camera_generation(&self) -> CameraGeneration {
+        let photosphere = PanoId::from(self.pano_id.as_str()).kind() == Some(PanoIdKind::Photosphere);
+        let other_owner = self.copyright.as_deref().is_some_and(|c| !c.contains("Google"));
+        if photosphere || other_owner {
+            return CameraGeneration::ThirdParty;
+        }
+        if self.source.as_deref().is_some_and(|source| source.eq_ignore_ascii_case("scout")) {
+            return CameraGeneration::Trekker;
+        }
+        match self.image_size() {
+            Some((width, height)) => CameraGeneration::from_image_size(width, height, self.date),
+            None => CameraGeneration::Unknown,
+        }
+    }
+
+    /// Whether the panorama has a depth map.
+    pub fn has_depth(&self) -> bool {
+        self.depth_data.is_some()
+    }
+
+    /// Decode the panorama's depth map, if it has one.
+    pub fn depth_map(&self) -> Result<Option<DepthMap>> {
+        self.depth_data.as_deref().map(DepthMap::decode).transpose()
+    }
+}
+
+/// A link from one panorama to a neighbouring one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PanoLink {
+    /// Panorama ID of the neighbour
+    pub pano_id: String,
+    /// Latitude of the neighbour
+    pub lat: Option<f64>,
+    /// Longitude of the neighbour
+    pub lon: Option<f64>,
+    /// Direction of the navigation arrow in degrees
+    pub heading: Option<f64>,
+}
+
+fn make_photometa_url(endpoint: &str, pano_id: &str, locale: &Locale) -> String {
+    let language = &locale.language;
+    let region = locale.region.to_lowercase();
+    format!(
+        "{endpoint}?authuser=0&hl={language}&gl={region}&pb=!1m4!1smaps_sv.tactile!11m2!2m1!1b1!2m2!1s{language}!2s{region}!3m3!1m2!1e2!2s{pano_id}!4m57!1e1!1e2!1e3!1e4!1e5!1e6!1e8!1e12!2m1!1e1!4m1!1i48!5m1!1e1!5m1!1e2!6m1!1e1!6m1!1e2!9m36!1m3!1e2!2b1!3e2!1m3!1e2!2b0!3e3!1m3!1e3!2b1!3e2!1m3!1e3!2b0!3e3!1m3!1e8!2b0!3e3!1m3!1e1!2b0!3e3!1m3!1e4!2b0!3e3!1m3!1e10!2b1!3e2!1m3!1e10!2b0!3e3"
+    )
+}
+
+/// Walk a path of array indices through a JSON value.
+fn at<'a>(value: &'a Value, path: &[usize]) -> Option<&'a Value> {
+    path.iter().try_fold(value, |v, &idx| v.get(idx))
+}
+
+fn f64_at(value: &Value, path: &[usize]) -> Option<f64> {
+    at(value, path)?.as_f64()
+}
+
+fn size_at(value: &Value, path: &[usize]) -> Option<(u32, u32)> {
+    let size = at(value, path)?;
+    Some((size.get(1)?.as_u64()? as u32, size.get(0)?.as_u64()? as u32))
+}
+
+/// Parse a photometa response.
+///
+/// This is what [`StreetView::get_photometa`](crate::StreetView::get_photometa)
+/// runs on every response, exposed for parsing saved responses offline.
+/// The `)]}'` prefix Google adds is optional.
+///
+/// # Errors
+///
+/// Returns `StreetViewError::NoPanoramasFound` for Google's "not found"
+/// response, `StreetViewError::EndpointFormatChanged` if the position is
+/// missing, and `StreetViewError::ParseError` if the text is not JSON.
+pub fn parse_photometa(text: &str) -> Result<PhotoMeta> {
+    let json = text.trim_start().strip_prefix(XSSI_PREFIX).unwrap_or(text);
+    let root: Value = serde_json::from_str(json)
+        .map_err(|e| StreetViewError::ParseError(format!("JSON parse error: {e}")))?;
+
+    let data = at(&root, &[1, 0]).ok_or(StreetViewError::NoPanoramasFound)?;
+    let pano_id = at(data, &[1, 1])
+        .and_then(Value::as_str)
+        .ok_or(StreetViewError::NoPanoramasFound)?
+        .to_string();
+
+    let changed = |path: &str| StreetViewError::EndpointFormatChanged {
+        path: path.to_string(),
+        body: text.to_string(),
+    };
+    let location = at(data, &[5, 0, 1]).ok_or_else(|| changed("[1][0][5][0][1]"))?;
+    let lat = f64_at(location, &[0, 2]).ok_or_else(|| changed("[1][0][5][0][1][0][2]"))?;
+    let lon = f64_at(location, &[0, 3]).ok_or_else(|| changed("[1][0][5][0][1][0][3]"))?;
+
+    // Sizes are listed per zoom level as [[height, width]]
+    let image_sizes = at(data, &[2, 3, 0])
+        .and_then(Value::as_array)
+        .map(|sizes| sizes.iter().filter_map(|s| size_at(s, &[0])).collect())
+        .unwrap_or_default();
+
+    let tile_size = at(data, &[2, 3, 1])
+        .and_then(|s| Some((s.get(0)?.as_u64()? as u32, s.get(1)?.as_u64()? as u32)));
+
+    let date = at(data, &[6, 7]).and_then(|d| {
+        let year = d.get(0)?.as_u64()?;
+        let month = d.get(1)?.as_u64()?;
+        CaptureDate::new(year.try_into().ok()?, month.try_into().ok()?)
+    });
+
+    let address = at(data, &[3, 2])
+        .and_then(Value::as_array)
+        .map(|lines| {
+            lines
+                .iter()
+                .filter_map(|line| line.get(0)?.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(PhotoMeta {
+        pano_id,
+        lat,
+        lon,
+        heading: f64_at(location, &[2, 0]),
+        pitch: f64_at(location, &[2, 1]),
+        roll: f64_at(location, &[2, 2]),
+        elevation: f64_at(location, &[1, 0]),
+        date,
+        image_sizes,
+        tile_size,
+        links: parse_links(data),
+        address,
+        copyright: at(data, &[4, 0, 0, 0, 0]).and_then(Value::as_str).map(str::to_string),
+        source: at(data, &[6, 5, 2]).and_then(Value::as_str).map(str::to_string),
+        depth_data: at(data, &[5, 0, 5, 1, 2]).and_then(Value::as_str).map(str::to_string),
+    })
+}
+
+/// Links reference panoramas in the nearby list by index.
+fn parse_links(data: &Value) -> Vec<PanoLink> {
+    let nearby = at(data, &[5, 0, 3, 0]).and_then(Value::as_array);
+    let links = at(data, &[5, 0, 6]).and_then(Value::as_array);
+
+    let (Some(nearby), Some(links)) = (nearby, links) else {
+        return Vec::new();
+    };
+
+    links
+        .iter()
+        .filter_map(|link| {
+            let target = nearby.get(link.get(0)?.as_u64()? as usize)?;
+            Some(PanoLink {
+                pano_id: at(target, &[0, 1])?.as_str()?.to_string(),
+                lat: f64_at(target, &[2, 0, 2]),
+                lon: f64_at(target, &[2, 0, 3]),
+                heading: f64_at(link, &[1, 3]),
+            })
+        })
+        .collect()
+}
+
+/// Fetch rich metadata for a panorama.
+pub(crate) async fn get_photometa(http: &Http, pano_id: &str) -> Result<PhotoMeta> {
+    let locale = http.locale().cloned().unwrap_or_default();
+    let url = make_photometa_url(&http.endpoints().photometa, pano_id, &locale);
+    http.fetch(&url, false, |body| parse_photometa(&String::from_utf8_lossy(body))).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Set `value` at a path of array indices, padding with nulls.
+    fn put(root: &mut Value, path: &[usize], value: Value) {
+        let mut node = root;
+        for &idx in path {
+            if !node.is_array() {
+                *node = json!([]);
+            }
+            let arr = node.as_array_mut().unwrap();
+            if arr.len() <= idx {
+                arr.resize(idx + 1, Value::Null);
+            }
+            node = &mut arr[idx];
+        }
+        *node = value;
+    }
+
+    #[test]
+    fn test_parse_photometa() {
+        let mut data = json!([]);
+        put(&mut data, &[1, 1], json!("PANO_A"));
+        put(&mut data, &[2, 3, 0], json!([[[256, 512]], [[8192, 16384]]]));
+        put(&mut data, &[2, 3, 1], json!([512, 512]));
+        put(&mut data, &[3, 2], json!([["Via Roma 1", "it"], ["Rome", "it"]]));
+        put(&mut data, &[4, 0, 0, 0, 0], json!("© 2021 Google"));
+        put(&mut data, &[5, 0, 1, 0], json!([null, null, 41.9, 12.47]));
+        put(&mut data, &[5, 0, 1, 1, 0], json!(21.5));
+        put(&mut data, &[5, 0, 1, 2], json!([90.0, 88.5, 0.5]));
+        put(&mut data, &[5, 0, 3, 0, 0], json!([[2, "PANO_A"]]));
+        put(&mut data, &[5, 0, 3, 0, 1], json!([[2, "PANO_B"], null, [[null, null, 41.91, 12.48]]]));
+        put(&mut data, &[5, 0, 5, 1, 2], json!("eJzt"));
+        put(&mut data, &[5, 0, 6], json!([[1, [null, null, null, 45.0]]]));
+        put(&mut data, &[6, 5, 2], json!("launch"));
+        put(&mut data, &[6, 7], json!([2021, 6]));
+
+        let mut root = json!([]);
+        put(&mut root, &[1, 0], data);
+        let text = format!(")]}}'\n{root}");
+
+        let meta = parse_photometa(&text).unwrap();
+        assert_eq!(meta.pano_id, "PANO_A");
+        assert_eq!((meta.lat, meta.lon), (41.9, 12.47));
+        assert_eq!(meta.elevation, Some(21.5));
+        assert_eq!(meta.max_zoom(), 1);
+        assert_eq!(meta.image_size(), Some((16384, 8192)));
+        assert_eq!(meta.tile_size, Some((512, 512)));
+        assert_eq!(meta.date, CaptureDate::new(2021, 6));
+        assert_eq!(meta.address, ["Via Roma 1", "Rome"]);
+        assert_eq!(meta.copyright.as_deref(), Some("© 2021 Google"));
+        assert_eq!(meta.source.as_deref(), Some("launch"));
+        assert!(meta.has_depth());
+        assert_eq!(
+            meta.links,
+            [PanoLink {
+                pano_id: "PANO_B".to_string(),
+                lat: Some(41.91),
+                lon: Some(12.48),
+                heading: Some(45.0),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_camera_generation() {
+        let mut meta = PhotoMeta {
+            pano_id: "Yx5YkWpCNlB3VqRQfN0v0A".to_string(),
+            lat: 41.9,
+            lon: 12.47,
+            heading: None,
+            pitch: None,
+            roll: None,
+            elevation: None,
+            date: CaptureDate::new(2021, 6),
+            image_sizes: vec![(512, 256), (16384, 8192)],
+            tile_size: None,
+            links: Vec::new(),
+            address: Vec::new(),
+            copyright: Some("© 2021 Google".to_string()),
+            source: Some("launch".to_string()),
+            depth_data: None,
+        };
+        assert_eq!(meta.camera_generation(), CameraGeneration::Gen4);
+        meta.image_sizes = vec![(13312, 6656)];
+        assert_eq!(meta.camera_generation(), CameraGeneration::Gen3);
+        meta.image_sizes = vec![(3328, 1664)];
+        assert_eq!(meta.camera_generation(), CameraGeneration::Gen2);
+        meta.date = CaptureDate::new(2008, 5);
+        assert_eq!(meta.camera_generation(), CameraGeneration::Gen1);
+        meta.image_sizes = vec![(7000, 3500)];
+        assert_eq!(meta.camera_generation(), CameraGeneration::Unknown);
+        meta.source = Some("scout".to_string());
+        assert_eq!(meta.camera_generation(), CameraGeneration::Trekker);
+
+        meta.copyright = Some("© Jane Doe".to_string());
+        assert_eq!(meta.camera_generation(), CameraGeneration::ThirdParty);
+        meta.copyright = None;
+        meta.pano_id = "AF1QipNbnQqLtEDGjzfF8uHpXQjMvZ0Rh4VPr-Nt-pMo".to_string();
+        meta.image_sizes = vec![(16384, 8192)];
+        assert_eq!(meta.camera_generation(), CameraGeneration::ThirdParty);
+    }
+
+    #[test]
+    fn test_parse_photometa_not_found() {
+        let text = ")]}'\n[[5, \"generic\", \"Not found\"]]";
+        assert!(matches!(parse_photometa(text), Err(StreetViewError::NoPanoramasFound)));
+    }
+}
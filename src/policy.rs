@@ -0,0 +1,151 @@
+//! Usage policy: compliance constraints enforced by the client.
+//!
+//! A [`Policy`] collects the switches that terms of service or local rules
+//! commonly require, and the client enforces them mechanically:
+//!
+//! - `max_qps`: every request (search, tiles, metadata) waits for a slot
+//!   from a shared rate limiter.
+//! - `official_endpoints_only`: calls that would reach Google's undocumented
+//!   endpoints fail with [`StreetViewError::PolicyViolation`] instead.
+//! - `no_face_storage`: recorded for imagery storage paths; see
+//!   [`Policy::check_storage`].
+//!
+//! Policies are advisory tools, not legal advice: check the terms that
+//! apply to your use and jurisdiction.
+
+use crate::error::{Result, StreetViewError};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Compliance constraints for a [`StreetView`](crate::StreetView) client.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Policy {
+    /// Maximum requests per second across all endpoints (default: unlimited)
+    pub max_qps: Option<f64>,
+    /// Refuse to call undocumented endpoints (default false)
+    pub official_endpoints_only: bool,
+    /// Imagery must not be stored unless faces have been removed (default false)
+    pub no_face_storage: bool,
+}
+
+impl Policy {
+    /// Create a permissive policy with no constraints.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Conservative preset: official endpoints only, at most 1 request per
+    /// second, and no storage of imagery containing faces.
+    pub fn strict() -> Self {
+        Self {
+            max_qps: Some(1.0),
+            official_endpoints_only: true,
+            no_face_storage: true,
+        }
+    }
+
+    /// Limit the request rate across all endpoints.
+    pub fn max_qps(mut self, qps: f64) -> Self {
+        self.max_qps = (qps > 0.0).then_some(qps);
+        self
+    }
+
+    /// Only allow official, documented Google Maps APIs.
+    pub fn official_endpoints_only(mut self, official_only: bool) -> Self {
+        self.official_endpoints_only = official_only;
+        self
+    }
+
+    /// Forbid storing imagery that may contain faces.
+    pub fn no_face_storage(mut self, no_face_storage: bool) -> Self {
+        self.no_face_storage = no_face_storage;
+        self
+    }
+
+    /// Check whether an undocumented endpoint may be used.
+    pub fn check_unofficial(&self, operation: &str) -> Result<()> {
+        if self.official_endpoints_only {
+            return Err(StreetViewError::PolicyViolation(format!(
+                "{operation} uses an undocumented endpoint, but the policy allows official endpoints only"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check whether imagery may be stored.
+    ///
+    /// `faces_removed` should be true when the imagery has been through a
+    /// face blurring pass.
+    pub fn check_storage(&self, faces_removed: bool) -> Result<()> {
+        if self.no_face_storage && !faces_removed {
+            return Err(StreetViewError::PolicyViolation(
+                "policy forbids storing imagery that has not had faces removed".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Internal: spaces requests evenly to stay under a maximum rate.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    interval: Duration,
+    next_slot: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(max_qps: f64) -> Self {
+        Self {
+            interval: Duration::from_secs_f64(1.0 / max_qps),
+            next_slot: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Wait until the next request slot is available.
+    pub(crate) async fn acquire(&self) {
+        let wait_until = {
+            let mut next_slot = self.next_slot.lock().unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            let slot = (*next_slot).max(now);
+            *next_slot = slot + self.interval;
+            slot
+        };
+        tokio::time::sleep_until(wait_until).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_official_only() {
+        let policy = Policy::new().official_endpoints_only(true);
+        assert!(matches!(
+            policy.check_unofficial("search"),
+            Err(StreetViewError::PolicyViolation(_))
+        ));
+        assert!(Policy::new().check_unofficial("search").is_ok());
+    }
+
+    #[test]
+    fn test_storage_check() {
+        let policy = Policy::strict();
+        assert!(policy.check_storage(false).is_err());
+        assert!(policy.check_storage(true).is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limiter_spacing() {
+        let limiter = RateLimiter::new(10.0);
+        let start = Instant::now();
+        for _ in 0..5 {
+            limiter.acquire().await;
+        }
+        // First slot is immediate, then 100 ms apart
+        assert_eq!(start.elapsed(), Duration::from_millis(400));
+    }
+}
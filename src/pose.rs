@@ -0,0 +1,379 @@
+//! Panorama poses: orientation as quaternions and rotation matrices, and
+//! WGS84 positions with ECEF and local ENU conversions.
+//!
+//! # Conventions
+//!
+//! - World frame is ENU: `x` = east, `y` = north, `z` = up.
+//! - Camera frame: `x` = right, `y` = forward, `z` = up. The identity pose
+//!   looks north with the horizon level.
+//! - `heading` is measured clockwise from north in degrees (compass bearing),
+//!   normalized to `[0, 360)` by [`normalize_heading`]. Math-convention yaw,
+//!   counter-clockwise from east, converts with [`compass_to_yaw`] and
+//!   [`yaw_to_compass`].
+//! - `pitch` is positive when the camera looks above the horizon.
+//! - `roll` is positive when the right side of the camera dips down.
+//!
+//! The rotation is applied as yaw, then pitch, then roll (intrinsic Z-X'-Y''),
+//! and maps camera-frame vectors into the world frame.
+
+use crate::types::Panorama;
+
+/// Orientation, and optionally position, of a panorama camera.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pose {
+    /// Camera position, if known
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub position: Option<GeoPosition>,
+    /// Compass heading in degrees, clockwise from north
+    pub heading: f64,
+    /// Pitch in degrees above the horizon
+    pub pitch: f64,
+    /// Roll in degrees, positive when the right side dips
+    pub roll: f64,
+}
+
+/// Unit quaternion in `(w, x, y, z)` order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Quaternion {
+    /// Scalar part
+    pub w: f64,
+    /// X component
+    pub x: f64,
+    /// Y component
+    pub y: f64,
+    /// Z component
+    pub z: f64,
+}
+
+impl Quaternion {
+    /// Hamilton product `self * other`.
+    pub fn multiply(&self, other: &Quaternion) -> Quaternion {
+        Quaternion {
+            w: self.w * other.w - self.x * other.x - self.y * other.y - self.z * other.z,
+            x: self.w * other.x + self.x * other.w + self.y * other.z - self.z * other.y,
+            y: self.w * other.y - self.x * other.z + self.y * other.w + self.z * other.x,
+            z: self.w * other.z + self.x * other.y - self.y * other.x + self.z * other.w,
+        }
+    }
+
+    /// Rotate a vector by this quaternion.
+    pub fn rotate(&self, v: [f64; 3]) -> [f64; 3] {
+        let p = Quaternion { w: 0.0, x: v[0], y: v[1], z: v[2] };
+        let r = self.multiply(&p).multiply(&self.conjugate());
+        [r.x, r.y, r.z]
+    }
+
+    /// The inverse rotation.
+    pub fn conjugate(&self) -> Quaternion {
+        Quaternion { w: self.w, x: -self.x, y: -self.y, z: -self.z }
+    }
+
+    pub(crate) fn axis_angle(axis: [f64; 3], degrees: f64) -> Quaternion {
+        let half = degrees.to_radians() / 2.0;
+        let s = half.sin();
+        Quaternion { w: half.cos(), x: axis[0] * s, y: axis[1] * s, z: axis[2] * s }
+    }
+}
+
+impl Pose {
+    /// Create a pose from heading, pitch, and roll in degrees.
+    pub fn new(heading: f64, pitch: f64, roll: f64) -> Self {
+        Self { position: None, heading, pitch, roll }
+    }
+
+    /// Set the camera position.
+    pub fn position(mut self, position: GeoPosition) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    /// Build the pose of a panorama from its search metadata.
+    ///
+    /// Google reports the camera tilt with 90° meaning level, so the pitch
+    /// is converted to degrees above the horizon. Missing values are
+    /// treated as level. The position is the panorama's location at its
+    /// elevation, or at altitude 0 if the elevation is unknown. That
+    /// elevation is above the EGM96 geoid rather than the ellipsoid; use
+    /// [`Panorama::elevation_wgs84`] with [`Pose::position`] where
+    /// absolute ECEF heights matter.
+    pub fn from_panorama(pano: &Panorama) -> Self {
+        Self {
+            position: Some(GeoPosition::new(pano.lat, pano.lon, pano.elevation.unwrap_or(0.0))),
+            heading: pano.heading,
+            pitch: pano.pitch.map(|tilt| tilt - 90.0).unwrap_or(0.0),
+            roll: pano.roll.unwrap_or(0.0),
+        }
+    }
+
+    /// Orientation as a unit quaternion (camera frame to world frame).
+    pub fn quaternion(&self) -> Quaternion {
+        // Compass heading is clockwise, rotation about +z is counterclockwise
+        let yaw = Quaternion::axis_angle([0.0, 0.0, 1.0], -self.heading);
+        let pitch = Quaternion::axis_angle([1.0, 0.0, 0.0], self.pitch);
+        let roll = Quaternion::axis_angle([0.0, 1.0, 0.0], self.roll);
+        yaw.multiply(&pitch).multiply(&roll)
+    }
+
+    /// Orientation as a row-major 3×3 rotation matrix (camera frame to world frame).
+    ///
+    /// The columns are the camera's right, forward, and up axes in ENU.
+    pub fn rotation_matrix(&self) -> [[f64; 3]; 3] {
+        let q = self.quaternion();
+        let (w, x, y, z) = (q.w, q.x, q.y, q.z);
+        [
+            [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - w * z), 2.0 * (x * z + w * y)],
+            [2.0 * (x * y + w * z), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - w * x)],
+            [2.0 * (x * z - w * y), 2.0 * (y * z + w * x), 1.0 - 2.0 * (x * x + y * y)],
+        ]
+    }
+
+    /// Camera position as an east, north, and up offset in meters from
+    /// `reference`, or `None` if the position is unknown.
+    pub fn enu(&self, reference: &GeoPosition) -> Option<[f64; 3]> {
+        Some(self.position?.to_enu(reference))
+    }
+
+    /// Forward viewing direction of the camera in ENU.
+    pub fn forward(&self) -> [f64; 3] {
+        self.quaternion().rotate([0.0, 1.0, 0.0])
+    }
+
+    /// Orientation as an nalgebra unit quaternion.
+    #[cfg(feature = "nalgebra")]
+    pub fn to_unit_quaternion(&self) -> nalgebra::UnitQuaternion<f64> {
+        let q = self.quaternion();
+        nalgebra::UnitQuaternion::from_quaternion(nalgebra::Quaternion::new(q.w, q.x, q.y, q.z))
+    }
+
+    /// Orientation as an nalgebra rotation matrix.
+    #[cfg(feature = "nalgebra")]
+    pub fn to_rotation3(&self) -> nalgebra::Rotation3<f64> {
+        self.to_unit_quaternion().to_rotation_matrix()
+    }
+}
+
+/// WGS84 semi-major axis in meters.
+const WGS84_A: f64 = 6_378_137.0;
+/// WGS84 first eccentricity squared.
+const WGS84_E2: f64 = 6.694_379_990_14e-3;
+
+/// A WGS84 position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GeoPosition {
+    /// Latitude in degrees
+    pub lat: f64,
+    /// Longitude in degrees
+    pub lon: f64,
+    /// Altitude in meters
+    pub alt: f64,
+}
+
+impl GeoPosition {
+    /// Create a position from latitude, longitude, and altitude.
+    pub fn new(lat: f64, lon: f64, alt: f64) -> Self {
+        Self { lat, lon, alt }
+    }
+
+    /// Earth-centered, Earth-fixed coordinates in meters.
+    pub fn to_ecef(&self) -> [f64; 3] {
+        let (sin_lat, cos_lat) = self.lat.to_radians().sin_cos();
+        let (sin_lon, cos_lon) = self.lon.to_radians().sin_cos();
+        let n = WGS84_A / (1.0 - WGS84_E2 * sin_lat * sin_lat).sqrt();
+        [
+            (n + self.alt) * cos_lat * cos_lon,
+            (n + self.alt) * cos_lat * sin_lon,
+            (n * (1.0 - WGS84_E2) + self.alt) * sin_lat,
+        ]
+    }
+
+    /// Position of Earth-centered, Earth-fixed coordinates in meters.
+    pub fn from_ecef(ecef: [f64; 3]) -> Self {
+        let [x, y, z] = ecef;
+        let p = x.hypot(y);
+        let mut lat = z.atan2(p * (1.0 - WGS84_E2));
+        let mut alt = 0.0;
+        // Converges to well under a millimeter in a few rounds
+        for _ in 0..5 {
+            let (sin_lat, cos_lat) = lat.sin_cos();
+            let n = WGS84_A / (1.0 - WGS84_E2 * sin_lat * sin_lat).sqrt();
+            alt = p * cos_lat + (z + WGS84_E2 * n * sin_lat) * sin_lat - n;
+            lat = z.atan2(p * (1.0 - WGS84_E2 * n / (n + alt)));
+        }
+        Self {
+            lat: lat.to_degrees(),
+            lon: y.atan2(x).to_degrees(),
+            alt,
+        }
+    }
+
+    /// East, north, and up offset in meters from `reference`, in the local
+    /// tangent plane at `reference`.
+    pub fn to_enu(&self, reference: &GeoPosition) -> [f64; 3] {
+        let (origin, target) = (reference.to_ecef(), self.to_ecef());
+        let d = [target[0] - origin[0], target[1] - origin[1], target[2] - origin[2]];
+        let [east, north, up] = enu_axes(reference);
+        let dot = |axis: [f64; 3]| axis[0] * d[0] + axis[1] * d[1] + axis[2] * d[2];
+        [dot(east), dot(north), dot(up)]
+    }
+
+    /// The position at an east, north, and up offset in meters from
+    /// `reference`. The inverse of [`to_enu`](Self::to_enu).
+    pub fn from_enu(reference: &GeoPosition, enu: [f64; 3]) -> Self {
+        let origin = reference.to_ecef();
+        let axes = enu_axes(reference);
+        let ecef = std::array::from_fn(|i| origin[i] + (0..3).map(|k| axes[k][i] * enu[k]).sum::<f64>());
+        Self::from_ecef(ecef)
+    }
+}
+
+/// East, north, and up unit vectors at `reference`, in ECEF.
+fn enu_axes(reference: &GeoPosition) -> [[f64; 3]; 3] {
+    let (sin_lat, cos_lat) = reference.lat.to_radians().sin_cos();
+    let (sin_lon, cos_lon) = reference.lon.to_radians().sin_cos();
+    [
+        [-sin_lon, cos_lon, 0.0],
+        [-sin_lat * cos_lon, -sin_lat * sin_lon, cos_lat],
+        [cos_lat * cos_lon, cos_lat * sin_lon, sin_lat],
+    ]
+}
+
+/// Normalize a compass heading in degrees to `[0, 360)`.
+pub fn normalize_heading(heading: f64) -> f64 {
+    let normalized = heading.rem_euclid(360.0);
+    // rem_euclid rounds tiny negative angles up to exactly 360
+    if normalized >= 360.0 {
+        0.0
+    } else {
+        normalized
+    }
+}
+
+/// Convert a compass heading (degrees clockwise from north) to
+/// math-convention yaw (degrees counter-clockwise from east), in
+/// `(-180, 180]`.
+pub fn compass_to_yaw(heading: f64) -> f64 {
+    let yaw = (90.0 - heading).rem_euclid(360.0);
+    if yaw > 180.0 {
+        yaw - 360.0
+    } else {
+        yaw
+    }
+}
+
+/// Convert math-convention yaw (degrees counter-clockwise from east) to a
+/// compass heading (degrees clockwise from north), in `[0, 360)`.
+pub fn yaw_to_compass(yaw: f64) -> f64 {
+    normalize_heading(90.0 - yaw)
+}
+
+impl Panorama {
+    /// Camera position and orientation of this panorama.
+    ///
+    /// See the [`pose`](crate::pose) module for axis conventions.
+    pub fn pose(&self) -> Pose {
+        Pose::from_panorama(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures::panorama;
+
+    fn assert_vec_eq(a: [f64; 3], b: [f64; 3]) {
+        for (x, y) in a.iter().zip(b.iter()) {
+            assert!((x - y).abs() < 1e-9, "{a:?} != {b:?}");
+        }
+    }
+
+    #[test]
+    fn test_heading_rotates_clockwise() {
+        // Heading 90° looks east
+        assert_vec_eq(Pose::new(90.0, 0.0, 0.0).forward(), [1.0, 0.0, 0.0]);
+        // Heading 180° looks south
+        assert_vec_eq(Pose::new(180.0, 0.0, 0.0).forward(), [0.0, -1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_heading_conventions() {
+        assert_eq!(normalize_heading(-90.0), 270.0);
+        assert_eq!(normalize_heading(360.0), 0.0);
+        assert_eq!(normalize_heading(-1e-20), 0.0);
+        assert_eq!(normalize_heading(725.5), 5.5);
+
+        // North is +90° yaw, east is 0°, west is 180°
+        assert_eq!(compass_to_yaw(0.0), 90.0);
+        assert_eq!(compass_to_yaw(90.0), 0.0);
+        assert_eq!(compass_to_yaw(180.0), -90.0);
+        assert_eq!(compass_to_yaw(270.0), 180.0);
+        for heading in [0.0, 12.5, 90.0, 181.0, 359.5] {
+            assert!((yaw_to_compass(compass_to_yaw(heading)) - heading).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_pitch_looks_up() {
+        assert_vec_eq(Pose::new(0.0, 90.0, 0.0).forward(), [0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_matrix_matches_quaternion() {
+        let pose = Pose::new(37.0, -12.0, 4.0);
+        let q = pose.quaternion();
+        let m = pose.rotation_matrix();
+        let v = [0.3, -0.5, 0.8];
+
+        let by_matrix = [
+            m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2],
+            m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2],
+            m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2],
+        ];
+        assert_vec_eq(by_matrix, q.rotate(v));
+    }
+
+    #[test]
+    fn test_from_panorama_tilt() {
+        let pano = Panorama {
+            heading: 10.0,
+            pitch: Some(92.0),
+            ..panorama("p", 0.0, 0.0)
+        };
+        let pose = pano.pose();
+        assert_eq!(pose.pitch, 2.0);
+        assert_eq!(pose.roll, 0.0);
+        assert_eq!(pose.position, Some(GeoPosition::new(0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn test_ecef_roundtrip() {
+        assert_vec_eq(GeoPosition::new(0.0, 0.0, 0.0).to_ecef(), [WGS84_A, 0.0, 0.0]);
+        assert_vec_eq(GeoPosition::new(0.0, 90.0, 10.0).to_ecef(), [0.0, WGS84_A + 10.0, 0.0]);
+
+        let position = GeoPosition::new(41.8982, 12.4765, 52.0);
+        let back = GeoPosition::from_ecef(position.to_ecef());
+        assert!((back.lat - position.lat).abs() < 1e-9 && (back.lon - position.lon).abs() < 1e-9);
+        assert!((back.alt - position.alt).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_enu() {
+        let reference = GeoPosition::new(48.0, 2.0, 35.0);
+        assert_vec_eq(reference.to_enu(&reference), [0.0, 0.0, 0.0]);
+
+        // 0.001° of latitude is ~111 m north
+        let [east, north, up] = GeoPosition::new(48.001, 2.0, 35.0).to_enu(&reference);
+        assert!(east.abs() < 1e-6 && (north - 111.2).abs() < 0.1 && up.abs() < 0.01);
+
+        let offset = [120.0, -45.0, 7.5];
+        let position = GeoPosition::from_enu(&reference, offset);
+        let back = position.to_enu(&reference);
+        assert!(back.iter().zip(offset).all(|(a, b)| (a - b).abs() < 1e-6), "{back:?}");
+
+        let pose = Pose::new(0.0, 0.0, 0.0).position(position);
+        assert_eq!(pose.enu(&reference), Some(back));
+        assert_eq!(Pose::new(0.0, 0.0, 0.0).enu(&reference), None);
+    }
+}
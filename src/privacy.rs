@@ -0,0 +1,300 @@
+//! Face and license-plate blurring before imagery is stored.
+//!
+//! A [`Redactor`] runs one or more [`Detector`]s over an image and blurs
+//! every region they report. Detection is pluggable: wrap an ONNX, OpenCV,
+//! or cloud model in a [`Detector`] implementation (closures work too), and
+//! the redactor takes care of padding, score filtering, and blurring.
+//!
+//! Attach a redactor to [`SnapshotOptions`](crate::SnapshotOptions) or a
+//! [`PanoramaStore`](crate::store::PanoramaStore) to redact everything they
+//! save. Imagery passed through a redactor with a face detector satisfies
+//! [`Policy::no_face_storage`](crate::Policy::no_face_storage).
+
+use crate::error::{Result, StreetViewError};
+use image::{imageops, DynamicImage, GenericImageView};
+use std::fmt;
+use std::sync::Arc;
+
+/// What a detected region contains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum DetectionKind {
+    /// A human face
+    Face,
+    /// A vehicle license plate
+    LicensePlate,
+}
+
+/// A region of an image reported by a [`Detector`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Detection {
+    /// What the region contains
+    pub kind: DetectionKind,
+    /// Left edge in pixels
+    pub x: u32,
+    /// Top edge in pixels
+    pub y: u32,
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// Detector confidence in [0, 1]
+    pub score: f32,
+}
+
+impl Detection {
+    /// Create a detection with a score of 1.
+    pub fn new(kind: DetectionKind, x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self { kind, x, y, width, height, score: 1.0 }
+    }
+
+    /// Set the detector confidence.
+    pub fn score(mut self, score: f32) -> Self {
+        self.score = score;
+        self
+    }
+}
+
+/// Finds faces, license plates, or both in an image.
+///
+/// Any `Fn(&DynamicImage) -> Result<Vec<Detection>>` closure is a detector.
+pub trait Detector: Send + Sync {
+    /// Return the regions to blur.
+    fn detect(&self, image: &DynamicImage) -> Result<Vec<Detection>>;
+
+    /// Whether this detector reports regions of `kind`.
+    ///
+    /// Defaults to `true`; plate-only detectors should return `false` for
+    /// [`DetectionKind::Face`] so they don't count towards
+    /// [`Policy::no_face_storage`](crate::Policy::no_face_storage).
+    fn detects(&self, kind: DetectionKind) -> bool {
+        let _ = kind;
+        true
+    }
+}
+
+impl<F> Detector for F
+where
+    F: Fn(&DynamicImage) -> Result<Vec<Detection>> + Send + Sync,
+{
+    fn detect(&self, image: &DynamicImage) -> Result<Vec<Detection>> {
+        self(image)
+    }
+}
+
+/// Blurs the regions found by its detectors.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rsstreetview::privacy::{Detection, DetectionKind, Redactor};
+/// # fn run_model(_: &image::DynamicImage) -> Vec<(u32, u32, u32, u32, f32)> { Vec::new() }
+/// # fn main() -> rsstreetview::Result<()> {
+/// let redactor = Redactor::new()
+///     .with_detector(|image: &image::DynamicImage| {
+///         Ok(run_model(image)
+///             .into_iter()
+///             .map(|(x, y, w, h, score)| Detection::new(DetectionKind::Face, x, y, w, h).score(score))
+///             .collect())
+///     })
+///     .min_score(0.5);
+///
+/// let mut view = image::open("view.jpg")?;
+/// let blurred = redactor.redact(&mut view)?;
+/// println!("blurred {} regions", blurred.len());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Redactor {
+    detectors: Vec<Arc<dyn Detector>>,
+    strength: f32,
+    padding: f32,
+    min_score: f32,
+}
+
+impl Redactor {
+    /// Create a redactor with no detectors.
+    ///
+    /// Defaults: blur strength 0.25, 10% padding around each region, and a
+    /// minimum score of 0.
+    pub fn new() -> Self {
+        Self {
+            detectors: Vec::new(),
+            strength: 0.25,
+            padding: 0.1,
+            min_score: 0.0,
+        }
+    }
+
+    /// Add a detector. Regions from every detector are blurred.
+    pub fn with_detector(mut self, detector: impl Detector + 'static) -> Self {
+        self.detectors.push(Arc::new(detector));
+        self
+    }
+
+    /// Set the blur strength: the Gaussian sigma as a fraction of the
+    /// region's larger side.
+    pub fn strength(mut self, strength: f32) -> Self {
+        self.strength = strength.max(0.01);
+        self
+    }
+
+    /// Grow each region by this fraction of its size on every side, so
+    /// loose detections still cover hair, chins, and plate frames.
+    pub fn padding(mut self, padding: f32) -> Self {
+        self.padding = padding.max(0.0);
+        self
+    }
+
+    /// Ignore detections scoring below this threshold.
+    pub fn min_score(mut self, min_score: f32) -> Self {
+        self.min_score = min_score;
+        self
+    }
+
+    /// Number of detectors.
+    pub fn detectors(&self) -> usize {
+        self.detectors.len()
+    }
+
+    /// Whether any detector finds faces, which `no_face_storage` requires.
+    ///
+    /// A redactor with no detectors blurs nothing and returns `false`.
+    pub fn removes_faces(&self) -> bool {
+        self.detectors.iter().any(|d| d.detects(DetectionKind::Face))
+    }
+
+    /// Run every detector and return the detections above the minimum score.
+    pub fn detect(&self, image: &DynamicImage) -> Result<Vec<Detection>> {
+        let mut found = Vec::new();
+        for detector in &self.detectors {
+            found.extend(detector.detect(image)?.into_iter().filter(|d| d.score >= self.min_score));
+        }
+        Ok(found)
+    }
+
+    /// Detect and blur faces and plates in place, returning what was blurred.
+    pub fn redact(&self, image: &mut DynamicImage) -> Result<Vec<Detection>> {
+        let found = self.detect(image)?;
+        for detection in &found {
+            self.blur_region(image, detection);
+        }
+        Ok(found)
+    }
+
+    /// [`redact`](Self::redact) on the blocking thread pool.
+    #[cfg_attr(not(feature = "unofficial"), allow(dead_code))]
+    pub(crate) async fn redact_async(&self, mut image: DynamicImage) -> Result<DynamicImage> {
+        let redactor = self.clone();
+        tokio::task::spawn_blocking(move || redactor.redact(&mut image).map(|_| image))
+            .await
+            .map_err(|e| StreetViewError::IoError(std::io::Error::other(e)))?
+    }
+
+    /// Blur one region, padded and clipped to the image.
+    pub fn blur_region(&self, image: &mut DynamicImage, detection: &Detection) {
+        let (width, height) = image.dimensions();
+        let pad_x = (detection.width as f32 * self.padding).round() as u32;
+        let pad_y = (detection.height as f32 * self.padding).round() as u32;
+        let x0 = detection.x.saturating_sub(pad_x).min(width);
+        let y0 = detection.y.saturating_sub(pad_y).min(height);
+        let x1 = detection.x.saturating_add(detection.width).saturating_add(pad_x).min(width);
+        let y1 = detection.y.saturating_add(detection.height).saturating_add(pad_y).min(height);
+        if x1 <= x0 || y1 <= y0 {
+            return;
+        }
+
+        let region = image.crop_imm(x0, y0, x1 - x0, y1 - y0);
+        let sigma = (self.strength * (x1 - x0).max(y1 - y0) as f32).max(1.0);
+        imageops::replace(image, &region.blur(sigma), i64::from(x0), i64::from(y0));
+    }
+}
+
+impl Default for Redactor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Redactor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Redactor")
+            .field("detectors", &self.detectors.len())
+            .field("strength", &self.strength)
+            .field("padding", &self.padding)
+            .field("min_score", &self.min_score)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    /// Black and white stripes, one pixel wide.
+    fn stripes() -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(64, 64, |x, _| match x % 2 {
+            0 => Rgb([0, 0, 0]),
+            _ => Rgb([255, 255, 255]),
+        }))
+    }
+
+    #[test]
+    fn test_redact() {
+        let redactor = Redactor::new()
+            .with_detector(|_: &DynamicImage| {
+                Ok(vec![
+                    Detection::new(DetectionKind::Face, 10, 10, 20, 20).score(0.9),
+                    Detection::new(DetectionKind::LicensePlate, 40, 40, 10, 5).score(0.2),
+                ])
+            })
+            .padding(0.0)
+            .min_score(0.5);
+
+        let mut image = stripes();
+        let found = redactor.redact(&mut image).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].kind, DetectionKind::Face);
+
+        // Stripes inside the face are smoothed to grey; everything else is untouched.
+        let [r, _, _, _] = image.get_pixel(20, 20).0;
+        assert!((64..192).contains(&r), "{r}");
+        assert_eq!(image.get_pixel(5, 5), stripes().get_pixel(5, 5));
+        assert_eq!(image.get_pixel(45, 42), stripes().get_pixel(45, 42));
+    }
+
+    struct PlateDetector;
+
+    impl Detector for PlateDetector {
+        fn detect(&self, _: &DynamicImage) -> Result<Vec<Detection>> {
+            Ok(Vec::new())
+        }
+
+        fn detects(&self, kind: DetectionKind) -> bool {
+            kind == DetectionKind::LicensePlate
+        }
+    }
+
+    #[test]
+    fn test_removes_faces() {
+        assert!(!Redactor::new().removes_faces());
+        assert!(!Redactor::new().with_detector(PlateDetector).removes_faces());
+        let faces = Redactor::new().with_detector(|_: &DynamicImage| Ok(Vec::new()));
+        assert!(faces.removes_faces());
+        assert!(faces.with_detector(PlateDetector).removes_faces());
+    }
+
+    #[test]
+    fn test_blur_region_clips_to_image() {
+        let mut image = stripes();
+        let redactor = Redactor::new();
+        redactor.blur_region(&mut image, &Detection::new(DetectionKind::Face, 60, 60, 50, 50));
+        redactor.blur_region(&mut image, &Detection::new(DetectionKind::Face, 100, 100, 5, 5));
+        assert_eq!(image.dimensions(), (64, 64));
+        assert_ne!(image.get_pixel(62, 62), stripes().get_pixel(62, 62));
+    }
+}
@@ -0,0 +1,376 @@
+//! Panorama providers behind one interface.
+//!
+//! [`PanoramaProvider`] is implemented by [`StreetView`](crate::StreetView)
+//! for Google, by [`BingStreetside`](crate::bing::BingStreetside) with the
+//! `bing` feature, by [`Mapillary`](crate::mapillary::Mapillary) with the
+//! `mapillary` feature, and by [`KartaView`](crate::kartaview::KartaView)
+//! with the `kartaview` feature, so code written against the trait can fall
+//! back to another provider where one lacks coverage.
+//!
+//! [`MultiProvider`] combines several providers in priority order, merging
+//! their results and tagging each with the provider that found it.
+//!
+//! Panorama IDs are provider specific: pass IDs back to the provider whose
+//! search returned them.
+
+use crate::error::{Result, StreetViewError};
+use crate::policy::RateLimiter;
+use crate::types::Panorama;
+use futures::future::BoxFuture;
+use image::DynamicImage;
+use std::sync::Arc;
+
+/// A source of street-level panoramas.
+pub trait PanoramaProvider: Send + Sync {
+    /// Short provider name (e.g. "google", "bing", "mapillary").
+    fn name(&self) -> &'static str;
+
+    /// Panoramas near a coordinate.
+    fn search(&self, lat: f64, lon: f64) -> BoxFuture<'_, Result<Vec<Panorama>>>;
+
+    /// Download a full panorama as an equirectangular image whose center
+    /// faces the panorama's `heading`.
+    ///
+    /// `zoom` starts at 1 for the smallest image; each provider clamps it
+    /// to the levels it offers.
+    fn download<'a>(&'a self, pano_id: &'a str, zoom: u8) -> BoxFuture<'a, Result<DynamicImage>>;
+}
+
+#[cfg(feature = "unofficial")]
+impl PanoramaProvider for crate::StreetView {
+    fn name(&self) -> &'static str {
+        "google"
+    }
+
+    fn search(&self, lat: f64, lon: f64) -> BoxFuture<'_, Result<Vec<Panorama>>> {
+        Box::pin(self.search_panoramas(lat, lon))
+    }
+
+    fn download<'a>(&'a self, pano_id: &'a str, zoom: u8) -> BoxFuture<'a, Result<DynamicImage>> {
+        Box::pin(self.download_panorama(pano_id, zoom.clamp(1, 7)))
+    }
+}
+
+/// Search each provider in turn and return the first non-empty result,
+/// together with the provider that found it.
+///
+/// # Errors
+///
+/// Returns the last provider's error if every provider failed, and
+/// `StreetViewError::NoPanoramasFound` if none of them has coverage.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "bing")]
+/// # #[tokio::main]
+/// # async fn main() -> rsstreetview::Result<()> {
+/// use rsstreetview::bing::BingStreetside;
+/// use rsstreetview::provider::{search_with_fallback, PanoramaProvider};
+/// use rsstreetview::StreetView;
+///
+/// let google = StreetView::new();
+/// let bing = BingStreetside::new(&google);
+/// let providers: [&dyn PanoramaProvider; 2] = [&google, &bing];
+///
+/// let (provider, panos) = search_with_fallback(&providers, 47.6205, -122.3493).await?;
+/// let image = provider.download(&panos[0].pano_id, 3).await?;
+/// image.save(format!("{}.jpg", provider.name()))?;
+/// # Ok(())
+/// # }
+/// # #[cfg(not(feature = "bing"))]
+/// # fn main() {}
+/// ```
+pub async fn search_with_fallback<'a>(
+    providers: &[&'a dyn PanoramaProvider],
+    lat: f64,
+    lon: f64,
+) -> Result<(&'a dyn PanoramaProvider, Vec<Panorama>)> {
+    let mut last_error = None;
+    let mut any_answered = false;
+
+    for &provider in providers {
+        match provider.search(lat, lon).await {
+            Ok(panos) if !panos.is_empty() => return Ok((provider, panos)),
+            Ok(_) => any_answered = true,
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    match last_error {
+        Some(e) if !any_answered => Err(e),
+        _ => Err(StreetViewError::NoPanoramasFound),
+    }
+}
+
+/// A panorama found by a [`MultiProvider`], with the provider that found it.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProviderPanorama {
+    /// [`PanoramaProvider::name`] of the provider
+    pub provider: String,
+    /// The panorama, with the provider's own ID
+    pub panorama: Panorama,
+}
+
+struct Registered {
+    provider: Arc<dyn PanoramaProvider>,
+    limiter: Option<RateLimiter>,
+}
+
+impl Registered {
+    async fn acquire(&self) {
+        if let Some(limiter) = &self.limiter {
+            limiter.acquire().await;
+        }
+    }
+}
+
+/// Providers queried in priority order, with merged and deduplicated
+/// results.
+///
+/// Each search asks every provider in the order they were added. A
+/// panorama within [`dedup_radius`](Self::dedup_radius) of one already
+/// found by a higher-priority provider is dropped, so where Google and Bing
+/// both have coverage only Google's panorama is kept. Providers that fail
+/// are skipped.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "bing")]
+/// # #[tokio::main]
+/// # async fn main() -> rsstreetview::Result<()> {
+/// use rsstreetview::bing::BingStreetside;
+/// use rsstreetview::provider::MultiProvider;
+/// use rsstreetview::StreetView;
+///
+/// let google = StreetView::new();
+/// let providers = MultiProvider::new()
+///     .with_provider(BingStreetside::new(&google))
+///     .with_rate_limited_provider(google, 2.0);
+///
+/// for found in providers.search(47.6205, -122.3493).await? {
+///     let image = providers.download(&found, 3).await?;
+///     image.save(format!("{}_{}.jpg", found.provider, found.panorama.pano_id))?;
+/// }
+/// # Ok(())
+/// # }
+/// # #[cfg(not(feature = "bing"))]
+/// # fn main() {}
+/// ```
+pub struct MultiProvider {
+    providers: Vec<Registered>,
+    dedup_radius: f64,
+    stop_at_first: bool,
+}
+
+impl Default for MultiProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MultiProvider {
+    /// An empty registry deduplicating within 5 meters.
+    pub fn new() -> Self {
+        Self {
+            providers: Vec::new(),
+            dedup_radius: 5.0,
+            stop_at_first: false,
+        }
+    }
+
+    /// Add a provider, below every provider added before it.
+    pub fn with_provider(mut self, provider: impl PanoramaProvider + 'static) -> Self {
+        self.providers.push(Registered {
+            provider: Arc::new(provider),
+            limiter: None,
+        });
+        self
+    }
+
+    /// Add a provider that is called at most `max_qps` times per second,
+    /// counting searches and downloads.
+    ///
+    /// This limits calls through the registry, on top of any HTTP rate limit
+    /// on the provider's client.
+    pub fn with_rate_limited_provider(mut self, provider: impl PanoramaProvider + 'static, max_qps: f64) -> Self {
+        self.providers.push(Registered {
+            provider: Arc::new(provider),
+            limiter: Some(RateLimiter::new(max_qps)),
+        });
+        self
+    }
+
+    /// Drop panoramas within `meters` of one from a higher-priority provider
+    /// (default 5). Zero keeps everything.
+    pub fn dedup_radius(mut self, meters: f64) -> Self {
+        self.dedup_radius = meters.max(0.0);
+        self
+    }
+
+    /// Stop at the first provider with results instead of merging all of
+    /// them (default false).
+    pub fn stop_at_first(mut self, stop: bool) -> Self {
+        self.stop_at_first = stop;
+        self
+    }
+
+    /// Names of the registered providers, in priority order.
+    pub fn names(&self) -> Vec<&'static str> {
+        self.providers.iter().map(|registered| registered.provider.name()).collect()
+    }
+
+    /// Search every provider and merge the results: providers in priority
+    /// order, each provider's panoramas in the order it returned them.
+    ///
+    /// # Errors
+    ///
+    /// Returns the last provider's error if every provider failed, and
+    /// `StreetViewError::NoPanoramasFound` if none of them has coverage.
+    pub async fn search(&self, lat: f64, lon: f64) -> Result<Vec<ProviderPanorama>> {
+        let mut found: Vec<ProviderPanorama> = Vec::new();
+        let mut last_error = None;
+        let mut any_answered = false;
+
+        for registered in &self.providers {
+            registered.acquire().await;
+            let name = registered.provider.name();
+            let panos = match registered.provider.search(lat, lon).await {
+                Ok(panos) => panos,
+                Err(error) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(provider = name, %error, "provider search failed");
+                    last_error = Some(error);
+                    continue;
+                }
+            };
+            any_answered = true;
+
+            let kept = found.len();
+            let new: Vec<_> = panos
+                .into_iter()
+                .filter(|pano| {
+                    let location = pano.location();
+                    !found[..kept]
+                        .iter()
+                        .any(|other| other.panorama.location().distance_to(&location) < self.dedup_radius)
+                })
+                .map(|panorama| ProviderPanorama {
+                    provider: name.to_string(),
+                    panorama,
+                })
+                .collect();
+            found.extend(new);
+
+            if self.stop_at_first && !found.is_empty() {
+                break;
+            }
+        }
+
+        match last_error {
+            _ if !found.is_empty() => Ok(found),
+            Some(e) if !any_answered => Err(e),
+            _ => Err(StreetViewError::NoPanoramasFound),
+        }
+    }
+
+    /// Download a search result from the provider that found it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `StreetViewError::InvalidResponse` if no registered provider
+    /// has the result's provider name.
+    pub async fn download(&self, found: &ProviderPanorama, zoom: u8) -> Result<DynamicImage> {
+        let registered = self
+            .providers
+            .iter()
+            .find(|registered| registered.provider.name() == found.provider)
+            .ok_or_else(|| StreetViewError::InvalidResponse(format!("No provider named {:?}", found.provider)))?;
+        registered.acquire().await;
+        registered.provider.download(&found.panorama.pano_id, zoom).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures::panorama;
+    use futures::future::ready;
+
+    struct Fixed(&'static str, Option<usize>);
+
+    impl PanoramaProvider for Fixed {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn search(&self, lat: f64, lon: f64) -> BoxFuture<'_, Result<Vec<Panorama>>> {
+            let panos = self.1.map(|count| {
+                (0..count)
+                    .map(|i| panorama(&format!("{}-{i}", self.0), lat, lon))
+                    .collect()
+            });
+            Box::pin(ready(panos.ok_or(StreetViewError::InvalidUrl)))
+        }
+
+        fn download<'a>(&'a self, _pano_id: &'a str, _zoom: u8) -> BoxFuture<'a, Result<DynamicImage>> {
+            Box::pin(ready(Ok(DynamicImage::new_rgb8(2, 1))))
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_multi_provider() {
+        let providers = MultiProvider::new()
+            .with_provider(Fixed("failing", None))
+            .with_provider(Fixed("first", Some(2)))
+            .with_rate_limited_provider(Fixed("second", Some(1)), 1.0);
+        assert_eq!(providers.names(), ["failing", "first", "second"]);
+
+        // "second" finds the same spot as "first", so its result is dropped.
+        let found = providers.search(1.0, 2.0).await.unwrap();
+        assert_eq!(found.len(), 2);
+        assert!(found.iter().all(|found| found.provider == "first"));
+
+        let found = providers.dedup_radius(0.0).search(1.0, 2.0).await.unwrap();
+        assert_eq!(found.len(), 3);
+        assert_eq!((found[2].provider.as_str(), found[2].panorama.pano_id.as_str()), ("second", "second-0"));
+
+        // The second call to "second" waits a full second for its rate limit.
+        let started = tokio::time::Instant::now();
+        let providers = MultiProvider::new().with_rate_limited_provider(Fixed("limited", Some(1)), 1.0);
+        let found = providers.search(0.0, 0.0).await.unwrap();
+        providers.download(&found[0], 1).await.unwrap();
+        assert_eq!(started.elapsed(), std::time::Duration::from_secs(1));
+
+        let providers = MultiProvider::new()
+            .with_provider(Fixed("first", Some(1)))
+            .with_provider(Fixed("second", Some(1)))
+            .stop_at_first(true);
+        let found = providers.dedup_radius(0.0).search(0.0, 0.0).await.unwrap();
+        assert_eq!(found.len(), 1);
+
+        let stranger = ProviderPanorama {
+            provider: "bing".to_string(),
+            panorama: found[0].panorama.clone(),
+        };
+        assert!(MultiProvider::new().download(&stranger, 1).await.is_err());
+        let result = MultiProvider::new().with_provider(Fixed("failing", None)).search(0.0, 0.0).await;
+        assert!(matches!(result, Err(StreetViewError::InvalidUrl)));
+    }
+
+    #[tokio::test]
+    async fn test_search_with_fallback() {
+        let (empty, failing, found) = (Fixed("empty", Some(0)), Fixed("failing", None), Fixed("found", Some(2)));
+
+        let (provider, panos) = search_with_fallback(&[&empty, &failing, &found], 0.0, 0.0).await.unwrap();
+        assert_eq!(provider.name(), "found");
+        assert_eq!(panos[0].pano_id, "found-0");
+
+        let result = search_with_fallback(&[&failing, &empty], 0.0, 0.0).await;
+        assert!(matches!(result, Err(StreetViewError::NoPanoramasFound)));
+        let result = search_with_fallback(&[&failing], 0.0, 0.0).await;
+        assert!(matches!(result, Err(StreetViewError::InvalidUrl)));
+    }
+}
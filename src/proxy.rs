@@ -0,0 +1,247 @@
+//! Rotating proxy pool.
+//!
+//! A [`ProxyPool`] attached with
+//! [`StreetView::with_proxy_pool`](crate::StreetView::with_proxy_pool)
+//! sends each request through the next proxy in the pool. Proxies that fail
+//! to connect several times in a row are removed, and
+//! [`check_health`](ProxyPool::check_health) prunes dead proxies up front.
+//!
+//! Proxied requests use a client built for each proxy, so settings from
+//! [`StreetViewBuilder::client`](crate::StreetViewBuilder::client) do not apply
+//! to them; set the timeout on the pool instead.
+
+use crate::error::{Result, StreetViewError};
+use reqwest::{Client, Proxy};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DEFAULT_MAX_FAILURES: u32 = 3;
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How the next proxy is chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ProxyRotation {
+    /// Cycle through the proxies in order (default)
+    #[default]
+    RoundRobin,
+    /// Pick a proxy at random for each request
+    Random,
+}
+
+#[derive(Debug)]
+struct ProxyEntry {
+    url: String,
+    client: Client,
+    failures: u32,
+}
+
+/// A set of proxies that requests are spread across.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rsstreetview::{ProxyPool, ProxyRotation, StreetView};
+/// # fn main() -> rsstreetview::Result<()> {
+/// let pool = ProxyPool::new(["http://proxy1:8080", "socks5://proxy2:1080"])?
+///     .rotation(ProxyRotation::Random);
+/// let client = StreetView::new().with_proxy_pool(pool);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ProxyPool {
+    entries: Mutex<Vec<ProxyEntry>>,
+    rotation: ProxyRotation,
+    max_failures: u32,
+    next: AtomicUsize,
+    rng: AtomicU64,
+}
+
+impl ProxyPool {
+    /// Create a pool from proxy URLs (`http://`, `https://`, or `socks5://`).
+    ///
+    /// # Errors
+    ///
+    /// Returns `StreetViewError::ParseError` if a URL is not a valid proxy.
+    pub fn new<I, S>(urls: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let entries = urls
+            .into_iter()
+            .map(|url| build_entry(url.as_ref(), DEFAULT_TIMEOUT))
+            .collect::<Result<Vec<_>>>()?;
+
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+
+        Ok(Self {
+            entries: Mutex::new(entries),
+            rotation: ProxyRotation::default(),
+            max_failures: DEFAULT_MAX_FAILURES,
+            next: AtomicUsize::new(0),
+            rng: AtomicU64::new(seed | 1),
+        })
+    }
+
+    /// Set how the next proxy is chosen.
+    pub fn rotation(mut self, rotation: ProxyRotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Remove a proxy after this many consecutive failed requests (default 3).
+    pub fn max_failures(mut self, failures: u32) -> Self {
+        self.max_failures = failures.max(1);
+        self
+    }
+
+    /// Set the request timeout for proxied requests (default 30 s).
+    pub fn timeout(self, timeout: Duration) -> Result<Self> {
+        let entries = self
+            .lock()
+            .iter()
+            .map(|entry| build_entry(&entry.url, timeout))
+            .collect::<Result<Vec<_>>>()?;
+        *self.lock() = entries;
+        Ok(self)
+    }
+
+    /// URLs of the proxies still in the pool.
+    pub fn proxies(&self) -> Vec<String> {
+        self.lock().iter().map(|entry| entry.url.clone()).collect()
+    }
+
+    /// Number of proxies still in the pool.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Whether every proxy has been removed.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Send a request to `url` through every proxy and remove the ones that
+    /// fail. Returns the URLs of the removed proxies.
+    pub async fn check_health(&self, url: &str) -> Vec<String> {
+        let candidates: Vec<(String, Client)> =
+            self.lock().iter().map(|entry| (entry.url.clone(), entry.client.clone())).collect();
+
+        let checks = candidates.into_iter().map(|(proxy, client)| async move {
+            let healthy = client.get(url).send().await.is_ok_and(|r| !r.status().is_server_error());
+            (proxy, healthy)
+        });
+
+        let dead: Vec<String> = futures::future::join_all(checks)
+            .await
+            .into_iter()
+            .filter(|(_, healthy)| !healthy)
+            .map(|(proxy, _)| proxy)
+            .collect();
+
+        self.lock().retain(|entry| !dead.contains(&entry.url));
+        dead
+    }
+
+    /// Choose the proxy for the next request.
+    pub(crate) fn pick(&self) -> Result<(String, Client)> {
+        let entries = self.lock();
+        if entries.is_empty() {
+            return Err(StreetViewError::NoProxiesAvailable);
+        }
+
+        let idx = match self.rotation {
+            ProxyRotation::RoundRobin => self.next.fetch_add(1, Ordering::Relaxed),
+            ProxyRotation::Random => self.next_random() as usize,
+        } % entries.len();
+
+        let entry = &entries[idx];
+        Ok((entry.url.clone(), entry.client.clone()))
+    }
+
+    /// Record the outcome of a request through `proxy`, removing it once it
+    /// has failed too many times in a row.
+    pub(crate) fn report(&self, proxy: &str, ok: bool) {
+        let mut entries = self.lock();
+        let Some(pos) = entries.iter().position(|entry| entry.url == proxy) else {
+            return;
+        };
+
+        if ok {
+            entries[pos].failures = 0;
+        } else {
+            entries[pos].failures += 1;
+            if entries[pos].failures >= self.max_failures {
+                entries.remove(pos);
+            }
+        }
+    }
+
+    /// xorshift64: plenty for spreading load, no extra dependency.
+    fn next_random(&self) -> u64 {
+        let mut x = self.rng.load(Ordering::Relaxed);
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.rng.store(x, Ordering::Relaxed);
+        x
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<ProxyEntry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn build_entry(url: &str, timeout: Duration) -> Result<ProxyEntry> {
+    let invalid = |e: reqwest::Error| StreetViewError::ParseError(format!("Invalid proxy {url}: {e}"));
+    let proxy = Proxy::all(url).map_err(invalid)?;
+    let client = Client::builder().proxy(proxy).timeout(timeout).build().map_err(invalid)?;
+    Ok(ProxyEntry {
+        url: url.to_string(),
+        client,
+        failures: 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_robin_and_removal() {
+        let pool = ProxyPool::new(["http://a:1", "http://b:2"]).unwrap().max_failures(2);
+        let picks: Vec<String> = (0..4).map(|_| pool.pick().unwrap().0).collect();
+        assert_eq!(picks, ["http://a:1", "http://b:2", "http://a:1", "http://b:2"]);
+
+        // A success resets the failure count
+        pool.report("http://a:1", false);
+        pool.report("http://a:1", true);
+        pool.report("http://a:1", false);
+        assert_eq!(pool.len(), 2);
+
+        pool.report("http://a:1", false);
+        assert_eq!(pool.proxies(), ["http://b:2"]);
+
+        pool.report("http://b:2", false);
+        pool.report("http://b:2", false);
+        assert!(matches!(pool.pick(), Err(StreetViewError::NoProxiesAvailable)));
+    }
+
+    #[test]
+    fn test_random_rotation() {
+        let pool = ProxyPool::new(["http://a:1", "http://b:2", "http://c:3"])
+            .unwrap()
+            .rotation(ProxyRotation::Random);
+        for _ in 0..20 {
+            assert!(pool.proxies().contains(&pool.pick().unwrap().0));
+        }
+        assert!(ProxyPool::new(["not a proxy url"]).is_err());
+    }
+}
@@ -0,0 +1,144 @@
+//! Quota tracking for billable official API requests.
+//!
+//! Each Street View Static API image request is billed; metadata requests
+//! are free. A [`QuotaTracker`] counts billable requests made by a client,
+//! for the session and per UTC day, and can refuse further requests once a
+//! budget is spent so a runaway batch job fails fast instead of running up
+//! a bill.
+//!
+//! Counts live in memory only. Requests are counted when sent, whether or
+//! not Google returns an image.
+
+use crate::error::{Result, StreetViewError};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Counts billable requests and enforces optional budgets.
+#[derive(Debug, Default)]
+pub struct QuotaTracker {
+    session_budget: Option<u64>,
+    daily_budget: Option<u64>,
+    counts: Mutex<Counts>,
+}
+
+#[derive(Debug, Default)]
+struct Counts {
+    session: u64,
+    day: u64,
+    today: u64,
+}
+
+impl QuotaTracker {
+    /// Create a tracker with no budgets; it only counts.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow at most `requests` billable requests over the tracker's lifetime.
+    pub fn session_budget(mut self, requests: u64) -> Self {
+        self.session_budget = Some(requests);
+        self
+    }
+
+    /// Allow at most `requests` billable requests per UTC day.
+    pub fn daily_budget(mut self, requests: u64) -> Self {
+        self.daily_budget = Some(requests);
+        self
+    }
+
+    /// Billable requests made since the tracker was created.
+    pub fn session_count(&self) -> u64 {
+        self.lock().session
+    }
+
+    /// Billable requests made today (UTC).
+    pub fn daily_count(&self) -> u64 {
+        let counts = self.lock();
+        if counts.day == current_day() {
+            counts.today
+        } else {
+            0
+        }
+    }
+
+    /// Requests left before a budget is hit, or `None` if unbudgeted.
+    pub fn remaining(&self) -> Option<u64> {
+        let session = self.session_budget.map(|b| b.saturating_sub(self.session_count()));
+        let daily = self.daily_budget.map(|b| b.saturating_sub(self.daily_count()));
+        match (session, daily) {
+            (Some(s), Some(d)) => Some(s.min(d)),
+            (s, d) => s.or(d),
+        }
+    }
+
+    /// Record one billable request, or fail if it would exceed a budget.
+    pub(crate) fn charge(&self) -> Result<()> {
+        self.charge_on(current_day())
+    }
+
+    fn charge_on(&self, day: u64) -> Result<()> {
+        let mut counts = self.lock();
+        if counts.day != day {
+            counts.day = day;
+            counts.today = 0;
+        }
+
+        if let Some(budget) = self.session_budget.filter(|&b| counts.session >= b) {
+            return Err(StreetViewError::QuotaBudgetExceeded(format!(
+                "session budget of {budget} requests spent"
+            )));
+        }
+        if let Some(budget) = self.daily_budget.filter(|&b| counts.today >= b) {
+            return Err(StreetViewError::QuotaBudgetExceeded(format!(
+                "daily budget of {budget} requests spent"
+            )));
+        }
+
+        counts.session += 1;
+        counts.today += 1;
+        Ok(())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Counts> {
+        self.counts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Days since the Unix epoch, in UTC.
+fn current_day() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / SECONDS_PER_DAY)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budgets() {
+        let tracker = QuotaTracker::new().session_budget(5).daily_budget(3);
+        for _ in 0..3 {
+            tracker.charge_on(1).unwrap();
+        }
+        assert!(matches!(tracker.charge_on(1), Err(StreetViewError::QuotaBudgetExceeded(_))));
+
+        // A new day resets the daily count but not the session count
+        tracker.charge_on(2).unwrap();
+        tracker.charge_on(2).unwrap();
+        assert_eq!(tracker.session_count(), 5);
+        assert!(matches!(tracker.charge_on(2), Err(StreetViewError::QuotaBudgetExceeded(_))));
+        assert_eq!(tracker.remaining(), Some(0));
+    }
+
+    #[test]
+    fn test_unbudgeted() {
+        let tracker = QuotaTracker::new();
+        tracker.charge().unwrap();
+        assert_eq!(tracker.daily_count(), 1);
+        assert_eq!(tracker.remaining(), None);
+    }
+}
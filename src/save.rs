@@ -1,11 +1,24 @@
-use crate::error::Result;
-use crate::types::{ImageFormat, SaveOptions};
+use crate::embed::EmbeddedMetadata;
+use crate::error::{Result, StreetViewError};
+use crate::sidecar::{sidecar_path, SidecarMetadata};
+use crate::types::{ChromaSubsampling, ImageFormat, Panorama, SaveOptions};
 use image::codecs::jpeg::JpegEncoder;
 use image::codecs::png::PngEncoder;
-use image::{DynamicImage, ExtendedColorType, ImageEncoder};
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::{ColorType, DynamicImage, ExtendedColorType, GenericImageView};
+use std::borrow::Cow;
 use std::fs::{self, File};
-use std::io::{BufWriter, Cursor};
-use std::path::Path;
+use std::future::Future;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+
+/// Size of the chunks handed from the blocking encoder to an async writer.
+const ASYNC_CHUNK_SIZE: usize = 256 * 1024;
+/// Number of chunks that may be queued before the encoder waits for the writer.
+const ASYNC_CHUNK_QUEUE: usize = 4;
 
 /// Save a panorama image with specific format and quality settings.
 ///
@@ -24,79 +37,388 @@ pub fn save_panorama(
 
     // Open output file
     let file = File::create(path)?;
-    let writer = BufWriter::new(file);
+    let mut writer = BufWriter::new(file);
+    encode_to_writer(img, options, &mut writer)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Encode a panorama image to bytes with specific format and quality settings.
+///
+/// Returns the encoded image as a `Vec<u8>`.
+pub fn encode_panorama(img: &DynamicImage, options: &SaveOptions) -> Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    encode_to_writer(img, options, &mut buffer)?;
+    Ok(buffer)
+}
 
-    // Convert image to RGB8 for encoding
-    let rgb_img = img.to_rgb8();
-    let (width, height) = rgb_img.dimensions();
+/// Encode a panorama image into any `Write` sink.
+///
+/// The encoded bytes are streamed into `writer` as they are produced, so the
+/// full encoded file never has to be held in memory. Wrap unbuffered sinks
+/// (files, sockets) in a `BufWriter`.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rsstreetview::{encode_to_writer, SaveOptions, ImageFormat};
+/// # fn run(image: &image::DynamicImage) -> Result<(), Box<dyn std::error::Error>> {
+/// let mut out = std::io::BufWriter::new(std::fs::File::create("panorama.jpg")?);
+/// encode_to_writer(image, &SaveOptions::new().format(ImageFormat::Jpeg), &mut out)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn encode_to_writer<W: Write>(img: &DynamicImage, options: &SaveOptions, writer: W) -> Result<()> {
+    let attributed;
+    let img = match &options.attribution {
+        Some(attribution) => {
+            let mut copy = img.clone();
+            attribution.apply(&mut copy)?;
+            attributed = copy;
+            &attributed
+        }
+        None => img,
+    };
 
     match options.format {
         ImageFormat::Jpeg => {
-            let mut encoder = JpegEncoder::new_with_quality(writer, options.jpeg_quality);
-            encoder.encode(
-                rgb_img.as_raw(),
-                width,
-                height,
-                ExtendedColorType::Rgb8,
-            )?;
+            // JPEG has no alpha channel; grayscale is kept as single-channel
+            let (width, height) = img.dimensions();
+            let converted;
+            let (pixels, color): (&[u8], _) = match img {
+                DynamicImage::ImageRgb8(buf) => (buf.as_raw().as_slice(), ExtendedColorType::Rgb8),
+                DynamicImage::ImageLuma8(buf) => (buf.as_raw().as_slice(), ExtendedColorType::L8),
+                _ if is_grayscale(img.color()) => {
+                    converted = img.to_luma8().into_raw();
+                    (converted.as_slice(), ExtendedColorType::L8)
+                }
+                _ => {
+                    converted = img.to_rgb8().into_raw();
+                    (converted.as_slice(), ExtendedColorType::Rgb8)
+                }
+            };
+            write_jpeg(writer, pixels, width, height, color, options)?;
         }
+        #[cfg(feature = "parallel")]
+        ImageFormat::Png if options.thread_count() > 1 => crate::parallel::write_png(img, options, writer)?,
         ImageFormat::Png => {
-            let encoder = PngEncoder::new(writer);
-            encoder.write_image(
-                rgb_img.as_raw(),
-                width,
-                height,
-                ExtendedColorType::Rgb8,
-            )?;
+            let encoder = PngEncoder::new_with_quality(
+                writer,
+                options.png_compression.into(),
+                options.png_filter.into(),
+            );
+            // PNG supports every 8- and 16-bit layout; only float images need converting
+            match img.color() {
+                ColorType::Rgb32F => DynamicImage::ImageRgb16(img.to_rgb16()).write_with_encoder(encoder)?,
+                ColorType::Rgba32F => DynamicImage::ImageRgba16(img.to_rgba16()).write_with_encoder(encoder)?,
+                _ => img.write_with_encoder(encoder)?,
+            }
         }
         ImageFormat::WebP => {
-            // The image crate's WebP encoder doesn't expose quality settings directly
-            // We drop the writer (which flushes and closes the file) and then use
-            // the image crate's built-in save method
-            drop(writer);
-            img.save_with_format(path, image::ImageFormat::WebP)?;
+            // The image crate's WebP encoder doesn't expose quality settings directly.
+            // It only takes 8-bit data, so deeper images are narrowed keeping their channels.
+            match img.color() {
+                ColorType::L8 | ColorType::La8 | ColorType::Rgb8 | ColorType::Rgba8 => {
+                    img.write_with_encoder(WebPEncoder::new_lossless(writer))?
+                }
+                ColorType::L16 => DynamicImage::ImageLuma8(img.to_luma8())
+                    .write_with_encoder(WebPEncoder::new_lossless(writer))?,
+                ColorType::La16 => DynamicImage::ImageLumaA8(img.to_luma_alpha8())
+                    .write_with_encoder(WebPEncoder::new_lossless(writer))?,
+                color if color.has_alpha() => DynamicImage::ImageRgba8(img.to_rgba8())
+                    .write_with_encoder(WebPEncoder::new_lossless(writer))?,
+                _ => DynamicImage::ImageRgb8(img.to_rgb8())
+                    .write_with_encoder(WebPEncoder::new_lossless(writer))?,
+            }
         }
     }
 
     Ok(())
 }
 
-/// Encode a panorama image to bytes with specific format and quality settings.
+fn is_grayscale(color: ColorType) -> bool {
+    matches!(
+        color,
+        ColorType::L8 | ColorType::La8 | ColorType::L16 | ColorType::La16
+    )
+}
+
+/// Encode 8-bit RGB or grayscale pixels as JPEG.
 ///
-/// Returns the encoded image as a `Vec<u8>`.
-pub fn encode_panorama(img: &DynamicImage, options: &SaveOptions) -> Result<Vec<u8>> {
-    let mut buffer = Cursor::new(Vec::new());
+/// The image crate's encoder is used by default. Progressive output and
+/// explicit chroma subsampling need the `jpeg-encoder` crate.
+fn write_jpeg<W: Write>(
+    writer: W,
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    color: ExtendedColorType,
+    options: &SaveOptions,
+) -> Result<()> {
+    if !options.jpeg_progressive && options.jpeg_subsampling.is_none() {
+        let mut encoder = JpegEncoder::new_with_quality(writer, options.jpeg_quality);
+        encoder.encode(pixels, width, height, color)?;
+        return Ok(());
+    }
 
-    // Convert image to RGB8 for encoding
-    let rgb_img = img.to_rgb8();
-    let (width, height) = rgb_img.dimensions();
+    let (width, height) = match (u16::try_from(width), u16::try_from(height)) {
+        (Ok(w), Ok(h)) => (w, h),
+        _ => {
+            return Err(StreetViewError::EncodeError(format!(
+                "{width}x{height} exceeds the JPEG size limit of 65535 pixels"
+            )))
+        }
+    };
 
-    match options.format {
-        ImageFormat::Jpeg => {
-            let mut encoder = JpegEncoder::new_with_quality(&mut buffer, options.jpeg_quality);
-            encoder.encode(
-                rgb_img.as_raw(),
-                width,
-                height,
-                ExtendedColorType::Rgb8,
-            )?;
+    let mut encoder = jpeg_encoder::Encoder::new(writer, options.jpeg_quality);
+    encoder.set_progressive(options.jpeg_progressive);
+    if let Some(subsampling) = options.jpeg_subsampling {
+        encoder.set_sampling_factor(match subsampling {
+            ChromaSubsampling::Yuv444 => jpeg_encoder::SamplingFactor::R_4_4_4,
+            ChromaSubsampling::Yuv422 => jpeg_encoder::SamplingFactor::R_4_2_2,
+            ChromaSubsampling::Yuv420 => jpeg_encoder::SamplingFactor::R_4_2_0,
+        });
+    }
+
+    let color_type = if color == ExtendedColorType::L8 {
+        jpeg_encoder::ColorType::Luma
+    } else {
+        jpeg_encoder::ColorType::Rgb
+    };
+    encoder
+        .encode(pixels, width, height, color_type)
+        .map_err(|e| StreetViewError::EncodeError(e.to_string()))
+}
+
+/// Save a panorama image with embedded EXIF/XMP metadata.
+///
+/// Embeds the GPS position, capture date, and GPano XMP tags
+/// (`ProjectionType=equirectangular`, full pano and cropped area sizes) so
+/// 360° viewers such as Google Photos and Facebook recognize the file as a
+/// panorama. Embedding can be turned off with `SaveOptions::embed_metadata(false)`.
+///
+/// With `SaveOptions::sidecar(true)`, a `name.json` file with the panorama
+/// and download parameters is also written next to the image.
+pub fn save_with_metadata(
+    img: &DynamicImage,
+    pano: &Panorama,
+    path: impl AsRef<Path>,
+    options: &SaveOptions,
+) -> Result<()> {
+    let path = path.as_ref();
+
+    // Create parent directories if they don't exist
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let file = File::create(path)?;
+    let mut writer = BufWriter::new(file);
+    encode_with_metadata_to_writer(img, pano, options, &mut writer)?;
+    writer.flush()?;
+
+    if options.sidecar {
+        SidecarMetadata::new(pano, img, options.format).write(sidecar_path(path))?;
+    }
+
+    Ok(())
+}
+
+/// Save an image plus downscaled thumbnails in one pass.
+///
+/// Each thumbnail is written next to the image as `name_{width}.ext` and
+/// keeps the aspect ratio. Thumbnails are resized from the next larger one
+/// rather than from the full image, so the full-resolution pixels are only
+/// read once. Widths that are zero or not smaller than the image are skipped.
+///
+/// Returns the paths of the thumbnails, largest first.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rsstreetview::{save_with_thumbnails, ImageFormat, SaveOptions};
+/// # fn run(image: &image::DynamicImage) -> rsstreetview::Result<()> {
+/// // Writes pano.jpg, pano_1024.jpg and pano_256.jpg
+/// let options = SaveOptions::new().format(ImageFormat::Jpeg);
+/// let thumbs = save_with_thumbnails(image, "pano.jpg", &[1024, 256], &options)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn save_with_thumbnails(
+    img: &DynamicImage,
+    path: impl AsRef<Path>,
+    widths: &[u32],
+    options: &SaveOptions,
+) -> Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    save_panorama(img, path, options)?;
+
+    let (width, height) = img.dimensions();
+    let mut widths: Vec<u32> = widths.iter().copied().filter(|&w| w > 0 && w < width).collect();
+    widths.sort_unstable_by(|a, b| b.cmp(a));
+    widths.dedup();
+
+    let mut source = Cow::Borrowed(img);
+    let mut paths = Vec::with_capacity(widths.len());
+    for thumb_width in widths {
+        let thumb_height = (height as u64 * thumb_width as u64 / width as u64).max(1) as u32;
+        let thumb = source.resize_exact(thumb_width, thumb_height, FilterType::Triangle);
+
+        let thumb_path = thumbnail_path(path, thumb_width);
+        save_panorama(&thumb, &thumb_path, options)?;
+        paths.push(thumb_path);
+        source = Cow::Owned(thumb);
+    }
+
+    Ok(paths)
+}
+
+/// Path of a thumbnail for an image: `name.jpg` -> `name_256.jpg`.
+pub fn thumbnail_path(image_path: impl AsRef<Path>, width: u32) -> PathBuf {
+    let image_path = image_path.as_ref();
+    let stem = image_path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match image_path.extension() {
+        Some(ext) => format!("{stem}_{width}.{}", ext.to_string_lossy()),
+        None => format!("{stem}_{width}"),
+    };
+    image_path.with_file_name(name)
+}
+
+/// Encode a panorama image with embedded EXIF/XMP metadata into any `Write` sink.
+pub fn encode_with_metadata_to_writer<W: Write>(
+    img: &DynamicImage,
+    pano: &Panorama,
+    options: &SaveOptions,
+    writer: W,
+) -> Result<()> {
+    if !options.embed_metadata {
+        return encode_to_writer(img, options, writer);
+    }
+
+    let (width, height) = img.dimensions();
+    let metadata = EmbeddedMetadata::new(pano, width, height);
+    let has_alpha = options.format == ImageFormat::WebP && img.color().has_alpha();
+
+    let mut splice = metadata.writer(options.format, width, height, has_alpha, writer);
+    encode_to_writer(img, options, &mut splice)?;
+    splice.finish()?;
+    Ok(())
+}
+
+/// Encode a panorama image into any `AsyncWrite` sink.
+///
+/// Encoding runs on tokio's blocking thread pool and the output is handed to
+/// `writer` in chunks as it is produced, with backpressure, so large
+/// panoramas can be streamed to network uploads (e.g. S3/GCS multipart)
+/// without buffering the whole encoded file.
+pub async fn encode_to_async_writer<W>(
+    img: DynamicImage,
+    options: &SaveOptions,
+    writer: &mut W,
+) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let (tx, mut rx) = mpsc::channel::<Vec<u8>>(ASYNC_CHUNK_QUEUE);
+    let options = options.clone();
+
+    let encoder = tokio::task::spawn_blocking(move || {
+        let mut sink = ChannelWriter::new(tx);
+        encode_to_writer(&img, &options, &mut sink)?;
+        sink.flush()?;
+        Ok::<(), StreetViewError>(())
+    });
+
+    let mut write_result = Ok(());
+    while let Some(chunk) = rx.recv().await {
+        if let Err(e) = writer.write_all(&chunk).await {
+            write_result = Err(e);
+            break;
         }
-        ImageFormat::Png => {
-            let encoder = PngEncoder::new(&mut buffer);
-            encoder.write_image(
-                rgb_img.as_raw(),
-                width,
-                height,
-                ExtendedColorType::Rgb8,
-            )?;
+    }
+    // Closing the receiver unblocks the encoder if the writer failed early
+    drop(rx);
+
+    let encode_result = encoder
+        .await
+        .map_err(|e| StreetViewError::IoError(std::io::Error::other(e)))?;
+    write_result?;
+    encode_result?;
+
+    writer.flush().await?;
+    Ok(())
+}
+
+/// `Write` adapter that forwards fixed-size chunks over a bounded channel.
+struct ChannelWriter {
+    tx: mpsc::Sender<Vec<u8>>,
+    buf: Vec<u8>,
+}
+
+impl ChannelWriter {
+    fn new(tx: mpsc::Sender<Vec<u8>>) -> Self {
+        Self {
+            tx,
+            buf: Vec::with_capacity(ASYNC_CHUNK_SIZE),
         }
-        ImageFormat::WebP => {
-            // For WebP, we need to use the image crate's built-in save
-            img.write_to(&mut buffer, image::ImageFormat::WebP)?;
+    }
+
+    fn send_buffered(&mut self) -> std::io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let chunk = std::mem::replace(&mut self.buf, Vec::with_capacity(ASYNC_CHUNK_SIZE));
+        self.tx
+            .blocking_send(chunk)
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "writer closed"))
+    }
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        if self.buf.len() >= ASYNC_CHUNK_SIZE {
+            self.send_buffered()?;
         }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.send_buffered()
+    }
+}
+
+/// Save a panorama image without blocking the async runtime.
+///
+/// Encoding runs on tokio's blocking thread pool and the result is written
+/// with `tokio::fs`. Takes the image by value because the encoder thread
+/// needs to own it.
+pub async fn save_panorama_async(
+    img: DynamicImage,
+    path: impl AsRef<Path>,
+    options: &SaveOptions,
+) -> Result<()> {
+    let path = path.as_ref();
+
+    // Create parent directories if they don't exist
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
     }
 
-    Ok(buffer.into_inner())
+    let file = tokio::fs::File::create(path).await?;
+    let mut writer = tokio::io::BufWriter::new(file);
+    encode_to_async_writer(img, options, &mut writer).await
+}
+
+/// Encode a panorama image to bytes without blocking the async runtime.
+///
+/// Encoding runs on tokio's blocking thread pool.
+pub async fn encode_panorama_async(img: DynamicImage, options: &SaveOptions) -> Result<Vec<u8>> {
+    let options = options.clone();
+    tokio::task::spawn_blocking(move || encode_panorama(&img, &options))
+        .await
+        .map_err(|e| StreetViewError::IoError(std::io::Error::other(e)))?
 }
 
 /// Extension trait for DynamicImage to add convenient save methods.
@@ -216,53 +538,133 @@ pub trait PanoramaSaveExt {
     /// # }
     /// ```
     fn to_png_bytes(&self) -> Result<Vec<u8>>;
+
+    /// Save image as WebP without blocking the async runtime.
+    ///
+    /// The async methods encode on tokio's blocking thread pool, like
+    /// [`save_panorama_async`], so they take the image by value.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rsstreetview::{StreetView, PanoramaSaveExt};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let client = StreetView::new();
+    /// # let panos = client.search_panoramas(41.8982208, 12.4764804).await?;
+    /// let image = client.download_panorama(&panos[0].pano_id, 5).await?;
+    /// image.save_webp_async("panorama.webp").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn save_webp_async(self, path: impl AsRef<Path> + Send) -> impl Future<Output = Result<()>> + Send;
+
+    /// Save image as JPEG with specified quality (1-100) without blocking
+    /// the async runtime.
+    fn save_jpeg_async(self, path: impl AsRef<Path> + Send, quality: u8) -> impl Future<Output = Result<()>> + Send;
+
+    /// Save image as PNG without blocking the async runtime.
+    fn save_png_async(self, path: impl AsRef<Path> + Send) -> impl Future<Output = Result<()>> + Send;
+
+    /// Encode image as WebP and return bytes, without blocking the async
+    /// runtime.
+    fn to_webp_bytes_async(self, effort: u8, quality: u8) -> impl Future<Output = Result<Vec<u8>>> + Send;
+
+    /// Encode image as JPEG and return bytes, without blocking the async
+    /// runtime.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rsstreetview::{StreetView, PanoramaSaveExt};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let client = StreetView::new();
+    /// # let panos = client.search_panoramas(41.8982208, 12.4764804).await?;
+    /// let image = client.download_panorama(&panos[0].pano_id, 3).await?;
+    /// let bytes = image.to_jpeg_bytes_async(90).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn to_jpeg_bytes_async(self, quality: u8) -> impl Future<Output = Result<Vec<u8>>> + Send;
+
+    /// Encode image as PNG and return bytes, without blocking the async
+    /// runtime.
+    fn to_png_bytes_async(self) -> impl Future<Output = Result<Vec<u8>>> + Send;
 }
 
 impl PanoramaSaveExt for DynamicImage {
     fn save_webp(&self, path: impl AsRef<Path>) -> Result<()> {
-        let options = SaveOptions::new()
-            .format(ImageFormat::WebP)
-            .webp_quality(85)
-            .webp_method(4);
-        save_panorama(self, path, &options)
+        save_panorama(self, path, &webp_options(4, 85))
     }
 
     fn save_jpeg(&self, path: impl AsRef<Path>, quality: u8) -> Result<()> {
-        let options = SaveOptions::new()
-            .format(ImageFormat::Jpeg)
-            .jpeg_quality(quality);
-        save_panorama(self, path, &options)
+        save_panorama(self, path, &jpeg_options(quality))
     }
 
     fn save_png(&self, path: impl AsRef<Path>) -> Result<()> {
-        let options = SaveOptions::new().format(ImageFormat::Png);
-        save_panorama(self, path, &options)
+        save_panorama(self, path, &png_options())
     }
 
     fn to_webp_bytes(&self, effort: u8, quality: u8) -> Result<Vec<u8>> {
-        let options = SaveOptions::new()
-            .format(ImageFormat::WebP)
-            .webp_quality(quality)
-            .webp_method(effort);
-        encode_panorama(self, &options)
+        encode_panorama(self, &webp_options(effort, quality))
     }
 
     fn to_jpeg_bytes(&self, quality: u8) -> Result<Vec<u8>> {
-        let options = SaveOptions::new()
-            .format(ImageFormat::Jpeg)
-            .jpeg_quality(quality);
-        encode_panorama(self, &options)
+        encode_panorama(self, &jpeg_options(quality))
     }
 
     fn to_png_bytes(&self) -> Result<Vec<u8>> {
-        let options = SaveOptions::new().format(ImageFormat::Png);
-        encode_panorama(self, &options)
+        encode_panorama(self, &png_options())
     }
+
+    async fn save_webp_async(self, path: impl AsRef<Path> + Send) -> Result<()> {
+        save_panorama_async(self, path, &webp_options(4, 85)).await
+    }
+
+    async fn save_jpeg_async(self, path: impl AsRef<Path> + Send, quality: u8) -> Result<()> {
+        save_panorama_async(self, path, &jpeg_options(quality)).await
+    }
+
+    async fn save_png_async(self, path: impl AsRef<Path> + Send) -> Result<()> {
+        save_panorama_async(self, path, &png_options()).await
+    }
+
+    async fn to_webp_bytes_async(self, effort: u8, quality: u8) -> Result<Vec<u8>> {
+        encode_panorama_async(self, &webp_options(effort, quality)).await
+    }
+
+    async fn to_jpeg_bytes_async(self, quality: u8) -> Result<Vec<u8>> {
+        encode_panorama_async(self, &jpeg_options(quality)).await
+    }
+
+    async fn to_png_bytes_async(self) -> Result<Vec<u8>> {
+        encode_panorama_async(self, &png_options()).await
+    }
+}
+
+fn webp_options(effort: u8, quality: u8) -> SaveOptions {
+    SaveOptions::new()
+        .format(ImageFormat::WebP)
+        .webp_quality(quality)
+        .webp_method(effort)
+}
+
+fn jpeg_options(quality: u8) -> SaveOptions {
+    SaveOptions::new()
+        .format(ImageFormat::Jpeg)
+        .jpeg_quality(quality)
+}
+
+fn png_options() -> SaveOptions {
+    SaveOptions::new().format(ImageFormat::Png)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::fixtures::panorama;
+    use crate::types::{CaptureDate, DateConfidence};
     use image::RgbImage;
 
     #[test]
@@ -293,4 +695,120 @@ mod tests {
         std::fs::remove_file(jpeg_path).ok();
         std::fs::remove_file(png_path).ok();
     }
+
+    #[test]
+    fn test_image_format_strings() {
+        assert_eq!("JPG".parse::<ImageFormat>().unwrap(), ImageFormat::Jpeg);
+        assert_eq!(".webp".parse::<ImageFormat>().unwrap(), ImageFormat::WebP);
+        assert!("gif".parse::<ImageFormat>().is_err());
+        for format in [ImageFormat::Jpeg, ImageFormat::Png, ImageFormat::WebP] {
+            assert_eq!(format.to_string().parse::<ImageFormat>().unwrap(), format);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_save_async() {
+        let img = DynamicImage::ImageRgb8(RgbImage::new(64, 32));
+        let path = std::env::temp_dir().join("rsstreetview_async").join("test.png");
+
+        let options = SaveOptions::new().format(ImageFormat::Png);
+        save_panorama_async(img.clone(), &path, &options).await.unwrap();
+        assert!(path.exists());
+
+        let bytes = encode_panorama_async(img.clone(), &options).await.unwrap();
+        assert_eq!(bytes, std::fs::read(&path).unwrap());
+
+        img.clone().save_png_async(&path).await.unwrap();
+        assert_eq!(img.to_png_bytes_async().await.unwrap(), bytes);
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_encode_with_metadata() {
+        let img = DynamicImage::ImageRgb8(RgbImage::new(64, 32));
+        let pano = Panorama {
+            heading: 90.0,
+            date: CaptureDate::new(2021, 4),
+            date_confidence: DateConfidence::Exact,
+            ..panorama("test_pano", 41.9, 12.5)
+        };
+
+        for format in [ImageFormat::Jpeg, ImageFormat::Png, ImageFormat::WebP] {
+            let options = SaveOptions::new().format(format);
+            let mut bytes = Vec::new();
+            encode_with_metadata_to_writer(&img, &pano, &options, &mut bytes).unwrap();
+
+            // Still decodes, and carries the GPano packet
+            let decoded = image::load_from_memory(&bytes).unwrap();
+            assert_eq!(decoded.dimensions(), (64, 32));
+            assert!(bytes.windows(15).any(|w| w == b"equirectangular"));
+        }
+    }
+
+    #[test]
+    fn test_save_with_thumbnails() {
+        let img = DynamicImage::ImageRgb8(image::RgbImage::new(1000, 500));
+        let path = std::env::temp_dir().join("rsstreetview_thumbs").join("pano.png");
+        let options = SaveOptions::new().format(ImageFormat::Png);
+
+        let thumbs = save_with_thumbnails(&img, &path, &[256, 600, 256, 2000], &options).unwrap();
+        assert_eq!(thumbs, [thumbnail_path(&path, 600), thumbnail_path(&path, 256)]);
+        assert!(thumbs[1].ends_with("pano_256.png"));
+        assert_eq!(image::image_dimensions(&thumbs[0]).unwrap(), (600, 300));
+        assert_eq!(image::image_dimensions(&thumbs[1]).unwrap(), (256, 128));
+
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+
+    #[test]
+    fn test_color_passthrough() {
+        let gray = DynamicImage::ImageLuma8(image::GrayImage::new(32, 16));
+        let rgba = DynamicImage::ImageRgba8(image::RgbaImage::new(32, 16));
+
+        let jpeg = encode_panorama(&gray, &SaveOptions::new().format(ImageFormat::Jpeg)).unwrap();
+        assert_eq!(image::load_from_memory(&jpeg).unwrap().color(), ColorType::L8);
+
+        let png = encode_panorama(&rgba, &SaveOptions::new().format(ImageFormat::Png)).unwrap();
+        assert_eq!(image::load_from_memory(&png).unwrap().color(), ColorType::Rgba8);
+
+        let webp = encode_panorama(&rgba, &SaveOptions::new().format(ImageFormat::WebP)).unwrap();
+        assert!(image::load_from_memory(&webp).unwrap().color().has_alpha());
+    }
+
+    #[test]
+    fn test_png_compression_options() {
+        let img = DynamicImage::ImageRgb8(RgbImage::from_fn(128, 64, |x, y| image::Rgb([x as u8, y as u8, 0])));
+
+        let fast = encode_panorama(&img, &SaveOptions::new().format(ImageFormat::Png).png_fast()).unwrap();
+        let small = encode_panorama(&img, &SaveOptions::new().format(ImageFormat::Png).png_smallest()).unwrap();
+
+        assert_eq!(image::load_from_memory(&fast).unwrap().to_rgb8(), img.to_rgb8());
+        assert_eq!(image::load_from_memory(&small).unwrap().to_rgb8(), img.to_rgb8());
+        assert!(small.len() <= fast.len());
+    }
+
+    #[test]
+    fn test_progressive_jpeg() {
+        let img = DynamicImage::ImageRgb8(RgbImage::new(64, 64));
+        let options = SaveOptions::new()
+            .format(ImageFormat::Jpeg)
+            .jpeg_progressive(true)
+            .jpeg_subsampling(ChromaSubsampling::Yuv420);
+        let bytes = encode_panorama(&img, &options).unwrap();
+
+        // SOF2 marks a progressive frame
+        assert!(bytes.windows(2).any(|w| w == [0xFF, 0xC2]));
+        assert_eq!(image::load_from_memory(&bytes).unwrap().dimensions(), (64, 64));
+    }
+
+    #[tokio::test]
+    async fn test_encode_to_async_writer() {
+        let img = DynamicImage::ImageRgb8(RgbImage::new(300, 200));
+        let options = SaveOptions::new().format(ImageFormat::Png);
+
+        let mut sink = Vec::new();
+        encode_to_async_writer(img.clone(), &options, &mut sink).await.unwrap();
+        assert_eq!(sink, encode_panorama(&img, &options).unwrap());
+    }
 }
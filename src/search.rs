@@ -1,138 +1,370 @@
+//! Panorama search through Google's undocumented `SingleImageSearch`
+//! endpoint, and parsers for its responses.
+
+use crate::cache::Lru;
 use crate::error::{Result, StreetViewError};
-use crate::types::Panorama;
+use crate::types::{CaptureDate, DateConfidence, Locale, Panorama};
 use regex::Regex;
-use reqwest::Client;
+use crate::http::Http;
+use futures::stream::{self, BoxStream, StreamExt};
+use serde::de::{self, Deserialize, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use std::collections::HashSet;
+use std::fmt;
+use std::marker::PhantomData;
+use serde_json::value::RawValue;
 use serde_json::Value;
 
-const SEARCH_ENDPOINT: &str = "https://maps.googleapis.com/maps/api/js/GeoPhotoService.SingleImageSearch";
 
 /// Build the search URL for a given GPS coordinate.
-fn make_search_url(lat: f64, lon: f64) -> String {
+fn make_search_url(endpoint: &str, lat: f64, lon: f64, locale: &Locale) -> String {
+    let Locale { language, region } = locale;
     // This constructs the undocumented Google endpoint URL
     format!(
-        "{SEARCH_ENDPOINT}?pb=!1m5!1sapiv3!5sUS!11m2!1m1!1b0!2m4!1m2!3d{lat}!4d{lon}!2d50!3m18!2m2!1sen!2sUS!9m1!1e2!11m12!1m3!1e2!2b1!3e2!1m3!1e3!2b1!3e2!1m3!1e10!2b1!3e2!4m6!1e1!1e2!1e3!1e4!1e8!1e6&callback=callbackfunc"
+        "{endpoint}?pb=!1m5!1sapiv3!5s{region}!11m2!1m1!1b0!2m4!1m2!3d{lat}!4d{lon}!2d50!3m18!2m2!1s{language}!2s{region}!9m1!1e2!11m12!1m3!1e2!2b1!3e2!1m3!1e3!2b1!3e2!1m3!1e10!2b1!3e2!4m6!1e1!1e2!1e3!1e4!1e8!1e6&callback=callbackfunc"
     )
 }
 
-/// Extract panoramas from Google's JavaScript callback response.
-fn extract_panoramas(text: &str) -> Result<Vec<Panorama>> {
+/// Locate the JSON payload inside `callbackfunc(...)`.
+fn callback_payload(text: &str) -> Option<&str> {
+    let start = text.find("callbackfunc(")? + "callbackfunc(".len();
+    let end = text.rfind(')')?;
+    (end > start).then(|| &text[start..end])
+}
+
+/// Visitor methods for every JSON type but arrays, reading them as
+/// `Self::Value::default()`, so a block that is missing or has moved reads
+/// as empty instead of failing the whole response.
+macro_rules! visit_non_arrays {
+    () => {
+        fn visit_bool<E: de::Error>(self, _: bool) -> std::result::Result<Self::Value, E> {
+            Ok(Default::default())
+        }
+
+        fn visit_i64<E: de::Error>(self, _: i64) -> std::result::Result<Self::Value, E> {
+            Ok(Default::default())
+        }
+
+        fn visit_u64<E: de::Error>(self, _: u64) -> std::result::Result<Self::Value, E> {
+            Ok(Default::default())
+        }
+
+        fn visit_f64<E: de::Error>(self, _: f64) -> std::result::Result<Self::Value, E> {
+            Ok(Default::default())
+        }
+
+        fn visit_str<E: de::Error>(self, _: &str) -> std::result::Result<Self::Value, E> {
+            Ok(Default::default())
+        }
+
+        fn visit_unit<E: de::Error>(self) -> std::result::Result<Self::Value, E> {
+            Ok(Default::default())
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<Self::Value, A::Error> {
+            while map.next_entry::<IgnoredAny, IgnoredAny>()?.is_some() {}
+            Ok(Default::default())
+        }
+    };
+}
+
+/// Element `I` of a JSON array, read in the same pass that skips its
+/// siblings. `None` if the value is not an array or is too short.
+#[derive(Debug)]
+struct At<const I: usize, T>(Option<T>);
+
+impl<const I: usize, T> At<I, T> {
+    fn into_inner(self) -> Option<T> {
+        self.0
+    }
+}
+
+impl<const I: usize, T> Default for At<I, T> {
+    fn default() -> Self {
+        Self(None)
+    }
+}
+
+impl<'de, const I: usize, T: Deserialize<'de>> Deserialize<'de> for At<I, T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct AtVisitor<const I: usize, T>(PhantomData<T>);
+
+        impl<'de, const I: usize, T: Deserialize<'de>> Visitor<'de> for AtVisitor<I, T> {
+            type Value = At<I, T>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("any JSON value")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<Self::Value, A::Error> {
+                for _ in 0..I {
+                    if seq.next_element::<IgnoredAny>()?.is_none() {
+                        return Ok(At(None));
+                    }
+                }
+                let value = seq.next_element()?;
+                while seq.next_element::<IgnoredAny>()?.is_some() {}
+                Ok(At(value))
+            }
+
+            visit_non_arrays!();
+        }
+
+        deserializer.deserialize_any(AtVisitor(PhantomData))
+    }
+}
+
+/// The elements of a JSON array as slices of the text, or `None` if the
+/// value is not an array.
+#[derive(Debug, Default)]
+struct Items<'a>(Option<Vec<&'a RawValue>>);
+
+impl<'de: 'a, 'a> Deserialize<'de> for Items<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct ItemsVisitor<'a>(PhantomData<&'a ()>);
+
+        impl<'de: 'a, 'a> Visitor<'de> for ItemsVisitor<'a> {
+            type Value = Items<'a>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("any JSON value")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<Self::Value, A::Error> {
+                let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(item) = seq.next_element()? {
+                    items.push(item);
+                }
+                Ok(Items(Some(items)))
+            }
+
+            visit_non_arrays!();
+        }
+
+        deserializer.deserialize_any(ItemsVisitor(PhantomData))
+    }
+}
+
+/// `data[1]` of a search response, read in one pass. The copyright notice
+/// at `[4][0][0][0][0]` and the street label, the first address line at
+/// `[3][2][0][0]`, belong to the panorama the search resolved to, whose ID
+/// is at `[1][1]`. The search result is at `[5][0]`.
+#[derive(Debug, Default)]
+struct SearchData<'a> {
+    main_pano: At<1, &'a RawValue>,
+    street_name: At<2, At<0, At<0, &'a RawValue>>>,
+    copyright: At<0, At<0, At<0, At<0, &'a RawValue>>>>,
+    result: At<0, SearchResult<'a>>,
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for SearchData<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct DataVisitor<'a>(PhantomData<&'a ()>);
+
+        impl<'de: 'a, 'a> Visitor<'de> for DataVisitor<'a> {
+            type Value = SearchData<'a>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("any JSON value")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<Self::Value, A::Error> {
+                seq.next_element::<IgnoredAny>()?;
+                let main_pano = seq.next_element()?.unwrap_or_default();
+                seq.next_element::<IgnoredAny>()?;
+                let street_name = seq.next_element()?.unwrap_or_default();
+                let copyright = seq.next_element()?.unwrap_or_default();
+                let result = seq.next_element()?.unwrap_or_default();
+                while seq.next_element::<IgnoredAny>()?.is_some() {}
+                Ok(SearchData { main_pano, street_name, copyright, result })
+            }
+
+            visit_non_arrays!();
+        }
+
+        deserializer.deserialize_any(DataVisitor(PhantomData))
+    }
+}
+
+/// `data[1][5][0]`: the panorama entries at `[3][0]`, kept as slices so a
+/// malformed entry can be reported by position, and their dates at `[8]`.
+#[derive(Debug, Default)]
+struct SearchResult<'a> {
+    entries: Option<Vec<&'a RawValue>>,
+    dates: Vec<Value>,
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for SearchResult<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct ResultVisitor<'a>(PhantomData<&'a ()>);
+
+        impl<'de: 'a, 'a> Visitor<'de> for ResultVisitor<'a> {
+            type Value = SearchResult<'a>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("any JSON value")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<Self::Value, A::Error> {
+                for _ in 0..3 {
+                    seq.next_element::<IgnoredAny>()?;
+                }
+                let entries = seq.next_element::<At<0, Items<'a>>>()?.and_then(At::into_inner).and_then(|items| items.0);
+                for _ in 4..8 {
+                    seq.next_element::<IgnoredAny>()?;
+                }
+                let dates = match seq.next_element()? {
+                    Some(Value::Array(dates)) => dates,
+                    _ => Vec::new(),
+                };
+                while seq.next_element::<IgnoredAny>()?.is_some() {}
+                Ok(SearchResult { entries, dates })
+            }
+
+            visit_non_arrays!();
+        }
+
+        deserializer.deserialize_any(ResultVisitor(PhantomData))
+    }
+}
+
+/// A panorama entry of a search response:
+/// `[[_, id], _, [[_, _, lat, lon], [altitude], [heading, pitch, roll]], [elevation], ...]`.
+///
+/// Read in one pass over the entry's text: the ID stays a slice of the
+/// response, and the elements after the elevation are skipped unparsed.
+#[derive(Debug)]
+struct PanoEntry<'a> {
+    key: Vec<&'a RawValue>,
+    location: Vec<Option<Vec<Option<f64>>>>,
+    elevation: Option<Vec<Option<f64>>>,
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for PanoEntry<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct EntryVisitor<'a>(PhantomData<&'a ()>);
+
+        impl<'de: 'a, 'a> Visitor<'de> for EntryVisitor<'a> {
+            type Value = PanoEntry<'a>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a panorama entry")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<Self::Value, A::Error> {
+                let key = seq.next_element::<Option<Vec<&'a RawValue>>>()?.flatten().unwrap_or_default();
+                seq.next_element::<IgnoredAny>()?;
+                let location = seq.next_element::<Option<Vec<_>>>()?.flatten().unwrap_or_default();
+                let elevation = seq.next_element::<Option<Vec<_>>>()?.flatten();
+                while seq.next_element::<IgnoredAny>()?.is_some() {}
+                Ok(PanoEntry { key, location, elevation })
+            }
+        }
+
+        deserializer.deserialize_seq(EntryVisitor(PhantomData))
+    }
+}
+
+/// Parse a search response: Google's JavaScript callback wrapping the
+/// panoramas found.
+///
+/// This is what [`StreetView::search_panoramas`](crate::StreetView::search_panoramas)
+/// runs on every response, exposed for parsing saved responses offline
+/// (such as those written by
+/// [`capture_raw_responses`](crate::StreetViewBuilder::capture_raw_responses)).
+///
+/// # Example
+///
+/// ```
+/// let panos = rsstreetview::search::parse_response("callbackfunc([[5,\"Search returned no images.\"]])")?;
+/// assert!(panos.is_empty());
+/// # Ok::<(), rsstreetview::StreetViewError>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns `StreetViewError::EndpointFormatChanged` if the response has no
+/// panoramas where they are expected, and `StreetViewError::ParseError` if
+/// it is not valid JSON.
+pub fn parse_response(text: &str) -> Result<Vec<Panorama>> {
     // Check if the search returned no images
     if text.contains("Search returned no images") {
         return Ok(Vec::new());
     }
 
+    let changed = |path: String| StreetViewError::EndpointFormatChanged {
+        path,
+        body: text.to_string(),
+    };
+
     // Extract JSON from the JavaScript callback: callbackfunc(JSON_DATA)
-    let re = Regex::new(r"callbackfunc\((.*)\)").unwrap();
-    let json_str = re
-        .captures(text)
-        .and_then(|cap| cap.get(1))
-        .ok_or_else(|| StreetViewError::ParseError("Could not extract JSON from response".to_string()))?
-        .as_str();
+    let json_str = callback_payload(text).ok_or_else(|| changed("callbackfunc(...)".to_string()))?;
 
-    let data: Value = serde_json::from_str(json_str)
+    // One pass over the response reads data[1], skipping every block it
+    // doesn't need without building a `Value` tree
+    let root: At<1, SearchData> = serde_json::from_str(json_str)
         .map_err(|e| StreetViewError::ParseError(format!("JSON parse error: {e}")))?;
+    let data = root.into_inner().unwrap_or_default();
+    let string = |raw: Option<&RawValue>| raw.and_then(|raw| serde_json::from_str::<String>(raw.get()).ok());
+    let main_pano = string(data.main_pano.into_inner());
+    let street_name = string(data.street_name.into_inner().and_then(At::into_inner).and_then(At::into_inner));
+    let copyright = string(
+        data.copyright
+            .into_inner()
+            .and_then(At::into_inner)
+            .and_then(At::into_inner)
+            .and_then(At::into_inner),
+    );
 
     // Navigate to the panorama data: data[1][5][0][3][0]
-    let pano_array = data
-        .get(1)
-        .and_then(|v| v.get(5))
-        .and_then(|v| v.get(0))
-        .and_then(|v| v.get(3))
-        .and_then(|v| v.get(0))
-        .and_then(|v| v.as_array())
-        .ok_or_else(|| StreetViewError::InvalidResponse("Panorama data not found".to_string()))?;
-
-    // Get dates: data[1][5][0][8]
-    // Each date is structured as: [[something], [year, month]]
-    // Format as "YYYY-MM" like Python does
-    let dates = data
-        .get(1)
-        .and_then(|v| v.get(5))
-        .and_then(|v| v.get(0))
-        .and_then(|v| v.get(8))
-        .and_then(|v| v.as_array())
-        .map(|arr| {
-            // Dates need to be reversed to align with panoramas
-            let mut dates: Vec<Option<String>> = arr
-                .iter()
-                .filter_map(|d| {
-                    // Each date is an array like [[...], [year, month]]
-                    let date_arr = d.as_array()?;
-                    let date_info = date_arr.get(1)?.as_array()?;
-                    let year = date_info.first()?.as_i64()?;
-                    let month = date_info.get(1)?.as_i64()?;
-                    Some(format!("{year}-{month:02}"))
-                })
-                .map(Some)
-                .collect();
-            dates.reverse();
-            dates
-        })
-        .unwrap_or_default();
+    let SearchResult { entries, dates: date_entries } = data.result.into_inner().unwrap_or_default();
+    let entries = entries.ok_or_else(|| changed("[1][5][0][3][0]".to_string()))?;
+
+    // Dates are at data[1][5][0][8]
+    let count = entries.len();
+    let dates = align_dates(&date_entries, count);
+
+    let mut panoramas = Vec::with_capacity(count);
 
     // Reverse panoramas to match Python behavior
     // Google returns them in reverse chronological order for some locations,
     // so we flip to make the 0th panorama align with 0th date
-    let pano_array: Vec<&Value> = pano_array.iter().rev().collect();
-
-    let mut panoramas = Vec::new();
-
-    for (idx, pano_data) in pano_array.iter().enumerate() {
-        let pano_arr = pano_data
-            .as_array()
-            .ok_or_else(|| StreetViewError::ParseError("Invalid panorama format".to_string()))?;
-
-        // Extract fields from the array
-        let pano_id = pano_arr.first()
-            .and_then(|v| v.get(1))
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| StreetViewError::ParseError("Missing pano_id".to_string()))?
-            .to_string();
-
-        // GPS coordinates are in pano_arr[2][0]
-        let coords = pano_arr
-            .get(2)
-            .and_then(|v| v.get(0))
-            .and_then(|v| v.as_array())
-            .ok_or_else(|| StreetViewError::ParseError("Missing coordinates".to_string()))?;
-
-        let lat = coords
-            .get(2)
-            .and_then(|v| v.as_f64())
-            .ok_or_else(|| StreetViewError::ParseError("Missing latitude".to_string()))?;
-
-        let lon = coords
-            .get(3)
-            .and_then(|v| v.as_f64())
-            .ok_or_else(|| StreetViewError::ParseError("Missing longitude".to_string()))?;
-
-        // Camera orientation is in pano_arr[2][2] (not pano_arr[2][0]!)
-        let orientation = pano_arr
-            .get(2)
-            .and_then(|v| v.get(2))
-            .and_then(|v| v.as_array());
-
-        let heading = orientation
-            .and_then(|arr| arr.first())
-            .and_then(|v| v.as_f64())
-            .unwrap_or(0.0);
-
-        let pitch = orientation
-            .and_then(|arr| arr.get(1))
-            .and_then(|v| v.as_f64());
-
-        let roll = orientation
-            .and_then(|arr| arr.get(2))
-            .and_then(|v| v.as_f64());
-
-        // Elevation is in pano_arr[3][0]
-        let elevation = pano_arr
-            .get(3)
-            .and_then(|v| v.get(0))
-            .and_then(|v| v.as_f64());
-
-        // Get date for this panorama
-        let date = dates.get(idx).and_then(|d| d.clone());
+    for (idx, raw) in entries.iter().rev().enumerate() {
+        let at = |suffix: &str| changed(format!("[1][5][0][3][0][{}]{suffix}", count - 1 - idx));
+        let entry: PanoEntry = serde_json::from_str(raw.get()).map_err(|_| at(""))?;
+
+        let pano_id: String = entry
+            .key
+            .get(1)
+            .and_then(|raw| serde_json::from_str(raw.get()).ok())
+            .ok_or_else(|| at("[0][1]"))?;
+
+        // GPS coordinates are in entry[2][0]
+        let location = |idx: usize| entry.location.get(idx).and_then(Option::as_deref).unwrap_or_default();
+        let coords = entry.location.first().and_then(Option::as_deref).ok_or_else(|| at("[2][0]"))?;
+        let lat = coords.get(2).copied().flatten().ok_or_else(|| at("[2][0][2]"))?;
+        let lon = coords.get(3).copied().flatten().ok_or_else(|| at("[2][0][3]"))?;
+
+        // Camera orientation is in entry[2][2] (not entry[2][0]!)
+        let orientation = location(2);
+        let heading = orientation.first().copied().flatten().unwrap_or(0.0);
+        let pitch = orientation.get(1).copied().flatten();
+        let roll = orientation.get(2).copied().flatten();
+
+        // Elevation above the EGM96 geoid is in entry[3][0]. When that is
+        // missing, fall back to the location block's altitude at
+        // entry[2][1][0], where photometa reports it.
+        let elevation = entry
+            .elevation
+            .as_deref()
+            .and_then(|e| e.first().copied().flatten())
+            .or_else(|| location(1).first().copied().flatten());
+
+        // Get date for this panorama, by its position in the response
+        let (date, date_confidence) = dates[count - 1 - idx];
+        let (copyright, street_name) = match main_pano.as_deref() == Some(pano_id.as_str()) {
+            true => (copyright.clone(), street_name.clone()),
+            false => (None, None),
+        };
 
         panoramas.push(Panorama {
             pano_id,
@@ -142,19 +374,144 @@ fn extract_panoramas(text: &str) -> Result<Vec<Panorama>> {
             pitch,
             roll,
             date,
+            date_confidence,
             elevation,
+            copyright,
+            street_name,
         });
     }
 
     Ok(panoramas)
 }
 
+/// A date from the search response's date list.
+#[derive(Debug, Clone, Copy)]
+struct DateEntry {
+    /// Position of the panorama in the response, if the entry names one
+    index: Option<usize>,
+    date: CaptureDate,
+    year_only: bool,
+}
+
+/// Parse a date entry, structured as `[[index], [year, month]]`.
+///
+/// Tolerates a missing index or month, `[month, year]` ordering, and dates
+/// given as text (`"2019-07"`, `"07/2019"`, `"2019"`).
+fn parse_date_entry(entry: &Value) -> Option<DateEntry> {
+    let index = match entry.get(0).and_then(Value::as_array).map(Vec::as_slice) {
+        Some([index]) => index.as_u64().map(|index| index as usize),
+        _ => None,
+    };
+
+    let numbers: Vec<u64> = match entry.get(1)? {
+        Value::Array(parts) => parts.iter().filter_map(Value::as_u64).collect(),
+        Value::String(text) => text
+            .split(|c: char| !c.is_ascii_digit())
+            .filter_map(|part| part.parse().ok())
+            .collect(),
+        _ => return None,
+    };
+    // The year is the only part that can exceed 31, whatever the order
+    let year_pos = numbers.iter().position(|&n| n >= 1000)?;
+    let year = u16::try_from(numbers[year_pos]).ok()?;
+    let month = numbers
+        .iter()
+        .enumerate()
+        .find(|&(pos, n)| pos != year_pos && (1..=12).contains(n))
+        .map(|(_, &n)| n as u8);
+
+    Some(DateEntry {
+        index,
+        date: CaptureDate::new(year, month.unwrap_or(1))?,
+        year_only: month.is_none(),
+    })
+}
+
+/// Assign dates to the `count` panoramas of a response, in response order.
+///
+/// When every entry names a distinct panorama, dates go to the panoramas
+/// they name. Otherwise they are matched by position from the end of the
+/// list, where undated (current) panoramas come first, and marked
+/// [`DateConfidence::Positional`]; unparseable entries still hold their
+/// position so later dates do not shift onto the wrong panorama.
+fn align_dates(entries: &[Value], count: usize) -> Vec<(Option<CaptureDate>, DateConfidence)> {
+    let parsed: Vec<Option<DateEntry>> = entries.iter().map(parse_date_entry).collect();
+    let mut dates = vec![(None, DateConfidence::None); count];
+    let confidence = |entry: &DateEntry| match entry.year_only {
+        true => DateConfidence::YearOnly,
+        false => DateConfidence::Exact,
+    };
+
+    let mut seen = HashSet::new();
+    let indexed = !parsed.is_empty()
+        && parsed.iter().all(|entry| {
+            entry.is_some_and(|entry| entry.index.is_some_and(|index| index < count && seen.insert(index)))
+        });
+    if indexed {
+        for entry in parsed.iter().flatten() {
+            if let Some(index) = entry.index {
+                dates[index] = (Some(entry.date), confidence(entry));
+            }
+        }
+        return dates;
+    }
+
+    for (slot, entry) in dates.iter_mut().rev().zip(parsed.iter().rev()) {
+        if let Some(entry) = entry {
+            *slot = (Some(entry.date), confidence(entry).min(DateConfidence::Positional));
+        }
+    }
+    dates
+}
+
 /// Search for panoramas at a given GPS coordinate.
-pub async fn search_panoramas(client: &Client, lat: f64, lon: f64) -> Result<Vec<Panorama>> {
-    let url = make_search_url(lat, lon);
-    let response = client.get(&url).send().await?;
-    let text = response.text().await?;
-    extract_panoramas(&text)
+pub(crate) async fn search_panoramas(http: &Http, lat: f64, lon: f64) -> Result<Vec<Panorama>> {
+    let locale = http.locale().cloned().unwrap_or_default();
+    let url = make_search_url(&http.endpoints().search, lat, lon, &locale);
+    let search = http.fetch(&url, false, |body| parse_response(&String::from_utf8_lossy(body)));
+    #[cfg(feature = "tracing")]
+    let search = tracing::Instrument::instrument(search, tracing::info_span!("search_panoramas", lat, lon));
+    search.await
+}
+
+/// Number of panorama IDs [`search_stream`] remembers for deduplication,
+/// roughly 15 MB. Nearby points keep finding the same panoramas, which keeps
+/// them fresh, so a scan moving across an area only forgets panoramas it
+/// has left behind.
+pub(crate) const SEEN_CAPACITY: usize = 100_000;
+
+/// Search many points, yielding each panorama once as soon as the search
+/// that found it completes.
+///
+/// Points are pulled from `points` lazily, with up to `concurrency`
+/// searches in flight, so the points can be an unbounded iterator (a grid
+/// over an area, samples along a route). Memory is bounded by the
+/// deduplication set, which keeps the IDs of the [`SEEN_CAPACITY`] most
+/// recently found panoramas; a panorama not found again within that many
+/// others is forgotten and may be yielded a second time. A failed search
+/// yields one error and the stream moves on to the next point; drop the
+/// stream to stop.
+pub(crate) fn search_stream<'a, I>(http: &'a Http, points: I, concurrency: usize) -> BoxStream<'a, Result<Panorama>>
+where
+    I: IntoIterator<Item = (f64, f64)>,
+    I::IntoIter: Send + 'a,
+{
+    let mut seen = Lru::default();
+    stream::iter(points)
+        .map(move |(lat, lon)| search_panoramas(http, lat, lon))
+        .buffer_unordered(concurrency.max(1))
+        .flat_map(move |result| {
+            let items: Vec<Result<Panorama>> = match result {
+                Ok(panos) => panos
+                    .into_iter()
+                    .filter(|pano| seen.insert(&pano.pano_id, (), SEEN_CAPACITY))
+                    .map(Ok)
+                    .collect(),
+                Err(e) => vec![Err(e)],
+            };
+            stream::iter(items)
+        })
+        .boxed()
 }
 
 /// Parse a Google Maps URL to extract GPS coordinates and panorama ID.
@@ -195,24 +552,24 @@ pub fn parse_url(url: &str) -> Result<(f64, f64, Option<String>)> {
 }
 
 /// Search for panoramas from a Google Maps URL.
-pub async fn search_panoramas_url(client: &Client, url: &str) -> Result<Vec<Panorama>> {
+pub(crate) async fn search_panoramas_url(http: &Http, url: &str) -> Result<Vec<Panorama>> {
     let (lat, lon, _) = parse_url(url)?;
-    search_panoramas(client, lat, lon).await
+    search_panoramas(http, lat, lon).await
 }
 
 /// Find the exact panorama shown in a Google Maps URL.
-pub async fn search_panoramas_url_exact(
-    client: &Client,
+pub(crate) async fn search_panoramas_url_exact(
+    http: &Http,
     url: &str,
 ) -> Result<Option<Panorama>> {
     let (lat, lon, pano_id) = parse_url(url)?;
 
     if let Some(target_id) = pano_id {
-        let panos = search_panoramas(client, lat, lon).await?;
+        let panos = search_panoramas(http, lat, lon).await?;
         Ok(panos.into_iter().find(|p| p.pano_id == target_id))
     } else {
         // No panorama ID in URL, return the first result
-        let panos = search_panoramas(client, lat, lon).await?;
+        let panos = search_panoramas(http, lat, lon).await?;
         Ok(panos.into_iter().next())
     }
 }
@@ -221,6 +578,101 @@ pub async fn search_panoramas_url_exact(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_response() {
+        let pano = serde_json::json!([
+            [2, "PANO_A"],
+            null,
+            [[null, null, 41.5, 12.5], null, [90.5, 88.0, 1.0]],
+            [30.0]
+        ]);
+        let data = serde_json::json!([
+            [0],
+            [0, 0, 0, 0, 0, [[0, 0, 0, [[pano]], 0, 0, 0, 0, [[[0], [2020, 5]]]]]]
+        ]);
+        let text = format!("/**/callbackfunc({data})");
+
+        let panos = parse_response(&text).unwrap();
+        assert_eq!(panos.len(), 1);
+        assert_eq!(panos[0].pano_id, "PANO_A");
+        assert_eq!(panos[0].lat, 41.5);
+        assert_eq!(panos[0].lon, 12.5);
+        assert_eq!(panos[0].heading, 90.5);
+        assert_eq!(panos[0].elevation, Some(30.0));
+        assert_eq!(panos[0].date, CaptureDate::new(2020, 5));
+        assert_eq!(panos[0].date_confidence, DateConfidence::Exact);
+
+        let pano = serde_json::json!([[2, "PANO_B"], null, [[null, null, 41.5, 12.5], [21.5], [0.0]]]);
+        let data = serde_json::json!([[0], [0, 0, 0, 0, 0, [[0, 0, 0, [[pano]]]]]]);
+        let panos = parse_response(&format!("/**/callbackfunc({data})")).unwrap();
+        assert_eq!(panos[0].elevation, Some(21.5));
+    }
+
+    #[test]
+    fn test_parse_date_entry() {
+        let parse = |entry: Value| parse_date_entry(&entry).map(|e| (e.index, e.date.to_string(), e.year_only));
+        assert_eq!(parse(serde_json::json!([[2], [2019, 7]])), Some((Some(2), "2019-07".into(), false)));
+        assert_eq!(parse(serde_json::json!([[0], [7, 2019]])), Some((Some(0), "2019-07".into(), false)));
+        assert_eq!(parse(serde_json::json!([[0], [2019]])), Some((Some(0), "2019-01".into(), true)));
+        assert_eq!(parse(serde_json::json!([null, "07/2019"])), Some((None, "2019-07".into(), false)));
+        assert_eq!(parse(serde_json::json!([[], "2019"])), Some((None, "2019-01".into(), true)));
+        assert_eq!(parse(serde_json::json!([[0], [7]])), None);
+    }
+
+    #[test]
+    fn test_align_dates() {
+        let dates = |entries: Value, count| -> Vec<(Option<String>, DateConfidence)> {
+            align_dates(entries.as_array().unwrap(), count)
+                .into_iter()
+                .map(|(date, confidence)| (date.map(|d| d.to_string()), confidence))
+                .collect()
+        };
+
+        // Indexed entries go to the panoramas they name, in any order
+        let indexed = serde_json::json!([[[2], [2014, 3]], [[1], [2017]]]);
+        assert_eq!(
+            dates(indexed, 3),
+            [
+                (None, DateConfidence::None),
+                (Some("2017-01".into()), DateConfidence::YearOnly),
+                (Some("2014-03".into()), DateConfidence::Exact),
+            ]
+        );
+
+        // Without indices, an unparseable entry keeps its slot
+        let positional = serde_json::json!([[null, [2014, 3]], [null, "unknown"], [null, [2021, 10]]]);
+        assert_eq!(
+            dates(positional, 4),
+            [
+                (None, DateConfidence::None),
+                (Some("2014-03".into()), DateConfidence::Positional),
+                (None, DateConfidence::None),
+                (Some("2021-10".into()), DateConfidence::Positional),
+            ]
+        );
+
+        // Out-of-range or repeated indices fall back to positions
+        let bad = serde_json::json!([[[5], [2014, 3]], [[5], [2015, 3]]]);
+        assert_eq!(dates(bad, 2)[0], (Some("2014-03".into()), DateConfidence::Positional));
+    }
+
+    #[test]
+    fn test_parse_response_malformed() {
+        assert!(parse_response("not a callback").is_err());
+        match parse_response("callbackfunc([1, 2])") {
+            Err(StreetViewError::EndpointFormatChanged { path, body }) => {
+                assert_eq!(path, "[1][5][0][3][0]");
+                assert_eq!(body, "callbackfunc([1, 2])");
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+        match parse_response("callbackfunc([[0], [0, 0, 0, 0, 0, [[0, 0, 0, [[5]]]]]])") {
+            Err(StreetViewError::EndpointFormatChanged { path, .. }) => assert_eq!(path, "[1][5][0][3][0][0]"),
+            other => panic!("unexpected {other:?}"),
+        }
+        assert!(matches!(parse_response("callbackfunc([[0], [0, 0)"), Err(StreetViewError::ParseError(_))));
+    }
+
     #[test]
     fn test_parse_url() {
         let url = "https://www.google.com/maps/@41.8982208,12.4764804,17z";
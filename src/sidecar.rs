@@ -0,0 +1,358 @@
+//! JSON sidecar metadata written next to saved images.
+
+use crate::embed;
+use crate::error::{Result, StreetViewError};
+use crate::types::{CaptureDate, DateConfidence, ImageFormat, Panorama};
+use crate::views::{CameraIntrinsics, ViewConfig};
+use image::{DynamicImage, GenericImageView};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Tile size of the panorama grid, used to infer the download zoom.
+const TILE_SIZE: u32 = 512;
+
+/// Metadata stored in the `.json` sidecar next to a saved image.
+///
+/// Contains everything needed to reproduce the download: the panorama
+/// identity and position, its capture date and orientation, and the
+/// image parameters it was saved with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SidecarMetadata {
+    /// Panorama ID
+    pub pano_id: String,
+    /// Latitude coordinate
+    pub lat: f64,
+    /// Longitude coordinate
+    pub lon: f64,
+    /// Capture month
+    pub date: Option<CaptureDate>,
+    /// Camera heading in degrees
+    pub heading: f64,
+    /// Camera pitch in degrees
+    pub pitch: Option<f64>,
+    /// Camera roll in degrees
+    pub roll: Option<f64>,
+    /// Elevation/altitude data
+    pub elevation: Option<f64>,
+    /// Copyright notice of the panorama, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub copyright: Option<String>,
+    /// Street or place name of the panorama, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub street_name: Option<String>,
+    /// Zoom level the panorama was downloaded at, if it can be inferred
+    pub zoom: Option<u8>,
+    /// Saved image width in pixels
+    pub width: u32,
+    /// Saved image height in pixels
+    pub height: u32,
+    /// Saved image format ("jpeg", "png" or "webp")
+    pub format: String,
+    /// Library name and version that wrote the file
+    pub generator: String,
+    /// View parameters, for perspective views extracted from the panorama
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub view: Option<SidecarView>,
+}
+
+/// View extraction parameters recorded in a sidecar.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SidecarView {
+    /// View label (e.g. "front")
+    pub label: Option<String>,
+    /// Heading in degrees
+    pub heading: u16,
+    /// Field of view in degrees
+    pub fov: u16,
+    /// Pitch in degrees
+    pub pitch: i16,
+    /// Pinhole intrinsics of the saved view
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "intrinsics")]
+    pub intrinsics: Option<CameraIntrinsics>,
+}
+
+impl SidecarMetadata {
+    /// Build sidecar metadata for a panorama image.
+    ///
+    /// The zoom level is inferred from the image width when it matches a
+    /// full panorama grid (`512 * 2^zoom`); set `zoom` explicitly for
+    /// cropped or resized images.
+    pub fn new(pano: &Panorama, img: &DynamicImage, format: ImageFormat) -> Self {
+        let (width, height) = img.dimensions();
+        Self {
+            pano_id: pano.pano_id.clone(),
+            lat: pano.lat,
+            lon: pano.lon,
+            date: pano.date,
+            heading: pano.heading,
+            pitch: pano.pitch,
+            roll: pano.roll,
+            elevation: pano.elevation,
+            copyright: pano.copyright.clone(),
+            street_name: pano.street_name.clone(),
+            zoom: infer_zoom(width),
+            width,
+            height,
+            format: format_name(format).to_string(),
+            generator: concat!("rsstreetview ", env!("CARGO_PKG_VERSION")).to_string(),
+            view: None,
+        }
+    }
+
+    /// Record the view parameters of an image extracted from the panorama.
+    ///
+    /// Also sets `zoom` to the zoom level the panorama was downloaded at,
+    /// and records the view's camera intrinsics at the saved image size.
+    pub fn view(mut self, label: Option<&str>, config: &ViewConfig) -> Self {
+        self.zoom = Some(config.zoom);
+        self.view = Some(SidecarView {
+            label: label.map(str::to_string),
+            heading: config.heading,
+            fov: config.fov,
+            pitch: config.pitch,
+            intrinsics: Some(config.intrinsics(self.width, self.height)),
+        });
+        self
+    }
+
+    /// Set the zoom level explicitly.
+    pub fn zoom(mut self, zoom: u8) -> Self {
+        self.zoom = Some(zoom);
+        self
+    }
+
+    /// The panorama this metadata was written for.
+    pub fn panorama(&self) -> Panorama {
+        Panorama {
+            pano_id: self.pano_id.clone(),
+            lat: self.lat,
+            lon: self.lon,
+            heading: self.heading,
+            pitch: self.pitch,
+            roll: self.roll,
+            date: self.date,
+            date_confidence: DateConfidence::of(self.date),
+            elevation: self.elevation,
+            copyright: self.copyright.clone(),
+            street_name: self.street_name.clone(),
+        }
+    }
+
+    /// Write this metadata to `path` as pretty-printed JSON.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| StreetViewError::ParseError(format!("JSON serialize error: {e}")))?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+
+    /// Read sidecar metadata from a JSON file.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        serde_json::from_str(&json)
+            .map_err(|e| StreetViewError::ParseError(format!("JSON parse error: {e}")))
+    }
+}
+
+/// Where recovered metadata came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum MetadataSource {
+    /// The `.json` sidecar next to the image
+    Sidecar,
+    /// The embedded XMP packet
+    Xmp,
+    /// The embedded EXIF block (no pano ID available)
+    Exif,
+}
+
+/// Metadata recovered from a previously saved panorama.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SavedMetadata {
+    /// Panorama ID (not stored in EXIF)
+    pub pano_id: Option<String>,
+    /// Latitude coordinate
+    pub lat: Option<f64>,
+    /// Longitude coordinate
+    pub lon: Option<f64>,
+    /// Capture month
+    pub date: Option<CaptureDate>,
+    /// Camera heading in degrees
+    pub heading: Option<f64>,
+    /// Projection type (e.g. "equirectangular")
+    pub projection: Option<String>,
+    /// Where the metadata was read from
+    pub source: MetadataSource,
+}
+
+/// Recover the metadata of an image saved by this crate.
+///
+/// Looks for the JSON sidecar first, then the embedded XMP packet, then
+/// the embedded EXIF GPS and date tags. This allows re-ingesting old
+/// archives without their original manifests.
+///
+/// # Errors
+///
+/// Returns `StreetViewError::ParseError` if the file carries none of them.
+pub fn read_saved_metadata(path: impl AsRef<Path>) -> Result<SavedMetadata> {
+    let path = path.as_ref();
+
+    let sidecar = sidecar_path(path);
+    if sidecar != path && sidecar.exists() {
+        let meta = SidecarMetadata::read(&sidecar)?;
+        return Ok(SavedMetadata {
+            pano_id: Some(meta.pano_id),
+            lat: Some(meta.lat),
+            lon: Some(meta.lon),
+            date: meta.date,
+            heading: Some(meta.heading),
+            projection: Some("equirectangular".to_string()),
+            source: MetadataSource::Sidecar,
+        });
+    }
+
+    let file = std::fs::read(path)?;
+    let exif = embed::parse_exif(&file);
+
+    if let Some(xmp) = embed::parse_xmp(&file) {
+        if xmp.pano_id.is_some() || xmp.projection.is_some() {
+            let exif = exif.unwrap_or_default();
+            return Ok(SavedMetadata {
+                pano_id: xmp.pano_id,
+                lat: xmp.lat.or(exif.lat),
+                lon: xmp.lon.or(exif.lon),
+                date: xmp.date.or(exif.date).and_then(|d| d.parse().ok()),
+                heading: xmp.heading,
+                projection: xmp.projection,
+                source: MetadataSource::Xmp,
+            });
+        }
+    }
+
+    match exif {
+        Some(exif) if exif.lat.is_some() || exif.date.is_some() => Ok(SavedMetadata {
+            pano_id: None,
+            lat: exif.lat,
+            lon: exif.lon,
+            date: exif.date.and_then(|d| d.parse().ok()),
+            heading: None,
+            projection: None,
+            source: MetadataSource::Exif,
+        }),
+        _ => Err(StreetViewError::ParseError(format!(
+            "No saved panorama metadata found for {}",
+            path.display()
+        ))),
+    }
+}
+
+/// Path of the sidecar for an image: `name.jpg` -> `name.json`.
+pub fn sidecar_path(image_path: impl AsRef<Path>) -> PathBuf {
+    image_path.as_ref().with_extension("json")
+}
+
+/// Sidecar serialization of [`CameraIntrinsics`], which only derives serde
+/// itself with the `serde` feature.
+mod intrinsics {
+    use crate::views::CameraIntrinsics;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(remote = "CameraIntrinsics")]
+    struct Fields {
+        width: u32,
+        height: u32,
+        fx: f64,
+        fy: f64,
+        cx: f64,
+        cy: f64,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Intrinsics(#[serde(with = "Fields")] CameraIntrinsics);
+
+    pub fn serialize<S: Serializer>(value: &Option<CameraIntrinsics>, serializer: S) -> Result<S::Ok, S::Error> {
+        value.map(Intrinsics).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<CameraIntrinsics>, D::Error> {
+        Ok(Option::<Intrinsics>::deserialize(deserializer)?.map(|Intrinsics(intrinsics)| intrinsics))
+    }
+}
+
+fn format_name(format: ImageFormat) -> &'static str {
+    match format {
+        ImageFormat::Jpeg => "jpeg",
+        ImageFormat::Png => "png",
+        ImageFormat::WebP => "webp",
+    }
+}
+
+fn infer_zoom(width: u32) -> Option<u8> {
+    (1..=7u8).find(|&zoom| TILE_SIZE << zoom == width)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures::panorama;
+    use image::RgbImage;
+
+    #[test]
+    fn test_sidecar_roundtrip() {
+        let pano = Panorama {
+            heading: 45.0,
+            pitch: Some(90.0),
+            date: CaptureDate::new(2018, 3),
+            date_confidence: DateConfidence::Exact,
+            ..panorama("sidecar_pano", 1.5, -2.5)
+        };
+        let img = DynamicImage::ImageRgb8(RgbImage::new(2048, 1024));
+        let meta = SidecarMetadata::new(&pano, &img, ImageFormat::Png);
+        assert_eq!(meta.zoom, Some(2));
+        assert_eq!(meta.format, "png");
+
+        let path = std::env::temp_dir().join("rsstreetview_sidecar.json");
+        meta.write(&path).unwrap();
+        assert_eq!(SidecarMetadata::read(&path).unwrap(), meta);
+        let restored = meta.panorama();
+        assert_eq!((restored.heading, restored.date, restored.date_confidence), (45.0, pano.date, DateConfidence::Exact));
+
+        // Views record their intrinsics
+        let view = DynamicImage::ImageRgb8(RgbImage::new(640, 640));
+        let meta = SidecarMetadata::new(&pano, &view, ImageFormat::Jpeg).view(Some("front"), &ViewConfig::new(0));
+        let intrinsics = meta.view.as_ref().unwrap().intrinsics.unwrap();
+        assert_eq!((intrinsics.width, intrinsics.cx), (640, 320.0));
+        meta.write(&path).unwrap();
+        assert_eq!(SidecarMetadata::read(&path).unwrap(), meta);
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_read_saved_metadata_embedded() {
+        let pano = Panorama {
+            heading: 10.0,
+            date: CaptureDate::new(2022, 9),
+            date_confidence: DateConfidence::Exact,
+            ..panorama("embedded_pano", 48.85, 2.29)
+        };
+        let img = DynamicImage::ImageRgb8(RgbImage::new(64, 32));
+        let path = std::env::temp_dir().join("rsstreetview_readback.png");
+        crate::save::save_with_metadata(&img, &pano, &path, &crate::SaveOptions::new().format(ImageFormat::Png))
+            .unwrap();
+
+        let meta = read_saved_metadata(&path).unwrap();
+        assert_eq!(meta.source, MetadataSource::Xmp);
+        assert_eq!(meta.pano_id.as_deref(), Some("embedded_pano"));
+        assert_eq!(meta.date, CaptureDate::new(2022, 9));
+        assert_eq!(meta.projection.as_deref(), Some("equirectangular"));
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_sidecar_path() {
+        assert_eq!(sidecar_path("out/pano.webp"), PathBuf::from("out/pano.json"));
+    }
+}
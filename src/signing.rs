@@ -0,0 +1,85 @@
+//! Digital signatures for official Google Maps API requests.
+//!
+//! Google requires requests beyond the unsigned quota to carry a
+//! `signature` parameter: the HMAC-SHA1 of the URL path and query, keyed
+//! with the URL signing secret from the Cloud console, in URL-safe base64.
+
+use crate::error::{Result, StreetViewError};
+use crate::utils::BASE64_URL_SAFE;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+
+/// Internal: signs official API URLs with a URL signing secret.
+#[derive(Clone)]
+pub(crate) struct UrlSigner {
+    mac: Hmac<Sha1>,
+}
+
+impl UrlSigner {
+    /// Create a signer from the URL-safe base64 secret shown in the console.
+    pub(crate) fn new(secret: &str) -> Result<Self> {
+        let key = BASE64_URL_SAFE
+            .decode(secret.trim())
+            .ok()
+            .filter(|key| !key.is_empty())
+            .ok_or_else(|| StreetViewError::ParseError("Invalid URL signing secret".to_string()))?;
+        let mac = Hmac::new_from_slice(&key)
+            .map_err(|_| StreetViewError::ParseError("Invalid URL signing secret".to_string()))?;
+        Ok(Self { mac })
+    }
+
+    /// Append a `signature` parameter to `url`.
+    pub(crate) fn sign(&self, url: &str) -> Result<String> {
+        let parsed = reqwest::Url::parse(url)
+            .map_err(|e| StreetViewError::ParseError(format!("Invalid URL to sign: {e}")))?;
+
+        let mut resource = parsed.path().to_string();
+        if let Some(query) = parsed.query() {
+            resource.push('?');
+            resource.push_str(query);
+        }
+
+        let signature = BASE64_URL_SAFE.encode(self.digest(resource.as_bytes()));
+        let separator = if parsed.query().is_some() { '&' } else { '?' };
+        Ok(format!("{parsed}{separator}signature={signature}"))
+    }
+
+    /// HMAC-SHA1 of `message` under the signing key.
+    fn digest(&self, message: &[u8]) -> [u8; 20] {
+        let mut mac = self.mac.clone();
+        mac.update(message);
+        mac.finalize().into_bytes().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    #[test]
+    fn test_hmac() {
+        // RFC 2202 test case 2
+        let signer = UrlSigner::new(&BASE64_URL_SAFE.encode("Jefe")).unwrap();
+        assert_eq!(
+            hex(&signer.digest(b"what do ya want for nothing?")),
+            "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79"
+        );
+    }
+
+    #[test]
+    fn test_sign_url() {
+        // Example from Google's URL signing documentation
+        let signer = UrlSigner::new("vNIXE0xscrmjlyV-12Nj_BvUPaw=").unwrap();
+        let signed = signer
+            .sign("https://maps.googleapis.com/maps/api/geocode/json?address=New+York&client=clientID")
+            .unwrap();
+        assert!(signed.ends_with("&signature=chaRF2hTJKOScPr-RQCEhZbSzIE="));
+
+        assert!(UrlSigner::new("not base64!").is_err());
+    }
+}
@@ -0,0 +1,160 @@
+//! Coalescing of identical in-flight requests.
+//!
+//! When many tasks request the same URL at once (a web service rendering
+//! the same panorama for several users, overlapping crawls sharing tiles),
+//! only the first request goes to the network. The others wait for it and
+//! receive a copy of its response.
+
+use crate::error::Result;
+use crate::transport::HttpResponse;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+/// Outcome of a flight: `None` while pending, `Some(None)` if the request
+/// failed, `Some(Some(response))` if it succeeded.
+type Outcome = Option<Option<Arc<HttpResponse>>>;
+
+/// Internal: requests currently in flight, keyed by URL.
+#[derive(Default)]
+pub(crate) struct SingleFlight {
+    flights: Mutex<HashMap<String, watch::Receiver<Outcome>>>,
+}
+
+enum Role {
+    Leader(watch::Sender<Outcome>, watch::Receiver<Outcome>),
+    Follower(watch::Receiver<Outcome>),
+}
+
+impl SingleFlight {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `request` for `key`, unless a request for the same key is
+    /// already in flight, in which case wait for and share its response.
+    ///
+    /// Errors are not shared: errors are not `Clone`, and a failure seen by
+    /// one caller (a timeout, a dropped connection) is worth retrying, so
+    /// waiting callers send their own request instead. The same happens if
+    /// the leading request is cancelled.
+    pub(crate) async fn run<F, Fut>(&self, key: &str, request: F) -> Result<HttpResponse>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<HttpResponse>>,
+    {
+        let role = {
+            let mut flights = self.lock();
+            match flights.get(key) {
+                Some(rx) => Role::Follower(rx.clone()),
+                None => {
+                    let (tx, rx) = watch::channel(None);
+                    flights.insert(key.to_string(), rx.clone());
+                    Role::Leader(tx, rx)
+                }
+            }
+        };
+
+        match role {
+            Role::Follower(mut rx) => {
+                let shared = match rx.wait_for(Option::is_some).await {
+                    Ok(outcome) => outcome.clone().flatten(),
+                    Err(_) => None,
+                };
+                match shared {
+                    Some(response) => Ok(HttpResponse::clone(&response)),
+                    None => request().await,
+                }
+            }
+            Role::Leader(tx, rx) => {
+                let landing = Landing { flights: self, key, rx };
+                let result = request().await;
+                drop(landing);
+                if tx.receiver_count() > 0 {
+                    tx.send_replace(Some(result.as_ref().ok().map(|response| Arc::new(response.clone()))));
+                }
+                result
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, watch::Receiver<Outcome>>> {
+        self.flights.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Removes a leader's flight when it finishes or is cancelled, so later
+/// requests start a new flight instead of joining a finished one.
+struct Landing<'a> {
+    flights: &'a SingleFlight,
+    key: &'a str,
+    rx: watch::Receiver<Outcome>,
+}
+
+impl Drop for Landing<'_> {
+    fn drop(&mut self) {
+        let mut flights = self.flights.lock();
+        if flights.get(self.key).is_some_and(|rx| rx.same_channel(&self.rx)) {
+            flights.remove(self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::StreetViewError;
+    use reqwest::header::HeaderMap;
+    use reqwest::{StatusCode, Version};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    fn response(body: &str) -> HttpResponse {
+        HttpResponse {
+            status: StatusCode::OK,
+            version: Version::HTTP_11,
+            headers: HeaderMap::new(),
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_coalesces_concurrent_requests() {
+        let flights = SingleFlight::new();
+        let sent = AtomicUsize::new(0);
+        let request = || async {
+            sent.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            Ok(response("tile"))
+        };
+
+        let results = futures::future::join_all((0..5).map(|_| flights.run("url", request))).await;
+        assert_eq!(sent.load(Ordering::SeqCst), 1);
+        assert!(results.iter().all(|r| r.as_ref().unwrap().body == b"tile"));
+        assert!(flights.lock().is_empty());
+
+        // Finished flights are not reused
+        flights.run("url", request).await.unwrap();
+        assert_eq!(sent.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_failed_leader() {
+        let flights = SingleFlight::new();
+        let sent = AtomicUsize::new(0);
+        let request = || async {
+            let n = sent.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            match n {
+                0 => Err(StreetViewError::InvalidResponse("reset".to_string())),
+                _ => Ok(response("retry")),
+            }
+        };
+
+        let (first, second) = tokio::join!(flights.run("url", request), flights.run("url", request));
+        assert!(first.is_err());
+        assert_eq!(second.unwrap().body, b"retry");
+        assert_eq!(sent.load(Ordering::SeqCst), 2);
+    }
+}
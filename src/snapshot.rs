@@ -0,0 +1,251 @@
+//! One-call "snapshot this place" workflow.
+//!
+//! [`StreetView::snapshot`](crate::StreetView::snapshot) geocodes an address
+//! (or takes coordinates), picks the nearest and most recent panorama,
+//! extracts a set of views, and saves them with JSON sidecars.
+
+use crate::error::Result;
+#[cfg(feature = "privacy")]
+use crate::privacy::Redactor;
+use crate::sidecar::{sidecar_path, SidecarMetadata};
+use crate::types::{ImageFormat, Location, Panorama, SaveOptions};
+use crate::views::{Direction, ViewConfig};
+use std::path::PathBuf;
+
+/// Panoramas this close to the nearest one count as "the same spot" when
+/// choosing the most recent capture.
+const SAME_SPOT_TOLERANCE_M: f64 = 10.0;
+
+/// Where to take a snapshot.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SnapshotTarget {
+    /// A postal address or place name (geocoded with the API key)
+    Address(String),
+    /// GPS coordinates as (lat, lon)
+    Coords(f64, f64),
+}
+
+impl From<&str> for SnapshotTarget {
+    fn from(address: &str) -> Self {
+        SnapshotTarget::Address(address.to_string())
+    }
+}
+
+impl From<String> for SnapshotTarget {
+    fn from(address: String) -> Self {
+        SnapshotTarget::Address(address)
+    }
+}
+
+impl From<(f64, f64)> for SnapshotTarget {
+    fn from((lat, lon): (f64, f64)) -> Self {
+        SnapshotTarget::Coords(lat, lon)
+    }
+}
+
+/// Options for [`StreetView::snapshot`](crate::StreetView::snapshot).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct SnapshotOptions {
+    /// Labelled views to extract (default: front, left, right at 1024×1024)
+    pub views: Vec<(String, ViewConfig)>,
+    /// Output directory (default: current directory)
+    pub out_dir: PathBuf,
+    /// Image save options (default: JPEG)
+    pub save: SaveOptions,
+    /// Blur faces and plates in each view before saving (default: none)
+    #[cfg(feature = "privacy")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub redactor: Option<Redactor>,
+}
+
+impl SnapshotOptions {
+    /// Create default snapshot options.
+    pub fn new() -> Self {
+        let views = [Direction::Front, Direction::Left, Direction::Right]
+            .into_iter()
+            .map(|dir| (dir.name().to_string(), ViewConfig::from_direction(dir).size(1024, 1024)))
+            .collect();
+
+        Self {
+            views,
+            out_dir: PathBuf::from("."),
+            save: SaveOptions::new().format(ImageFormat::Jpeg),
+            #[cfg(feature = "privacy")]
+            redactor: None,
+        }
+    }
+
+    /// Set the output directory.
+    pub fn out_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.out_dir = dir.into();
+        self
+    }
+
+    /// Replace the views to extract.
+    pub fn views(mut self, views: Vec<(String, ViewConfig)>) -> Self {
+        self.views = views;
+        self
+    }
+
+    /// Replace the views with one per direction, labelled by `label`.
+    ///
+    /// ```
+    /// # use rsstreetview::{Direction, SnapshotOptions};
+    /// let options = SnapshotOptions::new().directions(Direction::ALL, |dir| dir.compass_name().to_string());
+    /// assert_eq!(options.views.len(), 8);
+    /// assert_eq!(options.views[2].0, "east");
+    /// ```
+    pub fn directions(
+        mut self,
+        directions: impl IntoIterator<Item = Direction>,
+        label: impl Fn(Direction) -> String,
+    ) -> Self {
+        self.views = directions
+            .into_iter()
+            .map(|dir| (label(dir), ViewConfig::from_direction(dir).size(1024, 1024)))
+            .collect();
+        self
+    }
+
+    /// Add a labelled view.
+    pub fn add_view(mut self, label: impl Into<String>, config: ViewConfig) -> Self {
+        self.views.push((label.into(), config));
+        self
+    }
+
+    /// Set the image save options.
+    pub fn save_options(mut self, save: SaveOptions) -> Self {
+        self.save = save;
+        self
+    }
+
+    /// Blur faces and license plates in each view before it is saved.
+    #[cfg(feature = "privacy")]
+    pub fn redactor(mut self, redactor: Redactor) -> Self {
+        self.redactor = Some(redactor);
+        self
+    }
+
+    /// Whether saved views go through a face blurring pass, for
+    /// [`Policy::check_storage`](crate::Policy::check_storage).
+    pub(crate) fn removes_faces(&self) -> bool {
+        #[cfg(feature = "privacy")]
+        {
+            self.redactor.as_ref().is_some_and(Redactor::removes_faces)
+        }
+        #[cfg(not(feature = "privacy"))]
+        {
+            false
+        }
+    }
+}
+
+impl Default for SnapshotOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A view saved by a snapshot.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SavedView {
+    /// View label (e.g. "front")
+    pub label: String,
+    /// Path of the saved image
+    pub path: PathBuf,
+    /// Path of the JSON sidecar
+    pub sidecar: PathBuf,
+    /// Panorama the view was extracted from
+    pub panorama: Panorama,
+}
+
+/// Pick the nearest panorama, preferring the most recent capture among
+/// those at essentially the same spot.
+pub(crate) fn pick_panorama(panos: &[Panorama], lat: f64, lon: f64) -> Option<&Panorama> {
+    let target = Location::new(lat, lon);
+    let distance = |p: &Panorama| p.location().distance_to(&target);
+    let nearest = panos.iter().map(distance).fold(f64::INFINITY, f64::min);
+
+    panos
+        .iter()
+        .filter(|p| distance(p) <= nearest + SAME_SPOT_TOLERANCE_M)
+        .max_by(|a, b| a.date.cmp(&b.date))
+}
+
+/// Build the output path for a view.
+pub(crate) fn view_path(options: &SnapshotOptions, pano_id: &str, label: &str) -> PathBuf {
+    options
+        .out_dir
+        .join(format!("{pano_id}_{label}.{}", options.save.format.extension()))
+}
+
+/// Save views extracted from `pano` with their sidecars.
+pub(crate) async fn save_views(
+    pano: &Panorama,
+    views: Vec<image::DynamicImage>,
+    options: &SnapshotOptions,
+) -> Result<Vec<SavedView>> {
+    let mut saved = Vec::with_capacity(views.len());
+
+    for ((label, config), view) in options.views.iter().zip(views) {
+        #[cfg(feature = "privacy")]
+        let view = match &options.redactor {
+            Some(redactor) => redactor.redact_async(view).await?,
+            None => view,
+        };
+        let path = view_path(options, &pano.pano_id, label);
+        let sidecar = sidecar_path(&path);
+
+        let meta = SidecarMetadata::new(pano, &view, options.save.format).view(Some(label), config);
+        options.save.save_async(view, &path).await?;
+        meta.write(&sidecar)?;
+
+        saved.push(SavedView {
+            label: label.clone(),
+            path,
+            sidecar,
+            panorama: pano.clone(),
+        });
+    }
+
+    Ok(saved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures::panorama;
+    use crate::types::DateConfidence;
+
+    fn pano(id: &str, lat: f64, date: Option<&str>) -> Panorama {
+        Panorama {
+            date: date.map(|d| d.parse().unwrap()),
+            date_confidence: DateConfidence::Exact,
+            ..panorama(id, lat, 0.0)
+        }
+    }
+
+    #[test]
+    fn test_pick_panorama_prefers_recent_nearby() {
+        let panos = vec![
+            pano("near_old", 0.0, Some("2012-05")),
+            pano("near_new", 0.00002, Some("2021-08")),
+            pano("far_newest", 0.01, Some("2023-01")),
+        ];
+        assert_eq!(pick_panorama(&panos, 0.0, 0.0).unwrap().pano_id, "near_new");
+        assert!(pick_panorama(&[], 0.0, 0.0).is_none());
+    }
+
+    #[test]
+    fn test_default_views() {
+        let options = SnapshotOptions::new().out_dir("out");
+        let labels: Vec<&str> = options.views.iter().map(|(l, _)| l.as_str()).collect();
+        assert_eq!(labels, ["front", "left", "right"]);
+        assert_eq!(view_path(&options, "abc", "front"), PathBuf::from("out/abc_front.jpg"));
+    }
+}
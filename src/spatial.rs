@@ -0,0 +1,267 @@
+//! In-memory spatial index over a collection of panoramas.
+//!
+//! [`PanoIndex`] answers nearest-neighbor and radius queries over a corpus
+//! collected locally, without a database (see `index::PanoramaIndex` with
+//! the `sqlite` feature for a persistent one). Panoramas are stored as
+//! points on the unit sphere in a k-d tree, so queries work the same near
+//! the poles and across the antimeridian.
+
+use crate::types::Panorama;
+use crate::utils::EARTH_RADIUS_M;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// A point on the unit sphere and the index of its panorama.
+type Node = ([f64; 3], usize);
+
+/// Unit vector of a GPS coordinate.
+fn unit_vector(lat: f64, lon: f64) -> [f64; 3] {
+    let (sin_lat, cos_lat) = lat.to_radians().sin_cos();
+    let (sin_lon, cos_lon) = lon.to_radians().sin_cos();
+    [cos_lat * cos_lon, cos_lat * sin_lon, sin_lat]
+}
+
+fn squared_chord(a: [f64; 3], b: [f64; 3]) -> f64 {
+    (0..3).map(|i| (a[i] - b[i]).powi(2)).sum()
+}
+
+/// Great-circle distance in meters for a chord of the unit sphere.
+fn chord_to_meters(squared_chord: f64) -> f64 {
+    2.0 * EARTH_RADIUS_M * (squared_chord.sqrt() / 2.0).min(1.0).asin()
+}
+
+/// A nearest-neighbor candidate, ordered by distance.
+#[derive(Debug, Clone, Copy)]
+struct Candidate {
+    squared_chord: f64,
+    index: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.squared_chord.total_cmp(&other.squared_chord).then(self.index.cmp(&other.index))
+    }
+}
+
+/// A k-d tree of panoramas for nearest-neighbor and radius queries.
+///
+/// # Example
+///
+/// ```
+/// # use rsstreetview::PanoIndex;
+/// # let panos: Vec<rsstreetview::Panorama> = Vec::new();
+/// let index = PanoIndex::new(panos);
+/// for (pano, meters) in index.nearest(41.8982, 12.4764, 5) {
+///     println!("{} is {meters:.0} m away", pano.pano_id);
+/// }
+/// let around = index.within_radius(41.8982, 12.4764, 50.0);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PanoIndex {
+    panos: Vec<Panorama>,
+    /// Implicit tree: each slice's median splits its halves on the axis
+    /// cycling with depth
+    nodes: Vec<Node>,
+}
+
+impl PanoIndex {
+    /// Build an index over `panos`.
+    pub fn new(panos: impl IntoIterator<Item = Panorama>) -> Self {
+        let panos: Vec<Panorama> = panos.into_iter().collect();
+        let mut nodes: Vec<Node> = panos
+            .iter()
+            .enumerate()
+            .map(|(i, pano)| (unit_vector(pano.lat, pano.lon), i))
+            .collect();
+        build(&mut nodes, 0);
+        Self { panos, nodes }
+    }
+
+    /// Number of panoramas in the index.
+    pub fn len(&self) -> usize {
+        self.panos.len()
+    }
+
+    /// Whether the index is empty.
+    pub fn is_empty(&self) -> bool {
+        self.panos.is_empty()
+    }
+
+    /// Indexed panoramas, in the order they were given.
+    pub fn panoramas(&self) -> &[Panorama] {
+        &self.panos
+    }
+
+    /// The `k` panoramas nearest to a coordinate with their distances in
+    /// meters, nearest first.
+    pub fn nearest(&self, lat: f64, lon: f64, k: usize) -> Vec<(&Panorama, f64)> {
+        let mut heap = BinaryHeap::with_capacity(k + 1);
+        if k > 0 {
+            nearest(&self.nodes, 0, unit_vector(lat, lon), k, &mut heap);
+        }
+        self.resolve(heap.into_sorted_vec())
+    }
+
+    /// Panoramas within `meters` of a coordinate with their distances in
+    /// meters, nearest first.
+    pub fn within_radius(&self, lat: f64, lon: f64, meters: f64) -> Vec<(&Panorama, f64)> {
+        let angle = (meters / EARTH_RADIUS_M).min(std::f64::consts::PI);
+        let radius = (2.0 * (angle / 2.0).sin()).powi(2);
+        let mut found = Vec::new();
+        within(&self.nodes, 0, unit_vector(lat, lon), radius, &mut found);
+        found.sort();
+        self.resolve(found)
+    }
+
+    fn resolve(&self, candidates: Vec<Candidate>) -> Vec<(&Panorama, f64)> {
+        candidates
+            .into_iter()
+            .map(|c| (&self.panos[c.index], chord_to_meters(c.squared_chord)))
+            .collect()
+    }
+}
+
+impl FromIterator<Panorama> for PanoIndex {
+    fn from_iter<I: IntoIterator<Item = Panorama>>(panos: I) -> Self {
+        Self::new(panos)
+    }
+}
+
+fn build(nodes: &mut [Node], depth: usize) {
+    if nodes.len() <= 1 {
+        return;
+    }
+    let axis = depth % 3;
+    let mid = nodes.len() / 2;
+    nodes.select_nth_unstable_by(mid, |a, b| a.0[axis].total_cmp(&b.0[axis]));
+    let (left, right) = nodes.split_at_mut(mid);
+    build(left, depth + 1);
+    build(&mut right[1..], depth + 1);
+}
+
+fn nearest(nodes: &[Node], depth: usize, target: [f64; 3], k: usize, heap: &mut BinaryHeap<Candidate>) {
+    if nodes.is_empty() {
+        return;
+    }
+    let mid = nodes.len() / 2;
+    let (point, index) = nodes[mid];
+    heap.push(Candidate {
+        squared_chord: squared_chord(point, target),
+        index,
+    });
+    if heap.len() > k {
+        heap.pop();
+    }
+
+    let diff = target[depth % 3] - point[depth % 3];
+    let (near, far) = if diff < 0.0 {
+        (&nodes[..mid], &nodes[mid + 1..])
+    } else {
+        (&nodes[mid + 1..], &nodes[..mid])
+    };
+    nearest(near, depth + 1, target, k, heap);
+    // The far side can only help if the splitting plane is closer than the
+    // current k-th candidate
+    if heap.len() < k || heap.peek().is_some_and(|worst| diff * diff < worst.squared_chord) {
+        nearest(far, depth + 1, target, k, heap);
+    }
+}
+
+fn within(nodes: &[Node], depth: usize, target: [f64; 3], radius: f64, found: &mut Vec<Candidate>) {
+    if nodes.is_empty() {
+        return;
+    }
+    let mid = nodes.len() / 2;
+    let (point, index) = nodes[mid];
+    let distance = squared_chord(point, target);
+    if distance <= radius {
+        found.push(Candidate {
+            squared_chord: distance,
+            index,
+        });
+    }
+
+    let diff = target[depth % 3] - point[depth % 3];
+    if diff < 0.0 || diff * diff <= radius {
+        within(&nodes[..mid], depth + 1, target, radius, found);
+    }
+    if diff >= 0.0 || diff * diff <= radius {
+        within(&nodes[mid + 1..], depth + 1, target, radius, found);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures::panorama;
+    use crate::utils::haversine_distance;
+
+    fn panos(count: usize) -> Vec<Panorama> {
+        // Deterministic scatter over a few degrees, straddling the antimeridian
+        let mut state = 12345u64;
+        let mut next = || {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 11) as f64 / (1u64 << 53) as f64
+        };
+        (0..count)
+            .map(|i| panorama(
+                    &i.to_string(),
+                    10.0 + next() * 2.0,
+                    (179.0 + next() * 2.0 + 180.0).rem_euclid(360.0) - 180.0,
+                ))
+            .collect()
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        let panos = panos(500);
+        let index = PanoIndex::new(panos.clone());
+        assert_eq!(index.len(), 500);
+
+        for (lat, lon) in [(11.0, 180.0), (10.5, -179.5), (11.9, 179.2), (20.0, 0.0)] {
+            let mut expected: Vec<(String, f64)> = panos
+                .iter()
+                .map(|p| (p.pano_id.clone(), haversine_distance(lat, lon, p.lat, p.lon)))
+                .collect();
+            expected.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+            let nearest = index.nearest(lat, lon, 7);
+            assert_eq!(nearest.len(), 7);
+            for ((pano, meters), (id, expected_meters)) in nearest.iter().zip(&expected) {
+                assert_eq!(&pano.pano_id, id);
+                assert!((meters - expected_meters).abs() < 1e-3);
+            }
+
+            let within = index.within_radius(lat, lon, 25_000.0);
+            let expected_ids: Vec<_> = expected
+                .iter()
+                .take_while(|(_, meters)| *meters <= 25_000.0)
+                .map(|(id, _)| id)
+                .collect();
+            let ids: Vec<_> = within.iter().map(|(pano, _)| &pano.pano_id).collect();
+            assert_eq!(ids, expected_ids);
+        }
+    }
+
+    #[test]
+    fn test_empty() {
+        let index = PanoIndex::default();
+        assert!(index.nearest(0.0, 0.0, 3).is_empty());
+        assert!(index.within_radius(0.0, 0.0, 1e6).is_empty());
+        assert!(PanoIndex::new(panos(3)).nearest(0.0, 0.0, 0).is_empty());
+    }
+}
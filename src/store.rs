@@ -0,0 +1,277 @@
+//! Persistent panorama store with download-if-missing semantics.
+//!
+//! A [`PanoramaStore`] maps `(pano_id, zoom)` to a file in a directory.
+//! [`get_or_download`](PanoramaStore::get_or_download) returns the stored
+//! image when the file exists and downloads and saves it otherwise, so
+//! scraping pipelines can be rerun without fetching panoramas twice.
+//!
+//! With the `sqlite` feature, a store can also keep a
+//! [`PanoramaIndex`](crate::index::PanoramaIndex) of what it holds.
+
+use crate::error::{Result, StreetViewError};
+use crate::pano_id::PanoId;
+#[cfg(feature = "privacy")]
+use crate::privacy::Redactor;
+use crate::save::save_panorama_async;
+#[cfg(feature = "sqlite")]
+use crate::index::{IndexEntry, PanoramaIndex};
+use crate::types::{ImageFormat, Panorama, SaveOptions};
+use crate::StreetView;
+use image::DynamicImage;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "sqlite")]
+use std::sync::Arc;
+
+/// How panorama files are arranged inside the store directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum StoreLayout {
+    /// `{dir}/{pano_id}_z{zoom}.{ext}` (default)
+    #[default]
+    Flat,
+    /// `{dir}/z{zoom}/{pano_id}.{ext}`
+    ByZoom,
+    /// `{dir}/{first two characters of pano_id}/{pano_id}_z{zoom}.{ext}`,
+    /// keeping directories small for large corpora
+    Sharded,
+}
+
+/// A panorama returned by the store.
+#[derive(Debug, Clone)]
+pub struct StoredPanorama {
+    /// Path of the stored image
+    pub path: PathBuf,
+    /// The panorama image
+    pub image: DynamicImage,
+    /// Whether the panorama was downloaded by this call
+    pub downloaded: bool,
+}
+
+/// A directory of downloaded panoramas.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rsstreetview::StreetView;
+/// # use rsstreetview::store::{PanoramaStore, StoreLayout};
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let store = PanoramaStore::new(StreetView::new(), "panoramas").layout(StoreLayout::Sharded);
+/// let pano = store.get_or_download("PANO_ID", 4).await?;
+/// println!("{} (downloaded: {})", pano.path.display(), pano.downloaded);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct PanoramaStore {
+    client: StreetView,
+    dir: PathBuf,
+    layout: StoreLayout,
+    save: SaveOptions,
+    #[cfg(feature = "sqlite")]
+    index: Option<Arc<PanoramaIndex>>,
+    #[cfg(feature = "privacy")]
+    redactor: Option<Redactor>,
+}
+
+impl PanoramaStore {
+    /// Create a store in `dir` that downloads missing panoramas with `client`.
+    ///
+    /// Panoramas are saved as JPEG by default.
+    pub fn new(client: StreetView, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            client,
+            dir: dir.into(),
+            layout: StoreLayout::default(),
+            save: SaveOptions::new().format(ImageFormat::Jpeg),
+            #[cfg(feature = "sqlite")]
+            index: None,
+            #[cfg(feature = "privacy")]
+            redactor: None,
+        }
+    }
+
+    /// Set the directory layout.
+    pub fn layout(mut self, layout: StoreLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Set the save options for downloaded panoramas.
+    ///
+    /// The format also determines the file extension looked up.
+    pub fn save_options(mut self, save: SaveOptions) -> Self {
+        self.save = save;
+        self
+    }
+
+    /// Record panoramas stored with
+    /// [`get_or_download_panorama`](Self::get_or_download_panorama) in an index.
+    #[cfg(feature = "sqlite")]
+    pub fn index(mut self, index: PanoramaIndex) -> Self {
+        self.index = Some(Arc::new(index));
+        self
+    }
+
+    /// The store's index, if any.
+    #[cfg(feature = "sqlite")]
+    pub fn panorama_index(&self) -> Option<&PanoramaIndex> {
+        self.index.as_deref()
+    }
+
+    /// Blur faces and license plates in downloaded panoramas before they
+    /// are saved.
+    #[cfg(feature = "privacy")]
+    pub fn redactor(mut self, redactor: Redactor) -> Self {
+        self.redactor = Some(redactor);
+        self
+    }
+
+    /// The client used for downloads.
+    pub fn client(&self) -> &StreetView {
+        &self.client
+    }
+
+    /// The store directory.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Path where a panorama is (or would be) stored.
+    pub fn path(&self, pano_id: &str, zoom: u8) -> PathBuf {
+        let ext = self.save.format.extension();
+        match self.layout {
+            StoreLayout::Flat => self.dir.join(format!("{pano_id}_z{zoom}.{ext}")),
+            StoreLayout::ByZoom => self.dir.join(format!("z{zoom}")).join(format!("{pano_id}.{ext}")),
+            StoreLayout::Sharded => {
+                let shard: String = pano_id.chars().take(2).collect();
+                self.dir.join(shard).join(format!("{pano_id}_z{zoom}.{ext}"))
+            }
+        }
+    }
+
+    /// Whether a panorama is already stored.
+    pub fn contains(&self, pano_id: &str, zoom: u8) -> bool {
+        self.path(pano_id, zoom).is_file()
+    }
+
+    /// Return the stored panorama, downloading and saving it first if it
+    /// is missing.
+    ///
+    /// Files are written under a temporary name and renamed into place, so
+    /// an interrupted download never leaves a partial file that would later
+    /// be mistaken for a stored panorama.
+    ///
+    /// The ID is validated first, so it can never point outside the store
+    /// directory. Downloads fail with `StreetViewError::PolicyViolation`
+    /// when the client's policy sets `no_face_storage` and the store has no
+    /// redactor that detects faces.
+    pub async fn get_or_download(&self, pano_id: impl Into<PanoId>, zoom: u8) -> Result<StoredPanorama> {
+        let pano_id = PanoId::new(pano_id)?;
+        let path = self.path(pano_id.as_str(), zoom);
+
+        if path.is_file() {
+            let image = load_image(path.clone()).await?;
+            return Ok(StoredPanorama { path, image, downloaded: false });
+        }
+
+        #[cfg(feature = "privacy")]
+        let faces_removed = self.redactor.as_ref().is_some_and(Redactor::removes_faces);
+        #[cfg(not(feature = "privacy"))]
+        let faces_removed = false;
+        self.client.policy().check_storage(faces_removed)?;
+
+        let image = self.client.download_panorama(&pano_id, zoom).await?;
+        #[cfg(feature = "privacy")]
+        let image = match &self.redactor {
+            Some(redactor) => redactor.redact_async(image).await?,
+            None => image,
+        };
+        let tmp = path.with_extension(format!("{}.part", self.save.format.extension()));
+        save_panorama_async(image.clone(), &tmp, &self.save).await?;
+        tokio::fs::rename(&tmp, &path).await?;
+
+        Ok(StoredPanorama { path, image, downloaded: true })
+    }
+
+    /// Like [`get_or_download`](Self::get_or_download) for a search result.
+    ///
+    /// With an index attached, the panorama's location, date, file size,
+    /// and checksum are recorded whenever it is downloaded or missing from
+    /// the index.
+    pub async fn get_or_download_panorama(&self, pano: &Panorama, zoom: u8) -> Result<StoredPanorama> {
+        let stored = self.get_or_download(&pano.pano_id, zoom).await?;
+
+        #[cfg(feature = "sqlite")]
+        if let Some(index) = &self.index {
+            if stored.downloaded || index.get(&pano.pano_id, zoom)?.is_none() {
+                index.insert(&IndexEntry::from_file(pano, zoom, &stored.path)?)?;
+            }
+        }
+
+        Ok(stored)
+    }
+}
+
+/// Decode an image file on the blocking thread pool.
+async fn load_image(path: PathBuf) -> Result<DynamicImage> {
+    tokio::task::spawn_blocking(move || Ok(image::open(path)?))
+        .await
+        .map_err(|e| StreetViewError::IoError(std::io::Error::other(e)))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_paths() {
+        let store = PanoramaStore::new(StreetView::new(), "out");
+        assert_eq!(store.path("AbCd", 4), PathBuf::from("out/AbCd_z4.jpg"));
+
+        let store = store.layout(StoreLayout::ByZoom);
+        assert_eq!(store.path("AbCd", 4), PathBuf::from("out/z4/AbCd.jpg"));
+
+        let store = store
+            .layout(StoreLayout::Sharded)
+            .save_options(SaveOptions::new().format(ImageFormat::Png));
+        assert_eq!(store.path("AbCd", 4), PathBuf::from("out/Ab/AbCd_z4.png"));
+    }
+
+    #[tokio::test]
+    async fn test_get_stored_panorama() {
+        let dir = std::env::temp_dir().join("rsstreetview_store");
+        let _ = std::fs::remove_dir_all(&dir);
+        let store = PanoramaStore::new(StreetView::new(), &dir);
+        assert!(!store.contains("PANO000000000000000000", 3));
+
+        let image = DynamicImage::new_rgb8(8, 4);
+        save_panorama_async(image, store.path("PANO000000000000000000", 3), &store.save).await.unwrap();
+        assert!(store.contains("PANO000000000000000000", 3));
+
+        // Present on disk, so no download is attempted
+        let pano = store.get_or_download("PANO000000000000000000", 3).await.unwrap();
+        assert!(!pano.downloaded);
+        assert_eq!((pano.image.width(), pano.image.height()), (8, 4));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_no_face_storage_policy() {
+        let client = StreetView::new().with_policy(crate::Policy::new().no_face_storage(true));
+        let store = PanoramaStore::new(client, std::env::temp_dir().join("rsstreetview_store_policy"));
+        let err = store.get_or_download("PANO000000000000000000", 3).await.unwrap_err();
+        assert!(matches!(err, StreetViewError::PolicyViolation(_)));
+    }
+
+    #[cfg(feature = "privacy")]
+    #[tokio::test]
+    async fn test_no_face_storage_rejects_empty_redactor() {
+        let client = StreetView::new().with_policy(crate::Policy::new().no_face_storage(true));
+        let store = PanoramaStore::new(client, std::env::temp_dir().join("rsstreetview_store_policy"))
+            .redactor(Redactor::new());
+        let err = store.get_or_download("PANO000000000000000000", 3).await.unwrap_err();
+        assert!(matches!(err, StreetViewError::PolicyViolation(_)));
+    }
+}
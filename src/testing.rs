@@ -0,0 +1,790 @@
+//! Offline test doubles for code that uses [`StreetView`](crate::StreetView).
+//!
+//! A [`MockTransport`] installed with
+//! [`StreetViewBuilder::mock_transport`](crate::StreetViewBuilder::mock_transport)
+//! answers requests with canned responses instead of going to the network.
+//! Rate limiting, caching, quotas, and error handling still run as usual, so
+//! downstream crates can unit-test their own logic end to end.
+//! [`fixtures`] builds response bodies in the formats Google returns.
+//!
+//! Requires the `testing` feature.
+//!
+//! # Example
+//!
+//! ```
+//! # #[cfg(all(feature = "unofficial", feature = "testing"))]
+//! # #[tokio::main]
+//! # async fn main() -> rsstreetview::Result<()> {
+//! use rsstreetview::testing::{fixtures, MockResponse, MockTransport};
+//! use rsstreetview::StreetView;
+//!
+//! let pano = fixtures::panorama("PANO_ID", 41.9, 12.5);
+//! let mock = MockTransport::new()
+//!     .route("SingleImageSearch", MockResponse::ok(fixtures::search_response(&[pano])))
+//!     .route("output=tile", MockResponse::image(fixtures::tile_image()));
+//! let client = StreetView::builder().mock_transport(mock.clone()).build()?;
+//!
+//! let panos = client.search_panoramas(41.9, 12.5).await?;
+//! assert_eq!(panos[0].pano_id, "PANO_ID");
+//! assert_eq!(mock.requests().len(), 1);
+//! # Ok(())
+//! # }
+//! # #[cfg(not(all(feature = "unofficial", feature = "testing")))]
+//! # fn main() {}
+//! ```
+
+use crate::error::{Result, StreetViewError};
+use crate::transport::{HttpResponse, HttpTransport};
+use futures::future::BoxFuture;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Request, StatusCode, Version};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// A canned HTTP response.
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+    delay: Duration,
+}
+
+impl MockResponse {
+    /// A response with the given status code and body.
+    pub fn new(status: u16, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.into(),
+            delay: Duration::ZERO,
+        }
+    }
+
+    /// A `200 OK` response.
+    pub fn ok(body: impl Into<Vec<u8>>) -> Self {
+        Self::new(200, body)
+    }
+
+    /// A `200 OK` JSON response.
+    pub fn json(body: impl Into<Vec<u8>>) -> Self {
+        Self::ok(body).header("content-type", "application/json")
+    }
+
+    /// A `200 OK` JPEG response.
+    pub fn image(bytes: impl Into<Vec<u8>>) -> Self {
+        Self::ok(bytes).header("content-type", "image/jpeg")
+    }
+
+    /// Add a response header.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Answer only after `delay`, e.g. to test [`Timeouts`](crate::Timeouts).
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    fn to_response(&self) -> Result<HttpResponse> {
+        let invalid = |what: String| StreetViewError::ParseError(format!("Invalid mock {what}"));
+
+        let status = StatusCode::from_u16(self.status).map_err(|_| invalid(format!("status {}", self.status)))?;
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid(format!("header {name:?}")))?;
+            let value = HeaderValue::from_str(value).map_err(|_| invalid(format!("header value {value:?}")))?;
+            headers.append(name, value);
+        }
+        Ok(HttpResponse {
+            status,
+            version: Version::HTTP_11,
+            headers,
+            body: self.body.clone(),
+        })
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    routes: Vec<(String, MockResponse)>,
+    requests: Vec<String>,
+}
+
+/// Serves canned responses by URL and records the requests made.
+///
+/// Each request gets the response of the first route whose pattern occurs
+/// in the URL; unmatched requests get a `404 Not Found`. Clones share their
+/// routes and request log, so keep a clone to inspect requests after
+/// handing one to the builder.
+#[derive(Clone, Default)]
+pub struct MockTransport {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockTransport {
+    /// A transport with no routes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer requests whose URL contains `pattern` with `response`.
+    pub fn route(self, pattern: impl Into<String>, response: MockResponse) -> Self {
+        self.add_route(pattern, response);
+        self
+    }
+
+    /// Add a route to a transport that is already in use.
+    ///
+    /// Routes are matched in the order they were added.
+    pub fn add_route(&self, pattern: impl Into<String>, response: MockResponse) {
+        self.lock().routes.push((pattern.into(), response));
+    }
+
+    /// URLs of all requests made so far, in order.
+    pub fn requests(&self) -> Vec<String> {
+        self.lock().requests.clone()
+    }
+
+    fn respond(&self, url: &str) -> (Result<HttpResponse>, Duration) {
+        let mut state = self.lock();
+        state.requests.push(url.to_string());
+        match state.routes.iter().find(|(pattern, _)| url.contains(pattern.as_str())) {
+            Some((_, response)) => (response.to_response(), response.delay),
+            None => (MockResponse::new(404, "Not Found").to_response(), Duration::ZERO),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl HttpTransport for MockTransport {
+    fn execute(&self, request: Request) -> BoxFuture<'_, Result<HttpResponse>> {
+        let (response, delay) = self.respond(request.url().as_str());
+        Box::pin(async move {
+            if !delay.is_zero() {
+                tokio::time::sleep(delay).await;
+            }
+            response
+        })
+    }
+}
+
+/// Response bodies in the formats Google's endpoints return.
+pub mod fixtures {
+    use crate::types::{DateConfidence, Panorama};
+    use image::{DynamicImage, ImageFormat, Rgb, RgbImage};
+    use serde_json::{json, Value};
+    use std::io::Cursor;
+
+    /// Side length of a panorama tile.
+    pub const TILE_SIZE: u32 = 512;
+
+    /// A panorama with the given ID and position and no optional fields.
+    pub fn panorama(pano_id: &str, lat: f64, lon: f64) -> Panorama {
+        Panorama {
+            pano_id: pano_id.to_string(),
+            lat,
+            lon,
+            heading: 0.0,
+            pitch: None,
+            roll: None,
+            date: None,
+            date_confidence: DateConfidence::None,
+            elevation: None,
+            copyright: None,
+            street_name: None,
+        }
+    }
+
+    /// A search endpoint response that returns `panos` in order.
+    ///
+    /// Each date names its panorama by index, as in Google's responses.
+    pub fn search_response(panos: &[Panorama]) -> String {
+        let entries: Vec<Value> = panos
+            .iter()
+            .rev()
+            .map(|pano| {
+                json!([
+                    [2, pano.pano_id],
+                    null,
+                    [[null, null, pano.lat, pano.lon], null, [pano.heading, pano.pitch, pano.roll]],
+                    pano.elevation.map(|e| json!([e]))
+                ])
+            })
+            .collect();
+
+        // Panoramas are listed in reverse, so pano `i` is entry `len - 1 - i`
+        let dates: Vec<Value> = panos
+            .iter()
+            .enumerate()
+            .filter_map(|(i, pano)| Some(json!([[panos.len() - 1 - i], [pano.date?.year, pano.date?.month]])))
+            .rev()
+            .collect();
+
+        let result = json!([0, 0, 0, [entries], 0, 0, 0, 0, dates]);
+        format!("callbackfunc({})", json!([[0], [0, 0, 0, 0, 0, [result]]]))
+    }
+
+    /// A search endpoint response for a location with no panoramas.
+    pub fn empty_search_response() -> String {
+        "callbackfunc([[5,\"Search returned no images.\"]])".to_string()
+    }
+
+    /// An official metadata API response for `pano`.
+    ///
+    /// Uses `2020-01` if the panorama has no date, since the API always
+    /// returns one.
+    pub fn metadata_response(pano: &Panorama) -> String {
+        json!({
+            "status": "OK",
+            "pano_id": pano.pano_id,
+            "location": { "lat": pano.lat, "lng": pano.lon },
+            "date": pano.date.map_or_else(|| "2020-01".to_string(), |date| date.to_string()),
+            "copyright": "© Google",
+        })
+        .to_string()
+    }
+
+    /// An official API error response, e.g. `"ZERO_RESULTS"` or
+    /// `"REQUEST_DENIED"`.
+    pub fn api_status_response(status: &str) -> String {
+        json!({ "status": status }).to_string()
+    }
+
+    /// A JPEG-encoded panorama tile.
+    pub fn tile_image() -> Vec<u8> {
+        jpeg(TILE_SIZE, TILE_SIZE)
+    }
+
+    /// A JPEG-encoded Static API image.
+    pub fn static_image(width: u32, height: u32) -> Vec<u8> {
+        jpeg(width, height)
+    }
+
+    /// A Static API "Sorry, we have no imagery here" placeholder image.
+    pub fn no_imagery_image(width: u32, height: u32) -> Vec<u8> {
+        let image = RgbImage::from_fn(width, height, |x, y| {
+            // A dark band standing in for the message text
+            let text = y.abs_diff(height / 2) < height / 20 && x.abs_diff(width / 2) < width / 3;
+            Rgb(if text { [110, 110, 110] } else { [228, 227, 223] })
+        });
+        let mut bytes = Vec::new();
+        DynamicImage::ImageRgb8(image)
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Jpeg)
+            .expect("encoding to memory cannot fail");
+        bytes
+    }
+
+    fn jpeg(width: u32, height: u32) -> Vec<u8> {
+        let image = RgbImage::from_fn(width, height, |x, y| Rgb([(x % 256) as u8, (y % 256) as u8, 128]));
+        let mut bytes = Vec::new();
+        DynamicImage::ImageRgb8(image)
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Jpeg)
+            .expect("encoding to memory cannot fail");
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fixtures::*;
+    use super::*;
+    use crate::{CaptureDate, PanoId, StaticViewOptions, StreetView};
+
+    fn mock_client(mock: &MockTransport) -> StreetView {
+        StreetView::builder()
+            .api_key("KEY")
+            .mock_transport(mock.clone())
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_unmatched_request() {
+        let mock = MockTransport::new().route("metadata", MockResponse::json(api_status_response("ZERO_RESULTS")));
+        let client = mock_client(&mock);
+
+        let result = client.get_panorama_meta("PANO000000000000000000").await;
+        assert!(matches!(result, Err(StreetViewError::NoPanoramasFound)));
+        assert!(client.get_streetview("PANO000000000000000000", 64, 64, 0, 90, 0).await.is_err());
+
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].contains("pano=PANO000000000000000000"));
+    }
+
+    #[tokio::test]
+    async fn test_official_fixtures() {
+        let mut pano = panorama("PANO000000000000000000", 48.85, 2.35);
+        pano.date = CaptureDate::new(2019, 6);
+        let mock = MockTransport::new()
+            .route("metadata", MockResponse::json(metadata_response(&pano)))
+            .route("streetview?", MockResponse::image(static_image(64, 32)));
+        let client = mock_client(&mock);
+
+        let meta = client.get_panorama_meta("PANO000000000000000000").await.unwrap();
+        assert_eq!((meta.pano_id.as_str(), meta.date.as_str()), ("PANO000000000000000000", "2019-06"));
+        assert_eq!(meta.location.lat, 48.85);
+
+        let image = client.get_streetview("PANO000000000000000000", 64, 32, 0, 90, 0).await.unwrap();
+        assert_eq!((image.width(), image.height()), (64, 32));
+
+        let mock = MockTransport::new().route("streetview?", MockResponse::image(no_imagery_image(640, 640)));
+        let options = StaticViewOptions::pano("B000000000000000000000");
+        let result = mock_client(&mock).get_streetview_opts(&options).await;
+        assert!(matches!(result, Err(StreetViewError::NoImagery)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_meta_batch() {
+        let pano = panorama("A000000000000000000000", 48.85, 2.35);
+        let mock = MockTransport::new()
+            .route("pano=A000000000000000000000", MockResponse::json(metadata_response(&pano)))
+            .route("metadata", MockResponse::json(api_status_response("ZERO_RESULTS")));
+        let client = mock_client(&mock);
+
+        let ids = ["A000000000000000000000", "B000000000000000000000", "A000000000000000000000", "bad id"];
+        let start = tokio::time::Instant::now();
+        let results = client.get_panorama_meta_batch(&ids, 10.0).await.unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[&PanoId::from("A000000000000000000000")].as_ref().unwrap().location.lat, 48.85);
+        assert!(matches!(results[&PanoId::from("B000000000000000000000")], Err(StreetViewError::NoPanoramasFound)));
+        assert!(matches!(results[&PanoId::from("bad id")], Err(StreetViewError::InvalidPanoId(_))));
+        // Two requests, 100 ms apart
+        assert_eq!(mock.requests().len(), 2);
+        assert_eq!(start.elapsed(), std::time::Duration::from_millis(100));
+
+        let no_key = StreetView::builder().mock_transport(mock).build().unwrap();
+        assert!(matches!(no_key.get_panorama_meta_batch(&ids, 0.0).await, Err(StreetViewError::MissingApiKey)));
+    }
+
+    #[cfg(feature = "unofficial")]
+    #[tokio::test]
+    async fn test_unofficial_fixtures() {
+        let mut first = panorama("A000000000000000000000", 41.9, 12.5);
+        first.date = CaptureDate::new(2021, 3);
+        first.elevation = Some(30.0);
+        let second = panorama("B000000000000000000000", 41.9001, 12.5001);
+
+        let mock = MockTransport::new()
+            .route("SingleImageSearch", MockResponse::ok(search_response(&[first, second])))
+            .route("output=tile", MockResponse::image(tile_image()));
+        let client = mock_client(&mock);
+
+        let panos = client.search_panoramas(41.9, 12.5).await.unwrap();
+        assert_eq!(panos.len(), 2);
+        assert_eq!(panos[0].pano_id, "A000000000000000000000");
+        assert_eq!(panos[0].date, CaptureDate::new(2021, 3));
+        assert_eq!(panos[0].elevation, Some(30.0));
+        assert_eq!((panos[1].pano_id.as_str(), panos[1].lat), ("B000000000000000000000", 41.9001));
+
+        let image = client.download_panorama("A000000000000000000000", 1).await.unwrap();
+        assert_eq!((image.width(), image.height()), (2 * TILE_SIZE, TILE_SIZE));
+
+        let mock = MockTransport::new().route("SingleImageSearch", MockResponse::ok(empty_search_response()));
+        assert!(mock_client(&mock).search_panoramas(0.0, 0.0).await.unwrap().is_empty());
+    }
+
+    #[cfg(feature = "unofficial")]
+    #[tokio::test]
+    async fn test_search_stream() {
+        use futures::StreamExt;
+
+        let [a, b, c] = ["A", "B", "C"].map(|id| panorama(&format!("{id}000000000000000000000"), 1.0, 1.0));
+        let mock = MockTransport::new()
+            .route("!3d1!", MockResponse::ok(search_response(&[a, b.clone()])))
+            .route("!3d2!", MockResponse::ok(search_response(&[b, c])))
+            .route("!3d3!", MockResponse::ok(empty_search_response()));
+        let client = mock_client(&mock);
+
+        // Point 4 has no route, so its search fails
+        let points = [(1.0, 1.0), (2.0, 2.0), (3.0, 3.0), (4.0, 4.0)];
+        let results: Vec<_> = client.search_stream(points, 1).collect().await;
+        let ids: Vec<_> = results.iter().filter_map(|r| r.as_ref().ok()).map(|p| &p.pano_id[..1]).collect();
+        assert_eq!(ids, ["A", "B", "C"]);
+        assert_eq!(results.iter().filter(|r| r.is_err()).count(), 1);
+        assert_eq!(mock.requests().len(), 4);
+    }
+
+    #[cfg(feature = "unofficial")]
+    #[tokio::test]
+    async fn test_offline() {
+        let dir = std::env::temp_dir().join("rsstreetview_offline_cache");
+        let _ = std::fs::remove_dir_all(&dir);
+        let cache = crate::DiskCache::new(&dir).unwrap();
+        let panos = [panorama("A000000000000000000000", 41.9, 12.5)];
+        let mock = MockTransport::new()
+            .route("SingleImageSearch", MockResponse::ok(search_response(&panos)))
+            .route("output=tile", MockResponse::image(tile_image()));
+
+        // Warm the cache, then replay it without the network
+        let online = StreetView::builder().mock_transport(mock.clone()).cache(cache.clone()).build().unwrap();
+        online.search_panoramas(41.9, 12.5).await.unwrap();
+        let offline = StreetView::builder()
+            .mock_transport(mock.clone())
+            .cache(cache)
+            .offline(true)
+            .build()
+            .unwrap();
+        assert_eq!(offline.search_panoramas(41.9, 12.5).await.unwrap().len(), 1);
+
+        let result = offline.search_panoramas(48.85, 2.35).await;
+        assert!(matches!(result, Err(StreetViewError::OfflineCacheMiss { url }) if url.contains("48.85")));
+        let result = offline.download_panorama("A000000000000000000000", 1).await;
+        assert!(matches!(result, Err(StreetViewError::OfflineCacheMiss { .. })));
+        assert_eq!(mock.requests().len(), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(feature = "unofficial")]
+    #[tokio::test]
+    async fn test_typed_errors() {
+        let mock = MockTransport::new()
+            .route("output=tile", MockResponse::new(400, "Bad Request"))
+            .route("SingleImageSearch", MockResponse::new(429, "slow down").header("retry-after", "7"))
+            .route("photometa", MockResponse::new(429, "<a href=\"https://www.google.com/sorry/index\">"));
+        let client = mock_client(&mock);
+
+        match client.download_panorama("A000000000000000000000", 1).await {
+            Err(StreetViewError::TileNotFound { zoom: 1, body, .. }) => assert_eq!(body, "Bad Request"),
+            other => panic!("unexpected {other:?}"),
+        }
+        // Missing tiles are not retried
+        assert_eq!(mock.requests().len(), 2);
+
+        match client.search_panoramas(41.9, 12.5).await {
+            Err(StreetViewError::RateLimited { retry_after, body }) => {
+                assert_eq!(retry_after, Some(std::time::Duration::from_secs(7)));
+                assert_eq!(body, "slow down");
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+        assert!(matches!(
+            client.get_photometa("A000000000000000000000").await,
+            Err(StreetViewError::Blocked { status: 429, .. })
+        ));
+    }
+
+    #[cfg(feature = "unofficial")]
+    #[tokio::test]
+    async fn test_partial_download() {
+        use image::GenericImageView;
+
+        let mock = MockTransport::new()
+            .route("x=1&y=0", MockResponse::new(400, "Bad Request"))
+            .route("output=tile", MockResponse::image(tile_image()));
+        let client = mock_client(&mock);
+
+        let mut pano = client.download_panorama_partial("A000000000000000000000", 1).await.unwrap();
+        assert!(!pano.is_complete());
+        assert_eq!(pano.missing_tiles.len(), 1);
+        let missing = &pano.missing_tiles[0];
+        assert_eq!((missing.x, missing.y), (1, 0));
+        assert!(matches!(missing.error, StreetViewError::TileNotFound { x: 1, y: 0, .. }));
+
+        let pixel = |pano: &crate::PartialPanorama| pano.image.get_pixel(TILE_SIZE + 10, 10).0;
+        assert_eq!(pixel(&pano), [0, 0, 0, 255]);
+        pano.fill_missing(image::Rgb([255, 0, 0]));
+        assert_eq!(pixel(&pano), [255, 0, 0, 255]);
+
+        let mock = MockTransport::new().route("output=tile", MockResponse::new(404, ""));
+        let result = mock_client(&mock).download_panorama_partial("A000000000000000000000", 1).await;
+        assert!(matches!(result, Err(StreetViewError::TileNotFound { x: 0, y: 0, .. })));
+    }
+
+    #[cfg(feature = "unofficial")]
+    #[tokio::test]
+    async fn test_priority_download() {
+        use crate::{DownloadOptions, ViewConfig};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let mock = MockTransport::new().route("output=tile", MockResponse::image(tile_image()));
+        let client = mock_client(&mock);
+        let ready = Arc::new(AtomicUsize::new(0));
+        let seen = ready.clone();
+        let options = DownloadOptions::new()
+            .zoom(2)
+            .concurrency(1)
+            .prioritize_view(ViewConfig::new(359).fov(10))
+            .on_priority_ready(move |_| {
+                seen.fetch_add(1, Ordering::SeqCst);
+            });
+
+        let image = client.download_panorama_with("A000000000000000000000", &options).await.unwrap();
+        assert_eq!((image.width(), image.height()), (4 * TILE_SIZE, 2 * TILE_SIZE));
+        assert_eq!(ready.load(Ordering::SeqCst), 1);
+        // The view's tile is requested first
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 8);
+        assert!(requests[0].contains("x=3&y=0") || requests[0].contains("x=3&y=1"), "{}", requests[0]);
+    }
+
+    #[cfg(feature = "unofficial")]
+    #[tokio::test(start_paused = true)]
+    async fn test_timeouts() {
+        use crate::{RetryPolicy, Stage, Timeouts};
+
+        let slow = Duration::from_secs(45);
+        let mock = MockTransport::new()
+            .route("SingleImageSearch", MockResponse::ok("").delay(slow))
+            .route("output=tile", MockResponse::image(tile_image()).delay(slow));
+        let client = StreetView::builder()
+            .timeouts(Timeouts::new().tile(Duration::from_secs(10)))
+            .retry(RetryPolicy::none())
+            .mock_transport(mock.clone())
+            .build()
+            .unwrap();
+
+        let error = client.search_panoramas(41.9, 12.5).await.unwrap_err();
+        let StreetViewError::Timeout { stage, elapsed } = error else { panic!("{error}") };
+        assert_eq!((stage, elapsed), (Stage::Search, Duration::from_secs(30)));
+
+        let error = client.download_panorama("A000000000000000000000", 1).await.unwrap_err();
+        assert!(matches!(error, StreetViewError::Timeout { stage: Stage::Tile, .. }), "{error}");
+        assert_eq!(error.to_string(), "tile request timed out after 10.0s");
+
+        // Without limits the slow tiles arrive
+        let client = StreetView::builder()
+            .timeouts(Timeouts::none())
+            .mock_transport(mock)
+            .build()
+            .unwrap();
+        assert!(client.download_panorama("A000000000000000000000", 1).await.is_ok());
+    }
+
+    #[cfg(feature = "unofficial")]
+    #[tokio::test]
+    async fn test_tile_shards() {
+        use crate::Endpoints;
+
+        let mock = MockTransport::new().route("output=tile", MockResponse::image(tile_image()));
+        let client = StreetView::builder()
+            .endpoints(Endpoints::new().google_tile_shards())
+            .mock_transport(mock.clone())
+            .build()
+            .unwrap();
+        client.download_panorama("A000000000000000000000", 2).await.unwrap();
+
+        let requests = mock.requests();
+        for host in ["cbk0", "cbk1", "cbk2", "cbk3"] {
+            let count = requests.iter().filter(|url| url.starts_with(&format!("https://{host}."))).count();
+            assert_eq!(count, 2, "{host}");
+        }
+    }
+
+    #[cfg(feature = "unofficial")]
+    #[tokio::test]
+    async fn test_tile_order_download() {
+        use crate::{DownloadOptions, TileOrder};
+
+        let mock = MockTransport::new().route("output=tile", MockResponse::image(tile_image()));
+        let client = mock_client(&mock);
+        let options = DownloadOptions::new().zoom(2).concurrency(1).tile_order(TileOrder::ColumnMajor);
+        client.download_panorama_with("A000000000000000000000", &options).await.unwrap();
+
+        let requests = mock.requests();
+        let tiles: Vec<&str> = requests.iter().map(|url| &url[url.rfind("&x=").unwrap() + 1..]).collect();
+        assert_eq!(tiles[..4], ["x=0&y=0", "x=0&y=1", "x=1&y=0", "x=1&y=1"]);
+    }
+
+    #[cfg(feature = "unofficial")]
+    #[tokio::test]
+    async fn test_panorama_tiles() {
+        use crate::ViewConfig;
+        use image::GenericImageView;
+
+        let mock = MockTransport::new().route("output=tile", MockResponse::image(tile_image()));
+        let client = mock_client(&mock);
+
+        let tiles = client.fetch_panorama_tiles("A000000000000000000000", 2).await.unwrap();
+        assert_eq!((tiles.grid(), tiles.dimensions()), ((4, 2), (2048, 1024)));
+        assert_eq!(tiles.tile_bytes(3, 1), Some(&tile_image()[..]));
+        assert!(tiles.tile_bytes(4, 0).is_none());
+
+        let panorama = tiles.assemble().unwrap();
+        assert_eq!(panorama.dimensions(), (2048, 1024));
+        // Crops across tile edges match cropping the assembled panorama
+        let crop = tiles.crop(500, 400, 600, 300).unwrap();
+        assert_eq!(crop.to_rgb8(), panorama.crop_imm(500, 400, 600, 300).to_rgb8());
+        let config = ViewConfig::new(90).fov(60).size(64, 32);
+        let view = tiles.extract_view(&config).unwrap();
+        assert_eq!(view, crate::views::extract_view_from_panorama(&panorama, &config).unwrap());
+    }
+
+    #[cfg(feature = "unofficial")]
+    #[tokio::test]
+    async fn test_export_colmap() {
+        use crate::{ColmapOptions, ViewConfig};
+
+        let dir = std::env::temp_dir().join("rsstreetview_colmap");
+        let _ = std::fs::remove_dir_all(&dir);
+        let mock = MockTransport::new().route("output=tile", MockResponse::image(tile_image()));
+        let client = mock_client(&mock);
+        let panos = [panorama("A000000000000000000000", 48.0, 2.0), panorama("B000000000000000000000", 48.0001, 2.0)];
+        let options = ColmapOptions::new()
+            .out_dir(&dir)
+            .views(vec![
+                ("front".to_string(), ViewConfig::new(180).zoom(1).size(64, 64)),
+                ("back".to_string(), ViewConfig::new(0).zoom(1).size(64, 64)),
+            ]);
+
+        let model = client.export_colmap(&panos, &options).await.unwrap();
+        assert_eq!((model.cameras.len(), model.images.len()), (2, 4));
+        assert_eq!(model.images[3].name, "B000000000000000000000_back.jpg");
+        assert!(dir.join("images/A000000000000000000000_front.jpg").is_file());
+
+        let images = std::fs::read_to_string(dir.join("sparse/0/images.txt")).unwrap();
+        let lines: Vec<&str> = images.lines().filter(|line| !line.starts_with('#') && !line.is_empty()).collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[2].starts_with("3 ") && lines[2].ends_with(" 1 B000000000000000000000_front.jpg"));
+        let cameras = std::fs::read_to_string(dir.join("sparse/0/cameras.txt")).unwrap();
+        assert!(cameras.contains("\n2 PINHOLE 64 64 "));
+        assert!(dir.join("sparse/0/points3D.txt").is_file());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(feature = "unofficial")]
+    #[tokio::test]
+    async fn test_export_tile_pyramid() {
+        let dir = std::env::temp_dir().join("rsstreetview_pyramid");
+        let _ = std::fs::remove_dir_all(&dir);
+        let mock = MockTransport::new().route("output=tile", MockResponse::image(tile_image()));
+        let client = mock_client(&mock);
+
+        let pyramid = client.export_tile_pyramid("A000000000000000000000", &dir, 1..=2).await.unwrap();
+        assert_eq!(pyramid.levels.iter().map(|level| level.cols * level.rows).sum::<u32>(), 10);
+        assert_eq!((pyramid.downloaded, pyramid.existing), (10, 0));
+        // Tiles are saved as sent, without re-encoding
+        assert_eq!(std::fs::read(pyramid.tile_path(2, 3, 1)).unwrap(), tile_image());
+        assert!(dir.join("pyramid.json").is_file());
+
+        let again = client.export_tile_pyramid("A000000000000000000000", &dir, 1..=3).await.unwrap();
+        assert_eq!((again.downloaded, again.existing), (32, 10));
+        assert_eq!(mock.requests().len(), 42);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(feature = "unofficial")]
+    const PHOTOMETA: &str = include_str!("../tests/fixtures/photometa/piazza_navona.txt");
+
+    /// The photometa fixture with its per-zoom image sizes replaced.
+    #[cfg(feature = "unofficial")]
+    fn photometa_with_sizes(sizes: &str) -> String {
+        let full = "[[[256,512]],[[512,1024]],[[1024,2048]],[[2048,4096]],[[4096,8192]],[[8192,16384]]]";
+        assert!(PHOTOMETA.contains(full));
+        PHOTOMETA.replace(full, sizes)
+    }
+
+    #[cfg(feature = "unofficial")]
+    #[tokio::test]
+    async fn test_max_zoom() {
+        let mock = MockTransport::new().route("photometa", MockResponse::ok(PHOTOMETA));
+        assert_eq!(mock_client(&mock).get_max_zoom("A000000000000000000000").await.unwrap(), 5);
+
+        // Without image sizes, probe tiles from zoom 7 down
+        let mock = MockTransport::new()
+            .route("photometa", MockResponse::ok(photometa_with_sizes("[]")))
+            .route("zoom=7", MockResponse::new(400, "Bad Request"))
+            .route("zoom=6", MockResponse::new(400, "Bad Request"))
+            .route("zoom=5", MockResponse::new(400, "Bad Request"))
+            .route("output=tile", MockResponse::image(tile_image()));
+        assert_eq!(mock_client(&mock).get_max_zoom("A000000000000000000000").await.unwrap(), 4);
+        assert_eq!(mock.requests().len(), 5);
+    }
+
+    #[cfg(feature = "unofficial")]
+    #[tokio::test]
+    async fn test_download_clamps_zoom() {
+        use crate::DownloadOptions;
+        use image::GenericImageView;
+
+        let mock = MockTransport::new()
+            .route("photometa", MockResponse::ok(photometa_with_sizes("[[[256,512]],[[512,1024]]]")))
+            .route("output=tile", MockResponse::image(tile_image()));
+        let client = mock_client(&mock);
+
+        let options = DownloadOptions::new().zoom(3).clamp_zoom(true);
+        let image = client.download_panorama_with("A000000000000000000000", &options).await.unwrap();
+        assert_eq!(image.dimensions(), (1024, 512));
+        assert!(mock.requests().iter().filter(|url| url.contains("output=tile")).all(|url| url.contains("zoom=1")));
+    }
+
+    #[cfg(feature = "unofficial")]
+    #[tokio::test]
+    async fn test_panorama_cache() {
+        use crate::{PanoramaCache, ViewConfig};
+
+        let mock = MockTransport::new().route("output=tile", MockResponse::image(tile_image()));
+        let client = mock_client(&mock).with_panorama_cache(PanoramaCache::new(64 << 20));
+        let pano_id = "A000000000000000000000";
+
+        client.prefetch_panorama(pano_id, 1).await.unwrap();
+        assert_eq!(mock.requests().len(), 2);
+        assert!(client.panorama_cache().unwrap().contains(pano_id, 1));
+
+        // Views and downloads at the same zoom are served from memory
+        client.prefetch_panorama(pano_id, 1).await.unwrap();
+        client.extract_view(pano_id, &ViewConfig::new(90).zoom(1)).await.unwrap();
+        client.extract_multiple_views(pano_id, &[ViewConfig::new(0).zoom(1)]).await.unwrap();
+        client.download_panorama(pano_id, 1).await.unwrap();
+        assert_eq!(mock.requests().len(), 2);
+
+        // Another zoom level is downloaded and cached separately
+        client.extract_view(pano_id, &ViewConfig::new(90).zoom(2)).await.unwrap();
+        assert_eq!(mock.requests().len(), 2 + 8);
+        assert_eq!(client.panorama_cache().unwrap().len(), 2);
+    }
+
+    #[cfg(feature = "unofficial")]
+    #[tokio::test]
+    async fn test_extract_views_batch() {
+        use crate::{BatchViewOptions, ViewConfig};
+        use futures::StreamExt;
+        use image::GenericImageView;
+
+        let mock = MockTransport::new().route("output=tile", MockResponse::image(tile_image()));
+        let client = mock_client(&mock);
+        let views = vec![ViewConfig::new(0).zoom(1).size(64, 64), ViewConfig::new(90).zoom(1).size(32, 32)];
+        let requests = vec![
+            (PanoId::from("A000000000000000000000"), views.clone()),
+            (PanoId::from("not a pano id"), views.clone()),
+            (PanoId::from("B000000000000000000000"), Vec::new()),
+            (PanoId::from("C000000000000000000000"), views),
+        ];
+
+        let options = BatchViewOptions::new().download_concurrency(2).extract_concurrency(1);
+        let mut results: Vec<_> = client.extract_views_batch(&requests, &options).collect().await;
+        results.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+
+        let ids: Vec<&str> = results.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, ["A000000000000000000000", "B000000000000000000000", "C000000000000000000000", "not a pano id"]);
+        let sizes: Vec<_> = results[0].1.as_ref().unwrap().iter().map(|view| view.dimensions()).collect();
+        assert_eq!(sizes, [(64, 64), (32, 32)]);
+        assert!(results[1].1.as_ref().unwrap().is_empty());
+        assert_eq!(results[2].1.as_ref().unwrap().len(), 2);
+        assert!(matches!(results[3].1, Err(StreetViewError::InvalidPanoId(_))));
+        // Two panoramas of 2 tiles each at zoom 1, and nothing for the others
+        assert_eq!(mock.requests().len(), 4);
+    }
+
+    #[cfg(feature = "unofficial")]
+    #[tokio::test]
+    async fn test_estimate_download() {
+        let mock = MockTransport::new().route("output=tile", MockResponse::image(tile_image()));
+        let client = mock_client(&mock);
+
+        let estimate = client.estimate_download("A000000000000000000000", 3).await.unwrap();
+        assert!(estimate.probed);
+        assert_eq!(estimate.tiles, 32);
+        assert_eq!(estimate.approx_bytes, tile_image().len() as u64 * 32);
+        // One request, for a tile on the horizon row
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].contains("zoom=3&x=0&y=2"));
+    }
+}
@@ -0,0 +1,283 @@
+//! HTTP transport: how requests are executed, retried, and timed out.
+
+use crate::endpoints::EndpointKind;
+use crate::error::Result;
+use futures::future::BoxFuture;
+use reqwest::header::HeaderMap;
+use reqwest::{Client, Request, StatusCode, Version};
+use std::fmt;
+use std::time::Duration;
+
+/// Internal: a fully read HTTP response.
+#[derive(Clone)]
+pub(crate) struct HttpResponse {
+    pub(crate) status: StatusCode,
+    #[cfg_attr(not(any(feature = "tracing", feature = "metrics")), allow(dead_code))]
+    pub(crate) version: Version,
+    pub(crate) headers: HeaderMap,
+    pub(crate) body: Vec<u8>,
+}
+
+/// Internal: executes built requests.
+///
+/// `Http` prepares every request (headers, signing, rate limiting) and hands
+/// it to a transport, so tests can swap the network for canned responses.
+pub(crate) trait HttpTransport: Send + Sync {
+    fn execute(&self, request: Request) -> BoxFuture<'_, Result<HttpResponse>>;
+}
+
+/// Internal: the network transport.
+pub(crate) struct ReqwestTransport {
+    client: Client,
+}
+
+impl ReqwestTransport {
+    pub(crate) fn new(client: Client) -> Self {
+        Self { client }
+    }
+}
+
+impl HttpTransport for ReqwestTransport {
+    fn execute(&self, request: Request) -> BoxFuture<'_, Result<HttpResponse>> {
+        Box::pin(async move {
+            let response = self.client.execute(request).await?;
+            let status = response.status();
+            let version = response.version();
+            let headers = response.headers().clone();
+            let body = response.bytes().await?.to_vec();
+            Ok(HttpResponse {
+                status,
+                version,
+                headers,
+                body,
+            })
+        })
+    }
+}
+
+/// How failed tile downloads are retried.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RetryPolicy {
+    /// Retries after the first attempt (default 6)
+    pub max_retries: u32,
+    /// Delay before the first retry (default 2 s)
+    pub delay: Duration,
+    /// Factor the delay grows by after each retry (default 1.0, constant)
+    pub backoff: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 6,
+            delay: Duration::from_secs(2),
+            backoff: 1.0,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Create the default retry policy.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Never retry.
+    pub fn none() -> Self {
+        Self::new().max_retries(0)
+    }
+
+    /// Set the number of retries.
+    pub fn max_retries(mut self, retries: u32) -> Self {
+        self.max_retries = retries;
+        self
+    }
+
+    /// Set the delay before the first retry.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Grow the delay by `factor` after each retry (exponential backoff).
+    pub fn backoff(mut self, factor: f64) -> Self {
+        self.backoff = factor.max(1.0);
+        self
+    }
+
+    /// Delay before retry number `retry` (starting at 0).
+    pub fn delay_for(&self, retry: u32) -> Duration {
+        self.delay.mul_f64(self.backoff.powi(retry as i32))
+    }
+}
+
+/// Kind of request a [`Timeouts`] limit applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Stage {
+    /// Panorama search
+    Search,
+    /// Panorama tile
+    Tile,
+    /// Photometa, official metadata, and geocoding
+    Metadata,
+    /// Official Static API image
+    StaticImage,
+}
+
+impl From<EndpointKind> for Stage {
+    fn from(kind: EndpointKind) -> Self {
+        match kind {
+            #[cfg(feature = "unofficial")]
+            EndpointKind::Search => Stage::Search,
+            #[cfg(feature = "unofficial")]
+            EndpointKind::Tile => Stage::Tile,
+            #[cfg(feature = "unofficial")]
+            EndpointKind::Photometa => Stage::Metadata,
+            EndpointKind::Metadata | EndpointKind::Geocode => Stage::Metadata,
+            EndpointKind::StaticImage => Stage::StaticImage,
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Stage::Search => "search",
+            Stage::Tile => "tile",
+            Stage::Metadata => "metadata",
+            Stage::StaticImage => "static image",
+        })
+    }
+}
+
+/// How long a single request may take, per [`Stage`], before it fails with
+/// `StreetViewError::Timeout`.
+///
+/// Limits apply to each attempt, from sending the request to reading the
+/// whole body; waiting for a rate limit slot is not counted. A timed out
+/// tile is retried like any other failed tile. Timeouts set on a custom
+/// reqwest client still apply and fail with `StreetViewError::HttpError`.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rsstreetview::{StreetView, Timeouts};
+/// # use std::time::Duration;
+/// # fn main() -> rsstreetview::Result<()> {
+/// let client = StreetView::builder()
+///     .timeouts(Timeouts::new().search(Duration::from_secs(10)).tile(Duration::from_secs(20)))
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Timeouts {
+    /// Limit for searches (default 30 s)
+    pub search: Option<Duration>,
+    /// Limit for each tile (default 60 s)
+    pub tile: Option<Duration>,
+    /// Limit for metadata and geocoding requests (default 30 s)
+    pub metadata: Option<Duration>,
+    /// Limit for Static API images (default 60 s)
+    pub static_image: Option<Duration>,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            search: Some(Duration::from_secs(30)),
+            tile: Some(Duration::from_secs(60)),
+            metadata: Some(Duration::from_secs(30)),
+            static_image: Some(Duration::from_secs(60)),
+        }
+    }
+}
+
+impl Timeouts {
+    /// Create the default timeouts.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// No limits: requests take as long as the reqwest client allows.
+    pub fn none() -> Self {
+        Self {
+            search: None,
+            tile: None,
+            metadata: None,
+            static_image: None,
+        }
+    }
+
+    /// Use the same limit for every stage.
+    pub fn all(limit: Duration) -> Self {
+        Self {
+            search: Some(limit),
+            tile: Some(limit),
+            metadata: Some(limit),
+            static_image: Some(limit),
+        }
+    }
+
+    /// Set the limit for searches.
+    pub fn search(mut self, limit: Duration) -> Self {
+        self.search = Some(limit);
+        self
+    }
+
+    /// Set the limit for each tile.
+    pub fn tile(mut self, limit: Duration) -> Self {
+        self.tile = Some(limit);
+        self
+    }
+
+    /// Set the limit for metadata and geocoding requests.
+    pub fn metadata(mut self, limit: Duration) -> Self {
+        self.metadata = Some(limit);
+        self
+    }
+
+    /// Set the limit for Static API images.
+    pub fn static_image(mut self, limit: Duration) -> Self {
+        self.static_image = Some(limit);
+        self
+    }
+
+    /// The limit for `stage`, if any.
+    pub fn get(&self, stage: Stage) -> Option<Duration> {
+        match stage {
+            Stage::Search => self.search,
+            Stage::Tile => self.tile,
+            Stage::Metadata => self.metadata,
+            Stage::StaticImage => self.static_image,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_backoff() {
+        let retry = RetryPolicy::new().delay(Duration::from_millis(100)).backoff(2.0);
+        assert_eq!(retry.delay_for(0), Duration::from_millis(100));
+        assert_eq!(retry.delay_for(3), Duration::from_millis(800));
+        assert_eq!(RetryPolicy::new().delay_for(5), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_timeouts() {
+        assert_eq!(Stage::from(EndpointKind::Geocode), Stage::Metadata);
+        let timeouts = Timeouts::new().tile(Duration::from_secs(5));
+        assert_eq!(timeouts.get(Stage::Tile), Some(Duration::from_secs(5)));
+        assert_eq!(timeouts.get(Stage::Search), Some(Duration::from_secs(30)));
+        assert_eq!(Timeouts::none().get(Stage::Metadata), None);
+    }
+}
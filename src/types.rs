@@ -1,3 +1,5 @@
+use crate::attribution::{Attribution, AttributionPosition};
+use crate::geoid::Geoid;
 use serde::{Deserialize, Serialize};
 
 /// A Street View panorama with location and metadata.
@@ -9,16 +11,253 @@ pub struct Panorama {
     pub lat: f64,
     /// Longitude coordinate
     pub lon: f64,
-    /// Camera heading in degrees (0-360)
+    /// Compass heading of the image center in degrees, clockwise from
+    /// north, as reported by the provider. Usually in `[0, 360)`, but not
+    /// guaranteed; use [`compass_heading`](Self::compass_heading) when
+    /// comparing or storing headings.
     pub heading: f64,
     /// Camera pitch in degrees (optional)
     pub pitch: Option<f64>,
     /// Camera roll in degrees (optional)
     pub roll: Option<f64>,
-    /// Date in YYYY-MM format (optional)
-    pub date: Option<String>,
-    /// Elevation/altitude data (optional)
+    /// Capture month (optional)
+    pub date: Option<CaptureDate>,
+    /// How reliably `date` belongs to this panorama
+    #[serde(default)]
+    pub date_confidence: DateConfidence,
+    /// Camera elevation in meters above mean sea level (the EGM96 geoid),
+    /// not above the WGS84 ellipsoid. See [`elevation_wgs84`](Self::elevation_wgs84)
+    /// to convert.
     pub elevation: Option<f64>,
+    /// Copyright notice (e.g. "© 2021 Google"), when the source reports one
+    #[serde(default)]
+    pub copyright: Option<String>,
+    /// Street or place name (e.g. "Piazza Navona"), when the source
+    /// reports one
+    #[serde(default)]
+    pub street_name: Option<String>,
+}
+
+impl Panorama {
+    /// The panorama's position.
+    pub fn location(&self) -> Location {
+        Location::new(self.lat, self.lon)
+    }
+
+    /// Compass heading in degrees (0-360, clockwise from north) to look
+    /// from this panorama towards a target coordinate.
+    ///
+    /// This is the heading the official Static API expects. To look at the
+    /// target in a downloaded panorama image, subtract `self.heading`, the
+    /// compass heading of the image center.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rsstreetview::StreetView;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = StreetView::builder().api_key("YOUR_API_KEY").build()?;
+    /// let (lat, lon) = (41.8986, 12.4768); // the Pantheon
+    /// let pano = &client.search_panoramas(41.8982208, 12.4764804).await?[0];
+    ///
+    /// let heading = pano.heading_towards(lat, lon).round() as u16 % 360;
+    /// let view = client.get_streetview(&pano.pano_id, 640, 640, heading, 90, 0).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn heading_towards(&self, lat: f64, lon: f64) -> f64 {
+        crate::utils::initial_bearing(self.lat, self.lon, lat, lon)
+    }
+
+    /// Compass heading of the image center, in degrees clockwise from
+    /// north, normalized to `[0, 360)`.
+    ///
+    /// Heading 0 means the center column of an equirectangular panorama
+    /// faces north. For math-convention yaw, see [`yaw`](Self::yaw).
+    pub fn compass_heading(&self) -> f64 {
+        crate::pose::normalize_heading(self.heading)
+    }
+
+    /// Heading of the image center as math-convention yaw: degrees
+    /// counter-clockwise from east, in `(-180, 180]`.
+    pub fn yaw(&self) -> f64 {
+        crate::pose::compass_to_yaw(self.heading)
+    }
+
+    /// Elevation in meters above the EGM96 geoid (mean sea level), as
+    /// providers report it.
+    pub fn elevation_egm96(&self) -> Option<f64> {
+        self.elevation
+    }
+
+    /// Elevation in meters above the WGS84 ellipsoid, the height GPS,
+    /// ECEF coordinates, and [`GeoPosition`](crate::GeoPosition) use.
+    ///
+    /// `geoid` supplies the undulation at the panorama's position; see
+    /// [`geoid`](crate::geoid) for the models available.
+    pub fn elevation_wgs84(&self, geoid: &impl Geoid) -> Option<f64> {
+        self.elevation.map(|h| geoid.to_ellipsoidal(self.lat, self.lon, h))
+    }
+
+    /// Whether the panorama may have been captured within a range of
+    /// months (inclusive; `None` leaves that end open).
+    ///
+    /// A [`DateConfidence::YearOnly`] date matches when any month of its
+    /// year is in range, so a 2017 capture passes `after = 2017-06`.
+    /// Undated panoramas never match.
+    pub fn captured_within(&self, after: Option<CaptureDate>, before: Option<CaptureDate>) -> bool {
+        let Some(date) = self.date else {
+            return false;
+        };
+        let latest = match self.date_confidence {
+            DateConfidence::YearOnly => CaptureDate { month: 12, ..date },
+            _ => date,
+        };
+        after.is_none_or(|after| latest >= after) && before.is_none_or(|before| date <= before)
+    }
+}
+
+/// The month a panorama was captured.
+///
+/// Orders chronologically, and serializes and displays as `"YYYY-MM"`.
+///
+/// # Example
+///
+/// ```
+/// # use rsstreetview::CaptureDate;
+/// let date: CaptureDate = "2019-07".parse()?;
+/// assert_eq!((date.year, date.month), (2019, 7));
+/// assert!(date < CaptureDate::new(2020, 1).unwrap());
+/// assert_eq!(date.to_string(), "2019-07");
+/// # Ok::<(), rsstreetview::StreetViewError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CaptureDate {
+    /// Year
+    pub year: u16,
+    /// Month (1-12)
+    pub month: u8,
+}
+
+impl CaptureDate {
+    /// A capture date, or `None` if `month` is not 1-12.
+    pub fn new(year: u16, month: u8) -> Option<Self> {
+        (1..=12).contains(&month).then_some(Self { year, month })
+    }
+}
+
+impl std::fmt::Display for CaptureDate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}", self.year, self.month)
+    }
+}
+
+impl std::str::FromStr for CaptureDate {
+    type Err = crate::error::StreetViewError;
+
+    /// Parse `"YYYY-MM"`. A trailing day (`"YYYY-MM-DD"`) is ignored.
+    fn from_str(s: &str) -> crate::error::Result<Self> {
+        let invalid = || crate::error::StreetViewError::ParseError(format!("Invalid capture date {s:?}"));
+        let mut parts = s.trim().splitn(3, '-');
+        let year = parts.next().and_then(|y| y.parse().ok()).ok_or_else(invalid)?;
+        let month = parts.next().and_then(|m| m.parse().ok()).ok_or_else(invalid)?;
+        Self::new(year, month).ok_or_else(invalid)
+    }
+}
+
+impl Serialize for CaptureDate {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for CaptureDate {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// How a panorama's capture date was determined.
+///
+/// Search responses list dates separately from panoramas. Ordered from
+/// least to most reliable, so `pano.date_confidence >= DateConfidence::YearOnly`
+/// keeps dates that are tied to their panorama.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DateConfidence {
+    /// No capture date
+    #[default]
+    None,
+    /// Matched to the panorama by position in the response only, and
+    /// possibly misaligned if Google left dates out
+    Positional,
+    /// Tied to the panorama, but only the year is known; the date's month
+    /// is 1, and [`Panorama::captured_within`] treats it as the whole year
+    YearOnly,
+    /// Tied to the panorama with year and month
+    Exact,
+}
+
+impl DateConfidence {
+    /// `Exact` for a known date, `None` otherwise, for sources that report
+    /// each panorama's date directly.
+    pub fn of(date: Option<CaptureDate>) -> Self {
+        match date {
+            Some(_) => DateConfidence::Exact,
+            None => DateConfidence::None,
+        }
+    }
+}
+
+/// The camera system that captured a panorama.
+///
+/// Inferred from the full image size, capture date, and source, since
+/// Google does not report it. Resolution, colour, and black-border
+/// behaviour differ between generations: Gen 3 imagery is 13312×6656 and
+/// leaves black padding in the 16384×8192 tile grid of zoom 5, while Gen 4
+/// fills it. Trekker (backpack) and other special captures are recognized
+/// by the source photometa reports; search results carry no source, so
+/// [`from_image_size`](Self::from_image_size) reports them as the
+/// generation of their camera.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CameraGeneration {
+    /// First generation (2007-2008), 3328×1664
+    Gen1,
+    /// Second generation, 3328×1664, captured from 2009 on
+    Gen2,
+    /// Third generation, 13312×6656
+    Gen3,
+    /// Fourth generation, 16384×8192
+    Gen4,
+    /// Google's Trekker backpack, trolley, or another collect away from
+    /// the car, on a Gen 3 or Gen 4 camera
+    Trekker,
+    /// A user-contributed photosphere or other non-Google imagery
+    ThirdParty,
+    /// Not recognized
+    #[default]
+    Unknown,
+}
+
+impl CameraGeneration {
+    /// Infer the generation of Google imagery from its full image width and
+    /// capture date.
+    ///
+    /// Gen 1 and Gen 2 share a resolution, so panoramas of that size are
+    /// told apart by date: before 2009 is Gen 1. Without a date they count
+    /// as Gen 2.
+    pub fn from_image_size(width: u32, height: u32, date: Option<CaptureDate>) -> Self {
+        match (width, height) {
+            (16384, 8192) => CameraGeneration::Gen4,
+            (13312, 6656) => CameraGeneration::Gen3,
+            (3328, 1664) if date.is_some_and(|d| d.year < 2009) => CameraGeneration::Gen1,
+            (3328, 1664) => CameraGeneration::Gen2,
+            _ => CameraGeneration::Unknown,
+        }
+    }
 }
 
 /// GPS location with latitude and longitude.
@@ -30,9 +269,82 @@ pub struct Location {
     pub lng: f64,
 }
 
+impl Location {
+    /// Create a location from latitude and longitude.
+    pub fn new(lat: f64, lng: f64) -> Self {
+        Self { lat, lng }
+    }
+
+    /// Great-circle (haversine) distance to `other` in meters, on a
+    /// spherical Earth.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rsstreetview::Location;
+    /// let rome = Location::new(41.9028, 12.4964);
+    /// let paris = Location::new(48.8566, 2.3522);
+    /// assert!((rome.distance_to(&paris) / 1000.0 - 1106.0).abs() < 5.0);
+    /// assert!((rome.bearing_to(&paris) - 317.8).abs() < 0.1);
+    /// ```
+    pub fn distance_to(&self, other: &Location) -> f64 {
+        crate::utils::haversine_distance(self.lat, self.lng, other.lat, other.lng)
+    }
+
+    /// Initial compass bearing to `other` in degrees (0-360, clockwise from
+    /// north) along the great circle.
+    pub fn bearing_to(&self, other: &Location) -> f64 {
+        crate::utils::initial_bearing(self.lat, self.lng, other.lat, other.lng)
+    }
+}
+
+/// Status code returned by the official Google Maps APIs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ApiStatus {
+    /// Request succeeded
+    Ok,
+    /// No results near the requested location
+    ZeroResults,
+    /// The requested panorama or address was not found
+    NotFound,
+    /// Query rate limit exceeded
+    OverQueryLimit,
+    /// Daily quota exceeded or billing not enabled
+    OverDailyLimit,
+    /// API key missing, invalid, or not authorized for this API
+    RequestDenied,
+    /// Malformed request parameters
+    InvalidRequest,
+    /// Server error, the request may succeed if retried
+    UnknownError,
+    /// A status this library doesn't know about
+    #[serde(other)]
+    Other,
+}
+
+impl ApiStatus {
+    /// The status code as Google spells it (e.g. "ZERO_RESULTS").
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiStatus::Ok => "OK",
+            ApiStatus::ZeroResults => "ZERO_RESULTS",
+            ApiStatus::NotFound => "NOT_FOUND",
+            ApiStatus::OverQueryLimit => "OVER_QUERY_LIMIT",
+            ApiStatus::OverDailyLimit => "OVER_DAILY_LIMIT",
+            ApiStatus::RequestDenied => "REQUEST_DENIED",
+            ApiStatus::InvalidRequest => "INVALID_REQUEST",
+            ApiStatus::UnknownError => "UNKNOWN_ERROR",
+            ApiStatus::Other => "OTHER",
+        }
+    }
+}
+
 /// Official metadata from Google Maps API.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MetaData {
+    /// Response status (always `ApiStatus::Ok` for returned metadata)
+    pub status: ApiStatus,
     /// Date of panorama capture
     pub date: String,
     /// GPS location
@@ -43,8 +355,303 @@ pub struct MetaData {
     pub copyright: String,
 }
 
+impl MetaData {
+    /// The capture date, parsed from `date`.
+    pub fn capture_date(&self) -> Option<CaptureDate> {
+        self.date.parse().ok()
+    }
+}
+
+/// Which imagery the official metadata lookup may return.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum MetaSource {
+    /// Any panorama, including indoor imagery
+    #[default]
+    Default,
+    /// Only outdoor panoramas
+    Outdoor,
+}
+
+/// Options for looking up official metadata by location.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct MetaOptions {
+    /// Search radius in meters (Google's default is 50)
+    pub radius: Option<u32>,
+    /// Imagery source filter
+    pub source: MetaSource,
+}
+
+impl MetaOptions {
+    /// Create default lookup options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the search radius in meters.
+    pub fn radius(mut self, meters: u32) -> Self {
+        self.radius = Some(meters);
+        self
+    }
+
+    /// Set the imagery source filter.
+    pub fn source(mut self, source: MetaSource) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Shortcut for `source(MetaSource::Outdoor)`.
+    pub fn outdoor(self) -> Self {
+        self.source(MetaSource::Outdoor)
+    }
+}
+
+/// What a Static API image shows.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum StaticLocation {
+    /// A specific panorama by ID
+    Pano(String),
+    /// The panorama nearest to GPS coordinates as (lat, lon)
+    Coords(f64, f64),
+    /// The panorama nearest to an address (geocoded by Google)
+    Address(String),
+}
+
+/// Options for an official Static API image request.
+///
+/// Unlike [`get_streetview`](crate::StreetView::get_streetview), angles
+/// are fractional and the location lookup parameters are exposed.
+///
+/// # Example
+///
+/// ```
+/// # use rsstreetview::StaticViewOptions;
+/// let options = StaticViewOptions::at(41.8982208, 12.4764804)
+///     .heading(87.5)
+///     .fov(72.5)
+///     .pitch(-4.25)
+///     .radius(30)
+///     .outdoor()
+///     .return_error_code(true);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct StaticViewOptions {
+    /// Panorama or location to show
+    pub location: StaticLocation,
+    /// Image size as (width, height), capped at 640×640 (default 640×640)
+    pub size: (u32, u32),
+    /// Camera heading in degrees (default: Google faces the location)
+    pub heading: Option<f64>,
+    /// Horizontal field of view in degrees, capped at 120 (default 90)
+    pub fov: f64,
+    /// Camera pitch in degrees (default 0)
+    pub pitch: f64,
+    /// Search radius in meters around a location (Google's default is 50)
+    pub radius: Option<u32>,
+    /// Imagery source filter for a location
+    pub source: MetaSource,
+    /// Answer with `404` instead of a placeholder image when there is no
+    /// imagery (default false)
+    pub return_error_code: bool,
+}
+
+impl StaticViewOptions {
+    /// Show `location` with default options.
+    pub fn new(location: StaticLocation) -> Self {
+        Self {
+            location,
+            size: (640, 640),
+            heading: None,
+            fov: 90.0,
+            pitch: 0.0,
+            radius: None,
+            source: MetaSource::Default,
+            return_error_code: false,
+        }
+    }
+
+    /// Show a specific panorama.
+    pub fn pano(pano_id: impl Into<String>) -> Self {
+        Self::new(StaticLocation::Pano(pano_id.into()))
+    }
+
+    /// Show the panorama nearest to GPS coordinates.
+    pub fn at(lat: f64, lon: f64) -> Self {
+        Self::new(StaticLocation::Coords(lat, lon))
+    }
+
+    /// Show the panorama nearest to an address.
+    pub fn address(address: impl Into<String>) -> Self {
+        Self::new(StaticLocation::Address(address.into()))
+    }
+
+    /// Set the image size.
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.size = (width, height);
+        self
+    }
+
+    /// Set the camera heading in degrees.
+    pub fn heading(mut self, degrees: f64) -> Self {
+        self.heading = Some(degrees);
+        self
+    }
+
+    /// Set the horizontal field of view in degrees.
+    pub fn fov(mut self, degrees: f64) -> Self {
+        self.fov = degrees;
+        self
+    }
+
+    /// Set the camera pitch in degrees.
+    pub fn pitch(mut self, degrees: f64) -> Self {
+        self.pitch = degrees;
+        self
+    }
+
+    /// Set the search radius in meters.
+    pub fn radius(mut self, meters: u32) -> Self {
+        self.radius = Some(meters);
+        self
+    }
+
+    /// Set the imagery source filter.
+    pub fn source(mut self, source: MetaSource) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Shortcut for `source(MetaSource::Outdoor)`.
+    pub fn outdoor(self) -> Self {
+        self.source(MetaSource::Outdoor)
+    }
+
+    /// Have Google answer with `404` instead of its "no imagery"
+    /// placeholder. Either way the request fails with
+    /// `StreetViewError::NoImagery`; the status code is just more reliable
+    /// than recognizing the placeholder.
+    pub fn return_error_code(mut self, enabled: bool) -> Self {
+        self.return_error_code = enabled;
+        self
+    }
+}
+
+/// Language and region requested from Google's undocumented endpoints.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Locale {
+    /// Language code (e.g. "en", "ja")
+    pub language: String,
+    /// Region code (e.g. "US", "JP")
+    pub region: String,
+}
+
+impl Locale {
+    /// Create a locale from a language and a region code.
+    pub fn new(language: impl Into<String>, region: impl Into<String>) -> Self {
+        Self {
+            language: language.into(),
+            region: region.into(),
+        }
+    }
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Self::new("en", "US")
+    }
+}
+
+/// Headers sent to Google's undocumented endpoints.
+///
+/// Some networks get different or blocked responses with reqwest's default
+/// user-agent. Official API requests are sent without these headers.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RequestHeaders {
+    /// User-agents to rotate through, one per request (empty: reqwest default)
+    pub user_agents: Vec<String>,
+    /// `Accept-Language` value (e.g. "en-US,en;q=0.9")
+    pub accept_language: Option<String>,
+    /// Extra headers as (name, value) pairs
+    pub extra: Vec<(String, String)>,
+}
+
+impl RequestHeaders {
+    /// Create an empty header configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send a single user-agent.
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agents = vec![user_agent.into()];
+        self
+    }
+
+    /// Rotate through a pool of user-agents, one per request.
+    pub fn user_agents<I, S>(mut self, user_agents: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.user_agents = user_agents.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Set the `Accept-Language` header.
+    pub fn accept_language(mut self, language: impl Into<String>) -> Self {
+        self.accept_language = Some(language.into());
+        self
+    }
+
+    /// Add an extra header.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.extra.push((name.into(), value.into()));
+        self
+    }
+
+    /// Whether no headers are configured.
+    pub fn is_empty(&self) -> bool {
+        self.user_agents.is_empty() && self.accept_language.is_none() && self.extra.is_empty()
+    }
+}
+
+/// A Static API image together with the response that carried it.
+#[derive(Debug, Clone)]
+pub struct StaticImage {
+    /// The decoded image
+    pub image: image::DynamicImage,
+    /// HTTP status code
+    pub status: u16,
+    /// Response headers, including Google's `X-Static-Maps-*` headers
+    pub headers: reqwest::header::HeaderMap,
+}
+
+impl StaticImage {
+    /// Value of a response header, if present and valid text.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)?.to_str().ok()
+    }
+
+    /// The response `Content-Type` (e.g. "image/jpeg").
+    pub fn content_type(&self) -> Option<&str> {
+        self.header("content-type")
+    }
+}
+
 /// Image output format.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum ImageFormat {
     /// JPEG format
     Jpeg,
@@ -54,6 +661,43 @@ pub enum ImageFormat {
     WebP,
 }
 
+impl ImageFormat {
+    /// Conventional file extension for this format (without the dot).
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Png => "png",
+            ImageFormat::WebP => "webp",
+        }
+    }
+}
+
+/// Displays as `jpeg`, `png`, or `webp`, the same names it serializes as.
+impl std::fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ImageFormat::Jpeg => "jpeg",
+            ImageFormat::Png => "png",
+            ImageFormat::WebP => "webp",
+        })
+    }
+}
+
+impl std::str::FromStr for ImageFormat {
+    type Err = crate::error::StreetViewError;
+
+    /// Parse a format name or extension (`jpg`, `jpeg`, `png`, `webp`),
+    /// ignoring case and a leading dot.
+    fn from_str(s: &str) -> crate::error::Result<Self> {
+        match s.trim().trim_start_matches('.').to_ascii_lowercase().as_str() {
+            "jpg" | "jpeg" => Ok(ImageFormat::Jpeg),
+            "png" => Ok(ImageFormat::Png),
+            "webp" => Ok(ImageFormat::WebP),
+            _ => Err(crate::error::StreetViewError::ParseError(format!("Unknown image format {s:?}"))),
+        }
+    }
+}
+
 impl From<ImageFormat> for image::ImageFormat {
     fn from(format: ImageFormat) -> Self {
         match format {
@@ -64,18 +708,109 @@ impl From<ImageFormat> for image::ImageFormat {
     }
 }
 
+/// JPEG chroma subsampling mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ChromaSubsampling {
+    /// No subsampling: full color resolution, largest files
+    Yuv444,
+    /// Half horizontal color resolution
+    Yuv422,
+    /// Half horizontal and vertical color resolution, smallest files
+    Yuv420,
+}
+
+/// PNG compression effort.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum PngCompression {
+    /// Fastest encoding, larger files (recommended for zoom 4+)
+    Fast,
+    /// Balanced zlib level
+    #[default]
+    Default,
+    /// Smallest files, slowest encoding
+    Best,
+}
+
+impl From<PngCompression> for image::codecs::png::CompressionType {
+    fn from(compression: PngCompression) -> Self {
+        match compression {
+            PngCompression::Fast => image::codecs::png::CompressionType::Fast,
+            PngCompression::Default => image::codecs::png::CompressionType::Default,
+            PngCompression::Best => image::codecs::png::CompressionType::Best,
+        }
+    }
+}
+
+/// PNG row filter strategy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum PngFilter {
+    /// No filtering (fastest)
+    NoFilter,
+    /// Difference to the pixel on the left
+    Sub,
+    /// Difference to the pixel above
+    Up,
+    /// Difference to the average of left and above
+    Avg,
+    /// Paeth predictor
+    Paeth,
+    /// Pick the best filter per row (smallest files, slowest)
+    #[default]
+    Adaptive,
+}
+
+impl From<PngFilter> for image::codecs::png::FilterType {
+    fn from(filter: PngFilter) -> Self {
+        match filter {
+            PngFilter::NoFilter => image::codecs::png::FilterType::NoFilter,
+            PngFilter::Sub => image::codecs::png::FilterType::Sub,
+            PngFilter::Up => image::codecs::png::FilterType::Up,
+            PngFilter::Avg => image::codecs::png::FilterType::Avg,
+            PngFilter::Paeth => image::codecs::png::FilterType::Paeth,
+            PngFilter::Adaptive => image::codecs::png::FilterType::Adaptive,
+        }
+    }
+}
+
 /// Options for saving panorama images.
+///
+/// Images keep their color layout where the format allows it: grayscale is
+/// saved as single-channel JPEG/PNG/WebP and alpha is kept for PNG and WebP.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct SaveOptions {
     /// Image format
     pub format: ImageFormat,
     /// JPEG quality (1-100, default 90)
     pub jpeg_quality: u8,
+    /// Write progressive JPEG (default false)
+    pub jpeg_progressive: bool,
+    /// JPEG chroma subsampling (default: encoder default)
+    pub jpeg_subsampling: Option<ChromaSubsampling>,
+    /// PNG compression effort (default `PngCompression::Default`)
+    pub png_compression: PngCompression,
+    /// PNG row filter (default `PngFilter::Adaptive`)
+    pub png_filter: PngFilter,
     /// WebP quality (1-100, default 85)
     pub webp_quality: u8,
     /// WebP compression method (0-6, default 4)
     /// Higher values = slower but better compression
     pub webp_method: u8,
+    /// Embed EXIF/XMP GPano metadata when saving with a `Panorama` (default true)
+    pub embed_metadata: bool,
+    /// Write a `.json` sidecar next to images saved with a `Panorama` (default false)
+    pub sidecar: bool,
+    /// Encoder threads (default 1, 0 = one per CPU core)
+    pub threads: usize,
+    /// Copyright notice or logo drawn onto saved images (default: none)
+    pub attribution: Option<Attribution>,
 }
 
 impl SaveOptions {
@@ -84,8 +819,16 @@ impl SaveOptions {
         Self {
             format: ImageFormat::WebP,
             jpeg_quality: 90,
+            jpeg_progressive: false,
+            jpeg_subsampling: None,
+            png_compression: PngCompression::Default,
+            png_filter: PngFilter::Adaptive,
             webp_quality: 85,
             webp_method: 4,
+            embed_metadata: true,
+            sidecar: false,
+            threads: 1,
+            attribution: None,
         }
     }
 
@@ -101,6 +844,48 @@ impl SaveOptions {
         self
     }
 
+    /// Enable progressive JPEG encoding.
+    ///
+    /// Progressive files render a coarse preview first while loading, which
+    /// suits large panoramas served over the web.
+    pub fn jpeg_progressive(mut self, progressive: bool) -> Self {
+        self.jpeg_progressive = progressive;
+        self
+    }
+
+    /// Set JPEG chroma subsampling.
+    pub fn jpeg_subsampling(mut self, subsampling: ChromaSubsampling) -> Self {
+        self.jpeg_subsampling = Some(subsampling);
+        self
+    }
+
+    /// Set PNG compression effort.
+    ///
+    /// `PngCompression::Fast` is several times faster than the default on
+    /// large panoramas at the cost of bigger files.
+    pub fn png_compression(mut self, compression: PngCompression) -> Self {
+        self.png_compression = compression;
+        self
+    }
+
+    /// Set the PNG row filter strategy.
+    pub fn png_filter(mut self, filter: PngFilter) -> Self {
+        self.png_filter = filter;
+        self
+    }
+
+    /// Shortcut for the fastest PNG settings (fast compression, no filter).
+    pub fn png_fast(self) -> Self {
+        self.png_compression(PngCompression::Fast)
+            .png_filter(PngFilter::NoFilter)
+    }
+
+    /// Shortcut for the smallest PNG files (best compression, adaptive filter).
+    pub fn png_smallest(self) -> Self {
+        self.png_compression(PngCompression::Best)
+            .png_filter(PngFilter::Adaptive)
+    }
+
     /// Set WebP quality (1-100).
     pub fn webp_quality(mut self, quality: u8) -> Self {
         self.webp_quality = quality.clamp(1, 100);
@@ -116,10 +901,121 @@ impl SaveOptions {
         self
     }
 
+    /// Set whether `save_with_metadata` embeds EXIF/XMP metadata.
+    pub fn embed_metadata(mut self, embed: bool) -> Self {
+        self.embed_metadata = embed;
+        self
+    }
+
+    /// Set whether `save_with_metadata` writes a JSON sidecar file.
+    ///
+    /// The sidecar is named after the image (`pano.jpg` -> `pano.json`) and
+    /// records the pano ID, coordinates, date, orientation, zoom and image
+    /// parameters, so datasets stay reproducible.
+    pub fn sidecar(mut self, sidecar: bool) -> Self {
+        self.sidecar = sidecar;
+        self
+    }
+
+    /// Set the number of encoder threads (0 = one per CPU core).
+    ///
+    /// With the `parallel` feature, PNG output is filtered and compressed
+    /// in horizontal stripes on this many threads. Files are slightly
+    /// larger than single-threaded output because each stripe starts with
+    /// an empty compression window. Other formats are always encoded on
+    /// one thread.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Draw a copyright notice or logo onto every saved image.
+    ///
+    /// Accepts text (`"© 2021 Google"`) or an [`Attribution`] built with a
+    /// logo. Thumbnails get their own, proportionally smaller overlay.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rsstreetview::{AttributionPosition, ImageFormat, SaveOptions, StreetView};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = StreetView::new();
+    /// let meta = client.get_photometa("PANO_ID").await?;
+    /// let image = client.download_panorama("PANO_ID", 3).await?;
+    ///
+    /// let notice = meta.copyright.unwrap_or_else(|| "© Google".to_string());
+    /// SaveOptions::new()
+    ///     .format(ImageFormat::Jpeg)
+    ///     .attribution(notice, AttributionPosition::BottomRight)
+    ///     .save(&image, "pano.jpg")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn attribution(mut self, attribution: impl Into<Attribution>, position: AttributionPosition) -> Self {
+        self.attribution = Some(attribution.into().position(position));
+        self
+    }
+
+    /// Effective number of encoder threads.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn thread_count(&self) -> usize {
+        match self.threads {
+            0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        }
+    }
+
     /// Save an image with these options.
     pub fn save(&self, img: &image::DynamicImage, path: impl AsRef<std::path::Path>) -> crate::error::Result<()> {
         crate::save::save_panorama(img, path, self)
     }
+
+    /// Save an image with embedded EXIF/XMP GPano metadata from `pano`.
+    ///
+    /// The GPS position, capture date, and equirectangular projection tags
+    /// let 360° viewers recognize the file as a panorama.
+    pub fn save_with_metadata(
+        &self,
+        img: &image::DynamicImage,
+        pano: &Panorama,
+        path: impl AsRef<std::path::Path>,
+    ) -> crate::error::Result<()> {
+        crate::save::save_with_metadata(img, pano, path, self)
+    }
+
+    /// Save an image plus downscaled thumbnails (e.g. `&[1024, 256]` wide).
+    ///
+    /// See [`save_with_thumbnails`](crate::save_with_thumbnails).
+    pub fn save_with_thumbnails(
+        &self,
+        img: &image::DynamicImage,
+        path: impl AsRef<std::path::Path>,
+        widths: &[u32],
+    ) -> crate::error::Result<Vec<std::path::PathBuf>> {
+        crate::save::save_with_thumbnails(img, path, widths, self)
+    }
+
+    /// Encode an image to bytes with these options.
+    pub fn encode(&self, img: &image::DynamicImage) -> crate::error::Result<Vec<u8>> {
+        crate::save::encode_panorama(img, self)
+    }
+
+    /// Save an image with these options without blocking the async runtime.
+    ///
+    /// Encoding runs on `spawn_blocking` and the file is written with `tokio::fs`.
+    pub async fn save_async(
+        &self,
+        img: image::DynamicImage,
+        path: impl AsRef<std::path::Path>,
+    ) -> crate::error::Result<()> {
+        crate::save::save_panorama_async(img, path, self).await
+    }
+
+    /// Encode an image to bytes with these options without blocking the async runtime.
+    pub async fn encode_async(&self, img: image::DynamicImage) -> crate::error::Result<Vec<u8>> {
+        crate::save::encode_panorama_async(img, self).await
+    }
 }
 
 impl Default for SaveOptions {
@@ -129,6 +1025,7 @@ impl Default for SaveOptions {
 }
 
 /// Internal: Information about a single tile to download.
+#[cfg(feature = "unofficial")]
 #[derive(Debug, Clone)]
 pub(crate) struct TileInfo {
     pub x: u32,
@@ -137,6 +1034,7 @@ pub(crate) struct TileInfo {
 }
 
 /// Internal: A downloaded tile with its position.
+#[cfg(feature = "unofficial")]
 #[derive(Debug)]
 pub(crate) struct Tile {
     pub x: u32,
@@ -1,6 +1,65 @@
-use image::{DynamicImage, GenericImageView};
+//! Image utilities for downloaded panoramas: border cropping and
+//! coverage detection, sky masking, and exposure normalization.
+
+use crate::depth::DepthMap;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use image::{DynamicImage, GenericImageView, GrayImage, Luma, Rgba, RgbaImage};
 
 const BLACK_LUMINANCE_THRESHOLD: u8 = 4;
+/// Largest brightness change between neighbouring pixels inside the sky,
+/// after blurring.
+const SKY_GRADIENT_THRESHOLD: f32 = 10.0;
+/// Darkest luminance counted as sky.
+const SKY_MIN_LUMINANCE: f32 = 90.0;
+pub(crate) const EARTH_RADIUS_M: f64 = 6_371_008.8;
+
+/// URL-safe base64 as Google uses it, accepting input with or without
+/// padding.
+pub(crate) const BASE64_URL_SAFE: GeneralPurpose = GeneralPurpose::new(
+    &base64::alphabet::URL_SAFE,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Great-circle distance in meters between two GPS coordinates.
+pub(crate) fn haversine_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (phi1, phi2) = (lat1.to_radians(), lat2.to_radians());
+    let d_phi = (lat2 - lat1).to_radians();
+    let d_lambda = (lon2 - lon1).to_radians();
+
+    let a = (d_phi / 2.0).sin().powi(2) + phi1.cos() * phi2.cos() * (d_lambda / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_M * a.sqrt().asin()
+}
+
+/// Initial great-circle bearing in degrees (0-360, clockwise from north)
+/// from the first GPS coordinate towards the second.
+pub(crate) fn initial_bearing(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (phi1, phi2) = (lat1.to_radians(), lat2.to_radians());
+    let d_lambda = (lon2 - lon1).to_radians();
+
+    let y = d_lambda.sin() * phi2.cos();
+    let x = phi1.cos() * phi2.sin() - phi1.sin() * phi2.cos() * d_lambda.cos();
+    y.atan2(x).to_degrees().rem_euclid(360.0)
+}
+
+/// The GPS coordinate `distance` meters from the first along a great
+/// circle starting at `bearing` degrees, as `(lat, lon)`.
+pub(crate) fn destination(lat: f64, lon: f64, bearing: f64, distance: f64) -> (f64, f64) {
+    let (phi1, lambda1) = (lat.to_radians(), lon.to_radians());
+    let (theta, delta) = (bearing.to_radians(), distance / EARTH_RADIUS_M);
+
+    let phi2 = (phi1.sin() * delta.cos() + phi1.cos() * delta.sin() * theta.cos()).asin();
+    let lambda2 = lambda1
+        + (theta.sin() * delta.sin() * phi1.cos()).atan2(delta.cos() - phi1.sin() * phi2.sin());
+    (phi2.to_degrees(), (lambda2.to_degrees() + 540.0).rem_euclid(360.0) - 180.0)
+}
+
+/// Box of `radius` meters around a GPS coordinate, as
+/// `(south, west, north, east)` in degrees.
+pub(crate) fn bounding_box(lat: f64, lon: f64, radius: f64) -> (f64, f64, f64, f64) {
+    let d_lat = (radius / EARTH_RADIUS_M).to_degrees();
+    let d_lon = d_lat / lat.to_radians().cos().max(0.01);
+    (lat - d_lat, lon - d_lon, lat + d_lat, lon + d_lon)
+}
 
 /// Crop black borders from the bottom and right edges of a panorama.
 ///
@@ -125,11 +184,491 @@ pub fn crop_bottom_and_right_black_border(img: DynamicImage) -> DynamicImage {
     img.crop_imm(0, 0, right_crop, bottom_crop)
 }
 
+/// Rows and columns with at most this fraction of content pixels count as
+/// empty, so stray noise in black padding doesn't stop a crop.
+const MIN_CONTENT_FRACTION: f64 = 0.005;
+
+/// The part of an equirectangular canvas that holds image content.
+///
+/// Angles assume the canvas spans 360°×180°, as Google's photosphere tiles
+/// do, with yaw 0 at the center column.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Coverage {
+    /// Left edge of the content in pixels
+    pub x: u32,
+    /// Top edge of the content in pixels
+    pub y: u32,
+    /// Content width in pixels; `x + width` exceeds the canvas width when
+    /// the content wraps across the 360° seam
+    pub width: u32,
+    /// Content height in pixels
+    pub height: u32,
+    /// Yaw of the left edge in degrees relative to the canvas center, [-180, 180)
+    pub yaw_start: f64,
+    /// Horizontal field of view covered, in degrees
+    pub horizontal_fov: f64,
+    /// Pitch of the top edge in degrees (90 = zenith)
+    pub pitch_top: f64,
+    /// Pitch of the bottom edge in degrees (-90 = nadir)
+    pub pitch_bottom: f64,
+    /// Fraction of the cropped area that is content rather than black
+    pub content_fraction: f64,
+}
+
+impl Coverage {
+    /// Vertical field of view covered, in degrees.
+    pub fn vertical_fov(&self) -> f64 {
+        self.pitch_top - self.pitch_bottom
+    }
+
+    /// Whether the content covers the full sphere.
+    pub fn is_full(&self) -> bool {
+        self.horizontal_fov >= 359.9 && self.vertical_fov() >= 179.9
+    }
+}
+
+/// A panorama cropped to its content.
+#[derive(Debug, Clone)]
+pub struct SmartCrop {
+    /// The cropped image
+    pub image: DynamicImage,
+    /// Where the crop came from and the angles it covers
+    pub coverage: Coverage,
+}
+
+/// Find the content of a partially captured panorama.
+///
+/// Unlike [`crop_bottom_and_right_black_border`], black padding may be on
+/// any side. Columns are treated as circular, so content straddling the
+/// left and right edges of the canvas is found as one region: the largest
+/// run of empty columns is taken as the missing part of the sphere.
+/// Irregular black areas inside the content are kept, and reported through
+/// [`Coverage::content_fraction`].
+///
+/// Returns `None` if the image is entirely black.
+pub fn detect_coverage(img: &DynamicImage) -> Option<Coverage> {
+    let (width, height) = img.dimensions();
+    let luma = img.to_luma8();
+    let content = |x: u32, y: u32| luma.get_pixel(x, y)[0] > BLACK_LUMINANCE_THRESHOLD;
+
+    let mut row_counts = vec![0u32; height as usize];
+    let mut col_counts = vec![0u32; width as usize];
+    for y in 0..height {
+        for x in 0..width {
+            if content(x, y) {
+                row_counts[y as usize] += 1;
+                col_counts[x as usize] += 1;
+            }
+        }
+    }
+    let filled = |count: u32, of: u32| f64::from(count) > f64::from(of) * MIN_CONTENT_FRACTION;
+    let rows: Vec<bool> = row_counts.iter().map(|&c| filled(c, width)).collect();
+    let cols: Vec<bool> = col_counts.iter().map(|&c| filled(c, height)).collect();
+
+    let top = rows.iter().position(|&r| r)? as u32;
+    let bottom = rows.iter().rposition(|&r| r)? as u32 + 1;
+
+    // Longest circular run of empty columns
+    let first_filled = cols.iter().position(|&c| c)?;
+    let (mut gap_start, mut gap_len, mut run) = (0, 0, 0);
+    for i in 1..=width as usize {
+        let x = (first_filled + i) % width as usize;
+        if cols[x] {
+            run = 0;
+        } else {
+            run += 1;
+            if run > gap_len {
+                gap_len = run;
+                gap_start = (x + width as usize + 1 - run) % width as usize;
+            }
+        }
+    }
+    let left = ((gap_start + gap_len) % width as usize) as u32;
+    let crop_width = width - gap_len as u32;
+    let crop_height = bottom - top;
+
+    let content_pixels = (top..bottom)
+        .flat_map(|y| (0..crop_width).map(move |dx| ((left + dx) % width, y)))
+        .filter(|&(x, y)| content(x, y))
+        .count();
+
+    let yaw = |x: f64| (x / f64::from(width) - 0.5) * 360.0;
+    let pitch = |y: u32| 90.0 - f64::from(y) / f64::from(height) * 180.0;
+    Some(Coverage {
+        x: left,
+        y: top,
+        width: crop_width,
+        height: crop_height,
+        yaw_start: yaw(f64::from(left)),
+        horizontal_fov: f64::from(crop_width) / f64::from(width) * 360.0,
+        pitch_top: pitch(top),
+        pitch_bottom: pitch(bottom),
+        content_fraction: content_pixels as f64 / (f64::from(crop_width) * f64::from(crop_height)),
+    })
+}
+
+/// Crop a partially captured panorama to its content, on any side.
+///
+/// Content that wraps across the 360° seam is stitched back together, so
+/// the result is one contiguous image. See [`detect_coverage`]. Returns
+/// `None` if the image is entirely black.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rsstreetview::{StreetView, utils::smart_crop};
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = StreetView::new();
+/// let image = client.download_panorama("PHOTOSPHERE_ID", 3).await?;
+///
+/// if let Some(crop) = smart_crop(&image) {
+///     let c = crop.coverage;
+///     println!("{:.0}°×{:.0}° starting at yaw {:.0}°", c.horizontal_fov, c.vertical_fov(), c.yaw_start);
+///     crop.image.save("cropped.jpg")?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn smart_crop(img: &DynamicImage) -> Option<SmartCrop> {
+    let coverage = detect_coverage(img)?;
+    let width = img.width();
+    let image = if coverage.x + coverage.width <= width {
+        img.crop_imm(coverage.x, coverage.y, coverage.width, coverage.height)
+    } else {
+        let first = width - coverage.x;
+        let mut image = DynamicImage::new(coverage.width, coverage.height, img.color());
+        image::imageops::replace(&mut image, &img.crop_imm(coverage.x, coverage.y, first, coverage.height), 0, 0);
+        image::imageops::replace(
+            &mut image,
+            &img.crop_imm(0, coverage.y, coverage.width - first, coverage.height),
+            i64::from(first),
+            0,
+        );
+        image
+    };
+    Some(SmartCrop { image, coverage })
+}
+
+/// A sky mask and the image with its sky removed.
+#[derive(Debug, Clone)]
+pub struct SkyMask {
+    /// 255 where the pixel is sky, 0 elsewhere
+    pub mask: GrayImage,
+    /// The input image with sky pixels made transparent
+    pub image: RgbaImage,
+    /// Fraction of pixels that are sky (0.0-1.0)
+    pub sky_fraction: f64,
+}
+
+impl SkyMask {
+    fn new(img: &DynamicImage, mask: GrayImage) -> Self {
+        let mut image = img.to_rgba8();
+        for (pixel, sky) in image.pixels_mut().zip(mask.pixels()) {
+            if sky[0] == 255 {
+                *pixel = Rgba([0, 0, 0, 0]);
+            }
+        }
+        let sky = mask.pixels().filter(|sky| sky[0] == 255).count();
+        let sky_fraction = sky as f64 / (mask.width() as f64 * mask.height() as f64).max(1.0);
+        Self { mask, image, sky_fraction }
+    }
+}
+
+/// Find the sky in a panorama or view by color and smoothness.
+///
+/// Each column is scanned from the top: pixels belong to the sky while
+/// they are bright, blue or grey rather than saturated, and smooth, and
+/// the first pixel failing that (a roof line, tree, or wire) ends the sky
+/// in that column. This handles clear and overcast skies; for sunsets and
+/// heavy cloud texture, prefer [`mask_sky_with_depth`].
+///
+/// # Example
+///
+/// ```no_run
+/// # use rsstreetview::{StreetView, utils::mask_sky};
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = StreetView::new();
+/// let panos = client.search_panoramas(41.8982208, 12.4764804).await?;
+/// let image = client.download_panorama(&panos[0].pano_id, 3).await?;
+///
+/// let sky = mask_sky(&image);
+/// println!("{:.0}% sky", sky.sky_fraction * 100.0);
+/// sky.image.save("no_sky.png")?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn mask_sky(img: &DynamicImage) -> SkyMask {
+    let (width, height) = img.dimensions();
+    let rgb = image::imageops::blur(&img.to_rgb8(), 1.0);
+    let luma = |x: u32, y: u32| {
+        let [r, g, b] = rgb.get_pixel(x, y).0.map(f32::from);
+        0.299 * r + 0.587 * g + 0.114 * b
+    };
+    let sky_colored = |x: u32, y: u32| {
+        let [r, g, b] = rgb.get_pixel(x, y).0.map(i32::from);
+        let grey = r.max(g).max(b) - r.min(g).min(b) < 30;
+        luma(x, y) >= SKY_MIN_LUMINANCE && (grey || (b >= r && b + 10 >= g))
+    };
+
+    let mut mask = GrayImage::new(width, height);
+    for x in 0..width {
+        let right = (x + 1) % width;
+        for y in 0..height {
+            let smooth = (y == 0 || (luma(x, y) - luma(x, y - 1)).abs() < SKY_GRADIENT_THRESHOLD)
+                && (luma(x, y) - luma(right, y)).abs() < SKY_GRADIENT_THRESHOLD;
+            if !(smooth && sky_colored(x, y)) {
+                break;
+            }
+            mask.put_pixel(x, y, Luma([255]));
+        }
+    }
+    SkyMask::new(img, mask)
+}
+
+/// Mask the sky of a full panorama using its depth map: sky is wherever
+/// the depth map has no surface.
+///
+/// The depth map is coarse (typically 512×256), so mask edges are blocky
+/// when scaled to the panorama.
+pub fn mask_sky_with_depth(img: &DynamicImage, depth: &DepthMap) -> SkyMask {
+    let (width, height) = img.dimensions();
+    let mask = image::imageops::resize(&depth.sky_mask(), width, height, image::imageops::FilterType::Nearest);
+    SkyMask::new(img, mask)
+}
+
+/// Width of the strips compared on each side of a tile seam.
+const SEAM_STRIP: u32 = 4;
+/// Pull of each tile's gain toward 1, relative to one seam.
+const GAIN_REGULARIZATION: f64 = 0.05;
+const GAIN_ITERATIONS: usize = 200;
+
+/// Whether a pixel is black padding rather than image content.
+fn is_padding(pixel: &Rgba<u8>) -> bool {
+    pixel[3] == 0 || pixel.0[..3].iter().all(|&c| c <= BLACK_LUMINANCE_THRESHOLD)
+}
+
+/// Convert working RGBA pixels back, keeping alpha only if the input had it.
+fn from_rgba(rgba: RgbaImage, alpha: bool) -> DynamicImage {
+    match alpha {
+        true => DynamicImage::ImageRgba8(rgba),
+        false => DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(rgba).to_rgb8()),
+    }
+}
+
+/// Even out exposure differences between the tiles of a panorama.
+///
+/// Tiles are occasionally captured or encoded with slightly different
+/// brightness, leaving visible seams on the tile grid. Each tile gets one
+/// gain per channel, solved so that the pixels on both sides of every seam
+/// match as closely as possible while the overall exposure is kept.
+/// Google tiles are 512 pixels; the seam between the right and left edges
+/// is included when the width is a whole number of tiles. Black padding is
+/// ignored and left black.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rsstreetview::{StreetView, utils::balance_tiles};
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = StreetView::new();
+/// let image = client.download_panorama("PANO_ID", 4).await?;
+/// balance_tiles(image, 512).save("balanced.jpg")?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn balance_tiles(img: DynamicImage, tile_size: u32) -> DynamicImage {
+    let (width, height) = img.dimensions();
+    let tile_size = tile_size.max(SEAM_STRIP);
+    let (cols, rows) = (width.div_ceil(tile_size), height.div_ceil(tile_size));
+    if cols * rows < 2 {
+        return img;
+    }
+
+    let alpha = img.color().has_alpha();
+    let mut rgba = img.to_rgba8();
+
+    // Log mean of each channel over a strip, skipping padding
+    let strip = |x0: u32, y0: u32, x1: u32, y1: u32| -> Option<[f64; 3]> {
+        let mut sum = [0.0; 3];
+        let mut count = 0u32;
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let pixel = rgba.get_pixel(x, y);
+                if !is_padding(pixel) {
+                    (0..3).for_each(|c| sum[c] += f64::from(pixel[c]));
+                    count += 1;
+                }
+            }
+        }
+        (count > 0).then(|| sum.map(|s| (s / f64::from(count)).max(1.0).ln()))
+    };
+
+    // (tile a, tile b, log a - log b) for every seam with content on both sides
+    let tile = |col: u32, row: u32| (row * cols + col) as usize;
+    let mut seams = Vec::new();
+    for row in 0..rows {
+        let (y0, y1) = (row * tile_size, ((row + 1) * tile_size).min(height));
+        let wraps = width % tile_size == 0 && cols > 2;
+        for col in 0..if wraps { cols } else { cols - 1 } {
+            let seam = ((col + 1) * tile_size).min(width);
+            let a = strip(seam - SEAM_STRIP, y0, seam, y1);
+            let b = match seam % width {
+                0 => strip(0, y0, SEAM_STRIP, y1),
+                seam => strip(seam, y0, (seam + SEAM_STRIP).min(width), y1),
+            };
+            if let (Some(a), Some(b)) = (a, b) {
+                seams.push((tile(col, row), tile((col + 1) % cols, row), [0, 1, 2].map(|c| a[c] - b[c])));
+            }
+        }
+    }
+    for row in 0..rows - 1 {
+        let seam = (row + 1) * tile_size;
+        for col in 0..cols {
+            let (x0, x1) = (col * tile_size, ((col + 1) * tile_size).min(width));
+            let a = strip(x0, seam.saturating_sub(SEAM_STRIP), x1, seam);
+            let b = strip(x0, seam, x1, (seam + SEAM_STRIP).min(height));
+            if let (Some(a), Some(b)) = (a, b) {
+                seams.push((tile(col, row), tile(col, row + 1), [0, 1, 2].map(|c| a[c] - b[c])));
+            }
+        }
+    }
+    if seams.is_empty() {
+        return from_rgba(rgba, alpha);
+    }
+
+    // Gauss-Seidel on log gains: across a seam, want log a + g_a = log b + g_b
+    let mut neighbors = vec![Vec::new(); (cols * rows) as usize];
+    for &(a, b, diff) in &seams {
+        neighbors[a].push((b, diff.map(|d| -d)));
+        neighbors[b].push((a, diff));
+    }
+    let mut gains = vec![[0.0f64; 3]; neighbors.len()];
+    for _ in 0..GAIN_ITERATIONS {
+        for (t, around) in neighbors.iter().enumerate() {
+            let mut sum = [0.0; 3];
+            for (other, diff) in around {
+                (0..3).for_each(|c| sum[c] += gains[*other][c] + diff[c]);
+            }
+            gains[t] = sum.map(|s| s / (around.len() as f64 + GAIN_REGULARIZATION));
+        }
+    }
+    let mean = [0, 1, 2].map(|c| gains.iter().map(|g| g[c]).sum::<f64>() / gains.len() as f64);
+    let factors: Vec<[f64; 3]> = gains.iter().map(|g| [0, 1, 2].map(|c| (g[c] - mean[c]).exp())).collect();
+
+    for (x, y, pixel) in rgba.enumerate_pixels_mut() {
+        if !is_padding(pixel) {
+            let factor = factors[tile(x / tile_size, y / tile_size)];
+            (0..3).for_each(|c| pixel[c] = (f64::from(pixel[c]) * factor[c]).round().clamp(0.0, 255.0) as u8);
+        }
+    }
+    from_rgba(rgba, alpha)
+}
+
+/// Remap each color channel of `img` so its histogram matches `reference`.
+///
+/// Use it to bring captures of the same place from different dates to a
+/// common brightness and color balance before comparing or presenting
+/// them side by side. Black padding is ignored in both images and left
+/// black.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rsstreetview::utils::match_histogram;
+/// # fn run(old: image::DynamicImage, new: &image::DynamicImage) -> image::ImageResult<()> {
+/// match_histogram(old, new).save("old_matched.jpg")?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn match_histogram(img: DynamicImage, reference: &DynamicImage) -> DynamicImage {
+    let histograms = |image: &RgbaImage| {
+        let mut counts = [[0u64; 256]; 3];
+        for pixel in image.pixels().filter(|p| !is_padding(p)) {
+            (0..3).for_each(|c| counts[c][pixel[c] as usize] += 1);
+        }
+        counts.map(|counts| {
+            let total = counts.iter().sum::<u64>().max(1) as f64;
+            let mut cdf = [0.0f64; 256];
+            let mut running = 0;
+            for (value, count) in counts.iter().enumerate() {
+                running += count;
+                cdf[value] = running as f64 / total;
+            }
+            cdf
+        })
+    };
+
+    let alpha = img.color().has_alpha();
+    let mut rgba = img.to_rgba8();
+    let source = histograms(&rgba);
+    let target = histograms(&reference.to_rgba8());
+
+    // For each value, the first reference value whose CDF reaches the source CDF
+    let luts: [[u8; 256]; 3] = [0, 1, 2].map(|c| {
+        let mut lut = [0u8; 256];
+        let mut r = 0;
+        for v in 0..256 {
+            while r < 255 && target[c][r] < source[c][v] {
+                r += 1;
+            }
+            lut[v] = r as u8;
+        }
+        lut
+    });
+
+    for pixel in rgba.pixels_mut().filter(|p| !is_padding(p)) {
+        (0..3).for_each(|c| pixel[c] = luts[c][pixel[c] as usize]);
+    }
+    from_rgba(rgba, alpha)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use image::{Rgb, RgbImage};
 
+    #[test]
+    fn test_haversine_distance() {
+        // One degree of latitude is ~111.2 km
+        let d = haversine_distance(0.0, 0.0, 1.0, 0.0);
+        assert!((d - 111_195.0).abs() < 10.0);
+        assert_eq!(haversine_distance(41.9, 12.5, 41.9, 12.5), 0.0);
+    }
+
+    #[test]
+    fn test_initial_bearing() {
+        assert!((initial_bearing(0.0, 0.0, 1.0, 0.0) - 0.0).abs() < 1e-9);
+        assert!((initial_bearing(0.0, 0.0, 0.0, 1.0) - 90.0).abs() < 1e-9);
+        assert!((initial_bearing(0.0, 0.0, -1.0, 0.0) - 180.0).abs() < 1e-9);
+        assert!((initial_bearing(0.0, 0.0, 0.0, -1.0) - 270.0).abs() < 1e-9);
+        // Rome to Paris heads north-west
+        let bearing = initial_bearing(41.9028, 12.4964, 48.8566, 2.3522);
+        assert!((bearing - 317.8).abs() < 0.1, "{bearing}");
+    }
+
+    #[test]
+    fn test_mask_sky() {
+        // Smooth blue sky over a textured building, with a grey overcast
+        // column on the left.
+        let img = DynamicImage::ImageRgb8(RgbImage::from_fn(40, 40, |x, y| match (x, y) {
+            (0..=9, 0..=19) => Rgb([200, 200, 205]),
+            (_, 0..=19) => Rgb([120, 160, 220 + (y as u8 % 3)]),
+            _ if (x + y) % 2 == 0 => Rgb([90, 60, 40]),
+            _ => Rgb([160, 120, 90]),
+        }));
+        let sky = mask_sky(&img);
+        assert_eq!(sky.mask.get_pixel(20, 5)[0], 255);
+        assert_eq!(sky.mask.get_pixel(3, 5)[0], 255);
+        assert_eq!(sky.mask.get_pixel(20, 30)[0], 0);
+        assert_eq!(sky.image.get_pixel(20, 5)[3], 0);
+        assert_eq!(sky.image.get_pixel(20, 30)[3], 255);
+        assert!(sky.sky_fraction > 0.4 && sky.sky_fraction < 0.5, "{}", sky.sky_fraction);
+    }
+
     #[test]
     fn test_no_black_borders() {
         // Create image with no black borders
@@ -193,4 +732,67 @@ mod tests {
         let cropped = crop_bottom_and_right_black_border(DynamicImage::ImageRgb8(img));
         assert_eq!(cropped.dimensions(), (90, 90));
     }
+
+    #[test]
+    fn test_smart_crop() {
+        // Content in rows 20..60, wrapping from column 150 across the seam to 50
+        let img = RgbImage::from_fn(200, 100, |x, y| match (y, x) {
+            (20..=59, 150.. | ..=49) => Rgb([200, (x % 200) as u8, 0]),
+            _ => Rgb([0, 0, 0]),
+        });
+        let crop = smart_crop(&DynamicImage::ImageRgb8(img)).unwrap();
+        let c = crop.coverage;
+        assert_eq!((c.x, c.y, c.width, c.height), (150, 20, 100, 40));
+        assert_eq!(crop.image.dimensions(), (100, 40));
+        assert_eq!(crop.image.to_rgb8().get_pixel(0, 0)[1], 150);
+        assert_eq!(crop.image.to_rgb8().get_pixel(50, 0)[1], 0);
+        assert!((c.yaw_start - 90.0).abs() < 1e-9 && (c.horizontal_fov - 180.0).abs() < 1e-9);
+        assert!((c.pitch_top - 54.0).abs() < 1e-9 && (c.pitch_bottom + 18.0).abs() < 1e-9);
+        assert_eq!(c.content_fraction, 1.0);
+        assert!(!c.is_full());
+
+        let full = detect_coverage(&DynamicImage::ImageRgb8(RgbImage::from_pixel(20, 10, Rgb([9, 9, 9])))).unwrap();
+        assert!(full.is_full());
+        assert!(detect_coverage(&DynamicImage::new_rgb8(20, 10)).is_none());
+    }
+
+    /// A smooth, non-black test scene.
+    fn scene(width: u32, height: u32) -> RgbImage {
+        RgbImage::from_fn(width, height, |x, y| Rgb([100 + (x % 50) as u8, 120 + (y % 40) as u8, 140]))
+    }
+
+    #[test]
+    fn test_balance_tiles() {
+        // Four 16 px tiles; the second is underexposed
+        let mut img = scene(64, 16);
+        for y in 0..16 {
+            for x in 16..32 {
+                img.get_pixel_mut(x, y).0.iter_mut().for_each(|c| *c = (*c as f32 * 0.7) as u8);
+            }
+        }
+        let balanced = balance_tiles(DynamicImage::ImageRgb8(img), 16).to_rgb8();
+        let ratio = |x: u32| f32::from(balanced.get_pixel(x, 8)[2]) / f32::from(balanced.get_pixel(x - 1, 8)[2]);
+        assert!((ratio(16) - 1.0).abs() < 0.03, "{}", ratio(16));
+        assert!((ratio(32) - 1.0).abs() < 0.03, "{}", ratio(32));
+
+        // A single tile is returned unchanged
+        let single = DynamicImage::ImageRgb8(scene(16, 16));
+        assert_eq!(balance_tiles(single.clone(), 16), single);
+    }
+
+    #[test]
+    fn test_match_histogram() {
+        let reference = DynamicImage::ImageRgb8(scene(50, 40));
+        let mut dark = scene(50, 40);
+        dark.pixels_mut().for_each(|p| p.0 = p.0.map(|c| c / 2));
+        dark.put_pixel(0, 0, Rgb([0, 0, 0]));
+
+        let matched = match_histogram(DynamicImage::ImageRgb8(dark), &reference).to_rgb8();
+        assert_eq!(matched.get_pixel(0, 0).0, [0, 0, 0]);
+        let diff = |x, y| {
+            let (a, b) = (matched.get_pixel(x, y).0, reference.get_pixel(x, y).0);
+            (0..3).map(|c| (i32::from(a[c]) - i32::from(b[c])).abs()).max().unwrap()
+        };
+        assert!(diff(25, 20) <= 2 && diff(49, 39) <= 2, "{} {}", diff(25, 20), diff(49, 39));
+    }
 }
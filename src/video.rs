@@ -0,0 +1,576 @@
+//! Hyperlapse videos along a sequence of panoramas.
+//!
+//! [`Hyperlapse`] takes panoramas in travel order, looks forward along the
+//! path from each one, and renders a perspective frame per panorama. Frames
+//! can be streamed, saved as images, or encoded as an animated GIF or (with
+//! `ffmpeg` installed) an MP4.
+//!
+//! Frames come from a [`SequenceRenderer`], which smooths the view heading
+//! over neighbouring panoramas, levels out camera pitch and roll, and can
+//! cross-fade between panoramas, so hyperlapses do not jitter.
+//!
+//! Panoramas are downloaded through any
+//! [`PanoramaProvider`](crate::provider::PanoramaProvider), so hyperlapses
+//! work with Google as well as the other providers.
+//!
+//! Requires the `video` feature.
+
+use crate::error::{Result, StreetViewError};
+use crate::provider::PanoramaProvider;
+use crate::types::{Panorama, SaveOptions};
+use crate::utils::initial_bearing;
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, DynamicImage, Frame, Rgb, RgbImage};
+use std::f64::consts::PI;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// Panoramas downloaded ahead of the frame being rendered.
+const PREFETCH: usize = 2;
+
+/// Turns panoramas along a path into steady perspective frames.
+///
+/// Used by [`Hyperlapse`], and usable on its own with panoramas that are
+/// already downloaded. Three things keep sequences from jittering:
+///
+/// - Heading smoothing: each frame looks along a moving average of the
+///   route bearing rather than the raw bearing to the next panorama.
+/// - Pitch leveling: the camera pitch and roll from panorama metadata are
+///   cancelled, so the horizon stays level and centered.
+/// - Cross-fading: blended frames between consecutive panoramas soften the
+///   jump from one capture point to the next.
+///
+/// # Example
+///
+/// ```
+/// use rsstreetview::video::SequenceRenderer;
+///
+/// let renderer = SequenceRenderer::new().size(640, 360).smoothing(7).crossfade(2);
+/// # let panos: Vec<rsstreetview::Panorama> = Vec::new();
+/// # let images: Vec<image::RgbImage> = Vec::new();
+/// let frames = renderer.render(&panos, &images);
+/// ```
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct SequenceRenderer {
+    /// Horizontal field of view in degrees
+    pub fov: f64,
+    /// Frame width and height in pixels
+    pub size: (u32, u32),
+    /// Number of panoramas the heading is averaged over (1 disables
+    /// smoothing)
+    pub smoothing: usize,
+    /// Cancel the camera pitch and roll reported in panorama metadata
+    pub level_pitch: bool,
+    /// Blended frames inserted between consecutive panoramas
+    pub crossfade: u32,
+}
+
+impl SequenceRenderer {
+    /// Defaults: 90° field of view, 1280×720 frames, headings smoothed over
+    /// 5 panoramas, pitch leveling on, and no cross-fade.
+    pub fn new() -> Self {
+        Self {
+            fov: 90.0,
+            size: (1280, 720),
+            smoothing: 5,
+            level_pitch: true,
+            crossfade: 0,
+        }
+    }
+
+    /// Set the horizontal field of view in degrees (10-170).
+    pub fn fov(mut self, fov: f64) -> Self {
+        self.fov = fov.clamp(10.0, 170.0);
+        self
+    }
+
+    /// Set the frame size. Dimensions are rounded down to even numbers, as
+    /// most video codecs require.
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.size = ((width & !1).max(2), (height & !1).max(2));
+        self
+    }
+
+    /// Set how many panoramas the heading is averaged over (1 disables
+    /// smoothing).
+    pub fn smoothing(mut self, window: usize) -> Self {
+        self.smoothing = window.max(1);
+        self
+    }
+
+    /// Cancel the camera pitch and roll from panorama metadata (default
+    /// true).
+    pub fn level_pitch(mut self, level: bool) -> Self {
+        self.level_pitch = level;
+        self
+    }
+
+    /// Insert `frames` blended frames between consecutive panoramas
+    /// (default 0).
+    pub fn crossfade(mut self, frames: u32) -> Self {
+        self.crossfade = frames;
+        self
+    }
+
+    /// Compass heading each panorama's frame looks towards: the smoothed
+    /// bearing of the path through it.
+    ///
+    /// A single panorama looks along its own `heading`.
+    pub fn headings(&self, panos: &[Panorama]) -> Vec<f64> {
+        if panos.len() < 2 {
+            return panos.iter().map(|pano| pano.heading).collect();
+        }
+
+        let bearings: Vec<f64> = (0..panos.len())
+            .map(|i| {
+                let (from, to) = if i + 1 < panos.len() { (i, i + 1) } else { (i - 1, i) };
+                initial_bearing(panos[from].lat, panos[from].lon, panos[to].lat, panos[to].lon)
+            })
+            .collect();
+        smooth_headings(&bearings, self.smoothing)
+    }
+
+    /// Render one panorama's frame looking towards compass `heading`.
+    pub fn render_frame(&self, pano: &Panorama, image: &RgbImage, heading: f64) -> RgbImage {
+        let (pitch, roll) = match self.level_pitch {
+            true => (-pano.pitch.unwrap_or(0.0), -pano.roll.unwrap_or(0.0)),
+            false => (0.0, 0.0),
+        };
+        let (width, height) = self.size;
+        render_view(image, heading - pano.heading, pitch, roll, self.fov, width, height)
+    }
+
+    /// The cross-fade frames between two consecutive frames, excluding both.
+    pub fn crossfade_frames(&self, from: &RgbImage, to: &RgbImage) -> Vec<RgbImage> {
+        let steps = self.crossfade + 1;
+        (1..steps)
+            .map(|step| {
+                let t = f64::from(step) / f64::from(steps);
+                RgbImage::from_fn(from.width(), from.height(), |x, y| {
+                    let (a, b) = (from.get_pixel(x, y).0, to.get_pixel(x, y).0);
+                    Rgb(std::array::from_fn(|k| {
+                        (f64::from(a[k]) * (1.0 - t) + f64::from(b[k]) * t).round() as u8
+                    }))
+                })
+            })
+            .collect()
+    }
+
+    /// Render a whole sequence from downloaded panoramas, cross-fades
+    /// included: `n + (n - 1) * crossfade` frames for `n` panoramas.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `panos` and `images` differ in length.
+    pub fn render(&self, panos: &[Panorama], images: &[RgbImage]) -> Vec<RgbImage> {
+        assert_eq!(panos.len(), images.len(), "one image per panorama");
+        let mut frames: Vec<RgbImage> = Vec::new();
+        for ((pano, image), heading) in panos.iter().zip(images).zip(self.headings(panos)) {
+            let frame = self.render_frame(pano, image, heading);
+            if let Some(previous) = frames.last() {
+                frames.extend(self.crossfade_frames(previous, &frame));
+            }
+            frames.push(frame);
+        }
+        frames
+    }
+}
+
+impl Default for SequenceRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builder for a hyperlapse along an ordered list of panoramas.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rsstreetview::video::Hyperlapse;
+/// # use rsstreetview::StreetView;
+/// # #[tokio::main]
+/// # async fn main() -> rsstreetview::Result<()> {
+/// let client = StreetView::new();
+/// let mut route = Vec::new();
+/// for (lat, lon) in [(41.8982, 12.4764), (41.8984, 12.4767), (41.8986, 12.4770)] {
+///     route.push(client.search_panoramas(lat, lon).await?.remove(0));
+/// }
+///
+/// Hyperlapse::new(route).size(1280, 720).fps(12).write_mp4(&client, "route.mp4").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Hyperlapse {
+    panoramas: Vec<Panorama>,
+    renderer: SequenceRenderer,
+    zoom: u8,
+    fps: u32,
+}
+
+impl Hyperlapse {
+    /// A hyperlapse over `panoramas`, in travel order.
+    ///
+    /// Defaults: the [`SequenceRenderer`] defaults, zoom 3, and 10 frames
+    /// per second.
+    pub fn new(panoramas: Vec<Panorama>) -> Self {
+        Self {
+            panoramas,
+            renderer: SequenceRenderer::new(),
+            zoom: 3,
+            fps: 10,
+        }
+    }
+
+    /// Replace the frame renderer settings.
+    pub fn renderer(mut self, renderer: SequenceRenderer) -> Self {
+        self.renderer = renderer;
+        self
+    }
+
+    /// Set the horizontal field of view in degrees (10-170).
+    pub fn fov(mut self, fov: f64) -> Self {
+        self.renderer = self.renderer.fov(fov);
+        self
+    }
+
+    /// Set the frame size. Dimensions are rounded down to even numbers, as
+    /// most video codecs require.
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.renderer = self.renderer.size(width, height);
+        self
+    }
+
+    /// Set the zoom level panoramas are downloaded at.
+    pub fn zoom(mut self, zoom: u8) -> Self {
+        self.zoom = zoom;
+        self
+    }
+
+    /// Set the frame rate for GIF and MP4 output.
+    pub fn fps(mut self, fps: u32) -> Self {
+        self.fps = fps.max(1);
+        self
+    }
+
+    /// Set how many panoramas the heading is averaged over (1 disables
+    /// smoothing).
+    pub fn smoothing(mut self, window: usize) -> Self {
+        self.renderer = self.renderer.smoothing(window);
+        self
+    }
+
+    /// Compass heading each panorama's frame looks towards.
+    pub fn headings(&self) -> Vec<f64> {
+        self.renderer.headings(&self.panoramas)
+    }
+
+    /// Download each panorama and render its frames, in order, cross-fades
+    /// included.
+    ///
+    /// The next panoramas are downloaded while the current frame renders.
+    pub fn frames<'a>(&'a self, provider: &'a dyn PanoramaProvider) -> impl Stream<Item = Result<RgbImage>> + 'a {
+        let zoom = self.zoom;
+        let renderer = &self.renderer;
+
+        let rendered = stream::iter(self.panoramas.iter().zip(self.headings()))
+            .map(move |(pano, heading)| async move {
+                let image = provider.download(&pano.pano_id, zoom).await?;
+                let (renderer, pano) = (renderer.clone(), pano.clone());
+                tokio::task::spawn_blocking(move || renderer.render_frame(&pano, &image.to_rgb8(), heading))
+                    .await
+                    .map_err(|e| StreetViewError::IoError(std::io::Error::other(e)))
+            })
+            .buffered(PREFETCH);
+
+        rendered
+            .scan(None::<RgbImage>, move |previous, frame| {
+                let frames = match frame {
+                    Ok(frame) => {
+                        let mut frames: Vec<Result<RgbImage>> = match previous.as_ref() {
+                            Some(previous) => renderer.crossfade_frames(previous, &frame).into_iter().map(Ok).collect(),
+                            None => Vec::new(),
+                        };
+                        if renderer.crossfade > 0 {
+                            *previous = Some(frame.clone());
+                        }
+                        frames.push(Ok(frame));
+                        frames
+                    }
+                    Err(e) => vec![Err(e)],
+                };
+                futures::future::ready(Some(stream::iter(frames)))
+            })
+            .flatten()
+    }
+
+    /// Render every frame into memory.
+    pub async fn render(&self, provider: &dyn PanoramaProvider) -> Result<Vec<RgbImage>> {
+        self.frames(provider).try_collect().await
+    }
+
+    /// Save every frame to `dir` as `frame_00000.<ext>`, ..., and return
+    /// the paths in order.
+    pub async fn write_frames(
+        &self,
+        provider: &dyn PanoramaProvider,
+        dir: impl AsRef<Path>,
+        options: &SaveOptions,
+    ) -> Result<Vec<PathBuf>> {
+        let dir = dir.as_ref();
+        tokio::fs::create_dir_all(dir).await?;
+
+        let mut paths = Vec::with_capacity(self.panoramas.len());
+        let mut frames = std::pin::pin!(self.frames(provider));
+        while let Some(frame) = frames.next().await {
+            let path = dir.join(format!("frame_{:05}.{}", paths.len(), options.format.extension()));
+            options.save_async(DynamicImage::ImageRgb8(frame?), &path).await?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
+    /// Encode the hyperlapse as an endlessly looping animated GIF.
+    ///
+    /// All frames are held in memory until encoding, so keep GIFs short
+    /// and small; prefer [`write_mp4`](Self::write_mp4) for long routes.
+    pub async fn write_gif(&self, provider: &dyn PanoramaProvider, path: impl AsRef<Path>) -> Result<()> {
+        let frames = self.render(provider).await?;
+        let path = path.as_ref().to_path_buf();
+        let delay = Delay::from_numer_denom_ms(1000, self.fps);
+
+        tokio::task::spawn_blocking(move || {
+            let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+            let mut encoder = GifEncoder::new(file);
+            encoder.set_repeat(Repeat::Infinite)?;
+            let frames = frames
+                .into_iter()
+                .map(|frame| Frame::from_parts(DynamicImage::ImageRgb8(frame).to_rgba8(), 0, 0, delay));
+            encoder.encode_frames(frames)?;
+            Ok(())
+        })
+        .await
+        .map_err(|e| StreetViewError::IoError(std::io::Error::other(e)))?
+    }
+
+    /// Encode the hyperlapse as an H.264 MP4 by piping frames to `ffmpeg`.
+    ///
+    /// Frames are streamed to the encoder as they render.
+    ///
+    /// # Errors
+    ///
+    /// Returns `StreetViewError::IoError` if `ffmpeg` is not on the `PATH`,
+    /// and `StreetViewError::EncodeError` if it exits with an error.
+    pub async fn write_mp4(&self, provider: &dyn PanoramaProvider, path: impl AsRef<Path>) -> Result<()> {
+        let (width, height) = self.renderer.size;
+        let mut ffmpeg = tokio::process::Command::new("ffmpeg")
+            .args(["-y", "-loglevel", "error", "-f", "rawvideo", "-pix_fmt", "rgb24"])
+            .args(["-s", &format!("{width}x{height}"), "-r", &self.fps.to_string(), "-i", "-"])
+            .args(["-c:v", "libx264", "-pix_fmt", "yuv420p", "-movflags", "+faststart"])
+            .arg(path.as_ref())
+            .stdin(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let mut stdin = ffmpeg.stdin.take().expect("ffmpeg stdin is piped");
+        let mut stderr = ffmpeg.stderr.take().expect("ffmpeg stderr is piped");
+        let write = async move {
+            let mut frames = std::pin::pin!(self.frames(provider));
+            while let Some(frame) = frames.next().await {
+                stdin.write_all(frame?.as_raw()).await?;
+            }
+            // Closing stdin tells ffmpeg the input is complete
+            drop(stdin);
+            Ok(())
+        };
+
+        // Drain stderr while writing: once its pipe buffer fills, ffmpeg
+        // blocks on stderr and stops reading frames, and both sides wait
+        // on each other forever
+        let mut messages = Vec::new();
+        let (result, read): (Result<()>, _) = futures::join!(write, stderr.read_to_end(&mut messages));
+        let status = ffmpeg.wait().await?;
+        result?;
+        read?;
+        if !status.success() {
+            return Err(StreetViewError::EncodeError(format!(
+                "ffmpeg failed ({status}): {}",
+                String::from_utf8_lossy(&messages).trim()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Circular moving average of compass headings over a centered window.
+fn smooth_headings(headings: &[f64], window: usize) -> Vec<f64> {
+    let half = window / 2;
+    (0..headings.len())
+        .map(|i| {
+            let range = &headings[i.saturating_sub(half)..(i + half + 1).min(headings.len())];
+            let (sin, cos) = range.iter().fold((0.0, 0.0), |(sin, cos), heading| {
+                let radians = heading.to_radians();
+                (sin + radians.sin(), cos + radians.cos())
+            });
+            sin.atan2(cos).to_degrees().rem_euclid(360.0)
+        })
+        .collect()
+}
+
+/// Render a rectilinear view of an equirectangular panorama.
+///
+/// `yaw` is in degrees clockwise from the image center, `pitch` in degrees
+/// up from the horizon, and `roll` in degrees clockwise about the view
+/// direction; `fov` is the horizontal field of view.
+fn render_view(pano: &RgbImage, yaw: f64, pitch: f64, roll: f64, fov: f64, width: u32, height: u32) -> RgbImage {
+    let focal = f64::from(width) / 2.0 / (fov.to_radians() / 2.0).tan();
+    let (sin_yaw, cos_yaw) = yaw.to_radians().sin_cos();
+    let (sin_pitch, cos_pitch) = pitch.to_radians().sin_cos();
+    let (sin_roll, cos_roll) = roll.to_radians().sin_cos();
+
+    RgbImage::from_fn(width, height, |i, j| {
+        let x = f64::from(i) + 0.5 - f64::from(width) / 2.0;
+        let y = f64::from(height) / 2.0 - (f64::from(j) + 0.5);
+        // Roll about the view direction, tilt by pitch about the horizontal
+        // axis, then turn by yaw.
+        let (x, y) = (x * cos_roll + y * sin_roll, y * cos_roll - x * sin_roll);
+        let (y, z) = (y * cos_pitch + focal * sin_pitch, focal * cos_pitch - y * sin_pitch);
+        let (x, z) = (x * cos_yaw + z * sin_yaw, z * cos_yaw - x * sin_yaw);
+
+        let lon = x.atan2(z);
+        let lat = y.atan2(x.hypot(z));
+        sample_wrapped(pano, 0.5 + lon / (2.0 * PI), 0.5 - lat / PI)
+    })
+}
+
+/// Bilinear sample at texture coordinates in `[0, 1]`, wrapping around
+/// horizontally.
+fn sample_wrapped(image: &RgbImage, u: f64, v: f64) -> Rgb<u8> {
+    let (width, height) = image.dimensions();
+    let x = (u * f64::from(width) - 0.5).rem_euclid(f64::from(width));
+    let y = (v * f64::from(height) - 0.5).clamp(0.0, f64::from(height - 1));
+    let (x0, y0) = (x.floor() as u32 % width, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1) % width, (y0 + 1).min(height - 1));
+    let (fx, fy) = (x.fract(), y.fract());
+
+    let [a, b, c, d] = [(x0, y0), (x1, y0), (x0, y1), (x1, y1)].map(|(x, y)| image.get_pixel(x, y).0);
+    Rgb(std::array::from_fn(|k| {
+        let top = f64::from(a[k]) * (1.0 - fx) + f64::from(b[k]) * fx;
+        let bottom = f64::from(c[k]) * (1.0 - fx) + f64::from(d[k]) * fx;
+        (top * (1.0 - fy) + bottom * fy).round() as u8
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures::panorama;
+
+    fn pano(lat: f64, lon: f64) -> Panorama {
+        panorama(&format!("{lat},{lon}"), lat, lon)
+    }
+
+    struct Solid;
+
+    impl PanoramaProvider for Solid {
+        fn name(&self) -> &'static str {
+            "solid"
+        }
+
+        fn search(&self, _lat: f64, _lon: f64) -> futures::future::BoxFuture<'_, Result<Vec<Panorama>>> {
+            Box::pin(async { Ok(Vec::new()) })
+        }
+
+        fn download<'a>(&'a self, _pano_id: &'a str, _zoom: u8) -> futures::future::BoxFuture<'a, Result<DynamicImage>> {
+            Box::pin(async { Ok(DynamicImage::ImageRgb8(RgbImage::from_pixel(64, 32, Rgb([10, 20, 30])))) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_render_and_gif() {
+        let hyperlapse = Hyperlapse::new(vec![pano(0.0, 0.0), pano(0.0, 0.001)]).size(33, 17);
+        let frames = hyperlapse.render(&Solid).await.unwrap();
+        assert_eq!(frames.len(), 2);
+        let faded = hyperlapse.clone().renderer(SequenceRenderer::new().size(32, 16).crossfade(2));
+        assert_eq!(faded.render(&Solid).await.unwrap().len(), 4);
+        assert_eq!(frames[0].dimensions(), (32, 16));
+        assert_eq!(frames[1].get_pixel(5, 5).0, [10, 20, 30]);
+
+        let path = std::env::temp_dir().join(format!("rsstreetview_hyperlapse_{}.gif", std::process::id()));
+        hyperlapse.write_gif(&Solid, &path).await.unwrap();
+        assert!(std::fs::read(&path).unwrap().starts_with(b"GIF89a"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_headings() {
+        // East, east, then a sharp turn north.
+        let route = vec![pano(0.0, 0.0), pano(0.0, 0.001), pano(0.0, 0.002), pano(0.001, 0.002)];
+        let raw = Hyperlapse::new(route.clone()).smoothing(1).headings();
+        assert!((raw[0] - 90.0).abs() < 0.01 && (raw[2] - 0.0).abs() < 0.01);
+        assert!((raw[3] - 0.0).abs() < 0.01);
+
+        let smoothed = Hyperlapse::new(route).smoothing(3).headings();
+        assert!(smoothed[1] > 0.0 && smoothed[1] < 90.0);
+        assert!((smoothed[0] - 90.0).abs() < 0.01);
+
+        // Averaging wraps around north instead of pointing south.
+        let wrapped = smooth_headings(&[350.0, 10.0], 2);
+        assert!(wrapped[0].min(360.0 - wrapped[0]) < 0.01);
+    }
+
+    #[test]
+    fn test_render_view() {
+        // Quarter-width color bands centered on back, left, front, right.
+        let colors = [[255, 0, 0], [0, 255, 0], [0, 0, 255], [255, 255, 0]];
+        let pano = RgbImage::from_fn(64, 32, |x, _| Rgb(colors[((x + 8) / 16 % 4) as usize]));
+
+        let center = |yaw| render_view(&pano, yaw, 0.0, 0.0, 40.0, 8, 8).get_pixel(4, 4).0;
+        assert_eq!(center(0.0), colors[2]);
+        assert_eq!(center(90.0), colors[3]);
+        assert_eq!(center(-90.0), colors[1]);
+        assert_eq!(center(180.0), colors[0]);
+
+        // Looking straight up sees the top row, wherever it is.
+        let up = RgbImage::from_fn(64, 32, |_, y| if y < 2 { Rgb([255, 255, 255]) } else { Rgb([0, 0, 0]) });
+        assert_eq!(render_view(&up, 0.0, 90.0, 0.0, 20.0, 4, 4).get_pixel(2, 2).0, [255, 255, 255]);
+
+        // Rolled a quarter turn clockwise, the right edge of the frame shows
+        // what is below the camera.
+        let rolled = render_view(&up, 0.0, 0.0, 90.0, 170.0, 64, 8);
+        assert_eq!(rolled.get_pixel(0, 4).0, [255, 255, 255]);
+        assert_eq!(rolled.get_pixel(63, 4).0, [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_sequence_renderer() {
+        // Sky in the top half, ground in the bottom half.
+        let image = RgbImage::from_fn(64, 32, |_, y| if y < 16 { Rgb([200, 200, 255]) } else { Rgb([50, 50, 50]) });
+        let mut tilted = pano(0.0, 0.0);
+        tilted.pitch = Some(30.0);
+
+        // A camera pitched 30° up sees the horizon low in the frame;
+        // leveling puts it back in the middle.
+        let renderer = SequenceRenderer::new().size(16, 16).fov(60.0);
+        let leveled = renderer.render_frame(&tilted, &image, 0.0);
+        let raw = renderer.clone().level_pitch(false).render_frame(&tilted, &image, 0.0);
+        assert_eq!(leveled, render_view(&image, 0.0, -30.0, 0.0, 60.0, 16, 16));
+        assert_eq!(raw, render_view(&image, 0.0, 0.0, 0.0, 60.0, 16, 16));
+
+        let black = RgbImage::new(2, 2);
+        let white = RgbImage::from_pixel(2, 2, Rgb([255, 255, 255]));
+        let fades = renderer.clone().crossfade(3).crossfade_frames(&black, &white);
+        let levels: Vec<u8> = fades.iter().map(|frame| frame.get_pixel(0, 0)[0]).collect();
+        assert_eq!(levels, [64, 128, 191]);
+
+        let panos = [pano(0.0, 0.0), pano(0.0, 0.001), pano(0.0, 0.002)];
+        let images = [image.clone(), image.clone(), image];
+        assert_eq!(renderer.clone().crossfade(2).render(&panos, &images).len(), 7);
+        assert_eq!(renderer.render(&panos, &images).len(), 3);
+    }
+}
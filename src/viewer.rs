@@ -0,0 +1,468 @@
+//! Static HTML viewer export.
+//!
+//! Writes a self-contained folder with an interactive 360° viewer for a
+//! downloaded panorama. The folder needs no server and makes no network
+//! requests: the imagery is embedded into `index.html` as a data URI so it
+//! also works when opened straight from disk (`file://`), where browsers
+//! refuse to load local images into WebGL textures.
+//!
+//! With [`ViewerOptions::pannellum`] the page embeds a pannellum build,
+//! with its controls, fullscreen mode and compass. The crate does not ship
+//! pannellum, so the caller supplies the release files; without them the
+//! page falls back to a small built-in WebGL renderer. Both orient the view
+//! by the panorama's heading: pannellum receives it as `northOffset`, and
+//! the built-in viewer shows the compass bearing being looked at.
+
+use crate::error::Result;
+use crate::save::encode_panorama;
+use crate::types::{ImageFormat, Panorama, SaveOptions};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use image::{DynamicImage, GenericImageView};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Most GPUs cap texture size at 8192 or 16384 pixels.
+const DEFAULT_MAX_WIDTH: u32 = 8192;
+
+/// Options for exporting a panorama viewer.
+#[derive(Debug, Clone)]
+pub struct ViewerOptions {
+    /// Page title (defaults to the panorama ID)
+    pub title: Option<String>,
+    /// Maximum width of the embedded texture (default 8192)
+    pub max_width: u32,
+    /// JPEG quality of the embedded and full-size images (1-100, default 85)
+    pub jpeg_quality: u8,
+    /// Also write the full-resolution image next to `index.html` (default true)
+    pub include_full_image: bool,
+    /// pannellum build to embed instead of the built-in viewer
+    pub pannellum: Option<PannellumAssets>,
+}
+
+impl ViewerOptions {
+    /// Create default viewer options.
+    pub fn new() -> Self {
+        Self {
+            title: None,
+            max_width: DEFAULT_MAX_WIDTH,
+            jpeg_quality: 85,
+            include_full_image: true,
+            pannellum: None,
+        }
+    }
+
+    /// Set the page title.
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Set the maximum width of the embedded texture.
+    pub fn max_width(mut self, width: u32) -> Self {
+        self.max_width = width.max(256);
+        self
+    }
+
+    /// Set JPEG quality (1-100).
+    pub fn jpeg_quality(mut self, quality: u8) -> Self {
+        self.jpeg_quality = quality.clamp(1, 100);
+        self
+    }
+
+    /// Whether to write the full-resolution image alongside the viewer.
+    pub fn include_full_image(mut self, include: bool) -> Self {
+        self.include_full_image = include;
+        self
+    }
+
+    /// Embed a pannellum build instead of the built-in viewer.
+    pub fn pannellum(mut self, assets: PannellumAssets) -> Self {
+        self.pannellum = Some(assets);
+        self
+    }
+}
+
+impl Default for ViewerOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A pannellum build to embed in exported viewers.
+///
+/// pannellum is MIT-licensed; its license header travels with the embedded
+/// script.
+#[derive(Clone)]
+pub struct PannellumAssets {
+    js: String,
+    css: String,
+}
+
+impl PannellumAssets {
+    /// Use the given `pannellum.js` and `pannellum.css` sources.
+    pub fn new(js: impl Into<String>, css: impl Into<String>) -> Self {
+        Self {
+            js: js.into(),
+            css: css.into(),
+        }
+    }
+
+    /// Read `pannellum.js` and `pannellum.css` from a directory, such as
+    /// the `build/` folder of a pannellum release (2.5 or later).
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        Ok(Self::new(
+            fs::read_to_string(dir.join("pannellum.js"))?,
+            fs::read_to_string(dir.join("pannellum.css"))?,
+        ))
+    }
+}
+
+impl fmt::Debug for PannellumAssets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PannellumAssets")
+            .field("js", &format_args!("{} bytes", self.js.len()))
+            .field("css", &format_args!("{} bytes", self.css.len()))
+            .finish()
+    }
+}
+
+/// Export a panorama as a self-contained static HTML viewer.
+///
+/// Creates `out_dir` containing:
+/// - `index.html` - the viewer, with the imagery embedded
+/// - `metadata.json` - the panorama metadata shown in the side panel
+/// - `panorama.jpg` - the full-resolution image (unless disabled)
+///
+/// Returns the path to `index.html`.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rsstreetview::{StreetView, viewer::{export_viewer, ViewerOptions}};
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = StreetView::new();
+/// let panos = client.search_panoramas(41.8982208, 12.4764804).await?;
+/// let image = client.download_panorama(&panos[0].pano_id, 4).await?;
+///
+/// let index = export_viewer(&image, &panos[0], "evidence/trevi", &ViewerOptions::new())?;
+/// println!("Open {}", index.display());
+/// # Ok(())
+/// # }
+/// ```
+pub fn export_viewer(
+    img: &DynamicImage,
+    pano: &Panorama,
+    out_dir: impl AsRef<Path>,
+    options: &ViewerOptions,
+) -> Result<PathBuf> {
+    let out_dir = out_dir.as_ref();
+    fs::create_dir_all(out_dir)?;
+
+    let save_options = SaveOptions::new()
+        .format(ImageFormat::Jpeg)
+        .jpeg_quality(options.jpeg_quality);
+
+    if options.include_full_image {
+        save_options.save(img, out_dir.join("panorama.jpg"))?;
+    }
+
+    // Downscale the embedded texture to something every GPU can handle
+    let (width, height) = img.dimensions();
+    let texture = if width > options.max_width {
+        let new_height = (height as u64 * options.max_width as u64 / width as u64) as u32;
+        img.resize_exact(
+            options.max_width,
+            new_height.max(1),
+            image::imageops::FilterType::Triangle,
+        )
+    } else {
+        img.clone()
+    };
+    let texture_bytes = encode_panorama(&texture, &save_options)?;
+
+    let metadata = serde_json::to_string_pretty(pano)
+        .map_err(|e| crate::error::StreetViewError::ParseError(e.to_string()))?;
+    fs::write(out_dir.join("metadata.json"), &metadata)?;
+
+    let title = escape_html(&options.title.clone().unwrap_or_else(|| pano.pano_id.clone()));
+    let metadata = metadata.replace("</", "<\\/");
+    let image = BASE64.encode(&texture_bytes);
+    let html = match &options.pannellum {
+        Some(pannellum) => fill_template(
+            PANNELLUM_TEMPLATE,
+            &[
+                ("TITLE", &title),
+                ("METADATA", &metadata),
+                ("IMAGE", &image),
+                ("PANNELLUM_CSS", &pannellum.css.replace("</style", "<\\/style")),
+                ("PANNELLUM_JS", &pannellum.js.replace("</script", "<\\/script")),
+            ],
+        ),
+        None => fill_template(
+            VIEWER_TEMPLATE,
+            &[("TITLE", &title), ("METADATA", &metadata), ("IMAGE", &image)],
+        ),
+    };
+
+    let index = out_dir.join("index.html");
+    fs::write(&index, html)?;
+    Ok(index)
+}
+
+/// Substitute `{{NAME}}` placeholders in one pass over `template`, so
+/// placeholders appearing in the values themselves are left alone.
+fn fill_template(template: &str, values: &[(&str, &str)]) -> String {
+    let mut out = String::with_capacity(template.len() + values.iter().map(|(_, value)| value.len()).sum::<usize>());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let value = after.find("}}").and_then(|end| {
+            let (_, value) = values.iter().find(|(name, _)| *name == &after[..end])?;
+            Some((end, value))
+        });
+        match value {
+            Some((end, value)) => {
+                out.push_str(value);
+                rest = &after[end + 2..];
+            }
+            None => {
+                out.push_str("{{");
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Escape text for inclusion in HTML.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Single-file WebGL equirectangular viewer.
+const VIEWER_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{{TITLE}}</title>
+<style>
+  html, body { margin: 0; height: 100%; overflow: hidden; background: #000; font-family: sans-serif; }
+  canvas { width: 100%; height: 100%; display: block; cursor: grab; }
+  #panel { position: absolute; top: 10px; left: 10px; max-width: 320px; padding: 10px;
+           background: rgba(0, 0, 0, 0.6); color: #eee; font-size: 12px; border-radius: 4px; }
+  #panel pre { margin: 6px 0 0; white-space: pre-wrap; word-break: break-all; }
+  #compass { position: absolute; bottom: 10px; left: 50%; transform: translateX(-50%); padding: 4px 8px;
+             background: rgba(0, 0, 0, 0.6); color: #eee; font-size: 14px; border-radius: 4px; }
+</style>
+</head>
+<body>
+<canvas id="view"></canvas>
+<div id="panel"><strong>{{TITLE}}</strong><pre id="meta"></pre></div>
+<div id="compass"></div>
+<script id="metadata" type="application/json">{{METADATA}}</script>
+<script>
+(function () {
+  var meta = JSON.parse(document.getElementById("metadata").textContent);
+  document.getElementById("meta").textContent = JSON.stringify(meta, null, 2);
+
+  var canvas = document.getElementById("view");
+  var gl = canvas.getContext("webgl");
+  if (!gl) { document.body.insertAdjacentHTML("beforeend", "<p style='color:#fff'>WebGL unavailable</p>"); return; }
+
+  var vs = "attribute vec2 p; varying vec2 uv; void main() { uv = p; gl_Position = vec4(p, 0.0, 1.0); }";
+  var fs = [
+    "precision highp float;",
+    "varying vec2 uv; uniform sampler2D tex; uniform float yaw, pitch, focal, aspect;",
+    "void main() {",
+    "  vec3 d = normalize(vec3(uv.x * aspect, uv.y, focal));",
+    "  float cp = cos(pitch), sp = sin(pitch);",
+    "  d = vec3(d.x, d.y * cp + d.z * sp, -d.y * sp + d.z * cp);",
+    "  float cy = cos(yaw), sy = sin(yaw);",
+    "  d = vec3(d.x * cy + d.z * sy, d.y, -d.x * sy + d.z * cy);",
+    "  float u = atan(d.x, d.z) / 6.28318530718 + 0.5;",
+    "  float v = acos(clamp(d.y, -1.0, 1.0)) / 3.14159265359;",
+    "  gl_FragColor = texture2D(tex, vec2(u, v));",
+    "}"
+  ].join("\n");
+
+  function shader(type, src) {
+    var s = gl.createShader(type); gl.shaderSource(s, src); gl.compileShader(s); return s;
+  }
+  var prog = gl.createProgram();
+  gl.attachShader(prog, shader(gl.VERTEX_SHADER, vs));
+  gl.attachShader(prog, shader(gl.FRAGMENT_SHADER, fs));
+  gl.linkProgram(prog);
+  gl.useProgram(prog);
+
+  gl.bindBuffer(gl.ARRAY_BUFFER, gl.createBuffer());
+  gl.bufferData(gl.ARRAY_BUFFER, new Float32Array([-1, -1, 1, -1, -1, 1, 1, 1]), gl.STATIC_DRAW);
+  var loc = gl.getAttribLocation(prog, "p");
+  gl.enableVertexAttribArray(loc);
+  gl.vertexAttribPointer(loc, 2, gl.FLOAT, false, 0, 0);
+
+  // yaw is relative to the image center, which faces meta.heading
+  var yaw = 0, pitch = 0, fov = 90;
+  var compass = document.getElementById("compass");
+  var u = {};
+  ["yaw", "pitch", "focal", "aspect"].forEach(function (n) { u[n] = gl.getUniformLocation(prog, n); });
+
+  function draw() {
+    canvas.width = canvas.clientWidth * devicePixelRatio;
+    canvas.height = canvas.clientHeight * devicePixelRatio;
+    gl.viewport(0, 0, canvas.width, canvas.height);
+    gl.uniform1f(u.yaw, yaw);
+    gl.uniform1f(u.pitch, pitch);
+    gl.uniform1f(u.focal, 1 / Math.tan(fov * Math.PI / 360));
+    gl.uniform1f(u.aspect, canvas.width / canvas.height);
+    gl.drawArrays(gl.TRIANGLE_STRIP, 0, 4);
+
+    var bearing = (((meta.heading || 0) + yaw * 180 / Math.PI) % 360 + 360) % 360;
+    compass.textContent = Math.round(bearing) % 360 + "\u00b0 " + ["N", "NE", "E", "SE", "S", "SW", "W", "NW"][Math.round(bearing / 45) % 8];
+  }
+
+  var img = new Image();
+  img.onload = function () {
+    gl.bindTexture(gl.TEXTURE_2D, gl.createTexture());
+    gl.texImage2D(gl.TEXTURE_2D, 0, gl.RGB, gl.RGB, gl.UNSIGNED_BYTE, img);
+    gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_MIN_FILTER, gl.LINEAR);
+    gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_WRAP_S, gl.CLAMP_TO_EDGE);
+    gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_WRAP_T, gl.CLAMP_TO_EDGE);
+    draw();
+  };
+  img.src = "data:image/jpeg;base64,{{IMAGE}}";
+
+  var drag = null;
+  canvas.addEventListener("mousedown", function (e) { drag = [e.clientX, e.clientY]; });
+  window.addEventListener("mouseup", function () { drag = null; });
+  window.addEventListener("mousemove", function (e) {
+    if (!drag) return;
+    var scale = fov / canvas.clientHeight * Math.PI / 180;
+    yaw -= (e.clientX - drag[0]) * scale;
+    pitch = Math.max(-1.55, Math.min(1.55, pitch + (e.clientY - drag[1]) * scale));
+    drag = [e.clientX, e.clientY];
+    draw();
+  });
+  canvas.addEventListener("wheel", function (e) {
+    e.preventDefault();
+    fov = Math.max(20, Math.min(120, fov + e.deltaY * 0.05));
+    draw();
+  }, { passive: false });
+  window.addEventListener("resize", draw);
+})();
+</script>
+</body>
+</html>
+"#;
+
+/// Page embedding a pannellum build.
+const PANNELLUM_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{{TITLE}}</title>
+<style>{{PANNELLUM_CSS}}</style>
+<style>
+  html, body { margin: 0; height: 100%; overflow: hidden; background: #000; font-family: sans-serif; }
+  #view { width: 100%; height: 100%; }
+  #panel { position: absolute; bottom: 10px; left: 10px; max-width: 320px; max-height: 50%; overflow: auto;
+           padding: 10px; background: rgba(0, 0, 0, 0.6); color: #eee; font-size: 12px; border-radius: 4px; z-index: 10; }
+  #panel pre { margin: 6px 0 0; white-space: pre-wrap; word-break: break-all; }
+</style>
+</head>
+<body>
+<div id="view"></div>
+<div id="panel"><strong>{{TITLE}}</strong><pre id="meta"></pre></div>
+<script id="metadata" type="application/json">{{METADATA}}</script>
+<script>{{PANNELLUM_JS}}</script>
+<script>
+(function () {
+  var meta = JSON.parse(document.getElementById("metadata").textContent);
+  document.getElementById("meta").textContent = JSON.stringify(meta, null, 2);
+
+  // pannellum loads the panorama with XHR, which browsers refuse for data
+  // URIs in some contexts; a blob URL works everywhere, including file://
+  var data = atob("{{IMAGE}}");
+  var bytes = new Uint8Array(data.length);
+  for (var i = 0; i < data.length; i++) bytes[i] = data.charCodeAt(i);
+  var url = URL.createObjectURL(new Blob([bytes], { type: "image/jpeg" }));
+
+  pannellum.viewer("view", {
+    type: "equirectangular",
+    panorama: url,
+    autoLoad: true,
+    northOffset: meta.heading || 0,
+    compass: true,
+    hfov: 90
+  });
+})();
+</script>
+</body>
+</html>
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures::panorama;
+    use crate::types::{CaptureDate, DateConfidence};
+    use image::RgbImage;
+
+    #[test]
+    fn test_export_viewer() {
+        let img = DynamicImage::ImageRgb8(RgbImage::new(512, 256));
+        let pano = Panorama {
+            heading: 90.0,
+            date: CaptureDate::new(2020, 5),
+            date_confidence: DateConfidence::Exact,
+            ..panorama("test<pano>", 41.0, 12.0)
+        };
+
+        let dir = std::env::temp_dir().join("rsstreetview_viewer");
+        let options = ViewerOptions::new().max_width(256);
+        let index = export_viewer(&img, &pano, &dir, &options).unwrap();
+
+        let html = fs::read_to_string(&index).unwrap();
+        assert!(html.contains("<title>test&lt;pano&gt;</title>"));
+        assert!(html.contains("data:image/jpeg;base64,/9j/"));
+        assert!(dir.join("metadata.json").exists());
+        assert!(dir.join("panorama.jpg").exists());
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_export_viewer_pannellum() {
+        let img = DynamicImage::ImageRgb8(RgbImage::new(512, 256));
+        let pano = panorama("test_pano", 41.0, 12.0);
+
+        let dir = std::env::temp_dir().join("rsstreetview_viewer_pannellum");
+        let assets = PannellumAssets::new("window.pannellum = {}; // </script>", ".pnlm-container {}");
+        let index = export_viewer(&img, &pano, &dir, &ViewerOptions::new().pannellum(assets)).unwrap();
+
+        let html = fs::read_to_string(&index).unwrap();
+        assert!(html.contains("<style>.pnlm-container {}</style>"));
+        assert!(html.contains("window.pannellum = {}; // <\\/script>"));
+        assert!(html.contains("northOffset: meta.heading"));
+        assert!(html.contains("atob(\"/9j/"));
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_fill_template() {
+        let values = [("TITLE", "{{IMAGE}}"), ("IMAGE", "abc")];
+        assert_eq!(fill_template("<{{TITLE}}|{{IMAGE}}>", &values), "<{{IMAGE}}|abc>");
+        assert_eq!(fill_template("{{OTHER}} {{IMAGE", &values), "{{OTHER}} {{IMAGE");
+    }
+}
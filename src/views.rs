@@ -1,29 +1,89 @@
-use crate::error::Result;
-use crate::download::{download_panorama};
+use crate::error::{Result, StreetViewError};
+use crate::types::Panorama;
+#[cfg(feature = "unofficial")]
+use crate::download::cached_panorama;
+use image::imageops::FilterType;
 use image::{DynamicImage, GenericImageView};
+#[cfg(feature = "unofficial")]
+use crate::http::Http;
+#[cfg(feature = "unofficial")]
+use crate::pano_id::PanoId;
+#[cfg(feature = "unofficial")]
 use reqwest::Client;
+use std::fmt;
+use std::str::FromStr;
 
-/// Cardinal direction for view extraction.
-#[derive(Debug, Clone, Copy)]
+/// Cardinal or intercardinal direction for view extraction, relative to
+/// the panorama's heading.
+///
+/// With the panorama's front facing north, `Front` is north, `FrontRight`
+/// north-east, and so on clockwise.
+///
+/// # Example
+///
+/// ```
+/// # use rsstreetview::{Direction, ViewConfig};
+/// // Eight views, labelled with compass names
+/// let views: Vec<(String, ViewConfig)> = Direction::iter()
+///     .map(|dir| (dir.compass_name().to_string(), ViewConfig::from(dir).fov(60)))
+///     .collect();
+/// assert_eq!(views[1].0, "north_east");
+/// assert_eq!(views[1].1.heading, 45);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum Direction {
     /// Front view (heading 0°)
     Front,
+    /// Front right view (heading 45°)
+    FrontRight,
     /// Right view (heading 90°)
     Right,
+    /// Back right view (heading 135°)
+    BackRight,
     /// Back view (heading 180°)
     Back,
+    /// Back left view (heading 225°)
+    BackLeft,
     /// Left view (heading 270°)
     Left,
+    /// Front left view (heading 315°)
+    FrontLeft,
 }
 
 impl Direction {
+    /// All eight directions, clockwise from `Front`.
+    pub const ALL: [Direction; 8] = [
+        Direction::Front,
+        Direction::FrontRight,
+        Direction::Right,
+        Direction::BackRight,
+        Direction::Back,
+        Direction::BackLeft,
+        Direction::Left,
+        Direction::FrontLeft,
+    ];
+
+    /// The four cardinal directions, clockwise from `Front`.
+    pub const CARDINAL: [Direction; 4] = [Direction::Front, Direction::Right, Direction::Back, Direction::Left];
+
+    /// Iterate over all eight directions, clockwise from `Front`.
+    pub fn iter() -> impl Iterator<Item = Direction> {
+        Self::ALL.into_iter()
+    }
+
     /// Get the heading in degrees for this direction.
     pub fn heading(&self) -> u16 {
         match self {
             Direction::Front => 0,
+            Direction::FrontRight => 45,
             Direction::Right => 90,
+            Direction::BackRight => 135,
             Direction::Back => 180,
+            Direction::BackLeft => 225,
             Direction::Left => 270,
+            Direction::FrontLeft => 315,
         }
     }
 
@@ -31,15 +91,172 @@ impl Direction {
     pub fn name(&self) -> &'static str {
         match self {
             Direction::Front => "front",
+            Direction::FrontRight => "front_right",
             Direction::Right => "right",
+            Direction::BackRight => "back_right",
             Direction::Back => "back",
+            Direction::BackLeft => "back_left",
             Direction::Left => "left",
+            Direction::FrontLeft => "front_left",
+        }
+    }
+
+    /// The compass name of this direction when the panorama's front faces
+    /// north (e.g. `"north_east"` for `FrontRight`).
+    pub fn compass_name(&self) -> &'static str {
+        match self {
+            Direction::Front => "north",
+            Direction::FrontRight => "north_east",
+            Direction::Right => "east",
+            Direction::BackRight => "south_east",
+            Direction::Back => "south",
+            Direction::BackLeft => "south_west",
+            Direction::Left => "west",
+            Direction::FrontLeft => "north_west",
         }
     }
 }
 
+/// Interpolation used when resizing an extracted view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ResampleFilter {
+    /// Nearest neighbor (fastest, blocky)
+    Nearest,
+    /// Bilinear, good for thumbnails
+    Bilinear,
+    /// Bicubic (Catmull-Rom), a middle ground
+    Bicubic,
+    /// Gaussian (soft)
+    Gaussian,
+    /// Lanczos with a window of 3 (sharpest, slowest)
+    #[default]
+    Lanczos3,
+}
+
+impl From<ResampleFilter> for FilterType {
+    fn from(filter: ResampleFilter) -> Self {
+        match filter {
+            ResampleFilter::Nearest => FilterType::Nearest,
+            ResampleFilter::Bilinear => FilterType::Triangle,
+            ResampleFilter::Bicubic => FilterType::CatmullRom,
+            ResampleFilter::Gaussian => FilterType::Gaussian,
+            ResampleFilter::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
+}
+
+impl From<FilterType> for ResampleFilter {
+    fn from(filter: FilterType) -> Self {
+        match filter {
+            FilterType::Nearest => ResampleFilter::Nearest,
+            FilterType::Triangle => ResampleFilter::Bilinear,
+            FilterType::CatmullRom => ResampleFilter::Bicubic,
+            FilterType::Gaussian => ResampleFilter::Gaussian,
+            FilterType::Lanczos3 => ResampleFilter::Lanczos3,
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Direction {
+    type Err = StreetViewError;
+
+    /// Parse a direction name (`front`, `back_left`, ...), a compass name
+    /// (`north`, `south_west`, ...), or a compass abbreviation (`n`, `sw`,
+    /// ...), ignoring case and treating `-` like `_`.
+    fn from_str(s: &str) -> Result<Self> {
+        let name = s.trim().to_ascii_lowercase().replace('-', "_");
+        let abbreviations = ["n", "ne", "e", "se", "s", "sw", "w", "nw"];
+        Direction::iter()
+            .zip(abbreviations)
+            .find(|(dir, abbreviation)| [dir.name(), dir.compass_name(), abbreviation].contains(&name.as_str()))
+            .map(|(dir, _)| dir)
+            .ok_or_else(|| StreetViewError::ParseError(format!("Unknown direction {s:?}")))
+    }
+}
+
+impl From<Direction> for ViewConfig {
+    fn from(direction: Direction) -> Self {
+        Self::from_direction(direction)
+    }
+}
+
+/// The compact form, e.g. `h90,fov100,p-10,z4,s800x600`: heading, field
+/// of view, pitch, zoom, and size when set. The filter is not included.
+impl fmt::Display for ViewConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "h{},fov{},p{},z{}", self.heading, self.fov, self.pitch, self.zoom)?;
+        if let Some((width, height)) = self.size {
+            write!(f, ",s{width}x{height}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ViewConfig {
+    type Err = StreetViewError;
+
+    /// Parse the compact form: comma-separated `h<heading>`, `fov<degrees>`,
+    /// `p<pitch>`, `z<zoom>`, and `s<width>x<height>`, in any order, or a
+    /// [`Direction`] in place of `h`. Missing values take
+    /// [`ViewConfig::new`]'s defaults, and out of range values are an error.
+    ///
+    /// ```
+    /// # use rsstreetview::ViewConfig;
+    /// let config: ViewConfig = "h90,fov100,p-10,z4".parse()?;
+    /// assert_eq!((config.heading, config.fov, config.pitch, config.zoom), (90, 100, -10, 4));
+    ///
+    /// let config: ViewConfig = "back_left,s640x480".parse()?;
+    /// assert_eq!((config.heading, config.size), (225, Some((640, 480))));
+    /// assert_eq!(config.to_string(), "h225,fov90,p0,z3,s640x480");
+    /// # Ok::<(), rsstreetview::StreetViewError>(())
+    /// ```
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = |token: &str| StreetViewError::ParseError(format!("Invalid view {s:?}: bad {token:?}"));
+        let mut config = ViewConfig::new(0);
+        for token in s.split(',').map(str::trim).filter(|token| !token.is_empty()) {
+            if let Ok(direction) = token.parse::<Direction>() {
+                config.heading = direction.heading();
+                continue;
+            }
+            if let Some(fov) = prefixed(token, "fov").filter(|fov: &u16| (1..=180).contains(fov)) {
+                config.fov = fov;
+            } else if let Some(heading) = prefixed(token, "h").filter(|heading: &u16| *heading < 360) {
+                config.heading = heading;
+            } else if let Some(pitch) = prefixed(token, "p").filter(|pitch: &i16| pitch.abs() <= 90) {
+                config.pitch = pitch;
+            } else if let Some(zoom) = prefixed(token, "z").filter(|zoom: &u8| (1..=7).contains(zoom)) {
+                config.zoom = zoom;
+            } else if let Some(size) = token
+                .strip_prefix('s')
+                .and_then(|size| size.split_once('x'))
+                .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+            {
+                config.size = Some(size);
+            } else {
+                return Err(invalid(token));
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// The value after `prefix` in a compact view token.
+fn prefixed<T: FromStr>(token: &str, prefix: &str) -> Option<T> {
+    token.strip_prefix(prefix)?.parse().ok()
+}
+
 /// Configuration for extracting a view from a panorama.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct ViewConfig {
     /// Heading in degrees (0-360, where 0 is front/north)
     pub heading: u16,
@@ -60,9 +277,14 @@ pub struct ViewConfig {
     /// - Zoom 6: 32768×16384
     /// - Zoom 7: 65536×32768
     pub zoom: u8,
+    /// Interpolation used when resizing to `size` (default: Lanczos3)
+    pub filter: ResampleFilter,
 }
 
 impl ViewConfig {
+    /// Widest field of view [`validate`](Self::validate) accepts, in degrees.
+    pub const MAX_PERSPECTIVE_FOV: u16 = 140;
+
     /// Create a new view configuration with default values.
     ///
     /// By default, uses native resolution from the panorama (no resizing).
@@ -73,10 +295,11 @@ impl ViewConfig {
             pitch: 0,
             size: None,  // Native resolution by default
             zoom: 3,
+            filter: ResampleFilter::Lanczos3,
         }
     }
 
-    /// Create a view for a cardinal direction.
+    /// Create a view for a cardinal or intercardinal direction.
     pub fn from_direction(direction: Direction) -> Self {
         Self::new(direction.heading())
     }
@@ -116,6 +339,106 @@ impl ViewConfig {
         self.zoom = zoom.clamp(1, 7);
         self
     }
+
+    /// Set the interpolation used when resizing to a custom size.
+    ///
+    /// Lanczos3 is the default and the sharpest; `FilterType::Nearest` or
+    /// `FilterType::Triangle` (bilinear) are much faster for thumbnails,
+    /// and `FilterType::CatmullRom` (bicubic) is a middle ground.
+    pub fn filter(mut self, filter: impl Into<ResampleFilter>) -> Self {
+        self.filter = filter.into();
+        self
+    }
+
+    /// Check the configuration instead of relying on the builder's silent
+    /// clamping, reporting every problem found.
+    ///
+    /// Besides out of range values, this rejects fields of view above
+    /// [`MAX_PERSPECTIVE_FOV`](Self::MAX_PERSPECTIVE_FOV), which the
+    /// perspective camera of [`intrinsics`](Self::intrinsics) cannot
+    /// represent without extreme stretching, and views that reach past the
+    /// zenith or nadir, which are clipped at the panorama's edge.
+    ///
+    /// ```
+    /// # use rsstreetview::ViewConfig;
+    /// assert!(ViewConfig::new(90).fov(100).validate().is_ok());
+    ///
+    /// let mut config = ViewConfig::new(90).pitch(80);
+    /// config.fov = 170;
+    /// let error = config.validate().unwrap_err().to_string();
+    /// assert!(error.contains("fov 170°") && error.contains("zenith"));
+    /// ```
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = Vec::new();
+        if self.heading >= 360 {
+            problems.push(format!("heading {}° is not in 0-359", self.heading));
+        }
+        if self.fov == 0 {
+            problems.push("fov must be above 0°".to_string());
+        } else if self.fov > Self::MAX_PERSPECTIVE_FOV {
+            problems.push(format!(
+                "fov {}° is above {}°, the widest a perspective view can show without extreme stretching",
+                self.fov,
+                Self::MAX_PERSPECTIVE_FOV
+            ));
+        }
+        if !(-90..=90).contains(&self.pitch) {
+            problems.push(format!("pitch {}° is not in -90-90", self.pitch));
+        }
+        if !(1..=7).contains(&self.zoom) {
+            problems.push(format!("zoom {} is not in 1-7", self.zoom));
+        }
+        if let Some((width, height)) = self.size.filter(|&(width, height)| width == 0 || height == 0) {
+            problems.push(format!("size {width}x{height} is empty"));
+        }
+
+        let aspect = self.size.filter(|&(w, h)| w > 0 && h > 0).map_or(1.0, |(w, h)| f64::from(w) / f64::from(h));
+        let half_vertical = f64::from(self.fov.min(180)) / 2.0 / aspect;
+        if self.pitch.abs() <= 90 && f64::from(self.pitch.unsigned_abs()) + half_vertical > 90.0 {
+            problems.push(format!(
+                "pitch {}° with a vertical fov of {:.0}° reaches past the {} and would be clipped",
+                self.pitch,
+                half_vertical * 2.0,
+                if self.pitch >= 0 { "zenith" } else { "nadir" }
+            ));
+        }
+
+        match problems.is_empty() {
+            true => Ok(()),
+            false => Err(StreetViewError::InvalidViewConfig(problems.join("; "))),
+        }
+    }
+
+    /// [`validate`](Self::validate) the configuration, returning it if valid.
+    ///
+    /// ```
+    /// # use rsstreetview::ViewConfig;
+    /// let config = ViewConfig::new(45).fov(60).size(1280, 720).try_build()?;
+    /// # Ok::<(), rsstreetview::StreetViewError>(())
+    /// ```
+    pub fn try_build(self) -> Result<Self> {
+        self.validate()?;
+        Ok(self)
+    }
+
+    /// Pinhole camera intrinsics for this view saved at `width`×`height`.
+    ///
+    /// The focal lengths give the field of view the view actually covers,
+    /// after clipping at the panorama's edges. Views are cropped from the
+    /// equirectangular panorama rather than reprojected, so this is an
+    /// approximation that holds best for narrow fields of view.
+    pub fn intrinsics(&self, width: u32, height: u32) -> CameraIntrinsics {
+        let (heading_range, pitch_range, _) = view_geometry(self);
+        let focal = |pixels: u32, degrees: f64| f64::from(pixels) / 2.0 / (degrees.to_radians() / 2.0).tan();
+        CameraIntrinsics {
+            width,
+            height,
+            fx: focal(width, heading_range.1 - heading_range.0),
+            fy: focal(height, pitch_range.1 - pitch_range.0),
+            cx: f64::from(width) / 2.0,
+            cy: f64::from(height) / 2.0,
+        }
+    }
 }
 
 impl Default for ViewConfig {
@@ -152,13 +475,34 @@ impl Default for ViewConfig {
 /// # Ok(())
 /// # }
 /// ```
+#[cfg(feature = "unofficial")]
 pub async fn extract_view(
     client: &Client,
+    pano_id: impl Into<PanoId>,
+    config: &ViewConfig,
+) -> Result<DynamicImage> {
+    let pano_id = PanoId::new(pano_id)?;
+    fetch_and_extract_view(&Http::new(client.clone()), pano_id.as_str(), config).await
+}
+
+#[cfg(feature = "unofficial")]
+pub(crate) async fn fetch_and_extract_view_with_bounds(
+    http: &Http,
+    pano_id: &str,
+    config: &ViewConfig,
+) -> Result<ExtractedView> {
+    let panorama = cached_panorama(http, pano_id, config.zoom).await?;
+    extract_view_with_bounds(&panorama, config)
+}
+
+#[cfg(feature = "unofficial")]
+pub(crate) async fn fetch_and_extract_view(
+    http: &Http,
     pano_id: &str,
     config: &ViewConfig,
 ) -> Result<DynamicImage> {
     // Download panorama at the configured zoom level
-    let panorama = download_panorama(client, pano_id, config.zoom).await?;
+    let panorama = cached_panorama(http, pano_id, config.zoom).await?;
 
     // Extract the view from the panorama
     extract_view_from_panorama(&panorama, config)
@@ -178,7 +522,178 @@ pub fn extract_view_from_panorama(
     config: &ViewConfig,
 ) -> Result<DynamicImage> {
     let (pano_width, pano_height) = panorama.dimensions();
+    let (x_start, y_start, crop_width, crop_height) = crop_region(config, pano_width, pano_height);
 
+    // Simple crop (doesn't handle wrapping around the edges yet)
+    let cropped = panorama.crop_imm(x_start, y_start, crop_width, crop_height);
+
+    Ok(resize_view(cropped, config))
+}
+
+/// Resize a cropped view to `config.size`, if one is set.
+pub(crate) fn resize_view(cropped: DynamicImage, config: &ViewConfig) -> DynamicImage {
+    // Resize if custom size specified, otherwise use native resolution
+    if let Some((width, height)) = config.size {
+        // Resize to custom dimensions
+        let resized = image::imageops::resize(
+            &cropped,
+            width,
+            height,
+            config.filter.into(),
+        );
+
+        // Convert to RGB (resize returns RGBA)
+        let rgb_image = DynamicImage::ImageRgba8(resized).to_rgb8();
+        DynamicImage::ImageRgb8(rgb_image)
+    } else {
+        // Use native resolution (no resize)
+        cropped
+    }
+}
+
+/// Pinhole camera intrinsics of an extracted view, in pixels, as used by
+/// photogrammetry tools such as COLMAP (`PINHOLE` camera model).
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CameraIntrinsics {
+    /// Image width
+    pub width: u32,
+    /// Image height
+    pub height: u32,
+    /// Horizontal focal length
+    pub fx: f64,
+    /// Vertical focal length
+    pub fy: f64,
+    /// Principal point, horizontal
+    pub cx: f64,
+    /// Principal point, vertical
+    pub cy: f64,
+}
+
+impl CameraIntrinsics {
+    /// The 3×3 camera matrix `K`, row-major.
+    pub fn matrix(&self) -> [[f64; 3]; 3] {
+        [[self.fx, 0.0, self.cx], [0.0, self.fy, self.cy], [0.0, 0.0, 1.0]]
+    }
+}
+
+/// An extracted view along with the angles it actually covers.
+///
+/// Views are clipped at the panorama's edges, so the bounds can be
+/// narrower than the configured field of view. Headings are measured in
+/// the panorama image from its left edge, like [`ViewConfig::heading`];
+/// the image center, 180°, faces [`Panorama::heading`]. Use
+/// [`pixel_to_bearing`] for compass bearings.
+#[derive(Debug, Clone)]
+pub struct ExtractedView {
+    /// The view image
+    pub image: DynamicImage,
+    /// Headings of the left and right edges, in degrees
+    pub heading_range: (f64, f64),
+    /// Pitches of the bottom and top edges, in degrees (up is positive)
+    pub pitch_range: (f64, f64),
+    /// Horizontal and vertical resolution of `image`
+    pub pixels_per_degree: (f64, f64),
+}
+
+impl ExtractedView {
+    /// Bounds of `image`, cropped from `region` of a
+    /// `pano_width`×`pano_height` panorama.
+    fn new(image: DynamicImage, region: (u32, u32, u32, u32), pano_width: u32, pano_height: u32) -> Self {
+        let (heading_range, pitch_range) = angular_bounds(region, pano_width, pano_height);
+        let pixels_per_degree = (
+            f64::from(image.width()) / (heading_range.1 - heading_range.0),
+            f64::from(image.height()) / (pitch_range.1 - pitch_range.0),
+        );
+        Self {
+            image,
+            heading_range,
+            pitch_range,
+            pixels_per_degree,
+        }
+    }
+}
+
+/// Heading and pitch ranges of `region` of a `pano_width`×`pano_height`
+/// panorama.
+fn angular_bounds(region: (u32, u32, u32, u32), pano_width: u32, pano_height: u32) -> ((f64, f64), (f64, f64)) {
+    let (x, y, width, height) = region;
+    let degrees_x = |px: u32| f64::from(px) * 360.0 / f64::from(pano_width);
+    let degrees_y = |px: u32| f64::from(px) * 180.0 / f64::from(pano_height);
+    (
+        (degrees_x(x), degrees_x(x + width)),
+        (90.0 - degrees_y(y + height), 90.0 - degrees_y(y)),
+    )
+}
+
+/// Angular bounds and output size of the view `config` extracts from a
+/// panorama downloaded at `config.zoom`.
+fn view_geometry(config: &ViewConfig) -> ((f64, f64), (f64, f64), (f64, f64)) {
+    let zoom = config.zoom.clamp(1, 7);
+    let (pano_width, pano_height) = (512 << zoom, 256 << zoom);
+    let region = crop_region(config, pano_width, pano_height);
+    let (heading_range, pitch_range) = angular_bounds(region, pano_width, pano_height);
+    let (width, height) = config.size.unwrap_or((region.2, region.3));
+    (heading_range, pitch_range, (f64::from(width), f64::from(height)))
+}
+
+/// Convert a position in a view extracted with `config` from `pano` into
+/// a compass bearing and an elevation angle, both in degrees.
+///
+/// Positions are continuous: the view's left edge is `x = 0.0` and the
+/// center of pixel column `i` is `i + 0.5`; likewise for `y` from the top.
+/// Elevation is positive above the horizon. Camera tilt
+/// ([`Panorama::pitch`] and [`Panorama::roll`]) is not corrected.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rsstreetview::{StreetView, ViewConfig};
+/// # use rsstreetview::views::pixel_to_bearing;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = StreetView::new();
+/// let pano = &client.search_panoramas(41.8982208, 12.4764804).await?[0];
+/// let config = ViewConfig::new(180).size(640, 640);
+/// let view = client.extract_view(&pano.pano_id, &config).await?;
+/// // A detection centered at (412, 300) in the view
+/// let (bearing, elevation) = pixel_to_bearing(&config, pano, 412.0, 300.0);
+/// # Ok(())
+/// # }
+/// ```
+pub fn pixel_to_bearing(config: &ViewConfig, pano: &Panorama, x: f64, y: f64) -> (f64, f64) {
+    let (heading_range, pitch_range, (width, height)) = view_geometry(config);
+    let heading = heading_range.0 + x / width * (heading_range.1 - heading_range.0);
+    let elevation = pitch_range.1 - y / height * (pitch_range.1 - pitch_range.0);
+    ((pano.heading + heading - 180.0).rem_euclid(360.0), elevation)
+}
+
+/// Convert a compass bearing and elevation angle, in degrees, into a
+/// position in a view extracted with `config` from `pano`.
+///
+/// The inverse of [`pixel_to_bearing`]. Returns `None` if the direction is
+/// outside the view.
+pub fn bearing_to_pixel(config: &ViewConfig, pano: &Panorama, bearing: f64, elevation: f64) -> Option<(f64, f64)> {
+    let (heading_range, pitch_range, (width, height)) = view_geometry(config);
+    let heading = (bearing - pano.heading + 180.0).rem_euclid(360.0);
+    let x = (heading - heading_range.0) / (heading_range.1 - heading_range.0) * width;
+    let y = (pitch_range.1 - elevation) / (pitch_range.1 - pitch_range.0) * height;
+    ((0.0..=width).contains(&x) && (0.0..=height).contains(&y)).then_some((x, y))
+}
+
+/// Like [`extract_view_from_panorama`], but also returns the angles the
+/// view covers.
+pub fn extract_view_with_bounds(panorama: &DynamicImage, config: &ViewConfig) -> Result<ExtractedView> {
+    let (pano_width, pano_height) = panorama.dimensions();
+    let region = crop_region(config, pano_width, pano_height);
+    let image = extract_view_from_panorama(panorama, config)?;
+    Ok(ExtractedView::new(image, region, pano_width, pano_height))
+}
+
+/// The region of a `pano_width`×`pano_height` panorama that
+/// [`extract_view_from_panorama`] crops for `config`, as
+/// `(x, y, width, height)`.
+pub(crate) fn crop_region(config: &ViewConfig, pano_width: u32, pano_height: u32) -> (u32, u32, u32, u32) {
     // Calculate the horizontal span based on FOV
     // For equirectangular projection: pixels per degree = width / 360
     let pixels_per_degree_h = pano_width as f64 / 360.0;
@@ -216,26 +731,7 @@ pub fn extract_view_from_panorama(
     let x_end = (x_start + crop_width).min(pano_width);
     let y_end = (y_start + crop_height).min(pano_height);
 
-    // Simple crop (doesn't handle wrapping around the edges yet)
-    let cropped = panorama.crop_imm(x_start, y_start, x_end - x_start, y_end - y_start);
-
-    // Resize if custom size specified, otherwise use native resolution
-    if let Some((width, height)) = config.size {
-        // Resize to custom dimensions
-        let resized = image::imageops::resize(
-            &cropped,
-            width,
-            height,
-            image::imageops::FilterType::Lanczos3,
-        );
-
-        // Convert to RGB (resize returns RGBA)
-        let rgb_image = DynamicImage::ImageRgba8(resized).to_rgb8();
-        Ok(DynamicImage::ImageRgb8(rgb_image))
-    } else {
-        // Use native resolution (no resize)
-        Ok(cropped)
-    }
+    (x_start, y_start, x_end - x_start, y_end - y_start)
 }
 
 /// Extract multiple views from a panorama in one call.
@@ -267,8 +763,19 @@ pub fn extract_view_from_panorama(
 /// # Ok(())
 /// # }
 /// ```
+#[cfg(feature = "unofficial")]
 pub async fn extract_multiple_views(
     client: &Client,
+    pano_id: impl Into<PanoId>,
+    configs: &[ViewConfig],
+) -> Result<Vec<DynamicImage>> {
+    let pano_id = PanoId::new(pano_id)?;
+    fetch_and_extract_views(&Http::new(client.clone()), pano_id.as_str(), configs).await
+}
+
+#[cfg(feature = "unofficial")]
+pub(crate) async fn fetch_and_extract_views(
+    http: &Http,
     pano_id: &str,
     configs: &[ViewConfig],
 ) -> Result<Vec<DynamicImage>> {
@@ -279,7 +786,7 @@ pub async fn extract_multiple_views(
     // Download panorama once at the zoom level from the first config
     // (all configs should use the same zoom for efficiency)
     let zoom = configs[0].zoom;
-    let panorama = download_panorama(client, pano_id, zoom).await?;
+    let panorama = cached_panorama(http, pano_id, zoom).await?;
 
     // Extract all views from the same panorama
     let mut views = Vec::new();
@@ -291,9 +798,97 @@ pub async fn extract_multiple_views(
     Ok(views)
 }
 
+/// Options for [`StreetView::extract_views_batch`](crate::StreetView::extract_views_batch).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct BatchViewOptions {
+    /// Panoramas downloading at once (default 4)
+    pub download_concurrency: usize,
+    /// Panoramas having views extracted at once, each on a blocking thread
+    /// (default: one per CPU)
+    pub extract_concurrency: usize,
+}
+
+impl BatchViewOptions {
+    /// Defaults: 4 downloads and one extraction per CPU at a time.
+    pub fn new() -> Self {
+        Self {
+            download_concurrency: 4,
+            extract_concurrency: std::thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+
+    /// Set how many panoramas download at once.
+    pub fn download_concurrency(mut self, concurrency: usize) -> Self {
+        self.download_concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Set how many panoramas have views extracted at once.
+    pub fn extract_concurrency(mut self, concurrency: usize) -> Self {
+        self.extract_concurrency = concurrency.max(1);
+        self
+    }
+}
+
+impl Default for BatchViewOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Download each requested panorama once, at its first view's zoom, and
+/// extract its views on the blocking pool, with separate limits for the
+/// two stages.
+///
+/// At most `download_concurrency + extract_concurrency` panoramas are held
+/// in memory: finished downloads wait for an extraction slot.
+#[cfg(feature = "unofficial")]
+pub(crate) fn extract_views_batch<'a>(
+    http: &'a Http,
+    requests: &'a [(PanoId, Vec<ViewConfig>)],
+    options: &BatchViewOptions,
+) -> futures::stream::BoxStream<'a, (PanoId, Result<Vec<DynamicImage>>)> {
+    use futures::{stream, StreamExt};
+
+    stream::iter(requests)
+        .map(move |(pano_id, configs)| async move {
+            let panorama = match (PanoId::new(pano_id), configs.first()) {
+                (Err(e), _) => Err(e),
+                (Ok(_), None) => Ok(None),
+                (Ok(pano_id), Some(first)) => cached_panorama(http, pano_id.as_str(), first.zoom).await.map(Some),
+            };
+            (pano_id, configs, panorama)
+        })
+        .buffer_unordered(options.download_concurrency.max(1))
+        .map(|(pano_id, configs, panorama)| async move {
+            let views = match panorama {
+                Ok(Some(panorama)) => {
+                    let configs = configs.clone();
+                    tokio::task::spawn_blocking(move || {
+                        configs
+                            .iter()
+                            .map(|config| extract_view_from_panorama(&panorama, config))
+                            .collect::<Result<Vec<_>>>()
+                    })
+                    .await
+                    .map_err(|e| StreetViewError::IoError(std::io::Error::other(e)))
+                    .and_then(|views| views)
+                }
+                Ok(None) => Ok(Vec::new()),
+                Err(e) => Err(e),
+            };
+            (pano_id.clone(), views)
+        })
+        .buffer_unordered(options.extract_concurrency.max(1))
+        .boxed()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::fixtures::panorama;
 
     #[test]
     fn test_direction_headings() {
@@ -301,6 +896,11 @@ mod tests {
         assert_eq!(Direction::Right.heading(), 90);
         assert_eq!(Direction::Back.heading(), 180);
         assert_eq!(Direction::Left.heading(), 270);
+
+        let headings: Vec<u16> = Direction::iter().map(|dir| dir.heading()).collect();
+        assert_eq!(headings, [0, 45, 90, 135, 180, 225, 270, 315]);
+        assert_eq!(ViewConfig::from(Direction::BackLeft).heading, 225);
+        assert!(Direction::CARDINAL.iter().all(|dir| dir.heading() % 90 == 0));
     }
 
     #[test]
@@ -315,11 +915,146 @@ mod tests {
         assert_eq!(config.pitch, 10);
     }
 
+    #[test]
+    fn test_view_filter() {
+        let panorama = DynamicImage::ImageRgb8(image::RgbImage::from_fn(64, 32, |x, y| {
+            image::Rgb([(x * 4) as u8, (y * 8) as u8, 0])
+        }));
+        let config = ViewConfig::new(180).size(8, 8);
+        assert_eq!(config.filter, ResampleFilter::Lanczos3);
+
+        let nearest = config.clone().filter(FilterType::Nearest);
+        assert_eq!(nearest.filter, ResampleFilter::Nearest);
+        let expected = image::imageops::resize(&panorama.crop_imm(24, 8, 16, 16), 8, 8, FilterType::Nearest);
+        assert_eq!(extract_view_from_panorama(&panorama, &nearest).unwrap().to_rgba8(), expected);
+    }
+
+    #[test]
+    fn test_view_bounds() {
+        let panorama = DynamicImage::new_rgb8(720, 360);
+
+        let view = extract_view_with_bounds(&panorama, &ViewConfig::new(180).fov(90).pitch(10)).unwrap();
+        assert_eq!(view.heading_range, (135.0, 225.0));
+        assert_eq!(view.pitch_range, (-35.0, 55.0));
+        assert_eq!(view.pixels_per_degree, (2.0, 2.0));
+
+        // Clipped at the left edge, and resized
+        let view = extract_view_with_bounds(&panorama, &ViewConfig::new(20).fov(90).size(90, 45)).unwrap();
+        assert_eq!(view.heading_range, (0.0, 90.0));
+        assert_eq!(view.pitch_range, (-22.5, 22.5));
+        assert_eq!(view.pixels_per_degree, (1.0, 1.0));
+    }
+
+    #[test]
+    fn test_pixel_bearing() {
+        let pano = Panorama {
+            heading: 30.0,
+            ..panorama("A000000000000000000000", 0.0, 0.0)
+        };
+        // Zoom 1 is 1024x512; this view is 256x256 pixels of it, shown at 128x128
+        let config = ViewConfig::new(180).zoom(1).size(128, 128);
+
+        // The view's center faces the panorama's heading
+        assert_eq!(pixel_to_bearing(&config, &pano, 64.0, 64.0), (30.0, 0.0));
+        assert_eq!(pixel_to_bearing(&config, &pano, 0.0, 0.0), (345.0, 45.0));
+        assert_eq!(pixel_to_bearing(&config, &pano, 128.0, 128.0), (75.0, -45.0));
+
+        assert_eq!(bearing_to_pixel(&config, &pano, 345.0, 45.0), Some((0.0, 0.0)));
+        assert_eq!(bearing_to_pixel(&config, &pano, 52.5, -22.5), Some((96.0, 96.0)));
+        assert_eq!(bearing_to_pixel(&config, &pano, 210.0, 0.0), None);
+        assert_eq!(bearing_to_pixel(&config, &pano, 30.0, 60.0), None);
+    }
+
+    #[test]
+    fn test_intrinsics() {
+        let intrinsics = ViewConfig::new(180).fov(90).size(640, 320).intrinsics(640, 320);
+        assert_eq!((intrinsics.cx, intrinsics.cy), (320.0, 160.0));
+        assert!((intrinsics.fx - 320.0).abs() < 1e-9);
+        // 45° vertically
+        assert!((intrinsics.fy - 160.0 / 22.5f64.to_radians().tan()).abs() < 1e-9);
+        assert_eq!(intrinsics.matrix()[0], [intrinsics.fx, 0.0, 320.0]);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(ViewConfig::new(0).validate().is_ok());
+        assert!(ViewConfig::new(359).fov(140).size(1600, 900).validate().is_ok());
+        // 45° up plus a 45° half fov just reaches the zenith
+        assert!(ViewConfig::new(0).pitch(45).validate().is_ok());
+
+        let mut config = ViewConfig::new(0);
+        config.heading = 400;
+        config.zoom = 9;
+        config.size = Some((0, 100));
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.contains("heading 400°") && error.contains("zoom 9") && error.contains("size 0x100"), "{error}");
+
+        let error = ViewConfig::new(0).fov(150).try_build().unwrap_err().to_string();
+        assert!(error.contains("fov 150°"), "{error}");
+        let error = ViewConfig::new(0).pitch(-60).validate().unwrap_err();
+        assert!(matches!(&error, StreetViewError::InvalidViewConfig(msg) if msg.contains("nadir")), "{error}");
+        // A wide aspect ratio leaves room to look further down
+        assert!(ViewConfig::new(0).pitch(-60).size(1600, 400).validate().is_ok());
+    }
+
+    #[test]
+    fn test_parse_strings() {
+        assert_eq!("Front-Right".parse::<Direction>().unwrap(), Direction::FrontRight);
+        assert_eq!("south_west".parse::<Direction>().unwrap(), Direction::BackLeft);
+        assert_eq!("NW".parse::<Direction>().unwrap(), Direction::FrontLeft);
+        assert!("up".parse::<Direction>().is_err());
+        for dir in Direction::iter() {
+            assert_eq!(dir.to_string().parse::<Direction>().unwrap(), dir);
+        }
+
+        let config: ViewConfig = " h90, fov100 ,p-10,z4 ".parse().unwrap();
+        assert_eq!((config.heading, config.fov, config.pitch, config.zoom, config.size), (90, 100, -10, 4, None));
+        assert_eq!(config.to_string(), "h90,fov100,p-10,z4");
+        let config: ViewConfig = "s800x600,east".parse().unwrap();
+        assert_eq!((config.heading, config.size), (90, Some((800, 600))));
+        let back: ViewConfig = config.to_string().parse().unwrap();
+        assert_eq!(back.to_string(), config.to_string());
+        for bad in ["h360", "fov0", "p91", "z8", "s800", "x1", "h"] {
+            assert!(bad.parse::<ViewConfig>().is_err(), "{bad}");
+        }
+    }
+
     #[test]
     fn test_direction_names() {
         assert_eq!(Direction::Front.name(), "front");
         assert_eq!(Direction::Right.name(), "right");
         assert_eq!(Direction::Back.name(), "back");
         assert_eq!(Direction::Left.name(), "left");
+        assert_eq!(Direction::FrontLeft.name(), "front_left");
+        assert_eq!(Direction::BackRight.compass_name(), "south_east");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_roundtrip() {
+        use crate::types::{ImageFormat, SaveOptions};
+        use serde_json::json;
+
+        let config = ViewConfig::new(45).fov(120).pitch(-10).size(800, 600).zoom(4);
+        let value = serde_json::to_value(&config).unwrap();
+        assert_eq!(
+            value,
+            json!({ "heading": 45, "fov": 120, "pitch": -10, "size": [800, 600], "zoom": 4, "filter": "lanczos3" })
+        );
+        let back: ViewConfig = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&back).unwrap(), value);
+
+        // Missing fields take their defaults, so job configs stay short.
+        let config: ViewConfig = serde_json::from_str(r#"{"heading": 90}"#).unwrap();
+        assert_eq!((config.heading, config.fov, config.zoom), (90, 90, 3));
+
+        let options: SaveOptions = serde_json::from_str(r#"{"format": "jpeg", "jpeg_quality": 80}"#).unwrap();
+        assert_eq!(options.format, ImageFormat::Jpeg);
+        assert_eq!(options.jpeg_quality, 80);
+        assert_eq!(options.webp_quality, SaveOptions::new().webp_quality);
+
+        assert_eq!(serde_json::to_value(Direction::Left).unwrap(), json!("left"));
+        assert!(matches!(serde_json::from_str(r#""back""#).unwrap(), Direction::Back));
+        assert_eq!(serde_json::to_value(Direction::FrontRight).unwrap(), json!("front_right"));
     }
 }
@@ -0,0 +1,57 @@
+//! Snapshot tests for the response parsers.
+//!
+//! Every `tests/fixtures/{endpoint}/*.txt` file is a saved response; its
+//! parse result (or error) is compared with the `.snap.json` file beside
+//! it. To add a case, drop in a response (for example one written by
+//! `capture_raw_responses`) and run with `UPDATE_SNAPSHOTS=1` to write its
+//! snapshot, then review the snapshot before committing it.
+
+use rsstreetview::photometa::parse_photometa;
+use rsstreetview::search::parse_response;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+fn snapshot<T: Serialize>(result: rsstreetview::Result<T>) -> Value {
+    match result {
+        Ok(value) => serde_json::to_value(value).unwrap(),
+        Err(e) => json!({ "error": e.to_string() }),
+    }
+}
+
+fn check_fixtures(endpoint: &str, parse: impl Fn(&str) -> Value) {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(endpoint);
+    let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+
+    let mut fixtures: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+        .collect();
+    fixtures.sort();
+    assert!(!fixtures.is_empty(), "no fixtures in {}", dir.display());
+
+    for fixture in fixtures {
+        let actual = parse(&std::fs::read_to_string(&fixture).unwrap());
+        let snap = fixture.with_extension("snap.json");
+        if update {
+            std::fs::write(&snap, serde_json::to_string_pretty(&actual).unwrap() + "\n").unwrap();
+            continue;
+        }
+        let expected: Value = match std::fs::read_to_string(&snap) {
+            Ok(text) => serde_json::from_str(&text).unwrap(),
+            Err(_) => panic!("missing {}; run with UPDATE_SNAPSHOTS=1", snap.display()),
+        };
+        assert_eq!(actual, expected, "{} does not match its snapshot", fixture.display());
+    }
+}
+
+#[test]
+fn test_search_fixtures() {
+    check_fixtures("search", |text| snapshot(parse_response(text)));
+}
+
+#[test]
+fn test_photometa_fixtures() {
+    check_fixtures("photometa", |text| snapshot(parse_photometa(text)));
+}